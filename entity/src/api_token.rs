@@ -11,13 +11,31 @@ pub struct Model {
     #[sea_orm(unique)]
     pub uuid: Uuid,
     #[sea_orm(column_type = "Text", unique)]
-    pub token: String,
+    pub token_hash: String,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
     pub status: ApiTokenStatusEnum,
+    pub token_prefix: Option<String>,
+    pub display_name: Option<String>,
+    pub tenant_id: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
-pub enum Relation {}
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::tenant::Entity",
+        from = "Column::TenantId",
+        to = "super::tenant::Column::Id",
+        on_update = "Cascade",
+        on_delete = "Cascade"
+    )]
+    Tenant,
+}
+
+impl Related<super::tenant::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Tenant.def()
+    }
+}
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -55,10 +55,19 @@ pub struct Model {
     pub system_version: Option<String>,
     #[sea_orm(column_type = "String(StringLen::N(255))", nullable)]
     pub web_connector_app_name: Option<String>,
+    #[sea_orm(column_type = "String(StringLen::N(16))", nullable)]
+    pub qbxml_version: Option<String>,
+    pub initial_sync_completed_at: Option<DateTimeWithTimeZone>,
+    pub sync_window_start_hour: Option<i16>,
+    pub sync_window_end_hour: Option<i16>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub field_mapping: Option<Json>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::connection_api_key::Entity")]
+    ConnectionApiKey,
     #[sea_orm(has_many = "super::connection_run::Entity")]
     ConnectionRun,
     #[sea_orm(has_one = "super::erp_connection_credentials::Entity")]
@@ -69,6 +78,8 @@ pub enum Relation {
     InventoryRecord,
     #[sea_orm(has_many = "super::inventory_record_event::Entity")]
     InventoryRecordEvent,
+    #[sea_orm(has_many = "super::quarantine::Entity")]
+    Quarantine,
     #[sea_orm(
         belongs_to = "super::tenant::Entity",
         from = "Column::TenantId",
@@ -79,6 +90,12 @@ pub enum Relation {
     Tenant,
 }
 
+impl Related<super::connection_api_key::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ConnectionApiKey.def()
+    }
+}
+
 impl Related<super::connection_run::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ConnectionRun.def()
@@ -109,6 +126,12 @@ impl Related<super::inventory_record_event::Entity> for Entity {
     }
 }
 
+impl Related<super::quarantine::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Quarantine.def()
+    }
+}
+
 impl Related<super::tenant::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::Tenant.def()
@@ -0,0 +1,90 @@
+//! String conversions for the active enums in `sea_orm_active_enums`.
+//!
+//! Kept out of the generated file so `sea-orm-codegen` can regenerate it. `Display` writes
+//! the database value (`to_value`), and `FromStr` parses it back, trimmed and
+//! case-insensitively, so the wire name of every enum is its database value.
+//! `Serialize` writes the same string, so response structs can hold the enums directly
+//! (e.g. `"needs_reauth"`, never the variant name `NeedsReauth`).
+
+use std::fmt;
+use std::str::FromStr;
+
+use sea_orm::ActiveEnum;
+use serde::{Serialize, Serializer};
+
+use crate::sea_orm_active_enums::*;
+
+/// The tenant status enum; codegen names it after its Postgres type, `enum`.
+pub type TenantStatus = Enum;
+
+/// A string that is not a value of the enum it was parsed as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseEnumError {
+    pub value: String,
+    /// Every accepted value, in declaration order.
+    pub expected: Vec<String>,
+}
+
+impl fmt::Display for ParseEnumError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "unknown value `{}`, expected one of: {}",
+            self.value,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for ParseEnumError {}
+
+macro_rules! impl_enum_strings {
+    ($($name:ident),* $(,)?) => {
+        $(
+            impl fmt::Display for $name {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    f.write_str(&self.to_value())
+                }
+            }
+
+            impl FromStr for $name {
+                type Err = ParseEnumError;
+
+                fn from_str(s: &str) -> Result<Self, Self::Err> {
+                    Self::try_from_value(&s.trim().to_lowercase()).map_err(|_| ParseEnumError {
+                        value: s.to_string(),
+                        expected: Self::values(),
+                    })
+                }
+            }
+
+            impl Serialize for $name {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.serialize_str(&self.to_value())
+                }
+            }
+        )*
+    };
+}
+
+impl_enum_strings!(
+    AllowedIpAddressStatusEnum,
+    ApiTokenStatusEnum,
+    ConnectionRunStatus,
+    ConnectionRunType,
+    Currency,
+    Enum,
+    ErpConnectionAuthStatus,
+    ErpConnectionAuthTokenType,
+    ErpConnectionReauthReason,
+    ErpConnectionStatus,
+    ErpEnvironment,
+    ErpProvider,
+    ErpProviderAuthType,
+    ErpProviderType,
+    SyncEventCategory,
+    SyncEventDirection,
+    SyncEventMethod,
+    SyncEventStatus,
+    SystemIdKey,
+);
@@ -23,6 +23,9 @@ pub struct Model {
     pub rate_limit_window_seconds: Option<i32>,
     pub updated_at: DateTimeWithTimeZone,
     pub created_at: DateTimeWithTimeZone,
+    pub last_polled_at: Option<DateTimeWithTimeZone>,
+    pub next_poll_at: Option<DateTimeWithTimeZone>,
+    pub poll_interval_seconds: Option<i32>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
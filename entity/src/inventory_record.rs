@@ -19,6 +19,8 @@ pub struct Model {
     pub system_id_key: SystemIdKey,
     #[sea_orm(column_type = "String(StringLen::N(255))")]
     pub system_id: String,
+    pub deleted_at: Option<DateTimeWithTimeZone>,
+    pub merged_into_id: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -33,6 +35,14 @@ pub enum Relation {
     ConnectionIdentity,
     #[sea_orm(has_many = "super::inventory_record_event::Entity")]
     InventoryRecordEvent,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::MergedIntoId",
+        to = "Column::Id",
+        on_update = "Cascade",
+        on_delete = "SetNull"
+    )]
+    SelfRef,
     #[sea_orm(
         belongs_to = "super::tenant::Entity",
         from = "Column::TenantId",
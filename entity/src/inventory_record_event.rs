@@ -22,11 +22,12 @@ pub struct Model {
     pub name: Option<String>,
     #[sea_orm(column_type = "Text", nullable)]
     pub description: Option<String>,
-    #[sea_orm(column_type = "Text", nullable)]
-    pub attributes: Option<String>,
+    #[sea_orm(column_type = "JsonBinary", nullable)]
+    pub attributes: Option<Json>,
     pub qty: Option<i32>,
     #[sea_orm(column_type = "Text", nullable)]
     pub external_code: Option<String>,
+    pub qty_milli: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
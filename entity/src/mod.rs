@@ -3,13 +3,18 @@
 pub mod prelude;
 
 pub mod allowed_ip_address;
+pub mod allowed_origin;
 pub mod api_token;
+pub mod audit_log;
+pub mod connection_api_key;
 pub mod connection_identity;
 pub mod connection_run;
 pub mod erp_connection_credentials;
 pub mod erp_connection_sync_state;
 pub mod inventory_record;
 pub mod inventory_record_event;
+pub mod outbox;
+pub mod quarantine;
 pub mod sea_orm_active_enums;
 pub mod sync_event;
 pub mod tenant;
@@ -1,12 +1,17 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 2.0
 
 pub use super::allowed_ip_address::Entity as AllowedIpAddress;
+pub use super::allowed_origin::Entity as AllowedOrigin;
 pub use super::api_token::Entity as ApiToken;
+pub use super::audit_log::Entity as AuditLog;
+pub use super::connection_api_key::Entity as ConnectionApiKey;
 pub use super::connection_identity::Entity as ConnectionIdentity;
 pub use super::connection_run::Entity as ConnectionRun;
 pub use super::erp_connection_credentials::Entity as ErpConnectionCredentials;
 pub use super::erp_connection_sync_state::Entity as ErpConnectionSyncState;
 pub use super::inventory_record::Entity as InventoryRecord;
 pub use super::inventory_record_event::Entity as InventoryRecordEvent;
+pub use super::outbox::Entity as Outbox;
+pub use super::quarantine::Entity as Quarantine;
 pub use super::sync_event::Entity as SyncEvent;
 pub use super::tenant::Entity as Tenant;
@@ -51,6 +51,8 @@ pub enum ConnectionRunStatus {
 pub enum ConnectionRunType {
     #[sea_orm(string_value = "poll")]
     Poll,
+    #[sea_orm(string_value = "batch")]
+    Batch,
 }
 #[derive(Debug, Clone, PartialEq, Eq, EnumIter, DeriveActiveEnum)]
 #[sea_orm(rs_type = "String", db_type = "Enum", enum_name = "currency")]
@@ -237,4 +239,6 @@ pub enum SystemIdKey {
     Qbo,
     #[sea_orm(string_value = "sapo")]
     Sapo,
+    #[sea_orm(string_value = "sfdc")]
+    Sfdc,
 }
@@ -21,12 +21,28 @@ pub struct Model {
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::allowed_origin::Entity")]
+    AllowedOrigin,
+    #[sea_orm(has_many = "super::api_token::Entity")]
+    ApiToken,
     #[sea_orm(has_many = "super::connection_identity::Entity")]
     ConnectionIdentity,
     #[sea_orm(has_many = "super::inventory_record::Entity")]
     InventoryRecord,
 }
 
+impl Related<super::allowed_origin::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AllowedOrigin.def()
+    }
+}
+
+impl Related<super::api_token::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ApiToken.def()
+    }
+}
+
 impl Related<super::connection_identity::Entity> for Entity {
     fn to() -> RelationDef {
         Relation::ConnectionIdentity.def()
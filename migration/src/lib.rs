@@ -16,6 +16,25 @@ mod m20260216_000013_create_sync_event_tables;
 mod m20260216_000014_add_sync_event_connection_run_id;
 mod m20260216_000015_alter_inventory_record_event_attributes_to_text;
 mod m20260219_000016_rename_sync_event_direction_values;
+mod m20260301_000017_create_outbox_table;
+mod m20260302_000018_add_connection_identity_unique_indexes;
+mod m20260303_000019_add_sync_state_poll_schedule;
+mod m20260304_000020_alter_inventory_record_event_attributes_to_jsonb;
+mod m20260305_000021_add_api_token_tenant_and_hash;
+mod m20260306_000022_create_audit_log_table;
+mod m20260307_000023_add_connection_identity_qbxml_version;
+mod m20260308_000024_create_quarantine_table;
+mod m20260309_000025_add_connection_identity_initial_sync_completed_at;
+mod m20260310_000026_create_allowed_origin_table;
+mod m20260311_000027_add_sync_event_recurring_unique_index;
+mod m20260312_000028_add_connection_identity_sync_window;
+mod m20260313_000029_add_system_id_key_sfdc;
+mod m20260314_000030_add_inventory_record_event_qty_milli;
+mod m20260315_000031_add_connection_identity_field_mapping;
+mod m20260316_000032_add_inventory_record_merged;
+mod m20260317_000033_add_connection_run_type_batch;
+mod m20260318_000034_add_inventory_record_event_search;
+mod m20260319_000035_create_connection_api_key_table;
 
 pub struct Migrator;
 
@@ -39,6 +58,25 @@ impl MigratorTrait for Migrator {
            Box::new(m20260216_000014_add_sync_event_connection_run_id::Migration),
            Box::new(m20260216_000015_alter_inventory_record_event_attributes_to_text::Migration),
            Box::new(m20260219_000016_rename_sync_event_direction_values::Migration),
+           Box::new(m20260301_000017_create_outbox_table::Migration),
+           Box::new(m20260302_000018_add_connection_identity_unique_indexes::Migration),
+           Box::new(m20260303_000019_add_sync_state_poll_schedule::Migration),
+           Box::new(m20260304_000020_alter_inventory_record_event_attributes_to_jsonb::Migration),
+           Box::new(m20260305_000021_add_api_token_tenant_and_hash::Migration),
+           Box::new(m20260306_000022_create_audit_log_table::Migration),
+           Box::new(m20260307_000023_add_connection_identity_qbxml_version::Migration),
+           Box::new(m20260308_000024_create_quarantine_table::Migration),
+           Box::new(m20260309_000025_add_connection_identity_initial_sync_completed_at::Migration),
+           Box::new(m20260310_000026_create_allowed_origin_table::Migration),
+           Box::new(m20260311_000027_add_sync_event_recurring_unique_index::Migration),
+           Box::new(m20260312_000028_add_connection_identity_sync_window::Migration),
+           Box::new(m20260313_000029_add_system_id_key_sfdc::Migration),
+           Box::new(m20260314_000030_add_inventory_record_event_qty_milli::Migration),
+           Box::new(m20260315_000031_add_connection_identity_field_mapping::Migration),
+           Box::new(m20260316_000032_add_inventory_record_merged::Migration),
+           Box::new(m20260317_000033_add_connection_run_type_batch::Migration),
+           Box::new(m20260318_000034_add_inventory_record_event_search::Migration),
+           Box::new(m20260319_000035_create_connection_api_key_table::Migration),
         ]
     }
 }
@@ -0,0 +1,132 @@
+use sea_orm_migration::prelude::*;
+
+// ── Table ──
+
+#[derive(DeriveIden)]
+enum Outbox {
+    Table,
+    Id,
+    Uuid,
+    CreatedAt,
+    UpdatedAt,
+    Topic,
+    Payload,
+    Attempts,
+    LastError,
+    NextAttemptAt,
+    PublishedAt,
+}
+
+#[derive(DeriveIden)]
+enum OutboxIndexes {
+    OutboxUuidIdx,
+    OutboxUnpublishedIdx,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Outbox::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Outbox::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Outbox::Uuid)
+                            .uuid()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Outbox::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Outbox::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Outbox::Topic).string_len(255).not_null())
+                    .col(ColumnDef::new(Outbox::Payload).json_binary().not_null())
+                    .col(
+                        ColumnDef::new(Outbox::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(ColumnDef::new(Outbox::LastError).text().null())
+                    .col(
+                        ColumnDef::new(Outbox::NextAttemptAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Outbox::PublishedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(OutboxIndexes::OutboxUuidIdx.to_string())
+                    .table(Outbox::Table)
+                    .col(Outbox::Uuid)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // The relay only ever scans unpublished rows, so keep the index partial.
+        let table_name = Outbox::Table.to_string();
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                CREATE INDEX IF NOT EXISTS {} ON {} (next_attempt_at, id)
+                WHERE published_at IS NULL;
+                "#,
+                OutboxIndexes::OutboxUnpublishedIdx.to_string(),
+                table_name
+            ))
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                ALTER TABLE {}
+                ALTER COLUMN uuid
+                SET DEFAULT gen_random_uuid();
+                "#,
+                table_name
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Outbox::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
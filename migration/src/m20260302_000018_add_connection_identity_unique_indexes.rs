@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Only one active connection per company file for a tenant/provider/type.
+        db.execute_unprepared(
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS ux_connection_identity_active_company_file
+               ON connection_identity (tenant_id, erp_provider, erp_type, company_file_id)
+               WHERE status = 'active' AND company_file_id IS NOT NULL"#,
+        )
+        .await?;
+
+        // Only one active connection per provider realm (e.g. QBO realm id).
+        db.execute_unprepared(
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS ux_connection_identity_active_realm
+               ON connection_identity (provider_realm_id)
+               WHERE status = 'active' AND provider_realm_id IS NOT NULL"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("DROP INDEX IF EXISTS ux_connection_identity_active_realm")
+            .await?;
+        db.execute_unprepared("DROP INDEX IF EXISTS ux_connection_identity_active_company_file")
+            .await?;
+
+        Ok(())
+    }
+}
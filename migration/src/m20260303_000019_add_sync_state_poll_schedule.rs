@@ -0,0 +1,82 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum ErpConnectionSyncState {
+    Table,
+    LastPolledAt,
+    NextPollAt,
+    PollIntervalSeconds,
+}
+
+#[derive(DeriveIden)]
+enum ErpConnectionSyncStateIndexes {
+    ErpConnectionSyncStateNextPollAtIdx,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ErpConnectionSyncState::Table)
+                    .add_column(
+                        ColumnDef::new(ErpConnectionSyncState::LastPolledAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .add_column(
+                        ColumnDef::new(ErpConnectionSyncState::NextPollAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    // NULL = use the default poll interval
+                    .add_column(
+                        ColumnDef::new(ErpConnectionSyncState::PollIntervalSeconds)
+                            .integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(ErpConnectionSyncStateIndexes::ErpConnectionSyncStateNextPollAtIdx.to_string())
+                    .table(ErpConnectionSyncState::Table)
+                    .col(ErpConnectionSyncState::NextPollAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(ErpConnectionSyncStateIndexes::ErpConnectionSyncStateNextPollAtIdx.to_string())
+                    .table(ErpConnectionSyncState::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ErpConnectionSyncState::Table)
+                    .drop_column(ErpConnectionSyncState::LastPolledAt)
+                    .drop_column(ErpConnectionSyncState::NextPollAt)
+                    .drop_column(ErpConnectionSyncState::PollIntervalSeconds)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
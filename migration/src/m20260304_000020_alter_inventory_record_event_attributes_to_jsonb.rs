@@ -0,0 +1,42 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum InventoryRecordEvent {
+    Table,
+    Attributes,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Change attributes from text to jsonb. Values already holding a JSON object/array are
+        // kept as-is; legacy newline-separated text becomes a JSON array of its lines.
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE inventory_record_event
+                   ALTER COLUMN attributes TYPE jsonb
+                   USING (CASE
+                       WHEN attributes IS NULL THEN NULL
+                       WHEN attributes ~ '^\s*[\{\[]' THEN attributes::jsonb
+                       ELSE to_jsonb(string_to_array(attributes, E'\n'))
+                   END)"#,
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared(
+                r#"ALTER TABLE inventory_record_event
+                   ALTER COLUMN attributes TYPE text USING attributes::text"#,
+            )
+            .await?;
+        Ok(())
+    }
+}
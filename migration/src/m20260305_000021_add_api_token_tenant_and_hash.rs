@@ -0,0 +1,119 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum ApiToken {
+    Table,
+    Token,
+    TokenHash,
+    TokenPrefix,
+    DisplayName,
+    TenantId,
+}
+
+#[derive(DeriveIden)]
+enum Tenant {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ApiTokenIndexes {
+    ApiTokenTenantIdFk,
+    ApiTokenTenantIdIdx,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Only the SHA-256 of a token is kept from now on. Existing rows are hashed in
+        // place, so tokens already handed out keep working.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ApiToken::Table)
+                    .rename_column(ApiToken::Token, ApiToken::TokenHash)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .get_connection()
+            .execute_unprepared(
+                "UPDATE api_token SET token_hash = encode(sha256(convert_to(token_hash, 'UTF8')), 'hex')",
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ApiToken::Table)
+                    // first characters of the raw token, so listed tokens can be told apart
+                    .add_column(ColumnDef::new(ApiToken::TokenPrefix).string_len(12).null())
+                    .add_column(ColumnDef::new(ApiToken::DisplayName).string_len(255).null())
+                    // NULL for tokens created before tokens were tied to a tenant
+                    .add_column(ColumnDef::new(ApiToken::TenantId).big_integer().null())
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name(ApiTokenIndexes::ApiTokenTenantIdFk.to_string())
+                            .from_tbl(ApiToken::Table)
+                            .from_col(ApiToken::TenantId)
+                            .to_tbl(Tenant::Table)
+                            .to_col(Tenant::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(ApiTokenIndexes::ApiTokenTenantIdIdx.to_string())
+                    .table(ApiToken::Table)
+                    .col(ApiToken::TenantId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(ApiTokenIndexes::ApiTokenTenantIdIdx.to_string())
+                    .table(ApiToken::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ApiToken::Table)
+                    .drop_foreign_key(ApiTokenIndexes::ApiTokenTenantIdFk)
+                    .drop_column(ApiToken::TenantId)
+                    .drop_column(ApiToken::DisplayName)
+                    .drop_column(ApiToken::TokenPrefix)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Hashes can't be reversed: rolled-back tokens must be reissued.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ApiToken::Table)
+                    .rename_column(ApiToken::TokenHash, ApiToken::Token)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
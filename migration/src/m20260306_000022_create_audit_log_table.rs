@@ -0,0 +1,146 @@
+use sea_orm_migration::prelude::*;
+
+// ── Table ──
+
+#[derive(DeriveIden)]
+enum AuditLog {
+    Table,
+    Id,
+    Uuid,
+    CreatedAt,
+    ActorTokenId,
+    Action,
+    EntityType,
+    EntityUuid,
+    Before,
+    After,
+    RequestId,
+}
+
+#[derive(DeriveIden)]
+enum AuditLogIndexes {
+    AuditLogUuidIdx,
+    AuditLogEntityIdx,
+    AuditLogCreatedAtIdx,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AuditLog::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AuditLog::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AuditLog::Uuid)
+                            .uuid()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AuditLog::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    // no foreign key: the row must outlive the token that made the change
+                    .col(ColumnDef::new(AuditLog::ActorTokenId).big_integer().null())
+                    .col(ColumnDef::new(AuditLog::Action).string_len(64).not_null())
+                    .col(ColumnDef::new(AuditLog::EntityType).string_len(64).not_null())
+                    .col(ColumnDef::new(AuditLog::EntityUuid).uuid().not_null())
+                    .col(ColumnDef::new(AuditLog::Before).json_binary().null())
+                    .col(ColumnDef::new(AuditLog::After).json_binary().null())
+                    .col(ColumnDef::new(AuditLog::RequestId).string_len(255).null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(AuditLogIndexes::AuditLogUuidIdx.to_string())
+                    .table(AuditLog::Table)
+                    .col(AuditLog::Uuid)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(AuditLogIndexes::AuditLogEntityIdx.to_string())
+                    .table(AuditLog::Table)
+                    .col(AuditLog::EntityType)
+                    .col(AuditLog::EntityUuid)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(AuditLogIndexes::AuditLogCreatedAtIdx.to_string())
+                    .table(AuditLog::Table)
+                    .col(AuditLog::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        let table_name = AuditLog::Table.to_string();
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                ALTER TABLE {}
+                ALTER COLUMN uuid
+                SET DEFAULT gen_random_uuid();
+                "#,
+                table_name
+            ))
+            .await?;
+
+        // The trail is append-only: reject any UPDATE or DELETE of an audit row.
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                CREATE OR REPLACE FUNCTION audit_log_immutable() RETURNS trigger AS $$
+                BEGIN
+                    RAISE EXCEPTION 'audit_log rows are immutable';
+                END;
+                $$ LANGUAGE plpgsql;
+
+                CREATE TRIGGER audit_log_immutable_trigger
+                BEFORE UPDATE OR DELETE ON {}
+                FOR EACH ROW EXECUTE FUNCTION audit_log_immutable();
+                "#,
+                table_name
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AuditLog::Table).to_owned())
+            .await?;
+        manager
+            .get_connection()
+            .execute_unprepared("DROP FUNCTION IF EXISTS audit_log_immutable();")
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,46 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum ConnectionIdentity {
+    Table,
+    QbxmlVersion,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionIdentity::Table)
+                    // qbXML version QuickBooks Desktop reported for the company file; QBD
+                    // connections only
+                    .add_column(
+                        ColumnDef::new(ConnectionIdentity::QbxmlVersion)
+                            .string_len(16)
+                            .null()
+                            .default("13.0"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionIdentity::Table)
+                    .drop_column(ConnectionIdentity::QbxmlVersion)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use sea_orm_migration::prelude::*;
+
+// ── Table ──
+
+#[derive(DeriveIden)]
+enum Quarantine {
+    Table,
+    Id,
+    Uuid,
+    CreatedAt,
+    UpdatedAt,
+    ConnectionId,
+    ListId,
+    ItemType,
+    RawBody,
+    Error,
+    Attempts,
+}
+
+// References connection_identity table from m20260129_000007_create_connection_identity_table
+#[derive(DeriveIden)]
+enum ConnectionIdentity {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum QuarantineIndexes {
+    QuarantineUuidIdx,
+    QuarantineConnectionListIdIdx,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Quarantine::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Quarantine::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Quarantine::Uuid)
+                            .uuid()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(Quarantine::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(Quarantine::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(Quarantine::ConnectionId).big_integer().not_null())
+                    .col(ColumnDef::new(Quarantine::ListId).string_len(255).not_null())
+                    .col(ColumnDef::new(Quarantine::ItemType).string_len(32).null())
+                    // the item as parsed from the provider response, kept for inspection and replay
+                    .col(ColumnDef::new(Quarantine::RawBody).json_binary().not_null())
+                    .col(ColumnDef::new(Quarantine::Error).text().not_null())
+                    .col(
+                        ColumnDef::new(Quarantine::Attempts)
+                            .integer()
+                            .not_null()
+                            .default(1),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Quarantine::Table, Quarantine::ConnectionId)
+                            .to(ConnectionIdentity::Table, ConnectionIdentity::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(QuarantineIndexes::QuarantineUuidIdx.to_string())
+                    .table(Quarantine::Table)
+                    .col(Quarantine::Uuid)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // One row per item: a failure of an already quarantined item updates its row
+        manager
+            .create_index(
+                Index::create()
+                    .name(QuarantineIndexes::QuarantineConnectionListIdIdx.to_string())
+                    .table(Quarantine::Table)
+                    .col(Quarantine::ConnectionId)
+                    .col(Quarantine::ListId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        let table_name = Quarantine::Table.to_string();
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                ALTER TABLE {}
+                ALTER COLUMN uuid
+                SET DEFAULT gen_random_uuid();
+                "#,
+                table_name
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Quarantine::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
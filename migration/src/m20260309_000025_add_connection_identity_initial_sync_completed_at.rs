@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum ConnectionIdentity {
+    Table,
+    InitialSyncCompletedAt,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionIdentity::Table)
+                    // When the connection's first full List pass finished; null until then
+                    .add_column(
+                        ColumnDef::new(ConnectionIdentity::InitialSyncCompletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionIdentity::Table)
+                    .drop_column(ConnectionIdentity::InitialSyncCompletedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
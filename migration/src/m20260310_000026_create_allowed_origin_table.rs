@@ -0,0 +1,137 @@
+use sea_orm_migration::prelude::*;
+
+// ── Table ──
+
+#[derive(DeriveIden)]
+enum AllowedOrigin {
+    Table,
+    Id,
+    Uuid,
+    CreatedAt,
+    UpdatedAt,
+    TenantId,
+    Origin,
+}
+
+// References tenant table from m20260128_0000006_create_tenant_table
+#[derive(DeriveIden)]
+enum Tenant {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum AllowedOriginIndexes {
+    AllowedOriginUuidIdx,
+    AllowedOriginTenantOriginIdx,
+    AllowedOriginOriginIdx,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(AllowedOrigin::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(AllowedOrigin::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AllowedOrigin::Uuid)
+                            .uuid()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(AllowedOrigin::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(AllowedOrigin::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(AllowedOrigin::TenantId).big_integer().not_null())
+                    // normalized `scheme://host[:port]`, compared against the request Origin
+                    .col(ColumnDef::new(AllowedOrigin::Origin).text().not_null())
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(AllowedOrigin::Table, AllowedOrigin::TenantId)
+                            .to(Tenant::Table, Tenant::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(AllowedOriginIndexes::AllowedOriginUuidIdx.to_string())
+                    .table(AllowedOrigin::Table)
+                    .col(AllowedOrigin::Uuid)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // A tenant registers each origin once
+        manager
+            .create_index(
+                Index::create()
+                    .name(AllowedOriginIndexes::AllowedOriginTenantOriginIdx.to_string())
+                    .table(AllowedOrigin::Table)
+                    .col(AllowedOrigin::TenantId)
+                    .col(AllowedOrigin::Origin)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        // Preflights carry no API token and look the origin up across tenants
+        manager
+            .create_index(
+                Index::create()
+                    .name(AllowedOriginIndexes::AllowedOriginOriginIdx.to_string())
+                    .table(AllowedOrigin::Table)
+                    .col(AllowedOrigin::Origin)
+                    .to_owned(),
+            )
+            .await?;
+
+        let table_name = AllowedOrigin::Table.to_string();
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                ALTER TABLE {}
+                ALTER COLUMN uuid
+                SET DEFAULT gen_random_uuid();
+                "#,
+                table_name
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(AllowedOrigin::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Keep the oldest recurring List event per sync state/category; later copies were
+        // created by racing first polls.
+        db.execute_unprepared(
+            r#"DELETE FROM sync_event duplicate
+               USING sync_event kept
+               WHERE duplicate.sync_event_method = 'list' AND duplicate.details IS NULL
+                 AND kept.sync_event_method = 'list' AND kept.details IS NULL
+                 AND duplicate.connection_sync_state_id = kept.connection_sync_state_id
+                 AND duplicate.sync_event_category = kept.sync_event_category
+                 AND duplicate.id > kept.id"#,
+        )
+        .await?;
+
+        // Only one recurring List event per sync state and category. Events with `details`
+        // (e.g. a QBD `item_type`) are separate recurring pulls and stay unrestricted.
+        db.execute_unprepared(
+            r#"CREATE UNIQUE INDEX IF NOT EXISTS ux_sync_event_recurring_list
+               ON sync_event (connection_sync_state_id, sync_event_method, sync_event_category)
+               WHERE sync_event_method = 'list' AND details IS NULL"#,
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("DROP INDEX IF EXISTS ux_sync_event_recurring_list")
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum ConnectionIdentity {
+    Table,
+    SyncWindowStartHour,
+    SyncWindowEndHour,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionIdentity::Table)
+                    // UTC hours (0-23) pulls may run in: from the start hour up to, not
+                    // including, the end hour; wraps past midnight when start > end.
+                    // Null: no window, pulls run at any time
+                    .add_column(
+                        ColumnDef::new(ConnectionIdentity::SyncWindowStartHour)
+                            .small_integer()
+                            .null(),
+                    )
+                    .add_column(
+                        ColumnDef::new(ConnectionIdentity::SyncWindowEndHour)
+                            .small_integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionIdentity::Table)
+                    .drop_column(ConnectionIdentity::SyncWindowStartHour)
+                    .drop_column(ConnectionIdentity::SyncWindowEndHour)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
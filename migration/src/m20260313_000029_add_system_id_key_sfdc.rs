@@ -0,0 +1,22 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Salesforce products (`Product2.Id`)
+        db.execute_unprepared("ALTER TYPE system_id_key ADD VALUE IF NOT EXISTS 'sfdc'")
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres can't drop a value from an enum type; an unused 'sfdc' is harmless.
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum InventoryRecordEvent {
+    Table,
+    QtyMilli,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(InventoryRecordEvent::Table)
+                    // Quantity in thousandths, so fractional quantities (items sold by
+                    // weight, e.g. 10.5) survive; `qty` keeps the whole units
+                    .add_column(
+                        ColumnDef::new(InventoryRecordEvent::QtyMilli)
+                            .big_integer()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        let db = manager.get_connection();
+        db.execute_unprepared(
+            "UPDATE inventory_record_event SET qty_milli = qty::bigint * 1000 WHERE qty IS NOT NULL",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(InventoryRecordEvent::Table)
+                    .drop_column(InventoryRecordEvent::QtyMilli)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
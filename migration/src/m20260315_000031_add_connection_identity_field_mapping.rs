@@ -0,0 +1,45 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum ConnectionIdentity {
+    Table,
+    FieldMapping,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionIdentity::Table)
+                    // Which provider field fills system_id / name / external_code, e.g.
+                    // {"external_code": "ManufacturerPartNumber"}. Null: the defaults
+                    .add_column(
+                        ColumnDef::new(ConnectionIdentity::FieldMapping)
+                            .json_binary()
+                            .null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(ConnectionIdentity::Table)
+                    .drop_column(ConnectionIdentity::FieldMapping)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
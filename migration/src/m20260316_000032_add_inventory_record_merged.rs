@@ -0,0 +1,90 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveIden)]
+enum InventoryRecord {
+    Table,
+    Id,
+    DeletedAt,
+    MergedIntoId,
+}
+
+#[derive(DeriveIden)]
+enum InventoryRecordIndexes {
+    InventoryRecordMergedIntoIdIdx,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(InventoryRecord::Table)
+                    // Set when the record is soft-deleted, e.g. merged into another record;
+                    // deleted records are left out of listings and exports
+                    .add_column(
+                        ColumnDef::new(InventoryRecord::DeletedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    // The record a merged duplicate's events were moved to
+                    .add_column(
+                        ColumnDef::new(InventoryRecord::MergedIntoId)
+                            .big_integer()
+                            .null(),
+                    )
+                    .add_foreign_key(
+                        TableForeignKey::new()
+                            .name("fk_inventory_record_merged_into_id")
+                            .from_tbl(InventoryRecord::Table)
+                            .from_col(InventoryRecord::MergedIntoId)
+                            .to_tbl(InventoryRecord::Table)
+                            .to_col(InventoryRecord::Id)
+                            .on_delete(ForeignKeyAction::SetNull)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Pulls follow a merged duplicate to its primary by this column
+        manager
+            .create_index(
+                Index::create()
+                    .name(InventoryRecordIndexes::InventoryRecordMergedIntoIdIdx.to_string())
+                    .table(InventoryRecord::Table)
+                    .col(InventoryRecord::MergedIntoId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name(InventoryRecordIndexes::InventoryRecordMergedIntoIdIdx.to_string())
+                    .table(InventoryRecord::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(InventoryRecord::Table)
+                    .drop_foreign_key("fk_inventory_record_merged_into_id")
+                    .drop_column(InventoryRecord::DeletedAt)
+                    .drop_column(InventoryRecord::MergedIntoId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
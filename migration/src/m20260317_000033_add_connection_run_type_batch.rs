@@ -0,0 +1,22 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Inventory pushed through `POST /connections/{uuid}/inventory/batch`
+        db.execute_unprepared("ALTER TYPE connection_run_type ADD VALUE IF NOT EXISTS 'batch'")
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _manager: &SchemaManager) -> Result<(), DbErr> {
+        // Postgres can't drop a value from an enum type; an unused 'batch' is harmless.
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        // Full-text document of an event for `GET /connections/{uuid}/inventory/search`;
+        // name matches weigh more than description matches
+        db.execute_unprepared(
+            "ALTER TABLE inventory_record_event ADD COLUMN IF NOT EXISTS search_vector tsvector \
+             GENERATED ALWAYS AS ( \
+                 setweight(to_tsvector('english', coalesce(name, '')), 'A') || \
+                 setweight(to_tsvector('english', coalesce(description, '')), 'B') \
+             ) STORED",
+        )
+        .await?;
+
+        db.execute_unprepared(
+            "CREATE INDEX IF NOT EXISTS idx_inventory_record_event_search_vector \
+             ON inventory_record_event USING GIN (search_vector)",
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+
+        db.execute_unprepared("DROP INDEX IF EXISTS idx_inventory_record_event_search_vector")
+            .await?;
+        db.execute_unprepared(
+            "ALTER TABLE inventory_record_event DROP COLUMN IF EXISTS search_vector",
+        )
+        .await?;
+
+        Ok(())
+    }
+}
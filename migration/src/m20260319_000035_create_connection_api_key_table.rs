@@ -0,0 +1,138 @@
+use sea_orm_migration::prelude::*;
+
+// ── Table ──
+
+#[derive(DeriveIden)]
+enum ConnectionApiKey {
+    Table,
+    Id,
+    Uuid,
+    CreatedAt,
+    UpdatedAt,
+    ConnectionId,
+    KeyHash,
+    KeyPrefix,
+    DisplayName,
+    RevokedAt,
+}
+
+// References connection_identity table from m20260129_000007_create_connection_identity_table
+#[derive(DeriveIden)]
+enum ConnectionIdentity {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum ConnectionApiKeyIndexes {
+    ConnectionApiKeyUuidIdx,
+    ConnectionApiKeyConnectionIdIdx,
+}
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(ConnectionApiKey::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(ConnectionApiKey::Id)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ConnectionApiKey::Uuid)
+                            .uuid()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(ConnectionApiKey::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(
+                        ColumnDef::new(ConnectionApiKey::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null()
+                            .default(Expr::current_timestamp()),
+                    )
+                    .col(ColumnDef::new(ConnectionApiKey::ConnectionId).big_integer().not_null())
+                    // sha256 hex digest of the key; the key itself is never stored
+                    .col(
+                        ColumnDef::new(ConnectionApiKey::KeyHash)
+                            .text()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(ColumnDef::new(ConnectionApiKey::KeyPrefix).string_len(16).null())
+                    .col(ColumnDef::new(ConnectionApiKey::DisplayName).string_len(255).null())
+                    // set once the key is revoked; requests using it are rejected from then on
+                    .col(
+                        ColumnDef::new(ConnectionApiKey::RevokedAt)
+                            .timestamp_with_time_zone()
+                            .null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(ConnectionApiKey::Table, ConnectionApiKey::ConnectionId)
+                            .to(ConnectionIdentity::Table, ConnectionIdentity::Id)
+                            .on_delete(ForeignKeyAction::Cascade)
+                            .on_update(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(ConnectionApiKeyIndexes::ConnectionApiKeyUuidIdx.to_string())
+                    .table(ConnectionApiKey::Table)
+                    .col(ConnectionApiKey::Uuid)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name(ConnectionApiKeyIndexes::ConnectionApiKeyConnectionIdIdx.to_string())
+                    .table(ConnectionApiKey::Table)
+                    .col(ConnectionApiKey::ConnectionId)
+                    .to_owned(),
+            )
+            .await?;
+
+        let table_name = ConnectionApiKey::Table.to_string();
+        manager
+            .get_connection()
+            .execute_unprepared(&format!(
+                r#"
+                ALTER TABLE {}
+                ALTER COLUMN uuid
+                SET DEFAULT gen_random_uuid();
+                "#,
+                table_name
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(ConnectionApiKey::Table).to_owned())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use migration::MigratorTrait;
+use sea_orm::{DatabaseConnection, DbErr};
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Migration names known to the binary, split by whether the database has applied them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationStatus {
+    pub applied: Vec<String>,
+    pub pending: Vec<String>,
+}
+
+/// BEGUN IMPLEMENTATION ///
+impl MigrationStatus {
+    pub fn is_up_to_date(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+///reads `seaql_migrations` for migrator `M` (the app's `migration::Migrator` in production);
+///names are in migration order
+pub async fn migration_status<M: MigratorTrait>(
+    db: &DatabaseConnection,
+) -> Result<MigrationStatus, DbErr> {
+    let applied = M::get_applied_migrations(db).await?;
+    let pending = M::get_pending_migrations(db).await?;
+
+    Ok(MigrationStatus {
+        applied: applied.iter().map(|m| m.name().to_string()).collect(),
+        pending: pending.iter().map(|m| m.name().to_string()).collect(),
+    })
+}
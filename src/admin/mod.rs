@@ -1,3 +1,5 @@
+pub mod migrations;
+pub mod readiness;
 pub mod routes;
 pub mod services;
 
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use migration::MigratorTrait;
+use redis::aio::ConnectionManager;
+use sea_orm::{DatabaseBackend, DatabaseConnection};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::migrations::migration_status;
+
+///how long each dependency check may take before it counts as failed
+pub const READINESS_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// BEGUN STRUCTS AND ENUMS ///
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ReadinessReport {
+    pub db: DatabaseReadiness,
+    pub redis: RedisReadiness,
+    pub migrations: MigrationReadiness,
+    /// Version of the running binary.
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct DatabaseReadiness {
+    pub connected: bool,
+    /// Pool connections checked out; null when the backend doesn't report pool stats.
+    pub pool_active: Option<u32>,
+    /// Pool connections open and waiting to be checked out.
+    pub pool_idle: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct RedisReadiness {
+    pub connected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrationReadiness {
+    /// Null when `seaql_migrations` couldn't be read.
+    pub applied_count: Option<usize>,
+    pub pending_count: Option<usize>,
+}
+
+/// END STRUCTS AND ENUMS ///
+
+
+/// BEGUN IMPLEMENTATION ///
+impl ReadinessReport {
+    ///the critical checks: database and Redis reachable, every migration applied
+    pub fn is_ready(&self) -> bool {
+        self.db.connected && self.redis.connected && self.migrations.pending_count == Some(0)
+    }
+}
+
+///pings the database, reads its pool stats and the applied/pending migrations of migrator `M`
+pub async fn database_readiness<M: MigratorTrait>(
+    db: &DatabaseConnection,
+) -> (DatabaseReadiness, MigrationReadiness) {
+    let connected = matches!(
+        tokio::time::timeout(READINESS_CHECK_TIMEOUT, db.ping()).await,
+        Ok(Ok(()))
+    );
+
+    //sqlx reports `size` as every open connection, idle ones included
+    let (pool_active, pool_idle) = match db.get_database_backend() {
+        DatabaseBackend::Postgres => {
+            let pool = db.get_postgres_connection_pool();
+            let idle = pool.num_idle() as u32;
+            (Some(pool.size().saturating_sub(idle)), Some(idle))
+        }
+        _ => (None, None),
+    };
+
+    let migrations = match connected {
+        true => tokio::time::timeout(READINESS_CHECK_TIMEOUT, migration_status::<M>(db))
+            .await
+            .ok()
+            .and_then(Result::ok),
+        false => None,
+    };
+
+    (
+        DatabaseReadiness {
+            connected,
+            pool_active,
+            pool_idle,
+        },
+        MigrationReadiness {
+            applied_count: migrations.as_ref().map(|m| m.applied.len()),
+            pending_count: migrations.as_ref().map(|m| m.pending.len()),
+        },
+    )
+}
+
+///sends PING over the shared Redis connection
+pub async fn redis_readiness(redis: &ConnectionManager) -> RedisReadiness {
+    let mut redis = redis.clone();
+    let cmd = redis::cmd("PING");
+    let ping = cmd.query_async::<String>(&mut redis);
+    RedisReadiness {
+        connected: matches!(
+            tokio::time::timeout(READINESS_CHECK_TIMEOUT, ping).await,
+            Ok(Ok(_))
+        ),
+    }
+}
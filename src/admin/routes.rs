@@ -1,8 +1,27 @@
-use axum::{routing::get, Router};
+use axum::{middleware::from_fn, routing::{get, put}, Router};
+use crate::middleware::admin_token_auth_middleware;
 use crate::AppState;
 use super::services;
 
 pub fn create_router() -> Router<AppState> {
     Router::new()
         .route("/health", get(services::health_check))
+        .route("/readyz", get(services::readyz))
+        .route(
+            "/migrations",
+            get(services::get_migrations).layer(from_fn(admin_token_auth_middleware)),
+        )
+        .route(
+            "/log-level",
+            put(services::set_log_level).layer(from_fn(admin_token_auth_middleware)),
+        )
+        .route(
+            "/maintenance",
+            put(services::set_maintenance).layer(from_fn(admin_token_auth_middleware)),
+        )
+        .nest("/api-tokens", crate::security::routes::create_router())
+        .nest("/allowed-origins", crate::security::allowed_origin_routes::create_router())
+        .nest("/audit-log", crate::audit::routes::create_router())
+        .nest("/credentials", crate::erp_connection_credentials::routes::create_router())
+        .nest("/sync-events", crate::sync_event::routes::create_router())
 }
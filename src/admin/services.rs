@@ -1,13 +1,64 @@
-use axum::{http::StatusCode, Json};
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use utoipa::ToSchema;
 
+use crate::config::telemetry::{current_log_filter, set_log_filter, TelemetryError};
+use crate::middleware::maintenance::set_maintenance_mode;
+use crate::tenant::routes::ErrorResponse;
+use crate::validation::FieldError;
+use crate::AppState;
+use super::migrations::migration_status;
+use super::readiness::{database_readiness, redis_readiness, ReadinessReport};
+
 #[derive(ToSchema)]
 pub struct AdminHealthResponse {
     status: String,
     module: String,
 }
 
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MigrationStatusResponse {
+    /// True when every migration compiled into this binary has been applied.
+    pub up_to_date: bool,
+    /// Most recently applied migration; null on an empty database.
+    pub latest_applied: Option<String>,
+    pub applied: Vec<String>,
+    /// Migrations the binary knows about that the database hasn't applied yet.
+    pub pending: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LogLevelResponse {
+    /// Filter in effect after the change.
+    pub filter: String,
+    /// Filter that was replaced.
+    pub previous: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SetLogLevelRequest {
+    /// `RUST_LOG` syntax, e.g. `info,sea_orm=debug`.
+    pub filter: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MaintenanceModeResponse {
+    /// Whether maintenance mode is on after the change.
+    pub enabled: bool,
+    pub previous: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SetMaintenanceModeRequest {
+    pub enabled: bool,
+}
+
 #[utoipa::path(
     get,
     path = "/admin/health",
@@ -25,3 +76,119 @@ pub async fn health_check() -> (StatusCode, Json<Value>) {
         }))
     )
 }
+
+#[utoipa::path(
+    get,
+    path = "/admin/readyz",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "Database and Redis reachable, no pending migrations", body = ReadinessReport),
+        (status = 503, description = "A critical check failed; the report shows which", body = ReadinessReport)
+    )
+)]
+pub async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<ReadinessReport>) {
+    let ((db, migrations), redis) = tokio::join!(
+        database_readiness::<migration::Migrator>(&state.db),
+        redis_readiness(&state.redis),
+    );
+    let report = ReadinessReport {
+        db,
+        redis,
+        migrations,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+    };
+
+    let status = match report.is_ready() {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (status, Json(report))
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/migrations",
+    tag = "Admin",
+    responses(
+        (status = 200, description = "Applied and pending migrations", body = MigrationStatusResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn get_migrations(
+    State(state): State<AppState>,
+) -> Result<Json<MigrationStatusResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match migration_status::<migration::Migrator>(&state.db).await {
+        Ok(status) => Ok(Json(MigrationStatusResponse {
+            up_to_date: status.is_up_to_date(),
+            latest_applied: status.applied.last().cloned(),
+            applied: status.applied,
+            pending: status.pending,
+        })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Database error: {}", e),
+                details: None,
+            }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/log-level",
+    tag = "Admin",
+    request_body = SetLogLevelRequest,
+    responses(
+        (status = 200, description = "Tracing filter replaced", body = LogLevelResponse),
+        (status = 400, description = "Invalid filter directives", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 500, description = "Tracing is not initialized", body = ErrorResponse)
+    )
+)]
+pub async fn set_log_level(
+    Json(payload): Json<SetLogLevelRequest>,
+) -> Result<Json<LogLevelResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let previous = current_log_filter();
+    match set_log_filter(&payload.filter) {
+        Ok(filter) => {
+            tracing::warn!("Log filter changed from {:?} to {}", previous, filter);
+            Ok(Json(LogLevelResponse { filter, previous }))
+        }
+        Err(TelemetryError::Filter(msg)) => {
+            Err(ErrorResponse::validation(vec![FieldError::new("filter", msg)]))
+        }
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: e.to_string(),
+                details: None,
+            }),
+        )),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/maintenance",
+    tag = "Admin",
+    request_body = SetMaintenanceModeRequest,
+    responses(
+        (status = 200, description = "Maintenance mode switched", body = MaintenanceModeResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse)
+    )
+)]
+pub async fn set_maintenance(
+    Json(payload): Json<SetMaintenanceModeRequest>,
+) -> Json<MaintenanceModeResponse> {
+    let previous = set_maintenance_mode(payload.enabled);
+    tracing::warn!("Maintenance mode changed from {} to {}", previous, payload.enabled);
+    Json(MaintenanceModeResponse {
+        enabled: payload.enabled,
+        previous,
+    })
+}
@@ -0,0 +1,4 @@
+pub mod routes;
+pub mod services;
+
+pub use services::{AuditContext, AuditService};
@@ -0,0 +1,224 @@
+use axum::{
+    extract::{FromRequestParts, OriginalUri, Query, State},
+    http::{request::Parts, HeaderMap, StatusCode},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::pagination::{paginated_response, PageInfo};
+use crate::security::AuthenticatedToken;
+use crate::tenant::routes::ErrorResponse;
+use crate::validation::FieldError;
+use super::services::{AuditContext, AuditLogFilter, AuditService};
+
+/// Header a caller can set to tie audit rows to its own request id.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+
+/// RESPONSE SCHEMAS ///
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AuditLogResponse {
+    pub uuid: String,
+    pub created_at: String,
+    /// Row id of the API token that made the change; null for changes made without one.
+    pub actor_token_id: Option<i64>,
+    /// `create`, `update` or `delete`.
+    pub action: String,
+    /// Table of the changed row (`tenant`, `connection_identity`, `erp_connection_credentials`).
+    pub entity_type: String,
+    pub entity_uuid: String,
+    /// Changed fields before the change (null for creates, the whole row for hard deletes);
+    /// secrets read `[REDACTED]`.
+    #[schema(value_type = Option<Object>)]
+    pub before: Option<Value>,
+    /// Changed fields after the change (the whole row for creates, null for hard deletes).
+    #[schema(value_type = Option<Object>)]
+    pub after: Option<Value>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PaginatedAuditLogResponse {
+    pub items: Vec<AuditLogResponse>,
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+}
+
+
+/// REQUEST SCHEMAS ///
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ListAuditLogQuery {
+    #[param(default = 1)]
+    pub page: Option<u64>,
+    #[param(default = 50)]
+    pub per_page: Option<u64>,
+    pub entity_type: Option<String>,
+    pub entity_uuid: Option<String>,
+    pub action: Option<String>,
+    pub actor_token_id: Option<i64>,
+    /// Only changes at or after this time (RFC 3339).
+    pub since: Option<String>,
+    /// Only changes before this time (RFC 3339).
+    pub until: Option<String>,
+}
+
+
+/// VALIDATION ///
+impl ListAuditLogQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.per_page == Some(0) {
+            errors.push(FieldError::new("per_page", "must be greater than 0"));
+        }
+        if let Some(Err(_)) = self.entity_uuid.as_deref().map(Uuid::parse_str) {
+            errors.push(FieldError::new("entity_uuid", "must be a UUID"));
+        }
+        if let Some(action) = self.action.as_deref()
+            && !["create", "update", "delete"].contains(&action)
+        {
+            errors.push(FieldError::new("action", "must be one of: create, update, delete"));
+        }
+        for (field, value) in [("since", &self.since), ("until", &self.until)] {
+            if value.as_deref().is_some_and(|v| parse_time(v).is_none()) {
+                errors.push(FieldError::new(field, "must be an RFC 3339 timestamp"));
+            }
+        }
+        errors
+    }
+}
+
+
+/// HELPER FUNCTIONS ///
+///the actor and request id of an audited change, taken from the token accepted by
+///`api_token_auth_middleware` and the `X-Request-Id` header
+impl<S: Send + Sync> FromRequestParts<S> for AuditContext {
+    type Rejection = std::convert::Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(AuditContext {
+            actor_token_id: parts.extensions.get::<AuthenticatedToken>().map(|t| t.0),
+            request_id: parts
+                .headers
+                .get(REQUEST_ID_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string),
+        })
+    }
+}
+
+fn parse_time(value: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+    chrono::DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&chrono::Utc))
+}
+
+fn model_to_response(model: entity::audit_log::Model) -> AuditLogResponse {
+    AuditLogResponse {
+        uuid: model.uuid.to_string(),
+        created_at: model.created_at.to_rfc3339(),
+        actor_token_id: model.actor_token_id,
+        action: model.action,
+        entity_type: model.entity_type,
+        entity_uuid: model.entity_uuid.to_string(),
+        before: model.before,
+        after: model.after,
+        request_id: model.request_id,
+    }
+}
+
+
+/// ROUTE HANDLERS ///
+
+#[utoipa::path(
+    get,
+    path = "/admin/audit-log",
+    tag = "Admin",
+    params(ListAuditLogQuery),
+    responses(
+        (status = 200, description = "Audit rows, newest first", body = PaginatedAuditLogResponse,
+            headers(
+                ("X-Total-Count" = u64, description = "Total matching rows"),
+                ("X-Page" = u64, description = "Current page"),
+                ("X-Per-Page" = u64, description = "Page size"),
+                ("X-Total-Pages" = u64, description = "Number of pages"),
+                ("Link" = String, description = "RFC 5988 first/prev/next/last page links")
+            )),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn list_audit_log(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    Query(query): Query<ListAuditLogQuery>,
+) -> Result<(HeaderMap, Json<PaginatedAuditLogResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let errors = query.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(50);
+    let filter = AuditLogFilter {
+        entity_type: query.entity_type,
+        entity_uuid: query.entity_uuid.and_then(|u| Uuid::parse_str(&u).ok()),
+        action: query.action,
+        actor_token_id: query.actor_token_id,
+        since: query.since.as_deref().and_then(parse_time),
+        until: query.until.as_deref().and_then(parse_time),
+    };
+
+    match AuditService::new(state.db).get_all(page, per_page, Some(filter), None).await {
+        Ok(result) => {
+            let headers = paginated_response(
+                &PageInfo {
+                    page: result.page,
+                    per_page: result.per_page,
+                    total: result.total,
+                    total_pages: result.total_pages,
+                },
+                &uri,
+            );
+            Ok((
+                headers,
+                Json(PaginatedAuditLogResponse {
+                    items: result.items.into_iter().map(model_to_response).collect(),
+                    total: result.total,
+                    page: result.page,
+                    per_page: result.per_page,
+                    total_pages: result.total_pages,
+                }),
+            ))
+        }
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Database error: {}", e),
+                details: None,
+            }),
+        )),
+    }
+}
+
+
+/// ROUTER ///
+///mounted at /admin/audit-log behind the admin token middleware (read-only)
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_audit_log))
+        .layer(axum::middleware::from_fn(
+            crate::middleware::admin_token_auth_middleware,
+        ))
+}
@@ -0,0 +1,298 @@
+//! Audit trail of mutating operations.
+//!
+//! Services built `with_audit` call `record` in the same transaction as the change they
+//! make, so an audit row exists if and only if the change was committed. Rows are never
+//! updated or deleted (a Postgres trigger rejects both). Updates store only the fields that
+//! changed, as `before`/`after` objects; creates store the new row as `after`. Secret
+//! columns (tokens, passwords, keys) are written as `[REDACTED]`.
+
+use entity::audit_log;
+use sea_orm::sea_query::sea_value_to_json_value;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
+    EntityName, EntityTrait, IdenStatic, Iterable, ModelTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, Set,
+};
+use serde_json::{Map, Value};
+use uuid::Uuid;
+
+/// Replacement for secret values in `before`/`after`.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Columns never written to the audit trail in clear.
+pub const SECRET_FIELDS: [&str; 11] = [
+    "access_token",
+    "refresh_token",
+    "id_token_enc",
+    "provider_password",
+    "client_cert",
+    "private_key",
+    "session_token",
+    "api_access_token",
+    "api_access_token_key",
+    "enc_iv",
+    "enc_tag",
+];
+
+/// Columns that change on every write and say nothing about what was changed.
+const IGNORED_FIELDS: [&str; 1] = ["updated_at"];
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Who made a change: the API token that authenticated the request, and the request's
+/// `X-Request-Id` when the caller sent one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AuditContext {
+    pub actor_token_id: Option<i64>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditAction {
+    Create,
+    Update,
+    ///soft deletes included: the row stays, its status changes
+    Delete,
+}
+
+/// One change to record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub action: AuditAction,
+    /// Table of the changed row (e.g. `tenant`).
+    pub entity_type: String,
+    pub entity_uuid: Uuid,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+pub struct AuditService {
+    db: DatabaseConnection,
+}
+
+#[allow(dead_code)]
+#[derive(Default)]
+pub struct AuditLogFilter {
+    pub entity_type: Option<String>,
+    pub entity_uuid: Option<Uuid>,
+    pub action: Option<String>,
+    pub actor_token_id: Option<i64>,
+    pub since: Option<chrono::DateTime<chrono::Utc>>,
+    pub until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[allow(dead_code)]
+pub struct PaginatedAuditLog {
+    pub items: Vec<audit_log::Model>,
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+}
+
+/// END STRUCTS AND ENUMS ///
+
+
+/// BEGUN IMPLEMENTATION ///
+impl AuditAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+}
+
+impl AuditEntry {
+    ///a new row: `after` holds the whole (redacted) row
+    pub fn created<M: ModelTrait>(entity_uuid: Uuid, model: &M) -> Self {
+        Self {
+            action: AuditAction::Create,
+            entity_type: entity_type::<M>(),
+            entity_uuid,
+            before: None,
+            after: Some(redact(snapshot(model))),
+        }
+    }
+
+    ///a hard-deleted row: `before` holds the whole (redacted) row
+    pub fn deleted<M: ModelTrait>(entity_uuid: Uuid, model: &M) -> Self {
+        Self {
+            action: AuditAction::Delete,
+            entity_type: entity_type::<M>(),
+            entity_uuid,
+            before: Some(redact(snapshot(model))),
+            after: None,
+        }
+    }
+
+    ///an updated row: `before`/`after` hold only the fields that changed. Secrets are
+    ///compared before redaction, so a changed secret still shows up (as `REDACTED`)
+    pub fn changed<M: ModelTrait>(
+        action: AuditAction,
+        entity_uuid: Uuid,
+        before: &M,
+        after: &M,
+    ) -> Self {
+        let (before, after) = diff(&snapshot(before), &snapshot(after));
+        Self {
+            action,
+            entity_type: entity_type::<M>(),
+            entity_uuid,
+            before: Some(redact(before)),
+            after: Some(redact(after)),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl AuditService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    ///writes one audit row inside `txn`, so it commits or rolls back with the change
+    pub async fn record(
+        &self,
+        context: &AuditContext,
+        entry: AuditEntry,
+        txn: &DatabaseTransaction,
+    ) -> Result<audit_log::Model, DbErr> {
+        audit_log::ActiveModel {
+            uuid: Set(Uuid::new_v4()),
+            actor_token_id: Set(context.actor_token_id),
+            action: Set(entry.action.as_str().to_string()),
+            entity_type: Set(entry.entity_type),
+            entity_uuid: Set(entry.entity_uuid),
+            before: Set(entry.before),
+            after: Set(entry.after),
+            request_id: Set(context.request_id.clone()),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await
+    }
+
+    ///newest first
+    pub async fn get_all(
+        &self,
+        page: u64,
+        per_page: u64,
+        filter: Option<AuditLogFilter>,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<PaginatedAuditLog, DbErr> {
+        let mut condition = Condition::all();
+
+        if let Some(f) = filter {
+            if let Some(entity_type) = f.entity_type {
+                condition = condition.add(audit_log::Column::EntityType.eq(entity_type));
+            }
+            if let Some(entity_uuid) = f.entity_uuid {
+                condition = condition.add(audit_log::Column::EntityUuid.eq(entity_uuid));
+            }
+            if let Some(action) = f.action {
+                condition = condition.add(audit_log::Column::Action.eq(action));
+            }
+            if let Some(actor_token_id) = f.actor_token_id {
+                condition = condition.add(audit_log::Column::ActorTokenId.eq(actor_token_id));
+            }
+            if let Some(since) = f.since {
+                condition = condition.add(audit_log::Column::CreatedAt.gte(since));
+            }
+            if let Some(until) = f.until {
+                condition = condition.add(audit_log::Column::CreatedAt.lt(until));
+            }
+        }
+
+        let query = audit_log::Entity::find()
+            .filter(condition)
+            .order_by_desc(audit_log::Column::CreatedAt)
+            .order_by_desc(audit_log::Column::Id);
+
+        let total = match txn {
+            Some(txn) => query.clone().count(txn).await?,
+            None => query.clone().count(&self.db).await?,
+        };
+
+        let total_pages = (total as f64 / per_page as f64).ceil() as u64;
+
+        let items = match txn {
+            Some(txn) => {
+                query
+                    .paginate(txn, per_page)
+                    .fetch_page(page.saturating_sub(1))
+                    .await?
+            }
+            None => {
+                query
+                    .paginate(&self.db, per_page)
+                    .fetch_page(page.saturating_sub(1))
+                    .await?
+            }
+        };
+
+        Ok(PaginatedAuditLog {
+            items,
+            total,
+            page,
+            per_page,
+            total_pages,
+        })
+    }
+}
+
+fn entity_type<M: ModelTrait>() -> String {
+    M::Entity::default().table_name().to_string()
+}
+
+///the row as a JSON object keyed by column name (secrets included; see `redact`)
+pub fn snapshot<M: ModelTrait>(model: &M) -> Value {
+    let row: Map<String, Value> = <M::Entity as EntityTrait>::Column::iter()
+        .map(|col| (col.as_str().to_string(), sea_value_to_json_value(&model.get(col))))
+        .collect();
+    Value::Object(row)
+}
+
+///replaces every non-null secret field of a JSON object with `REDACTED`
+pub fn redact(mut value: Value) -> Value {
+    if let Value::Object(map) = &mut value {
+        for (key, field) in map.iter_mut() {
+            if SECRET_FIELDS.contains(&key.as_str()) && !field.is_null() {
+                *field = Value::String(REDACTED.to_string());
+            }
+        }
+    }
+    value
+}
+
+///names of the columns whose value differs between two rows, in column name order;
+///`updated_at` is left out
+pub fn changed_fields<M: ModelTrait>(before: &M, after: &M) -> Vec<String> {
+    let (_, after) = diff(&snapshot(before), &snapshot(after));
+    after
+        .as_object()
+        .map(|fields| fields.keys().cloned().collect())
+        .unwrap_or_default()
+}
+
+///the fields whose value differs between two snapshots, as (`before`, `after`) objects;
+///`updated_at` is left out
+pub fn diff(before: &Value, after: &Value) -> (Value, Value) {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let mut old = Map::new();
+    let mut new = Map::new();
+    for (key, value) in after {
+        if IGNORED_FIELDS.contains(&key.as_str()) {
+            continue;
+        }
+        let previous = before.get(key).unwrap_or(&Value::Null);
+        if previous != value {
+            old.insert(key.clone(), previous.clone());
+            new.insert(key.clone(), value.clone());
+        }
+    }
+    (Value::Object(old), Value::Object(new))
+}
@@ -0,0 +1,25 @@
+use axum::Json;
+
+use crate::tenant::routes::ErrorResponse;
+
+use super::services::{capabilities, CapabilitiesResponse, FeatureFlags};
+
+/// ROUTE HANDLERS ///
+#[utoipa::path(
+    get,
+    path = "/capabilities",
+    tag = "Capabilities",
+    responses(
+        (status = 200, description = "Providers, sync kinds and feature flags of this deployment", body = CapabilitiesResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse)
+    )
+)]
+pub async fn get_capabilities() -> Json<CapabilitiesResponse> {
+    let config = crate::config::env::get();
+    Json(capabilities(FeatureFlags {
+        encryption_required: config.credentials.require_encryption,
+        scheduler_enabled: config.sync.scheduler_enabled,
+        webhooks_enabled: false,
+        outbox_relay_enabled: config.outbox.relay_enabled,
+    }))
+}
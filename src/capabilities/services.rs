@@ -0,0 +1,136 @@
+//! What this deployment supports, for `GET /capabilities`.
+//!
+//! The integrations are compile-time constants (`INTEGRATIONS`): every provider/`erp_type`
+//! pair with a sync implemented, and the category, method and direction of each sync. The
+//! feature flags come from the runtime config, so integrators can tell what a deployment does
+//! without guessing from its version.
+
+use entity::sea_orm_active_enums::{
+    ErpProvider, ErpProviderType, SyncEventCategory, SyncEventDirection, SyncEventMethod,
+};
+use sea_orm::ActiveEnum;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// A provider/`erp_type` pair this build can sync, with the syncs implemented for it.
+pub struct Integration {
+    pub provider: ErpProvider,
+    pub erp_type: ErpProviderType,
+    pub syncs: &'static [SyncKind],
+}
+
+/// One kind of sync event an integration runs.
+pub struct SyncKind {
+    pub direction: SyncEventDirection,
+    pub category: SyncEventCategory,
+    pub method: SyncEventMethod,
+}
+
+/// Deployment settings integrators may depend on.
+#[derive(Debug, Clone, Copy, Default, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FeatureFlags {
+    /// Credentials must be stored encrypted (`REQUIRE_CREDENTIAL_ENCRYPTION`).
+    pub encryption_required: bool,
+    /// API connections are polled on a schedule (`SYNC_SCHEDULER_ENABLED`); Desktop
+    /// connections are always driven by the Web Connector.
+    pub scheduler_enabled: bool,
+    /// Outbound webhooks; not implemented, so always false. Changes are published through the
+    /// outbox instead.
+    pub webhooks_enabled: bool,
+    /// Outbox messages are relayed to the Redis stream (`OUTBOX_RELAY_ENABLED`).
+    pub outbox_relay_enabled: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SyncCapabilityResponse {
+    pub category: String,
+    pub method: String,
+    pub direction: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ProviderCapabilityResponse {
+    pub erp_provider: String,
+    pub erp_type: String,
+    pub syncs: Vec<SyncCapabilityResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CapabilitiesResponse {
+    pub providers: Vec<ProviderCapabilityResponse>,
+    /// `erp_type`s with at least one integration.
+    pub erp_types: Vec<String>,
+    pub features: FeatureFlags,
+}
+
+// END STRUCTS AND ENUMS
+
+const PULL_INVENTORY: SyncKind = SyncKind {
+    direction: SyncEventDirection::PullFromExternal,
+    category: SyncEventCategory::Inventory,
+    method: SyncEventMethod::List,
+};
+
+const PUSH_ORDERS: SyncKind = SyncKind {
+    direction: SyncEventDirection::PushToExternal,
+    category: SyncEventCategory::Order,
+    method: SyncEventMethod::Create,
+};
+
+/// Integrations implemented in this build.
+pub const INTEGRATIONS: &[Integration] = &[
+    Integration {
+        provider: ErpProvider::Quickbooks,
+        erp_type: ErpProviderType::Desktop,
+        syncs: &[PULL_INVENTORY, PUSH_ORDERS],
+    },
+    Integration {
+        provider: ErpProvider::Sap,
+        erp_type: ErpProviderType::Api,
+        syncs: &[PULL_INVENTORY],
+    },
+    Integration {
+        provider: ErpProvider::Salesforce,
+        erp_type: ErpProviderType::Api,
+        syncs: &[PULL_INVENTORY],
+    },
+];
+
+/// BEGUN IMPLEMENTATION ///
+///the integrations of this build with the deployment's `features`
+pub fn capabilities(features: FeatureFlags) -> CapabilitiesResponse {
+    let providers = INTEGRATIONS
+        .iter()
+        .map(|integration| ProviderCapabilityResponse {
+            erp_provider: integration.provider.to_value(),
+            erp_type: integration.erp_type.to_value(),
+            syncs: integration
+                .syncs
+                .iter()
+                .map(|sync| SyncCapabilityResponse {
+                    category: sync.category.to_value(),
+                    method: sync.method.to_value(),
+                    direction: sync.direction.to_value(),
+                })
+                .collect(),
+        })
+        .collect::<Vec<_>>();
+
+    let mut erp_types: Vec<String> = Vec::new();
+    for provider in &providers {
+        if !erp_types.contains(&provider.erp_type) {
+            erp_types.push(provider.erp_type.clone());
+        }
+    }
+
+    CapabilitiesResponse {
+        providers,
+        erp_types,
+        features,
+    }
+}
@@ -1 +1,3 @@
 pub mod quickbooks;
+pub mod salesforce;
+pub mod sap;
@@ -0,0 +1,90 @@
+//! Per-connection choice of which QBD field fills each identity field of an inventory record.
+//!
+//! A connection's `field_mapping` (JSON) names, for `system_id`, `name` and `external_code`,
+//! the QBD field to read, by its key in the item's flattened body, e.g.
+//! `{"external_code": "ManufacturerPartNumber"}`. Unset keys keep the defaults: `ListID`,
+//! `Name` and `FullName`. An item without the mapped field falls back to the default field.
+//!
+//! Changing `system_id` on a connection that already has records matches items to records by
+//! the new field, so the existing records stop being updated and new ones are created.
+
+use entity::connection_identity;
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::qbxml::QbdInventoryItem;
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// QBD field (key in the item's `raw` body) read for each mapped record field.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QbdFieldMapping {
+    /// The record's `system_id`, which items are matched on.
+    pub system_id: String,
+    /// The event's `name`.
+    pub name: String,
+    /// The event's `external_code`.
+    pub external_code: String,
+}
+
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl Default for QbdFieldMapping {
+    fn default() -> Self {
+        Self {
+            system_id: "ListID".to_string(),
+            name: "Name".to_string(),
+            external_code: "FullName".to_string(),
+        }
+    }
+}
+
+impl QbdFieldMapping {
+    ///parses a stored or submitted `field_mapping`; the error names what is wrong with it
+    pub fn from_json(value: &Value) -> Result<Self, String> {
+        let mapping: Self = serde_json::from_value(value.clone())
+            .map_err(|e| format!("Invalid field mapping: {}", e))?;
+        for (key, field) in [
+            ("system_id", &mapping.system_id),
+            ("name", &mapping.name),
+            ("external_code", &mapping.external_code),
+        ] {
+            if field.trim().is_empty() {
+                return Err(format!("Field mapping '{}' must name a QBD field", key));
+            }
+        }
+        Ok(mapping)
+    }
+
+    ///the connection's mapping; the defaults when it has none or an unreadable one
+    pub fn of(conn: &connection_identity::Model) -> Self {
+        let Some(value) = conn.field_mapping.as_ref().filter(|v| !v.is_null()) else {
+            return Self::default();
+        };
+        Self::from_json(value).unwrap_or_else(|e| {
+            tracing::warn!(connection_id = conn.id, error = %e, "Ignoring connection field_mapping");
+            Self::default()
+        })
+    }
+
+    ///sets `list_id`, `name` and `full_name` of `item` from the mapped fields of its raw body
+    pub fn apply(&self, item: &mut QbdInventoryItem) {
+        let defaults = Self::default();
+        let field = |mapped: &str, default: &str| {
+            [mapped, default]
+                .into_iter()
+                .find_map(|key| item.raw.get(key).and_then(|v| v.as_str()))
+                .map(str::to_string)
+        };
+        let system_id = field(&self.system_id, &defaults.system_id);
+        let name = field(&self.name, &defaults.name);
+        let external_code = field(&self.external_code, &defaults.external_code);
+
+        if let Some(system_id) = system_id {
+            item.list_id = system_id;
+        }
+        item.name = name;
+        item.full_name = external_code;
+    }
+}
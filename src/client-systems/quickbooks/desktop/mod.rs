@@ -1,5 +1,14 @@
+pub mod field_mapping;
+pub mod order_push;
+pub mod order_qbxml;
+pub mod poll_health;
 pub mod poll_services;
+pub mod qbd_status;
+pub mod qbxml;
+pub mod qwc_credentials;
 pub mod routes;
 pub mod services;
+#[cfg(feature = "test-util")]
+pub mod test_support;
 
 pub use routes::{create_poll_router, create_router};
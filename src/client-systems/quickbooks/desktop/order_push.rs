@@ -0,0 +1,383 @@
+//! Order push to QuickBooks Desktop over the Web Connector.
+//!
+//! Orders are queued as PushToExternal Order/Create sync events on the connection's sync
+//! state, with the order in `original_record_body`:
+//!
+//! ```json
+//! {
+//!   "txn_type": "invoice",                      // or "sales_receipt"; invoice when absent
+//!   "customer": { "list_id": "80000001-1234567890", "full_name": "Acme Corp" },
+//!   "ref_number": "SO-1001", "txn_date": "2026-01-31", "memo": "Web order",
+//!   "lines": [
+//!     { "inventory_record_uuid": "…", "quantity": 2, "rate_cents": 1999, "desc": "Widget" }
+//!   ]
+//! }
+//! ```
+//!
+//! `QbdPollService` asks for a push before the inventory pull in each request phase:
+//!   1. Pushes still InProgress never got a response; they are marked Error rather than
+//!      sent again, since QBD may have created the transaction already
+//!   2. The oldest Pending push is turned into an `InvoiceAddRq` / `SalesReceiptAddRq`.
+//!      Each line's `inventory_record_uuid` is resolved to the ListID (`system_id`) of an
+//!      inventory record pulled from this connection. An order that can't be built (bad
+//!      payload, unknown item) is marked Error and the next one is tried
+//!   3. The event is marked InProgress with `attempts` incremented and the transaction type
+//!      in `details.txn_type`
+//!
+//! The response phase parses the `*AddRs` and settles the event: Success with the new
+//! `details.txn_id` (and `details.ref_number`), or Error with QBD's code, message and
+//! whether it is retryable in `last_error`, like a failed pull.
+
+use chrono::{NaiveDate, Utc};
+use entity::sea_orm_active_enums::{
+    SyncEventCategory, SyncEventDirection, SyncEventMethod, SyncEventStatus, SystemIdKey,
+};
+use entity::{connection_identity, erp_connection_sync_state, inventory_record, sync_event};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter,
+    QueryOrder, QuerySelect, Set,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::order_qbxml::{
+    build_txn_add_xml, parse_txn_add_response, QbdOrder, QbdOrderLine, QbdTxnType,
+};
+use super::poll_services::QbdPollError;
+use super::qbd_status::{self, QbdStatusOutcome};
+
+/// Key in the sync event `details` JSON holding the transaction type sent.
+pub const TXN_TYPE_KEY: &str = "txn_type";
+
+/// Key in the sync event `details` JSON holding the TxnID QBD assigned.
+pub const TXN_ID_KEY: &str = "txn_id";
+
+// ── Payload ───────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Deserialize)]
+struct OrderPayload {
+    txn_type: Option<String>,
+    customer: Option<CustomerPayload>,
+    ref_number: Option<String>,
+    txn_date: Option<String>,
+    memo: Option<String>,
+    #[serde(default)]
+    lines: Vec<OrderLinePayload>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CustomerPayload {
+    list_id: Option<String>,
+    full_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OrderLinePayload {
+    inventory_record_uuid: Uuid,
+    quantity: i32,
+    rate_cents: Option<i64>,
+    desc: Option<String>,
+}
+
+// ── Service ───────────────────────────────────────────────────────────────────
+
+pub struct QbdOrderPushService {
+    db: DatabaseConnection,
+}
+
+impl QbdOrderPushService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    // ── Request phase ─────────────────────────────────────────────────────────
+
+    /// QBXML for the connection's next Pending order push, marking its event InProgress;
+    /// None when there is nothing to push.
+    pub async fn next_request(
+        &self,
+        conn: &connection_identity::Model,
+    ) -> Result<Option<String>, DbErr> {
+        let Some(sync_state_id) = self.sync_state_id(conn).await? else {
+            return Ok(None);
+        };
+
+        let stale = sync_event::Entity::find()
+            .filter(pushes(sync_state_id))
+            .filter(sync_event::Column::Status.eq(SyncEventStatus::InProgress))
+            .all(&self.db)
+            .await?;
+        for event in stale {
+            let message = "No response received from QuickBooks; not resent to avoid a duplicate transaction";
+            self.settle(event, SyncEventStatus::Error, None, Some(json!({ "message": message })))
+                .await?;
+        }
+
+        loop {
+            let Some(event) = sync_event::Entity::find()
+                .filter(pushes(sync_state_id))
+                .filter(sync_event::Column::Status.eq(SyncEventStatus::Pending))
+                .order_by_asc(sync_event::Column::CreatedAt)
+                .order_by_asc(sync_event::Column::Id)
+                .one(&self.db)
+                .await?
+            else {
+                return Ok(None);
+            };
+
+            match self.build_request(conn, &event).await? {
+                Ok((txn_type, xml)) => {
+                    let mut details = event.details.clone().unwrap_or_else(|| json!({}));
+                    if let Some(details) = details.as_object_mut() {
+                        details.insert(TXN_TYPE_KEY.to_string(), json!(txn_type.as_str()));
+                    }
+                    let attempts = event.attempts + 1;
+                    let mut active: sync_event::ActiveModel = event.into();
+                    active.status = Set(SyncEventStatus::InProgress);
+                    active.attempts = Set(attempts);
+                    active.details = Set(Some(details));
+                    active.updated_at = Set(Utc::now().into());
+                    active.update(&self.db).await?;
+                    return Ok(Some(xml));
+                }
+                Err(message) => {
+                    tracing::warn!(event_uuid = %event.uuid, "Order push skipped: {message}");
+                    self.settle(event, SyncEventStatus::Error, None, Some(json!({ "message": message })))
+                        .await?;
+                }
+            }
+        }
+    }
+
+    // ── Response phase ────────────────────────────────────────────────────────
+
+    /// The push awaiting QBD's response, if the last request sent was one.
+    pub async fn in_progress(
+        &self,
+        conn: &connection_identity::Model,
+    ) -> Result<Option<sync_event::Model>, DbErr> {
+        let Some(sync_state_id) = self.sync_state_id(conn).await? else {
+            return Ok(None);
+        };
+        sync_event::Entity::find()
+            .filter(pushes(sync_state_id))
+            .filter(sync_event::Column::Status.eq(SyncEventStatus::InProgress))
+            .one(&self.db)
+            .await
+    }
+
+    /// Settle `event` from QBD's response: Success with the TxnID in `details`, or Error.
+    /// A response that isn't parseable is recorded on the event and returned as an error.
+    pub async fn apply_response(
+        &self,
+        event: sync_event::Model,
+        body: Option<String>,
+        error: Option<String>,
+    ) -> Result<(), QbdPollError> {
+        let body = match (body, error) {
+            (Some(body), None) => body,
+            (_, error) => {
+                let message = error.unwrap_or_else(|| "QuickBooks returned no response".to_string());
+                self.settle(event, SyncEventStatus::Error, None, Some(json!({ "message": message })))
+                    .await?;
+                return Ok(());
+            }
+        };
+
+        let txn_type = event
+            .details
+            .as_ref()
+            .and_then(|d| d.get(TXN_TYPE_KEY))
+            .and_then(|v| v.as_str())
+            .and_then(QbdTxnType::parse)
+            .unwrap_or_default();
+
+        let parsed = match parse_txn_add_response(&body, txn_type) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                let message = format!("XML parse error: {e}");
+                self.settle(event, SyncEventStatus::Error, None, Some(json!({ "message": message })))
+                    .await?;
+                return Err(QbdPollError::XmlParse(message));
+            }
+        };
+
+        let status = qbd_status::classify(&parsed.status_code, &parsed.status_severity);
+        let message = status.describe(&parsed.status_message);
+        match (status.outcome(), parsed.txn_id) {
+            (QbdStatusOutcome::Success | QbdStatusOutcome::Warning, Some(txn_id)) => {
+                let mut details = event.details.clone().unwrap_or_else(|| json!({}));
+                if let Some(details) = details.as_object_mut() {
+                    details.insert(TXN_ID_KEY.to_string(), json!(txn_id));
+                    if let Some(ref_number) = parsed.ref_number {
+                        details.insert("ref_number".to_string(), json!(ref_number));
+                    }
+                }
+                let warnings = (status.outcome() == QbdStatusOutcome::Warning)
+                    .then(|| json!({ "warnings": [message] }));
+                self.settle(event, SyncEventStatus::Success, Some(details), warnings)
+                    .await?;
+            }
+            (QbdStatusOutcome::Error, _) => {
+                let last_error = json!({
+                    "message": message,
+                    "code": status.code,
+                    "retryable": status.retryable,
+                });
+                self.settle(event, SyncEventStatus::Error, None, Some(last_error))
+                    .await?;
+            }
+            _ => {
+                let last_error = json!({ "message": format!("{message}; response has no TxnID") });
+                self.settle(event, SyncEventStatus::Error, None, Some(last_error))
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    // ── Private helpers ───────────────────────────────────────────────────────
+
+    async fn sync_state_id(&self, conn: &connection_identity::Model) -> Result<Option<i64>, DbErr> {
+        erp_connection_sync_state::Entity::find()
+            .select_only()
+            .column(erp_connection_sync_state::Column::Id)
+            .filter(erp_connection_sync_state::Column::ConnectionId.eq(conn.id))
+            .into_tuple()
+            .one(&self.db)
+            .await
+    }
+
+    /// The event's order as QBXML, or why it can't be sent.
+    async fn build_request(
+        &self,
+        conn: &connection_identity::Model,
+        event: &sync_event::Model,
+    ) -> Result<Result<(QbdTxnType, String), String>, DbErr> {
+        let payload = match parse_payload(event.original_record_body.as_ref()) {
+            Ok(payload) => payload,
+            Err(message) => return Ok(Err(message)),
+        };
+
+        let uuids: Vec<Uuid> = payload.lines.iter().map(|l| l.inventory_record_uuid).collect();
+        let list_ids: HashMap<Uuid, String> = inventory_record::Entity::find()
+            .select_only()
+            .column(inventory_record::Column::Uuid)
+            .column(inventory_record::Column::SystemId)
+            .filter(inventory_record::Column::Uuid.is_in(uuids))
+            .filter(inventory_record::Column::SystemIdKey.eq(SystemIdKey::Qbd))
+            .filter(inventory_record::Column::OriginatingConnectionId.eq(conn.id))
+            .into_tuple::<(Uuid, String)>()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .collect();
+
+        Ok(order_from_payload(payload, &list_ids).map(|(txn_type, order)| {
+            let xml = build_txn_add_xml(txn_type, &order, conn.qbxml_version.as_deref());
+            (txn_type, xml)
+        }))
+    }
+
+    async fn settle(
+        &self,
+        event: sync_event::Model,
+        status: SyncEventStatus,
+        details: Option<Value>,
+        last_error: Option<Value>,
+    ) -> Result<(), DbErr> {
+        let errored = status == SyncEventStatus::Error;
+        let mut active: sync_event::ActiveModel = event.into();
+        active.status = Set(status);
+        if let Some(details) = details {
+            active.details = Set(Some(details));
+        }
+        active.last_error = Set(last_error);
+        if errored {
+            active.last_errored_date = Set(Some(Utc::now().into()));
+        }
+        active.updated_at = Set(Utc::now().into());
+        active.update(&self.db).await?;
+        Ok(())
+    }
+}
+
+/// Order pushes queued on a sync state.
+fn pushes(sync_state_id: i64) -> Condition {
+    Condition::all()
+        .add(sync_event::Column::ConnectionSyncStateId.eq(sync_state_id))
+        .add(sync_event::Column::EventDirection.eq(SyncEventDirection::PushToExternal))
+        .add(sync_event::Column::SyncEventMethod.eq(SyncEventMethod::Create))
+        .add(sync_event::Column::SyncEventCategory.eq(SyncEventCategory::Order))
+}
+
+fn parse_payload(body: Option<&Value>) -> Result<OrderPayload, String> {
+    let body = body.ok_or_else(|| "Order push has no original_record_body".to_string())?;
+    serde_json::from_value(body.clone()).map_err(|e| format!("Invalid order payload: {e}"))
+}
+
+/// Map the payload onto a `QbdOrder`, resolving each line's item through `list_ids`
+/// (inventory record uuid → QBD ListID).
+fn order_from_payload(
+    payload: OrderPayload,
+    list_ids: &HashMap<Uuid, String>,
+) -> Result<(QbdTxnType, QbdOrder), String> {
+    let txn_type = match payload.txn_type.as_deref() {
+        None => QbdTxnType::Invoice,
+        Some(value) => QbdTxnType::parse(value)
+            .ok_or_else(|| format!("Unknown txn_type '{value}'; expected invoice or sales_receipt"))?,
+    };
+    if let Some(date) = &payload.txn_date
+        && NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err()
+    {
+        return Err(format!("txn_date '{date}' is not a YYYY-MM-DD date"));
+    }
+
+    let customer = payload.customer.unwrap_or(CustomerPayload { list_id: None, full_name: None });
+    let non_blank = |value: Option<String>| value.filter(|v| !v.trim().is_empty());
+    let (customer_list_id, customer_full_name) =
+        (non_blank(customer.list_id), non_blank(customer.full_name));
+    if txn_type == QbdTxnType::Invoice && customer_list_id.is_none() && customer_full_name.is_none() {
+        return Err("An invoice needs customer.list_id or customer.full_name".to_string());
+    }
+
+    if payload.lines.is_empty() {
+        return Err("Order has no lines".to_string());
+    }
+    let lines = payload
+        .lines
+        .into_iter()
+        .enumerate()
+        .map(|(i, line)| {
+            if line.quantity <= 0 {
+                return Err(format!("lines[{i}]: quantity must be positive"));
+            }
+            let item_list_id = list_ids.get(&line.inventory_record_uuid).ok_or_else(|| {
+                format!(
+                    "lines[{i}]: inventory record {} has no QBD ListID on this connection",
+                    line.inventory_record_uuid
+                )
+            })?;
+            Ok(QbdOrderLine {
+                item_list_id: item_list_id.clone(),
+                desc: line.desc,
+                quantity: line.quantity,
+                rate_cents: line.rate_cents,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+
+    Ok((
+        txn_type,
+        QbdOrder {
+            customer_list_id,
+            customer_full_name,
+            ref_number: payload.ref_number,
+            txn_date: payload.txn_date,
+            memo: payload.memo,
+            lines,
+        },
+    ))
+}
@@ -0,0 +1,277 @@
+//! QBXML request builders and response parsers for pushing orders to QuickBooks Desktop.
+//!
+//! An order is pushed as either an invoice (`InvoiceAddRq`) or a sales receipt
+//! (`SalesReceiptAddRq`). Both requests share the same shape — a customer, header fields
+//! and `*LineAdd` lines referencing items by ListID — so the builder and the parser are
+//! driven by a `QbdTxnType` rather than duplicated per transaction.
+
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+use super::qbxml::request_qbxml_version;
+
+// ── Transaction types ─────────────────────────────────────────────────────────
+
+/// QBD transaction an order is pushed as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QbdTxnType {
+    #[default]
+    Invoice,
+    SalesReceipt,
+}
+
+impl QbdTxnType {
+    /// Value of the order payload's `txn_type`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QbdTxnType::Invoice => "invoice",
+            QbdTxnType::SalesReceipt => "sales_receipt",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "invoice" => Some(QbdTxnType::Invoice),
+            "sales_receipt" => Some(QbdTxnType::SalesReceipt),
+            _ => None,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            QbdTxnType::Invoice => "Invoice",
+            QbdTxnType::SalesReceipt => "SalesReceipt",
+        }
+    }
+}
+
+// ── Order types ───────────────────────────────────────────────────────────────
+
+/// An order ready to be sent: every line's item is already resolved to a QBD ListID.
+#[derive(Debug, Clone, Default)]
+pub struct QbdOrder {
+    /// `CustomerRef`: by ListID when known, otherwise by full name.
+    pub customer_list_id: Option<String>,
+    pub customer_full_name: Option<String>,
+    pub ref_number: Option<String>,
+    /// `YYYY-MM-DD`.
+    pub txn_date: Option<String>,
+    pub memo: Option<String>,
+    pub lines: Vec<QbdOrderLine>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct QbdOrderLine {
+    /// QBD ListID of the item (the inventory record's `system_id`).
+    pub item_list_id: String,
+    pub desc: Option<String>,
+    pub quantity: i32,
+    /// Unit price in integer cents; QBD uses the item's own price when None.
+    pub rate_cents: Option<i64>,
+}
+
+// ── Parsed types ──────────────────────────────────────────────────────────────
+
+pub struct ParsedTxnAddResponse {
+    pub status_code: String,
+    /// `statusSeverity`: `Info`, `Warn` or `Error` (see `qbd_status`).
+    pub status_severity: String,
+    pub status_message: String,
+    /// TxnID of the created transaction; None when QBD refused the request.
+    pub txn_id: Option<String>,
+    /// RefNumber QBD assigned (or kept).
+    pub ref_number: Option<String>,
+}
+
+// ── QBXML builders ────────────────────────────────────────────────────────────
+
+/// Build an `InvoiceAddRq` for `order`.
+pub fn build_invoice_add_xml(order: &QbdOrder, qbxml_version: Option<&str>) -> String {
+    build_txn_add_xml(QbdTxnType::Invoice, order, qbxml_version)
+}
+
+/// Build a `SalesReceiptAddRq` for `order`.
+pub fn build_sales_receipt_add_xml(order: &QbdOrder, qbxml_version: Option<&str>) -> String {
+    build_txn_add_xml(QbdTxnType::SalesReceipt, order, qbxml_version)
+}
+
+/// Build the `<{Txn}AddRq>` for `txn_type`.
+///
+/// Elements follow the order the QBXML schema requires: `CustomerRef`, `TxnDate`,
+/// `RefNumber`, `Memo`, then one `<{Txn}LineAdd>` per line. Text is XML-escaped and rates
+/// are written as decimal amounts (`1999` cents → `19.99`). The `<?qbxml version?>` header
+/// is the connection's `qbxml_version`, or `DEFAULT_QBXML_VERSION` when it has none.
+pub fn build_txn_add_xml(
+    txn_type: QbdTxnType,
+    order: &QbdOrder,
+    qbxml_version: Option<&str>,
+) -> String {
+    let version = request_qbxml_version(qbxml_version);
+    let txn = txn_type.name();
+
+    let mut body = String::new();
+    let customer = match (&order.customer_list_id, &order.customer_full_name) {
+        (Some(list_id), _) => Some(element("ListID", list_id)),
+        (None, Some(full_name)) => Some(element("FullName", full_name)),
+        (None, None) => None,
+    };
+    if let Some(customer) = customer {
+        body.push_str(&format!("        <CustomerRef>{customer}</CustomerRef>\n"));
+    }
+    for (tag, value) in [
+        ("TxnDate", &order.txn_date),
+        ("RefNumber", &order.ref_number),
+        ("Memo", &order.memo),
+    ] {
+        if let Some(value) = value {
+            body.push_str(&format!("        {}\n", element(tag, value)));
+        }
+    }
+    for line in &order.lines {
+        body.push_str(&format!("        <{txn}LineAdd>\n"));
+        body.push_str(&format!(
+            "          <ItemRef>{}</ItemRef>\n",
+            element("ListID", &line.item_list_id)
+        ));
+        if let Some(desc) = &line.desc {
+            body.push_str(&format!("          {}\n", element("Desc", desc)));
+        }
+        body.push_str(&format!("          <Quantity>{}</Quantity>\n", line.quantity));
+        if let Some(rate) = line.rate_cents {
+            body.push_str(&format!("          <Rate>{}</Rate>\n", format_amount(rate)));
+        }
+        body.push_str(&format!("        </{txn}LineAdd>\n"));
+    }
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<?qbxml version="{version}"?>
+<QBXML>
+  <QBXMLMsgsRq onError="stopOnError">
+    <{txn}AddRq requestID="1">
+      <{txn}Add>
+{body}      </{txn}Add>
+    </{txn}AddRq>
+  </QBXMLMsgsRq>
+</QBXML>"#
+    )
+}
+
+/// Format integer cents as a QBD amount (e.g. `-550` → `-5.50`).
+pub fn format_amount(cents: i64) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = cents.unsigned_abs();
+    format!("{sign}{}.{:02}", cents / 100, cents % 100)
+}
+
+fn element(tag: &str, value: &str) -> String {
+    format!("<{tag}>{}</{tag}>", escape(value))
+}
+
+// ── XML parser ────────────────────────────────────────────────────────────────
+
+/// Parse a QBD `InvoiceAddRs` QBXML response.
+pub fn parse_invoice_add_response(xml: &str) -> Result<ParsedTxnAddResponse, String> {
+    parse_txn_add_response(xml, QbdTxnType::Invoice)
+}
+
+/// Parse a QBD `SalesReceiptAddRs` QBXML response.
+pub fn parse_sales_receipt_add_response(xml: &str) -> Result<ParsedTxnAddResponse, String> {
+    parse_txn_add_response(xml, QbdTxnType::SalesReceipt)
+}
+
+/// Parse the `<{Txn}AddRs>` response for `txn_type`.
+///
+/// `TxnID` and `RefNumber` are only read as direct children of the `*Ret` element, so the
+/// ids inside nested refs and lines (e.g. `TxnLineID`, `CustomerRef/ListID`) are ignored.
+/// A response without the `*AddRs` element is an error.
+pub fn parse_txn_add_response(
+    xml: &str,
+    txn_type: QbdTxnType,
+) -> Result<ParsedTxnAddResponse, String> {
+    let rs_tag = format!("{}AddRs", txn_type.name());
+    let ret_tag = format!("{}Ret", txn_type.name());
+
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+
+    let mut found = false;
+    let mut status_code = "0".to_string();
+    let mut status_severity = "Info".to_string();
+    let mut status_message = String::new();
+    let mut txn_id: Option<String> = None;
+    let mut ref_number: Option<String> = None;
+
+    let mut in_ret = false;
+    // Element path below the `*Ret` element.
+    let mut path: Vec<String> = Vec::new();
+
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf) {
+            // A refused request has no `*Ret`, so its `*AddRs` is usually self-closing.
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                if e.name().as_ref() == rs_tag.as_bytes() =>
+            {
+                found = true;
+                for attr in e.attributes().flatten() {
+                    let val = String::from_utf8_lossy(attr.value.as_ref()).to_string();
+                    match attr.key.as_ref() {
+                        b"statusCode" => status_code = val,
+                        b"statusSeverity" => status_severity = val,
+                        b"statusMessage" => status_message = val,
+                        _ => {}
+                    }
+                }
+            }
+
+            Ok(Event::Start(ref e)) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+                if name == ret_tag {
+                    in_ret = true;
+                    path.clear();
+                } else if in_ret {
+                    path.push(name);
+                }
+            }
+
+            Ok(Event::End(ref e)) => {
+                let name = String::from_utf8_lossy(e.name().as_ref()).to_string();
+                if name == ret_tag {
+                    in_ret = false;
+                } else if in_ret {
+                    path.pop();
+                }
+            }
+
+            Ok(Event::Text(ref e)) if in_ret && path.len() == 1 => {
+                if let Ok(text) = e.unescape() {
+                    let text = text.trim().to_string();
+                    match path[0].as_str() {
+                        _ if text.is_empty() => {}
+                        "TxnID" => txn_id = Some(text),
+                        "RefNumber" => ref_number = Some(text),
+                        _ => {}
+                    }
+                }
+            }
+
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("{e}")),
+            _ => {}
+        }
+    }
+
+    if !found {
+        return Err(format!("Response has no {rs_tag} element"));
+    }
+
+    Ok(ParsedTxnAddResponse {
+        status_code,
+        status_severity,
+        status_message,
+        txn_id,
+        ref_number,
+    })
+}
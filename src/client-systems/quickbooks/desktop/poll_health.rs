@@ -0,0 +1,180 @@
+//! Health of the QuickBooks Desktop poll pipeline (`GET /poll/v1/healthz`).
+//!
+//! The server only sees QBD when the Web Connector calls in, so a wedged pipeline shows up as
+//! sync events nobody finishes rather than as errors. For active Desktop connections the
+//! report counts:
+//!   - connections with an InProgress sync event untouched for `stuck_after`
+//!     (`SyncEventService::find_stale_in_progress`): a request phase handed out work and no
+//!     response ever came back
+//!   - connections backing off after failed pulls (`rate_limit_backoff_until` in the future)
+//!   - connections whose `auth_status` is `needs_reauth`
+//!
+//! Only stuck connections affect the verdict: more than `max_stuck` of them is `degraded`.
+//! Backoff and reauth are reported for context; they are per-customer problems, not a
+//! pipeline failure.
+
+use std::collections::{HashMap, HashSet};
+
+use chrono::Duration;
+use entity::sea_orm_active_enums::{ErpConnectionAuthStatus, ErpConnectionStatus, ErpProviderType};
+use entity::{connection_identity, connection_run, erp_connection_sync_state};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait, QueryFilter,
+    QuerySelect,
+};
+
+use crate::clock::{self, SharedClock};
+use crate::sync_event::services::SyncEventService;
+
+/// How long an event may stay InProgress before it counts as stuck. A page is answered
+/// within seconds; this leaves room for a slow QuickBooks company file.
+pub const DEFAULT_STUCK_AFTER_SECS: i64 = 900;
+
+/// Stuck connections tolerated before the pipeline is reported `degraded`.
+pub const DEFAULT_MAX_STUCK_CONNECTIONS: u64 = 0;
+
+// ── Report ────────────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PollHealth {
+    /// Connections with at least one stuck InProgress event.
+    pub stuck_connections: u64,
+    /// Stuck InProgress events across those connections.
+    pub stuck_events: u64,
+    pub backed_off_connections: u64,
+    pub needs_reauth_connections: u64,
+    /// More than `max_stuck` connections are stuck.
+    pub degraded: bool,
+}
+
+// ── Service ───────────────────────────────────────────────────────────────────
+
+pub struct QbdPollHealthService {
+    db: DatabaseConnection,
+    clock: SharedClock,
+    stuck_after: Duration,
+    max_stuck: u64,
+}
+
+impl QbdPollHealthService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            clock: clock::system(),
+            stuck_after: Duration::seconds(DEFAULT_STUCK_AFTER_SECS),
+            max_stuck: DEFAULT_MAX_STUCK_CONNECTIONS,
+        }
+    }
+
+    /// Uses `clock` to decide which events are stale and which backoffs are still running.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// How long an InProgress event may go untouched before it counts as stuck.
+    pub fn with_stuck_after(mut self, stuck_after: Duration) -> Self {
+        self.stuck_after = stuck_after;
+        self
+    }
+
+    /// Stuck connections tolerated before the report is `degraded`.
+    pub fn with_max_stuck(mut self, max_stuck: u64) -> Self {
+        self.max_stuck = max_stuck;
+        self
+    }
+
+    pub fn stuck_after(&self) -> Duration {
+        self.stuck_after
+    }
+
+    pub fn max_stuck(&self) -> u64 {
+        self.max_stuck
+    }
+
+    /// Active, enabled Desktop connections; the ones the Web Connector polls.
+    fn polled_connections() -> Condition {
+        Condition::all()
+            .add(connection_identity::Column::ErpType.eq(ErpProviderType::Desktop))
+            .add(connection_identity::Column::Status.eq(ErpConnectionStatus::Active))
+            .add(connection_identity::Column::IsEnabled.eq(true))
+    }
+
+    pub async fn check(&self) -> Result<PollHealth, DbErr> {
+        let now = self.clock.now();
+
+        let stale = SyncEventService::new(self.db.clone())
+            .find_stale_in_progress((now - self.stuck_after).into(), None)
+            .await?;
+
+        //events point at their connection through the sync state, or through the run when
+        //they have no sync state
+        let state_ids: HashSet<i64> = stale.iter().filter_map(|e| e.connection_sync_state_id).collect();
+        let run_ids: HashSet<i64> = stale
+            .iter()
+            .filter(|e| e.connection_sync_state_id.is_none())
+            .filter_map(|e| e.connection_run_id)
+            .collect();
+        let state_connections: HashMap<i64, i64> = erp_connection_sync_state::Entity::find()
+            .select_only()
+            .column(erp_connection_sync_state::Column::Id)
+            .column(erp_connection_sync_state::Column::ConnectionId)
+            .filter(erp_connection_sync_state::Column::Id.is_in(state_ids))
+            .into_tuple::<(i64, i64)>()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .collect();
+        let run_connections: HashMap<i64, i64> = connection_run::Entity::find()
+            .select_only()
+            .column(connection_run::Column::Id)
+            .column(connection_run::Column::ConnectionId)
+            .filter(connection_run::Column::Id.is_in(run_ids))
+            .into_tuple::<(i64, i64)>()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .collect();
+        let event_connections: Vec<i64> = stale
+            .iter()
+            .filter_map(|e| match e.connection_sync_state_id {
+                Some(state_id) => state_connections.get(&state_id).copied(),
+                None => e.connection_run_id.and_then(|run_id| run_connections.get(&run_id).copied()),
+            })
+            .collect();
+
+        let polled: HashSet<i64> = connection_identity::Entity::find()
+            .select_only()
+            .column(connection_identity::Column::Id)
+            .filter(Self::polled_connections())
+            .filter(connection_identity::Column::Id.is_in(event_connections.iter().copied()))
+            .into_tuple::<i64>()
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .collect();
+        let stuck_events = event_connections.iter().filter(|id| polled.contains(id)).count() as u64;
+
+        let backed_off_connections = connection_identity::Entity::find()
+            .inner_join(erp_connection_sync_state::Entity)
+            .filter(Self::polled_connections())
+            .filter(erp_connection_sync_state::Column::RateLimitBackoffUntil.gt(now))
+            .count(&self.db)
+            .await?;
+
+        let needs_reauth_connections = connection_identity::Entity::find()
+            .filter(Self::polled_connections())
+            .filter(connection_identity::Column::AuthStatus.eq(ErpConnectionAuthStatus::NeedsReauth))
+            .count(&self.db)
+            .await?;
+
+        let stuck_connections = polled.len() as u64;
+        Ok(PollHealth {
+            stuck_connections,
+            stuck_events,
+            backed_off_connections,
+            needs_reauth_connections,
+            degraded: stuck_connections > self.max_stuck,
+        })
+    }
+}
@@ -1,64 +1,138 @@
 //! QuickBooks Desktop Web Connector poll service.
 //!
-//! Two-phase protocol:
+//! `QbdPollService` is the QBD `PullAdapter`: it validates Web Connector credentials and
+//! speaks QBXML, while `SyncOrchestrator` owns the sync event / connection run lifecycle,
+//! the sync lock and backoff (see `crate::sync::orchestrator`).
 //!
 //! **Request phase** (`handle_request`):
-//!   1. Validate credentials → 403 if invalid
-//!   2. Ensure an `erp_connection_sync_state` row exists for the connection
-//!   3. Look up the single recurring List/Inventory sync event for this connection
-//!      - If none exists → create ConnectionRun + SyncEvent (status = InProgress)
-//!      - If Pending or Error → create a fresh ConnectionRun for *this* poll cycle,
-//!        update the event to InProgress, increment attempts
-//!   4. Build an `ItemInventoryQueryRq` using the cursor stored in `sync_state`
-//!      (iterator="Continue" + iteratorID) or a fresh Start if no cursor
-//!   5. Return the QBXML string plus UUIDs the caller must echo back in the response phase
+//!   1. Validate credentials → 403 if invalid, or if the poll was authorized with another
+//!      connection's API key (`with_connection_scope`). A connection whose `environment` the
+//!      server doesn't serve (`with_required_environment`, e.g. a sandbox connection on a
+//!      production-only server) gets `has_work: false` and nothing else happens. Otherwise
+//!      store the qbXML version QBD reported (capped at the version the queries are written
+//!      for) as the connection's `qbxml_version`
+//!   2. A Pending order push goes first (`order_push`): its `InvoiceAddRq` /
+//!      `SalesReceiptAddRq` is sent instead of a query, and the steps below wait for the
+//!      next request
+//!   3. Orchestrator picks the recurring List/Inventory sync event and starts a run
+//!      (no work outside the connection's sync window, `crate::sync::window`)
+//!      - `details.item_type` selects the query: `inventory` (default), `service`
+//!        or `non_inventory`
+//!      - If a cursor is stored, the event for the cursor's item type goes first so
+//!        pagination finishes before another item list starts
+//!   4. Build the item query (`ItemInventoryQueryRq` / `ItemServiceQueryRq` /
+//!      `ItemNonInventoryQueryRq`) using the cursor stored in `sync_state`
+//!      (iterator="Continue" + iteratorID) or a fresh Start if no cursor. The cursor is
+//!      read as a `SyncCursor::QbdIterator` (`crate::sync::cursor`); one that doesn't
+//!      deserialize is logged and also gets a fresh Start. The query carries the
+//!      connection's `qbxml_version` in the `<?qbxml version?>` header (13.0 when unset)
 //!
 //! **Response phase** (`handle_response`):
-//!   1. Validate credentials
-//!   2. If QBD returned an error → mark event Error + run Error, return
-//!   3. Parse the XML response (ItemInventoryQueryRs)
-//!   4. Upsert each ItemInventoryRet into `inventory_record` / `inventory_record_event`
-//!      - Match on `system_id_key=Qbd` + `system_id={ListID}` + `connection_id`
-//!      - Create record+event if new; update latest event if existing
-//!   5. Update the cursor in `sync_state` (None if pagination complete)
-//!   6. Mark sync event:
-//!      - List events → **Pending** (never Completed; will be re-run)
-//!      - Other methods → **Success** (or Error on failure)
-//!   7. Update ConnectionRun to Error on failure (stays Success otherwise)
-
-use std::collections::HashMap;
+//!   1. Validate credentials; a connection in another environment gets `has_more: false`
+//!      without anything being applied. If an order push is InProgress, the response is its `*AddRs`:
+//!      the push event is settled with the TxnID (`order_push`) and the steps below are
+//!      skipped
+//!   2. Classify QBD's status code (`qbd_status`):
+//!      - `1` (no matching records) → an empty page; pagination is complete
+//!      - success with no items and nothing remaining (a company file without items) → the
+//!        same: the cursor is cleared, the event goes back to Pending and the initial sync
+//!        counts as complete, with nothing upserted
+//!      - a warning (e.g. `500`) → the page is applied and the warning is kept on the
+//!        event's `last_error.warnings`
+//!      - an error → orchestrator marks event Error + run Error, storing the code, message
+//!        and whether it is retryable in `last_error`. An expired/invalid iterator is
+//!        recoverable instead: the cursor is cleared and the event goes back to Pending, so
+//!        the next cycle sends `iterator="Start"`
+//!   3. Parse the XML response for the event's item type (e.g. ItemInventoryQueryRs), then
+//!      read each item's `system_id`, name and `external_code` from the QBD fields the
+//!      connection's `field_mapping` names (`field_mapping`; ListID, Name and FullName by default)
+//!   4. Validate each item's body against the event category's payload schema
+//!      (`crate::validation::payload`); invalid items are skipped and reported in the
+//!      event's `last_error.rejected`
+//!   5. Upsert each remaining item into `inventory_record` / `inventory_record_event`
+//!      - Match on `system_id_key=Qbd` + `system_id={ListID}` + `connection_id`; existing
+//!        records and their latest events are looked up for the whole page at once
+//!      - Items are written one after another in the page transaction, each under its own
+//!        savepoint; per-item failures are collected into the event's `last_error.errors`,
+//!        and once the item's savepoint is rolled back the failed item is written to
+//!        `quarantine` (`crate::quarantine`) in the page transaction so it can be replayed
+//!        (`replay_quarantined`)
+//!      - The item type is recorded in `original_record_body` and event `attributes`
+//!        (JSON, with `manufacturer_part_number` when present)
+//!      - Create record+event if new. For an existing record, compare the item against
+//!        its latest event (name, description, full name, price, qty, attributes): any
+//!        difference inserts a NEW event and the old one stays as history; an unchanged
+//!        item writes no event at all
+//!      - Report each written item's `inventory_record_event` id; the orchestrator records
+//!        a Create/Update sync event per item linked to it (`inventory_record_event_id`)
+//!      - Enqueue an `inventory.upserted` outbox message in the same transaction (only
+//!        for items that wrote an event)
+//!      - A deadlock or serialization failure fails the page instead of the item; the
+//!        orchestrator reruns the page transaction (`with_retry_policy`)
+//!   6. Return the next cursor (None if pagination complete); the orchestrator stores it
+//!      and hands the event back to Pending
+//!
+//! **Dry run** (`PollResponseInput::dry_run`): steps 1 and 3 only. The response is parsed
+//! for the InProgress event's item type and summarized (`DryRunSummary`: item count,
+//! would-be creates/updates/rejections, a sample item, the cursor decision) without any
+//! writes.
+//!
+//! **Reprocess** (`reprocess`): re-derives a connection's records from their stored
+//! `original_record_body` with the current field mapping and appends events where the result
+//! differs (step 5 without QBD), to correct data after a parser fix.
+//!
+//! **Replay** (`replay_quarantined`): applies one quarantined item again from its stored
+//! body (step 5 for that item); the quarantine row is removed once it applies.
+//!
+//! **Errors**: both phases fail with `QbdPollError`; `QbdPollError::to_response` maps it to
+//! the status code and `{success: false, message, code}` envelope both endpoints return.
+//!
+//! Each phase runs in a span (`qbd.poll.request` / `qbd.poll.response`) carrying
+//! `connection_id` and the `event_uuid` the orchestrator picked, exported over OTLP when
+//! tracing export is enabled.
 
+use axum::{http::StatusCode, Json};
 use entity::sea_orm_active_enums::{
-    ConnectionRunStatus, ConnectionRunType, ErpProvider, ErpProviderType,
-    SyncEventCategory, SyncEventDirection, SyncEventMethod, SyncEventStatus, SystemIdKey,
+    ErpEnvironment, ErpProvider, ErpProviderType, SyncEventCategory, SyncEventMethod, SyncEventStatus,
+    SystemIdKey,
 };
 use entity::{
-    connection_identity, connection_run, erp_connection_credentials, erp_connection_sync_state,
-    inventory_record, inventory_record_event, sync_event,
+    connection_identity, erp_connection_credentials, erp_connection_sync_state, inventory_record,
+    inventory_record_event, quarantine, sync_event,
 };
-use quick_xml::events::Event;
-use quick_xml::Reader;
+use sea_orm::sea_query::{Expr, Func, Query};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
-    EntityTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+    ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, TransactionTrait,
 };
+use chrono::{DateTime, Utc};
+use serde::Serialize;
 use serde_json::{json, Value};
-use uuid::Uuid;
-
-use crate::connection_run::services::{
-    ConnectionRunService, CreateConnectionRun, UpdateConnectionRun,
-};
-use crate::erp_connection_sync_state::services::{
-    CreateErpConnectionSyncState, ErpConnectionSyncStateService,
+use std::collections::HashMap;
+use tracing::Span;
+use utoipa::ToSchema;
+
+use crate::erp_connection_credentials::codec::CredentialCodec;
+use crate::erp_connection_credentials::lookup_cache::{CachedCredential, CredentialLookupCache};
+use crate::erp_connection_credentials::secret_store::{SecretField, SecretResolver};
+use crate::events::bus::{ConnectionReauthNeeded, InventoryUpserted, SyncEventFailed};
+use crate::events::{DomainEvent, EventBus};
+use crate::inventory_records::upsert::{self, ExistingRecord, InventoryItem, UpsertOutcome};
+use crate::outbox::services::{OutboxPayload, OutboxService, TOPIC_INVENTORY_UPSERTED};
+use crate::quarantine::services::{QuarantineItem, QuarantineService};
+use crate::sync::{
+    AppliedRecord, PollDecision, ProviderStatus, PullAdapter, PullError, PullOutcome, PullRequest,
+    PullResponse, RejectedRecord, RetryPolicy, SyncCursor, SyncOrchestrator, Transient,
 };
-use crate::inventory_records::events_services::{
-    CreateInventoryRecordEvent, InventoryRecordEventService, UpdateInventoryRecordEvent,
+use crate::validation::payload::validate_record_body;
+
+use super::field_mapping::QbdFieldMapping;
+use super::order_push::QbdOrderPushService;
+use super::qbd_status::{self, QbdStatusOutcome};
+use super::qbxml::{
+    build_item_query_xml, item_from_raw, negotiate_qbxml_version, parse_item_query_response,
+    ParsedInventoryResponse, QbdInventoryItem, QbdItemType, ITEM_TYPE_KEY,
 };
-use crate::inventory_records::services::{CreateInventoryRecord, InventoryRecordService};
-use crate::sync_event::services::{CreateSyncEvent, SyncEventService, UpdateSyncEvent};
-
-/// Items returned per QBXML page.
-const PAGE_SIZE: u32 = 50;
 
 // ── Errors ────────────────────────────────────────────────────────────────────
 
@@ -69,17 +143,80 @@ pub enum QbdPollError {
     XmlParse(String),
 }
 
+/// JSON error envelope returned by both poll endpoints (`/poll/v1/qbwc` and
+/// `/poll/v1/qbwc/receive`), so adapters handle one shape for every failure.
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct QbdPollErrorResponse {
+    /// Always false.
+    pub success: bool,
+    pub message: String,
+    /// Machine-readable error code: `unauthorized`, `database_error` or `invalid_response`.
+    pub code: String,
+}
+
+impl QbdPollError {
+    /// HTTP status for this error.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            QbdPollError::Unauthorized => StatusCode::FORBIDDEN,
+            QbdPollError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            QbdPollError::XmlParse(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+
+    /// Stable error code for the `code` field.
+    pub fn code(&self) -> &'static str {
+        match self {
+            QbdPollError::Unauthorized => "unauthorized",
+            QbdPollError::Db(_) => "database_error",
+            QbdPollError::XmlParse(_) => "invalid_response",
+        }
+    }
+
+    /// User-facing error message.
+    pub fn message(&self) -> String {
+        match self {
+            QbdPollError::Unauthorized => "Invalid Web Connector credentials".to_string(),
+            QbdPollError::Db(e) => format!("Database error: {e}"),
+            QbdPollError::XmlParse(e) => e.clone(),
+        }
+    }
+
+    /// The status and JSON envelope either poll endpoint answers with.
+    pub fn to_response(&self) -> (StatusCode, Json<QbdPollErrorResponse>) {
+        (
+            self.status_code(),
+            Json(QbdPollErrorResponse {
+                success: false,
+                message: self.message(),
+                code: self.code().to_string(),
+            }),
+        )
+    }
+}
+
 impl From<DbErr> for QbdPollError {
     fn from(e: DbErr) -> Self {
         QbdPollError::Db(e)
     }
 }
 
+impl From<PullError> for QbdPollError {
+    fn from(e: PullError) -> Self {
+        match e {
+            PullError::Provider(msg) | PullError::CursorExpired(msg) => QbdPollError::XmlParse(msg),
+            PullError::Status(status) => QbdPollError::XmlParse(status.message),
+            PullError::Db(e) => QbdPollError::Db(e),
+        }
+    }
+}
+
 // ── Public I/O types ──────────────────────────────────────────────────────────
 
 /// Output of `handle_request` (maps to sendRequestXML).
 pub struct PollRequestOutput {
-    /// Whether there is an inventory sync to perform.
+    /// Whether there is an order push or inventory sync to perform.
     pub has_work: bool,
     /// QBXML to send to QuickBooks Desktop (None when has_work is false).
     pub xml: Option<String>,
@@ -91,163 +228,218 @@ pub struct PollResponseInput {
     pub qbd_response_xml: Option<String>,
     /// Human-readable error returned by QBD (when QBD returned an error instead of XML).
     pub qbd_error: Option<String>,
+    /// Parse and summarize the response without writing anything.
+    pub dry_run: bool,
 }
 
 /// Output of `handle_response`.
 pub struct PollResponseOutput {
-    /// True when there are more pages to fetch (cursor not exhausted).
+    /// True when there are more pages to fetch (cursor not exhausted), and after an order
+    /// push so the pull still runs.
     /// Maps to QBWC's receiveResponseXML return value: 100 = keep going, 0 = done.
+    /// Always false for a dry run, so QBWC does not loop on a page that was never applied.
+    pub has_more: bool,
+    /// What the response would have done; only set for a dry run.
+    pub dry_run: Option<DryRunSummary>,
+}
+
+/// Result of a dry-run `handle_response`: what applying the page would do.
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunSummary {
+    pub item_type: &'static str,
+    pub status_code: String,
+    pub status_message: String,
+    /// Items parsed from the page.
+    pub item_count: usize,
+    /// Items with no `inventory_record` for this connection yet.
+    pub would_create: usize,
+    /// Items matching an existing `inventory_record`.
+    pub would_update: usize,
+    /// Items failing payload validation; not counted as creates or updates.
+    pub would_reject: usize,
+    /// First parsed item, as it would be stored.
+    pub sample: Option<Value>,
+    /// Cursor that would be stored; None when pagination would complete.
+    pub cursor: Option<Value>,
     pub has_more: bool,
 }
 
-// ── Internal parsed types ─────────────────────────────────────────────────────
+/// Result of `reprocess`: what re-deriving the stored raw bodies changed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReprocessSummary {
+    /// Records whose `original_record_body` was re-parsed.
+    pub scanned: usize,
+    /// Records whose derived fields differed; each got a new `inventory_record_event`.
+    pub updated: usize,
+    /// Records whose latest event already matched.
+    pub unchanged: usize,
+    /// Records without a usable raw body (missing, or no ListID).
+    pub skipped: usize,
+}
 
-struct ParsedInventoryResponse {
-    iterator_id: Option<String>,
-    /// Items remaining after this page; 0 means pagination is complete.
-    remaining_count: i64,
-    status_code: String,
-    status_message: String,
-    items: Vec<QbdInventoryItem>,
+/// Result of `replay_quarantined`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReplayOutcome {
+    /// The item was upserted and its quarantine row removed.
+    Applied,
+    /// The item failed again; the row was kept with this error.
+    Failed(String),
 }
 
-struct QbdInventoryItem {
-    /// QBD ListID — used as the `system_id`.
-    list_id: String,
-    name: Option<String>,
-    full_name: Option<String>,
-    /// Sales price converted to integer cents.
-    sales_price_cents: Option<i32>,
-    qty_on_hand: Option<i32>,
-    sales_desc: Option<String>,
-    /// All parsed fields as a JSON blob stored in `original_record_body`.
-    raw: Value,
+/// Result of `sync_progress`: where the connection's inventory pull stands.
+#[derive(Debug, Clone, Default)]
+pub struct SyncProgress {
+    /// QBD iterator of the pass being paginated; None between passes.
+    pub iterator_id: Option<String>,
+    /// Items QBD reported as still to come in the current pass.
+    pub remaining_count: Option<i64>,
+    /// Item list being pulled (`inventory`, `service`, `non_inventory`).
+    pub item_type: Option<&'static str>,
+    /// The recurring List/Inventory event the pass belongs to.
+    pub event_uuid: Option<uuid::Uuid>,
+    pub event_status: Option<SyncEventStatus>,
+    /// `inventory_record` rows pulled from this connection so far.
+    pub records_synced: u64,
+    /// True while a cursor is stored or the event is InProgress.
+    pub in_progress: bool,
+    /// `records_synced / (records_synced + remaining_count)`, e.g. `"62.5%"`; only while a
+    /// cursor is stored, since the total is unknown otherwise.
+    pub percent_complete: Option<String>,
 }
 
 // ── Service ───────────────────────────────────────────────────────────────────
 
 pub struct QbdPollService {
     db: DatabaseConnection,
+    retry: RetryPolicy,
+    required_environment: Option<ErpEnvironment>,
+    secrets: SecretResolver,
+    credential_cache: Option<CredentialLookupCache>,
+    events: Option<EventBus>,
+    connection_scope: Option<i64>,
 }
 
 impl QbdPollService {
     pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+        Self {
+            db,
+            retry: RetryPolicy::default(),
+            required_environment: None,
+            secrets: SecretResolver::default(),
+            credential_cache: None,
+            events: None,
+            connection_scope: None,
+        }
+    }
+
+    /// How often a page is retried after a deadlock or serialization failure.
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Only poll connections of this environment (None: any). Keeps a sandbox company file
+    /// from being pulled by a production server and the other way round.
+    pub fn with_required_environment(mut self, environment: Option<ErpEnvironment>) -> Self {
+        self.required_environment = environment;
+        self
+    }
+
+    /// Decodes the stored Web Connector password before it is compared. The password is
+    /// stored under `enc_scheme = "none"`, which the default codec reads as is.
+    pub fn with_codec(mut self, codec: CredentialCodec) -> Self {
+        self.secrets = self.secrets.with_codec(codec);
+        self
+    }
+
+    /// Where the Web Connector password is read from: the connection's
+    /// `secret_storage_ref` store when it has one, otherwise the credentials row (through
+    /// the resolver's codec, which replaces `with_codec`'s).
+    pub fn with_secret_resolver(mut self, secrets: SecretResolver) -> Self {
+        self.secrets = secrets;
+        self
+    }
+
+    /// Remembers successful credential checks by username, so later calls skip the
+    /// credentials query and the secret store (None: check every call against the database).
+    pub fn with_credential_cache(mut self, cache: Option<CredentialLookupCache>) -> Self {
+        self.credential_cache = cache;
+        self
+    }
+
+    /// Publishes what each applied pull page did (`apply_pull`) on `events` (None: publish
+    /// nothing).
+    pub fn with_event_bus(mut self, events: Option<EventBus>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Only serve the connection with this id (None: whichever the credentials belong to).
+    /// Set from the connection API key a poll was authorized with, so a key can't drive
+    /// another connection's poll even with that connection's Web Connector credentials.
+    pub fn with_connection_scope(mut self, connection_id: Option<i64>) -> Self {
+        self.connection_scope = connection_id;
+        self
     }
 
     // ── Request phase ─────────────────────────────────────────────────────────
 
-    /// Find (or create) the recurring List/Inventory sync event and return the
-    /// QBXML query to execute against QuickBooks Desktop.
+    /// Return the next QBXML to execute against QuickBooks Desktop: a pending order push,
+    /// otherwise the query that starts (or continues) the recurring List/Inventory pull.
+    /// A disabled connection, or one outside the server's environment, gets no work. Each
+    /// call logs its `PollDecision` once.
+    ///
+    /// `qbxml_version` is the version QBD reported to the Web Connector for this session;
+    /// once the credentials check out, the negotiated version is stored on the connection
+    /// and used for the query's `<?qbxml version?>` header.
+    #[tracing::instrument(name = "qbd.poll.request", skip_all, fields(connection_id, event_uuid))]
     pub async fn handle_request(
         &self,
         username: &str,
         password: &str,
+        qbxml_version: Option<&str>,
     ) -> Result<PollRequestOutput, QbdPollError> {
-        let (conn, _creds) = self.validate_credentials(username, password).await?;
-        let sync_state = self.ensure_sync_state(conn.id).await?;
-
-        // Build the cursor XML now (before we mutate the event).
-        let cursor = sync_state.sync_cursor.clone();
-        let xml = build_item_inventory_query_xml(cursor.as_ref());
-
-        let run_svc = ConnectionRunService::new(self.db.clone());
-        let sync_event_svc = SyncEventService::new(self.db.clone());
-
-        let txn = self.db.begin().await?;
-        // Find the ONE recurring List/Inventory event for this connection that
-        // is ready to be processed (Pending or Error).
-        let maybe_event = sync_event::Entity::find()
-            .filter(
-                Condition::any()
-                    .add(sync_event::Column::Status.eq(SyncEventStatus::Pending))
-                    .add(sync_event::Column::Status.eq(SyncEventStatus::Error)),
-            )
-            .filter(sync_event::Column::ConnectionSyncStateId.eq(sync_state.id))
-            .filter(sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
-            .filter(sync_event::Column::SyncEventCategory.eq(SyncEventCategory::Inventory))
-            .one(&txn)
-            .await?;
-
-        match maybe_event {
-            None => {
-                // First ever poll — create a fresh event and run.
-                let run = run_svc
-                    .create(
-                        CreateConnectionRun {
-                            connection_id: conn.id,
-                            status: Some(ConnectionRunStatus::Success),
-                            run_type: Some(ConnectionRunType::Poll),
-                            error_message: None,
-                        },
-                        Some(&txn),
-                    )
-                    .await?;
-
-                let _event = sync_event_svc
-                    .create(
-                        CreateSyncEvent {
-                            original_record_body: None,
-                            details: None,
-                            event_direction: SyncEventDirection::PullFromExternal,
-                            inventory_record_event_id: None,
-                            sync_event_method: SyncEventMethod::List,
-                            sync_event_category: SyncEventCategory::Inventory,
-                            attempts: Some(1),
-                            status: Some(SyncEventStatus::InProgress),
-                            last_error: None,
-                            last_errored_date: None,
-                            connection_sync_state_id: Some(sync_state.id),
-                            connection_run_id: Some(run.id),
-                        },
-                        Some(&txn),
-                    )
-                    .await?;
-            }
+        let mut conn = self.validate_credentials(username, password).await?;
+        Span::current().record("connection_id", conn.id);
+        if !conn.is_enabled || !self.environment_allowed(&conn) {
+            let decision = match conn.is_enabled {
+                true => PollDecision::WrongEnvironment,
+                false => PollDecision::Disabled,
+            };
+            decision.log(conn.id);
+            return Ok(PollRequestOutput {
+                has_work: false,
+                xml: None,
+            });
+        }
 
-            Some(event) => {
-                // Existing event — create a NEW ConnectionRun per poll per event.
-                let run = run_svc
-                    .create(
-                        CreateConnectionRun {
-                            connection_id: conn.id,
-                            status: Some(ConnectionRunStatus::Success),
-                            run_type: Some(ConnectionRunType::Poll),
-                            error_message: None,
-                        },
-                        Some(&txn),
-                    )
-                    .await?;
+        let negotiated = qbxml_version.and_then(negotiate_qbxml_version);
+        if let Some(version) = negotiated
+            && conn.qbxml_version.as_deref() != Some(version.as_str())
+        {
+            connection_identity::Entity::update_many()
+                .col_expr(connection_identity::Column::QbxmlVersion, Expr::value(version.clone()))
+                .filter(connection_identity::Column::Id.eq(conn.id))
+                .exec(&self.db)
+                .await?;
+            conn.qbxml_version = Some(version);
+        }
 
-                // Mark InProgress and link to the new run.
-                let _ = sync_event_svc
-                    .update_by_uuid(
-                        event.uuid,
-                        UpdateSyncEvent {
-                            status: Some(SyncEventStatus::InProgress),
-                            attempts: Some(event.attempts + 1),
-                            connection_run_id: Some(run.id),
-                            original_record_body: None,
-                            details: None,
-                            event_direction: None,
-                            inventory_record_event_id: None,
-                            sync_event_method: None,
-                            sync_event_category: None,
-                            last_error: None,
-                            last_errored_date: None,
-                            connection_sync_state_id: None,
-                        },
-                        Some(&txn),
-                    )
-                    .await;
-            }
+        if let Some(xml) = QbdOrderPushService::new(self.db.clone()).next_request(&conn).await? {
+            PollDecision::OrderPush.log(conn.id);
+            return Ok(PollRequestOutput {
+                has_work: true,
+                xml: Some(xml),
+            });
         }
 
-        txn.commit().await?;
+        let request = SyncOrchestrator::new(self.db.clone(), self)
+            .next_request(&conn)
+            .await?;
 
         Ok(PollRequestOutput {
-            has_work: true,
-            xml: Some(xml),
+            has_work: request.is_some(),
+            xml: request.map(|r| r.payload),
         })
     }
 
@@ -255,595 +447,837 @@ impl QbdPollService {
 
     /// Process the XML response returned by QuickBooks Desktop (receiveResponseXML).
     ///
-    /// Finds the current InProgress sync event for this connection — no UUID echoing
-    /// required, the server tracks all state. Returns `has_more` so the adapter can
+    /// Applies it to the current InProgress sync event for this connection (the order push,
+    /// if one was sent) — no UUID echoing required, the server tracks all state. Returns `has_more` so the adapter can
     /// signal QBWC to call sendRequestXML again (100) or stop (0).
+    #[tracing::instrument(name = "qbd.poll.response", skip_all, fields(connection_id, event_uuid))]
     pub async fn handle_response(
         &self,
         username: &str,
         password: &str,
         input: PollResponseInput,
     ) -> Result<PollResponseOutput, QbdPollError> {
-        let (conn, _creds) = self.validate_credentials(username, password).await?;
-        let sync_state = self.ensure_sync_state(conn.id).await?;
-
-        let sync_event_svc = SyncEventService::new(self.db.clone());
-        let run_svc = ConnectionRunService::new(self.db.clone());
-
-        // Find the InProgress List/Inventory event for this connection.
-        // There should be at most one at a time since handle_request marks it
-        // InProgress before returning the QBXML to the adapter.
-        let event = sync_event::Entity::find()
-            .filter(sync_event::Column::ConnectionSyncStateId.eq(sync_state.id))
-            .filter(sync_event::Column::Status.eq(SyncEventStatus::InProgress))
-            .filter(sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
-            .filter(sync_event::Column::SyncEventCategory.eq(SyncEventCategory::Inventory))
-            .one(&self.db)
+        let conn = self.validate_credentials(username, password).await?;
+        Span::current().record("connection_id", conn.id);
+        if !self.environment_allowed(&conn) {
+            return Ok(PollResponseOutput {
+                has_more: false,
+                dry_run: None,
+            });
+        }
+
+        if input.dry_run {
+            let summary = self.dry_run_response(&conn, input.qbd_response_xml).await?;
+            return Ok(PollResponseOutput {
+                has_more: false,
+                dry_run: Some(summary),
+            });
+        }
+
+        let pushes = QbdOrderPushService::new(self.db.clone());
+        if let Some(event) = pushes.in_progress(&conn).await? {
+            Span::current().record("event_uuid", tracing::field::display(event.uuid));
+            pushes
+                .apply_response(event, input.qbd_response_xml, input.qbd_error)
+                .await?;
+            // The pull (or another push) still has to run.
+            return Ok(PollResponseOutput {
+                has_more: true,
+                dry_run: None,
+            });
+        }
+
+        let outcome = self
+            .apply_pull(
+                &conn,
+                PullResponse {
+                    body: input.qbd_response_xml,
+                    error: input.qbd_error,
+                },
+            )
             .await?;
 
-        // Load the ConnectionRun created when we sent the request.
-        let run = if let Some(ref ev) = event {
-            if let Some(run_id) = ev.connection_run_id {
-                connection_run::Entity::find_by_id(run_id)
-                    .one(&self.db)
-                    .await?
-            } else {
-                None
-            }
-        } else {
-            None
-        };
+        Ok(PollResponseOutput {
+            has_more: outcome.has_more(),
+            dry_run: None,
+        })
+    }
 
-        // ── QBD returned an error ─────────────────────────────────────────────
-        if let Some(ref err_msg) = input.qbd_error {
-            let err_body = json!({ "message": err_msg });
-            let txn = self.db.begin().await?;
-            if let Some(ref ev) = event {
-                let _ = sync_event_svc
-                    .update_by_uuid(
-                        ev.uuid,
-                        UpdateSyncEvent {
-                            status: Some(SyncEventStatus::Error),
-                            last_error: Some(err_body.clone()),
-                            last_errored_date: Some(chrono::Utc::now()),
-                            attempts: None,
-                            original_record_body: None,
-                            details: None,
-                            event_direction: None,
-                            inventory_record_event_id: None,
-                            sync_event_method: None,
-                            sync_event_category: None,
-                            connection_sync_state_id: None,
-                            connection_run_id: None,
-                        },
-                        Some(&txn),
-                    )
-                    .await;
+    /// Apply QBD's answer to the in-flight inventory pull (`SyncOrchestrator::apply_response`),
+    /// then publish what it did on the event bus: `InventoryUpserted` per written record,
+    /// `SyncEventFailed` for a failed page or a QBD error reply, and `ConnectionReauthNeeded`
+    /// when QBD refused the Web Connector user's access. Events are only published once the
+    /// page's transaction has settled.
+    pub async fn apply_pull(
+        &self,
+        conn: &connection_identity::Model,
+        response: PullResponse,
+    ) -> Result<PullOutcome, QbdPollError> {
+        let qbd_error = response.error.clone();
+        let result = SyncOrchestrator::new(self.db.clone(), self)
+            .with_retry(self.retry)
+            .apply_response(conn, response)
+            .await;
+
+        if let Some(bus) = &self.events {
+            for event in pull_events(conn, qbd_error, &result) {
+                bus.publish(event);
             }
-            if let Some(ref r) = run {
-                let _ = run_svc
-                    .update_by_uuid(
-                        r.uuid,
-                        UpdateConnectionRun {
-                            status: Some(ConnectionRunStatus::Error),
-                            error_message: Some(err_msg.clone()),
-                        },
-                        Some(&txn),
-                    )
-                    .await;
+        }
+        Ok(result?)
+    }
+
+    // ── Reprocess ─────────────────────────────────────────────────────────────
+
+    /// Re-derive the connection's inventory records from their stored `original_record_body`
+    /// with the current field mapping (`qbxml::item_from_raw`, then the connection's
+    /// `field_mapping`), without contacting QBD.
+    ///
+    /// Used to correct data after a parser fix. Records changed at or after `since` (all when
+    /// None) are re-parsed, except merged duplicates; one whose derived fields differ from its
+    /// latest event gets a new event and an `inventory.upserted` outbox message, exactly like
+    /// a pull. The raw body itself is left as stored. Runs in one transaction.
+    pub async fn reprocess(
+        &self,
+        conn: &connection_identity::Model,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<ReprocessSummary, QbdPollError> {
+        let txn = self.db.begin().await?;
+
+        let mut query = inventory_record::Entity::find()
+            .filter(inventory_record::Column::SystemIdKey.eq(SystemIdKey::Qbd))
+            .filter(inventory_record::Column::OriginatingConnectionId.eq(conn.id))
+            .filter(inventory_record::Column::DeletedAt.is_null())
+            .order_by_asc(inventory_record::Column::Id);
+        if let Some(since) = since {
+            query = query.filter(inventory_record::Column::UpdatedAt.gte(since));
+        }
+        let records = query.all(&txn).await?;
+
+        let outbox_svc = OutboxService::new(self.db.clone());
+        let mapping = QbdFieldMapping::of(conn);
+        let mut summary = ReprocessSummary::default();
+        for existing in upsert::with_latest_events(conn, records, &txn).await? {
+            let item = existing.record.original_record_body.as_ref().and_then(item_from_raw);
+            let Some(mut item) = item else {
+                summary.skipped += 1;
+                continue;
+            };
+            mapping.apply(&mut item);
+            summary.scanned += 1;
+
+            let (record, applied) = self
+                .upsert_inventory_item(conn, &item, Some(&existing), &txn)
+                .await?;
+            if applied.is_none() {
+                summary.unchanged += 1;
+                continue;
             }
-            txn.commit().await?;
-            return Ok(PollResponseOutput { has_more: false });
+            outbox_svc
+                .enqueue(&txn, inventory_upserted_message(conn, &record, &item))
+                .await?;
+            summary.updated += 1;
         }
 
-        // ── Parse XML ─────────────────────────────────────────────────────────
-        let xml_str = match input.qbd_response_xml.as_deref() {
-            Some(x) => x,
-            None => return Ok(PollResponseOutput { has_more: false }),
+        txn.commit().await?;
+        Ok(summary)
+    }
+
+    /// Apply a quarantined item again from its stored body, like a pulled item: upsert,
+    /// history event and `inventory.upserted` message. Runs in one transaction that also
+    /// deletes the quarantine row. When the item fails again, that transaction is rolled
+    /// back and the row is updated with the new error instead.
+    pub async fn replay_quarantined(
+        &self,
+        conn: &connection_identity::Model,
+        entry: &quarantine::Model,
+    ) -> Result<ReplayOutcome, QbdPollError> {
+        let quarantine_svc = QuarantineService::new(self.db.clone());
+        let Some(mut item) = item_from_raw(&entry.raw_body) else {
+            return Ok(ReplayOutcome::Failed(
+                "Stored body is not a QuickBooks Desktop item (no ListID)".to_string(),
+            ));
         };
+        QbdFieldMapping::of(conn).apply(&mut item);
 
-        let parsed = match parse_inventory_response(xml_str) {
-            Ok(p) => p,
+        let txn = self.db.begin().await?;
+        let existing =
+            upsert::existing_records(conn, SystemIdKey::Qbd, &[item.list_id.as_str()], &txn).await?;
+        match self
+            .upsert_inventory_item(conn, &item, existing.get(&item.list_id), &txn)
+            .await
+        {
+            Ok((record, applied)) => {
+                if applied.is_some() {
+                    OutboxService::new(self.db.clone())
+                        .enqueue(&txn, inventory_upserted_message(conn, &record, &item))
+                        .await?;
+                }
+                quarantine_svc.delete_by_id(&txn, entry.id).await?;
+                txn.commit().await?;
+                Ok(ReplayOutcome::Applied)
+            }
+            Err(QbdPollError::Db(e)) if e.is_transient() => Err(QbdPollError::Db(e)),
             Err(e) => {
-                let msg = format!("XML parse error: {e}");
+                // The failed write may have aborted the transaction, so the new error is
+                // recorded in a fresh one.
+                txn.rollback().await?;
+                let error = format!("ListID={}: {:?}", item.list_id, e);
                 let txn = self.db.begin().await?;
-                self.mark_event_and_run_error(&event, &run, &msg, &sync_event_svc, &run_svc, Some(&txn))
-                    .await;
-                let _ = txn.commit().await;
-                return Err(QbdPollError::XmlParse(msg));
+                quarantine_svc
+                    .quarantine(&txn, quarantine_item(conn, &item, &error))
+                    .await?;
+                txn.commit().await?;
+                Ok(ReplayOutcome::Failed(error))
             }
-        };
+        }
+    }
 
-        // QBD can return statusCode != "0" as a soft error inside the XML.
-        if parsed.status_code != "0" {
-            let msg = format!(
-                "QBD status {}: {}",
-                parsed.status_code, parsed.status_message
-            );
-            let txn = self.db.begin().await?;
-            self.mark_event_and_run_error(&event, &run, &msg, &sync_event_svc, &run_svc, Some(&txn))
-                .await;
-            let _ = txn.commit().await;
-            return Err(QbdPollError::XmlParse(msg));
-        }
-
-        // ── Upsert inventory items + update cursor + mark event/run in one transaction ──
-        let new_cursor = if parsed.remaining_count > 0 {
-            Some(json!({
-                "iterator_id": parsed.iterator_id,
-                "remaining_count": parsed.remaining_count,
-            }))
-        } else {
-            None
+    /// Progress of the connection's inventory pull, from its stored `sync_cursor`, the
+    /// recurring List/Inventory event and the records pulled so far. Read-only.
+    pub async fn sync_progress(
+        &self,
+        conn: &connection_identity::Model,
+    ) -> Result<SyncProgress, DbErr> {
+        let records_synced = inventory_record::Entity::find()
+            .filter(inventory_record::Column::SystemIdKey.eq(SystemIdKey::Qbd))
+            .filter(inventory_record::Column::OriginatingConnectionId.eq(conn.id))
+            .count(&self.db)
+            .await?;
+
+        //the sync state with its List/Inventory events in one query; the per-item and push
+        //events it also owns are filtered out. A state holding only those drops out too, which
+        //reads the same: a cursor is only ever stored by a List/Inventory event
+        let sync_state = erp_connection_sync_state::Entity::find()
+            .filter(erp_connection_sync_state::Column::ConnectionId.eq(conn.id))
+            .find_with_related(sync_event::Entity)
+            .filter(
+                Condition::any().add(sync_event::Column::Id.is_null()).add(
+                    Condition::all()
+                        .add(sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
+                        .add(sync_event::Column::SyncEventCategory.eq(SyncEventCategory::Inventory)),
+                ),
+            )
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .next();
+        let Some((sync_state, mut events)) = sync_state else {
+            return Ok(SyncProgress {
+                records_synced,
+                ..Default::default()
+            });
         };
+        events.sort_by_key(|e| std::cmp::Reverse(e.updated_at));
 
-        let txn = self.db.begin().await?;
-        let mut errors: Vec<String> = Vec::new();
-        for item in &parsed.items {
-            if let Err(e) = self.upsert_inventory_item(&conn, item, Some(&txn)).await {
-                errors.push(format!("ListID={}: {:?}", item.list_id, e));
+        //a malformed cursor reads as none, as it does for the next request
+        let (iterator_id, remaining_count, cursor_type) = match SyncCursor::of(&sync_state) {
+            Some(SyncCursor::QbdIterator { iterator_id, remaining_count, item_type, .. }) => {
+                (iterator_id, Some(remaining_count), Some(cursor_item_type(item_type.as_deref())))
             }
-        }
+            _ => (None, None, None),
+        };
 
-        let sync_state_svc = ErpConnectionSyncStateService::new(self.db.clone());
-        if let Ok(Some(ss)) = sync_state_svc.get_by_id(sync_state.id, Some(&txn)).await {
-            let mut active: erp_connection_sync_state::ActiveModel = ss.into();
-            active.sync_cursor = Set(new_cursor);
-            active.updated_at = Set(chrono::Utc::now().into());
-            let _ = active.update(&txn).await;
-        }
-
-        let has_errors = !errors.is_empty();
-        if let Some(ref ev) = event {
-            let is_list = ev.sync_event_method == SyncEventMethod::List;
-            let (new_status, last_error) = if has_errors {
-                let err_body = json!({ "errors": errors });
-                let status = if is_list {
-                    SyncEventStatus::Pending
-                } else {
-                    SyncEventStatus::Error
-                };
-                (status, Some(err_body))
-            } else {
-                let status = if is_list {
-                    SyncEventStatus::Pending
-                } else {
-                    SyncEventStatus::Success
-                };
-                (status, None)
-            };
-            let _ = sync_event_svc
-                .update_by_uuid(
-                    ev.uuid,
-                    UpdateSyncEvent {
-                        status: Some(new_status),
-                        last_error,
-                        last_errored_date: if has_errors {
-                            Some(chrono::Utc::now())
-                        } else {
-                            None
-                        },
-                        attempts: None,
-                        original_record_body: None,
-                        details: None,
-                        event_direction: None,
-                        inventory_record_event_id: None,
-                        sync_event_method: None,
-                        sync_event_category: None,
-                        connection_sync_state_id: None,
-                        connection_run_id: None,
-                    },
-                    Some(&txn),
-                )
-                .await;
-        }
-        if has_errors {
-            if let Some(ref r) = run {
-                let _ = run_svc
-                    .update_by_uuid(
-                        r.uuid,
-                        UpdateConnectionRun {
-                            status: Some(ConnectionRunStatus::Error),
-                            error_message: Some(errors.join("; ")),
-                        },
-                        Some(&txn),
-                    )
-                    .await;
-            }
-        }
-        txn.commit().await?;
+        //the event paginating the stored cursor, else the one in flight, else the latest
+        let event = cursor_type
+            .and_then(|t| events.iter().find(|e| QbdItemType::from_details(e.details.as_ref()) == t))
+            .or_else(|| events.iter().find(|e| e.status == SyncEventStatus::InProgress))
+            .or_else(|| events.first());
 
-        Ok(PollResponseOutput {
-            has_more: parsed.remaining_count > 0,
+        let item_type = match (cursor_type, event) {
+            (Some(cursor_type), _) => Some(cursor_type),
+            (None, Some(event)) => Some(QbdItemType::from_details(event.details.as_ref())),
+            (None, None) => None,
+        };
+
+        Ok(SyncProgress {
+            iterator_id,
+            remaining_count,
+            item_type: item_type.map(|t| t.as_str()),
+            event_uuid: event.map(|e| e.uuid),
+            event_status: event.map(|e| e.status.clone()),
+            records_synced,
+            in_progress: cursor_type.is_some()
+                || event.is_some_and(|e| e.status == SyncEventStatus::InProgress),
+            percent_complete: remaining_count.and_then(|r| percent_complete(records_synced, r)),
         })
     }
 
     // ── Private helpers ───────────────────────────────────────────────────────
 
+    /// Parse a response for the connection's InProgress event (read-only) and report what
+    /// applying it would do. Nothing is written: no sync state, event, run or record changes.
+    async fn dry_run_response(
+        &self,
+        conn: &connection_identity::Model,
+        body: Option<String>,
+    ) -> Result<DryRunSummary, QbdPollError> {
+        let body = body.ok_or_else(|| {
+            QbdPollError::XmlParse("Dry run requires qbd_response_xml".to_string())
+        })?;
+
+        let sync_state = erp_connection_sync_state::Entity::find()
+            .filter(erp_connection_sync_state::Column::ConnectionId.eq(conn.id))
+            .one(&self.db)
+            .await?;
+        let event = match &sync_state {
+            Some(state) => {
+                sync_event::Entity::find()
+                    .filter(sync_event::Column::ConnectionSyncStateId.eq(state.id))
+                    .filter(sync_event::Column::Status.eq(SyncEventStatus::InProgress))
+                    .filter(sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
+                    .one(&self.db)
+                    .await?
+            }
+            None => None,
+        };
+        if let Some(event) = &event {
+            Span::current().record("event_uuid", tracing::field::display(event.uuid));
+        }
+        let item_type = QbdItemType::from_details(event.as_ref().and_then(|e| e.details.as_ref()));
+        let category = event
+            .as_ref()
+            .map_or(SyncEventCategory::Inventory, |e| e.sync_event_category.clone());
+
+        let mut parsed = parse_item_query_response(&body, item_type)
+            .map_err(|e| QbdPollError::XmlParse(format!("XML parse error: {e}")))?;
+        let mapping = QbdFieldMapping::of(conn);
+        parsed.items.iter_mut().for_each(|item| mapping.apply(item));
+        let cursor = next_cursor(&parsed, item_type);
+
+        let valid: Vec<&QbdInventoryItem> = parsed
+            .items
+            .iter()
+            .filter(|item| validate_record_body(&category, &item.raw).is_empty())
+            .collect();
+        let list_ids: Vec<&str> = valid.iter().map(|i| i.list_id.as_str()).collect();
+        let existing = inventory_record::Entity::find()
+            .filter(inventory_record::Column::SystemIdKey.eq(SystemIdKey::Qbd))
+            .filter(inventory_record::Column::SystemId.is_in(list_ids))
+            .filter(inventory_record::Column::OriginatingConnectionId.eq(conn.id))
+            .count(&self.db)
+            .await? as usize;
+
+        Ok(DryRunSummary {
+            item_type: item_type.as_str(),
+            status_code: parsed.status_code,
+            status_message: parsed.status_message,
+            item_count: parsed.items.len(),
+            would_create: valid.len().saturating_sub(existing),
+            would_update: existing,
+            would_reject: parsed.items.len() - valid.len(),
+            sample: parsed.items.first().map(|item| item.raw.clone()),
+            has_more: cursor.is_some(),
+            cursor,
+        })
+    }
+
     async fn validate_credentials(
         &self,
         username: &str,
         password: &str,
-    ) -> Result<
-        (
-            connection_identity::Model,
-            erp_connection_credentials::Model,
-        ),
-        QbdPollError,
-    > {
-        let creds = erp_connection_credentials::Entity::find()
+    ) -> Result<connection_identity::Model, QbdPollError> {
+        let conn = self.resolve_credentials(username, password).await?;
+        if let Some(scope) = self.connection_scope.filter(|scope| *scope != conn.id) {
+            tracing::warn!(
+                connection_id = conn.id,
+                key_connection_id = scope,
+                "Connection API key used for another connection's poll"
+            );
+            return Err(QbdPollError::Unauthorized);
+        }
+        Ok(conn)
+    }
+
+    ///the QBD Desktop connection the Web Connector credentials belong to
+    async fn resolve_credentials(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<connection_identity::Model, QbdPollError> {
+        if let Some(conn) = self.cached_connection(username, password).await? {
+            return Ok(conn);
+        }
+
+        let (creds, conn) = erp_connection_credentials::Entity::find()
             .filter(erp_connection_credentials::Column::ProviderUserId.eq(username))
+            .find_also_related(connection_identity::Entity)
             .one(&self.db)
             .await?
             .ok_or(QbdPollError::Unauthorized)?;
 
-        if creds.provider_password.as_deref().unwrap_or("") != password {
+        let conn = conn.ok_or(QbdPollError::Unauthorized)?;
+
+        //from the connection's secret store when it has a secret_storage_ref
+        let stored = self
+            .secrets
+            .resolve(&conn, &creds, SecretField::ProviderPassword)
+            .await
+            .map_err(|e| {
+                tracing::error!(credentials_id = creds.id, error = %e, "Can't read the Web Connector password");
+                QbdPollError::Unauthorized
+            })?;
+        if stored.as_deref().unwrap_or("") != password {
             return Err(QbdPollError::Unauthorized);
         }
 
-        let conn = connection_identity::Entity::find_by_id(creds.connection_id)
-            .one(&self.db)
-            .await?
-            .ok_or(QbdPollError::Unauthorized)?;
-
         if conn.erp_provider != ErpProvider::Quickbooks
             || conn.erp_type != ErpProviderType::Desktop
         {
             return Err(QbdPollError::Unauthorized);
         }
 
-        Ok((conn, creds))
+        if let Some(cache) = &self.credential_cache {
+            cache.insert(
+                username,
+                CachedCredential::new(conn.id, password, conn.erp_provider.clone(), conn.erp_type.clone()),
+            );
+        }
+        Ok(conn)
+    }
+
+    /// The connection of a cached credential check for `username`, reloaded by id so its
+    /// state is current. None on a miss, a different password or a connection that is gone or
+    /// no longer QBD Desktop; the full check runs then.
+    async fn cached_connection(
+        &self,
+        username: &str,
+        password: &str,
+    ) -> Result<Option<connection_identity::Model>, QbdPollError> {
+        let Some(cache) = &self.credential_cache else {
+            return Ok(None);
+        };
+        let Some(cached) = cache.get(username) else {
+            return Ok(None);
+        };
+        if !cached.password_matches(password) {
+            return Ok(None);
+        }
+
+        let conn = connection_identity::Entity::find_by_id(cached.connection_id)
+            .one(&self.db)
+            .await?
+            .filter(|conn| {
+                conn.erp_provider == cached.erp_provider
+                    && conn.erp_type == cached.erp_type
+                    && conn.erp_provider == ErpProvider::Quickbooks
+                    && conn.erp_type == ErpProviderType::Desktop
+            });
+        if conn.is_none() {
+            cache.invalidate_connection(cached.connection_id);
+        }
+        Ok(conn)
+    }
+
+    /// Whether the server polls connections of `conn`'s environment; logs a warning when not.
+    fn environment_allowed(&self, conn: &connection_identity::Model) -> bool {
+        match &self.required_environment {
+            Some(required) if *required != conn.environment => {
+                tracing::warn!(
+                    connection_id = conn.id,
+                    environment = ?conn.environment,
+                    required = ?required,
+                    "Refusing to poll a connection outside the server's environment"
+                );
+                false
+            }
+            _ => true,
+        }
     }
 
-    async fn ensure_sync_state(
+    /// Stamp `initial_sync_completed_at` once the connection's first pass has paged to the end.
+    /// Every pass opens with `iterator="Start"`, so the last page of any pass closes a full
+    /// one; later passes leave the stamp alone. Written in the page transaction.
+    async fn mark_initial_sync_complete(
         &self,
-        connection_id: i64,
-    ) -> Result<erp_connection_sync_state::Model, QbdPollError> {
-        let svc = ErpConnectionSyncStateService::new(self.db.clone());
-        match svc.get_by_connection_id(connection_id, None).await? {
-            Some(s) => Ok(s),
-            None => Ok(svc
-                .create(
-                    CreateErpConnectionSyncState {
-                        connection_id,
-                        sync_cursor: None,
-                        sync_lock_owner: None,
-                        sync_lock_until: None,
-                        rate_limit_remaining: None,
-                        rate_limit: None,
-                        rate_limit_reset_at: None,
-                        rate_limit_backoff_until: None,
-                        rate_limit_window_seconds: None,
-                    },
-                    None,
-                )
-                .await?),
-        }
-    }
-
-    /// Create or update a single inventory item from a QBD response.
+        conn: &connection_identity::Model,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), DbErr> {
+        if conn.initial_sync_completed_at.is_some() {
+            return Ok(());
+        }
+        connection_identity::Entity::update_many()
+            .col_expr(
+                connection_identity::Column::InitialSyncCompletedAt,
+                Expr::value(Utc::now()),
+            )
+            .filter(connection_identity::Column::Id.eq(conn.id))
+            .filter(connection_identity::Column::InitialSyncCompletedAt.is_null())
+            .exec(txn)
+            .await?;
+        Ok(())
+    }
+
+    /// Create or update a single inventory item from a QBD response through the shared
+    /// inventory upsert (`inventory_records::upsert`).
     ///
-    /// - Matches on `system_id_key=Qbd` + `system_id={ListID}` + `originating_connection_id`
-    /// - Creates `inventory_record` + `inventory_record_event` if new
-    /// - Updates the most recent `inventory_record_event` if the record already exists
+    /// - `item` has had the connection's `QbdFieldMapping` applied: `list_id` is the
+    ///   `system_id`, `name` the name and `full_name` the `external_code`
+    /// - `existing` is the item's record and latest event from `upsert::existing_records`
+    /// - Returns the canonical `inventory_record`, plus the event written for the item (None
+    ///   when nothing changed)
     async fn upsert_inventory_item(
         &self,
         conn: &connection_identity::Model,
         item: &QbdInventoryItem,
-        txn: Option<&DatabaseTransaction>,
-    ) -> Result<(), QbdPollError> {
-        let inv_svc = InventoryRecordService::new(self.db.clone());
-        let evt_svc = InventoryRecordEventService::new(self.db.clone());
-
-        // Look up the canonical inventory record by QBD ListID.
-        let record = match txn {
-            Some(t) => inventory_record::Entity::find()
-                .filter(inventory_record::Column::SystemIdKey.eq(SystemIdKey::Qbd))
-                .filter(inventory_record::Column::SystemId.eq(&item.list_id))
-                .filter(inventory_record::Column::OriginatingConnectionId.eq(conn.id))
-                .one(t)
-                .await?,
-            None => inventory_record::Entity::find()
-                .filter(inventory_record::Column::SystemIdKey.eq(SystemIdKey::Qbd))
-                .filter(inventory_record::Column::SystemId.eq(&item.list_id))
-                .filter(inventory_record::Column::OriginatingConnectionId.eq(conn.id))
-                .one(&self.db)
-                .await?,
-        };
+        existing: Option<&ExistingRecord>,
+        txn: &DatabaseTransaction,
+    ) -> Result<(inventory_record::Model, Option<AppliedRecord>), QbdPollError> {
+        let upserted = upsert::upsert_inventory_item(&self.db, conn, &inventory_item(item), existing, txn)
+            .await
+            .map_err(QbdPollError::Db)?;
+        let applied = upserted.event.map(|event| AppliedRecord {
+            record_id: item.list_id.clone(),
+            inventory_record_event_id: event.id,
+            created: upserted.outcome == UpsertOutcome::Created,
+        });
+        Ok((upserted.record, applied))
+    }
 
-        let record = match record {
-            Some(r) => {
-                let _ = inv_svc
-                    .update_by_id(
-                        r.id,
-                        crate::inventory_records::services::UpdateInventoryRecord {
-                            original_record_body: Some(item.raw.clone()),
-                            system_id_key: None,
-                            system_id: None,
-                        },
-                        txn,
-                    )
-                    .await;
-                r
+    /// Upsert one valid item of a page with its outbox message, quarantining it when the
+    /// upsert fails. None for an unchanged item.
+    ///
+    /// The item is written under its own savepoint: a failed statement aborts a Postgres
+    /// transaction, so the savepoint is rolled back before the quarantine row is written
+    /// and the rest of the page can go on in the page transaction.
+    async fn apply_page_item(
+        &self,
+        conn: &connection_identity::Model,
+        item: &QbdInventoryItem,
+        existing: Option<&ExistingRecord>,
+        txn: &DatabaseTransaction,
+    ) -> Result<Option<AppliedRecord>, ItemFailure> {
+        let savepoint = txn.begin().await.map_err(ItemFailure::Savepoint)?;
+        let (record, applied) = match self
+            .upsert_inventory_item(conn, item, existing, &savepoint)
+            .await
+        {
+            Ok(upserted) => upserted,
+            Err(QbdPollError::Db(e)) if e.is_transient() => {
+                return Err(ItemFailure::Transient(e));
             }
-            None => {
-                inv_svc
-                    .create(
-                        CreateInventoryRecord {
-                            tenant_id: conn.tenant_id,
-                            originating_connection_id: conn.id,
-                            original_record_body: Some(item.raw.clone()),
-                            system_id_key: SystemIdKey::Qbd,
-                            system_id: item.list_id.clone(),
-                        },
-                        txn,
-                    )
-                    .await?
+            Err(e) => {
+                let error = format!("ListID={}: {:?}", item.list_id, e);
+                savepoint.rollback().await.map_err(ItemFailure::Savepoint)?;
+                // Kept with the page so the item can be replayed once fixed.
+                QuarantineService::new(self.db.clone())
+                    .quarantine(txn, quarantine_item(conn, item, &error))
+                    .await
+                    .map_err(ItemFailure::Quarantine)?;
+                return Err(ItemFailure::Upsert(error));
             }
         };
+        // An unchanged item wrote nothing, so there is nothing to announce.
+        if applied.is_some() {
+            // Written in the upsert transaction so the message exists iff the change commits.
+            OutboxService::new(self.db.clone())
+                .enqueue(&savepoint, inventory_upserted_message(conn, &record, item))
+                .await
+                .map_err(ItemFailure::Outbox)?;
+        }
+        savepoint.commit().await.map_err(ItemFailure::Savepoint)?;
+        Ok(applied)
+    }
+}
+
+// ── Pull adapter ──────────────────────────────────────────────────────────────
 
-        let existing_event = match txn {
-            Some(t) => inventory_record_event::Entity::find()
-                .filter(inventory_record_event::Column::InventoryRecordId.eq(record.id))
-                .filter(inventory_record_event::Column::ConnectionId.eq(conn.id))
-                .order_by_desc(inventory_record_event::Column::CreatedAt)
-                .one(t)
-                .await?,
-            None => inventory_record_event::Entity::find()
-                .filter(inventory_record_event::Column::InventoryRecordId.eq(record.id))
-                .filter(inventory_record_event::Column::ConnectionId.eq(conn.id))
-                .order_by_desc(inventory_record_event::Column::CreatedAt)
-                .one(&self.db)
-                .await?,
+impl PullAdapter for QbdPollService {
+    fn next_request(
+        &self,
+        conn: &connection_identity::Model,
+        sync_state: &erp_connection_sync_state::Model,
+        event: &sync_event::Model,
+    ) -> PullRequest {
+        let item_type = QbdItemType::from_details(event.details.as_ref());
+        // A cursor from a different item list can't be continued by this query.
+        let iterator_id = match SyncCursor::of(sync_state) {
+            Some(SyncCursor::QbdIterator { iterator_id, item_type: cursor_type, .. })
+                if cursor_item_type(cursor_type.as_deref()) == item_type =>
+            {
+                iterator_id
+            }
+            _ => None,
         };
 
-        match existing_event {
-            Some(ev) => {
-                let _ = evt_svc
-                    .update_by_id(
-                        ev.id,
-                        UpdateInventoryRecordEvent {
-                            original_record_body: Some(item.raw.clone()),
-                            price: item.sales_price_cents,
-                            currency: None,
-                            name: item.name.clone(),
-                            description: item.sales_desc.clone(),
-                            attributes: None,
-                            qty: item.qty_on_hand,
-                            external_code: item.full_name.clone(),
-                        },
-                        txn,
-                    )
-                    .await;
+        PullRequest {
+            payload: build_item_query_xml(item_type, iterator_id.as_deref(), conn.qbxml_version.as_deref()),
+        }
+    }
+
+    async fn apply_response(
+        &self,
+        conn: &connection_identity::Model,
+        event: &sync_event::Model,
+        body: &str,
+        txn: &DatabaseTransaction,
+    ) -> Result<PullOutcome, PullError> {
+        let item_type = QbdItemType::from_details(event.details.as_ref());
+
+        let mut parsed = parse_item_query_response(body, item_type)
+            .map_err(|e| PullError::Provider(format!("XML parse error: {e}")))?;
+        let mapping = QbdFieldMapping::of(conn);
+        parsed.items.iter_mut().for_each(|item| mapping.apply(item));
+
+        // QBD reports request failures as a status inside the XML (see `qbd_status`).
+        if parsed.is_iterator_expired() {
+            return Err(PullError::CursorExpired(format!(
+                "QBD status {}: {}",
+                parsed.status_code, parsed.status_message
+            )));
+        }
+        let status = qbd_status::classify(&parsed.status_code, &parsed.status_severity);
+        let mut warnings: Vec<String> = Vec::new();
+        match status.outcome() {
+            QbdStatusOutcome::Success => {}
+            // Nothing matched the query: an empty last page.
+            QbdStatusOutcome::NoRecords => {
+                self.mark_initial_sync_complete(conn, txn).await?;
+                return Ok(PullOutcome::default());
             }
-            None => {
-                evt_svc
-                    .create(
-                        CreateInventoryRecordEvent {
-                            inventory_record_id: record.id,
-                            connection_id: conn.id,
-                            original_record_body: Some(item.raw.clone()),
-                            price: item.sales_price_cents,
-                            currency: None,
-                            name: item.name.clone(),
-                            description: item.sales_desc.clone(),
-                            attributes: None,
-                            qty: item.qty_on_hand,
-                            external_code: item.full_name.clone(),
-                        },
-                        txn,
-                    )
-                    .await?;
+            QbdStatusOutcome::Warning => warnings.push(status.describe(&parsed.status_message)),
+            QbdStatusOutcome::Error => {
+                return Err(PullError::Status(ProviderStatus {
+                    message: status.describe(&parsed.status_message),
+                    code: status.code,
+                    retryable: status.retryable,
+                }));
             }
         }
 
-        Ok(())
+        // A company file without items answers status 0 with no `*Ret` and nothing
+        // remaining: a completed pass, same as NoRecords.
+        if parsed.items.is_empty() && parsed.remaining_count == 0 {
+            tracing::debug!(
+                connection_id = conn.id,
+                item_type = item_type.as_str(),
+                "Empty item list; pass complete"
+            );
+            self.mark_initial_sync_complete(conn, txn).await?;
+            return Ok(PullOutcome {
+                warnings,
+                ..PullOutcome::default()
+            });
+        }
+
+        let mut valid: Vec<&QbdInventoryItem> = Vec::new();
+        let mut rejected: Vec<RejectedRecord> = Vec::new();
+        for item in &parsed.items {
+            let field_errors = validate_record_body(&event.sync_event_category, &item.raw);
+            if field_errors.is_empty() {
+                valid.push(item);
+            } else {
+                rejected.push(RejectedRecord {
+                    record_id: item.list_id.clone(),
+                    errors: field_errors,
+                });
+            }
+        }
+
+        let list_ids: Vec<&str> = valid.iter().map(|i| i.list_id.as_str()).collect();
+        let existing = upsert::existing_records(conn, SystemIdKey::Qbd, &list_ids, txn).await?;
+
+        // Items are upserted one after another in the page transaction, which keeps each
+        // outbox message tied to its change. They all share the transaction's single
+        // connection, so running them concurrently would only queue them on it.
+        let mut errors: Vec<String> = Vec::new();
+        let mut applied: Vec<AppliedRecord> = Vec::new();
+        for item in valid {
+            match self
+                .apply_page_item(conn, item, existing.get(&item.list_id), txn)
+                .await
+            {
+                Ok(Some(record)) => applied.push(record),
+                Ok(None) => {}
+                Err(ItemFailure::Upsert(msg)) => errors.push(msg),
+                Err(
+                    ItemFailure::Outbox(e)
+                    | ItemFailure::Quarantine(e)
+                    | ItemFailure::Transient(e)
+                    | ItemFailure::Savepoint(e),
+                ) => return Err(e.into()),
+            }
+        }
+
+        let cursor = next_cursor(&parsed, item_type);
+        if cursor.is_none() {
+            self.mark_initial_sync_complete(conn, txn).await?;
+        }
+
+        Ok(PullOutcome {
+            cursor,
+            errors,
+            rejected,
+            applied,
+            warnings,
+        })
     }
 
-    /// Best-effort: mark a sync event and connection run as Error.
-    async fn mark_event_and_run_error(
-        &self,
-        event: &Option<sync_event::Model>,
-        run: &Option<connection_run::Model>,
-        message: &str,
-        sync_event_svc: &SyncEventService,
-        run_svc: &ConnectionRunService,
-        txn: Option<&DatabaseTransaction>,
-    ) {
-        let err_body = json!({ "message": message });
-
-        if let Some(ev) = event {
-            let _ = sync_event_svc
-                .update_by_uuid(
-                    ev.uuid,
-                    UpdateSyncEvent {
-                        status: Some(SyncEventStatus::Error),
-                        last_error: Some(err_body),
-                        last_errored_date: Some(chrono::Utc::now()),
-                        attempts: None,
-                        original_record_body: None,
-                        details: None,
-                        event_direction: None,
-                        inventory_record_event_id: None,
-                        sync_event_method: None,
-                        sync_event_category: None,
-                        connection_sync_state_id: None,
-                        connection_run_id: None,
-                    },
-                    txn,
-                )
-                .await;
-        }
-
-        if let Some(r) = run {
-            let _ = run_svc
-                .update_by_uuid(
-                    r.uuid,
-                    UpdateConnectionRun {
-                        status: Some(ConnectionRunStatus::Error),
-                        error_message: Some(message.to_string()),
-                    },
-                    txn,
-                )
-                .await;
+    fn resumes_cursor(&self, event: &sync_event::Model, cursor: &Value) -> bool {
+        match SyncCursor::parse(cursor) {
+            Ok(SyncCursor::QbdIterator { item_type, .. }) => {
+                QbdItemType::from_details(event.details.as_ref())
+                    == cursor_item_type(item_type.as_deref())
+            }
+            _ => false,
         }
     }
 }
 
-// ── QBXML builders ────────────────────────────────────────────────────────────
-
-/// Build an `ItemInventoryQueryRq`.
-///
-/// Uses `iterator="Continue" iteratorID="..."` when a cursor is present,
-/// otherwise `iterator="Start"`.
-fn build_item_inventory_query_xml(cursor: Option<&Value>) -> String {
-    let iterator_id = cursor
-        .and_then(|c| c.get("iterator_id"))
-        .and_then(|v| v.as_str());
-
-    match iterator_id {
-        None => format!(
-            r#"<?xml version="1.0" encoding="utf-8"?>
-<?qbxml version="13.0"?>
-<QBXML>
-  <QBXMLMsgsRq onError="stopOnError">
-    <ItemInventoryQueryRq requestID="1" iterator="Start" maxReturned="{ps}">
-    </ItemInventoryQueryRq>
-  </QBXMLMsgsRq>
-</QBXML>"#,
-            ps = PAGE_SIZE
-        ),
-        Some(id) => format!(
-            r#"<?xml version="1.0" encoding="utf-8"?>
-<?qbxml version="13.0"?>
-<QBXML>
-  <QBXMLMsgsRq onError="stopOnError">
-    <ItemInventoryQueryRq requestID="1" iterator="Continue" iteratorID="{id}" maxReturned="{ps}">
-    </ItemInventoryQueryRq>
-  </QBXMLMsgsRq>
-</QBXML>"#,
-            id = id,
-            ps = PAGE_SIZE
-        ),
+/// Why a single item of a page wasn't applied. Upsert failures are reported per item;
+/// an outbox failure fails the page, since the change would commit without its message,
+/// and so does a failure to quarantine the item, which would otherwise be lost.
+/// A transient error fails the page as well, and the orchestrator retries the page as a
+/// whole; so does a failure to open, release or roll back the item's savepoint.
+enum ItemFailure {
+    Upsert(String),
+    Outbox(DbErr),
+    Quarantine(DbErr),
+    Transient(DbErr),
+    Savepoint(DbErr),
+}
+
+/// The quarantine row for an item whose upsert failed with `error`.
+fn quarantine_item(
+    conn: &connection_identity::Model,
+    item: &QbdInventoryItem,
+    error: &str,
+) -> QuarantineItem {
+    QuarantineItem {
+        connection_id: conn.id,
+        list_id: item.list_id.clone(),
+        item_type: Some(item.item_type.as_str().to_string()),
+        raw_body: item.raw.clone(),
+        error: error.to_string(),
     }
 }
 
-// ── XML parser ────────────────────────────────────────────────────────────────
-
-/// Parse a QBD `ItemInventoryQueryRs` QBXML response.
-fn parse_inventory_response(xml: &str) -> Result<ParsedInventoryResponse, String> {
-    let mut reader = Reader::from_str(xml);
-    let mut buf = Vec::new();
-
-    let mut iterator_id: Option<String> = None;
-    let mut remaining_count: i64 = 0;
-    let mut status_code = "0".to_string();
-    let mut status_message = String::new();
-    let mut items: Vec<QbdInventoryItem> = Vec::new();
-
-    let mut in_item = false;
-    let mut current_tag: Option<String> = None;
-    let mut current_data: HashMap<String, String> = HashMap::new();
-
-    loop {
-        buf.clear();
-        match reader.read_event_into(&mut buf) {
-            Ok(Event::Start(ref e)) => {
-                let name =
-                    String::from_utf8_lossy(e.name().as_ref()).to_string();
-
-                match name.as_str() {
-                    "ItemInventoryQueryRs" => {
-                        for attr in e.attributes().flatten() {
-                            let key =
-                                String::from_utf8_lossy(attr.key.as_ref()).to_string();
-                            let val =
-                                String::from_utf8_lossy(attr.value.as_ref()).to_string();
-                            match key.as_str() {
-                                "iteratorID" => iterator_id = Some(val),
-                                "iteratorRemainingCount" => {
-                                    remaining_count = val.parse().unwrap_or(0);
-                                }
-                                "statusCode" => status_code = val,
-                                "statusMessage" => status_message = val,
-                                _ => {}
-                            }
-                        }
-                    }
-                    "ItemInventoryRet" => {
-                        in_item = true;
-                        current_data.clear();
-                        current_tag = None;
-                    }
-                    _ if in_item => {
-                        current_tag = Some(name);
-                    }
-                    _ => {}
-                }
-            }
+/// Cursor to store after a page; None once QBD reports no items remaining.
+fn next_cursor(parsed: &ParsedInventoryResponse, item_type: QbdItemType) -> Option<Value> {
+    (parsed.remaining_count > 0).then(|| {
+        SyncCursor::QbdIterator {
+            iterator_id: parsed.iterator_id.clone(),
+            remaining_count: parsed.remaining_count,
+            item_type: Some(item_type.as_str().to_string()),
+            seen_ids: Vec::new(),
+            from_modified_date: None,
+        }
+        .to_value()
+    })
+}
 
-            Ok(Event::End(ref e)) => {
-                let name =
-                    String::from_utf8_lossy(e.name().as_ref()).to_string();
-
-                if name == "ItemInventoryRet" {
-                    in_item = false;
-                    current_tag = None;
-
-                    if let Some(list_id) = current_data.get("ListID").cloned() {
-                        let price_cents = current_data
-                            .get("SalesPrice")
-                            .and_then(|p| p.parse::<f64>().ok())
-                            .map(|p| (p * 100.0).round() as i32);
-
-                        let qty = current_data
-                            .get("QuantityOnHand")
-                            .and_then(|q| q.parse::<i32>().ok());
-
-                        let raw: Value = current_data
-                            .iter()
-                            .fold(serde_json::Map::new(), |mut m, (k, v)| {
-                                m.insert(k.clone(), Value::String(v.clone()));
-                                m
-                            })
-                            .into();
-
-                        items.push(QbdInventoryItem {
-                            list_id,
-                            name: current_data.get("Name").cloned(),
-                            full_name: current_data.get("FullName").cloned(),
-                            sales_price_cents: price_cents,
-                            qty_on_hand: qty,
-                            sales_desc: current_data.get("SalesDesc").cloned(),
-                            raw,
-                        });
-                    }
-                    current_data.clear();
-                } else if in_item {
-                    current_tag = None;
-                }
-            }
+/// Item list of a stored QBD cursor; inventory when unset or unknown, as for event `details`.
+fn cursor_item_type(item_type: Option<&str>) -> QbdItemType {
+    item_type.and_then(QbdItemType::parse).unwrap_or_default()
+}
 
-            Ok(Event::Text(ref e)) if in_item => {
-                if let (Some(tag), Ok(text)) = (&current_tag, e.unescape()) {
-                    let text = text.trim().to_string();
-                    if !text.is_empty() {
-                        current_data.insert(tag.clone(), text);
-                    }
-                }
-            }
+/// `synced` out of `synced + remaining`, to one decimal; None when both are zero.
+fn percent_complete(synced: u64, remaining: i64) -> Option<String> {
+    let total = synced as f64 + remaining.max(0) as f64;
+    (total > 0.0).then(|| format!("{:.1}%", synced as f64 / total * 100.0))
+}
 
-            Ok(Event::Eof) => break,
-            Err(e) => return Err(format!("{e}")),
-            _ => {}
+/// The item as the shared upsert takes it. Its `raw` body is stored but never compared
+/// (`upsert::event_matches`): QBD bumps fields like `EditSequence` without any change to
+/// the values tracked here, so a re-pull of an unchanged item writes nothing.
+fn inventory_item(item: &QbdInventoryItem) -> InventoryItem {
+    InventoryItem {
+        system_id_key: SystemIdKey::Qbd,
+        system_id: item.list_id.clone(),
+        name: item.name.clone(),
+        description: item.sales_desc.clone(),
+        price: item.sales_price_cents,
+        currency: None,
+        qty: item.qty_on_hand,
+        qty_milli: item.qty_on_hand_milli,
+        external_code: item.full_name.clone(),
+        attributes: Some(item_attributes(item)),
+        raw: item.raw.clone(),
+    }
+}
+
+/// Structured attributes stored on the inventory record event: the QBD item type, plus
+/// the manufacturer part number when QBD sends one.
+fn item_attributes(item: &QbdInventoryItem) -> Value {
+    let mut attributes = json!({ ITEM_TYPE_KEY: item.item_type.as_str() });
+    if let Some(mpn) = &item.manufacturer_part_number {
+        attributes["manufacturer_part_number"] = json!(mpn);
+    }
+    attributes
+}
+
+// ── Domain events ─────────────────────────────────────────────────────────────
+
+/// Events describing the result of applying one pull response for `conn`.
+fn pull_events(
+    conn: &connection_identity::Model,
+    qbd_error: Option<String>,
+    result: &Result<PullOutcome, PullError>,
+) -> Vec<DomainEvent> {
+    let failed = |code: Option<String>, message: String| {
+        DomainEvent::SyncEventFailed(SyncEventFailed {
+            tenant_id: conn.tenant_id,
+            connection_id: conn.id,
+            connection_uuid: conn.uuid,
+            code,
+            message,
+        })
+    };
+    match result {
+        // A QBD error reply fails the event but still answers with an empty outcome.
+        Ok(_) if qbd_error.is_some() => vec![failed(None, qbd_error.unwrap_or_default())],
+        Ok(outcome) => outcome
+            .applied
+            .iter()
+            .map(|record| {
+                DomainEvent::InventoryUpserted(InventoryUpserted {
+                    tenant_id: conn.tenant_id,
+                    connection_id: conn.id,
+                    connection_uuid: conn.uuid,
+                    record_id: record.record_id.clone(),
+                    inventory_record_event_id: record.inventory_record_event_id,
+                    created: record.created,
+                })
+            })
+            .collect(),
+        Err(PullError::Provider(message)) => vec![failed(None, message.clone())],
+        Err(PullError::Status(status)) => {
+            let mut events = vec![failed(Some(status.code.clone()), status.message.clone())];
+            if qbd_status::needs_reauth(&status.code) {
+                events.push(DomainEvent::ConnectionReauthNeeded(ConnectionReauthNeeded {
+                    tenant_id: conn.tenant_id,
+                    connection_id: conn.id,
+                    connection_uuid: conn.uuid,
+                    reason: status.message.clone(),
+                }));
+            }
+            events
         }
+        Err(PullError::CursorExpired(_) | PullError::Db(_)) => Vec::new(),
     }
+}
 
-    Ok(ParsedInventoryResponse {
-        iterator_id,
-        remaining_count,
-        status_code,
-        status_message,
-        items,
-    })
+// ── Outbox messages ───────────────────────────────────────────────────────────
+
+/// Build the `inventory.upserted` message for a single QBD item.
+fn inventory_upserted_message(
+    conn: &connection_identity::Model,
+    record: &inventory_record::Model,
+    item: &QbdInventoryItem,
+) -> OutboxPayload {
+    OutboxPayload {
+        topic: TOPIC_INVENTORY_UPSERTED.to_string(),
+        payload: json!({
+            "tenant_id": conn.tenant_id,
+            "connection_uuid": conn.uuid,
+            "inventory_record_uuid": record.uuid,
+            "system_id_key": "qbd",
+            "system_id": record.system_id,
+            ITEM_TYPE_KEY: item.item_type.as_str(),
+            "name": item.name,
+            "qty": item.qty_on_hand,
+            "qty_milli": item.qty_on_hand_milli,
+            "price": item.sales_price_cents,
+        }),
+    }
 }
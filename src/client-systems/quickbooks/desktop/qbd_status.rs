@@ -0,0 +1,117 @@
+//! QuickBooks Desktop response status codes.
+//!
+//! Every `*Rs` element carries `statusCode`, `statusSeverity` (`Info`/`Warn`/`Error`) and
+//! `statusMessage`. `classify` maps the common codes to whether resending can succeed, a
+//! severity and a description, and decides what a page with that status means:
+//!   - `0`: OK
+//!   - `1` (no matching records): an empty page, i.e. success with nothing to apply
+//!   - warnings (e.g. `500`, some requested objects not found): the page is applied and the
+//!     warning is kept as a note on the sync event
+//!   - errors (e.g. `3200`, `3250`): the page fails and the event is marked Error
+//!
+//! Codes not listed fall back to QBD's own `statusSeverity`.
+
+/// BEGUN STRUCTS AND ENUMS ///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QbdStatusSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// What a response page with a given status amounts to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QbdStatusOutcome {
+    Success,
+    /// The query matched nothing; treated as an empty page.
+    NoRecords,
+    /// Applied, with the status kept as a note.
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QbdStatus {
+    pub code: String,
+    pub severity: QbdStatusSeverity,
+    /// Whether sending the same request again can succeed (e.g. a record locked by a user).
+    pub retryable: bool,
+    /// Our description of the code; QBD's `statusMessage` is reported alongside it.
+    pub message: &'static str,
+}
+
+/// Known codes: (code, severity, retryable, description).
+const KNOWN_STATUSES: &[(&str, QbdStatusSeverity, bool, &str)] = &[
+    ("0", QbdStatusSeverity::Info, false, "Status OK"),
+    ("1", QbdStatusSeverity::Info, false, "No matching records found"),
+    ("500", QbdStatusSeverity::Warning, false, "Some of the requested objects could not be found"),
+    ("1000", QbdStatusSeverity::Error, true, "QuickBooks had an internal error processing the request"),
+    ("3000", QbdStatusSeverity::Error, false, "The object ID in the request is invalid"),
+    ("3100", QbdStatusSeverity::Error, false, "The name is already in use"),
+    ("3120", QbdStatusSeverity::Error, false, "The object in the request could not be found"),
+    ("3140", QbdStatusSeverity::Error, false, "The request references an object that does not exist"),
+    ("3170", QbdStatusSeverity::Error, true, "The record could not be modified"),
+    ("3175", QbdStatusSeverity::Error, true, "The record is in use by another QuickBooks user"),
+    ("3180", QbdStatusSeverity::Error, true, "The record could not be saved"),
+    ("3200", QbdStatusSeverity::Error, false, "The request is invalid: the edit sequence is out of date"),
+    ("3250", QbdStatusSeverity::Error, false, "The feature is not enabled or not available in this QuickBooks version"),
+    ("3260", QbdStatusSeverity::Error, false, "The Web Connector user lacks permission for this request"),
+];
+
+/// BEGUN IMPLEMENTATION ///
+impl QbdStatusSeverity {
+    ///parses QBD's `statusSeverity` attribute; anything unrecognized counts as an error
+    pub fn from_attribute(severity: &str) -> Self {
+        match severity.trim().to_ascii_lowercase().as_str() {
+            "info" => QbdStatusSeverity::Info,
+            "warn" | "warning" => QbdStatusSeverity::Warning,
+            _ => QbdStatusSeverity::Error,
+        }
+    }
+}
+
+impl QbdStatus {
+    pub fn outcome(&self) -> QbdStatusOutcome {
+        match (self.code.as_str(), self.severity) {
+            ("1", _) => QbdStatusOutcome::NoRecords,
+            (_, QbdStatusSeverity::Info) => QbdStatusOutcome::Success,
+            (_, QbdStatusSeverity::Warning) => QbdStatusOutcome::Warning,
+            (_, QbdStatusSeverity::Error) => QbdStatusOutcome::Error,
+        }
+    }
+
+    ///one line for `last_error`/run messages: the code, our description and QBD's message
+    pub fn describe(&self, status_message: &str) -> String {
+        let status_message = status_message.trim();
+        if status_message.is_empty() || status_message == self.message {
+            format!("QBD status {}: {}", self.code, self.message)
+        } else {
+            format!("QBD status {}: {} ({})", self.code, self.message, status_message)
+        }
+    }
+}
+
+///maps a `statusCode` to its class; unknown codes use `severity` (QBD's `statusSeverity`)
+pub fn classify(code: &str, severity: &str) -> QbdStatus {
+    let code = code.trim();
+    match KNOWN_STATUSES.iter().find(|(known, ..)| *known == code) {
+        Some((_, severity, retryable, message)) => QbdStatus {
+            code: code.to_string(),
+            severity: *severity,
+            retryable: *retryable,
+            message,
+        },
+        None => QbdStatus {
+            code: code.to_string(),
+            severity: QbdStatusSeverity::from_attribute(severity),
+            retryable: false,
+            message: "Unrecognized QuickBooks status",
+        },
+    }
+}
+
+///whether `code` means QuickBooks refused the Web Connector user's access, which only a
+///re-authorization of the application in QuickBooks fixes
+pub fn needs_reauth(code: &str) -> bool {
+    code.trim() == "3260"
+}
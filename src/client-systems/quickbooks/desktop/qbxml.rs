@@ -0,0 +1,403 @@
+//! QBXML request builders and response parsers for QuickBooks Desktop item queries.
+//!
+//! All item queries (`ItemInventoryQuery`, `ItemServiceQuery`, `ItemNonInventoryQuery`)
+//! share the same iterator-based pagination, so the builders and the parser are driven
+//! by a `QbdItemType` rather than duplicated per query.
+
+use std::collections::HashMap;
+
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_json::Value;
+
+/// Items returned per QBXML page.
+pub const PAGE_SIZE: u32 = 50;
+
+/// Key in the sync event `details` JSON (and the cursor) selecting the item query.
+pub const ITEM_TYPE_KEY: &str = "item_type";
+
+/// qbXML version requested when a connection has none stored (or an unusable one), and the
+/// highest version requested at all: the queries built here don't need anything newer.
+pub const DEFAULT_QBXML_VERSION: &str = "13.0";
+
+// ── qbXML version ─────────────────────────────────────────────────────────────
+
+/// `major.minor` parsed from a version string such as `"6.0"` or `"13"`.
+fn parse_qbxml_version(version: &str) -> Option<(u32, u32)> {
+    let version = version.trim();
+    let (major, minor) = version.split_once('.').unwrap_or((version, "0"));
+    Some((major.parse().ok()?, minor.parse().ok()?))
+}
+
+/// The version to request from a QuickBooks install reporting `reported`: the reported
+/// version, capped at `DEFAULT_QBXML_VERSION`, as `major.minor`. None when `reported`
+/// isn't a version.
+pub fn negotiate_qbxml_version(reported: &str) -> Option<String> {
+    let reported = parse_qbxml_version(reported)?;
+    let supported = parse_qbxml_version(DEFAULT_QBXML_VERSION)?;
+    let (major, minor) = reported.min(supported);
+    Some(format!("{major}.{minor}"))
+}
+
+/// The `<?qbxml version?>` to send for a connection's stored `qbxml_version`, or
+/// `DEFAULT_QBXML_VERSION` when it has none (or an unusable one).
+pub fn request_qbxml_version(qbxml_version: Option<&str>) -> String {
+    qbxml_version
+        .and_then(parse_qbxml_version)
+        .map_or_else(|| DEFAULT_QBXML_VERSION.to_string(), |(major, minor)| format!("{major}.{minor}"))
+}
+
+// ── Item types ────────────────────────────────────────────────────────────────
+
+/// QBD item list a sync event pulls from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QbdItemType {
+    #[default]
+    Inventory,
+    Service,
+    NonInventory,
+}
+
+impl QbdItemType {
+    /// Value stored in `details.item_type` and on upserted records.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            QbdItemType::Inventory => "inventory",
+            QbdItemType::Service => "service",
+            QbdItemType::NonInventory => "non_inventory",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "inventory" => Some(QbdItemType::Inventory),
+            "service" => Some(QbdItemType::Service),
+            "non_inventory" => Some(QbdItemType::NonInventory),
+            _ => None,
+        }
+    }
+
+    /// Read `item_type` from a sync event's `details` (or a cursor); defaults to inventory.
+    pub fn from_details(details: Option<&Value>) -> Self {
+        details
+            .and_then(|d| d.get(ITEM_TYPE_KEY))
+            .and_then(|v| v.as_str())
+            .and_then(Self::parse)
+            .unwrap_or_default()
+    }
+
+    fn query_name(&self) -> &'static str {
+        match self {
+            QbdItemType::Inventory => "ItemInventoryQuery",
+            QbdItemType::Service => "ItemServiceQuery",
+            QbdItemType::NonInventory => "ItemNonInventoryQuery",
+        }
+    }
+
+    fn ret_tag(&self) -> &'static str {
+        match self {
+            QbdItemType::Inventory => "ItemInventoryRet",
+            QbdItemType::Service => "ItemServiceRet",
+            QbdItemType::NonInventory => "ItemNonInventoryRet",
+        }
+    }
+}
+
+// ── Parsed types ──────────────────────────────────────────────────────────────
+
+pub struct ParsedInventoryResponse {
+    pub iterator_id: Option<String>,
+    /// Items remaining after this page; 0 means pagination is complete.
+    pub remaining_count: i64,
+    pub status_code: String,
+    /// `statusSeverity`: `Info`, `Warn` or `Error` (see `qbd_status`).
+    pub status_severity: String,
+    pub status_message: String,
+    pub items: Vec<QbdInventoryItem>,
+}
+
+impl ParsedInventoryResponse {
+    /// QBD refused `iterator="Continue"` because the iterator expired or is unknown (it only
+    /// lives while the company file session does). The status message names the iterator.
+    pub fn is_iterator_expired(&self) -> bool {
+        let message = self.status_message.to_ascii_lowercase();
+        self.status_code != "0"
+            && message.contains("iterator")
+            && ["invalid", "expired", "not found", "does not exist"]
+                .iter()
+                .any(|reason| message.contains(reason))
+    }
+}
+
+pub struct QbdInventoryItem {
+    pub item_type: QbdItemType,
+    /// QBD ListID — used as the `system_id` (or the field the connection's `field_mapping`
+    /// names, once `QbdFieldMapping::apply` has run).
+    pub list_id: String,
+    pub name: Option<String>,
+    pub full_name: Option<String>,
+    /// Sales price converted to integer cents.
+    pub sales_price_cents: Option<i32>,
+    /// Only inventory items track quantity. Whole units; a fractional quantity (items sold
+    /// by weight, e.g. `10.5`) is truncated here and kept exactly in `qty_on_hand_milli`.
+    pub qty_on_hand: Option<i32>,
+    /// Quantity in thousandths, e.g. `10.5` → `10500`.
+    pub qty_on_hand_milli: Option<i64>,
+    pub sales_desc: Option<String>,
+    pub manufacturer_part_number: Option<String>,
+    /// All parsed fields as a JSON blob stored in `original_record_body`.
+    /// Nested elements are keyed by path, e.g. `SalesOrPurchase.Price`.
+    pub raw: Value,
+}
+
+// ── QBXML builders ────────────────────────────────────────────────────────────
+
+/// Build an item query request for `item_type`.
+///
+/// Uses `iterator="Continue" iteratorID="..."` when continuing from a stored cursor's
+/// `iterator_id`, otherwise `iterator="Start"`. The `<?qbxml version?>` header is the connection's
+/// `qbxml_version`, or `DEFAULT_QBXML_VERSION` when it has none (or an unusable one).
+pub fn build_item_query_xml(
+    item_type: QbdItemType,
+    iterator_id: Option<&str>,
+    qbxml_version: Option<&str>,
+) -> String {
+    let version = request_qbxml_version(qbxml_version);
+
+    let rq = format!("{}Rq", item_type.query_name());
+    let iterator = match iterator_id {
+        None => r#"iterator="Start""#.to_string(),
+        Some(id) => format!(r#"iterator="Continue" iteratorID="{id}""#),
+    };
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<?qbxml version="{version}"?>
+<QBXML>
+  <QBXMLMsgsRq onError="stopOnError">
+    <{rq} requestID="1" {iterator} maxReturned="{ps}">
+    </{rq}>
+  </QBXMLMsgsRq>
+</QBXML>"#,
+        ps = PAGE_SIZE
+    )
+}
+
+/// Build an `ItemInventoryQueryRq`.
+pub fn build_item_inventory_query_xml(iterator_id: Option<&str>, qbxml_version: Option<&str>) -> String {
+    build_item_query_xml(QbdItemType::Inventory, iterator_id, qbxml_version)
+}
+
+/// Build an `ItemServiceQueryRq`.
+pub fn build_item_service_query_xml(iterator_id: Option<&str>, qbxml_version: Option<&str>) -> String {
+    build_item_query_xml(QbdItemType::Service, iterator_id, qbxml_version)
+}
+
+/// Build an `ItemNonInventoryQueryRq`.
+pub fn build_item_non_inventory_query_xml(
+    iterator_id: Option<&str>,
+    qbxml_version: Option<&str>,
+) -> String {
+    build_item_query_xml(QbdItemType::NonInventory, iterator_id, qbxml_version)
+}
+
+// ── XML parser ────────────────────────────────────────────────────────────────
+
+/// Parse a QBD `ItemInventoryQueryRs` QBXML response.
+pub fn parse_inventory_response(xml: &str) -> Result<ParsedInventoryResponse, String> {
+    parse_item_query_response(xml, QbdItemType::Inventory)
+}
+
+/// Parse a QBD `ItemServiceQueryRs` QBXML response.
+pub fn parse_item_service_response(xml: &str) -> Result<ParsedInventoryResponse, String> {
+    parse_item_query_response(xml, QbdItemType::Service)
+}
+
+/// Parse a QBD `ItemNonInventoryQueryRs` QBXML response.
+pub fn parse_item_non_inventory_response(xml: &str) -> Result<ParsedInventoryResponse, String> {
+    parse_item_query_response(xml, QbdItemType::NonInventory)
+}
+
+/// Parse the `<{Query}Rs>` response for `item_type`.
+pub fn parse_item_query_response(
+    xml: &str,
+    item_type: QbdItemType,
+) -> Result<ParsedInventoryResponse, String> {
+    let rs_tag = format!("{}Rs", item_type.query_name());
+    let ret_tag = item_type.ret_tag();
+
+    let mut reader = Reader::from_str(xml);
+    let mut buf = Vec::new();
+
+    let mut iterator_id: Option<String> = None;
+    let mut remaining_count: i64 = 0;
+    let mut status_code = "0".to_string();
+    let mut status_severity = "Info".to_string();
+    let mut status_message = String::new();
+    let mut items: Vec<QbdInventoryItem> = Vec::new();
+
+    let mut in_item = false;
+    // Element path below the `*Ret` element, so nested refs (e.g. IncomeAccountRef/ListID)
+    // don't overwrite the item's own fields.
+    let mut path: Vec<String> = Vec::new();
+    let mut current_data: HashMap<String, String> = HashMap::new();
+
+    loop {
+        buf.clear();
+        match reader.read_event_into(&mut buf) {
+            // A response without items (e.g. status 1, no matching records) is self-closing.
+            Ok(Event::Start(ref e)) | Ok(Event::Empty(ref e))
+                if e.name().as_ref() == rs_tag.as_bytes() =>
+            {
+                for attr in e.attributes().flatten() {
+                    let key =
+                        String::from_utf8_lossy(attr.key.as_ref()).to_string();
+                    let val =
+                        String::from_utf8_lossy(attr.value.as_ref()).to_string();
+                    match key.as_str() {
+                        "iteratorID" => iterator_id = Some(val),
+                        "iteratorRemainingCount" => {
+                            remaining_count = val.parse().unwrap_or(0);
+                        }
+                        "statusCode" => status_code = val,
+                        "statusSeverity" => status_severity = val,
+                        "statusMessage" => status_message = val,
+                        _ => {}
+                    }
+                }
+            }
+
+            Ok(Event::Start(ref e)) => {
+                let name =
+                    String::from_utf8_lossy(e.name().as_ref()).to_string();
+
+                if name == ret_tag {
+
+                    in_item = true;
+                    current_data.clear();
+                    path.clear();
+                } else if in_item {
+                    path.push(name);
+                }
+            }
+
+            Ok(Event::End(ref e)) => {
+                let name =
+                    String::from_utf8_lossy(e.name().as_ref()).to_string();
+
+                if name == ret_tag {
+                    in_item = false;
+                    path.clear();
+                    if let Some(item) = build_item(item_type, &current_data) {
+                        items.push(item);
+                    }
+                    current_data.clear();
+                } else if in_item {
+                    path.pop();
+                }
+            }
+
+            Ok(Event::Text(ref e)) if in_item => {
+                if let (false, Ok(text)) = (path.is_empty(), e.unescape()) {
+                    let text = text.trim().to_string();
+                    if !text.is_empty() {
+                        current_data.insert(path.join("."), text);
+                    }
+                }
+            }
+
+            Ok(Event::Eof) => break,
+            Err(e) => return Err(format!("{e}")),
+            _ => {}
+        }
+    }
+
+    Ok(ParsedInventoryResponse {
+        iterator_id,
+        remaining_count,
+        status_code,
+        status_severity,
+        status_message,
+        items,
+    })
+}
+
+/// Rebuild an item from a stored `original_record_body` (the `raw` blob of a past parse),
+/// re-running the field mapping without QBD. None if the blob has no ListID.
+pub fn item_from_raw(raw: &Value) -> Option<QbdInventoryItem> {
+    let fields = raw.as_object()?;
+    let item_type = QbdItemType::from_details(Some(raw));
+    let data: HashMap<String, String> = fields
+        .iter()
+        .filter(|(k, _)| k.as_str() != ITEM_TYPE_KEY)
+        .filter_map(|(k, v)| v.as_str().map(|v| (k.clone(), v.to_string())))
+        .collect();
+
+    let mut item = build_item(item_type, &data)?;
+    item.raw = raw.clone();
+    Some(item)
+}
+
+/// Convert a QBD amount (e.g. `19.99`) to integer cents.
+pub fn normalize_price(value: &str) -> Option<i32> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .map(|p| (p * 100.0).round() as i32)
+}
+
+/// Convert a QBD quantity (e.g. `10.5`) to integer thousandths.
+pub fn normalize_qty(value: &str) -> Option<i64> {
+    value
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|q| q.is_finite())
+        .map(|q| (q * 1000.0).round() as i64)
+}
+
+/// Map the flattened fields of one `*Ret` element onto a `QbdInventoryItem`.
+///
+/// Service and non-inventory items carry price/description under either
+/// `SalesOrPurchase` or `SalesAndPurchase`.
+fn build_item(item_type: QbdItemType, data: &HashMap<String, String>) -> Option<QbdInventoryItem> {
+    let list_id = data.get("ListID").cloned()?;
+    let first = |keys: &[&str]| keys.iter().find_map(|k| data.get(*k).cloned());
+
+    let price_cents = first(&[
+        "SalesPrice",
+        "SalesOrPurchase.Price",
+        "SalesAndPurchase.SalesPrice",
+    ])
+    .and_then(|p| normalize_price(&p));
+
+    let qty_milli = match item_type {
+        QbdItemType::Inventory => data.get("QuantityOnHand").and_then(|q| normalize_qty(q)),
+        _ => None,
+    };
+
+    let mut raw = data
+        .iter()
+        .fold(serde_json::Map::new(), |mut m, (k, v)| {
+            m.insert(k.clone(), Value::String(v.clone()));
+            m
+        });
+    raw.insert(
+        ITEM_TYPE_KEY.to_string(),
+        Value::String(item_type.as_str().to_string()),
+    );
+
+    Some(QbdInventoryItem {
+        item_type,
+        list_id,
+        name: data.get("Name").cloned(),
+        full_name: data.get("FullName").cloned(),
+        sales_price_cents: price_cents,
+        qty_on_hand: qty_milli.and_then(|q| i32::try_from(q / 1000).ok()),
+        qty_on_hand_milli: qty_milli,
+        sales_desc: first(&["SalesDesc", "SalesOrPurchase.Desc", "SalesAndPurchase.SalesDesc"]),
+        manufacturer_part_number: data.get("ManufacturerPartNumber").cloned(),
+        raw: raw.into(),
+    })
+}
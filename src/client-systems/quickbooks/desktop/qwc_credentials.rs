@@ -0,0 +1,91 @@
+//! Usernames and passwords generated for QuickBooks Web Connector (QBWC) connections.
+//!
+//! Some QuickBooks versions refuse Web Connector passwords that are short or use a single
+//! character class, so generated passwords follow a `PasswordPolicy` (`QBWC_PASSWORD_MIN_LENGTH`,
+//! `QBWC_PASSWORD_MIXED_CHARSET`) and are checked against it before they are handed out.
+//! Usernames are `QBWC_USERNAME_PREFIX` followed by a random suffix. Both only use ASCII letters
+//! and digits (plus `_`/`-` in the prefix), so they need no escaping in the .qwc file or the
+//! SOAP calls.
+
+use uuid::Uuid;
+
+/// Username prefix when `QBWC_USERNAME_PREFIX` is unset.
+pub const DEFAULT_USERNAME_PREFIX: &str = "pro_portals_";
+
+/// Bounds of the configurable password length.
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+pub const MAX_PASSWORD_LENGTH: usize = 64;
+
+const PASSWORD_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// What a generated Web Connector password must satisfy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordPolicy {
+    /// Length of generated passwords, within `MIN_PASSWORD_LENGTH..=MAX_PASSWORD_LENGTH`.
+    pub min_length: usize,
+    /// Require at least one lowercase letter, one uppercase letter and one digit.
+    pub mixed_charset: bool,
+}
+
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl PasswordPolicy {
+    ///`min_length` is clamped to the lengths QBWC accepts
+    pub fn new(min_length: usize, mixed_charset: bool) -> Self {
+        Self {
+            min_length: min_length.clamp(MIN_PASSWORD_LENGTH, MAX_PASSWORD_LENGTH),
+            mixed_charset,
+        }
+    }
+
+    ///whether `password` may be handed to the Web Connector under this policy
+    pub fn is_satisfied_by(&self, password: &str) -> bool {
+        let length = password.chars().count();
+        if length < self.min_length
+            || length > MAX_PASSWORD_LENGTH
+            || !password.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return false;
+        }
+        !self.mixed_charset
+            || (password.chars().any(|c| c.is_ascii_lowercase())
+                && password.chars().any(|c| c.is_ascii_uppercase())
+                && password.chars().any(|c| c.is_ascii_digit()))
+    }
+
+    ///a random password satisfying the policy; candidates missing a character class are
+    ///drawn again
+    pub fn generate(&self) -> String {
+        loop {
+            let password = random_alphanumeric(self.min_length);
+            if self.is_satisfied_by(&password) {
+                return password;
+            }
+        }
+    }
+}
+
+///`prefix` followed by a random 32-character hex suffix
+pub fn generate_username(prefix: &str) -> String {
+    format!("{}{}", prefix, Uuid::new_v4().simple())
+}
+
+///`length` characters drawn uniformly from `PASSWORD_ALPHABET`, using the random bytes of v4
+///UUIDs (the version and variant bytes are skipped)
+fn random_alphanumeric(length: usize) -> String {
+    //largest multiple of the alphabet size that fits a byte, so `byte % size` stays uniform
+    let limit = (256 / PASSWORD_ALPHABET.len() * PASSWORD_ALPHABET.len()) as u8;
+    let mut password = String::with_capacity(length);
+    while password.len() < length {
+        let bytes = Uuid::new_v4().into_bytes();
+        for (i, byte) in bytes.into_iter().enumerate() {
+            if i == 6 || i == 8 || byte >= limit || password.len() == length {
+                continue;
+            }
+            password.push(PASSWORD_ALPHABET[byte as usize % PASSWORD_ALPHABET.len()] as char);
+        }
+    }
+    password
+}
@@ -2,36 +2,49 @@
 //!
 //! Credential / .qwc generation:
 //!   POST /client-systems/quickbooks/desktop/qwc
+//!   GET  /client-systems/quickbooks/desktop/qwc/{tenant_id} — download the existing .qwc again
 //!
 //! Poll cycle (mounted at /poll/v1 in the main router):
 //!   POST /poll/v1/qbwc         — request phase: returns QBXML for QBD to execute
 //!   POST /poll/v1/qbwc/receive — response phase: processes QBD response, upserts records
+//!   GET  /poll/v1/healthz      — stuck / backed-off / needs-reauth Desktop connections
 
 use axum::{
-    extract::State,
+    extract::{Extension, Path, State},
     http::{header, StatusCode},
-    response::IntoResponse,
-    routing::post,
+    response::{IntoResponse, Response},
+    routing::{get, post},
     Json, Router,
 };
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::client_systems::quickbooks::desktop::poll_health::QbdPollHealthService;
 use crate::client_systems::quickbooks::desktop::poll_services::{
-    PollResponseInput, PollResponseOutput, QbdPollError, QbdPollService,
+    PollResponseInput, PollResponseOutput, QbdPollService,
 };
-use crate::client_systems::quickbooks::desktop::services::{generate_qwc, QbdDesktopError};
+use crate::client_systems::quickbooks::desktop::services::{
+    existing_qwc, generate_qwc, QbdDesktopError,
+};
+use crate::config;
+use crate::erp_connection_credentials;
+use crate::security::{AuthenticatedConnection, AuthenticatedTenant};
+use crate::tenant::default::tenant_id_or_default;
+use crate::sync::RetryPolicy;
 use crate::AppState;
 
 // ── .qwc generation ───────────────────────────────────────────────────────────
 
 #[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct GenerateQwcRequest {
-    /// If omitted, a new tenant is created and used.
+    /// If omitted, the server's default tenant (`DEFAULT_TENANT_ID`) is used; without one, a
+    /// new tenant is created and used.
     pub tenant_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct GenerateQwcResponse {
     pub tenant_id: String,
     pub password: String,
@@ -44,6 +57,7 @@ pub struct GenerateQwcResponse {
 }
 
 #[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct GenerateQwcErrorResponse {
     pub error: String,
 }
@@ -56,14 +70,22 @@ pub struct GenerateQwcErrorResponse {
     responses(
         (status = 200, description = "QWC file and credentials", body = GenerateQwcResponse),
         (status = 404, description = "Tenant not found", body = GenerateQwcErrorResponse),
+        (status = 409, description = "A connection for this company file, or its credentials, already exists", body = GenerateQwcErrorResponse),
         (status = 500, description = "Internal server error", body = GenerateQwcErrorResponse)
     )
 )]
 pub async fn generate_qwc_handler(
     State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
     Json(body): Json<GenerateQwcRequest>,
 ) -> Result<Json<GenerateQwcResponse>, (StatusCode, Json<GenerateQwcErrorResponse>)> {
-    let out = generate_qwc(&state.db, body.tenant_id.as_deref())
+    let tenant_scoped = matches!(tenant, Some(Extension(AuthenticatedTenant(Some(_)))));
+    let tenant_id = tenant_id_or_default(
+        body.tenant_id.as_deref(),
+        config::env::get().tenant.default_tenant_id.as_deref(),
+        tenant_scoped,
+    );
+    let out = generate_qwc(&state.db, tenant_id)
         .await
         .map_err(|e| {
             (
@@ -82,19 +104,78 @@ pub async fn generate_qwc_handler(
     }))
 }
 
+/// File name the downloaded .qwc is saved as.
+pub const QWC_DOWNLOAD_FILENAME: &str = "Pro_Portals_ERP_Connector.qwc";
+
+#[utoipa::path(
+    get,
+    path = "/qwc/{tenant_id}",
+    tag = "QuickBooks Desktop",
+    params(
+        ("tenant_id" = String, Path, description = "Public tenant id (TN_xxx)")
+    ),
+    responses(
+        (status = 200, description = "The tenant's existing .qwc file, rebuilt from the stored username and file id", content_type = "application/octet-stream", body = String),
+        (status = 404, description = "Tenant not found, or it has no QuickBooks Desktop connection with Web Connector credentials", body = GenerateQwcErrorResponse),
+        (status = 500, description = "Internal server error", body = GenerateQwcErrorResponse)
+    )
+)]
+pub async fn download_qwc_handler(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    Path(tenant_id): Path<String>,
+) -> Result<Response, (StatusCode, Json<GenerateQwcErrorResponse>)> {
+    let qwc_error = |e: QbdDesktopError| {
+        (
+            e.status_code(),
+            Json(GenerateQwcErrorResponse {
+                error: e.message(),
+            }),
+        )
+    };
+    let qwc = existing_qwc(&state.db, &tenant_id).await.map_err(qwc_error)?;
+
+    //a tenant-scoped API token only sees its own tenant
+    if let Some(Extension(AuthenticatedTenant(Some(scoped)))) = tenant
+        && scoped != qwc.tenant_db_id
+    {
+        return Err(qwc_error(QbdDesktopError::TenantNotFound));
+    }
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", QWC_DOWNLOAD_FILENAME),
+            ),
+        ],
+        qwc.qwc_xml,
+    )
+        .into_response())
+}
+
 pub fn create_router() -> Router<AppState> {
-    Router::new().route("/qwc", post(generate_qwc_handler))
+    Router::new()
+        .route("/qwc", post(generate_qwc_handler))
+        .route("/qwc/{tenant_id}", get(download_qwc_handler))
 }
 
 // ── Poll: request phase ───────────────────────────────────────────────────────
 
 #[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct QbdPollRequestBody {
     pub username: String,
     pub password: String,
+    /// qbXML version QuickBooks reported to the Web Connector (`qbXMLMajorVers`.
+    /// `qbXMLMinorVers` of sendRequestXML), e.g. `"13.0"`. Stored on the connection and used
+    /// for the queries' version header; the stored (or default) version is kept when omitted.
+    pub qbxml_version: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct QbdPollRequestResponse {
     pub has_work: bool,
     /// QBXML to execute against QuickBooks Desktop. Null when has_work is false.
@@ -106,32 +187,35 @@ pub struct QbdPollRequestResponse {
 /// Called by the QBWC adapter on each poll cycle.
 /// Returns credentials-validated QBXML to execute against QuickBooks Desktop,
 /// along with UUIDs that must be echoed back in the /receive call.
+/// Failures answer with the same JSON envelope as /receive (`QbdPollError::to_response`).
+/// Under a connection API key, only that connection's credentials are accepted.
 pub async fn qbwc_request_handler(
     State(state): State<AppState>,
+    scope: Option<Extension<AuthenticatedConnection>>,
     Json(body): Json<QbdPollRequestBody>,
 ) -> impl IntoResponse {
-    let svc = QbdPollService::new(state.db.clone());
-    match svc.handle_request(&body.username, &body.password).await {
+    let svc = QbdPollService::new(state.db.clone())
+        .with_connection_scope(scope.map(|Extension(key)| key.connection_id))
+        .with_required_environment(config::env::get().sync.required_environment.clone())
+        .with_secret_resolver(erp_connection_credentials::secret_resolver())
+        .with_credential_cache(erp_connection_credentials::lookup_cache::shared());
+    match svc
+        .handle_request(&body.username, &body.password, body.qbxml_version.as_deref())
+        .await
+    {
         Ok(out) => Json(QbdPollRequestResponse {
             has_work: out.has_work,
             xml: out.xml,
         })
         .into_response(),
-        Err(QbdPollError::Unauthorized) => StatusCode::FORBIDDEN.into_response(),
-        Err(QbdPollError::Db(e)) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            format!("Database error: {e}"),
-        )
-            .into_response(),
-        Err(QbdPollError::XmlParse(e)) => {
-            (StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
-        }
+        Err(e) => e.to_response().into_response(),
     }
 }
 
 // ── Poll: response phase ──────────────────────────────────────────────────────
 
 #[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct QbdPollReceiveBody {
     pub username: String,
     pub password: String,
@@ -139,13 +223,19 @@ pub struct QbdPollReceiveBody {
     pub qbd_response_xml: Option<String>,
     /// Error message from QBD (when QBD returned an error instead of XML).
     pub qbd_error: Option<String>,
+    /// Parse and summarize qbd_response_xml without writing anything (diagnostics).
+    /// The summary is returned as JSON in `message`.
+    pub dry_run: Option<bool>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct QbdPollReceiveResponse {
     pub success: bool,
     /// True when QBWC should call sendRequestXML again immediately (more pages).
     /// Maps to QBWC's receiveResponseXML integer: 100 = keep going, 0 = done.
+    /// Error responses (`QbdPollErrorResponse`) carry no `has_more`; adapters should
+    /// return a negative integer to QBWC so it reports the error and stops the session.
     pub has_more: bool,
     pub message: Option<String>,
 }
@@ -155,45 +245,97 @@ pub struct QbdPollReceiveResponse {
 /// Called after QuickBooks Desktop executes the query and returns data.
 /// Processes the response: upserts inventory records, updates the cursor,
 /// and marks the sync event back to Pending (list) or Success (other).
+/// With `dry_run: true` nothing is written; `message` carries a JSON summary of what
+/// the response would have done. Failures answer with `QbdPollError::to_response`.
 pub async fn qbwc_receive_handler(
     State(state): State<AppState>,
+    scope: Option<Extension<AuthenticatedConnection>>,
     Json(body): Json<QbdPollReceiveBody>,
 ) -> impl IntoResponse {
-    let svc = QbdPollService::new(state.db.clone());
+    let svc = QbdPollService::new(state.db.clone())
+        .with_connection_scope(scope.map(|Extension(key)| key.connection_id))
+        .with_retry_policy(RetryPolicy::new(config::env::get().sync.transient_retries))
+        .with_required_environment(config::env::get().sync.required_environment.clone())
+        .with_secret_resolver(erp_connection_credentials::secret_resolver())
+        .with_credential_cache(erp_connection_credentials::lookup_cache::shared())
+        .with_event_bus(Some(state.events.clone()));
     // Extract credentials before moving other fields into PollResponseInput.
     let username = body.username;
     let password = body.password;
     let input = PollResponseInput {
         qbd_response_xml: body.qbd_response_xml,
         qbd_error: body.qbd_error,
+        dry_run: body.dry_run.unwrap_or(false),
     };
 
     match svc.handle_response(&username, &password, input).await {
-        Ok(PollResponseOutput { has_more }) => Json(QbdPollReceiveResponse {
+        Ok(PollResponseOutput { has_more, dry_run }) => Json(QbdPollReceiveResponse {
             success: true,
             has_more,
-            message: None,
+            message: dry_run.and_then(|summary| serde_json::to_string(&summary).ok()),
         })
         .into_response(),
-        Err(QbdPollError::Unauthorized) => StatusCode::FORBIDDEN.into_response(),
-        Err(QbdPollError::Db(e)) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(QbdPollReceiveResponse {
-                success: false,
-                has_more: false,
-                message: Some(format!("Database error: {e}")),
-            }),
-        )
-            .into_response(),
-        Err(QbdPollError::XmlParse(e)) => (
-            StatusCode::UNPROCESSABLE_ENTITY,
-            Json(QbdPollReceiveResponse {
-                success: false,
-                has_more: false,
-                message: Some(e),
-            }),
-        )
-            .into_response(),
+        Err(e) => e.to_response().into_response(),
+    }
+}
+
+// ── Poll: health ──────────────────────────────────────────────────────────────
+
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct QbdPollHealthResponse {
+    /// `ok`, `degraded` (too many stuck connections) or `error` (the check itself failed).
+    pub status: String,
+    /// Desktop connections with an InProgress sync event older than `stuck_after_seconds`.
+    pub stuck_connections: u64,
+    pub stuck_events: u64,
+    /// Desktop connections whose pulls are backing off after failures.
+    pub backed_off_connections: u64,
+    pub needs_reauth_connections: u64,
+    pub stuck_after_seconds: i64,
+    /// Stuck connections tolerated before the status is `degraded`.
+    pub max_stuck_connections: u64,
+}
+
+/// GET /poll/v1/healthz
+///
+/// Health of the QBD poll pipeline: 200 while at most `max_stuck_connections` connections
+/// have a stuck InProgress event, 503 otherwise (or when the check can't read the database).
+/// Backed-off and needs-reauth counts are informational and never fail the probe.
+pub async fn qbd_poll_health_handler(
+    State(state): State<AppState>,
+) -> (StatusCode, Json<QbdPollHealthResponse>) {
+    let svc = QbdPollHealthService::new(state.db.clone());
+    let mut response = QbdPollHealthResponse {
+        status: "error".to_string(),
+        stuck_connections: 0,
+        stuck_events: 0,
+        backed_off_connections: 0,
+        needs_reauth_connections: 0,
+        stuck_after_seconds: svc.stuck_after().num_seconds(),
+        max_stuck_connections: svc.max_stuck(),
+    };
+
+    match svc.check().await {
+        Ok(health) => {
+            response.status = match health.degraded {
+                true => "degraded".to_string(),
+                false => "ok".to_string(),
+            };
+            response.stuck_connections = health.stuck_connections;
+            response.stuck_events = health.stuck_events;
+            response.backed_off_connections = health.backed_off_connections;
+            response.needs_reauth_connections = health.needs_reauth_connections;
+            let status = match health.degraded {
+                true => StatusCode::SERVICE_UNAVAILABLE,
+                false => StatusCode::OK,
+            };
+            (status, Json(response))
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "QBD poll health check failed");
+            (StatusCode::SERVICE_UNAVAILABLE, Json(response))
+        }
     }
 }
 
@@ -203,4 +345,5 @@ pub fn create_poll_router() -> Router<AppState> {
     Router::new()
         .route("/qbwc", post(qbwc_request_handler))
         .route("/qbwc/receive", post(qbwc_receive_handler))
+        .route("/healthz", get(qbd_poll_health_handler))
 }
@@ -4,31 +4,76 @@ use base64::Engine;
 use entity::connection_identity;
 use entity::erp_connection_credentials;
 use entity::sea_orm_active_enums::{
-    ErpEnvironment, ErpProvider, ErpProviderAuthType, ErpProviderType,
+    ErpConnectionStatus, ErpEnvironment, ErpProvider, ErpProviderAuthType, ErpProviderType,
 };
 use sea_orm::{
     ActiveModelTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait, Set,
+    TransactionTrait,
 };
 use uuid::Uuid;
 
-use crate::connection_identity::services::{ConnectionIdentityService, CreateConnectionIdentity};
+use crate::connection_identity::services::{
+    ConnectionIdentityError, ConnectionIdentityService, CreateConnectionIdentity,
+};
 use crate::erp_connection_credentials::services::{
-    CreateErpConnectionCredentials, ErpConnectionCredentialsService,
+    CreateErpConnectionCredentials, ErpConnectionCredentialsError, ErpConnectionCredentialsService,
 };
+use crate::config;
+use crate::db::unique::UniqueViolation;
+use crate::erp_connection_credentials::codec::CredentialCodec;
 use crate::tenant::services::TenantService;
 
+use super::qwc_credentials::{self, PasswordPolicy};
+
 /// Template is read at compile time so we never overwrite it.
 const QWC_TEMPLATE: &str = include_str!("./QBD_QBWC_TEMPLATE.qwc");
 
 #[derive(Debug)]
 pub enum QbdDesktopError {
     TenantNotFound,
+    /// The tenant has no QuickBooks Desktop connection with Web Connector credentials.
+    ConnectionNotFound,
+    /// A duplicate company file, tenant or credentials row; the message says which.
+    Conflict(String),
     Db(DbErr),
 }
 
 impl From<DbErr> for QbdDesktopError {
     fn from(err: DbErr) -> Self {
-        QbdDesktopError::Db(err)
+        match UniqueViolation::from_db_err(&err) {
+            Some(violation) => QbdDesktopError::Conflict(violation.message()),
+            None => QbdDesktopError::Db(err),
+        }
+    }
+}
+
+impl From<ConnectionIdentityError> for QbdDesktopError {
+    fn from(err: ConnectionIdentityError) -> Self {
+        match err {
+            ConnectionIdentityError::Conflict(_) => QbdDesktopError::Conflict(
+                "A QuickBooks Desktop connection with this company file already exists".to_string(),
+            ),
+            ConnectionIdentityError::NotFound => {
+                QbdDesktopError::Db(DbErr::RecordNotFound("connection_identity".to_string()))
+            }
+            ConnectionIdentityError::Db(e) => QbdDesktopError::Db(e),
+        }
+    }
+}
+
+impl From<ErpConnectionCredentialsError> for QbdDesktopError {
+    fn from(err: ErpConnectionCredentialsError) -> Self {
+        match err {
+            ErpConnectionCredentialsError::NotFound => QbdDesktopError::Db(DbErr::RecordNotFound(
+                "erp_connection_credentials".to_string(),
+            )),
+            ErpConnectionCredentialsError::MissingCredential(missing) => {
+                QbdDesktopError::Db(DbErr::Custom(missing.message()))
+            }
+            ErpConnectionCredentialsError::Codec(e) => QbdDesktopError::Db(DbErr::Custom(e.message())),
+            ErpConnectionCredentialsError::Conflict(message) => QbdDesktopError::Conflict(message),
+            ErpConnectionCredentialsError::Db(e) => QbdDesktopError::Db(e),
+        }
     }
 }
 
@@ -36,7 +81,10 @@ impl QbdDesktopError {
     /// HTTP status for this error.
     pub fn status_code(&self) -> axum::http::StatusCode {
         match self {
-            QbdDesktopError::TenantNotFound => axum::http::StatusCode::NOT_FOUND,
+            QbdDesktopError::TenantNotFound | QbdDesktopError::ConnectionNotFound => {
+                axum::http::StatusCode::NOT_FOUND
+            }
+            QbdDesktopError::Conflict(_) => axum::http::StatusCode::CONFLICT,
             QbdDesktopError::Db(_) => axum::http::StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -45,6 +93,11 @@ impl QbdDesktopError {
     pub fn message(&self) -> String {
         match self {
             QbdDesktopError::TenantNotFound => "Tenant not found".to_string(),
+            QbdDesktopError::ConnectionNotFound => {
+                "No QuickBooks Desktop connection with Web Connector credentials for this tenant"
+                    .to_string()
+            }
+            QbdDesktopError::Conflict(message) => message.clone(),
             QbdDesktopError::Db(e) => format!("Database error: {}", e),
         }
     }
@@ -59,6 +112,15 @@ pub struct QwcResult {
     pub qwc_xml: String,
 }
 
+/// A .qwc rebuilt from a connection's stored username and file id.
+pub struct ExistingQwc {
+    /// DB id of the tenant owning the connection.
+    pub tenant_db_id: i64,
+    pub username: String,
+    pub file_id: String,
+    pub qwc_xml: String,
+}
+
 /// Output for the generate-qwc API: all fields needed for the JSON response.
 pub struct GenerateQwcOutput {
     pub tenant_id: String,
@@ -98,14 +160,19 @@ pub async fn ensure_tenant(
     }
 }
 
-/// Generates a random username with prefix `pro_portals_`.
+/// Generates a random username with the `QBWC_USERNAME_PREFIX` prefix.
 fn random_username() -> String {
-    format!("pro_portals_{}", Uuid::new_v4().simple())
+    qwc_credentials::generate_username(&config::env::get().credentials.qbwc_username_prefix)
 }
 
-/// Generates a random password (alphanumeric-friendly for Web Connector).
+/// Generates a random password meeting the configured QBWC password policy.
 fn random_password() -> String {
-    Uuid::new_v4().simple().to_string()
+    let credentials = &config::env::get().credentials;
+    PasswordPolicy::new(
+        credentials.qbwc_password_min_length,
+        credentials.qbwc_password_mixed_charset,
+    )
+    .generate()
 }
 
 /// Generates a random file id (GUID format for QBWC).
@@ -113,13 +180,14 @@ fn random_file_id() -> String {
     Uuid::new_v4().to_string()
 }
 
-/// Finds an existing QuickBooks Desktop connection for the tenant, if any.
+/// Finds the tenant's active QuickBooks Desktop connection, if any, with its credentials.
+/// Connections that already have Web Connector credentials are preferred.
 async fn find_qbd_connection(
     db: &DatabaseConnection,
     tenant_db_id: i64,
     txn: Option<&DatabaseTransaction>,
 ) -> Result<
-    Option<(connection_identity::Model, erp_connection_credentials::Model)>,
+    Option<(connection_identity::Model, Option<erp_connection_credentials::Model>)>,
     DbErr,
 > {
     let conn_svc = ConnectionIdentityService::new(db.clone());
@@ -127,36 +195,77 @@ async fn find_qbd_connection(
         .get_by_tenant_id(tenant_db_id, txn)
         .await?;
     let cred_svc = ErpConnectionCredentialsService::new(db.clone());
+    let mut fallback = None;
     for conn in connections {
-        if conn.erp_provider != ErpProvider::Quickbooks || conn.erp_type != ErpProviderType::Desktop
+        if conn.erp_provider != ErpProvider::Quickbooks
+            || conn.erp_type != ErpProviderType::Desktop
+            || conn.status != ErpConnectionStatus::Active
         {
             continue;
         }
-        if let Some(creds) = cred_svc.get_by_connection_id(conn.id, txn).await? {
-            if creds.provider_user_id.is_some() && creds.provider_password.is_some() {
-                return Ok(Some((conn, creds)));
-            }
+        let creds = cred_svc.get_by_connection_id(conn.id, txn).await?;
+        if creds
+            .as_ref()
+            .is_some_and(|c| c.provider_user_id.is_some() && c.provider_password.is_some())
+        {
+            return Ok(Some((conn, creds)));
+        }
+        if fallback.is_none() {
+            fallback = Some((conn, creds));
         }
     }
-    Ok(None)
+    Ok(fallback)
 }
 
 /// Gets or creates QuickBooks Desktop credentials for the tenant.
 /// Returns (tenant_id_string, username, password, file_id, qwc_xml).
+/// Without `txn` the connection and its credentials are written in a transaction of their own,
+/// so a failed credentials insert never leaves a connection without credentials behind.
 pub async fn get_or_create_qbd_credentials_and_qwc(
     db: &DatabaseConnection,
     tenant_db_id: i64,
     tenant_id_str: &str,
     txn: Option<&DatabaseTransaction>,
 ) -> Result<QwcResult, QbdDesktopError> {
+    let Some(txn) = txn else {
+        let txn = db.begin().await?;
+        let result = Box::pin(get_or_create_qbd_credentials_and_qwc(
+            db,
+            tenant_db_id,
+            tenant_id_str,
+            Some(&txn),
+        ))
+        .await;
+        return match result {
+            Ok(result) => {
+                txn.commit().await?;
+                Ok(result)
+            }
+            Err(e) => {
+                txn.rollback().await?;
+                Err(e)
+            }
+        };
+    };
+    let txn = Some(txn);
+
     let conn_svc = ConnectionIdentityService::new(db.clone());
-    let cred_svc = ErpConnectionCredentialsService::new(db.clone());
+    let cred_svc = ErpConnectionCredentialsService::new(db.clone()).with_codec(
+        CredentialCodec::new()
+            .with_require_encryption(config::env::get().credentials.require_encryption),
+    );
 
-    if let Some((conn, creds)) = find_qbd_connection(db, tenant_db_id, txn).await? {
-        let username = creds.provider_user_id.unwrap_or_default();
-        let password = creds.provider_password.unwrap_or_default();
+    let existing = find_qbd_connection(db, tenant_db_id, txn).await?;
+
+    if let Some((conn, Some(creds))) = &existing
+        && creds.provider_user_id.is_some()
+        && creds.provider_password.is_some()
+    {
+        let username = creds.provider_user_id.clone().unwrap_or_default();
+        let password = creds.provider_password.clone().unwrap_or_default();
         let file_id = conn
             .company_file_id
+            .clone()
             .unwrap_or_else(|| Uuid::new_v4().to_string());
         let qwc_xml = format_qwc_template(&username, &password, &file_id);
         return Ok(QwcResult {
@@ -170,6 +279,53 @@ pub async fn get_or_create_qbd_credentials_and_qwc(
 
     let username = random_username();
     let password = random_password();
+
+    // Reuse an existing QBD connection that is missing Web Connector credentials
+    // instead of creating a second connection for the same tenant.
+    if let Some((conn, creds)) = existing {
+        let file_id = match conn.company_file_id.clone() {
+            Some(file_id) => file_id,
+            None => {
+                let file_id = random_file_id();
+                let mut active: connection_identity::ActiveModel = conn.clone().into();
+                active.company_file_id = Set(Some(file_id.clone()));
+                active.updated_at = Set(chrono::Utc::now().into());
+                match txn {
+                    Some(txn) => active.update(txn).await?,
+                    None => active.update(db).await?,
+                };
+                file_id
+            }
+        };
+
+        match creds {
+            Some(creds) => {
+                let mut active: erp_connection_credentials::ActiveModel = creds.into();
+                active.provider_user_id = Set(Some(username.clone()));
+                active.provider_password = Set(Some(password.clone()));
+                active.updated_at = Set(chrono::Utc::now().into());
+                match txn {
+                    Some(txn) => active.update(txn).await?,
+                    None => active.update(db).await?,
+                };
+            }
+            None => {
+                cred_svc
+                    .create(qbd_credentials(conn.id, &username, &password), txn)
+                    .await?;
+            }
+        }
+
+        let qwc_xml = format_qwc_template(&username, &password, &file_id);
+        return Ok(QwcResult {
+            tenant_id: tenant_id_str.to_string(),
+            username,
+            password,
+            file_id,
+            qwc_xml,
+        });
+    }
+
     let file_id = random_file_id();
 
     let connection = conn_svc
@@ -193,42 +349,14 @@ pub async fn get_or_create_qbd_credentials_and_qwc(
                 secret_version: None,
                 sync_enabled_push: Some(true),
                 sync_enabled_pull: Some(true),
+                sync_window: None,
             },
             txn,
         )
         .await?;
 
-    let _creds = cred_svc
-        .create(
-            CreateErpConnectionCredentials {
-                connection_id: connection.id,
-                client_id: None,
-                issuer_base_url: None,
-                token_type: None,
-                reauth_required_reason: None,
-                reauth_url: None,
-                enc_scheme: Some("none".to_string()),
-                enc_key_id: "qbd-webconnector".to_string(),
-                enc_version: Some(1),
-                enc_iv: None,
-                enc_tag: None,
-                access_token: None,
-                refresh_token: None,
-                access_token_expires_at: None,
-                refresh_token_expires_at: None,
-                id_token_enc: None,
-                provider_user_id: Some(username.clone()),
-                provider_password: Some(password.clone()),
-                client_cert: None,
-                private_key: None,
-                cert_expires_at: None,
-                session_token: None,
-                session_expires_at: None,
-                api_access_token: None,
-                api_access_token_key: None,
-            },
-            txn,
-        )
+    cred_svc
+        .create(qbd_credentials(connection.id, &username, &password), txn)
         .await?;
 
     let qwc_xml = format_qwc_template(&username, &password, &file_id);
@@ -241,6 +369,69 @@ pub async fn get_or_create_qbd_credentials_and_qwc(
     })
 }
 
+/// Rebuilds the .qwc of the tenant's QuickBooks Desktop connection from its stored username
+/// and file id, without touching the credentials. The password is not part of the .qwc.
+pub async fn existing_qwc(
+    db: &DatabaseConnection,
+    tenant_id: &str,
+) -> Result<ExistingQwc, QbdDesktopError> {
+    let tenant = TenantService::new(db.clone())
+        .get_by_tenant_id(tenant_id, None)
+        .await?
+        .ok_or(QbdDesktopError::TenantNotFound)?;
+
+    let Some((conn, Some(creds))) = find_qbd_connection(db, tenant.id, None).await? else {
+        return Err(QbdDesktopError::ConnectionNotFound);
+    };
+    let (Some(username), Some(file_id)) = (creds.provider_user_id, conn.company_file_id) else {
+        return Err(QbdDesktopError::ConnectionNotFound);
+    };
+
+    let qwc_xml = format_qwc_template(&username, "", &file_id);
+    Ok(ExistingQwc {
+        tenant_db_id: tenant.id,
+        username,
+        file_id,
+        qwc_xml,
+    })
+}
+
+/// Web Connector username/password credentials for a QBD connection.
+fn qbd_credentials(
+    connection_id: i64,
+    username: &str,
+    password: &str,
+) -> CreateErpConnectionCredentials {
+    CreateErpConnectionCredentials {
+        connection_id,
+        erp_auth_type: Some(ErpProviderAuthType::UsernamePassword),
+        client_id: None,
+        issuer_base_url: None,
+        token_type: None,
+        reauth_required_reason: None,
+        reauth_url: None,
+        enc_scheme: Some("none".to_string()),
+        enc_key_id: "qbd-webconnector".to_string(),
+        enc_version: Some(1),
+        enc_iv: None,
+        enc_tag: None,
+        access_token: None,
+        refresh_token: None,
+        access_token_expires_at: None,
+        refresh_token_expires_at: None,
+        id_token_enc: None,
+        provider_user_id: Some(username.to_string()),
+        provider_password: Some(password.to_string()),
+        client_cert: None,
+        private_key: None,
+        cert_expires_at: None,
+        session_token: None,
+        session_expires_at: None,
+        api_access_token: None,
+        api_access_token_key: None,
+    }
+}
+
 /// Fills the .qwc template with username, password, and file_id.
 /// Template placeholders: {{username}}, {{fileid}}. Password is not in the .qwc file.
 fn format_qwc_template(username: &str, _password: &str, file_id: &str) -> String {
@@ -251,13 +442,29 @@ fn format_qwc_template(username: &str, _password: &str, file_id: &str) -> String
 
 /// Full flow: ensure tenant (create if no tenant_id), get or create QBD credentials,
 /// build .qwc XML, base64-encode it, and return the API output.
+/// The tenant, connection and credentials are provisioned in one transaction: any failure
+/// rolls all of them back.
 pub async fn generate_qwc(
     db: &DatabaseConnection,
     tenant_id: Option<&str>,
 ) -> Result<GenerateQwcOutput, QbdDesktopError> {
-    let (tenant_db_id, tenant_id_str) = ensure_tenant(db, tenant_id, None).await?;
-    let result =
-        get_or_create_qbd_credentials_and_qwc(db, tenant_db_id, &tenant_id_str, None).await?;
+    let txn = db.begin().await?;
+    let provisioned = async {
+        let (tenant_db_id, tenant_id_str) = ensure_tenant(db, tenant_id, Some(&txn)).await?;
+        get_or_create_qbd_credentials_and_qwc(db, tenant_db_id, &tenant_id_str, Some(&txn)).await
+    }
+    .await;
+    let result = match provisioned {
+        Ok(result) => {
+            txn.commit().await?;
+            result
+        }
+        Err(e) => {
+            txn.rollback().await?;
+            return Err(e);
+        }
+    };
+
     let qwc_file_base64 = base64::engine::general_purpose::STANDARD.encode(result.qwc_xml.as_bytes());
     Ok(GenerateQwcOutput {
         tenant_id: result.tenant_id,
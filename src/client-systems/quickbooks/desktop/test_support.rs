@@ -0,0 +1,248 @@
+//! Driving the QuickBooks Desktop poll pipeline without a Web Connector (`test-util` feature).
+//!
+//! `QbdPollHarness` plays the Web Connector's part in a poll cycle:
+//!   1. `seed` inserts an active Desktop connection and its Web Connector credentials
+//!   2. `begin_request` fabricates the request phase: the orchestrator picks the recurring
+//!      inventory event and starts a run, exactly as `handle_request` does once the
+//!      credentials check out
+//!   3. `respond` feeds a canned `ItemInventoryQueryRs` into `handle_response` and returns the
+//!      connection's `inventory_record` rows afterwards
+//!
+//! `pull` repeats 2–3 for every page. `inventory_page` builds the canned XML.
+//!
+//! `handle_response` reads the connection row back, which SQLite can't do
+//! (`connection_identity.scopes` is a Postgres array). Against SQLite, build the harness with
+//! `without_credential_check`: pages then go straight to `SyncOrchestrator::apply_response`
+//! through `QbdPollService::apply_pull`, i.e. everything `handle_response` does after the
+//! credential and environment checks (there are no order pushes in a harness pull).
+//!
+//! The harness writes rows but creates no tables; run migrations (Postgres) or create them
+//! from the entities (SQLite) first.
+
+use entity::sea_orm_active_enums::{
+    ErpConnectionAuthStatus, ErpConnectionAuthTokenType, ErpConnectionStatus, ErpEnvironment,
+    ErpProvider, ErpProviderAuthType, ErpProviderType,
+};
+use entity::{connection_identity, erp_connection_credentials, inventory_record};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, NotSet,
+    QueryFilter, QueryOrder, Set,
+};
+
+use crate::sync::{PullResponse, SyncOrchestrator};
+
+use super::poll_services::{PollResponseInput, QbdPollError, QbdPollService};
+
+pub const HARNESS_USERNAME: &str = "qbwc-harness";
+pub const HARNESS_PASSWORD: &str = "qbwc-harness-pass";
+
+// ── Canned responses ──────────────────────────────────────────────────────────
+
+/// An `ItemInventoryQueryRs` page with items `first..first + count` (ListID `8000{n:04}-…`,
+/// `QuantityOnHand` n), reporting `remaining` items still to come under one iterator.
+pub fn inventory_page(first: usize, count: usize, remaining: usize) -> String {
+    let items: String = (first..first + count)
+        .map(|n| {
+            format!(
+                "<ItemInventoryRet><ListID>8000{n:04}-1234567890</ListID><Name>Item {n}</Name>\
+                 <FullName>Items:Item {n}</FullName><QuantityOnHand>{n}</QuantityOnHand></ItemInventoryRet>"
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <ItemInventoryQueryRs requestID="1" statusCode="0" statusSeverity="Info" statusMessage="Status OK" iteratorRemainingCount="{remaining}" iteratorID="{{harness-iterator}}">
+      {items}
+    </ItemInventoryQueryRs>
+  </QBXMLMsgsRs>
+</QBXML>"#
+    )
+}
+
+// ── Harness ───────────────────────────────────────────────────────────────────
+
+/// What a harness response left behind.
+#[derive(Debug)]
+pub struct HarnessPage {
+    /// Whether QBWC would be told to call again.
+    pub has_more: bool,
+    /// The connection's inventory records after the page, by id.
+    pub records: Vec<inventory_record::Model>,
+}
+
+pub struct QbdPollHarness {
+    db: DatabaseConnection,
+    service: QbdPollService,
+    connection: connection_identity::Model,
+    credential_check: bool,
+}
+
+impl QbdPollHarness {
+    /// Inserts an active, enabled Desktop connection for `tenant_id` with Web Connector
+    /// credentials `HARNESS_USERNAME` / `HARNESS_PASSWORD` (stored under `enc_scheme = "none"`).
+    pub async fn seed(db: DatabaseConnection, tenant_id: i64) -> Result<Self, DbErr> {
+        let now = chrono::Utc::now().into();
+        let mut connection = connection_identity::Model {
+            id: 0,
+            uuid: uuid::Uuid::new_v4(),
+            tenant_id,
+            erp_provider: ErpProvider::Quickbooks,
+            erp_type: ErpProviderType::Desktop,
+            erp_auth_type: ErpProviderAuthType::UsernamePassword,
+            display_name: Some("QBD harness".to_string()),
+            environment: ErpEnvironment::Production,
+            status: ErpConnectionStatus::Active,
+            auth_status: ErpConnectionAuthStatus::Connected,
+            created_at: now,
+            updated_at: now,
+            is_enabled: true,
+            last_success_at: None,
+            last_error_code: None,
+            last_error_message: None,
+            error_at: None,
+            sync_enabled_push: true,
+            sync_enabled_pull: true,
+            secret_storage_ref: None,
+            secret_version: None,
+            scopes: None,
+            provider_realm_id: None,
+            provider_tenant_id: None,
+            company_file_identity: None,
+            company_file_path: None,
+            company_file_id: None,
+            system_version: None,
+            web_connector_app_name: None,
+            qbxml_version: None,
+            initial_sync_completed_at: None,
+            sync_window_start_hour: None,
+            sync_window_end_hour: None,
+            field_mapping: None,
+        };
+        let mut active = connection.clone().into_active_model().reset_all();
+        active.id = NotSet;
+        //left to the column default, which SQLite has no array type for
+        active.scopes = NotSet;
+        connection.id = connection_identity::Entity::insert(active)
+            .exec(&db)
+            .await?
+            .last_insert_id;
+
+        erp_connection_credentials::ActiveModel {
+            uuid: Set(uuid::Uuid::new_v4()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            connection_id: Set(connection.id),
+            token_type: Set(ErpConnectionAuthTokenType::Bearer),
+            enc_scheme: Set("none".to_string()),
+            enc_key_id: Set("qbd-webconnector".to_string()),
+            enc_version: Set(1),
+            provider_user_id: Set(Some(HARNESS_USERNAME.to_string())),
+            provider_password: Set(Some(HARNESS_PASSWORD.to_string())),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await?;
+
+        Ok(Self {
+            service: QbdPollService::new(db.clone()),
+            db,
+            connection,
+            credential_check: true,
+        })
+    }
+
+    /// Replaces the poll service, e.g. to set its retry policy or codec.
+    pub fn with_service(mut self, service: QbdPollService) -> Self {
+        self.service = service;
+        self
+    }
+
+    /// Applies pages without `handle_response`'s credential lookup; needed on SQLite.
+    pub fn without_credential_check(mut self) -> Self {
+        self.credential_check = false;
+        self
+    }
+
+    pub fn db(&self) -> &DatabaseConnection {
+        &self.db
+    }
+
+    pub fn connection(&self) -> &connection_identity::Model {
+        &self.connection
+    }
+
+    pub fn service(&self) -> &QbdPollService {
+        &self.service
+    }
+
+    /// Fabricates the request phase: starts (or continues) the inventory pull and returns
+    /// the QBXML query QBD would be sent; None when the orchestrator has nothing to do.
+    pub async fn begin_request(&self) -> Result<Option<String>, DbErr> {
+        let request = SyncOrchestrator::new(self.db.clone(), &self.service)
+            .next_request(&self.connection)
+            .await?;
+        Ok(request.map(|r| r.payload))
+    }
+
+    /// Feeds `xml` to the in-flight request as QBD's answer.
+    pub async fn respond(&self, xml: &str) -> Result<HarnessPage, QbdPollError> {
+        let has_more = if self.credential_check {
+            self.service
+                .handle_response(
+                    HARNESS_USERNAME,
+                    HARNESS_PASSWORD,
+                    PollResponseInput {
+                        qbd_response_xml: Some(xml.to_string()),
+                        qbd_error: None,
+                        dry_run: false,
+                    },
+                )
+                .await?
+                .has_more
+        } else {
+            self.service
+                .apply_pull(
+                    &self.connection,
+                    PullResponse {
+                        body: Some(xml.to_string()),
+                        error: None,
+                    },
+                )
+                .await?
+                .has_more()
+        };
+        Ok(HarnessPage {
+            has_more,
+            records: self.records().await?,
+        })
+    }
+
+    /// Runs one request/response cycle per page, in order, and returns the last page's
+    /// result. Fails if the orchestrator hands out no request for a page.
+    pub async fn pull(&self, pages: &[String]) -> Result<HarnessPage, QbdPollError> {
+        let mut last = HarnessPage {
+            has_more: false,
+            records: Vec::new(),
+        };
+        for page in pages {
+            if self.begin_request().await?.is_none() {
+                return Err(QbdPollError::Db(DbErr::Custom(
+                    "no request handed out for the next page".to_string(),
+                )));
+            }
+            last = self.respond(page).await?;
+        }
+        Ok(last)
+    }
+
+    /// The connection's inventory records, by id.
+    pub async fn records(&self) -> Result<Vec<inventory_record::Model>, DbErr> {
+        inventory_record::Entity::find()
+            .filter(inventory_record::Column::OriginatingConnectionId.eq(self.connection.id))
+            .order_by_asc(inventory_record::Column::Id)
+            .all(&self.db)
+            .await
+    }
+}
@@ -0,0 +1,813 @@
+//! Salesforce pull adapter (REST query and Bulk API 2.0).
+//!
+//! `SalesforceAdapter` is the `ApiPullAdapter` for `erp_provider = salesforce`,
+//! `erp_type = api` connections: the poll scheduler calls it, and `SyncOrchestrator` owns the
+//! sync event / connection run lifecycle, the sync lock and backoff (see
+//! `crate::sync::orchestrator`).
+//!
+//! The connection's credentials hold what the API needs:
+//!   - `issuer_base_url`: the instance URL, e.g. `https://acme.my.salesforce.com`
+//!   - `access_token`: an OAuth access token (refreshing it is not covered here; a rejected
+//!     token fails the run)
+//!
+//! Products (`Product2`) are pulled one of two ways, chosen when a pass starts:
+//!   - **REST** (`/query`) for catalogs under the bulk threshold (`BULK_THRESHOLD`).
+//!     Salesforce pages the result itself; the next page's `nextRecordsUrl` is the cursor
+//!   - **Bulk API 2.0** (`/jobs/query`) for larger ones, where REST pages (2,000 records at
+//!     best) make the pass slow. The cursor is the job id and the locator of the next
+//!     results page
+//!
+//! **Request** (`next_request`): the stored cursor as a `SalesforceRequest`; `Start` when
+//! there is none.
+//!
+//! **Fetch** (`fetch`):
+//!   1. `Start`: count the products (`SELECT COUNT() FROM Product2`). Under the threshold,
+//!      run the REST query. Otherwise submit a Bulk query job, check it every
+//!      `bulk_poll_interval` until it is `JobComplete` (giving up and aborting it after
+//!      `bulk_max_wait`), and download the first `BULK_PAGE_SIZE` results as CSV
+//!   2. `Rest`: `GET` the stored `nextRecordsUrl`
+//!   3. `Bulk`: download the results at the stored locator
+//!   4. A failed, aborted or timed-out job, missing credentials and error statuses are
+//!      returned as the response's `error`, so the orchestrator marks the event and run Error
+//!      (with the job's `errorMessage`) and backs off. Nothing is stored for the job: the
+//!      next pass counts again and submits a new one
+//!
+//! **Response** (`apply_response`):
+//!   1. Parse the page (REST records, or the Bulk CSV); the next cursor is the REST
+//!      `nextRecordsUrl`, or the job's `Sforce-Locator` while it is not `null`
+//!   2. Upsert each product into `inventory_record` / `inventory_record_event`, matched on
+//!      `system_id_key=Sfdc` + `system_id={Id}` + `connection_id`. An existing record gets a
+//!      NEW event only when the product differs from its latest one (name, description,
+//!      product code); an unchanged product writes nothing
+//!   3. Enqueue an `inventory.upserted` outbox message per written product, in the page
+//!      transaction
+//!
+//! Salesforce keeps Bulk results for 7 days. A stored job whose results are gone
+//! (`404`) restarts the pass from `Start` instead of failing it.
+//!
+//! Not covered yet: token refresh, prices (`PricebookEntry`), quantities, payload
+//! validation/quarantine and push.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use entity::sea_orm_active_enums::{ErpProvider, ErpProviderType, SystemIdKey};
+use entity::{
+    connection_identity, erp_connection_credentials, erp_connection_sync_state, inventory_record,
+    inventory_record_event, sync_event,
+};
+use sea_orm::sea_query::{Expr, Func, Query};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait, QueryFilter,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::inventory_records::events_services::{
+    CreateInventoryRecordEvent, InventoryRecordEventService,
+};
+use crate::inventory_records::services::{CreateInventoryRecord, InventoryRecordService};
+use crate::outbox::services::{OutboxPayload, OutboxService, TOPIC_INVENTORY_UPSERTED};
+use crate::sync::{
+    AppliedRecord, ApiPullAdapter, PullAdapter, PullError, PullOutcome, PullRequest, PullResponse,
+};
+
+/// REST API version the adapter calls.
+pub const SALESFORCE_API_VERSION: &str = "v60.0";
+
+/// Catalogs with at least this many products are pulled through a Bulk query job.
+pub const BULK_THRESHOLD: u64 = 10_000;
+
+/// Bulk results downloaded per page (`maxRecords`).
+pub const BULK_PAGE_SIZE: u32 = 10_000;
+
+/// How often a submitted Bulk job is checked.
+pub const BULK_POLL_INTERVAL_SECS: u64 = 5;
+
+/// How long a Bulk job may take before it is aborted; stays within the sync lock lease
+/// (`PULL_LOCK_LEASE_SECS`).
+pub const BULK_MAX_WAIT_SECS: u64 = 240;
+
+/// Upper bound for each API call.
+pub const SALESFORCE_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+const PRODUCT_QUERY: &str = "SELECT Id, Name, ProductCode, Description, IsActive FROM Product2";
+const COUNT_QUERY: &str = "SELECT COUNT() FROM Product2";
+
+//DEBUG AND ERRORS ///
+#[derive(Debug)]
+pub enum SalesforceError {
+    ///credentials the API needs are not set
+    MissingCredentials(&'static str),
+    ///the API answered with an error status
+    Api { status: u16, message: String },
+    ///a Bulk query job ended Failed or Aborted
+    BulkJobFailed { job_id: String, message: String },
+    ///a Bulk query job was still running after `bulk_max_wait`
+    BulkJobTimeout { job_id: String, waited: Duration },
+    ///a success response that isn't what the API documents
+    Unreadable(String),
+    Http(reqwest::Error),
+    Db(DbErr),
+}
+
+impl From<reqwest::Error> for SalesforceError {
+    fn from(err: reqwest::Error) -> Self {
+        SalesforceError::Http(err)
+    }
+}
+
+impl From<DbErr> for SalesforceError {
+    fn from(err: DbErr) -> Self {
+        SalesforceError::Db(err)
+    }
+}
+
+impl std::fmt::Display for SalesforceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SalesforceError::MissingCredentials(field) => {
+                write!(f, "Salesforce connection is missing {}", field)
+            }
+            SalesforceError::Api { status, message } => {
+                write!(f, "Salesforce API returned {}: {}", status, message)
+            }
+            SalesforceError::BulkJobFailed { job_id, message } => {
+                write!(f, "Salesforce Bulk query job {} failed: {}", job_id, message)
+            }
+            SalesforceError::BulkJobTimeout { job_id, waited } => write!(
+                f,
+                "Salesforce Bulk query job {} did not complete within {}s",
+                job_id,
+                waited.as_secs()
+            ),
+            SalesforceError::Unreadable(msg) => write!(f, "Unreadable Salesforce response: {}", msg),
+            SalesforceError::Http(e) => write!(f, "Salesforce API unreachable: {}", e),
+            SalesforceError::Db(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+//END DEBUG AND ERRORS
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+pub struct SalesforceAdapter {
+    db: DatabaseConnection,
+    client: reqwest::Client,
+    /// Product count from which a pass uses Bulk instead of REST.
+    bulk_threshold: u64,
+    bulk_poll_interval: Duration,
+    bulk_max_wait: Duration,
+}
+
+/// What `fetch` is asked for; stored (except `Start`) as the sync cursor.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SalesforceRequest {
+    /// A new pass: count the catalog and query it through REST or Bulk.
+    Start,
+    /// The next page of a REST query.
+    Rest { next_records_url: String },
+    /// The next results page of a completed Bulk query job.
+    Bulk { job_id: String, locator: String },
+}
+
+/// What `fetch` hands to `apply_response` as the body.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum SalesforcePage {
+    /// A REST query response, as Salesforce sent it.
+    Rest { response: Value },
+    /// A page of Bulk job results; `locator` is None on the last one.
+    Bulk {
+        job_id: String,
+        locator: Option<String>,
+        csv: String,
+    },
+    /// The stored Bulk job's results are gone; the pass starts over.
+    Expired { message: String },
+}
+
+/// One product of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SalesforceProduct {
+    /// `Id`; used as the `system_id`.
+    pub id: String,
+    pub name: Option<String>,
+    pub product_code: Option<String>,
+    pub description: Option<String>,
+    /// The product's fields as Salesforce sent them (a CSV row as an object), stored in
+    /// `original_record_body`.
+    pub raw: Value,
+}
+
+/// Where to send requests, read from the connection's credentials.
+struct SalesforceApi {
+    instance_url: String,
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    #[serde(rename = "totalSize")]
+    total_size: u64,
+}
+
+#[derive(Deserialize)]
+struct BulkJob {
+    id: String,
+    state: String,
+    #[serde(rename = "errorMessage")]
+    error_message: Option<String>,
+}
+
+/// A page product's stored record and latest event on this connection.
+struct ExistingRecord {
+    record: inventory_record::Model,
+    event: Option<inventory_record_event::Model>,
+}
+// END STRUCTS AND ENUMS
+
+
+/// BEGUN IMPLEMENTATION ///
+impl SalesforceApi {
+    ///`path` under the versioned REST root
+    fn url(&self, path: &str) -> String {
+        format!("{}/services/data/{}/{}", self.instance_url, SALESFORCE_API_VERSION, path)
+    }
+}
+
+impl SalesforceAdapter {
+    pub fn new(db: DatabaseConnection) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(SALESFORCE_REQUEST_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+        Self {
+            db,
+            client,
+            bulk_threshold: BULK_THRESHOLD,
+            bulk_poll_interval: Duration::from_secs(BULK_POLL_INTERVAL_SECS),
+            bulk_max_wait: Duration::from_secs(BULK_MAX_WAIT_SECS),
+        }
+    }
+
+    pub fn with_bulk_threshold(mut self, threshold: u64) -> Self {
+        self.bulk_threshold = threshold;
+        self
+    }
+
+    ///checks a submitted Bulk job every `interval`, aborting it after `max_wait`
+    pub fn with_bulk_polling(mut self, interval: Duration, max_wait: Duration) -> Self {
+        self.bulk_poll_interval = interval;
+        self.bulk_max_wait = max_wait;
+        self
+    }
+
+    ///fetches what `request` asks for, as the body `apply_response` reads
+    async fn get_page(
+        &self,
+        conn: &connection_identity::Model,
+        request: &PullRequest,
+    ) -> Result<SalesforcePage, SalesforceError> {
+        let creds = erp_connection_credentials::Entity::find()
+            .filter(erp_connection_credentials::Column::ConnectionId.eq(conn.id))
+            .one(&self.db)
+            .await?
+            .ok_or(SalesforceError::MissingCredentials("credentials"))?;
+        let api = api_details(&creds)?;
+        let request: SalesforceRequest = serde_json::from_str(&request.payload)
+            .map_err(|e| SalesforceError::Unreadable(format!("pull request: {e}")))?;
+
+        match request {
+            SalesforceRequest::Start => {
+                let count = self.count_products(&api).await?;
+                if count < self.bulk_threshold {
+                    let response = self
+                        .send_json(&api, self.client.get(api.url("query")).query(&[("q", PRODUCT_QUERY)]))
+                        .await?;
+                    return Ok(SalesforcePage::Rest { response });
+                }
+                tracing::info!(
+                    connection_id = conn.id,
+                    count,
+                    "Salesforce catalog at or above the bulk threshold; submitting a Bulk query job"
+                );
+                let job_id = self.submit_bulk_job(&api).await?;
+                self.wait_for_job(&api, &job_id).await?;
+                self.bulk_results(&api, job_id, None).await
+            }
+            SalesforceRequest::Rest { next_records_url } => {
+                let url = format!("{}{}", api.instance_url, next_records_url);
+                let response = self.send_json(&api, self.client.get(url)).await?;
+                Ok(SalesforcePage::Rest { response })
+            }
+            SalesforceRequest::Bulk { job_id, locator } => {
+                match self.bulk_results(&api, job_id.clone(), Some(&locator)).await {
+                    Err(SalesforceError::Api { status: 404, message }) => {
+                        Ok(SalesforcePage::Expired {
+                            message: format!("Bulk query job {} results are gone: {}", job_id, message),
+                        })
+                    }
+                    result => result,
+                }
+            }
+        }
+    }
+
+    async fn count_products(&self, api: &SalesforceApi) -> Result<u64, SalesforceError> {
+        let response = self
+            .send_json(api, self.client.get(api.url("query")).query(&[("q", COUNT_QUERY)]))
+            .await?;
+        let parsed: QueryResponse = serde_json::from_value(response)
+            .map_err(|e| SalesforceError::Unreadable(format!("count query: {e}")))?;
+        Ok(parsed.total_size)
+    }
+
+    ///`POST /jobs/query`; returns the job id
+    async fn submit_bulk_job(&self, api: &SalesforceApi) -> Result<String, SalesforceError> {
+        let body = json!({ "operation": "query", "query": PRODUCT_QUERY });
+        let response = self
+            .send_json(api, json_request(self.client.post(api.url("jobs/query")), &body))
+            .await?;
+        let job: BulkJob = serde_json::from_value(response)
+            .map_err(|e| SalesforceError::Unreadable(format!("Bulk job: {e}")))?;
+        Ok(job.id)
+    }
+
+    ///waits for `JobComplete`; a job that fails, is aborted or outlasts `bulk_max_wait`
+    ///(which aborts it) is an error
+    async fn wait_for_job(&self, api: &SalesforceApi, job_id: &str) -> Result<(), SalesforceError> {
+        let started = tokio::time::Instant::now();
+        loop {
+            let response = self
+                .send_json(api, self.client.get(api.url(&format!("jobs/query/{}", job_id))))
+                .await?;
+            let job: BulkJob = serde_json::from_value(response)
+                .map_err(|e| SalesforceError::Unreadable(format!("Bulk job: {e}")))?;
+            match job.state.as_str() {
+                "JobComplete" => return Ok(()),
+                "Failed" | "Aborted" => {
+                    return Err(SalesforceError::BulkJobFailed {
+                        job_id: job.id,
+                        message: job.error_message.unwrap_or(job.state),
+                    });
+                }
+                _ => {}
+            }
+
+            if started.elapsed() >= self.bulk_max_wait {
+                let abort = json_request(
+                    self.client.patch(api.url(&format!("jobs/query/{}", job_id))),
+                    &json!({ "state": "Aborted" }),
+                );
+                if let Err(e) = self.send(api, abort).await {
+                    tracing::warn!(job_id, "Could not abort Salesforce Bulk query job: {}", e);
+                }
+                return Err(SalesforceError::BulkJobTimeout {
+                    job_id: job_id.to_string(),
+                    waited: self.bulk_max_wait,
+                });
+            }
+            tokio::time::sleep(self.bulk_poll_interval).await;
+        }
+    }
+
+    ///one results page of a completed job, from `locator` (the first page when None)
+    async fn bulk_results(
+        &self,
+        api: &SalesforceApi,
+        job_id: String,
+        locator: Option<&str>,
+    ) -> Result<SalesforcePage, SalesforceError> {
+        let mut request = self
+            .client
+            .get(api.url(&format!("jobs/query/{}/results", job_id)))
+            .query(&[("maxRecords", BULK_PAGE_SIZE.to_string())])
+            .header(reqwest::header::ACCEPT, "text/csv");
+        if let Some(locator) = locator {
+            request = request.query(&[("locator", locator)]);
+        }
+        let res = self.send(api, request).await?;
+        //"null" on the last page
+        let next_locator = res
+            .headers()
+            .get("Sforce-Locator")
+            .and_then(|v| v.to_str().ok())
+            .filter(|v| !v.is_empty() && *v != "null")
+            .map(str::to_string);
+        let csv = res.text().await?;
+        Ok(SalesforcePage::Bulk {
+            job_id,
+            locator: next_locator,
+            csv,
+        })
+    }
+
+    ///sends `request` with the access token; an error status becomes `SalesforceError::Api`
+    async fn send(
+        &self,
+        api: &SalesforceApi,
+        request: reqwest::RequestBuilder,
+    ) -> Result<reqwest::Response, SalesforceError> {
+        let res = request.bearer_auth(&api.access_token).send().await?;
+        let status = res.status();
+        if status.is_success() {
+            return Ok(res);
+        }
+        let text = res.text().await.unwrap_or_default();
+        Err(SalesforceError::Api {
+            status: status.as_u16(),
+            message: api_message(&text),
+        })
+    }
+
+    async fn send_json<T: DeserializeOwned>(
+        &self,
+        api: &SalesforceApi,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, SalesforceError> {
+        let text = self
+            .send(api, request.header(reqwest::header::ACCEPT, "application/json"))
+            .await?
+            .text()
+            .await?;
+        serde_json::from_str(&text).map_err(|e| SalesforceError::Unreadable(e.to_string()))
+    }
+
+    async fn existing_records(
+        &self,
+        conn: &connection_identity::Model,
+        ids: &[&str],
+        txn: &DatabaseTransaction,
+    ) -> Result<HashMap<String, ExistingRecord>, DbErr> {
+        let records = inventory_record::Entity::find()
+            .filter(inventory_record::Column::SystemIdKey.eq(SystemIdKey::Sfdc))
+            .filter(inventory_record::Column::SystemId.is_in(ids.iter().copied()))
+            .filter(inventory_record::Column::OriginatingConnectionId.eq(conn.id))
+            .all(txn)
+            .await?;
+        if records.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        //only the newest event of each record is compared against
+        let latest_ids = Query::select()
+            .expr(Func::max(Expr::col(inventory_record_event::Column::Id)))
+            .from(inventory_record_event::Entity)
+            .and_where(
+                inventory_record_event::Column::InventoryRecordId
+                    .is_in(records.iter().map(|r| r.id)),
+            )
+            .and_where(inventory_record_event::Column::ConnectionId.eq(conn.id))
+            .group_by_col(inventory_record_event::Column::InventoryRecordId)
+            .to_owned();
+        let mut latest: HashMap<i64, inventory_record_event::Model> =
+            inventory_record_event::Entity::find()
+                .filter(inventory_record_event::Column::Id.in_subquery(latest_ids))
+                .all(txn)
+                .await?
+                .into_iter()
+                .map(|event| (event.inventory_record_id, event))
+                .collect();
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                let event = latest.remove(&record.id);
+                (record.system_id.clone(), ExistingRecord { record, event })
+            })
+            .collect())
+    }
+
+    ///creates or updates one product; the applied record is None when nothing changed
+    async fn upsert_product(
+        &self,
+        conn: &connection_identity::Model,
+        product: &SalesforceProduct,
+        existing: Option<&ExistingRecord>,
+        txn: &DatabaseTransaction,
+    ) -> Result<(inventory_record::Model, Option<AppliedRecord>), DbErr> {
+        let record = match existing {
+            Some(existing) => existing.record.clone(),
+            None => {
+                InventoryRecordService::new(self.db.clone())
+                    .create(
+                        CreateInventoryRecord {
+                            tenant_id: conn.tenant_id,
+                            originating_connection_id: conn.id,
+                            original_record_body: Some(product.raw.clone()),
+                            system_id_key: SystemIdKey::Sfdc,
+                            system_id: product.id.clone(),
+                        },
+                        Some(txn),
+                    )
+                    .await?
+            }
+        };
+
+        let latest = existing.and_then(|e| e.event.as_ref());
+        if latest.is_some_and(|event| event_matches_product(event, product)) {
+            return Ok((record, None));
+        }
+
+        let event = InventoryRecordEventService::new(self.db.clone())
+            .create(
+                CreateInventoryRecordEvent {
+                    inventory_record_id: record.id,
+                    connection_id: conn.id,
+                    original_record_body: Some(product.raw.clone()),
+                    price: None,
+                    currency: None,
+                    name: product.name.clone(),
+                    description: product.description.clone(),
+                    attributes: None,
+                    qty: None,
+                    external_code: product.product_code.clone(),
+                    qty_milli: None,
+                },
+                Some(txn),
+            )
+            .await?;
+
+        let applied = AppliedRecord {
+            record_id: product.id.clone(),
+            inventory_record_event_id: event.id,
+            created: existing.is_none(),
+        };
+        Ok((record, Some(applied)))
+    }
+}
+
+impl PullAdapter for SalesforceAdapter {
+    fn next_request(
+        &self,
+        _conn: &connection_identity::Model,
+        sync_state: &erp_connection_sync_state::Model,
+        _event: &sync_event::Model,
+    ) -> PullRequest {
+        PullRequest {
+            payload: request_payload(sync_state.sync_cursor.as_ref()),
+        }
+    }
+
+    async fn apply_response(
+        &self,
+        conn: &connection_identity::Model,
+        _event: &sync_event::Model,
+        body: &str,
+        txn: &DatabaseTransaction,
+    ) -> Result<PullOutcome, PullError> {
+        let (products, cursor) = parse_page(body)?;
+
+        let ids: Vec<&str> = products.iter().map(|p| p.id.as_str()).collect();
+        let existing = self.existing_records(conn, &ids, txn).await?;
+
+        //products are written one after another; a failed write fails the page, which the
+        //orchestrator records on the event and run
+        let outbox_svc = OutboxService::new(self.db.clone());
+        let mut applied: Vec<AppliedRecord> = Vec::new();
+        for product in &products {
+            let (record, written) = self
+                .upsert_product(conn, product, existing.get(&product.id), txn)
+                .await?;
+            let Some(written) = written else {
+                continue;
+            };
+            //written in the page transaction so the message exists iff the change commits
+            outbox_svc
+                .enqueue(txn, inventory_upserted_message(conn, &record, product))
+                .await?;
+            applied.push(written);
+        }
+
+        Ok(PullOutcome {
+            cursor,
+            errors: vec![],
+            rejected: vec![],
+            applied,
+            warnings: vec![],
+        })
+    }
+}
+
+impl ApiPullAdapter for SalesforceAdapter {
+    fn handles(&self, conn: &connection_identity::Model) -> bool {
+        conn.erp_provider == ErpProvider::Salesforce && conn.erp_type == ErpProviderType::Api
+    }
+
+    async fn fetch(&self, conn: &connection_identity::Model, request: PullRequest) -> PullResponse {
+        match self.get_page(conn, &request).await {
+            Ok(page) => PullResponse {
+                body: Some(serde_json::to_string(&page).unwrap_or_default()),
+                error: None,
+            },
+            Err(e) => {
+                tracing::warn!(connection_id = conn.id, "Salesforce fetch failed: {}", e);
+                PullResponse {
+                    body: None,
+                    error: Some(e.to_string()),
+                }
+            }
+        }
+    }
+}
+
+///the instance URL and access token of a connection
+fn api_details(creds: &erp_connection_credentials::Model) -> Result<SalesforceApi, SalesforceError> {
+    let required = |value: &Option<String>, field: &'static str| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+            .ok_or(SalesforceError::MissingCredentials(field))
+    };
+    Ok(SalesforceApi {
+        instance_url: required(&creds.issuer_base_url, "issuer_base_url")?
+            .trim_end_matches('/')
+            .to_string(),
+        access_token: required(&creds.access_token, "access_token")?,
+    })
+}
+
+///`request` with `body` as its JSON body
+fn json_request(request: reqwest::RequestBuilder, body: &Value) -> reqwest::RequestBuilder {
+    request
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_string())
+}
+
+///the `SalesforceRequest` payload continuing from `cursor` (`Start` without one)
+pub fn request_payload(cursor: Option<&Value>) -> String {
+    let request = cursor
+        .and_then(|c| serde_json::from_value::<SalesforceRequest>(c.clone()).ok())
+        .unwrap_or(SalesforceRequest::Start);
+    serde_json::to_string(&request).unwrap_or_default()
+}
+
+///the products of a page and the cursor of the next one (None on the last page)
+pub fn parse_page(body: &str) -> Result<(Vec<SalesforceProduct>, Option<Value>), PullError> {
+    let page: SalesforcePage = serde_json::from_str(body)
+        .map_err(|e| PullError::Provider(format!("Salesforce page parse error: {e}")))?;
+
+    let (products, next) = match page {
+        SalesforcePage::Expired { message } => return Err(PullError::CursorExpired(message)),
+        SalesforcePage::Rest { response } => {
+            let next = response
+                .get("nextRecordsUrl")
+                .and_then(Value::as_str)
+                .filter(|_| response.get("done").and_then(Value::as_bool) != Some(true))
+                .map(|url| SalesforceRequest::Rest {
+                    next_records_url: url.to_string(),
+                });
+            let records = response
+                .get("records")
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default();
+            let products = records
+                .into_iter()
+                .filter_map(|mut record| {
+                    //type and URL of the record, not product data
+                    if let Some(fields) = record.as_object_mut() {
+                        fields.remove("attributes");
+                    }
+                    product_from_fields(record)
+                })
+                .collect();
+            (products, next)
+        }
+        SalesforcePage::Bulk {
+            job_id,
+            locator,
+            csv,
+        } => {
+            let products = parse_bulk_csv(&csv)?;
+            let next = locator.map(|locator| SalesforceRequest::Bulk { job_id, locator });
+            (products, next)
+        }
+    };
+
+    let cursor = next.and_then(|request| serde_json::to_value(request).ok());
+    Ok((products, cursor))
+}
+
+///the products of a Bulk results page (a header row, then one row per product; empty
+///fields are null)
+pub fn parse_bulk_csv(csv: &str) -> Result<Vec<SalesforceProduct>, PullError> {
+    let mut rows = parse_csv(csv)
+        .map_err(|e| PullError::Provider(format!("Salesforce Bulk results parse error: {e}")))?
+        .into_iter();
+    let Some(header) = rows.next() else {
+        return Ok(Vec::new());
+    };
+
+    Ok(rows
+        .filter_map(|row| {
+            let fields: Map<String, Value> = header
+                .iter()
+                .zip(row)
+                .map(|(name, value)| {
+                    let value = match value.is_empty() {
+                        true => Value::Null,
+                        false => Value::String(value),
+                    };
+                    (name.clone(), value)
+                })
+                .collect();
+            product_from_fields(Value::Object(fields))
+        })
+        .collect())
+}
+
+///the rows of an RFC 4180 CSV body; quoted fields may hold commas, newlines and doubled
+///quotes
+pub fn parse_csv(body: &str) -> Result<Vec<Vec<String>>, String> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = body.chars().peekable();
+    while let Some(c) = chars.next() {
+        if quoted {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => quoted = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => quoted = true,
+            ',' => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quoted field".to_string());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+///a product from its fields; None without an `Id`, which it couldn't be matched on
+fn product_from_fields(raw: Value) -> Option<SalesforceProduct> {
+    let id = raw.get("Id").and_then(Value::as_str)?.to_string();
+    let text = |key: &str| raw.get(key).and_then(Value::as_str).map(str::to_string);
+    Some(SalesforceProduct {
+        name: text("Name"),
+        product_code: text("ProductCode"),
+        description: text("Description"),
+        id,
+        raw,
+    })
+}
+
+///the `message` of a Salesforce error body (`[{"message", "errorCode"}]`), or the body itself
+fn api_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v.pointer("/0/message").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_else(|| body.chars().take(200).collect())
+}
+
+///whether `event` already holds the product's values
+fn event_matches_product(event: &inventory_record_event::Model, product: &SalesforceProduct) -> bool {
+    event.name == product.name
+        && event.description == product.description
+        && event.external_code == product.product_code
+}
+
+///the `inventory.upserted` message for one Salesforce product
+fn inventory_upserted_message(
+    conn: &connection_identity::Model,
+    record: &inventory_record::Model,
+    product: &SalesforceProduct,
+) -> OutboxPayload {
+    OutboxPayload {
+        topic: TOPIC_INVENTORY_UPSERTED.to_string(),
+        payload: json!({
+            "tenant_id": conn.tenant_id,
+            "connection_uuid": conn.uuid,
+            "inventory_record_uuid": record.uuid,
+            "system_id_key": "sfdc",
+            "system_id": record.system_id,
+            "name": product.name,
+            "qty": null,
+        }),
+    }
+}
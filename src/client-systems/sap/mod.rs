@@ -0,0 +1,579 @@
+//! SAP Business One pull adapter (Service Layer).
+//!
+//! `SapAdapter` is the `ApiPullAdapter` for `erp_provider = sap`, `erp_type = api`
+//! connections: the poll scheduler calls it, and `SyncOrchestrator` owns the sync event /
+//! connection run lifecycle, the sync lock and backoff (see `crate::sync::orchestrator`).
+//!
+//! The connection's credentials hold what the Service Layer needs:
+//!   - `issuer_base_url`: the Service Layer root, e.g. `https://sap.example.com:50000/b1s/v1`
+//!   - `provider_user_id` / `provider_password`: the B1 user
+//!   - the connection's `provider_tenant_id`: the company database (`CompanyDB`)
+//!
+//! **Request** (`next_request`): `Items` ordered by `ItemCode`, `SAP_PAGE_SIZE` at a time,
+//! skipping the number of items stored in `sync_state.sync_cursor` (`{"skip": N}`, a
+//! `SyncCursor::RestOffset`; 0 when there is no cursor, or a malformed one).
+//!
+//! **Fetch** (`fetch`):
+//!   1. Reuse the stored session (`session_token`) while `session_expires_at` is more than
+//!      `SESSION_EXPIRY_MARGIN_SECS` away; otherwise `POST /Login` and store the new
+//!      `SessionId` and its expiry (`SessionTimeout` minutes from now) in the credentials
+//!   2. `GET` the request with the `B1SESSION` cookie. A `401` means the Service Layer
+//!      dropped the session early: log in again and resend once
+//!   3. Missing credentials, a failed login and other error statuses are returned as the
+//!      response's `error`, so the orchestrator marks the event and run Error and backs off
+//!
+//! **Response** (`apply_response`):
+//!   1. Parse the page (`value`); the next cursor comes from the Service Layer's
+//!      `odata.nextLink` (its `$skip`), None once there is no next link
+//!   2. Upsert each item into `inventory_record` / `inventory_record_event`, matched on
+//!      `system_id_key=Sapo` + `system_id={ItemCode}` + `connection_id`. An existing record
+//!      gets a NEW event only when the item differs from its latest one (name, foreign name,
+//!      quantity on stock); an unchanged item writes nothing
+//!   3. Enqueue an `inventory.upserted` outbox message per written item, in the page
+//!      transaction
+//!
+//! Not covered yet: price lists, payload validation/quarantine, concurrent upserts and push.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use entity::sea_orm_active_enums::{ErpProvider, ErpProviderType, SystemIdKey};
+use entity::{
+    connection_identity, erp_connection_credentials, erp_connection_sync_state, inventory_record,
+    inventory_record_event, sync_event,
+};
+use sea_orm::sea_query::{Expr, Func, Query};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait, QueryFilter,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::inventory_records::events_services::{
+    CreateInventoryRecordEvent, InventoryRecordEventService,
+};
+use crate::inventory_records::services::{CreateInventoryRecord, InventoryRecordService};
+use crate::outbox::services::{OutboxPayload, OutboxService, TOPIC_INVENTORY_UPSERTED};
+use crate::sync::{
+    AppliedRecord, ApiPullAdapter, PullAdapter, PullError, PullOutcome, PullRequest, PullResponse,
+    SyncCursor,
+};
+
+/// Items requested per page (`$top`).
+pub const SAP_PAGE_SIZE: u32 = 100;
+
+/// A stored session this close to expiring is replaced before the request.
+pub const SESSION_EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// Upper bound for each Service Layer call.
+pub const SAP_REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// Service Layer default when the login response has no `SessionTimeout`.
+const DEFAULT_SESSION_TIMEOUT_MINUTES: i64 = 30;
+
+const ITEM_FIELDS: &str = "ItemCode,ItemName,ForeignName,QuantityOnStock,ItemsGroupCode";
+
+//DEBUG AND ERRORS ///
+#[derive(Debug)]
+pub enum SapError {
+    ///credentials or connection fields the Service Layer needs are not set
+    MissingCredentials(&'static str),
+    ///the Service Layer refused the login
+    Login(String),
+    Http(reqwest::Error),
+    Db(DbErr),
+}
+
+impl From<reqwest::Error> for SapError {
+    fn from(err: reqwest::Error) -> Self {
+        SapError::Http(err)
+    }
+}
+
+impl From<DbErr> for SapError {
+    fn from(err: DbErr) -> Self {
+        SapError::Db(err)
+    }
+}
+
+impl std::fmt::Display for SapError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SapError::MissingCredentials(field) => {
+                write!(f, "SAP connection is missing {}", field)
+            }
+            SapError::Login(msg) => write!(f, "SAP login failed: {}", msg),
+            SapError::Http(e) => write!(f, "SAP Service Layer unreachable: {}", e),
+            SapError::Db(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+//END DEBUG AND ERRORS
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+pub struct SapAdapter {
+    db: DatabaseConnection,
+    client: reqwest::Client,
+}
+
+/// A Service Layer session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SapSession {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Where to log in, read from the connection and its credentials.
+struct SapLogin {
+    base_url: String,
+    company_db: String,
+    username: String,
+    password: String,
+}
+
+#[derive(Deserialize)]
+struct LoginResponse {
+    #[serde(rename = "SessionId")]
+    session_id: String,
+    /// Minutes of inactivity before the session expires.
+    #[serde(rename = "SessionTimeout")]
+    session_timeout: Option<i64>,
+}
+
+#[derive(Deserialize)]
+struct ItemsPage {
+    #[serde(default)]
+    value: Vec<Value>,
+    #[serde(rename = "odata.nextLink", alias = "@odata.nextLink")]
+    next_link: Option<String>,
+}
+
+/// One item of a page.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SapItem {
+    /// `ItemCode`; used as the `system_id`.
+    pub item_code: String,
+    pub name: Option<String>,
+    pub foreign_name: Option<String>,
+    /// `QuantityOnStock`, rounded.
+    pub qty_on_stock: Option<i32>,
+    /// The item as the Service Layer sent it, stored in `original_record_body`.
+    pub raw: Value,
+}
+
+/// A page item's stored record and latest event on this connection.
+struct ExistingRecord {
+    record: inventory_record::Model,
+    event: Option<inventory_record_event::Model>,
+}
+// END STRUCTS AND ENUMS
+
+
+/// BEGUN IMPLEMENTATION ///
+impl SapAdapter {
+    pub fn new(db: DatabaseConnection) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(SAP_REQUEST_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+        Self { db, client }
+    }
+
+    ///the stored session when it is still good, otherwise a new one (stored for next time)
+    pub async fn session(
+        &self,
+        conn: &connection_identity::Model,
+        creds: &erp_connection_credentials::Model,
+    ) -> Result<SapSession, SapError> {
+        let margin = chrono::Duration::seconds(SESSION_EXPIRY_MARGIN_SECS);
+        if let (Some(token), Some(expires_at)) = (&creds.session_token, creds.session_expires_at)
+            && expires_at.with_timezone(&Utc) > Utc::now() + margin
+        {
+            return Ok(SapSession {
+                token: token.clone(),
+                expires_at: expires_at.with_timezone(&Utc),
+            });
+        }
+        self.login(conn, creds).await
+    }
+
+    ///`POST /Login`; stores the session in the connection's credentials
+    pub async fn login(
+        &self,
+        conn: &connection_identity::Model,
+        creds: &erp_connection_credentials::Model,
+    ) -> Result<SapSession, SapError> {
+        let login = login_details(conn, creds)?;
+        let body = json!({
+            "CompanyDB": login.company_db,
+            "UserName": login.username,
+            "Password": login.password,
+        });
+        let res = self
+            .client
+            .post(format!("{}/Login", login.base_url))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await?;
+        let status = res.status();
+        let text = res.text().await?;
+        if !status.is_success() {
+            return Err(SapError::Login(format!("{} {}", status, service_layer_message(&text))));
+        }
+        let parsed: LoginResponse = serde_json::from_str(&text)
+            .map_err(|e| SapError::Login(format!("unreadable login response: {e}")))?;
+
+        let timeout = parsed.session_timeout.unwrap_or(DEFAULT_SESSION_TIMEOUT_MINUTES);
+        let session = SapSession {
+            token: parsed.session_id,
+            expires_at: Utc::now() + chrono::Duration::minutes(timeout),
+        };
+        erp_connection_credentials::Entity::update_many()
+            .col_expr(
+                erp_connection_credentials::Column::SessionToken,
+                Expr::value(session.token.clone()),
+            )
+            .col_expr(
+                erp_connection_credentials::Column::SessionExpiresAt,
+                Expr::value(chrono::DateTime::<chrono::FixedOffset>::from(session.expires_at)),
+            )
+            .col_expr(
+                erp_connection_credentials::Column::UpdatedAt,
+                Expr::value(chrono::DateTime::<chrono::FixedOffset>::from(Utc::now())),
+            )
+            .filter(erp_connection_credentials::Column::Id.eq(creds.id))
+            .exec(&self.db)
+            .await?;
+        Ok(session)
+    }
+
+    ///sends `request`, logging in again and resending once when the session was dropped
+    async fn get_page(
+        &self,
+        conn: &connection_identity::Model,
+        request: &PullRequest,
+    ) -> Result<PullResponse, SapError> {
+        let creds = erp_connection_credentials::Entity::find()
+            .filter(erp_connection_credentials::Column::ConnectionId.eq(conn.id))
+            .one(&self.db)
+            .await?
+            .ok_or(SapError::MissingCredentials("credentials"))?;
+        let base_url = login_details(conn, &creds)?.base_url;
+        let url = format!("{}/{}", base_url, request.payload);
+
+        let mut session = self.session(conn, &creds).await?;
+        let mut relogged = false;
+        loop {
+            let res = self
+                .client
+                .get(&url)
+                .header(reqwest::header::COOKIE, format!("B1SESSION={}", session.token))
+                .header(reqwest::header::ACCEPT, "application/json")
+                .send()
+                .await?;
+            let status = res.status();
+            if status == reqwest::StatusCode::UNAUTHORIZED && !relogged {
+                tracing::info!(connection_id = conn.id, "SAP session expired; logging in again");
+                session = self.login(conn, &creds).await?;
+                relogged = true;
+                continue;
+            }
+
+            let text = res.text().await?;
+            return Ok(match status.is_success() {
+                true => PullResponse {
+                    body: Some(text),
+                    error: None,
+                },
+                false => PullResponse {
+                    body: None,
+                    error: Some(format!(
+                        "SAP Service Layer returned {}: {}",
+                        status,
+                        service_layer_message(&text)
+                    )),
+                },
+            });
+        }
+    }
+
+    async fn existing_records(
+        &self,
+        conn: &connection_identity::Model,
+        item_codes: &[&str],
+        txn: &DatabaseTransaction,
+    ) -> Result<HashMap<String, ExistingRecord>, DbErr> {
+        let records = inventory_record::Entity::find()
+            .filter(inventory_record::Column::SystemIdKey.eq(SystemIdKey::Sapo))
+            .filter(inventory_record::Column::SystemId.is_in(item_codes.iter().copied()))
+            .filter(inventory_record::Column::OriginatingConnectionId.eq(conn.id))
+            .all(txn)
+            .await?;
+        if records.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        //only the newest event of each record is compared against
+        let latest_ids = Query::select()
+            .expr(Func::max(Expr::col(inventory_record_event::Column::Id)))
+            .from(inventory_record_event::Entity)
+            .and_where(
+                inventory_record_event::Column::InventoryRecordId
+                    .is_in(records.iter().map(|r| r.id)),
+            )
+            .and_where(inventory_record_event::Column::ConnectionId.eq(conn.id))
+            .group_by_col(inventory_record_event::Column::InventoryRecordId)
+            .to_owned();
+        let mut latest: HashMap<i64, inventory_record_event::Model> =
+            inventory_record_event::Entity::find()
+                .filter(inventory_record_event::Column::Id.in_subquery(latest_ids))
+                .all(txn)
+                .await?
+                .into_iter()
+                .map(|event| (event.inventory_record_id, event))
+                .collect();
+
+        Ok(records
+            .into_iter()
+            .map(|record| {
+                let event = latest.remove(&record.id);
+                (record.system_id.clone(), ExistingRecord { record, event })
+            })
+            .collect())
+    }
+
+    ///creates or updates one item; the applied record is None when nothing changed
+    async fn upsert_item(
+        &self,
+        conn: &connection_identity::Model,
+        item: &SapItem,
+        existing: Option<&ExistingRecord>,
+        txn: &DatabaseTransaction,
+    ) -> Result<(inventory_record::Model, Option<AppliedRecord>), DbErr> {
+        let record = match existing {
+            Some(existing) => existing.record.clone(),
+            None => {
+                InventoryRecordService::new(self.db.clone())
+                    .create(
+                        CreateInventoryRecord {
+                            tenant_id: conn.tenant_id,
+                            originating_connection_id: conn.id,
+                            original_record_body: Some(item.raw.clone()),
+                            system_id_key: SystemIdKey::Sapo,
+                            system_id: item.item_code.clone(),
+                        },
+                        Some(txn),
+                    )
+                    .await?
+            }
+        };
+
+        let latest = existing.and_then(|e| e.event.as_ref());
+        if latest.is_some_and(|event| event_matches_item(event, item)) {
+            return Ok((record, None));
+        }
+
+        let event = InventoryRecordEventService::new(self.db.clone())
+            .create(
+                CreateInventoryRecordEvent {
+                    inventory_record_id: record.id,
+                    connection_id: conn.id,
+                    original_record_body: Some(item.raw.clone()),
+                    price: None,
+                    currency: None,
+                    name: item.name.clone(),
+                    description: item.foreign_name.clone(),
+                    attributes: None,
+                    qty: item.qty_on_stock,
+                    external_code: Some(item.item_code.clone()),
+                    qty_milli: item.qty_on_stock.map(|q| i64::from(q) * 1000),
+                },
+                Some(txn),
+            )
+            .await?;
+
+        let applied = AppliedRecord {
+            record_id: item.item_code.clone(),
+            inventory_record_event_id: event.id,
+            created: existing.is_none(),
+        };
+        Ok((record, Some(applied)))
+    }
+}
+
+impl PullAdapter for SapAdapter {
+    fn next_request(
+        &self,
+        _conn: &connection_identity::Model,
+        sync_state: &erp_connection_sync_state::Model,
+        _event: &sync_event::Model,
+    ) -> PullRequest {
+        let skip = match SyncCursor::of(sync_state) {
+            Some(SyncCursor::RestOffset { skip }) => skip,
+            _ => 0,
+        };
+        PullRequest {
+            payload: items_path(skip),
+        }
+    }
+
+    async fn apply_response(
+        &self,
+        conn: &connection_identity::Model,
+        _event: &sync_event::Model,
+        body: &str,
+        txn: &DatabaseTransaction,
+    ) -> Result<PullOutcome, PullError> {
+        let (items, cursor) = parse_items_page(body)?;
+
+        let codes: Vec<&str> = items.iter().map(|i| i.item_code.as_str()).collect();
+        let existing = self.existing_records(conn, &codes, txn).await?;
+
+        //items are written one after another; a failed write fails the page, which the
+        //orchestrator records on the event and run
+        let outbox_svc = OutboxService::new(self.db.clone());
+        let mut applied: Vec<AppliedRecord> = Vec::new();
+        for item in &items {
+            let (record, written) = self
+                .upsert_item(conn, item, existing.get(&item.item_code), txn)
+                .await?;
+            let Some(written) = written else {
+                continue;
+            };
+            //written in the page transaction so the message exists iff the change commits
+            outbox_svc
+                .enqueue(txn, inventory_upserted_message(conn, &record, item))
+                .await?;
+            applied.push(written);
+        }
+
+        Ok(PullOutcome {
+            cursor,
+            errors: vec![],
+            rejected: vec![],
+            applied,
+            warnings: vec![],
+        })
+    }
+}
+
+impl ApiPullAdapter for SapAdapter {
+    fn handles(&self, conn: &connection_identity::Model) -> bool {
+        conn.erp_provider == ErpProvider::Sap && conn.erp_type == ErpProviderType::Api
+    }
+
+    async fn fetch(&self, conn: &connection_identity::Model, request: PullRequest) -> PullResponse {
+        self.get_page(conn, &request).await.unwrap_or_else(|e| {
+            tracing::warn!(connection_id = conn.id, "SAP fetch failed: {}", e);
+            PullResponse {
+                body: None,
+                error: Some(e.to_string()),
+            }
+        })
+    }
+}
+
+///the Service Layer root, company database and user of a connection
+fn login_details(
+    conn: &connection_identity::Model,
+    creds: &erp_connection_credentials::Model,
+) -> Result<SapLogin, SapError> {
+    let required = |value: &Option<String>, field: &'static str| {
+        value
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_string)
+            .ok_or(SapError::MissingCredentials(field))
+    };
+    Ok(SapLogin {
+        base_url: required(&creds.issuer_base_url, "issuer_base_url")?
+            .trim_end_matches('/')
+            .to_string(),
+        company_db: required(&conn.provider_tenant_id, "provider_tenant_id")?,
+        username: required(&creds.provider_user_id, "provider_user_id")?,
+        password: required(&creds.provider_password, "provider_password")?,
+    })
+}
+
+///the `Items` query for the page starting at `skip`, relative to the Service Layer root
+pub fn items_path(skip: u64) -> String {
+    format!(
+        "Items?$select={}&$orderby=ItemCode&$skip={}&$top={}",
+        ITEM_FIELDS, skip, SAP_PAGE_SIZE
+    )
+}
+
+///the items of a page and the cursor of the next one (None on the last page)
+pub fn parse_items_page(body: &str) -> Result<(Vec<SapItem>, Option<Value>), PullError> {
+    let page: ItemsPage = serde_json::from_str(body)
+        .map_err(|e| PullError::Provider(format!("SAP items parse error: {e}")))?;
+
+    let items = page
+        .value
+        .into_iter()
+        .filter_map(|raw| {
+            let item_code = raw.get("ItemCode").and_then(Value::as_str)?.to_string();
+            let text = |key: &str| raw.get(key).and_then(Value::as_str).map(str::to_string);
+            Some(SapItem {
+                name: text("ItemName"),
+                foreign_name: text("ForeignName"),
+                qty_on_stock: raw
+                    .get("QuantityOnStock")
+                    .and_then(Value::as_f64)
+                    .map(|q| q.round() as i32),
+                item_code,
+                raw,
+            })
+        })
+        .collect();
+
+    //`Items?$skip=200` (the query string is otherwise ours)
+    let cursor = page
+        .next_link
+        .as_deref()
+        .and_then(|link| link.split(['?', '&']).find_map(|p| p.strip_prefix("$skip=")))
+        .and_then(|skip| skip.parse::<u64>().ok())
+        .map(|skip| SyncCursor::RestOffset { skip }.to_value());
+    Ok((items, cursor))
+}
+
+///the `error.message.value` of a Service Layer error body, or the body itself
+fn service_layer_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v.pointer("/error/message/value").and_then(Value::as_str).map(str::to_string))
+        .unwrap_or_else(|| body.chars().take(200).collect())
+}
+
+///whether `event` already holds the item's values
+fn event_matches_item(event: &inventory_record_event::Model, item: &SapItem) -> bool {
+    event.name == item.name
+        && event.description == item.foreign_name
+        && event.qty == item.qty_on_stock
+        && event.external_code.as_deref() == Some(item.item_code.as_str())
+}
+
+///the `inventory.upserted` message for one SAP item
+fn inventory_upserted_message(
+    conn: &connection_identity::Model,
+    record: &inventory_record::Model,
+    item: &SapItem,
+) -> OutboxPayload {
+    OutboxPayload {
+        topic: TOPIC_INVENTORY_UPSERTED.to_string(),
+        payload: json!({
+            "tenant_id": conn.tenant_id,
+            "connection_uuid": conn.uuid,
+            "inventory_record_uuid": record.uuid,
+            "system_id_key": "sapo",
+            "system_id": record.system_id,
+            "name": item.name,
+            "qty": item.qty_on_stock,
+        }),
+    }
+}
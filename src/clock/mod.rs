@@ -0,0 +1,61 @@
+//! Time source for services with time-dependent logic.
+//!
+//! Sync locks, pull backoff and connection status timestamps read the time through a
+//! `Clock` instead of calling `chrono::Utc::now()` directly, so tests can pin it with a
+//! `FixedClock`. Services default to `SystemClock` and take another one via `with_clock`.
+
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+
+/// Clock shared between a service and the services it builds.
+pub type SharedClock = Arc<dyn Clock>;
+
+/// BEGUN STRUCTS AND ENUMS ///
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Wall-clock time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+/// A clock that only moves when told to.
+#[derive(Debug)]
+pub struct FixedClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+/// BEGUN IMPLEMENTATION ///
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+impl FixedClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            now: Mutex::new(now),
+        }
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+///the default clock for services
+pub fn system() -> SharedClock {
+    Arc::new(SystemClock)
+}
@@ -1,4 +1,10 @@
-use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use std::time::Duration;
+
+use futures_util::future::try_join_all;
+use sea_orm::{
+    ConnectOptions, ConnectionTrait, Database, DatabaseConnection, DbBackend, DbErr,
+    TransactionTrait,
+};
 use super::env;
 
 ///creates and returns a database connection using central config
@@ -16,9 +22,79 @@ pub async fn connect() -> Result<DatabaseConnection, sea_orm::DbErr> {
 
     tracing::info!("Connecting to database...");
 
-    let conn = Database::connect(opt).await?;
+    let mut conn = Database::connect(opt).await?;
+    //every query's duration goes to the db_query_duration_seconds histogram
+    conn.set_metric_callback(super::metrics::observe_db_query);
 
     tracing::info!("Database connection established");
 
+    if db.pool_warmup {
+        warmup(&conn, db.min_connections).await?;
+    }
+
     Ok(conn)
 }
+
+///opens `connections` pooled connections at once (at least one) and runs `SELECT 1` on
+///each, so an unreachable database or an undersized pool fails at startup instead of as
+///acquire timeouts in handlers
+pub async fn warmup(db: &DatabaseConnection, connections: u32) -> Result<(), DbErr> {
+    let connections = connections.max(1);
+    tracing::info!(connections, "Warming up database pool...");
+
+    //each open transaction holds its own connection until the vec is dropped
+    let txns = try_join_all((0..connections).map(|_| db.begin()))
+        .await
+        .map_err(|e| warmup_error(connections, e))?;
+    for txn in &txns {
+        txn.execute_unprepared("SELECT 1")
+            .await
+            .map_err(|e| warmup_error(connections, e))?;
+    }
+    drop(txns);
+
+    tracing::info!(connections, "Database pool warmed up");
+    Ok(())
+}
+
+fn warmup_error(connections: u32, e: DbErr) -> DbErr {
+    DbErr::Custom(format!(
+        "database pool warmup failed: could not open {connections} connection(s) and run SELECT 1 \
+         (is the database reachable, and DB_MIN_CONNECTIONS <= DB_MAX_CONNECTIONS?): {e}"
+    ))
+}
+
+///connections currently open in the pool and how many of them are idle; None for
+///non-Postgres connections
+pub fn pool_stats(db: &DatabaseConnection) -> Option<(u32, usize)> {
+    match db.get_database_backend() {
+        DbBackend::Postgres => {
+            let pool = db.get_postgres_connection_pool();
+            Some((pool.size(), pool.num_idle()))
+        }
+        _ => None,
+    }
+}
+
+///logs pool size/active/idle every `interval`; warns when every connection is in use,
+///which is what precedes acquire timeouts
+pub fn spawn_pool_health_logger(db: DatabaseConnection, interval: Duration) {
+    let max_connections = env::get().db.max_connections;
+
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let Some((size, idle)) = pool_stats(&db) else {
+                return;
+            };
+            let active = (size as usize).saturating_sub(idle);
+
+            if size >= max_connections && idle == 0 {
+                tracing::warn!(size, active, idle, max_connections, "Database pool exhausted");
+            } else {
+                tracing::info!(size, active, idle, max_connections, "Database pool health");
+            }
+        }
+    });
+}
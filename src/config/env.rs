@@ -1,4 +1,5 @@
 use axum::http::Method;
+use entity::sea_orm_active_enums::ErpEnvironment;
 use std::env;
 use std::sync::OnceLock;
 use std::time::Duration;
@@ -16,6 +17,14 @@ pub struct AppConfig {
     pub hosts: HostsConfig,
     pub middleware: MiddlewareConfig,
     pub logging: LoggingConfig,
+    pub outbox: OutboxConfig,
+    pub telemetry: TelemetryConfig,
+    pub providers: ProvidersConfig,
+    pub sync: SyncConfig,
+    pub rate_limit: RateLimitConfig,
+    pub jobs: JobsConfig,
+    pub credentials: CredentialsConfig,
+    pub tenant: TenantConfig,
 }
 
 #[derive(Debug)]
@@ -23,6 +32,12 @@ pub struct ServerConfig {
     pub port: String,
     pub rust_log: String,
     pub base_url: Option<String>,
+    ///external origin (e.g. `https://api.example.com`) used for absolute URLs when the
+    ///request doesn't come through a trusted proxy; see config::public_url
+    pub public_base_url: Option<String>,
+    ///start in maintenance mode (503 for everything but health/metrics); see
+    ///middleware::maintenance
+    pub maintenance_mode: bool,
 }
 
 #[derive(Debug)]
@@ -35,6 +50,10 @@ pub struct DatabaseConfig {
     pub idle_timeout: Duration,
     pub max_lifetime: Duration,
     pub sqlx_logging: bool,
+    ///open `min_connections` and run `SELECT 1` on each before serving
+    pub pool_warmup: bool,
+    ///how often pool size/idle counts are logged; disabled when None
+    pub pool_health_log_interval: Option<Duration>,
 }
 
 #[derive(Debug)]
@@ -60,7 +79,104 @@ pub struct MiddlewareConfig {
     pub api_token_auth_enabled: bool,
     pub ip_address_auth_enabled: bool,
     pub request_logging_enabled: bool,
+    ///log (truncated, redacted) request bodies of requests the auth middlewares reject
+    pub log_request_body: bool,
     pub trusted_proxies: Vec<String>,
+    ///secret for the /admin/api-tokens routes; those routes are disabled when unset
+    pub admin_token: Option<String>,
+    ///IP ranges allowed to scrape /metrics (see security::metrics_access)
+    pub metrics_allowed_cidrs: Vec<String>,
+    ///bearer token that also admits a /metrics scrape; /metrics is open while this and
+    ///metrics_allowed_cidrs are both unset
+    pub metrics_token: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct OutboxConfig {
+    pub relay_enabled: bool,
+    pub stream_key: String,
+    pub batch_size: u64,
+    pub poll_interval: Duration,
+}
+
+#[derive(Debug)]
+pub struct TelemetryConfig {
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+#[derive(Debug)]
+pub struct ProvidersConfig {
+    pub qbo_api_base_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct SyncConfig {
+    ///retries of a page transaction after a deadlock or serialization failure
+    pub transient_retries: u32,
+    ///internal poll scheduler for API connections (see sync::scheduler)
+    pub scheduler_enabled: bool,
+    ///how often the scheduler looks for due connections
+    pub scheduler_tick: Duration,
+    ///upper bound for the random delay before each scheduled pull
+    pub scheduler_jitter: Duration,
+    ///only poll connections of this environment (None: any); see
+    ///QbdPollService::with_required_environment
+    pub required_environment: Option<ErpEnvironment>,
+}
+
+#[derive(Debug)]
+pub struct RateLimitConfig {
+    ///per-tenant limit on authenticated API requests (see middleware::rate_limit)
+    pub enabled: bool,
+    ///requests allowed per tenant within `window`
+    pub requests: u64,
+    pub window: Duration,
+}
+
+#[derive(Debug)]
+pub struct JobsConfig {
+    ///background job workers started at boot (see jobs::worker); 0 disables them
+    pub workers: usize,
+    ///failed attempts before a job is moved to the dead-letter list
+    pub max_attempts: u32,
+    ///how long a worker blocks waiting for a job before checking again
+    pub block: Duration,
+}
+
+#[derive(Debug)]
+pub struct CredentialsConfig {
+    ///refuse `enc_scheme = "none"` credentials except for the auth types in
+    ///erp_connection_credentials::codec::PLAINTEXT_AUTH_TYPES
+    pub require_encryption: bool,
+    ///how often the credentials_expiring_total gauge is refreshed (see
+    ///erp_connection_credentials::expiry); None disables the check
+    pub expiry_check_interval: Option<Duration>,
+    ///days ahead the gauge looks for expiring certificates and refresh tokens
+    pub expiry_window_days: i64,
+    ///Vault server `vault:` secret_storage_refs are read from (see
+    ///erp_connection_credentials::secret_store); None leaves them unresolvable
+    pub vault_addr: Option<String>,
+    pub vault_token: Option<String>,
+    ///Web Connector usernames whose credential check is cached (see
+    ///erp_connection_credentials::lookup_cache); 0 disables the cache
+    pub lookup_cache_size: usize,
+    ///how long a cached credential check is trusted
+    pub lookup_cache_ttl: Duration,
+    ///prefix of generated Web Connector usernames (see
+    ///client_systems::quickbooks::desktop::qwc_credentials)
+    pub qbwc_username_prefix: String,
+    ///length of generated Web Connector passwords
+    pub qbwc_password_min_length: usize,
+    ///generated Web Connector passwords mix lowercase, uppercase and digits
+    pub qbwc_password_mixed_charset: bool,
+}
+
+#[derive(Debug)]
+pub struct TenantConfig {
+    ///tenant (TN_xxx) created at startup and used by create APIs when a request names none,
+    ///for single-tenant deployments (see tenant::default); None requires a tenant_id
+    pub default_tenant_id: Option<String>,
 }
 
 #[derive(Debug)]
@@ -75,7 +191,14 @@ impl AppConfig {
             server: ServerConfig {
                 port: env::var("PORT").unwrap_or_else(|_| "3000".to_string()),
                 rust_log: env::var("RUST_LOG").unwrap_or_else(|_| "debug".to_string()),
-                base_url: Some("/api".to_string())
+                base_url: parse_base_url(env::var("BASE_URL").ok().as_deref()),
+                public_base_url: env::var("PUBLIC_BASE_URL")
+                    .ok()
+                    .map(|v| v.trim().trim_end_matches('/').to_string())
+                    .filter(|v| !v.is_empty()),
+                maintenance_mode: env::var("MAINTENANCE_MODE")
+                    .map(|v| v.to_lowercase() == "true" || v == "1")
+                    .unwrap_or(false),
             },
 
             db: DatabaseConfig {
@@ -116,6 +239,14 @@ impl AppConfig {
                 sqlx_logging: env::var("DB_SQLX_LOGGING")
                     .map(|v| v.to_lowercase() != "false" && v != "0")
                     .unwrap_or(true),
+                pool_warmup: env::var("DB_POOL_WARMUP")
+                    .map(|v| v.to_lowercase() != "false" && v != "0")
+                    .unwrap_or(false),
+                pool_health_log_interval: env::var("DB_POOL_HEALTH_LOG_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|secs| *secs > 0)
+                    .map(Duration::from_secs),
             },
 
             redis: RedisConfig {
@@ -148,6 +279,8 @@ impl AppConfig {
                     "x-custom-host".to_string(),
                     "accept".to_string(),
                     "origin".to_string(),
+                    "if-none-match".to_string(),
+                    "idempotency-key".to_string(),
                 ],
                 allow_credentials: env::var("CORS_ALLOW_CREDENTIALS")
                     .map(|v| v.to_lowercase() != "false" && v != "0")
@@ -176,15 +309,14 @@ impl AppConfig {
                 request_logging_enabled: env::var("REQUEST_LOGGING")
                     .map(|v| v.to_lowercase() != "false" && v != "0")
                     .unwrap_or(true),
-                trusted_proxies: env::var("TRUSTED_PROXIES")
-                    .map(|proxies| {
-                        proxies
-                            .split(',')
-                            .map(|s| s.trim().to_string())
-                            .filter(|s| !s.is_empty())
-                            .collect()
-                    })
-                    .unwrap_or_default(),
+                log_request_body: env::var("LOG_REQUEST_BODY")
+                    .map(|v| v.to_lowercase() == "true" || v == "1")
+                    .unwrap_or(false),
+                trusted_proxies: parse_trusted_proxies(env::var("TRUSTED_PROXIES").ok().as_deref()),
+                admin_token: env::var("ADMIN_TOKEN").ok().filter(|v| !v.is_empty()),
+                //same comma-separated format as TRUSTED_PROXIES
+                metrics_allowed_cidrs: parse_trusted_proxies(env::var("METRICS_ALLOWED_CIDRS").ok().as_deref()),
+                metrics_token: env::var("METRICS_REQUIRE_AUTH").ok().filter(|v| !v.is_empty()),
             },
 
             logging: LoggingConfig {
@@ -193,16 +325,208 @@ impl AppConfig {
                     "cookie".to_string(),
                     "set-cookie".to_string(),
                     "x-api-key".to_string(),
+                    "x-admin-token".to_string(),
                     "x-auth-token".to_string(),
                     "x-access-token".to_string(),
                     "x-refresh-token".to_string(),
                     "proxy-authorization".to_string(),
                 ],
             },
+
+            outbox: OutboxConfig {
+                relay_enabled: env::var("OUTBOX_RELAY_ENABLED")
+                    .map(|v| v.to_lowercase() != "false" && v != "0")
+                    .unwrap_or(true),
+                stream_key: env::var("OUTBOX_STREAM_KEY")
+                    .unwrap_or_else(|_| "erp-proxy:outbox".to_string()),
+                batch_size: env::var("OUTBOX_BATCH_SIZE")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(100),
+                poll_interval: Duration::from_millis(
+                    env::var("OUTBOX_POLL_INTERVAL_MS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(1000),
+                ),
+            },
+
+            telemetry: TelemetryConfig {
+                otlp_endpoint: env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                    .ok()
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty()),
+                service_name: env::var("OTEL_SERVICE_NAME")
+                    .unwrap_or_else(|_| "erp-proxy-server".to_string()),
+            },
+
+            providers: ProvidersConfig {
+                qbo_api_base_url: env::var("QBO_API_BASE_URL")
+                    .ok()
+                    .filter(|v| !v.trim().is_empty()),
+            },
+
+            sync: SyncConfig {
+                transient_retries: env::var("SYNC_TRANSIENT_RETRIES")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(3),
+                scheduler_enabled: env::var("SYNC_SCHEDULER_ENABLED")
+                    .map(|v| v.to_lowercase() != "false" && v != "0")
+                    .unwrap_or(false),
+                scheduler_tick: Duration::from_secs(
+                    env::var("SYNC_SCHEDULER_TICK_SECS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .filter(|secs| *secs > 0)
+                        .unwrap_or(30),
+                ),
+                scheduler_jitter: Duration::from_secs(
+                    env::var("SYNC_SCHEDULER_JITTER_SECS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(10),
+                ),
+                required_environment: match env::var("CONNECTION_ENVIRONMENT_MODE")
+                    .map(|v| v.to_lowercase())
+                    .as_deref()
+                {
+                    Ok("production_only") => Some(ErpEnvironment::Production),
+                    Ok("sandbox_only") => Some(ErpEnvironment::Sandbox),
+                    _ => None,
+                },
+            },
+
+            rate_limit: RateLimitConfig {
+                enabled: env::var("RATE_LIMIT_ENABLED")
+                    .map(|v| v.to_lowercase() != "false" && v != "0")
+                    .unwrap_or(false),
+                requests: env::var("RATE_LIMIT_REQUESTS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|requests| *requests > 0)
+                    .unwrap_or(600),
+                window: Duration::from_secs(
+                    env::var("RATE_LIMIT_WINDOW_SECS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .filter(|secs| *secs > 0)
+                        .unwrap_or(60),
+                ),
+            },
+
+            jobs: JobsConfig {
+                workers: env::var("JOBS_WORKERS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(2),
+                max_attempts: env::var("JOBS_MAX_ATTEMPTS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|attempts| *attempts > 0)
+                    .unwrap_or(5),
+                block: Duration::from_secs(
+                    env::var("JOBS_BLOCK_SECS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .filter(|secs| *secs > 0)
+                        .unwrap_or(5),
+                ),
+            },
+
+            credentials: CredentialsConfig {
+                require_encryption: env::var("REQUIRE_CREDENTIAL_ENCRYPTION")
+                    .map(|v| v.to_lowercase() == "true" || v == "1")
+                    .unwrap_or(false),
+                expiry_check_interval: env::var("CREDENTIAL_EXPIRY_CHECK_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .or(Some(3600))
+                    .filter(|secs| *secs > 0)
+                    .map(Duration::from_secs),
+                expiry_window_days: env::var("CREDENTIAL_EXPIRY_WINDOW_DAYS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|days| (1..=365).contains(days))
+                    .unwrap_or(30),
+                vault_addr: env::var("VAULT_ADDR").ok().filter(|v| !v.is_empty()),
+                vault_token: env::var("VAULT_TOKEN").ok().filter(|v| !v.is_empty()),
+                lookup_cache_size: env::var("CREDENTIAL_CACHE_SIZE")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(1000),
+                lookup_cache_ttl: Duration::from_secs(
+                    env::var("CREDENTIAL_CACHE_TTL_SECS")
+                        .ok()
+                        .and_then(|v| v.parse().ok())
+                        .unwrap_or(60),
+                ),
+                qbwc_username_prefix: parse_qbwc_username_prefix(
+                    env::var("QBWC_USERNAME_PREFIX").ok().as_deref(),
+                ),
+                qbwc_password_min_length: env::var("QBWC_PASSWORD_MIN_LENGTH")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .filter(|length| (8..=64).contains(length))
+                    .unwrap_or(16),
+                qbwc_password_mixed_charset: env::var("QBWC_PASSWORD_MIXED_CHARSET")
+                    .map(|v| v.to_lowercase() != "false" && v != "0")
+                    .unwrap_or(true),
+            },
+            tenant: TenantConfig {
+                default_tenant_id: env::var("DEFAULT_TENANT_ID")
+                    .ok()
+                    .map(|v| v.trim().to_string())
+                    .filter(|v| !v.is_empty()),
+            },
         }
     }
 }
 
+///parses TRUSTED_PROXIES: comma-separated proxy IPs, blanks dropped; empty when unset
+pub fn parse_trusted_proxies(value: Option<&str>) -> Vec<String> {
+    value
+        .map(|proxies| {
+            proxies
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+///parses BASE_URL, the prefix routes are also mounted under: `/api` when unset, None (root
+///only) when empty or `/`; a missing leading slash is added and a trailing one dropped
+pub fn parse_base_url(value: Option<&str>) -> Option<String> {
+    let Some(value) = value else {
+        return Some("/api".to_string());
+    };
+    let trimmed = value.trim().trim_matches('/');
+    match trimmed.is_empty() {
+        true => None,
+        false => Some(format!("/{}", trimmed)),
+    }
+}
+
+///parses QBWC_USERNAME_PREFIX: `pro_portals_` when unset; a value with characters other than
+///ASCII letters, digits, `_` and `-`, or longer than 32, falls back to it as well. Empty means
+///no prefix
+pub fn parse_qbwc_username_prefix(value: Option<&str>) -> String {
+    const DEFAULT: &str = "pro_portals_";
+    let Some(value) = value.map(str::trim) else {
+        return DEFAULT.to_string();
+    };
+    let valid = value.len() <= 32
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    match valid {
+        true => value.to_string(),
+        false => DEFAULT.to_string(),
+    }
+}
+
 ///initializes the global config from environment variables
 ///call once at startup after dotenvy::dotenv()
 pub fn init() {
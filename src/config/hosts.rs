@@ -7,17 +7,39 @@ pub fn get_allowed_hosts() -> Vec<String> {
 
 ///checks if a host is allowed
 pub fn is_host_allowed(host: &str) -> bool {
-    let allowed = get_allowed_hosts();
+    host_matches(host, &get_allowed_hosts())
+}
 
-    //strip port if present
-    let host_without_port = host.split(':').next().unwrap_or(host);
+///checks a Host header against allowed host patterns
+///"*.example.com" (or ".example.com") matches any subdomain depth of example.com, but not example.com itself
+///every other entry must match exactly; comparison ignores case and the port
+pub fn host_matches(host: &str, allowed: &[String]) -> bool {
+    let host = strip_port(host).to_ascii_lowercase();
 
     allowed.iter().any(|allowed_host| {
-        //exact match
-        host_without_port == allowed_host
-        //wildcard subdomain match (e.g., ".ddev.site" matches "anything.ddev.site")
-        || (allowed_host.starts_with('.') && host_without_port.ends_with(allowed_host))
-        //match subdomains
-        || host_without_port.ends_with(&format!(".{}", allowed_host))
+        let allowed_host = allowed_host.trim().to_ascii_lowercase();
+        match allowed_host
+            .strip_prefix("*.")
+            .or_else(|| allowed_host.strip_prefix('.'))
+        {
+            //wildcard subdomain match (e.g., "*.ddev.site" matches "a.b.ddev.site")
+            Some(suffix) if !suffix.is_empty() => host
+                .strip_suffix(suffix)
+                .is_some_and(|sub| sub.len() > 1 && sub.ends_with('.')),
+            Some(_) => false,
+            //exact match
+            None => host == allowed_host,
+        }
     })
 }
+
+///strips the port, keeping bracketed IPv6 literals intact (e.g., "[::1]:3000" -> "[::1]")
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        return match host.find(']') {
+            Some(end) => &host[..=end],
+            None => host,
+        };
+    }
+    host.split(':').next().unwrap_or(host)
+}
@@ -5,6 +5,10 @@ pub static REGISTRY: OnceLock<Registry> = OnceLock::new();
 pub static HTTP_REQUESTS_TOTAL: OnceLock<IntCounterVec> = OnceLock::new();
 pub static HTTP_REQUEST_DURATION: OnceLock<HistogramVec> = OnceLock::new();
 pub static HTTP_REQUESTS_IN_FLIGHT: OnceLock<IntGauge> = OnceLock::new();
+pub static DB_QUERY_DURATION: OnceLock<HistogramVec> = OnceLock::new();
+pub static CONNECTION_RUNS_TOTAL: OnceLock<IntCounterVec> = OnceLock::new();
+pub static CREDENTIALS_EXPIRING_TOTAL: OnceLock<IntGauge> = OnceLock::new();
+pub static DOMAIN_EVENTS_TOTAL: OnceLock<IntCounterVec> = OnceLock::new();
 
 ///initializes prometheus metrics registry and registers all metrics
 pub fn init_metrics() {
@@ -32,6 +36,35 @@ pub fn init_metrics() {
     )
     .expect("Failed to create http_requests_in_flight metric");
 
+    //database query duration histogram, fed by the connection's metric callback
+    let db_query_duration = HistogramVec::new(
+        HistogramOpts::new("db_query_duration_seconds", "Database query duration in seconds")
+            .buckets(vec![0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5]),
+        &["operation", "entity"],
+    )
+    .expect("Failed to create db_query_duration metric");
+
+    //connection runs by the status they were created or updated to
+    let connection_runs_total = IntCounterVec::new(
+        Opts::new("connection_runs_total", "Total number of connection runs by status"),
+        &["status"],
+    )
+    .expect("Failed to create connection_runs_total metric");
+
+    //connections with a certificate or refresh token expiring soon, set by the expiry check
+    let credentials_expiring_total = IntGauge::new(
+        "credentials_expiring_total",
+        "Number of connections with credentials expiring within the configured window",
+    )
+    .expect("Failed to create credentials_expiring_total metric");
+
+    //domain events published on the event bus, counted by its metrics subscriber
+    let domain_events_total = IntCounterVec::new(
+        Opts::new("domain_events_total", "Total number of domain events by event"),
+        &["event"],
+    )
+    .expect("Failed to create domain_events_total metric");
+
     //register all metrics
     registry
         .register(Box::new(http_requests_total.clone()))
@@ -42,6 +75,18 @@ pub fn init_metrics() {
     registry
         .register(Box::new(http_requests_in_flight.clone()))
         .expect("Failed to register http_requests_in_flight");
+    registry
+        .register(Box::new(db_query_duration.clone()))
+        .expect("Failed to register db_query_duration");
+    registry
+        .register(Box::new(connection_runs_total.clone()))
+        .expect("Failed to register connection_runs_total");
+    registry
+        .register(Box::new(credentials_expiring_total.clone()))
+        .expect("Failed to register credentials_expiring_total");
+    registry
+        .register(Box::new(domain_events_total.clone()))
+        .expect("Failed to register domain_events_total");
 
     //store in static variables
     REGISTRY.set(registry).expect("Failed to set registry");
@@ -54,6 +99,80 @@ pub fn init_metrics() {
     HTTP_REQUESTS_IN_FLIGHT
         .set(http_requests_in_flight)
         .expect("Failed to set http_requests_in_flight");
+    DB_QUERY_DURATION
+        .set(db_query_duration)
+        .expect("Failed to set db_query_duration");
+    CONNECTION_RUNS_TOTAL
+        .set(connection_runs_total)
+        .expect("Failed to set connection_runs_total");
+    CREDENTIALS_EXPIRING_TOTAL
+        .set(credentials_expiring_total)
+        .expect("Failed to set credentials_expiring_total");
+    DOMAIN_EVENTS_TOTAL
+        .set(domain_events_total)
+        .expect("Failed to set domain_events_total");
 
     tracing::info!("Prometheus metrics initialized");
 }
+
+///metric callback for `DatabaseConnection::set_metric_callback`: records each query's
+///duration in `db_query_duration_seconds` (no-op until `init_metrics` has run)
+pub fn observe_db_query(info: &sea_orm::metric::Info<'_>) {
+    if let Some(histogram) = DB_QUERY_DURATION.get() {
+        let (operation, entity) = classify_statement(&info.statement.sql);
+        histogram
+            .with_label_values(&[operation, entity.as_str()])
+            .observe(info.elapsed.as_secs_f64());
+    }
+}
+
+///counts a connection run created with, or updated to, `status` in `connection_runs_total`
+///(no-op until `init_metrics` has run)
+pub fn record_connection_run(status: &str) {
+    if let Some(counter) = CONNECTION_RUNS_TOTAL.get() {
+        counter.with_label_values(&[status]).inc();
+    }
+}
+
+///counts a domain event in `domain_events_total` (no-op until `init_metrics` has run)
+pub fn record_domain_event(event: &str) {
+    if let Some(counter) = DOMAIN_EVENTS_TOTAL.get() {
+        counter.with_label_values(&[event]).inc();
+    }
+}
+
+///sets `credentials_expiring_total` (no-op until `init_metrics` has run)
+pub fn set_credentials_expiring(count: i64) {
+    if let Some(gauge) = CREDENTIALS_EXPIRING_TOTAL.get() {
+        gauge.set(count);
+    }
+}
+
+///the operation (`select`/`insert`/`update`/`delete`/`other`) and table a statement runs
+///against, read from its SQL; the table is `unknown` when it can't be found (e.g. `SELECT 1`)
+pub fn classify_statement(sql: &str) -> (&'static str, String) {
+    let sql = sql.trim_start();
+    let keyword = sql.split_whitespace().next().unwrap_or("").to_ascii_lowercase();
+    let (operation, table_after) = match keyword.as_str() {
+        "select" => ("select", " FROM "),
+        "insert" => ("insert", " INTO "),
+        "update" => ("update", "UPDATE "),
+        "delete" => ("delete", " FROM "),
+        _ => return ("other", "unknown".to_string()),
+    };
+
+    //a subquery source (`FROM (SELECT ...)`, as paginator counts use) is skipped for the
+    //table inside it
+    let entity = sql
+        .match_indices(table_after)
+        .filter_map(|(i, _)| sql[i + table_after.len()..].split_whitespace().next())
+        .find(|table| !table.starts_with('('))
+        .map(|table| {
+            //`"schema"."table"` -> `table`
+            let table = table.rsplit('.').next().unwrap_or(table);
+            table.trim_matches(|c| c == '"' || c == '`' || c == ')').to_string()
+        })
+        .filter(|table| !table.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+    (operation, entity)
+}
@@ -4,8 +4,10 @@ pub mod database;
 pub mod env;
 pub mod hosts;
 pub mod ip_address_auth;
+pub mod public_url;
 pub mod metrics;
 pub mod redis;
+pub mod telemetry;
 
 pub use api_token_auth::is_enabled as is_api_token_auth_enabled;
 pub use cors::{get_allow_credentials, get_allowed_headers, get_allowed_methods, get_allowed_origins};
@@ -14,3 +16,4 @@ pub use hosts::{get_allowed_hosts, is_host_allowed};
 pub use ip_address_auth::is_enabled as is_ip_address_auth_enabled;
 pub use metrics::init_metrics;
 pub use redis::connect as redis_connect;
+pub use telemetry::init_tracing;
@@ -0,0 +1,129 @@
+//! External scheme and host of the server, for absolute URLs such as OAuth redirect URIs.
+//!
+//! Behind a TLS-terminating proxy the app only sees plain HTTP. `X-Forwarded-Proto` and
+//! `X-Forwarded-Host` are honored only when the connecting peer is listed in
+//! `TRUSTED_PROXIES`; from any other peer they are ignored, since a client could set them to
+//! anything. Without trusted forwarded headers `PUBLIC_BASE_URL` is used, and as a last resort
+//! the request's own `Host` header over `http`.
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::header::HOST;
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, StatusCode};
+use std::net::{IpAddr, SocketAddr};
+
+use super::env;
+
+pub const FORWARDED_PROTO_HEADER: &str = "x-forwarded-proto";
+pub const FORWARDED_HOST_HEADER: &str = "x-forwarded-host";
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Scheme and host a client used to reach the server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalOrigin {
+    pub scheme: String,
+    pub host: String,
+}
+
+/// BEGUN IMPLEMENTATION ///
+impl ExternalOrigin {
+    ///parses an absolute base URL (e.g. `PUBLIC_BASE_URL`); any path is ignored
+    pub fn parse(url: &str) -> Option<Self> {
+        let (scheme, rest) = url.trim().split_once("://")?;
+        let scheme = scheme.to_ascii_lowercase();
+        let host = rest.split('/').next().unwrap_or_default();
+        if !matches!(scheme.as_str(), "http" | "https") || host.is_empty() {
+            return None;
+        }
+        Some(Self { scheme, host: host.to_string() })
+    }
+
+    pub fn base_url(&self) -> String {
+        format!("{}://{}", self.scheme, self.host)
+    }
+
+    ///whether the client connected over TLS, i.e. cookies set for it can be `Secure`
+    #[allow(dead_code)]
+    pub fn is_secure(&self) -> bool {
+        self.scheme == "https"
+    }
+}
+
+///whether `peer` is one of the configured trusted proxies
+pub fn is_trusted_peer(peer: Option<IpAddr>, trusted: &[String]) -> bool {
+    peer.is_some_and(|ip| trusted.iter().any(|t| t.parse::<IpAddr>().is_ok_and(|t| t == ip)))
+}
+
+///first value of a (possibly comma-separated) forwarded header
+fn first_forwarded_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(',').next())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+///derives the external origin of a request; see the module docs for the order of sources
+pub fn resolve_origin(
+    headers: &HeaderMap,
+    peer: Option<IpAddr>,
+    trusted: &[String],
+    public_base_url: Option<&str>,
+) -> Option<ExternalOrigin> {
+    let host_header = headers.get(HOST).and_then(|v| v.to_str().ok()).filter(|v| !v.is_empty());
+
+    if is_trusted_peer(peer, trusted)
+        && let Some(proto) = first_forwarded_value(headers, FORWARDED_PROTO_HEADER)
+    {
+        let scheme = proto.to_ascii_lowercase();
+        let host = first_forwarded_value(headers, FORWARDED_HOST_HEADER).or(host_header);
+        if matches!(scheme.as_str(), "http" | "https")
+            && let Some(host) = host
+        {
+            return Some(ExternalOrigin { scheme, host: host.to_string() });
+        }
+    }
+
+    if let Some(origin) = public_base_url.and_then(ExternalOrigin::parse) {
+        return Some(origin);
+    }
+
+    host_header.map(|host| ExternalOrigin {
+        scheme: "http".to_string(),
+        host: host.to_string(),
+    })
+}
+
+///`resolve_origin` with `TRUSTED_PROXIES` and `PUBLIC_BASE_URL` from central config
+pub fn request_origin(headers: &HeaderMap, peer: Option<IpAddr>) -> Option<ExternalOrigin> {
+    let config = env::get();
+    resolve_origin(
+        headers,
+        peer,
+        &config.middleware.trusted_proxies,
+        config.server.public_base_url.as_deref(),
+    )
+}
+
+///absolute redirect URI for an OAuth callback mounted at `path` (e.g.
+///`/api/connections/oauth/callback`) on the given origin
+#[allow(dead_code)]
+pub fn oauth_redirect_uri(origin: &ExternalOrigin, path: &str) -> String {
+    format!("{}/{}", origin.base_url(), path.trim_start_matches('/'))
+}
+
+///the request's external origin; the peer address comes from `ConnectInfo`, which is absent
+///in router tests, so forwarded headers are never trusted there
+impl<S: Send + Sync> FromRequestParts<S> for ExternalOrigin {
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        request_origin(&parts.headers, peer)
+            .ok_or((StatusCode::BAD_REQUEST, "Cannot determine the external host of the request"))
+    }
+}
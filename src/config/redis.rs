@@ -1,4 +1,4 @@
-use redis::{Client, aio::ConnectionManager};
+use redis::{Client, aio::{ConnectionManager, ConnectionManagerConfig}};
 use super::env;
 
 ///creates and returns an async Redis connection manager
@@ -16,6 +16,14 @@ pub async fn connect() -> Result<ConnectionManager, redis::RedisError> {
     Ok(connection_manager)
 }
 
+///opens a connection of its own for blocking commands (e.g. BRPOPLPUSH in jobs::worker):
+///a blocked command holds up everything queued behind it on a multiplexed connection, and
+///the default response timeout would cut it short
+pub async fn connect_blocking() -> Result<ConnectionManager, redis::RedisError> {
+    let config = ConnectionManagerConfig::new().set_response_timeout(None);
+    ConnectionManager::new_with_config(get_client()?, config).await
+}
+
 ///gets a Redis client (for connection pooling or async operations)
 pub fn get_client() -> Result<Client, redis::RedisError> {
     let redis = &env::get().redis;
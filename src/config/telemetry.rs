@@ -0,0 +1,149 @@
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use opentelemetry_sdk::Resource;
+use std::sync::OnceLock;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{reload, EnvFilter, Registry};
+
+///filter used when `RUST_LOG` holds no valid directives
+pub const DEFAULT_LOG_FILTER: &str = "info";
+
+///swaps the global subscriber's filter at runtime (see `set_log_filter`)
+static LOG_FILTER: OnceLock<reload::Handle<EnvFilter, Registry>> = OnceLock::new();
+
+#[derive(Debug)]
+pub enum TelemetryError {
+    ///the OTLP exporter could not be built
+    Exporter(String),
+    ///a global subscriber is already installed, or none is installed to reload
+    Subscriber(String),
+    ///the log filter directives could not be parsed
+    Filter(String),
+}
+
+impl std::fmt::Display for TelemetryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TelemetryError::Exporter(msg) => write!(f, "OTLP exporter error: {}", msg),
+            TelemetryError::Subscriber(msg) => write!(f, "Tracing subscriber error: {}", msg),
+            TelemetryError::Filter(msg) => write!(f, "Invalid log filter: {}", msg),
+        }
+    }
+}
+
+///keeps the tracer provider alive; dropping it flushes pending spans
+pub struct TelemetryGuard {
+    provider: Option<SdkTracerProvider>,
+}
+
+impl TelemetryGuard {
+    ///whether spans are exported over OTLP
+    pub fn is_exporting(&self) -> bool {
+        self.provider.is_some()
+    }
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Some(provider) = self.provider.take()
+            && let Err(e) = provider.shutdown()
+        {
+            eprintln!("Failed to shut down OTLP tracer provider: {}", e);
+        }
+    }
+}
+
+///installs the global tracing subscriber: the fmt logger, plus an OpenTelemetry layer
+///exporting spans over OTLP/HTTP when `otlp_endpoint` is set (no-op otherwise)
+///`log_filter` uses `RUST_LOG` syntax and can be replaced later with `set_log_filter`
+pub fn init_tracing(
+    otlp_endpoint: Option<&str>,
+    service_name: &str,
+    log_filter: &str,
+) -> Result<TelemetryGuard, TelemetryError> {
+    let provider = otlp_endpoint
+        .map(|endpoint| build_tracer_provider(endpoint, service_name))
+        .transpose()?;
+
+    let otel_layer = provider.as_ref().map(|provider| {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer(service_name.to_string()))
+    });
+
+    let (filter, invalid_filter) = match parse_log_filter(log_filter) {
+        Ok(filter) => (filter, None),
+        Err(e) => (EnvFilter::new(DEFAULT_LOG_FILTER), Some(e)),
+    };
+    let (filter_layer, filter_handle) = reload::Layer::new(filter);
+
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(tracing_subscriber::fmt::layer().with_target(false).compact())
+        .with(otel_layer)
+        .try_init()
+        .map_err(|e| TelemetryError::Subscriber(e.to_string()))?;
+    let _ = LOG_FILTER.set(filter_handle);
+
+    if let Some(e) = invalid_filter {
+        tracing::warn!("{}; logging at {}", e, DEFAULT_LOG_FILTER);
+    }
+    if let Some(endpoint) = otlp_endpoint {
+        tracing::info!("OTLP trace export enabled ({})", endpoint);
+    }
+
+    Ok(TelemetryGuard { provider })
+}
+
+///parses `RUST_LOG`-style directives (e.g. `info,sea_orm=debug`); unknown levels and
+///malformed directives are rejected instead of being skipped
+pub fn parse_log_filter(directives: &str) -> Result<EnvFilter, TelemetryError> {
+    let directives = directives.trim();
+    if directives.is_empty() {
+        return Err(TelemetryError::Filter("no directives given".to_string()));
+    }
+    EnvFilter::builder()
+        .parse(directives)
+        .map_err(|e| TelemetryError::Filter(format!("{} ({})", directives, e)))
+}
+
+///replaces the filter of the subscriber installed by `init_tracing`; returns the new
+///filter as it will be reported by `current_log_filter`
+pub fn set_log_filter(directives: &str) -> Result<String, TelemetryError> {
+    let filter = parse_log_filter(directives)?;
+    let handle = LOG_FILTER
+        .get()
+        .ok_or_else(|| TelemetryError::Subscriber("tracing is not initialized".to_string()))?;
+    let applied = filter.to_string();
+    handle
+        .reload(filter)
+        .map_err(|e| TelemetryError::Subscriber(e.to_string()))?;
+    Ok(applied)
+}
+
+///the active filter directives, or None before `init_tracing`
+pub fn current_log_filter() -> Option<String> {
+    LOG_FILTER.get()?.with_current(|filter| filter.to_string()).ok()
+}
+
+///builds a batching tracer provider for the collector at `endpoint` (e.g. http://otel-collector:4318)
+fn build_tracer_provider(
+    endpoint: &str,
+    service_name: &str,
+) -> Result<SdkTracerProvider, TelemetryError> {
+    //the base endpoint is shared by all signals; traces go to /v1/traces
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!("{}/v1/traces", endpoint.trim_end_matches('/')))
+        .build()
+        .map_err(|e| TelemetryError::Exporter(e.to_string()))?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(
+            Resource::builder_empty()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
@@ -0,0 +1,55 @@
+//! The latest error of a connection (no routes).
+//!
+//! The connection's most recent sync event with a `last_error` (by `last_errored_date`), among
+//! the events tied to it through one of its runs or its sync state, with the error message of
+//! the run the event belongs to. QBD status errors and failed pages both land there, so this is
+//! what support looks at first.
+
+use entity::{connection_run, sync_event};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder};
+
+use super::timeline::connection_events;
+
+/// BEGUN STRUCTS AND ENUMS ///
+pub struct LastError {
+    pub event: sync_event::Model,
+    /// The run the event belongs to, if any.
+    pub run: Option<connection_run::Model>,
+}
+
+pub struct LastErrorService {
+    db: DatabaseConnection,
+}
+
+// END STRUCTS AND ENUMS
+
+
+/// BEGUN IMPLEMENTATION ///
+impl LastErrorService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    ///the connection's most recently errored sync event and its run; None when none has errored
+    pub async fn latest(&self, connection_id: i64) -> Result<Option<LastError>, DbErr> {
+        let event = sync_event::Entity::find()
+            .filter(connection_events(connection_id))
+            .filter(sync_event::Column::LastError.is_not_null())
+            .filter(sync_event::Column::LastErroredDate.is_not_null())
+            .order_by_desc(sync_event::Column::LastErroredDate)
+            .order_by_desc(sync_event::Column::Id)
+            .one(&self.db)
+            .await?;
+        let Some(event) = event else {
+            return Ok(None);
+        };
+
+        let run = match event.connection_run_id {
+            Some(run_id) => connection_run::Entity::find_by_id(run_id).one(&self.db).await?,
+            None => None,
+        };
+        Ok(Some(LastError { event, run }))
+    }
+}
+
+// END IMPLEMENTATION
@@ -1,3 +1,9 @@
+pub mod last_error;
+pub mod probe;
+pub mod routes;
 pub mod services;
+pub mod status;
+pub mod timeline;
 
+pub use routes::create_router;
 pub use services::ConnectionIdentityService;
@@ -0,0 +1,259 @@
+//! Connection test / ping (no routes).
+//!
+//! QuickBooks Desktop can't be called from the server (the Web Connector polls us), so its
+//! test only checks that Web Connector credentials exist. API providers get one minimal
+//! authenticated call with the stored access token:
+//!   - QuickBooks Online: `GET {base}/v3/company/{realm_id}/companyinfo/{realm_id}`
+//!   - Salesforce: `GET {instance_url}/services/data/` (instance URL = `issuer_base_url`)
+//!
+//! `ConnectionTestService::run` records API results on the connection: success through
+//! `record_success` (auth_status `connected`), failures through `record_error`.
+
+use std::time::{Duration, Instant};
+
+use entity::sea_orm_active_enums::{ErpEnvironment, ErpProvider, ErpProviderType};
+use entity::{connection_identity, erp_connection_credentials};
+use sea_orm::{ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter};
+
+use super::services::{ConnectionIdentityError, ConnectionIdentityService};
+
+/// Upper bound for the provider call.
+pub const PROBE_TIMEOUT_SECS: u64 = 10;
+
+pub const QBO_PRODUCTION_BASE_URL: &str = "https://quickbooks.api.intuit.com";
+pub const QBO_SANDBOX_BASE_URL: &str = "https://sandbox-quickbooks.api.intuit.com";
+
+//DEBUG AND ERRORS ///
+#[derive(Debug)]
+pub enum ConnectionTestError {
+    ///no test is available for this provider/type
+    Unsupported,
+    NotFound,
+    Db(DbErr),
+}
+
+impl From<DbErr> for ConnectionTestError {
+    fn from(err: DbErr) -> Self {
+        ConnectionTestError::Db(err)
+    }
+}
+
+impl From<ConnectionIdentityError> for ConnectionTestError {
+    fn from(err: ConnectionIdentityError) -> Self {
+        match err {
+            ConnectionIdentityError::NotFound => ConnectionTestError::NotFound,
+            ConnectionIdentityError::Db(e) => ConnectionTestError::Db(e),
+            //record_success/record_error don't touch provider identity
+            ConnectionIdentityError::Conflict(_) => ConnectionTestError::Db(DbErr::Custom(
+                "unexpected identity conflict while recording test result".to_string(),
+            )),
+        }
+    }
+}
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Outcome of one connection test.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProbeOutcome {
+    pub ok: bool,
+    /// Whether the provider answered; `None` when the server can't call it (QuickBooks Desktop).
+    pub reachable: Option<bool>,
+    /// Whether the provider accepted the token; `None` when not determined.
+    pub token_valid: Option<bool>,
+    /// Username the Web Connector must use (QuickBooks Desktop only).
+    pub expected_username: Option<String>,
+    /// Time spent calling the provider.
+    pub latency_ms: u64,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
+}
+
+impl ProbeOutcome {
+    fn success() -> Self {
+        Self {
+            ok: true,
+            reachable: None,
+            token_valid: None,
+            expected_username: None,
+            latency_ms: 0,
+            error_code: None,
+            error_message: None,
+        }
+    }
+
+    fn failure(code: &str, message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error_code: Some(code.to_string()),
+            error_message: Some(message.into()),
+            ..Self::success()
+        }
+    }
+}
+
+pub struct ConnectionTestService {
+    db: DatabaseConnection,
+    client: reqwest::Client,
+    qbo_base_url: Option<String>,
+}
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+///QuickBooks Desktop: the Web Connector can only log in when username and password are stored
+pub fn probe_qbd(creds: Option<&erp_connection_credentials::Model>) -> ProbeOutcome {
+    let username = creds.and_then(|c| c.provider_user_id.clone());
+    let has_password = creds.is_some_and(|c| c.provider_password.is_some());
+
+    match username {
+        Some(username) if has_password => ProbeOutcome {
+            expected_username: Some(username),
+            ..ProbeOutcome::success()
+        },
+        _ => ProbeOutcome::failure(
+            "missing_credentials",
+            "Web Connector username/password are not set; download the .qwc file again",
+        ),
+    }
+}
+
+///calls `url` with the bearer token; 2xx = reachable + valid token, 401/403 = invalid token
+pub async fn probe_api(client: &reqwest::Client, url: &str, access_token: &str) -> ProbeOutcome {
+    let started = Instant::now();
+    let response = client
+        .get(url)
+        .bearer_auth(access_token)
+        .header(reqwest::header::ACCEPT, "application/json")
+        .send()
+        .await;
+    let latency_ms = started.elapsed().as_millis() as u64;
+
+    let outcome = match response {
+        Ok(res) if res.status().is_success() => ProbeOutcome {
+            reachable: Some(true),
+            token_valid: Some(true),
+            ..ProbeOutcome::success()
+        },
+        Ok(res)
+            if res.status() == reqwest::StatusCode::UNAUTHORIZED
+                || res.status() == reqwest::StatusCode::FORBIDDEN =>
+        {
+            ProbeOutcome {
+                reachable: Some(true),
+                token_valid: Some(false),
+                ..ProbeOutcome::failure(
+                    "token_invalid",
+                    format!("Provider rejected the access token ({})", res.status()),
+                )
+            }
+        }
+        Ok(res) => ProbeOutcome {
+            reachable: Some(true),
+            ..ProbeOutcome::failure(
+                "provider_error",
+                format!("Provider returned {}", res.status()),
+            )
+        },
+        Err(e) => ProbeOutcome {
+            reachable: Some(false),
+            ..ProbeOutcome::failure("unreachable", format!("Provider unreachable: {e}"))
+        },
+    };
+
+    ProbeOutcome {
+        latency_ms,
+        ..outcome
+    }
+}
+
+impl ConnectionTestService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(PROBE_TIMEOUT_SECS))
+            .build()
+            .unwrap_or_default();
+        Self {
+            db,
+            client,
+            qbo_base_url: None,
+        }
+    }
+
+    ///overrides the QuickBooks Online API base URL (defaults to production/sandbox by environment)
+    pub fn with_qbo_base_url(mut self, base_url: Option<String>) -> Self {
+        self.qbo_base_url = base_url;
+        self
+    }
+
+    ///tests the connection and, for API providers, records the result on it
+    pub async fn run(
+        &self,
+        conn: &connection_identity::Model,
+    ) -> Result<ProbeOutcome, ConnectionTestError> {
+        let creds = erp_connection_credentials::Entity::find()
+            .filter(erp_connection_credentials::Column::ConnectionId.eq(conn.id))
+            .one(&self.db)
+            .await?;
+
+        let outcome = match (&conn.erp_provider, &conn.erp_type) {
+            (ErpProvider::Quickbooks, ErpProviderType::Desktop | ErpProviderType::Webconnector) => {
+                //nothing was called, so auth_status is left alone
+                return Ok(probe_qbd(creds.as_ref()));
+            }
+            (ErpProvider::Quickbooks, ErpProviderType::Api) | (ErpProvider::Salesforce, _) => {
+                self.probe_api_connection(conn, creds.as_ref()).await
+            }
+            _ => return Err(ConnectionTestError::Unsupported),
+        };
+
+        let identity_svc = ConnectionIdentityService::new(self.db.clone());
+        match (&outcome.error_code, &outcome.error_message) {
+            (Some(code), Some(message)) => {
+                identity_svc
+                    .record_error(conn.uuid, code, message, None)
+                    .await?;
+            }
+            _ => {
+                identity_svc.record_success(conn.uuid, None).await?;
+            }
+        }
+
+        Ok(outcome)
+    }
+
+    async fn probe_api_connection(
+        &self,
+        conn: &connection_identity::Model,
+        creds: Option<&erp_connection_credentials::Model>,
+    ) -> ProbeOutcome {
+        let Some(token) = creds.and_then(|c| c.access_token.as_deref()) else {
+            return ProbeOutcome {
+                token_valid: Some(false),
+                ..ProbeOutcome::failure("missing_credentials", "No access token is stored")
+            };
+        };
+
+        let url = match conn.erp_provider {
+            ErpProvider::Salesforce => creds
+                .and_then(|c| c.issuer_base_url.as_deref())
+                .map(|instance| format!("{}/services/data/", instance.trim_end_matches('/'))),
+            _ => conn.provider_realm_id.as_deref().map(|realm| {
+                let base = self.qbo_base_url.as_deref().unwrap_or(match conn.environment {
+                    ErpEnvironment::Production => QBO_PRODUCTION_BASE_URL,
+                    ErpEnvironment::Sandbox => QBO_SANDBOX_BASE_URL,
+                });
+                format!(
+                    "{}/v3/company/{realm}/companyinfo/{realm}",
+                    base.trim_end_matches('/')
+                )
+            }),
+        };
+        let Some(url) = url else {
+            return ProbeOutcome::failure(
+                "missing_configuration",
+                "Connection has no provider realm id / instance URL to call",
+            );
+        };
+
+        probe_api(&self.client, &url, token).await
+    }
+}
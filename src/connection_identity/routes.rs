@@ -0,0 +1,2551 @@
+use axum::{
+    body::Body,
+    extract::{Extension, OriginalUri, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    middleware::from_fn,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use sea_orm::ActiveEnum;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::audit::AuditContext;
+use crate::client_systems::quickbooks::desktop::field_mapping::QbdFieldMapping;
+use crate::client_systems::quickbooks::desktop::poll_services::{
+    QbdPollError, QbdPollService, ReplayOutcome,
+};
+use crate::config;
+use crate::connection_run::services::{
+    ConnectionRunService, ConnectionRunSummary, RunCursor, DEFAULT_RUNS_PAGE_LIMIT,
+    MAX_RUNS_PAGE_LIMIT,
+};
+use crate::erp_connection_credentials::masked::MaskedCredentialsResponse;
+use crate::idempotency::{fingerprint, idempotent_create, IdempotencyKey, IdempotencyStore};
+use crate::middleware::admin_token_auth_middleware;
+use crate::inventory_records::events_services::{
+    ChangedField, InventoryRecordEventFilter, InventoryRecordEventService, DEFAULT_SEARCH_LIMIT,
+    MAX_SEARCH_LIMIT,
+};
+use crate::inventory_records::batch::{
+    BatchItem, BatchItemResult, BatchItemStatus, InventoryBatchService, MAX_BATCH_ITEMS,
+};
+use crate::inventory_records::export::{inventory_export_stream, ExportFormat, EXPORT_MEDIA_TYPES};
+use crate::inventory_records::routes::{event_to_response, InventoryRecordEventResponse};
+use crate::inventory_records::upsert::{system_id_key_for, InventoryItem};
+use crate::pagination::{paginated_response, PageInfo};
+use crate::quarantine::QuarantineService;
+use crate::security::connection_api_key::{
+    ConnectionApiKeyError, ConnectionApiKeyService, CreateConnectionApiKey,
+};
+use crate::security::AuthenticatedTenant;
+use crate::sync::SyncWindow;
+use crate::tenant::routes::{DeleteResponse, ErrorResponse};
+use crate::tenant::default::tenant_id_or_default;
+use crate::tenant::TenantService;
+use crate::validation::{
+    check_length, parse_enum, parse_optional_enum, FieldError, PathUuid, MAX_DISPLAY_NAME_LEN,
+    MAX_IDENTIFIER_LEN,
+};
+use super::last_error::{LastError, LastErrorService};
+use super::probe::{ConnectionTestError, ConnectionTestService};
+use super::status::{ConnectionStatus, ConnectionStatusService, MAX_STATUS_BATCH_SIZE};
+use super::timeline::{TimelineEntry, TimelineService, DEFAULT_TIMELINE_LIMIT, MAX_TIMELINE_LIMIT};
+use super::services::{
+    ConnectionIdentityError, ConnectionIdentityFilter, ConnectionIdentityService, ConnectionOrder,
+    ConnectionDependents, CreateConnectionIdentity, UpdateConnectionIdentity,
+};
+use entity::{connection_api_key, connection_run};
+use entity::sea_orm_active_enums::{
+    ConnectionRunStatus, ConnectionRunType, Currency, ErpConnectionAuthStatus,
+    ErpConnectionStatus, ErpEnvironment, ErpProvider, ErpProviderAuthType, ErpProviderType,
+    SyncEventStatus, SystemIdKey,
+};
+
+
+/// RESPONSE SCHEMAS ///
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionResponse {
+    pub id: i64,
+    pub uuid: String,
+    pub tenant_id: i64,
+    #[schema(value_type = String)]
+    pub erp_provider: ErpProvider,
+    #[schema(value_type = String)]
+    pub erp_type: ErpProviderType,
+    #[schema(value_type = String)]
+    pub erp_auth_type: ErpProviderAuthType,
+    pub display_name: Option<String>,
+    #[schema(value_type = String)]
+    pub environment: ErpEnvironment,
+    #[schema(value_type = String)]
+    pub status: ErpConnectionStatus,
+    #[schema(value_type = String)]
+    pub auth_status: ErpConnectionAuthStatus,
+    pub is_enabled: bool,
+    pub sync_enabled_push: bool,
+    pub sync_enabled_pull: bool,
+    pub scopes: Option<Vec<String>>,
+    pub provider_realm_id: Option<String>,
+    pub provider_tenant_id: Option<String>,
+    pub company_file_identity: Option<String>,
+    pub company_file_path: Option<String>,
+    pub company_file_id: Option<String>,
+    pub system_version: Option<String>,
+    pub web_connector_app_name: Option<String>,
+    /// qbXML version negotiated with QuickBooks Desktop (QBD connections only).
+    pub qbxml_version: Option<String>,
+    /// When the first full inventory pass finished (QBD connections only); null until then.
+    pub initial_sync_completed_at: Option<String>,
+    /// UTC hours pulls may run in; null when they run at any time.
+    pub sync_window: Option<SyncWindowResponse>,
+    /// QBD fields read for `system_id`, `name` and `external_code`; null for the defaults
+    /// (`ListID`, `Name`, `FullName`).
+    #[schema(value_type = Option<Object>)]
+    pub field_mapping: Option<Value>,
+    pub last_success_at: Option<String>,
+    pub last_error_code: Option<String>,
+    pub last_error_message: Option<String>,
+    pub error_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UpdateConnectionResponse {
+    #[serde(flatten)]
+    pub connection: ConnectionResponse,
+    /// Fields whose value the update changed (`updated_at` aside), e.g. `["display_name"]`;
+    /// empty when every sent value matched the stored one.
+    pub changed_fields: Vec<String>,
+}
+
+/// Pulls run from `start_hour` up to, not including, `end_hour` (UTC); the window wraps past
+/// midnight when `start_hour` is greater.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SyncWindowResponse {
+    pub start_hour: u8,
+    pub end_hour: u8,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PaginatedConnectionsResponse {
+    pub items: Vec<ConnectionResponse>,
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionTestResponse {
+    pub ok: bool,
+    #[schema(value_type = String)]
+    pub erp_provider: ErpProvider,
+    /// Whether the provider answered; null when the server can't call it (QuickBooks Desktop).
+    pub reachable: Option<bool>,
+    /// Whether the provider accepted the stored token; null when not determined.
+    pub token_valid: Option<bool>,
+    /// Username the Web Connector must log in with (QuickBooks Desktop only).
+    pub expected_username: Option<String>,
+    pub latency_ms: u64,
+    pub error_code: Option<String>,
+    pub error_message: Option<String>,
+    /// auth_status after the test (updated for API providers).
+    #[schema(value_type = String)]
+    pub auth_status: ErpConnectionAuthStatus,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ReprocessResponse {
+    /// Records whose stored raw body was re-parsed.
+    pub scanned: usize,
+    /// Records whose derived fields changed; each got a new inventory record event.
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Records without a usable raw body.
+    pub skipped: usize,
+}
+
+/// Outcome of one batch item, in request order.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct InventoryBatchItemResponse {
+    pub index: usize,
+    pub system_id: String,
+    /// created, updated, unchanged or failed.
+    pub status: String,
+    #[schema(value_type = Option<String>)]
+    pub inventory_record_uuid: Option<Uuid>,
+    /// Why the item failed.
+    pub error: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct InventoryBatchResponse {
+    /// The `batch` connection run recording the request.
+    #[schema(value_type = String)]
+    pub run_uuid: Uuid,
+    /// The sync event recording the request.
+    #[schema(value_type = String)]
+    pub sync_event_uuid: Uuid,
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+    pub items: Vec<InventoryBatchItemResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct InventorySearchResponse {
+    /// Newest event of each matching record, best match first.
+    pub items: Vec<InventoryRecordEventResponse>,
+}
+
+/// Rows a hard delete removes with the connection: counted by the delete preview,
+/// removed by the purge.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionDependentsResponse {
+    pub connection_uuid: String,
+    pub credentials: u64,
+    pub sync_states: u64,
+    pub connection_runs: u64,
+    pub sync_events: u64,
+    pub inventory_records: u64,
+    pub inventory_record_events: u64,
+}
+
+/// Status aggregate of one connection.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionStatusResponse {
+    pub uuid: String,
+    #[schema(value_type = String)]
+    pub status: ErpConnectionStatus,
+    #[schema(value_type = String)]
+    pub auth_status: ErpConnectionAuthStatus,
+    pub is_enabled: bool,
+    pub last_success_at: Option<String>,
+    pub last_error_code: Option<String>,
+    pub last_error_message: Option<String>,
+    pub error_at: Option<String>,
+    /// Sync events waiting to be picked up.
+    pub pending_events: u64,
+    /// Sync events being processed.
+    pub in_progress_events: u64,
+    pub runs: ConnectionRunSummaryResponse,
+}
+
+/// Recent connection runs of one connection.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionRunSummaryResponse {
+    /// Runs started in the last 24 hours that succeeded (or are still running).
+    pub success_24h: u64,
+    /// Runs started in the last 24 hours that ended in an error.
+    pub error_24h: u64,
+    /// When the latest errored run failed, however long ago.
+    pub last_error_at: Option<String>,
+    pub last_error_message: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionStatusBatchResponse {
+    /// In request order.
+    pub items: Vec<ConnectionStatusResponse>,
+    /// Requested UUIDs with no connection (or another tenant's connection).
+    pub not_found: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SyncProgressResponse {
+    /// QBD iterator of the pass being paginated; null between passes.
+    pub iterator_id: Option<String>,
+    /// Items QBD reported as still to come in the current pass.
+    pub remaining_count: Option<i64>,
+    /// Item list being pulled (`inventory`, `service`, `non_inventory`).
+    pub item_type: Option<String>,
+    pub event_uuid: Option<String>,
+    /// Status of the recurring inventory sync event (`pending`, `in_progress`, `success`, `error`).
+    #[schema(value_type = Option<String>)]
+    pub event_status: Option<SyncEventStatus>,
+    /// Inventory records pulled from this connection so far.
+    pub records_synced: u64,
+    /// Whether a pass is under way (a cursor is stored or the event is in progress).
+    pub in_progress: bool,
+    /// Estimate such as `"62.5%"`, from records synced and `remaining_count`; null when no
+    /// pass is being paginated.
+    pub percent_complete: Option<String>,
+    /// When the connection's first full pass finished; null while the initial sync is running.
+    pub initial_sync_completed_at: Option<String>,
+}
+
+/// An item a pull could not apply, kept for inspection and replay.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct QuarantinedItemResponse {
+    pub uuid: String,
+    /// Provider id of the item (QBD `ListID`).
+    pub list_id: String,
+    pub item_type: Option<String>,
+    /// The item as parsed from the provider response.
+    #[schema(value_type = Object)]
+    pub raw_body: Value,
+    /// Why the last attempt failed.
+    pub error: String,
+    /// Failed attempts so far (pulls and replays).
+    pub attempts: i32,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PaginatedQuarantineResponse {
+    pub items: Vec<QuarantinedItemResponse>,
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct QuarantineReplayResponse {
+    pub uuid: String,
+    pub list_id: String,
+    /// Always true; a failed replay answers 422 and keeps the item quarantined.
+    pub replayed: bool,
+}
+
+/// One entry of a connection's timeline.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TimelineEntryResponse {
+    /// `run_started`, `run_failed`, `event_success`, `event_error` or `auth_status_changed`.
+    pub kind: String,
+    pub at: String,
+    /// UUID of the run, sync event or audit log entry the entry comes from.
+    pub source_uuid: String,
+    /// Error message of failures; `old -> new` for auth status changes.
+    pub message: Option<String>,
+}
+
+/// One connection run (a poll or push attempt).
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionRunResponse {
+    pub uuid: String,
+    #[schema(value_type = String)]
+    pub status: ConnectionRunStatus,
+    #[schema(value_type = String)]
+    pub run_type: ConnectionRunType,
+    pub error_message: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionRunsResponse {
+    /// Newest first; runs created at the same time are ordered by id.
+    pub items: Vec<ConnectionRunResponse>,
+    /// Pass as `cursor` to get the next (older) page; null on the last page.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct TimelineResponse {
+    /// Newest first.
+    pub items: Vec<TimelineEntryResponse>,
+    /// Pass as `before` to get the next (older) page; null on the last page.
+    pub next_before: Option<String>,
+}
+
+/// The connection's most recently errored sync event and the run it belongs to.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct LastErrorResponse {
+    pub sync_event_uuid: String,
+    /// The event's `last_error`, e.g. `{"errors": [...]}` or a QBD status code and message.
+    #[schema(value_type = Object)]
+    pub last_error: Option<Value>,
+    pub last_errored_date: Option<String>,
+    /// The event's run; null for events outside a run.
+    pub run_uuid: Option<String>,
+    /// The run's `error_message`; null when the run didn't fail.
+    pub run_error_message: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PaginatedConnectionEventsResponse {
+    pub items: Vec<InventoryRecordEventResponse>,
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+}
+
+/// Connection API key metadata; the key itself is never returned after it is minted.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionApiKeyResponse {
+    pub uuid: String,
+    pub display_name: Option<String>,
+    /// First characters of the key (`ck_` and 8 more), to tell keys apart.
+    pub key_prefix: Option<String>,
+    /// When the key was revoked; null while it is usable.
+    pub revoked_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct IssuedConnectionApiKeyResponse {
+    /// The raw key. Shown only in this response: store it now, it can't be read back.
+    pub key: String,
+    #[serde(flatten)]
+    pub metadata: ConnectionApiKeyResponse,
+}
+
+
+/// REQUEST SCHEMAS ///
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateConnectionRequest {
+    /// Owning tenant (TN_xxx format). May be omitted when the server has a default tenant
+    /// (`DEFAULT_TENANT_ID`) and the API token isn't tenant-scoped.
+    pub tenant_id: Option<String>,
+    pub erp_provider: String,
+    pub erp_type: String,
+    pub erp_auth_type: String,
+    pub display_name: Option<String>,
+    pub environment: Option<String>,
+    pub scopes: Option<Vec<String>>,
+    pub provider_realm_id: Option<String>,
+    pub provider_tenant_id: Option<String>,
+    pub company_file_identity: Option<String>,
+    pub company_file_path: Option<String>,
+    pub company_file_id: Option<String>,
+    pub system_version: Option<String>,
+    pub web_connector_app_name: Option<String>,
+    pub sync_enabled_push: Option<bool>,
+    pub sync_enabled_pull: Option<bool>,
+    /// Only pull within these UTC hours; pulls run at any time when omitted.
+    pub sync_window: Option<SyncWindowRequest>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UpdateConnectionRequest {
+    pub display_name: Option<String>,
+    pub environment: Option<String>,
+    pub status: Option<String>,
+    pub auth_status: Option<String>,
+    pub is_enabled: Option<bool>,
+    pub scopes: Option<Vec<String>>,
+    pub provider_realm_id: Option<String>,
+    pub provider_tenant_id: Option<String>,
+    pub company_file_identity: Option<String>,
+    pub company_file_path: Option<String>,
+    pub company_file_id: Option<String>,
+    pub system_version: Option<String>,
+    pub web_connector_app_name: Option<String>,
+    pub sync_enabled_push: Option<bool>,
+    pub sync_enabled_pull: Option<bool>,
+    /// Omit to keep the current window; null removes it.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    #[schema(value_type = Option<SyncWindowRequest>)]
+    pub sync_window: Option<Option<SyncWindowRequest>>,
+    /// QBD fields to read for `system_id`, `name` and `external_code`, e.g.
+    /// `{"external_code": "ManufacturerPartNumber"}`; unset keys keep their default. Omit to
+    /// keep the current mapping; null goes back to the defaults.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    #[schema(value_type = Option<Object>)]
+    pub field_mapping: Option<Option<Value>>,
+}
+
+/// UTC hours (0-23) pulls may run in: from `start_hour` up to, not including, `end_hour`.
+/// `{"start_hour": 22, "end_hour": 6}` wraps past midnight.
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SyncWindowRequest {
+    pub start_hour: i16,
+    pub end_hour: i16,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ListConnectionsQuery {
+    #[param(default = 1)]
+    pub page: Option<u64>,
+    #[param(default = 20)]
+    pub per_page: Option<u64>,
+    pub erp_provider: Option<String>,
+    pub erp_type: Option<String>,
+    pub status: Option<String>,
+    pub auth_status: Option<String>,
+    pub environment: Option<String>,
+    pub is_enabled: Option<bool>,
+    pub display_name: Option<String>,
+    /// `created_at` (newest first) or `health`: auth_status `error`/`needs_reauth` first,
+    /// then the oldest `last_success_at`.
+    #[param(default = "created_at")]
+    pub order_by: Option<String>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct SearchConnectionsQuery {
+    /// Matched case-insensitively (substring) against display_name, provider_realm_id,
+    /// provider_tenant_id, company_file_id, company_file_path and the credentials'
+    /// provider_user_id.
+    pub q: Option<String>,
+    /// Tenant to search (TN_xxx format).
+    pub tenant_id: Option<String>,
+    #[param(default = 1)]
+    pub page: Option<u64>,
+    #[param(default = 20)]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct SearchInventoryQuery {
+    /// Words matched against the records' current name and description (full-text, each
+    /// word as a prefix); queries under 3 characters are matched as a substring instead.
+    pub q: Option<String>,
+    /// Most results to return (1-100).
+    #[param(default = 20)]
+    pub limit: Option<u64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ReprocessQuery {
+    /// Only records changed at or after this time (RFC 3339). All records when omitted.
+    pub since: Option<DateTime<Utc>>,
+}
+
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ListQuarantineQuery {
+    #[param(default = 1)]
+    pub page: Option<u64>,
+    #[param(default = 20)]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ConnectionStatusBatchRequest {
+    /// Connection UUIDs; at most 100, duplicates are answered once.
+    #[schema(value_type = Vec<String>)]
+    pub uuids: Vec<Uuid>,
+}
+
+/// One inventory item pushed by a client.
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct InventoryBatchItemRequest {
+    /// The item's id in the connection's system; repeated ids in one batch fail.
+    pub system_id: String,
+    pub name: Option<String>,
+    /// Price in cents.
+    pub price: Option<i32>,
+    pub qty: Option<i32>,
+    /// Defaults to usd when a price is given.
+    pub currency: Option<String>,
+    pub external_code: Option<String>,
+    /// The item as the source system holds it (a JSON object), stored as the record's raw body.
+    /// Defaults to the other fields.
+    #[schema(value_type = Option<Object>)]
+    pub raw: Option<Value>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct InventoryBatchRequest {
+    /// At most 500 items.
+    pub items: Vec<InventoryBatchItemRequest>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ListRunsQuery {
+    /// The previous page's `next_cursor`. The newest runs when omitted.
+    pub cursor: Option<String>,
+    #[param(default = 50, maximum = 200)]
+    pub limit: Option<u64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct TimelineQuery {
+    /// Only entries strictly older than this time (RFC 3339); the previous page's
+    /// `next_before`. The newest entries when omitted.
+    pub before: Option<DateTime<Utc>>,
+    #[param(default = 50, maximum = 200)]
+    pub limit: Option<u64>,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ListConnectionEventsQuery {
+    /// Only events created at or after this time (RFC 3339).
+    pub since: Option<DateTime<Utc>>,
+    /// Only events created before this time (RFC 3339).
+    pub until: Option<DateTime<Utc>>,
+    /// Comma-separated `price`, `qty`, `name`: only events that changed any of them from the
+    /// record's previous event.
+    pub changed: Option<String>,
+    #[param(default = 1)]
+    pub page: Option<u64>,
+    #[param(default = 20)]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateConnectionApiKeyRequest {
+    pub display_name: Option<String>,
+}
+
+
+/// VALIDATION ///
+///validated create payload with enum strings resolved
+pub struct ValidCreateConnection {
+    pub erp_provider: ErpProvider,
+    pub erp_type: ErpProviderType,
+    pub erp_auth_type: ErpProviderAuthType,
+    pub environment: Option<ErpEnvironment>,
+    pub sync_window: Option<SyncWindow>,
+}
+
+///validated update payload with enum strings resolved
+pub struct ValidUpdateConnection {
+    pub environment: Option<ErpEnvironment>,
+    pub status: Option<ErpConnectionStatus>,
+    pub auth_status: Option<ErpConnectionAuthStatus>,
+    pub sync_window: Option<Option<SyncWindow>>,
+    pub field_mapping: Option<Option<Value>>,
+}
+
+///records an error for hours outside 0-23, or a window that starts and ends at the same hour
+fn parse_sync_window(errors: &mut Vec<FieldError>, window: &SyncWindowRequest) -> Option<SyncWindow> {
+    let mut valid = true;
+    for (field, hour) in [
+        ("sync_window.start_hour", window.start_hour),
+        ("sync_window.end_hour", window.end_hour),
+    ] {
+        if !(0..=23).contains(&hour) {
+            errors.push(FieldError::new(field, "must be between 0 and 23"));
+            valid = false;
+        }
+    }
+    if valid && window.start_hour == window.end_hour {
+        errors.push(FieldError::new(
+            "sync_window",
+            "start_hour and end_hour must differ; omit the window to sync at any time",
+        ));
+    }
+    SyncWindow::new(window.start_hour, window.end_hour)
+}
+
+impl CreateConnectionRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        self.parse().err().unwrap_or_default()
+    }
+
+    ///validates and resolves enum strings in one pass
+    pub fn parse(&self) -> Result<ValidCreateConnection, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        if self.tenant_id.as_deref().is_some_and(|id| id.trim().is_empty()) {
+            errors.push(FieldError::new("tenant_id", "must not be blank"));
+        }
+        let erp_provider = parse_enum::<ErpProvider>(&mut errors, "erp_provider", &self.erp_provider);
+        let erp_type = parse_enum::<ErpProviderType>(&mut errors, "erp_type", &self.erp_type);
+        let erp_auth_type =
+            parse_enum::<ErpProviderAuthType>(&mut errors, "erp_auth_type", &self.erp_auth_type);
+        let environment =
+            parse_optional_enum::<ErpEnvironment>(&mut errors, "environment", self.environment.as_deref());
+        let sync_window = self
+            .sync_window
+            .as_ref()
+            .and_then(|w| parse_sync_window(&mut errors, w));
+
+        check_length(&mut errors, "display_name", self.display_name.as_deref(), MAX_DISPLAY_NAME_LEN);
+        check_identifier_lengths(
+            &mut errors,
+            &[
+                ("provider_realm_id", self.provider_realm_id.as_deref()),
+                ("provider_tenant_id", self.provider_tenant_id.as_deref()),
+                ("company_file_id", self.company_file_id.as_deref()),
+                ("system_version", self.system_version.as_deref()),
+                ("web_connector_app_name", self.web_connector_app_name.as_deref()),
+            ],
+        );
+
+        match (erp_provider, erp_type, erp_auth_type) {
+            (Some(erp_provider), Some(erp_type), Some(erp_auth_type)) if errors.is_empty() => {
+                Ok(ValidCreateConnection {
+                    erp_provider,
+                    erp_type,
+                    erp_auth_type,
+                    environment,
+                    sync_window,
+                })
+            }
+            _ => Err(errors),
+        }
+    }
+}
+
+impl UpdateConnectionRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        self.parse().err().unwrap_or_default()
+    }
+
+    ///validates and resolves enum strings in one pass
+    pub fn parse(&self) -> Result<ValidUpdateConnection, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        let environment =
+            parse_optional_enum::<ErpEnvironment>(&mut errors, "environment", self.environment.as_deref());
+        let status =
+            parse_optional_enum::<ErpConnectionStatus>(&mut errors, "status", self.status.as_deref());
+        let auth_status = parse_optional_enum::<ErpConnectionAuthStatus>(
+            &mut errors,
+            "auth_status",
+            self.auth_status.as_deref(),
+        );
+        let sync_window = self
+            .sync_window
+            .as_ref()
+            .map(|w| w.as_ref().and_then(|w| parse_sync_window(&mut errors, w)));
+        if let Some(Some(mapping)) = &self.field_mapping
+            && let Err(message) = QbdFieldMapping::from_json(mapping)
+        {
+            errors.push(FieldError::new("field_mapping", message));
+        }
+
+        check_length(&mut errors, "display_name", self.display_name.as_deref(), MAX_DISPLAY_NAME_LEN);
+        check_identifier_lengths(
+            &mut errors,
+            &[
+                ("provider_realm_id", self.provider_realm_id.as_deref()),
+                ("provider_tenant_id", self.provider_tenant_id.as_deref()),
+                ("company_file_id", self.company_file_id.as_deref()),
+                ("system_version", self.system_version.as_deref()),
+                ("web_connector_app_name", self.web_connector_app_name.as_deref()),
+            ],
+        );
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(ValidUpdateConnection {
+            environment,
+            status,
+            auth_status,
+            sync_window,
+            field_mapping: self.field_mapping.clone(),
+        })
+    }
+}
+
+impl ListConnectionsQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        self.parse().err().unwrap_or_default()
+    }
+
+    ///validates and resolves enum strings in one pass
+    pub fn parse(&self) -> Result<ConnectionIdentityFilter, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        let filter = ConnectionIdentityFilter {
+            tenant_id: None,
+            erp_provider: parse_optional_enum(&mut errors, "erp_provider", self.erp_provider.as_deref()),
+            erp_type: parse_optional_enum(&mut errors, "erp_type", self.erp_type.as_deref()),
+            status: parse_optional_enum(&mut errors, "status", self.status.as_deref()),
+            auth_status: parse_optional_enum(&mut errors, "auth_status", self.auth_status.as_deref()),
+            environment: parse_optional_enum(&mut errors, "environment", self.environment.as_deref()),
+            is_enabled: self.is_enabled,
+            display_name: self.display_name.clone(),
+        };
+        if self.per_page == Some(0) {
+            errors.push(FieldError::new("per_page", "must be greater than 0"));
+        }
+        if let Some(order_by) = self.order_by.as_deref()
+            && !["created_at", "health"].contains(&order_by)
+        {
+            errors.push(FieldError::new("order_by", "must be one of: created_at, health"));
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(filter)
+    }
+
+    ///the requested order; call after `parse`
+    pub fn order(&self) -> ConnectionOrder {
+        match self.order_by.as_deref() {
+            Some("health") => ConnectionOrder::HealthPriority,
+            _ => ConnectionOrder::CreatedAt,
+        }
+    }
+}
+
+impl SearchConnectionsQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        match self.q.as_deref().map(str::trim) {
+            None | Some("") => errors.push(FieldError::new("q", "is required")),
+            Some(q) => check_length(&mut errors, "q", Some(q), MAX_IDENTIFIER_LEN),
+        }
+        if self.tenant_id.as_deref().is_none_or(|t| t.trim().is_empty()) {
+            errors.push(FieldError::new("tenant_id", "is required"));
+        }
+        if self.per_page == Some(0) {
+            errors.push(FieldError::new("per_page", "must be greater than 0"));
+        }
+
+        errors
+    }
+}
+
+impl SearchInventoryQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+
+        match self.q.as_deref().map(str::trim) {
+            None | Some("") => errors.push(FieldError::new("q", "is required")),
+            Some(q) => check_length(&mut errors, "q", Some(q), MAX_IDENTIFIER_LEN),
+        }
+        if self.limit.is_some_and(|limit| limit == 0 || limit > MAX_SEARCH_LIMIT) {
+            errors.push(FieldError::new(
+                "limit",
+                format!("must be between 1 and {MAX_SEARCH_LIMIT}"),
+            ));
+        }
+
+        errors
+    }
+}
+
+///VARCHAR(255) columns on connection_identity
+impl ListQuarantineQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.per_page == Some(0) {
+            errors.push(FieldError::new("per_page", "must be greater than 0"));
+        }
+        errors
+    }
+}
+
+impl ListRunsQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.limit.is_some_and(|limit| limit == 0 || limit > MAX_RUNS_PAGE_LIMIT) {
+            errors.push(FieldError::new(
+                "limit",
+                format!("must be between 1 and {}", MAX_RUNS_PAGE_LIMIT),
+            ));
+        }
+        if self.cursor.as_deref().is_some_and(|c| RunCursor::decode(c).is_none()) {
+            errors.push(FieldError::new("cursor", "is not a cursor returned by this endpoint"));
+        }
+        errors
+    }
+}
+
+impl TimelineQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.limit.is_some_and(|limit| limit == 0 || limit > MAX_TIMELINE_LIMIT) {
+            errors.push(FieldError::new(
+                "limit",
+                format!("must be between 1 and {}", MAX_TIMELINE_LIMIT),
+            ));
+        }
+        errors
+    }
+}
+
+impl ListConnectionEventsQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if let (Some(since), Some(until)) = (self.since, self.until)
+            && since >= until
+        {
+            errors.push(FieldError::new("until", "must be after since"));
+        }
+        if self
+            .changed_fields()
+            .is_some_and(|fields| fields.iter().any(Option::is_none))
+        {
+            errors.push(FieldError::new("changed", "must list price, qty or name"));
+        }
+        if self.per_page == Some(0) {
+            errors.push(FieldError::new("per_page", "must be greater than 0"));
+        }
+        errors
+    }
+
+    ///the `changed` entries, None for ones that aren't a field
+    fn changed_fields(&self) -> Option<Vec<Option<ChangedField>>> {
+        self.changed
+            .as_deref()
+            .map(|changed| changed.split(',').map(ChangedField::parse).collect())
+    }
+}
+
+impl ConnectionStatusBatchRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.uuids.is_empty() || self.uuids.len() > MAX_STATUS_BATCH_SIZE {
+            errors.push(FieldError::new(
+                "uuids",
+                format!("must hold between 1 and {} UUIDs", MAX_STATUS_BATCH_SIZE),
+            ));
+        }
+        errors
+    }
+}
+
+impl InventoryBatchRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.items.is_empty() || self.items.len() > MAX_BATCH_ITEMS {
+            errors.push(FieldError::new(
+                "items",
+                format!("must hold between 1 and {} items", MAX_BATCH_ITEMS),
+            ));
+        }
+        errors
+    }
+}
+
+impl InventoryBatchItemRequest {
+    ///the item to upsert under `system_id_key`; an invalid item is kept as a failure
+    ///("field: message; ...") so the rest of the batch still applies
+    pub fn into_batch_item(self, system_id_key: &SystemIdKey) -> BatchItem {
+        let mut errors = Vec::new();
+        check_length(&mut errors, "system_id", Some(&self.system_id), MAX_IDENTIFIER_LEN);
+        check_length(&mut errors, "name", self.name.as_deref(), MAX_DISPLAY_NAME_LEN);
+        check_length(&mut errors, "external_code", self.external_code.as_deref(), MAX_IDENTIFIER_LEN);
+        if self.price.is_some_and(|p| p < 0) {
+            errors.push(FieldError::new("price", "must not be negative"));
+        }
+        let currency = parse_optional_enum::<Currency>(&mut errors, "currency", self.currency.as_deref());
+        if self.raw.as_ref().is_some_and(|raw| !raw.is_object()) {
+            errors.push(FieldError::new("raw", "must be a JSON object"));
+        }
+
+        let system_id = self.system_id.trim().to_string();
+        if !errors.is_empty() {
+            let message = errors
+                .iter()
+                .map(|e| format!("{}: {}", e.field, e.message))
+                .collect::<Vec<_>>()
+                .join("; ");
+            return BatchItem { system_id, item: Err(message) };
+        }
+
+        let currency = currency.or_else(|| self.price.map(|_| Currency::Usd));
+        let raw = self.raw.unwrap_or_else(|| {
+            serde_json::json!({
+                "system_id": system_id,
+                "name": self.name,
+                "price": self.price,
+                "qty": self.qty,
+                "currency": currency.as_ref().map(|c| c.to_value()),
+                "external_code": self.external_code,
+            })
+        });
+        BatchItem {
+            system_id: system_id.clone(),
+            item: Ok(InventoryItem {
+                system_id_key: system_id_key.clone(),
+                system_id,
+                name: self.name,
+                description: None,
+                price: self.price,
+                currency,
+                qty: self.qty,
+                qty_milli: self.qty.map(|q| q as i64 * 1000),
+                external_code: self.external_code,
+                attributes: None,
+                raw,
+            }),
+        }
+    }
+}
+
+impl CreateConnectionApiKeyRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_length(&mut errors, "display_name", self.display_name.as_deref(), MAX_DISPLAY_NAME_LEN);
+        errors
+    }
+}
+
+fn check_identifier_lengths(errors: &mut Vec<FieldError>, fields: &[(&str, Option<&str>)]) {
+    for (field, value) in fields {
+        check_length(errors, field, *value, MAX_IDENTIFIER_LEN);
+    }
+}
+
+
+/// HELPER FUNCTIONS ///
+///tells a field sent as null (`Some(None)`) apart from one left out (`None`, via
+///`#[serde(default)]`)
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+pub fn model_to_response(model: entity::connection_identity::Model) -> ConnectionResponse {
+    let sync_window = SyncWindow::of(&model).map(|w| SyncWindowResponse {
+        start_hour: w.start_hour,
+        end_hour: w.end_hour,
+    });
+    ConnectionResponse {
+        id: model.id,
+        uuid: model.uuid.to_string(),
+        tenant_id: model.tenant_id,
+        erp_provider: model.erp_provider,
+        erp_type: model.erp_type,
+        erp_auth_type: model.erp_auth_type,
+        display_name: model.display_name,
+        environment: model.environment,
+        status: model.status,
+        auth_status: model.auth_status,
+        is_enabled: model.is_enabled,
+        sync_enabled_push: model.sync_enabled_push,
+        sync_enabled_pull: model.sync_enabled_pull,
+        scopes: model.scopes,
+        provider_realm_id: model.provider_realm_id,
+        provider_tenant_id: model.provider_tenant_id,
+        company_file_identity: model.company_file_identity,
+        company_file_path: model.company_file_path,
+        company_file_id: model.company_file_id,
+        system_version: model.system_version,
+        web_connector_app_name: model.web_connector_app_name,
+        qbxml_version: model.qbxml_version,
+        initial_sync_completed_at: model.initial_sync_completed_at.map(|t| t.to_rfc3339()),
+        sync_window,
+        field_mapping: model.field_mapping,
+        last_success_at: model.last_success_at.map(|t| t.to_rfc3339()),
+        last_error_code: model.last_error_code,
+        last_error_message: model.last_error_message,
+        error_at: model.error_at.map(|t| t.to_rfc3339()),
+        created_at: model.created_at.to_rfc3339(),
+        updated_at: model.updated_at.to_rfc3339(),
+    }
+}
+
+fn status_to_response(status: ConnectionStatus, runs: ConnectionRunSummary) -> ConnectionStatusResponse {
+    let connection = status.connection;
+    ConnectionStatusResponse {
+        uuid: connection.uuid.to_string(),
+        status: connection.status,
+        auth_status: connection.auth_status,
+        is_enabled: connection.is_enabled,
+        last_success_at: connection.last_success_at.map(|t| t.to_rfc3339()),
+        last_error_code: connection.last_error_code,
+        last_error_message: connection.last_error_message,
+        error_at: connection.error_at.map(|t| t.to_rfc3339()),
+        pending_events: status.events.pending,
+        in_progress_events: status.events.in_progress,
+        runs: ConnectionRunSummaryResponse {
+            success_24h: runs.success_24h,
+            error_24h: runs.error_24h,
+            last_error_at: runs.last_error_at.map(|t| t.to_rfc3339()),
+            last_error_message: runs.last_error_message,
+        },
+    }
+}
+
+fn dependents_to_response(uuid: Uuid, dependents: ConnectionDependents) -> ConnectionDependentsResponse {
+    ConnectionDependentsResponse {
+        connection_uuid: uuid.to_string(),
+        credentials: dependents.credentials,
+        sync_states: dependents.sync_states,
+        connection_runs: dependents.connection_runs,
+        sync_events: dependents.sync_events,
+        inventory_records: dependents.inventory_records,
+        inventory_record_events: dependents.inventory_record_events,
+    }
+}
+
+fn quarantined_to_response(model: entity::quarantine::Model) -> QuarantinedItemResponse {
+    QuarantinedItemResponse {
+        uuid: model.uuid.to_string(),
+        list_id: model.list_id,
+        item_type: model.item_type,
+        raw_body: model.raw_body,
+        error: model.error,
+        attempts: model.attempts,
+        created_at: model.created_at.to_rfc3339(),
+        updated_at: model.updated_at.to_rfc3339(),
+    }
+}
+
+///a tenant-scoped API token only sees its own tenant's connections; anything else is
+///answered as if the connection didn't exist
+fn in_tenant_scope(
+    connection: &entity::connection_identity::Model,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+) -> bool {
+    match tenant {
+        Some(Extension(AuthenticatedTenant(Some(tenant_id)))) => connection.tenant_id == tenant_id,
+        _ => true,
+    }
+}
+
+fn last_error_to_response(last_error: LastError) -> LastErrorResponse {
+    let LastError { event, run } = last_error;
+    LastErrorResponse {
+        sync_event_uuid: event.uuid.to_string(),
+        last_error: event.last_error,
+        last_errored_date: event.last_errored_date.map(|t| t.to_rfc3339()),
+        run_uuid: run.as_ref().map(|run| run.uuid.to_string()),
+        run_error_message: run.and_then(|run| run.error_message),
+    }
+}
+
+fn batch_item_to_response(result: BatchItemResult) -> InventoryBatchItemResponse {
+    InventoryBatchItemResponse {
+        index: result.index,
+        system_id: result.system_id,
+        status: result.status.as_str().to_string(),
+        inventory_record_uuid: result.inventory_record_uuid,
+        error: result.error,
+    }
+}
+
+fn api_key_to_response(model: connection_api_key::Model) -> ConnectionApiKeyResponse {
+    ConnectionApiKeyResponse {
+        uuid: model.uuid.to_string(),
+        display_name: model.display_name,
+        key_prefix: model.key_prefix,
+        revoked_at: model.revoked_at.map(|t| t.to_rfc3339()),
+        created_at: model.created_at.to_rfc3339(),
+        updated_at: model.updated_at.to_rfc3339(),
+    }
+}
+
+fn run_to_response(run: connection_run::Model) -> ConnectionRunResponse {
+    ConnectionRunResponse {
+        uuid: run.uuid.to_string(),
+        status: run.status,
+        run_type: run.run_type,
+        error_message: run.error_message,
+        created_at: run.created_at.to_rfc3339(),
+        updated_at: run.updated_at.to_rfc3339(),
+    }
+}
+
+fn timeline_entry_to_response(entry: TimelineEntry) -> TimelineEntryResponse {
+    TimelineEntryResponse {
+        kind: entry.kind.as_str().to_string(),
+        at: entry.at.to_rfc3339(),
+        source_uuid: entry.source_uuid.to_string(),
+        message: entry.message,
+    }
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.into(),
+            details: None,
+        }),
+    )
+}
+
+fn not_found() -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::NOT_FOUND, "Connection not found")
+}
+
+fn conflict(message: String) -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::CONFLICT, message)
+}
+
+fn db_error(e: impl std::fmt::Display) -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))
+}
+
+
+/// ROUTE HANDLERS ///
+
+#[utoipa::path(
+    get,
+    path = "/connections",
+    tag = "Connection",
+    params(ListConnectionsQuery),
+    responses(
+        (status = 200, description = "List of connections", body = PaginatedConnectionsResponse,
+            headers(
+                ("X-Total-Count" = u64, description = "Total matching connections"),
+                ("X-Page" = u64, description = "Current page"),
+                ("X-Per-Page" = u64, description = "Page size"),
+                ("X-Total-Pages" = u64, description = "Number of pages"),
+                ("Link" = String, description = "RFC 5988 first/prev/next/last page links")
+            )),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn list_connections(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    Query(query): Query<ListConnectionsQuery>,
+) -> Result<(HeaderMap, Json<PaginatedConnectionsResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let mut filter = query.parse().map_err(ErrorResponse::validation)?;
+    //a tenant-scoped API token only sees its own tenant's connections
+    filter.tenant_id = tenant.and_then(|Extension(AuthenticatedTenant(tenant_id))| tenant_id);
+    let order_by = query.order();
+    let service = ConnectionIdentityService::new(state.db);
+
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(20);
+
+    match service.get_all(page, per_page, Some(filter), order_by, None).await {
+        Ok(result) => {
+            let headers = paginated_response(
+                &PageInfo {
+                    page: result.page,
+                    per_page: result.per_page,
+                    total: result.total,
+                    total_pages: result.total_pages,
+                },
+                &uri,
+            );
+            Ok((
+                headers,
+                Json(PaginatedConnectionsResponse {
+                    items: result.items.into_iter().map(model_to_response).collect(),
+                    total: result.total,
+                    page: result.page,
+                    per_page: result.per_page,
+                    total_pages: result.total_pages,
+                }),
+            ))
+        }
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/search",
+    tag = "Connection",
+    params(SearchConnectionsQuery),
+    responses(
+        (status = 200, description = "Matching connections of the tenant", body = PaginatedConnectionsResponse,
+            headers(
+                ("X-Total-Count" = u64, description = "Total matching connections"),
+                ("X-Page" = u64, description = "Current page"),
+                ("X-Per-Page" = u64, description = "Page size"),
+                ("X-Total-Pages" = u64, description = "Number of pages"),
+                ("Link" = String, description = "RFC 5988 first/prev/next/last page links")
+            )),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Tenant not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn search_connections(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    scope: Option<Extension<AuthenticatedTenant>>,
+    Query(query): Query<SearchConnectionsQuery>,
+) -> Result<(HeaderMap, Json<PaginatedConnectionsResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let errors = query.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+    let q = query.q.as_deref().unwrap_or_default();
+    let tenant_id = query.tenant_id.as_deref().unwrap_or_default();
+    let scoped_tenant = scope.and_then(|Extension(AuthenticatedTenant(tenant_id))| tenant_id);
+
+    let tenant = match TenantService::new(state.db.clone())
+        .get_by_tenant_id(tenant_id, None)
+        .await
+    {
+        //a tenant-scoped API token only searches its own tenant
+        Ok(Some(tenant)) if scoped_tenant.is_none_or(|id| id == tenant.id) => tenant,
+        Ok(_) => return Err(error(StatusCode::NOT_FOUND, "Tenant not found")),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(20);
+
+    match ConnectionIdentityService::new(state.db)
+        .search(q, tenant.id, page, per_page, None)
+        .await
+    {
+        Ok(result) => {
+            let headers = paginated_response(
+                &PageInfo {
+                    page: result.page,
+                    per_page: result.per_page,
+                    total: result.total,
+                    total_pages: result.total_pages,
+                },
+                &uri,
+            );
+            Ok((
+                headers,
+                Json(PaginatedConnectionsResponse {
+                    items: result.items.into_iter().map(model_to_response).collect(),
+                    total: result.total,
+                    page: result.page,
+                    per_page: result.per_page,
+                    total_pages: result.total_pages,
+                }),
+            ))
+        }
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/connections/status-batch",
+    tag = "Connection",
+    request_body = ConnectionStatusBatchRequest,
+    responses(
+        (status = 200, description = "Status aggregates of the requested connections", body = ConnectionStatusBatchResponse),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn status_batch(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    Json(body): Json<ConnectionStatusBatchRequest>,
+) -> Result<Json<ConnectionStatusBatchResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let errors = body.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let mut uuids = Vec::with_capacity(body.uuids.len());
+    for uuid in body.uuids {
+        if !uuids.contains(&uuid) {
+            uuids.push(uuid);
+        }
+    }
+    //a tenant-scoped API token only sees its own tenant's connections
+    let tenant_id = tenant.and_then(|Extension(AuthenticatedTenant(tenant_id))| tenant_id);
+
+    let statuses = ConnectionStatusService::new(state.db.clone())
+        .get_batch(&uuids, tenant_id)
+        .await
+        .map_err(db_error)?;
+    let ids: Vec<i64> = statuses.iter().map(|s| s.connection.id).collect();
+    let mut runs = ConnectionRunService::new(state.db)
+        .summaries(&ids, None)
+        .await
+        .map_err(db_error)?;
+
+    let not_found = uuids
+        .iter()
+        .filter(|uuid| !statuses.iter().any(|s| s.connection.uuid == **uuid))
+        .map(|uuid| uuid.to_string())
+        .collect();
+    Ok(Json(ConnectionStatusBatchResponse {
+        items: statuses
+            .into_iter()
+            .map(|status| {
+                let summary = runs.remove(&status.connection.id).unwrap_or_default();
+                status_to_response(status, summary)
+            })
+            .collect(),
+        not_found,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    responses(
+        (status = 200, description = "Connection found", body = ConnectionResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn get_connection(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+) -> Result<Json<ConnectionResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+    Ok(Json(model_to_response(connection)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/inventory",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID"),
+        ("Accept" = Option<String>, Header, description = "text/csv (default), application/json or application/x-ndjson")
+    ),
+    responses(
+        (status = 200, description = "Inventory records with their latest event, streamed in the format the Accept header asks for (also served at /connections/{uuid}/inventory.csv)", content(
+            (String = "text/csv"),
+            (String = "application/json"),
+            (String = "application/x-ndjson")
+        )),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 406, description = "None of the accepted media types can be produced", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn export_inventory(
+    State(state): State<AppState>,
+    PathUuid(uuid): PathUuid,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok());
+    let Some(format) = ExportFormat::from_accept(accept) else {
+        return Err(error(
+            StatusCode::NOT_ACCEPTABLE,
+            format!("Inventory can be exported as {}", EXPORT_MEDIA_TYPES.join(", ")),
+        ));
+    };
+
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+
+    //records are only exported while the owning tenant is active
+    let tenant_service = TenantService::with_cache(state.db.clone(), state.redis.clone());
+    match tenant_service.is_tenant_active_by_id(connection.tenant_id, None).await {
+        Ok(true) => {}
+        Ok(false) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    }
+
+    let stream = inventory_export_stream(state.db, connection.tenant_id, connection.id, format);
+    let filename = format!(
+        "attachment; filename=\"inventory-{}.{}\"",
+        connection.uuid,
+        format.extension()
+    );
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::VARY, header::ACCEPT.to_string()),
+            (header::CONTENT_DISPOSITION, filename),
+        ],
+        Body::from_stream(stream),
+    )
+        .into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/connections",
+    tag = "Connection",
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Repeats with the same key within 24 hours return the first response instead of creating another connection")
+    ),
+    request_body = CreateConnectionRequest,
+    responses(
+        (status = 201, description = "Connection created (or replayed for a repeated Idempotency-Key, with Idempotent-Replayed: true)", body = ConnectionResponse),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Tenant not found", body = ErrorResponse),
+        (status = 409, description = "Duplicate provider identity, or a request with the same Idempotency-Key is still being processed", body = ErrorResponse),
+        (status = 422, description = "Idempotency-Key already used with a different body", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn create_connection(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    headers: HeaderMap,
+    Json(body): Json<CreateConnectionRequest>,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let valid = body.parse().map_err(ErrorResponse::validation)?;
+    let key = IdempotencyKey::from_headers(&headers, "connection:create", audit.actor_token_id)
+        .map_err(ErrorResponse::idempotency_key)?;
+    let request_fingerprint = fingerprint(&body);
+
+    let tenant_scoped = matches!(tenant, Some(Extension(AuthenticatedTenant(Some(_)))));
+    let Some(tenant_id) = tenant_id_or_default(
+        body.tenant_id.as_deref(),
+        config::env::get().tenant.default_tenant_id.as_deref(),
+        tenant_scoped,
+    ) else {
+        return Err(ErrorResponse::validation(vec![FieldError::new("tenant_id", "is required")]));
+    };
+    let tenant = match TenantService::new(state.db.clone())
+        .get_by_tenant_id(tenant_id, None)
+        .await
+    {
+        Ok(Some(tenant)) => tenant,
+        Ok(None) => return Err(error(StatusCode::NOT_FOUND, "Tenant not found")),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    let service = ConnectionIdentityService::new(state.db).with_audit(audit);
+
+    let data = CreateConnectionIdentity {
+        tenant_id: tenant.id,
+        erp_provider: valid.erp_provider,
+        erp_type: valid.erp_type,
+        erp_auth_type: valid.erp_auth_type,
+        display_name: body.display_name,
+        environment: valid.environment,
+        scopes: body.scopes,
+        provider_realm_id: body.provider_realm_id,
+        provider_tenant_id: body.provider_tenant_id,
+        company_file_identity: body.company_file_identity,
+        company_file_path: body.company_file_path,
+        company_file_id: body.company_file_id,
+        system_version: body.system_version,
+        web_connector_app_name: body.web_connector_app_name,
+        secret_storage_ref: None,
+        secret_version: None,
+        sync_enabled_push: body.sync_enabled_push,
+        sync_enabled_pull: body.sync_enabled_pull,
+        sync_window: valid.sync_window,
+    };
+
+    let created = idempotent_create(
+        &IdempotencyStore::Redis(state.redis),
+        key.as_ref(),
+        &request_fingerprint,
+        || async { service.create(data, None).await.map(model_to_response) },
+    )
+    .await
+    .map_err(|e| {
+        ErrorResponse::idempotency(e, |e| match e {
+            ConnectionIdentityError::Conflict(message) => conflict(message),
+            ConnectionIdentityError::NotFound => not_found(),
+            ConnectionIdentityError::Db(e) => db_error(e),
+        })
+    })?;
+    Ok(created.into_response(StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    put,
+    path = "/connections/{uuid}",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    request_body = UpdateConnectionRequest,
+    responses(
+        (status = 200, description = "Connection updated", body = UpdateConnectionResponse),
+        (status = 400, description = "Validation failed or malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 409, description = "Duplicate provider identity", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn update_connection(
+    State(state): State<AppState>,
+    PathUuid(uuid): PathUuid,
+    audit: AuditContext,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    Json(body): Json<UpdateConnectionRequest>,
+) -> Result<Json<UpdateConnectionResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let valid = body.parse().map_err(ErrorResponse::validation)?;
+    scoped_connection(&state, uuid, tenant).await?;
+    let service = ConnectionIdentityService::new(state.db).with_audit(audit);
+
+    let patch = UpdateConnectionIdentity {
+        display_name: body.display_name,
+        environment: valid.environment,
+        status: valid.status,
+        auth_status: valid.auth_status,
+        is_enabled: body.is_enabled,
+        scopes: body.scopes,
+        provider_realm_id: body.provider_realm_id,
+        provider_tenant_id: body.provider_tenant_id,
+        company_file_identity: body.company_file_identity,
+        company_file_path: body.company_file_path,
+        company_file_id: body.company_file_id,
+        system_version: body.system_version,
+        web_connector_app_name: body.web_connector_app_name,
+        secret_storage_ref: None,
+        secret_version: None,
+        sync_enabled_push: body.sync_enabled_push,
+        sync_enabled_pull: body.sync_enabled_pull,
+        last_error_code: None,
+        last_error_message: None,
+        sync_window: valid.sync_window,
+        field_mapping: valid.field_mapping,
+    };
+
+    match service.update_by_uuid(uuid, patch, None).await {
+        Ok(Some(updated)) => Ok(Json(UpdateConnectionResponse {
+            connection: model_to_response(updated.model),
+            changed_fields: updated.changed_fields,
+        })),
+        Ok(None) | Err(ConnectionIdentityError::NotFound) => Err(not_found()),
+        Err(ConnectionIdentityError::Conflict(message)) => Err(conflict(message)),
+        Err(ConnectionIdentityError::Db(e)) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/connections/{uuid}",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    responses(
+        (status = 200, description = "Connection removed (soft delete)", body = DeleteResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn delete_connection(
+    State(state): State<AppState>,
+    PathUuid(uuid): PathUuid,
+    audit: AuditContext,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+) -> Result<Json<DeleteResponse>, (StatusCode, Json<ErrorResponse>)> {
+    scoped_connection(&state, uuid, tenant).await?;
+    let service = ConnectionIdentityService::new(state.db).with_audit(audit);
+
+    match service.delete_by_uuid(uuid, None).await {
+        Ok(Some(_)) => Ok(Json(DeleteResponse {
+            message: "Connection removed successfully".to_string(),
+        })),
+        Ok(None) | Err(ConnectionIdentityError::NotFound) => Err(not_found()),
+        Err(ConnectionIdentityError::Conflict(message)) => Err(conflict(message)),
+        Err(ConnectionIdentityError::Db(e)) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/connections/{uuid}/test",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    responses(
+        (status = 200, description = "Test result (check `ok`); API providers also update auth_status", body = ConnectionTestResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 422, description = "No connection test for this provider", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn test_connection(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+) -> Result<Json<ConnectionTestResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let service = ConnectionIdentityService::new(state.db.clone());
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+
+    let tester = ConnectionTestService::new(state.db)
+        .with_qbo_base_url(config::env::get().providers.qbo_api_base_url.clone());
+    let outcome = match tester.run(&connection).await {
+        Ok(outcome) => outcome,
+        Err(ConnectionTestError::Unsupported) => {
+            return Err(error(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Connection test is not supported for this provider",
+            ));
+        }
+        Err(ConnectionTestError::NotFound) => return Err(not_found()),
+        Err(ConnectionTestError::Db(e)) => return Err(db_error(e)),
+    };
+
+    //re-read so the response reflects record_success/record_error
+    let auth_status = match service.get_by_uuid(uuid, None).await {
+        Ok(Some(updated)) => updated.auth_status,
+        Ok(None) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    Ok(Json(ConnectionTestResponse {
+        ok: outcome.ok,
+        erp_provider: connection.erp_provider,
+        reachable: outcome.reachable,
+        token_valid: outcome.token_valid,
+        expected_username: outcome.expected_username,
+        latency_ms: outcome.latency_ms,
+        error_code: outcome.error_code,
+        error_message: outcome.error_message,
+        auth_status,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/connections/{uuid}/reprocess",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID"),
+        ReprocessQuery
+    ),
+    responses(
+        (status = 200, description = "Stored raw bodies re-parsed; changed records got a new event", body = ReprocessResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 422, description = "Reprocessing is not supported for this provider", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn reprocess_connection(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+    Query(query): Query<ReprocessQuery>,
+) -> Result<Json<ReprocessResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+
+    //only QBD stores the raw provider payload the records are re-derived from
+    if connection.erp_provider != ErpProvider::Quickbooks
+        || connection.erp_type != ErpProviderType::Desktop
+    {
+        return Err(error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Reprocessing is only supported for QuickBooks Desktop connections",
+        ));
+    }
+
+    match QbdPollService::new(state.db).reprocess(&connection, query.since).await {
+        Ok(summary) => Ok(Json(ReprocessResponse {
+            scanned: summary.scanned,
+            updated: summary.updated,
+            unchanged: summary.unchanged,
+            skipped: summary.skipped,
+        })),
+        Err(QbdPollError::Db(e)) => Err(db_error(e)),
+        Err(e) => Err(error(e.status_code(), e.message())),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/connections/{uuid}/inventory/batch",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    request_body = InventoryBatchRequest,
+    responses(
+        (status = 200, description = "Items upserted in one transaction; each item reports created, updated, unchanged or failed", body = InventoryBatchResponse),
+        (status = 400, description = "Malformed UUID or validation failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 422, description = "Batched inventory is not supported for this provider", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn batch_upsert_inventory(
+    State(state): State<AppState>,
+    PathUuid(uuid): PathUuid,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    Json(body): Json<InventoryBatchRequest>,
+) -> Result<Json<InventoryBatchResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let errors = body.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let service = ConnectionIdentityService::new(state.db.clone());
+    let connection = match service.get_by_uuid(uuid, None).await {
+        Ok(Some(connection)) if in_tenant_scope(&connection, tenant) => connection,
+        Ok(_) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    //QBD records only come from the Web Connector
+    let Some(system_id_key) = system_id_key_for(&connection) else {
+        return Err(error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Batched inventory is not supported for this provider",
+        ));
+    };
+
+    let items = body
+        .items
+        .into_iter()
+        .map(|item| item.into_batch_item(&system_id_key))
+        .collect();
+    let outcome = InventoryBatchService::new(state.db)
+        .upsert(&connection, items, None)
+        .await
+        .map_err(db_error)?;
+
+    let count = |status: BatchItemStatus| outcome.items.iter().filter(|i| i.status == status).count();
+    Ok(Json(InventoryBatchResponse {
+        run_uuid: outcome.run.uuid,
+        sync_event_uuid: outcome.event.uuid,
+        created: count(BatchItemStatus::Created),
+        updated: count(BatchItemStatus::Updated),
+        unchanged: count(BatchItemStatus::Unchanged),
+        failed: count(BatchItemStatus::Failed),
+        items: outcome.items.into_iter().map(batch_item_to_response).collect(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/inventory/search",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID"),
+        SearchInventoryQuery
+    ),
+    responses(
+        (status = 200, description = "Records of the connection matching the query, exact name matches first, then by relevance", body = InventorySearchResponse),
+        (status = 400, description = "Malformed UUID or validation failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn search_inventory(
+    State(state): State<AppState>,
+    PathUuid(uuid): PathUuid,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    Query(query): Query<SearchInventoryQuery>,
+) -> Result<Json<InventorySearchResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let errors = query.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let service = ConnectionIdentityService::new(state.db.clone());
+    let connection = match service.get_by_uuid(uuid, None).await {
+        Ok(Some(connection)) if in_tenant_scope(&connection, tenant) => connection,
+        Ok(_) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    let q = query.q.as_deref().unwrap_or_default();
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    let items = InventoryRecordEventService::new(state.db)
+        .search(q, connection.id, limit, None)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(InventorySearchResponse {
+        items: items.into_iter().map(event_to_response).collect(),
+    }))
+}
+
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/sync-progress",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    responses(
+        (status = 200, description = "Cursor and progress of the inventory pull", body = SyncProgressResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 422, description = "Sync progress is not supported for this provider", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn get_sync_progress(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+) -> Result<Json<SyncProgressResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+
+    //the cursor's iterator/remaining_count shape is QBD's
+    if connection.erp_provider != ErpProvider::Quickbooks
+        || connection.erp_type != ErpProviderType::Desktop
+    {
+        return Err(error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Sync progress is only supported for QuickBooks Desktop connections",
+        ));
+    }
+
+    match QbdPollService::new(state.db).sync_progress(&connection).await {
+        Ok(progress) => Ok(Json(SyncProgressResponse {
+            iterator_id: progress.iterator_id,
+            remaining_count: progress.remaining_count,
+            item_type: progress.item_type.map(str::to_string),
+            event_uuid: progress.event_uuid.map(|u| u.to_string()),
+            event_status: progress.event_status,
+            records_synced: progress.records_synced,
+            in_progress: progress.in_progress,
+            percent_complete: progress.percent_complete,
+            initial_sync_completed_at: connection.initial_sync_completed_at.map(|t| t.to_rfc3339()),
+        })),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/runs",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID"),
+        ListRunsQuery
+    ),
+    responses(
+        (status = 200, description = "The connection's runs, newest first", body = ConnectionRunsResponse),
+        (status = 400, description = "Validation failed or malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn list_runs(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+    Query(query): Query<ListRunsQuery>,
+) -> Result<Json<ConnectionRunsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let errors = query.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let service = ConnectionIdentityService::new(state.db.clone());
+    let connection = match service.get_by_uuid(uuid, None).await {
+        Ok(Some(connection)) => connection,
+        Ok(None) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+    if !in_tenant_scope(&connection, tenant) {
+        return Err(not_found());
+    }
+
+    let after = query.cursor.as_deref().and_then(RunCursor::decode);
+    let limit = query.limit.unwrap_or(DEFAULT_RUNS_PAGE_LIMIT);
+    match ConnectionRunService::new(state.db)
+        .page_by_connection_id(connection.id, after, limit, None)
+        .await
+    {
+        Ok(page) => Ok(Json(ConnectionRunsResponse {
+            items: page.runs.into_iter().map(run_to_response).collect(),
+            next_cursor: page.next_cursor.map(|cursor| cursor.encode()),
+        })),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/timeline",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID"),
+        TimelineQuery
+    ),
+    responses(
+        (status = 200, description = "Runs, sync event outcomes and auth status changes, newest first", body = TimelineResponse),
+        (status = 400, description = "Validation failed or malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn get_timeline(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+    Query(query): Query<TimelineQuery>,
+) -> Result<Json<TimelineResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let errors = query.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let service = ConnectionIdentityService::new(state.db.clone());
+    let connection = match service.get_by_uuid(uuid, None).await {
+        Ok(Some(connection)) => connection,
+        Ok(None) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+    if !in_tenant_scope(&connection, tenant) {
+        return Err(not_found());
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_TIMELINE_LIMIT);
+    let before = query.before.map(|before| before.fixed_offset());
+    match TimelineService::new(state.db).get_page(&connection, before, limit).await {
+        Ok(page) => Ok(Json(TimelineResponse {
+            items: page.entries.into_iter().map(timeline_entry_to_response).collect(),
+            next_before: page.next_before.map(|t| t.to_rfc3339()),
+        })),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/last-error",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    responses(
+        (status = 200, description = "The most recently errored sync event and its run's error message", body = LastErrorResponse),
+        (status = 204, description = "No sync event of the connection has errored"),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn get_last_error(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let service = ConnectionIdentityService::new(state.db.clone());
+    let connection = match service.get_by_uuid(uuid, None).await {
+        Ok(Some(connection)) => connection,
+        Ok(None) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+    if !in_tenant_scope(&connection, tenant) {
+        return Err(not_found());
+    }
+
+    match LastErrorService::new(state.db).latest(connection.id).await {
+        Ok(Some(last_error)) => Ok(Json(last_error_to_response(last_error)).into_response()),
+        Ok(None) => Ok(StatusCode::NO_CONTENT.into_response()),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/events",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID"),
+        ListConnectionEventsQuery
+    ),
+    responses(
+        (status = 200, description = "Inventory record events written by the connection, newest first", body = PaginatedConnectionEventsResponse,
+            headers(
+                ("X-Total-Count" = u64, description = "Total matching events"),
+                ("X-Page" = u64, description = "Current page"),
+                ("X-Per-Page" = u64, description = "Page size"),
+                ("X-Total-Pages" = u64, description = "Number of pages"),
+                ("Link" = String, description = "RFC 5988 first/prev/next/last page links")
+            )),
+        (status = 400, description = "Validation failed or malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn list_events(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    OriginalUri(uri): OriginalUri,
+    PathUuid(uuid): PathUuid,
+    Query(query): Query<ListConnectionEventsQuery>,
+) -> Result<(HeaderMap, Json<PaginatedConnectionEventsResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let errors = query.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let service = ConnectionIdentityService::new(state.db.clone());
+    let connection = match service.get_by_uuid(uuid, None).await {
+        Ok(Some(connection)) => connection,
+        Ok(None) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+    if !in_tenant_scope(&connection, tenant) {
+        return Err(not_found());
+    }
+
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(20);
+    let filter = InventoryRecordEventFilter {
+        connection_id: Some(connection.id),
+        since: query.since,
+        until: query.until,
+        changed: query.changed_fields().unwrap_or_default().into_iter().flatten().collect(),
+        ..Default::default()
+    };
+
+    match InventoryRecordEventService::new(state.db)
+        .get_all(page, per_page, Some(filter), None)
+        .await
+    {
+        Ok(result) => {
+            let headers = paginated_response(
+                &PageInfo {
+                    page: result.page,
+                    per_page: result.per_page,
+                    total: result.total,
+                    total_pages: result.total_pages,
+                },
+                &uri,
+            );
+            Ok((
+                headers,
+                Json(PaginatedConnectionEventsResponse {
+                    items: result.items.into_iter().map(event_to_response).collect(),
+                    total: result.total,
+                    page: result.page,
+                    per_page: result.per_page,
+                    total_pages: result.total_pages,
+                }),
+            ))
+        }
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/quarantine",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID"),
+        ListQuarantineQuery
+    ),
+    responses(
+        (status = 200, description = "Items a pull could not apply, most recently failed first", body = PaginatedQuarantineResponse,
+            headers(
+                ("X-Total-Count" = u64, description = "Total quarantined items"),
+                ("X-Page" = u64, description = "Current page"),
+                ("X-Per-Page" = u64, description = "Page size"),
+                ("X-Total-Pages" = u64, description = "Number of pages"),
+                ("Link" = String, description = "RFC 5988 first/prev/next/last page links")
+            )),
+        (status = 400, description = "Validation failed or malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn list_quarantine(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+    Query(query): Query<ListQuarantineQuery>,
+) -> Result<(HeaderMap, Json<PaginatedQuarantineResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let errors = query.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(20);
+
+    match QuarantineService::new(state.db).get_all(connection.id, page, per_page, None).await {
+        Ok(result) => {
+            let headers = paginated_response(
+                &PageInfo {
+                    page: result.page,
+                    per_page: result.per_page,
+                    total: result.total,
+                    total_pages: result.total_pages,
+                },
+                &uri,
+            );
+            Ok((
+                headers,
+                Json(PaginatedQuarantineResponse {
+                    items: result.items.into_iter().map(quarantined_to_response).collect(),
+                    total: result.total,
+                    page: result.page,
+                    per_page: result.per_page,
+                    total_pages: result.total_pages,
+                }),
+            ))
+        }
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/connections/{uuid}/quarantine/{item_uuid}/replay",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID"),
+        ("item_uuid" = String, Path, description = "Quarantined item UUID")
+    ),
+    responses(
+        (status = 200, description = "Item applied from its stored body and removed from quarantine", body = QuarantineReplayResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Connection or quarantined item not found", body = ErrorResponse),
+        (status = 422, description = "The item failed again (kept with the new error), or replay is not supported for this provider", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn replay_quarantined(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid((uuid, item_uuid)): PathUuid<(Uuid, Uuid)>,
+) -> Result<Json<QuarantineReplayResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+
+    //quarantined bodies are QBD items
+    if connection.erp_provider != ErpProvider::Quickbooks
+        || connection.erp_type != ErpProviderType::Desktop
+    {
+        return Err(error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Replay is only supported for QuickBooks Desktop connections",
+        ));
+    }
+
+    let entry = match QuarantineService::new(state.db.clone())
+        .get_by_uuid(connection.id, item_uuid, None)
+        .await
+    {
+        Ok(Some(entry)) => entry,
+        Ok(None) => return Err(error(StatusCode::NOT_FOUND, "Quarantined item not found")),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    match QbdPollService::new(state.db).replay_quarantined(&connection, &entry).await {
+        Ok(ReplayOutcome::Applied) => Ok(Json(QuarantineReplayResponse {
+            uuid: entry.uuid.to_string(),
+            list_id: entry.list_id,
+            replayed: true,
+        })),
+        Ok(ReplayOutcome::Failed(message)) => Err(error(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Replay failed: {}", message),
+        )),
+        Err(QbdPollError::Db(e)) => Err(db_error(e)),
+        Err(e) => Err(error(e.status_code(), e.message())),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/credentials",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    responses(
+        (status = 200, description = "Credential metadata; secrets are reported as has_* flags only", body = MaskedCredentialsResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid API token or X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Connection or credentials not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn get_credentials(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+) -> Result<Json<MaskedCredentialsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let service = ConnectionIdentityService::new(state.db);
+    let (connection, credentials) = match service.get_with_credentials_by_uuid(uuid, None).await {
+        Ok(Some(found)) => found,
+        Ok(None) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+    if !in_tenant_scope(&connection, tenant) {
+        return Err(not_found());
+    }
+
+    match credentials {
+        Some(credentials) => Ok(Json(MaskedCredentialsResponse::from_model(
+            &credentials,
+            connection.erp_auth_type,
+        ))),
+        None => Err(error(StatusCode::NOT_FOUND, "Connection has no credentials")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/delete-preview",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    responses(
+        (status = 200, description = "Dependent rows a purge would delete", body = ConnectionDependentsResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn delete_preview(
+    State(state): State<AppState>,
+    PathUuid(uuid): PathUuid,
+) -> Result<Json<ConnectionDependentsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let service = ConnectionIdentityService::new(state.db);
+    let connection = match service.get_by_uuid(uuid, None).await {
+        Ok(Some(connection)) => connection,
+        Ok(None) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    match service.delete_preview(connection.id, None).await {
+        Ok(dependents) => Ok(Json(dependents_to_response(uuid, dependents))),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/connections/{uuid}/purge",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    responses(
+        (status = 200, description = "Connection and its dependent rows deleted (hard delete)", body = ConnectionDependentsResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn purge_connection(
+    State(state): State<AppState>,
+    PathUuid(uuid): PathUuid,
+    audit: AuditContext,
+) -> Result<Json<ConnectionDependentsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let service = ConnectionIdentityService::new(state.db).with_audit(audit);
+    let connection = match service.get_by_uuid(uuid, None).await {
+        Ok(Some(connection)) => connection,
+        Ok(None) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    match service.purge(&connection, None).await {
+        Ok(removed) => {
+            tracing::warn!(
+                event = "connection_purged",
+                connection_uuid = %uuid,
+                tenant_id = connection.tenant_id,
+                "Connection hard-deleted with its dependent rows"
+            );
+            Ok(Json(dependents_to_response(uuid, removed)))
+        }
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+///the connection behind `uuid` when the caller's tenant may see it
+async fn scoped_connection(
+    state: &AppState,
+    uuid: Uuid,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+) -> Result<entity::connection_identity::Model, (StatusCode, Json<ErrorResponse>)> {
+    match ConnectionIdentityService::new(state.db.clone()).get_by_uuid(uuid, None).await {
+        Ok(Some(connection)) if in_tenant_scope(&connection, tenant) => Ok(connection),
+        Ok(_) => Err(not_found()),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/connections/{uuid}/api-keys",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    responses(
+        (status = 200, description = "The connection's API keys, revoked ones included", body = Vec<ConnectionApiKeyResponse>),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid API token or X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+) -> Result<Json<Vec<ConnectionApiKeyResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+
+    match ConnectionApiKeyService::new(state.db).get_all(connection.id, None).await {
+        Ok(keys) => Ok(Json(keys.into_iter().map(api_key_to_response).collect())),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/connections/{uuid}/api-keys",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID")
+    ),
+    request_body = CreateConnectionApiKeyRequest,
+    responses(
+        (status = 201, description = "Key minted; the raw key is only returned here", body = IssuedConnectionApiKeyResponse),
+        (status = 400, description = "Malformed UUID or validation failed", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid API token or X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Connection not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+    Json(body): Json<CreateConnectionApiKeyRequest>,
+) -> Result<(StatusCode, Json<IssuedConnectionApiKeyResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let errors = body.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+
+    let data = CreateConnectionApiKey {
+        connection_id: connection.id,
+        display_name: body.display_name,
+    };
+    match ConnectionApiKeyService::new(state.db).create(data, None).await {
+        Ok(issued) => Ok((
+            StatusCode::CREATED,
+            Json(IssuedConnectionApiKeyResponse {
+                key: issued.key,
+                metadata: api_key_to_response(issued.model),
+            }),
+        )),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/connections/{uuid}/api-keys/{key_uuid}",
+    tag = "Connection",
+    params(
+        ("uuid" = String, Path, description = "Connection UUID"),
+        ("key_uuid" = String, Path, description = "Connection API key UUID")
+    ),
+    responses(
+        (status = 200, description = "Key revoked; requests using it are rejected", body = DeleteResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid API token or X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Connection or API key not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid((uuid, key_uuid)): PathUuid<(Uuid, Uuid)>,
+) -> Result<Json<DeleteResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let connection = scoped_connection(&state, uuid, tenant).await?;
+
+    match ConnectionApiKeyService::new(state.db)
+        .revoke(connection.id, key_uuid, None)
+        .await
+    {
+        Ok(_) => Ok(Json(DeleteResponse {
+            message: "Connection API key revoked successfully".to_string(),
+        })),
+        Err(ConnectionApiKeyError::NotFound) => {
+            Err(error(StatusCode::NOT_FOUND, "Connection API key not found"))
+        }
+        Err(ConnectionApiKeyError::Db(e)) => Err(db_error(e)),
+    }
+}
+
+
+/// ROUTER ///
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_connections).post(create_connection))
+        .route("/search", get(search_connections))
+        .route("/status-batch", post(status_batch))
+        .route("/{uuid}", get(get_connection).put(update_connection).delete(delete_connection))
+        .route("/{uuid}/inventory", get(export_inventory))
+        .route("/{uuid}/inventory.csv", get(export_inventory))
+        .route("/{uuid}/inventory/batch", post(batch_upsert_inventory))
+        .route("/{uuid}/inventory/search", get(search_inventory))
+        .route("/{uuid}/test", post(test_connection))
+        .route("/{uuid}/reprocess", post(reprocess_connection))
+        .route("/{uuid}/sync-progress", get(get_sync_progress))
+        .route("/{uuid}/runs", get(list_runs))
+        .route("/{uuid}/timeline", get(get_timeline))
+        .route("/{uuid}/last-error", get(get_last_error))
+        .route("/{uuid}/events", get(list_events))
+        .route("/{uuid}/quarantine", get(list_quarantine))
+        .route("/{uuid}/quarantine/{item_uuid}/replay", post(replay_quarantined))
+        //credential metadata needs the tenant's API token and ADMIN_TOKEN
+        .route(
+            "/{uuid}/credentials",
+            get(get_credentials).layer(from_fn(admin_token_auth_middleware)),
+        )
+        //minting and revoking connection API keys needs the tenant's API token and ADMIN_TOKEN
+        .route(
+            "/{uuid}/api-keys",
+            get(list_api_keys)
+                .post(create_api_key)
+                .layer(from_fn(admin_token_auth_middleware)),
+        )
+        .route(
+            "/{uuid}/api-keys/{key_uuid}",
+            delete(revoke_api_key).layer(from_fn(admin_token_auth_middleware)),
+        )
+        //hard delete is operator-only: ADMIN_TOKEN instead of an API token
+        .route(
+            "/{uuid}/delete-preview",
+            get(delete_preview).layer(from_fn(admin_token_auth_middleware)),
+        )
+        .route(
+            "/{uuid}/purge",
+            delete(purge_connection).layer(from_fn(admin_token_auth_middleware)),
+        )
+}
@@ -1,27 +1,53 @@
+use sea_orm::sea_query::extension::postgres::PgExpr;
+use sea_orm::sea_query::{Expr, NullOrdering, Order};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
-    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
+    ActiveEnum, ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Select, Set,
+    QueryTrait, TransactionTrait,
+};
+use entity::{
+    connection_identity, connection_run, erp_connection_credentials, erp_connection_sync_state,
+    inventory_record, inventory_record_event, sync_event,
 };
-use entity::connection_identity;
 use entity::sea_orm_active_enums::{
     ErpConnectionAuthStatus, ErpConnectionStatus, ErpEnvironment,
     ErpProvider, ErpProviderAuthType, ErpProviderType,
 };
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
+use crate::audit::services::{
+    changed_fields, AuditAction, AuditContext, AuditEntry, AuditService,
+};
+use crate::clock::{self, SharedClock};
+use crate::db::unique::UniqueViolation;
+use crate::outbox::services::{OutboxPayload, OutboxService, TOPIC_CONNECTION_NEEDS_REAUTH};
+use crate::sync::window::{self, SyncWindow};
+
 
 //DEBUG AND ERRORS ///
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum ConnectionIdentityError {
     NotFound,
+    ///an active connection already exists for the same provider identity; the message
+    ///says which
+    Conflict(String),
     Db(DbErr),
 }
 
+/// Message of the service-level duplicate identity pre-check.
+pub const IDENTITY_CONFLICT_MESSAGE: &str =
+    "An active connection with the same provider identity already exists";
+
 #[allow(dead_code)]
 impl From<DbErr> for ConnectionIdentityError {
     fn from(err: DbErr) -> Self {
-        ConnectionIdentityError::Db(err)
+        //the partial unique indexes back up the service-level pre-check
+        match UniqueViolation::from_db_err(&err) {
+            Some(violation) => ConnectionIdentityError::Conflict(violation.message()),
+            None => ConnectionIdentityError::Db(err),
+        }
     }
 }
 
@@ -31,6 +57,8 @@ impl From<DbErr> for ConnectionIdentityError {
 /// BEGUN STRUCTS AND ENUMS ///
 pub struct ConnectionIdentityService {
     db: DatabaseConnection,
+    clock: SharedClock,
+    audit: Option<AuditContext>,
 }
 
 #[allow(dead_code)]
@@ -53,6 +81,8 @@ pub struct CreateConnectionIdentity {
     pub secret_version: Option<String>,
     pub sync_enabled_push: Option<bool>,
     pub sync_enabled_pull: Option<bool>,
+    /// UTC hours pulls may run in; None: any time.
+    pub sync_window: Option<SyncWindow>,
 }
 
 #[allow(dead_code)]
@@ -76,6 +106,19 @@ pub struct UpdateConnectionIdentity {
     pub sync_enabled_pull: Option<bool>,
     pub last_error_code: Option<String>,
     pub last_error_message: Option<String>,
+    /// `Some(None)` removes the window.
+    pub sync_window: Option<Option<SyncWindow>>,
+    /// `Some(None)` goes back to the default QBD field mapping.
+    pub field_mapping: Option<Option<serde_json::Value>>,
+}
+
+/// Result of `update_by_uuid`: the updated row and what the patch actually changed.
+#[allow(dead_code)]
+pub struct UpdatedConnectionIdentity {
+    pub model: connection_identity::Model,
+    /// Columns whose value differs from before the update (`updated_at` aside), in column
+    /// name order; empty when the patch only repeated current values.
+    pub changed_fields: Vec<String>,
 }
 
 #[allow(dead_code)]
@@ -91,6 +134,33 @@ pub struct ConnectionIdentityFilter {
     pub display_name: Option<String>,
 }
 
+/// Order of `get_all` results.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConnectionOrder {
+    /// Newest first.
+    #[default]
+    CreatedAt,
+    /// Connections needing attention first: auth_status `error` or `needs_reauth`, then the
+    /// rest; within each group the oldest `last_success_at` first (never succeeded before
+    /// any), then newest first.
+    HealthPriority,
+}
+
+/// Rows removed together with a connection by a hard delete (`purge`). Everything but
+/// the sync events goes through the FK cascades; sync events only have their links
+/// nulled by those, so `purge` deletes them itself.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionDependents {
+    pub credentials: u64,
+    pub sync_states: u64,
+    pub connection_runs: u64,
+    pub sync_events: u64,
+    pub inventory_records: u64,
+    pub inventory_record_events: u64,
+}
+
 #[allow(dead_code)]
 pub struct PaginatedConnectionIdentities {
     pub items: Vec<connection_identity::Model>,
@@ -107,7 +177,40 @@ pub struct PaginatedConnectionIdentities {
 #[allow(dead_code)]
 impl ConnectionIdentityService {
     pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+        Self {
+            db,
+            clock: clock::system(),
+            audit: None,
+        }
+    }
+
+    ///uses `clock` for updated_at and the success/error timestamps
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    ///records every create/update/delete in `audit_log`, in the same transaction as the
+    ///change (one is started when the caller passes none)
+    pub fn with_audit(mut self, audit: AuditContext) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    ///whether `conn` may sync at `now`: inside its sync window, or it has none
+    pub fn is_in_sync_window(conn: &connection_identity::Model, now: DateTime<Utc>) -> bool {
+        window::in_sync_window(conn, now)
+    }
+
+    async fn record_audit(
+        &self,
+        entry: AuditEntry,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<(), DbErr> {
+        if let (Some(audit), Some(txn)) = (&self.audit, txn) {
+            AuditService::new(self.db.clone()).record(audit, entry, txn).await?;
+        }
+        Ok(())
     }
 
     pub async fn get_by_id(
@@ -150,46 +253,81 @@ impl ConnectionIdentityService {
         }
     }
 
+    ///the connection and its credentials (None if it has none), joined in one query
+    pub async fn get_with_credentials_by_uuid(
+        &self,
+        uuid: Uuid,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<(connection_identity::Model, Option<erp_connection_credentials::Model>)>, DbErr>
+    {
+        let query = connection_identity::Entity::find()
+            .filter(connection_identity::Column::Uuid.eq(uuid))
+            .find_also_related(erp_connection_credentials::Entity);
+        match txn {
+            Some(txn) => query.one(txn).await,
+            None => query.one(&self.db).await,
+        }
+    }
+
     pub async fn get_all(
         &self,
         page: u64,
         per_page: u64,
         filter: Option<ConnectionIdentityFilter>,
+        order_by: ConnectionOrder,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<PaginatedConnectionIdentities, DbErr> {
-        let mut condition = Condition::all();
+        self.paginate(list_query(filter, order_by), page, per_page, txn).await
+    }
 
-        if let Some(f) = filter {
-            if let Some(tenant_id) = f.tenant_id {
-                condition = condition.add(connection_identity::Column::TenantId.eq(tenant_id));
-            }
-            if let Some(erp_provider) = f.erp_provider {
-                condition = condition.add(connection_identity::Column::ErpProvider.eq(erp_provider));
-            }
-            if let Some(erp_type) = f.erp_type {
-                condition = condition.add(connection_identity::Column::ErpType.eq(erp_type));
-            }
-            if let Some(status) = f.status {
-                condition = condition.add(connection_identity::Column::Status.eq(status));
-            }
-            if let Some(auth_status) = f.auth_status {
-                condition = condition.add(connection_identity::Column::AuthStatus.eq(auth_status));
-            }
-            if let Some(environment) = f.environment {
-                condition = condition.add(connection_identity::Column::Environment.eq(environment));
-            }
-            if let Some(is_enabled) = f.is_enabled {
-                condition = condition.add(connection_identity::Column::IsEnabled.eq(is_enabled));
-            }
-            if let Some(display_name) = f.display_name {
-                condition = condition.add(connection_identity::Column::DisplayName.contains(&display_name));
-            }
-        }
+    ///case-insensitive substring search (ILIKE) over a tenant's connections: display name,
+    ///realm/tenant ids, company file id/path, and the credentials' provider_user_id
+    ///(e.g. the Web Connector username)
+    pub async fn search(
+        &self,
+        q: &str,
+        tenant_id: i64,
+        page: u64,
+        per_page: u64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<PaginatedConnectionIdentities, DbErr> {
+        let pattern = format!("%{}%", escape_like(q.trim()));
+        let matches = |column: connection_identity::Column| {
+            Expr::col((connection_identity::Entity, column)).ilike(pattern.as_str())
+        };
 
+        let condition = Condition::any()
+            .add(matches(connection_identity::Column::DisplayName))
+            .add(matches(connection_identity::Column::ProviderRealmId))
+            .add(matches(connection_identity::Column::ProviderTenantId))
+            .add(matches(connection_identity::Column::CompanyFileId))
+            .add(matches(connection_identity::Column::CompanyFilePath))
+            .add(
+                Expr::col((
+                    erp_connection_credentials::Entity,
+                    erp_connection_credentials::Column::ProviderUserId,
+                ))
+                .ilike(pattern.as_str()),
+            );
+
+        //a connection with several credential rows must still appear once
         let query = connection_identity::Entity::find()
+            .left_join(erp_connection_credentials::Entity)
+            .filter(connection_identity::Column::TenantId.eq(tenant_id))
             .filter(condition)
+            .distinct()
             .order_by_desc(connection_identity::Column::CreatedAt);
 
+        self.paginate(query, page, per_page, txn).await
+    }
+
+    async fn paginate(
+        &self,
+        query: Select<connection_identity::Entity>,
+        page: u64,
+        per_page: u64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<PaginatedConnectionIdentities, DbErr> {
         let total = match txn {
             Some(txn) => query.clone().count(txn).await?,
             None => query.clone().count(&self.db).await?,
@@ -236,11 +374,72 @@ impl ConnectionIdentityService {
         }
     }
 
+    ///finds an active connection that shares a provider identity with the given values:
+    ///same (tenant_id, erp_provider, erp_type, company_file_id) or same provider_realm_id
+    pub async fn find_conflicting(
+        &self,
+        tenant_id: i64,
+        erp_provider: &ErpProvider,
+        erp_type: &ErpProviderType,
+        company_file_id: Option<&str>,
+        provider_realm_id: Option<&str>,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<connection_identity::Model>, DbErr> {
+        let mut identity = Condition::any();
+        if let Some(company_file_id) = company_file_id {
+            identity = identity.add(
+                Condition::all()
+                    .add(connection_identity::Column::TenantId.eq(tenant_id))
+                    .add(connection_identity::Column::ErpProvider.eq(erp_provider.clone()))
+                    .add(connection_identity::Column::ErpType.eq(erp_type.clone()))
+                    .add(connection_identity::Column::CompanyFileId.eq(company_file_id)),
+            );
+        }
+        if let Some(provider_realm_id) = provider_realm_id {
+            identity =
+                identity.add(connection_identity::Column::ProviderRealmId.eq(provider_realm_id));
+        }
+        if identity.is_empty() {
+            return Ok(None);
+        }
+
+        let query = connection_identity::Entity::find()
+            .filter(connection_identity::Column::Status.eq(ErpConnectionStatus::Active))
+            .filter(identity);
+
+        match txn {
+            Some(txn) => query.one(txn).await,
+            None => query.one(&self.db).await,
+        }
+    }
+
+    ///returns `Conflict` when an active connection with the same provider identity exists
     pub async fn create(
         &self,
         data: CreateConnectionIdentity,
         txn: Option<&DatabaseTransaction>,
-    ) -> Result<connection_identity::Model, DbErr> {
+    ) -> Result<connection_identity::Model, ConnectionIdentityError> {
+        if let (Some(_), None) = (&self.audit, txn) {
+            let txn = self.db.begin().await?;
+            let created = Box::pin(self.create(data, Some(&txn))).await?;
+            txn.commit().await?;
+            return Ok(created);
+        }
+
+        let existing = self
+            .find_conflicting(
+                data.tenant_id,
+                &data.erp_provider,
+                &data.erp_type,
+                data.company_file_id.as_deref(),
+                data.provider_realm_id.as_deref(),
+                txn,
+            )
+            .await?;
+        if existing.is_some() {
+            return Err(ConnectionIdentityError::Conflict(IDENTITY_CONFLICT_MESSAGE.to_string()));
+        }
+
         let active = connection_identity::ActiveModel {
             tenant_id: Set(data.tenant_id),
             erp_provider: Set(data.erp_provider),
@@ -263,21 +462,48 @@ impl ConnectionIdentityService {
             web_connector_app_name: Set(data.web_connector_app_name),
             secret_storage_ref: Set(data.secret_storage_ref),
             secret_version: Set(data.secret_version),
+            sync_window_start_hour: Set(data.sync_window.map(|w| w.start_hour as i16)),
+            sync_window_end_hour: Set(data.sync_window.map(|w| w.end_hour as i16)),
             ..Default::default()
         };
 
-        match txn {
-            Some(txn) => active.insert(txn).await,
-            None => active.insert(&self.db).await,
-        }
+        let created = match txn {
+            Some(txn) => active.insert(txn).await?,
+            None => active.insert(&self.db).await?,
+        };
+        self.record_audit(AuditEntry::created(created.uuid, &created), txn)
+            .await?;
+
+        Ok(created)
     }
 
+    ///applies `patch`; the result lists the fields whose value it changed
     pub async fn update_by_uuid(
         &self,
         uuid: Uuid,
         patch: UpdateConnectionIdentity,
         txn: Option<&DatabaseTransaction>,
-    ) -> Result<Option<connection_identity::Model>, ConnectionIdentityError> {
+    ) -> Result<Option<UpdatedConnectionIdentity>, ConnectionIdentityError> {
+        self.update_audited(uuid, patch, AuditAction::Update, txn).await
+    }
+
+    ///`update_by_uuid`, with `action` as what the audit row records (soft deletes are
+    ///updates of the status)
+    async fn update_audited(
+        &self,
+        uuid: Uuid,
+        patch: UpdateConnectionIdentity,
+        action: AuditAction,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<UpdatedConnectionIdentity>, ConnectionIdentityError> {
+        //the audit row and a `connection.needs_reauth` message commit with the update
+        if txn.is_none() && (self.audit.is_some() || patch.auth_status.is_some()) {
+            let txn = self.db.begin().await?;
+            let updated = Box::pin(self.update_audited(uuid, patch, action, Some(&txn))).await?;
+            txn.commit().await?;
+            return Ok(updated);
+        }
+
         let model = match txn {
             Some(txn) => {
                 connection_identity::Entity::find()
@@ -297,7 +523,7 @@ impl ConnectionIdentityService {
             return Err(ConnectionIdentityError::NotFound);
         };
 
-        let mut active: connection_identity::ActiveModel = model.into();
+        let mut active: connection_identity::ActiveModel = model.clone().into();
 
         if let Some(display_name) = patch.display_name {
             active.display_name = Set(Some(display_name));
@@ -356,13 +582,38 @@ impl ConnectionIdentityService {
         if let Some(last_error_message) = patch.last_error_message {
             active.last_error_message = Set(Some(last_error_message));
         }
+        if let Some(window) = patch.sync_window {
+            active.sync_window_start_hour = Set(window.map(|w| w.start_hour as i16));
+            active.sync_window_end_hour = Set(window.map(|w| w.end_hour as i16));
+        }
+        if let Some(field_mapping) = patch.field_mapping {
+            active.field_mapping = Set(field_mapping);
+        }
 
-        active.updated_at = Set(chrono::Utc::now().into());
+        active.updated_at = Set(self.clock.now().into());
 
-        match txn {
-            Some(txn) => Ok(Some(active.update(txn).await?)),
-            None => Ok(Some(active.update(&self.db).await?)),
+        let updated = match txn {
+            Some(txn) => active.update(txn).await?,
+            None => active.update(&self.db).await?,
+        };
+        self.record_audit(AuditEntry::changed(action, uuid, &model, &updated), txn)
+            .await?;
+        //a patch that sets the error message along with the status says why
+        let reason = match updated.last_error_message != model.last_error_message {
+            true => updated.last_error_message.as_deref(),
+            false => None,
+        };
+        if let (Some(txn), Some(message)) = (
+            txn,
+            needs_reauth_message(&model.auth_status, &updated, reason.unwrap_or("auth_status updated")),
+        ) {
+            OutboxService::new(self.db.clone()).enqueue(txn, message).await?;
         }
+
+        Ok(Some(UpdatedConnectionIdentity {
+            changed_fields: changed_fields(&model, &updated),
+            model: updated,
+        }))
     }
 
     ///soft delete - sets status to removed instead of deleting
@@ -371,7 +622,7 @@ impl ConnectionIdentityService {
         uuid: Uuid,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<connection_identity::Model>, ConnectionIdentityError> {
-        self.update_by_uuid(
+        self.update_audited(
             uuid,
             UpdateConnectionIdentity {
                 status: Some(ErpConnectionStatus::Removed),
@@ -393,10 +644,207 @@ impl ConnectionIdentityService {
                 sync_enabled_pull: None,
                 last_error_code: None,
                 last_error_message: None,
+                sync_window: None,
+                field_mapping: None,
             },
+            AuditAction::Delete,
             txn,
         )
         .await
+        .map(|updated| updated.map(|u| u.model))
+    }
+
+    ///counts the rows a `purge` of the connection would delete, without deleting anything
+    pub async fn delete_preview(
+        &self,
+        connection_id: i64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<ConnectionDependents, DbErr> {
+        match txn {
+            Some(txn) => self.count_dependents(connection_id, txn).await,
+            None => {
+                let txn = self.db.begin().await?;
+                let dependents = self.count_dependents(connection_id, &txn).await?;
+                txn.commit().await?;
+                Ok(dependents)
+            }
+        }
+    }
+
+    ///hard delete - removes the connection and every dependent row (see
+    ///`ConnectionDependents`) in one transaction and returns what was removed
+    pub async fn purge(
+        &self,
+        connection: &connection_identity::Model,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<ConnectionDependents, DbErr> {
+        match txn {
+            Some(txn) => self.apply_purge(connection, txn).await,
+            None => {
+                let txn = self.db.begin().await?;
+                let removed = self.apply_purge(connection, &txn).await?;
+                txn.commit().await?;
+                Ok(removed)
+            }
+        }
+    }
+
+    async fn apply_purge(
+        &self,
+        connection: &connection_identity::Model,
+        txn: &DatabaseTransaction,
+    ) -> Result<ConnectionDependents, DbErr> {
+        let removed = self.count_dependents(connection.id, txn).await?;
+
+        sync_event::Entity::delete_many()
+            .filter(sync_events_of(connection.id))
+            .exec(txn)
+            .await?;
+        //credentials, sync state, runs, inventory records and their events cascade
+        connection_identity::Entity::delete_many()
+            .filter(connection_identity::Column::Id.eq(connection.id))
+            .exec(txn)
+            .await?;
+
+        self.record_audit(AuditEntry::deleted(connection.uuid, connection), Some(txn))
+            .await?;
+        Ok(removed)
+    }
+
+    async fn count_dependents(
+        &self,
+        connection_id: i64,
+        txn: &DatabaseTransaction,
+    ) -> Result<ConnectionDependents, DbErr> {
+        Ok(ConnectionDependents {
+            credentials: erp_connection_credentials::Entity::find()
+                .filter(erp_connection_credentials::Column::ConnectionId.eq(connection_id))
+                .count(txn)
+                .await?,
+            sync_states: erp_connection_sync_state::Entity::find()
+                .filter(erp_connection_sync_state::Column::ConnectionId.eq(connection_id))
+                .count(txn)
+                .await?,
+            connection_runs: connection_run::Entity::find()
+                .filter(connection_run::Column::ConnectionId.eq(connection_id))
+                .count(txn)
+                .await?,
+            sync_events: sync_event::Entity::find()
+                .filter(sync_events_of(connection_id))
+                .count(txn)
+                .await?,
+            inventory_records: inventory_record::Entity::find()
+                .filter(inventory_record::Column::OriginatingConnectionId.eq(connection_id))
+                .count(txn)
+                .await?,
+            //events of the connection's records, plus any it wrote on other records
+            inventory_record_events: inventory_record_event::Entity::find()
+                .filter(
+                    Condition::any()
+                        .add(inventory_record_event::Column::ConnectionId.eq(connection_id))
+                        .add(
+                            inventory_record_event::Column::InventoryRecordId.in_subquery(
+                                inventory_record::Entity::find()
+                                    .select_only()
+                                    .column(inventory_record::Column::Id)
+                                    .filter(
+                                        inventory_record::Column::OriginatingConnectionId
+                                            .eq(connection_id),
+                                    )
+                                    .into_query(),
+                            ),
+                        ),
+                )
+                .count(txn)
+                .await?,
+        })
+    }
+
+    ///enables or disables every connection of a tenant with a single UPDATE and returns the
+    ///number of connections updated; disabling also releases their sync locks so a run
+    ///in flight can't keep a disabled connection locked. With an audit context, each
+    ///connection whose flag changes gets an audit row
+    pub async fn set_enabled_by_tenant(
+        &self,
+        tenant_id: i64,
+        enabled: bool,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<u64, DbErr> {
+        match txn {
+            Some(txn) => self.apply_enabled_by_tenant(tenant_id, enabled, txn).await,
+            None => {
+                let txn = self.db.begin().await?;
+                let updated = self.apply_enabled_by_tenant(tenant_id, enabled, &txn).await?;
+                txn.commit().await?;
+                Ok(updated)
+            }
+        }
+    }
+
+    async fn apply_enabled_by_tenant(
+        &self,
+        tenant_id: i64,
+        enabled: bool,
+        txn: &DatabaseTransaction,
+    ) -> Result<u64, DbErr> {
+        let now = chrono::DateTime::<chrono::FixedOffset>::from(self.clock.now());
+
+        //read before the UPDATE so each audit row has the connection as it was
+        let changing = match &self.audit {
+            Some(_) => {
+                connection_identity::Entity::find()
+                    .filter(connection_identity::Column::TenantId.eq(tenant_id))
+                    .filter(connection_identity::Column::IsEnabled.ne(enabled))
+                    .all(txn)
+                    .await?
+            }
+            None => Vec::new(),
+        };
+
+        let result = connection_identity::Entity::update_many()
+            .col_expr(connection_identity::Column::IsEnabled, Expr::value(enabled))
+            .col_expr(connection_identity::Column::UpdatedAt, Expr::value(now))
+            .filter(connection_identity::Column::TenantId.eq(tenant_id))
+            .exec(txn)
+            .await?;
+
+        if !enabled {
+            let tenant_connections = connection_identity::Entity::find()
+                .select_only()
+                .column(connection_identity::Column::Id)
+                .filter(connection_identity::Column::TenantId.eq(tenant_id))
+                .into_query();
+
+            erp_connection_sync_state::Entity::update_many()
+                .col_expr(
+                    erp_connection_sync_state::Column::SyncLockOwner,
+                    Expr::value(Option::<String>::None),
+                )
+                .col_expr(
+                    erp_connection_sync_state::Column::SyncLockUntil,
+                    Expr::value(Option::<chrono::DateTime<chrono::FixedOffset>>::None),
+                )
+                .col_expr(erp_connection_sync_state::Column::UpdatedAt, Expr::value(now))
+                .filter(erp_connection_sync_state::Column::ConnectionId.in_subquery(tenant_connections))
+                .filter(erp_connection_sync_state::Column::SyncLockOwner.is_not_null())
+                .exec(txn)
+                .await?;
+        }
+
+        for before in changing {
+            let after = connection_identity::Model {
+                is_enabled: enabled,
+                updated_at: now,
+                ..before.clone()
+            };
+            self.record_audit(
+                AuditEntry::changed(AuditAction::Update, before.uuid, &before, &after),
+                Some(txn),
+            )
+            .await?;
+        }
+
+        Ok(result.rows_affected)
     }
 
     ///records a successful sync/operation timestamp
@@ -424,13 +872,14 @@ impl ConnectionIdentityService {
             return Err(ConnectionIdentityError::NotFound);
         };
 
+        let now = self.clock.now();
         let mut active: connection_identity::ActiveModel = model.into();
-        active.last_success_at = Set(Some(chrono::Utc::now().into()));
+        active.last_success_at = Set(Some(now.into()));
         active.last_error_code = Set(None);
         active.last_error_message = Set(None);
         active.error_at = Set(None);
         active.auth_status = Set(ErpConnectionAuthStatus::Connected);
-        active.updated_at = Set(chrono::Utc::now().into());
+        active.updated_at = Set(now.into());
 
         match txn {
             Some(txn) => Ok(Some(active.update(txn).await?)),
@@ -438,7 +887,8 @@ impl ConnectionIdentityService {
         }
     }
 
-    ///records an error on the connection
+    ///records an error on the connection; the first error after a healthy state also
+    ///enqueues a `connection.needs_reauth` message in the same transaction
     pub async fn record_error(
         &self,
         uuid: Uuid,
@@ -446,35 +896,145 @@ impl ConnectionIdentityService {
         error_message: &str,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<connection_identity::Model>, ConnectionIdentityError> {
-        let model = match txn {
-            Some(txn) => {
-                connection_identity::Entity::find()
-                    .filter(connection_identity::Column::Uuid.eq(uuid))
-                    .one(txn)
-                    .await?
-            }
-            None => {
-                connection_identity::Entity::find()
-                    .filter(connection_identity::Column::Uuid.eq(uuid))
-                    .one(&self.db)
-                    .await?
+        let Some(txn) = txn else {
+            let txn = self.db.begin().await?;
+            let recorded = Box::pin(self.record_error(uuid, error_code, error_message, Some(&txn))).await;
+            match recorded {
+                Ok(_) => txn.commit().await?,
+                Err(_) => txn.rollback().await?,
             }
+            return recorded;
         };
 
+        let model = connection_identity::Entity::find()
+            .filter(connection_identity::Column::Uuid.eq(uuid))
+            .one(txn)
+            .await?;
+
         let Some(model) = model else {
             return Err(ConnectionIdentityError::NotFound);
         };
 
+        let now = self.clock.now();
+        let previous = model.auth_status.clone();
         let mut active: connection_identity::ActiveModel = model.into();
         active.last_error_code = Set(Some(error_code.to_string()));
         active.last_error_message = Set(Some(error_message.to_string()));
-        active.error_at = Set(Some(chrono::Utc::now().into()));
+        active.error_at = Set(Some(now.into()));
         active.auth_status = Set(ErpConnectionAuthStatus::Error);
-        active.updated_at = Set(chrono::Utc::now().into());
+        active.updated_at = Set(now.into());
 
-        match txn {
-            Some(txn) => Ok(Some(active.update(txn).await?)),
-            None => Ok(Some(active.update(&self.db).await?)),
+        let updated = active.update(txn).await?;
+        if let Some(message) = needs_reauth_message(&previous, &updated, error_message) {
+            OutboxService::new(self.db.clone()).enqueue(txn, message).await?;
         }
+        Ok(Some(updated))
+    }
+}
+
+///the `connection.needs_reauth` message for a connection whose `auth_status` went from
+///`previous` to `needs_reauth` or `error`; None when it was already in that state (a repeated
+///error notifies nobody) or is healthy
+pub fn needs_reauth_message(
+    previous: &ErpConnectionAuthStatus,
+    updated: &connection_identity::Model,
+    reason: &str,
+) -> Option<OutboxPayload> {
+    let unhealthy = matches!(
+        updated.auth_status,
+        ErpConnectionAuthStatus::NeedsReauth | ErpConnectionAuthStatus::Error
+    );
+    if !unhealthy || *previous == updated.auth_status {
+        return None;
     }
+    Some(OutboxPayload {
+        topic: TOPIC_CONNECTION_NEEDS_REAUTH.to_string(),
+        payload: serde_json::json!({
+            "tenant_id": updated.tenant_id,
+            "connection_uuid": updated.uuid,
+            "erp_provider": updated.erp_provider.to_value(),
+            "erp_type": updated.erp_type.to_value(),
+            "auth_status": updated.auth_status.to_value(),
+            "previous_auth_status": previous.to_value(),
+            "reason": reason,
+            "error_code": updated.last_error_code,
+        }),
+    })
+}
+
+///the `get_all` query: connections matching `filter`, in `order_by` order
+pub fn list_query(
+    filter: Option<ConnectionIdentityFilter>,
+    order_by: ConnectionOrder,
+) -> Select<connection_identity::Entity> {
+    let mut condition = Condition::all();
+
+    if let Some(f) = filter {
+        if let Some(tenant_id) = f.tenant_id {
+            condition = condition.add(connection_identity::Column::TenantId.eq(tenant_id));
+        }
+        if let Some(erp_provider) = f.erp_provider {
+            condition = condition.add(connection_identity::Column::ErpProvider.eq(erp_provider));
+        }
+        if let Some(erp_type) = f.erp_type {
+            condition = condition.add(connection_identity::Column::ErpType.eq(erp_type));
+        }
+        if let Some(status) = f.status {
+            condition = condition.add(connection_identity::Column::Status.eq(status));
+        }
+        if let Some(auth_status) = f.auth_status {
+            condition = condition.add(connection_identity::Column::AuthStatus.eq(auth_status));
+        }
+        if let Some(environment) = f.environment {
+            condition = condition.add(connection_identity::Column::Environment.eq(environment));
+        }
+        if let Some(is_enabled) = f.is_enabled {
+            condition = condition.add(connection_identity::Column::IsEnabled.eq(is_enabled));
+        }
+        if let Some(display_name) = f.display_name {
+            condition = condition.add(connection_identity::Column::DisplayName.contains(&display_name));
+        }
+    }
+
+    let mut query = connection_identity::Entity::find().filter(condition);
+    if order_by == ConnectionOrder::HealthPriority {
+        let unhealthy = connection_identity::Column::AuthStatus.is_in([
+            ErpConnectionAuthStatus::Error,
+            ErpConnectionAuthStatus::NeedsReauth,
+        ]);
+        let priority: Expr = Expr::case(unhealthy, 0).finally(1).into();
+        query = query
+            .order_by(priority, Order::Asc)
+            .order_by_with_nulls(
+                connection_identity::Column::LastSuccessAt,
+                Order::Asc,
+                NullOrdering::First,
+            );
+    }
+    query.order_by_desc(connection_identity::Column::CreatedAt)
+}
+
+///sync events of the connection's sync state or of its runs
+fn sync_events_of(connection_id: i64) -> Condition {
+    let sync_states = erp_connection_sync_state::Entity::find()
+        .select_only()
+        .column(erp_connection_sync_state::Column::Id)
+        .filter(erp_connection_sync_state::Column::ConnectionId.eq(connection_id))
+        .into_query();
+    let runs = connection_run::Entity::find()
+        .select_only()
+        .column(connection_run::Column::Id)
+        .filter(connection_run::Column::ConnectionId.eq(connection_id))
+        .into_query();
+    Condition::any()
+        .add(sync_event::Column::ConnectionSyncStateId.in_subquery(sync_states))
+        .add(sync_event::Column::ConnectionRunId.in_subquery(runs))
+}
+
+///escapes LIKE wildcards so user input matches literally
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
@@ -0,0 +1,153 @@
+//! Status aggregates of several connections at once (no routes).
+//!
+//! Dashboards show many connections side by side, so the batch is read with a fixed number of
+//! queries whatever its size: one for the connections, then one grouped count of their
+//! unsettled sync events per way an event is tied to a connection (its sync state, or a run
+//! when it has no sync state).
+
+use std::collections::HashMap;
+
+use entity::sea_orm_active_enums::{ErpConnectionAuthStatus, ErpConnectionStatus, SyncEventStatus};
+use entity::{connection_identity, connection_run, erp_connection_sync_state, sync_event};
+use sea_orm::sea_query::{Expr, Func};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DbErr, DerivePartialModel, EntityTrait, QueryFilter,
+    QuerySelect, RelationTrait,
+};
+use sea_orm::JoinType;
+use uuid::Uuid;
+
+/// Most connections one batch request may ask for.
+pub const MAX_STATUS_BATCH_SIZE: usize = 100;
+
+/// BEGUN STRUCTS AND ENUMS ///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SyncEventCounts {
+    /// Sync events waiting to be picked up.
+    pub pending: u64,
+    /// Sync events being processed.
+    pub in_progress: u64,
+}
+
+/// The connection columns a status reports.
+#[derive(Debug, Clone, PartialEq, Eq, DerivePartialModel)]
+#[sea_orm(entity = "connection_identity::Entity")]
+pub struct ConnectionStatusColumns {
+    pub id: i64,
+    pub uuid: Uuid,
+    pub status: ErpConnectionStatus,
+    pub auth_status: ErpConnectionAuthStatus,
+    pub is_enabled: bool,
+    pub last_success_at: Option<DateTimeWithTimeZone>,
+    pub last_error_code: Option<String>,
+    pub last_error_message: Option<String>,
+    pub error_at: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectionStatus {
+    pub connection: ConnectionStatusColumns,
+    pub events: SyncEventCounts,
+}
+
+pub struct ConnectionStatusService {
+    db: DatabaseConnection,
+}
+
+/// END STRUCTS AND ENUMS ///
+
+
+/// BEGUN IMPLEMENTATION ///
+impl ConnectionStatusService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    ///statuses of the connections among `uuids`, in request order; unknown uuids (and other
+    ///tenants' connections when `tenant_id` is given) are left out
+    pub async fn get_batch(
+        &self,
+        uuids: &[Uuid],
+        tenant_id: Option<i64>,
+    ) -> Result<Vec<ConnectionStatus>, DbErr> {
+        if uuids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut query = connection_identity::Entity::find()
+            .filter(connection_identity::Column::Uuid.is_in(uuids.iter().copied()));
+        if let Some(tenant_id) = tenant_id {
+            query = query.filter(connection_identity::Column::TenantId.eq(tenant_id));
+        }
+        let connections = query
+            .into_partial_model::<ConnectionStatusColumns>()
+            .all(&self.db)
+            .await?;
+
+        let ids: Vec<i64> = connections.iter().map(|c| c.id).collect();
+        let counts = self.event_counts(&ids).await?;
+
+        let mut by_uuid: HashMap<Uuid, ConnectionStatusColumns> =
+            connections.into_iter().map(|c| (c.uuid, c)).collect();
+        Ok(uuids
+            .iter()
+            .filter_map(|uuid| by_uuid.remove(uuid))
+            .map(|connection| ConnectionStatus {
+                events: counts.get(&connection.id).copied().unwrap_or_default(),
+                connection,
+            })
+            .collect())
+    }
+
+    ///pending and in-progress sync events per connection id
+    async fn event_counts(
+        &self,
+        connection_ids: &[i64],
+    ) -> Result<HashMap<i64, SyncEventCounts>, DbErr> {
+        let mut counts: HashMap<i64, SyncEventCounts> = HashMap::new();
+        if connection_ids.is_empty() {
+            return Ok(counts);
+        }
+
+        let unsettled = [SyncEventStatus::Pending, SyncEventStatus::InProgress];
+        let by_sync_state = sync_event::Entity::find()
+            .select_only()
+            .column(erp_connection_sync_state::Column::ConnectionId)
+            .column(sync_event::Column::Status)
+            .expr(Func::count(Expr::col((sync_event::Entity, sync_event::Column::Id))))
+            .join(JoinType::InnerJoin, sync_event::Relation::ErpConnectionSyncState.def())
+            .filter(erp_connection_sync_state::Column::ConnectionId.is_in(connection_ids.iter().copied()))
+            .filter(sync_event::Column::Status.is_in(unsettled.clone()))
+            .group_by(erp_connection_sync_state::Column::ConnectionId)
+            .group_by(sync_event::Column::Status)
+            .into_tuple::<(i64, SyncEventStatus, i64)>()
+            .all(&self.db)
+            .await?;
+        //events created for a run without a sync state
+        let by_run = sync_event::Entity::find()
+            .select_only()
+            .column(connection_run::Column::ConnectionId)
+            .column(sync_event::Column::Status)
+            .expr(Func::count(Expr::col((sync_event::Entity, sync_event::Column::Id))))
+            .join(JoinType::InnerJoin, sync_event::Relation::ConnectionRun.def())
+            .filter(connection_run::Column::ConnectionId.is_in(connection_ids.iter().copied()))
+            .filter(sync_event::Column::ConnectionSyncStateId.is_null())
+            .filter(sync_event::Column::Status.is_in(unsettled))
+            .group_by(connection_run::Column::ConnectionId)
+            .group_by(sync_event::Column::Status)
+            .into_tuple::<(i64, SyncEventStatus, i64)>()
+            .all(&self.db)
+            .await?;
+
+        for (connection_id, status, count) in by_sync_state.into_iter().chain(by_run) {
+            let entry = counts.entry(connection_id).or_default();
+            match status {
+                SyncEventStatus::Pending => entry.pending += count as u64,
+                SyncEventStatus::InProgress => entry.in_progress += count as u64,
+                _ => {}
+            }
+        }
+        Ok(counts)
+    }
+}
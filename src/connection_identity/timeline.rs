@@ -0,0 +1,235 @@
+//! Chronological activity of one connection (no routes).
+//!
+//! Merges the connection's runs, the outcomes of its sync events and the auth status changes
+//! recorded in the audit log into one list, newest first:
+//!   - `run_started`: a run's `created_at`
+//!   - `run_failed`: a failed run's `updated_at`, with its error message
+//!   - `event_success` / `event_error`: a sync event's `updated_at` once it has settled
+//!     (pending and in-progress events are left out)
+//!   - `auth_status_changed`: an audited update of the connection's `auth_status`
+//!
+//! Pages are cut by timestamp: the next page is requested with the previous page's
+//! `next_before`, and only holds entries strictly older than it.
+
+use chrono::{DateTime, FixedOffset};
+use entity::sea_orm_active_enums::{ConnectionRunStatus, SyncEventStatus};
+use entity::{audit_log, connection_identity, connection_run, erp_connection_sync_state, sync_event};
+use sea_orm::sea_query::{Expr, ExprTrait, Query};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+pub const DEFAULT_TIMELINE_LIMIT: u64 = 50;
+pub const MAX_TIMELINE_LIMIT: u64 = 200;
+
+/// BEGUN STRUCTS AND ENUMS ///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineEntryKind {
+    RunStarted,
+    RunFailed,
+    EventSuccess,
+    EventError,
+    AuthStatusChanged,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineEntry {
+    pub kind: TimelineEntryKind,
+    pub at: DateTime<FixedOffset>,
+    /// The run, sync event or audit row the entry comes from.
+    pub source_uuid: Uuid,
+    /// Error message of failures; `old -> new` for auth status changes.
+    pub message: Option<String>,
+}
+
+pub struct TimelinePage {
+    pub entries: Vec<TimelineEntry>,
+    /// Pass as `before` to get the next (older) page; None on the last page.
+    pub next_before: Option<DateTime<FixedOffset>>,
+}
+
+pub struct TimelineService {
+    db: DatabaseConnection,
+}
+
+/// END STRUCTS AND ENUMS ///
+
+
+/// BEGUN IMPLEMENTATION ///
+impl TimelineEntryKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimelineEntryKind::RunStarted => "run_started",
+            TimelineEntryKind::RunFailed => "run_failed",
+            TimelineEntryKind::EventSuccess => "event_success",
+            TimelineEntryKind::EventError => "event_error",
+            TimelineEntryKind::AuthStatusChanged => "auth_status_changed",
+        }
+    }
+}
+
+impl TimelineService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    ///up to `limit` entries older than `before` (newest first when None)
+    pub async fn get_page(
+        &self,
+        connection: &connection_identity::Model,
+        before: Option<DateTime<FixedOffset>>,
+        limit: u64,
+    ) -> Result<TimelinePage, DbErr> {
+        let limit = limit.clamp(1, MAX_TIMELINE_LIMIT);
+        let is_before = |at: &DateTime<FixedOffset>| before.is_none_or(|before| *at < before);
+
+        //each source is cut at `limit` rows, which is all a page can take from it
+        let mut entries = Vec::new();
+        for run in self.runs(connection.id, before, limit).await? {
+            if is_before(&run.created_at) {
+                entries.push(TimelineEntry {
+                    kind: TimelineEntryKind::RunStarted,
+                    at: run.created_at,
+                    source_uuid: run.uuid,
+                    message: None,
+                });
+            }
+            if run.status == ConnectionRunStatus::Error && is_before(&run.updated_at) {
+                entries.push(TimelineEntry {
+                    kind: TimelineEntryKind::RunFailed,
+                    at: run.updated_at,
+                    source_uuid: run.uuid,
+                    message: run.error_message,
+                });
+            }
+        }
+        for event in self.settled_events(connection.id, before, limit).await? {
+            let (kind, message) = match event.status {
+                SyncEventStatus::Error => {
+                    (TimelineEntryKind::EventError, event.last_error.as_ref().map(json_message))
+                }
+                _ => (TimelineEntryKind::EventSuccess, None),
+            };
+            entries.push(TimelineEntry {
+                kind,
+                at: event.updated_at,
+                source_uuid: event.uuid,
+                message,
+            });
+        }
+        for change in self.auth_status_changes(connection.uuid, before, limit).await? {
+            let status = |snapshot: &Option<Value>| {
+                snapshot
+                    .as_ref()
+                    .and_then(|s| s.get("auth_status"))
+                    .map(json_message)
+                    .unwrap_or_else(|| "unknown".to_string())
+            };
+            entries.push(TimelineEntry {
+                kind: TimelineEntryKind::AuthStatusChanged,
+                at: change.created_at,
+                source_uuid: change.uuid,
+                message: Some(format!("{} -> {}", status(&change.before), status(&change.after))),
+            });
+        }
+
+        entries.sort_by_key(|e| std::cmp::Reverse(e.at));
+        entries.truncate(limit as usize);
+        let next_before = match entries.last() {
+            Some(last) if entries.len() as u64 == limit => Some(last.at),
+            _ => None,
+        };
+        Ok(TimelinePage { entries, next_before })
+    }
+
+    async fn runs(
+        &self,
+        connection_id: i64,
+        before: Option<DateTime<FixedOffset>>,
+        limit: u64,
+    ) -> Result<Vec<connection_run::Model>, DbErr> {
+        let mut query = connection_run::Entity::find()
+            .filter(connection_run::Column::ConnectionId.eq(connection_id));
+        if let Some(before) = before {
+            query = query.filter(connection_run::Column::CreatedAt.lt(before));
+        }
+        query
+            .order_by_desc(connection_run::Column::CreatedAt)
+            .order_by_desc(connection_run::Column::Id)
+            .limit(limit)
+            .all(&self.db)
+            .await
+    }
+
+    ///settled sync events of the connection
+    async fn settled_events(
+        &self,
+        connection_id: i64,
+        before: Option<DateTime<FixedOffset>>,
+        limit: u64,
+    ) -> Result<Vec<sync_event::Model>, DbErr> {
+        let mut query = sync_event::Entity::find()
+            .filter(connection_events(connection_id))
+            .filter(
+                sync_event::Column::Status.is_in([SyncEventStatus::Success, SyncEventStatus::Error]),
+            );
+        if let Some(before) = before {
+            query = query.filter(sync_event::Column::UpdatedAt.lt(before));
+        }
+        query
+            .order_by_desc(sync_event::Column::UpdatedAt)
+            .order_by_desc(sync_event::Column::Id)
+            .limit(limit)
+            .all(&self.db)
+            .await
+    }
+
+    ///audited updates of the connection whose changed fields include `auth_status`
+    async fn auth_status_changes(
+        &self,
+        connection_uuid: Uuid,
+        before: Option<DateTime<FixedOffset>>,
+        limit: u64,
+    ) -> Result<Vec<audit_log::Model>, DbErr> {
+        let mut query = audit_log::Entity::find()
+            .filter(audit_log::Column::EntityType.eq("connection_identity"))
+            .filter(audit_log::Column::EntityUuid.eq(connection_uuid))
+            .filter(audit_log::Column::Action.eq("update"))
+            //`after` only holds changed fields; text match works on jsonb and SQLite alike
+            .filter(Expr::cust("CAST(\"after\" AS TEXT)").like("%\"auth_status\"%"));
+        if let Some(before) = before {
+            query = query.filter(audit_log::Column::CreatedAt.lt(before));
+        }
+        query
+            .order_by_desc(audit_log::Column::CreatedAt)
+            .order_by_desc(audit_log::Column::Id)
+            .limit(limit)
+            .all(&self.db)
+            .await
+    }
+}
+
+///sync events tied to the connection through one of its runs or its sync state
+pub fn connection_events(connection_id: i64) -> Condition {
+    let run_ids = Query::select()
+        .column(connection_run::Column::Id)
+        .from(connection_run::Entity)
+        .and_where(connection_run::Column::ConnectionId.eq(connection_id))
+        .to_owned();
+    let sync_state_ids = Query::select()
+        .column(erp_connection_sync_state::Column::Id)
+        .from(erp_connection_sync_state::Entity)
+        .and_where(erp_connection_sync_state::Column::ConnectionId.eq(connection_id))
+        .to_owned();
+    Condition::any()
+        .add(sync_event::Column::ConnectionRunId.in_subquery(run_ids))
+        .add(sync_event::Column::ConnectionSyncStateId.in_subquery(sync_state_ids))
+}
+
+///a JSON string as-is, anything else in its JSON form
+fn json_message(value: &Value) -> String {
+    value.as_str().map_or_else(|| value.to_string(), str::to_string)
+}
@@ -1,11 +1,25 @@
+use std::collections::HashMap;
+
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
 use entity::connection_run;
 use entity::sea_orm_active_enums::{ConnectionRunStatus, ConnectionRunType};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::{Expr, Func};
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
-    QueryFilter, QueryOrder, Set,QuerySelect
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, Set,QuerySelect
 };
 use uuid::Uuid;
 
+use crate::config::metrics;
+
+/// How far back `summary` counts runs.
+pub const SUMMARY_WINDOW_HOURS: i64 = 24;
+
+pub const DEFAULT_RUNS_PAGE_LIMIT: u64 = 50;
+pub const MAX_RUNS_PAGE_LIMIT: u64 = 200;
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum ConnectionRunError {
@@ -27,6 +41,9 @@ pub struct ConnectionRunService {
 
 #[allow(dead_code)]
 pub struct CreateConnectionRun {
+    /// Defaults to a fresh uuid; set it when the uuid must be known before the insert
+    /// (e.g. as the sync lock owner).
+    pub uuid: Option<Uuid>,
     pub connection_id: i64,
     pub status: Option<ConnectionRunStatus>,
     pub run_type: Option<ConnectionRunType>,
@@ -39,6 +56,59 @@ pub struct UpdateConnectionRun {
     pub error_message: Option<String>,
 }
 
+/// Keyset position in a connection's runs: the last run of the previous page. Runs are
+/// ordered newest first by `created_at`, then by `id`, so runs sharing a timestamp still
+/// have one fixed order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RunCursor {
+    pub created_at: DateTimeWithTimeZone,
+    pub id: i64,
+}
+
+/// One page of a connection's runs, newest first.
+#[derive(Debug, Clone)]
+pub struct ConnectionRunPage {
+    pub runs: Vec<connection_run::Model>,
+    /// Where the next (older) page starts; None on the last page.
+    pub next_cursor: Option<RunCursor>,
+}
+
+/// Recent runs of one connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectionRunSummary {
+    /// Runs created in the last `SUMMARY_WINDOW_HOURS` that are still Success.
+    pub success_24h: u64,
+    /// Runs created in the last `SUMMARY_WINDOW_HOURS` that ended in Error.
+    pub error_24h: u64,
+    /// When the latest errored run (of any age) last changed.
+    pub last_error_at: Option<DateTimeWithTimeZone>,
+    pub last_error_message: Option<String>,
+}
+
+impl RunCursor {
+    pub fn of(run: &connection_run::Model) -> Self {
+        Self {
+            created_at: run.created_at,
+            id: run.id,
+        }
+    }
+
+    ///opaque token for query strings: base64url of `<created_at rfc3339>|<id>`
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}|{}", self.created_at.to_rfc3339(), self.id))
+    }
+
+    ///None when `token` wasn't produced by `encode`
+    pub fn decode(token: &str) -> Option<Self> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(token).ok()?).ok()?;
+        let (created_at, id) = raw.split_once('|')?;
+        Some(Self {
+            created_at: chrono::DateTime::parse_from_rfc3339(created_at).ok()?,
+            id: id.parse().ok()?,
+        })
+    }
+}
+
 #[allow(dead_code)]
 impl ConnectionRunService {
     pub fn new(db: DatabaseConnection) -> Self {
@@ -86,6 +156,7 @@ impl ConnectionRunService {
         let query = connection_run::Entity::find()
             .filter(connection_run::Column::ConnectionId.eq(connection_id))
             .order_by_desc(connection_run::Column::CreatedAt)
+            .order_by_desc(connection_run::Column::Id)
             .limit(limit);
 
         match txn {
@@ -94,12 +165,56 @@ impl ConnectionRunService {
         }
     }
 
+    ///one page of the connection's runs, newest first; `after` is the previous page's
+    ///`next_cursor`. Keyset on (`created_at`, `id`), so runs created meanwhile don't shift
+    ///the pages and every run is returned exactly once
+    pub async fn page_by_connection_id(
+        &self,
+        connection_id: i64,
+        after: Option<RunCursor>,
+        limit: u64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<ConnectionRunPage, DbErr> {
+        let limit = limit.clamp(1, MAX_RUNS_PAGE_LIMIT);
+        let mut query = connection_run::Entity::find()
+            .filter(connection_run::Column::ConnectionId.eq(connection_id))
+            .order_by_desc(connection_run::Column::CreatedAt)
+            .order_by_desc(connection_run::Column::Id)
+            //one extra row tells whether another page follows
+            .limit(limit + 1);
+        if let Some(after) = after {
+            query = query.filter(
+                Condition::any()
+                    .add(connection_run::Column::CreatedAt.lt(after.created_at))
+                    .add(
+                        Condition::all()
+                            .add(connection_run::Column::CreatedAt.eq(after.created_at))
+                            .add(connection_run::Column::Id.lt(after.id)),
+                    ),
+            );
+        }
+
+        let mut runs = match txn {
+            Some(txn) => query.all(txn).await?,
+            None => query.all(&self.db).await?,
+        };
+        let next_cursor = match runs.len() as u64 > limit {
+            true => {
+                runs.truncate(limit as usize);
+                runs.last().map(RunCursor::of)
+            }
+            false => None,
+        };
+        Ok(ConnectionRunPage { runs, next_cursor })
+    }
+
     pub async fn create(
         &self,
         data: CreateConnectionRun,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<connection_run::Model, DbErr> {
         let active = connection_run::ActiveModel {
+            uuid: Set(data.uuid.unwrap_or_else(Uuid::new_v4)),
             connection_id: Set(data.connection_id),
             status: Set(data.status.unwrap_or(ConnectionRunStatus::Success)),
             run_type: Set(data.run_type.unwrap_or(ConnectionRunType::Poll)),
@@ -107,10 +222,12 @@ impl ConnectionRunService {
             ..Default::default()
         };
 
-        match txn {
-            Some(txn) => active.insert(txn).await,
-            None => active.insert(&self.db).await,
-        }
+        let model = match txn {
+            Some(txn) => active.insert(txn).await?,
+            None => active.insert(&self.db).await?,
+        };
+        metrics::record_connection_run(&model.status.to_string());
+        Ok(model)
     }
 
     pub async fn update_by_uuid(
@@ -138,6 +255,7 @@ impl ConnectionRunService {
             return Err(ConnectionRunError::NotFound);
         };
 
+        let previous_status = model.status.clone();
         let mut active: connection_run::ActiveModel = model.into();
         if let Some(v) = patch.status {
             active.status = Set(v);
@@ -147,9 +265,90 @@ impl ConnectionRunService {
         }
         active.updated_at = Set(chrono::Utc::now().into());
 
-        match txn {
-            Some(txn) => Ok(Some(active.update(txn).await?)),
-            None => Ok(Some(active.update(&self.db).await?)),
+        let model = match txn {
+            Some(txn) => active.update(txn).await?,
+            None => active.update(&self.db).await?,
+        };
+        if model.status != previous_status {
+            metrics::record_connection_run(&model.status.to_string());
+        }
+        Ok(Some(model))
+    }
+
+    ///run counts of the last `SUMMARY_WINDOW_HOURS` and the latest error of one connection
+    pub async fn summary(
+        &self,
+        connection_id: i64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<ConnectionRunSummary, DbErr> {
+        Ok(self
+            .summaries(&[connection_id], txn)
+            .await?
+            .remove(&connection_id)
+            .unwrap_or_default())
+    }
+
+    ///`summary` of several connections with a fixed number of queries; connections without
+    ///runs are left out
+    pub async fn summaries(
+        &self,
+        connection_ids: &[i64],
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<HashMap<i64, ConnectionRunSummary>, DbErr> {
+        let mut summaries: HashMap<i64, ConnectionRunSummary> = HashMap::new();
+        if connection_ids.is_empty() {
+            return Ok(summaries);
+        }
+
+        let since = chrono::Utc::now() - chrono::Duration::hours(SUMMARY_WINDOW_HOURS);
+        let counts_query = connection_run::Entity::find()
+            .select_only()
+            .column(connection_run::Column::ConnectionId)
+            .column(connection_run::Column::Status)
+            .expr(Func::count(Expr::col(connection_run::Column::Id)))
+            .filter(connection_run::Column::ConnectionId.is_in(connection_ids.iter().copied()))
+            .filter(connection_run::Column::CreatedAt.gte(since))
+            .group_by(connection_run::Column::ConnectionId)
+            .group_by(connection_run::Column::Status)
+            .into_tuple::<(i64, ConnectionRunStatus, i64)>();
+        //the newest errored run per connection
+        let last_errors_query = connection_run::Entity::find()
+            .select_only()
+            .expr(Func::max(Expr::col(connection_run::Column::Id)))
+            .filter(connection_run::Column::ConnectionId.is_in(connection_ids.iter().copied()))
+            .filter(connection_run::Column::Status.eq(ConnectionRunStatus::Error))
+            .group_by(connection_run::Column::ConnectionId)
+            .into_tuple::<i64>();
+
+        let (counts, last_error_ids) = match txn {
+            Some(txn) => (counts_query.all(txn).await?, last_errors_query.all(txn).await?),
+            None => (
+                counts_query.all(&self.db).await?,
+                last_errors_query.all(&self.db).await?,
+            ),
+        };
+        for (connection_id, status, count) in counts {
+            let entry = summaries.entry(connection_id).or_default();
+            match status {
+                ConnectionRunStatus::Success => entry.success_24h += count as u64,
+                ConnectionRunStatus::Error => entry.error_24h += count as u64,
+            }
+        }
+        if last_error_ids.is_empty() {
+            return Ok(summaries);
+        }
+
+        let last_errors_query = connection_run::Entity::find()
+            .filter(connection_run::Column::Id.is_in(last_error_ids));
+        let last_errors = match txn {
+            Some(txn) => last_errors_query.all(txn).await?,
+            None => last_errors_query.all(&self.db).await?,
+        };
+        for run in last_errors {
+            let entry = summaries.entry(run.connection_id).or_default();
+            entry.last_error_at = Some(run.updated_at);
+            entry.last_error_message = run.error_message;
         }
+        Ok(summaries)
     }
 }
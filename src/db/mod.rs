@@ -0,0 +1,22 @@
+//! Running a service query on the pool or on the caller's transaction.
+//!
+//! Service methods take `txn: Option<&DatabaseTransaction>` so a caller can fold several
+//! writes into one transaction. Instead of matching on it around every query, a method picks
+//! its executor once with `conn(&self.db, txn)` and runs each query on that:
+//! `Conn` implements `ConnectionTrait`, so `.one(&conn)`, `.insert(&conn)`,
+//! `.paginate(&conn, n)` etc. work as they do on the pool.
+
+use sea_orm::{DatabaseConnection, DatabaseTransaction};
+
+pub mod unique;
+
+/// The pool or a transaction, whichever a service call runs on.
+pub use sea_orm::DatabaseExecutor as Conn;
+
+///`txn` when the caller passed one, the pool otherwise
+pub fn conn<'a>(db: &'a DatabaseConnection, txn: Option<&'a DatabaseTransaction>) -> Conn<'a> {
+    match txn {
+        Some(txn) => Conn::Transaction(txn),
+        None => Conn::Connection(db),
+    }
+}
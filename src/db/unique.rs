@@ -0,0 +1,90 @@
+//! Telling unique-constraint violations apart from other database errors.
+//!
+//! Services map a write refused by a unique index or constraint (Postgres SQLSTATE 23505)
+//! to their `Conflict` error, which routes answer with `409`. The message names what already
+//! exists, looked up by the constraint name Postgres reports (or the `table.column` SQLite
+//! reports), instead of echoing the database error.
+
+use sea_orm::{DbErr, SqlErr};
+
+/// Friendly messages by constraint/index name (Postgres) or `table.column` (SQLite).
+const CONSTRAINT_MESSAGES: &[(&str, &str)] = &[
+    (
+        "ux_connection_identity_active_company_file",
+        "A connection with this company file already exists",
+    ),
+    (
+        "ux_connection_identity_active_realm",
+        "A connection with this provider realm already exists",
+    ),
+    (
+        "erp_connection_credentials_connection_id_idx",
+        "This connection already has credentials",
+    ),
+    (
+        "erp_connection_credentials.connection_id",
+        "This connection already has credentials",
+    ),
+    ("tenant_id_idx", "A tenant with this tenant_id already exists"),
+    ("tenant.tenant_id", "A tenant with this tenant_id already exists"),
+];
+
+/// Message for a violated constraint that has no entry above.
+pub const DEFAULT_CONFLICT_MESSAGE: &str = "A record with the same unique value already exists";
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// A write refused by a unique index or constraint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UniqueViolation {
+    /// Constraint or index name (Postgres), `table.column` (SQLite); None when the error
+    /// doesn't say.
+    pub constraint: Option<String>,
+}
+
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl UniqueViolation {
+    ///the violation `err` reports, None for any other error
+    pub fn from_db_err(err: &DbErr) -> Option<Self> {
+        match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(message)) => Some(Self {
+                constraint: constraint_name(&message),
+            }),
+            _ => None,
+        }
+    }
+
+    ///user-facing message naming what already exists
+    pub fn message(&self) -> String {
+        let constraint = self.constraint.as_deref().unwrap_or_default();
+        let known = CONSTRAINT_MESSAGES
+            .iter()
+            .find(|(name, _)| *name == constraint)
+            .map(|(_, message)| *message);
+        if let Some(message) = known {
+            return message.to_string();
+        }
+        //every table's public uuid is unique (`<table>_uuid_key` / `<table>.uuid`)
+        if constraint.ends_with("_uuid_key")
+            || constraint.ends_with("_uuid_idx")
+            || constraint.ends_with(".uuid")
+        {
+            return "A record with this uuid already exists".to_string();
+        }
+        DEFAULT_CONFLICT_MESSAGE.to_string()
+    }
+}
+
+///the constraint a unique violation message names: `... unique constraint "name"` (Postgres)
+///or `UNIQUE constraint failed: table.column` (SQLite)
+fn constraint_name(message: &str) -> Option<String> {
+    if let Some((_, rest)) = message.split_once("unique constraint \"") {
+        return rest.split_once('"').map(|(name, _)| name.to_string());
+    }
+    message
+        .split_once("UNIQUE constraint failed: ")
+        .and_then(|(_, rest)| rest.split([',', ' ']).next())
+        .filter(|column| !column.is_empty())
+        .map(str::to_string)
+}
@@ -0,0 +1,210 @@
+//! Reading and writing credential secrets according to their `enc_scheme`.
+//!
+//! Every secret column of a credentials row is stored under the row's `enc_scheme`:
+//!   - `none`: stored as given. Used for the QuickBooks Web Connector password, which the
+//!     server compares itself
+//!   - `kms-envelope-v1`: sealed by a `SecretCipher` (the KMS envelope client) under
+//!     `enc_key_id`. Without a cipher configured these secrets can't be written or read
+//!
+//! `CredentialCodec` is the one place that dispatches on the scheme, so writes go through
+//! `encode` and reads through `decode`/`open`. It also enforces `REQUIRE_CREDENTIAL_ENCRYPTION`:
+//! when set, `none` is only accepted for the auth types in `PLAINTEXT_AUTH_TYPES`.
+
+use std::sync::Arc;
+
+use entity::erp_connection_credentials;
+use entity::sea_orm_active_enums::ErpProviderAuthType;
+use sea_orm::ActiveEnum;
+
+pub const ENC_SCHEME_NONE: &str = "none";
+pub const ENC_SCHEME_KMS_ENVELOPE_V1: &str = "kms-envelope-v1";
+
+/// Auth types whose secrets may stay plaintext while encryption is required: the Web
+/// Connector's username/password is generated and checked by this server, never sent to a
+/// provider.
+pub const PLAINTEXT_AUTH_TYPES: [ErpProviderAuthType; 1] = [ErpProviderAuthType::UsernamePassword];
+
+
+//DEBUG AND ERRORS ///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CodecError {
+    /// `enc_scheme` is not one this server knows.
+    UnknownScheme(String),
+    /// The scheme needs a cipher and none is configured.
+    NoCipher(EncScheme),
+    /// Plaintext credentials rejected by `REQUIRE_CREDENTIAL_ENCRYPTION`.
+    PlaintextNotAllowed(Option<ErpProviderAuthType>),
+    Cipher(String),
+}
+
+impl CodecError {
+    /// User-facing error message.
+    pub fn message(&self) -> String {
+        match self {
+            CodecError::UnknownScheme(scheme) => format!("Unknown enc_scheme '{}'", scheme),
+            CodecError::NoCipher(scheme) => format!(
+                "No cipher is configured for enc_scheme '{}'",
+                scheme.as_str()
+            ),
+            CodecError::PlaintextNotAllowed(Some(auth_type)) => format!(
+                "Credential encryption is required; {} credentials can't use enc_scheme '{}'",
+                auth_type.to_value(),
+                ENC_SCHEME_NONE
+            ),
+            CodecError::PlaintextNotAllowed(None) => format!(
+                "Credential encryption is required; enc_scheme '{}' is not allowed",
+                ENC_SCHEME_NONE
+            ),
+            CodecError::Cipher(e) => format!("Credential cipher error: {}", e),
+        }
+    }
+}
+
+impl std::fmt::Display for CodecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+//END DEBUG AND ERRORS
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncScheme {
+    None,
+    KmsEnvelopeV1,
+}
+
+/// Seals and opens secrets for an encrypting scheme; `key_id` is the row's `enc_key_id`.
+pub trait SecretCipher: Send + Sync {
+    fn seal(&self, key_id: &str, plaintext: &str) -> Result<String, CodecError>;
+    fn open(&self, key_id: &str, sealed: &str) -> Result<String, CodecError>;
+}
+
+#[derive(Clone, Default)]
+pub struct CredentialCodec {
+    /// Cipher for `kms-envelope-v1`.
+    envelope: Option<Arc<dyn SecretCipher>>,
+    /// Reject `none` for auth types outside `PLAINTEXT_AUTH_TYPES`.
+    require_encryption: bool,
+}
+
+// END STRUCTS AND ENUMS
+
+
+/// BEGUN IMPLEMENTATION ///
+impl EncScheme {
+    pub fn parse(value: &str) -> Result<Self, CodecError> {
+        match value {
+            ENC_SCHEME_NONE => Ok(EncScheme::None),
+            ENC_SCHEME_KMS_ENVELOPE_V1 => Ok(EncScheme::KmsEnvelopeV1),
+            other => Err(CodecError::UnknownScheme(other.to_string())),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EncScheme::None => ENC_SCHEME_NONE,
+            EncScheme::KmsEnvelopeV1 => ENC_SCHEME_KMS_ENVELOPE_V1,
+        }
+    }
+}
+
+impl std::fmt::Debug for CredentialCodec {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CredentialCodec")
+            .field("envelope", &self.envelope.is_some())
+            .field("require_encryption", &self.require_encryption)
+            .finish()
+    }
+}
+
+impl CredentialCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_envelope_cipher(mut self, cipher: Arc<dyn SecretCipher>) -> Self {
+        self.envelope = Some(cipher);
+        self
+    }
+
+    ///enforces REQUIRE_CREDENTIAL_ENCRYPTION on `check_policy`
+    pub fn with_require_encryption(mut self, require_encryption: bool) -> Self {
+        self.require_encryption = require_encryption;
+        self
+    }
+
+    pub fn requires_encryption(&self) -> bool {
+        self.require_encryption
+    }
+
+    ///whether credentials of `auth_type` may be stored under `scheme`; only `none` is ever
+    ///refused, and only while encryption is required
+    pub fn check_policy(
+        &self,
+        scheme: EncScheme,
+        auth_type: Option<&ErpProviderAuthType>,
+    ) -> Result<(), CodecError> {
+        let plaintext_allowed = auth_type.is_some_and(|t| PLAINTEXT_AUTH_TYPES.contains(t));
+        match scheme {
+            EncScheme::None if self.require_encryption && !plaintext_allowed => {
+                Err(CodecError::PlaintextNotAllowed(auth_type.cloned()))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn cipher(&self, scheme: EncScheme) -> Result<&dyn SecretCipher, CodecError> {
+        self.envelope
+            .as_deref()
+            .ok_or(CodecError::NoCipher(scheme))
+    }
+
+    ///the value to store for `plaintext` under `scheme`
+    pub fn encode(
+        &self,
+        scheme: EncScheme,
+        key_id: &str,
+        plaintext: &str,
+    ) -> Result<String, CodecError> {
+        match scheme {
+            EncScheme::None => Ok(plaintext.to_string()),
+            EncScheme::KmsEnvelopeV1 => self.cipher(scheme)?.seal(key_id, plaintext),
+        }
+    }
+
+    ///the plaintext of a value stored under `scheme`
+    pub fn decode(&self, scheme: EncScheme, key_id: &str, stored: &str) -> Result<String, CodecError> {
+        match scheme {
+            EncScheme::None => Ok(stored.to_string()),
+            EncScheme::KmsEnvelopeV1 => self.cipher(scheme)?.open(key_id, stored),
+        }
+    }
+
+    ///encodes an optional secret column
+    pub fn encode_field(
+        &self,
+        scheme: EncScheme,
+        key_id: &str,
+        value: Option<String>,
+    ) -> Result<Option<String>, CodecError> {
+        value
+            .map(|v| self.encode(scheme, key_id, &v))
+            .transpose()
+    }
+
+    ///decodes one of `creds`' secret columns under the row's scheme and key
+    pub fn open(
+        &self,
+        creds: &erp_connection_credentials::Model,
+        value: Option<&str>,
+    ) -> Result<Option<String>, CodecError> {
+        let Some(stored) = value else {
+            return Ok(None);
+        };
+        let scheme = EncScheme::parse(&creds.enc_scheme)?;
+        self.decode(scheme, &creds.enc_key_id, stored).map(Some)
+    }
+}
@@ -0,0 +1,195 @@
+//! Credentials about to expire.
+//!
+//! Client certificates (`cert_expires_at`) and refresh tokens (`refresh_token_expires_at`)
+//! can't be renewed by the server: once one lapses, the connection stops syncing until
+//! someone re-authorizes it. `CredentialExpiryService::find_expiring` lists the connections
+//! with such a credential expiring within a window (or already expired), and `spawn` keeps
+//! the `credentials_expiring_total` gauge current so it can be alerted on.
+//!
+//! Access and session tokens are left out: they live minutes to hours and are refreshed
+//! as part of normal syncing. Removed connections are left out too. Only the expiry columns
+//! are read, never the secrets.
+
+use std::time::Duration;
+
+use entity::sea_orm_active_enums::{ErpConnectionStatus, ErpProvider};
+use entity::{connection_identity, erp_connection_credentials};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+
+use crate::clock::{self, SharedClock};
+use crate::config::metrics;
+
+/// Window used by the gauge and when the route gets no `within_days`.
+pub const DEFAULT_EXPIRY_WINDOW_DAYS: i64 = 30;
+pub const MAX_EXPIRY_WINDOW_DAYS: i64 = 365;
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// One credential of a connection that expires within the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiringField {
+    /// `cert_expires_at` or `refresh_token_expires_at`.
+    pub field: &'static str,
+    pub expires_at: DateTimeWithTimeZone,
+    /// Already past `expires_at`.
+    pub expired: bool,
+}
+
+/// A connection with at least one credential expiring within the window.
+#[derive(Debug, Clone)]
+pub struct ExpiringCredentials {
+    pub connection_id: i64,
+    pub connection_uuid: uuid::Uuid,
+    pub tenant_id: i64,
+    pub erp_provider: ErpProvider,
+    pub display_name: Option<String>,
+    pub status: ErpConnectionStatus,
+    pub credentials_uuid: uuid::Uuid,
+    /// Soonest first.
+    pub fields: Vec<ExpiringField>,
+}
+
+pub struct CredentialExpiryService {
+    db: DatabaseConnection,
+    clock: SharedClock,
+}
+
+// END STRUCTS AND ENUMS
+
+
+/// BEGUN IMPLEMENTATION ///
+impl ExpiringCredentials {
+    pub fn earliest_expires_at(&self) -> Option<DateTimeWithTimeZone> {
+        self.fields.first().map(|f| f.expires_at)
+    }
+}
+
+impl CredentialExpiryService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            clock: clock::system(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    ///connections of any tenant with a certificate or refresh token expiring in the next
+    ///`within_days` days (expired ones included), soonest first
+    pub async fn find_expiring(&self, within_days: i64) -> Result<Vec<ExpiringCredentials>, DbErr> {
+        let now = self.clock.now();
+        let cutoff: DateTimeWithTimeZone = (now + chrono::Duration::days(within_days)).into();
+
+        let rows = erp_connection_credentials::Entity::find()
+            .select_only()
+            .column(erp_connection_credentials::Column::Uuid)
+            .column(erp_connection_credentials::Column::CertExpiresAt)
+            .column(erp_connection_credentials::Column::RefreshTokenExpiresAt)
+            .column(connection_identity::Column::Id)
+            .column(connection_identity::Column::Uuid)
+            .column(connection_identity::Column::TenantId)
+            .column(connection_identity::Column::ErpProvider)
+            .column(connection_identity::Column::DisplayName)
+            .column(connection_identity::Column::Status)
+            .inner_join(connection_identity::Entity)
+            .filter(
+                Condition::any()
+                    .add(erp_connection_credentials::Column::CertExpiresAt.lte(cutoff))
+                    .add(erp_connection_credentials::Column::RefreshTokenExpiresAt.lte(cutoff)),
+            )
+            .filter(connection_identity::Column::Status.ne(ErpConnectionStatus::Removed))
+            .order_by_asc(erp_connection_credentials::Column::Id)
+            .into_tuple::<(
+                uuid::Uuid,
+                Option<DateTimeWithTimeZone>,
+                Option<DateTimeWithTimeZone>,
+                i64,
+                uuid::Uuid,
+                i64,
+                ErpProvider,
+                Option<String>,
+                ErpConnectionStatus,
+            )>()
+            .all(&self.db)
+            .await?;
+
+        let mut expiring: Vec<ExpiringCredentials> = rows
+            .into_iter()
+            .map(|(
+                credentials_uuid,
+                cert_expires_at,
+                refresh_token_expires_at,
+                connection_id,
+                connection_uuid,
+                tenant_id,
+                erp_provider,
+                display_name,
+                status,
+            )| {
+                let mut fields: Vec<ExpiringField> = [
+                    ("cert_expires_at", cert_expires_at),
+                    ("refresh_token_expires_at", refresh_token_expires_at),
+                ]
+                .into_iter()
+                .filter_map(|(field, expires_at)| {
+                    let expires_at = expires_at.filter(|at| *at <= cutoff)?;
+                    Some(ExpiringField {
+                        field,
+                        expires_at,
+                        expired: expires_at <= now,
+                    })
+                })
+                .collect();
+                fields.sort_by_key(|f| f.expires_at);
+                ExpiringCredentials {
+                    connection_id,
+                    connection_uuid,
+                    tenant_id,
+                    erp_provider,
+                    display_name,
+                    status,
+                    credentials_uuid,
+                    fields,
+                }
+            })
+            .collect();
+        expiring.sort_by_key(|e| (e.earliest_expires_at(), e.connection_id));
+        Ok(expiring)
+    }
+
+    ///sets `credentials_expiring_total` to the number of connections `find_expiring` lists
+    ///for `within_days`
+    pub async fn update_gauge(&self, within_days: i64) -> Result<usize, DbErr> {
+        let count = self.find_expiring(within_days).await?.len();
+        metrics::set_credentials_expiring(count as i64);
+        Ok(count)
+    }
+}
+
+///spawns the loop refreshing `credentials_expiring_total` every `interval`
+pub fn spawn(db: DatabaseConnection, interval: Duration, within_days: i64) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let service = CredentialExpiryService::new(db);
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match service.update_gauge(within_days).await {
+                Ok(count) if count > 0 => tracing::warn!(
+                    count,
+                    within_days,
+                    "Connections with credentials expiring soon"
+                ),
+                Ok(_) => {}
+                Err(e) => tracing::error!(error = %e, "Credential expiry check failed"),
+            }
+        }
+    })
+}
@@ -0,0 +1,164 @@
+//! In-process cache of Web Connector credential lookups.
+//!
+//! Every QBWC call is authenticated with the connection's username and password, which
+//! costs a credentials + connection query and, for connections with a `secret_storage_ref`,
+//! a secret store read. A successful check is cached by username as (connection id, SHA-256
+//! of the password, provider, type), so later calls only reload the connection by id.
+//!
+//! Entries expire after `CREDENTIAL_CACHE_TTL_SECS`; once `CREDENTIAL_CACHE_SIZE` usernames
+//! are cached the least recently used one is dropped (a size of 0 disables the cache).
+//! `ErpConnectionCredentialsService` invalidates a connection's entries whenever its
+//! credentials are updated. Secrets rotated in an external store are picked up once the
+//! entry expires.
+
+use entity::sea_orm_active_enums::{ErpProvider, ErpProviderType};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// What a successful credential check is remembered as; never the password itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedCredential {
+    pub connection_id: i64,
+    /// SHA-256 of the password that was accepted.
+    pub password_hash: [u8; 32],
+    pub erp_provider: ErpProvider,
+    pub erp_type: ErpProviderType,
+}
+
+struct Entry {
+    credential: CachedCredential,
+    expires_at: Instant,
+    /// Value of the cache's clock when the entry was last read or written.
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    map: HashMap<String, Entry>,
+    clock: u64,
+}
+
+/// Bounded TTL + LRU cache keyed by Web Connector username; clones share the entries.
+#[derive(Clone)]
+pub struct CredentialLookupCache {
+    entries: Arc<Mutex<Entries>>,
+    capacity: usize,
+    ttl: Duration,
+}
+
+// END STRUCTS AND ENUMS
+
+static SHARED: OnceLock<Option<CredentialLookupCache>> = OnceLock::new();
+
+/// BEGUN IMPLEMENTATION ///
+impl CachedCredential {
+    pub fn new(
+        connection_id: i64,
+        password: &str,
+        erp_provider: ErpProvider,
+        erp_type: ErpProviderType,
+    ) -> Self {
+        Self {
+            connection_id,
+            password_hash: hash_password(password),
+            erp_provider,
+            erp_type,
+        }
+    }
+
+    ///whether `password` is the one that was accepted; compares the hashes in constant time
+    pub fn password_matches(&self, password: &str) -> bool {
+        hash_password(password)
+            .iter()
+            .zip(self.password_hash.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+    }
+}
+
+impl CredentialLookupCache {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: Arc::new(Mutex::new(Entries::default())),
+            capacity: capacity.max(1),
+            ttl,
+        }
+    }
+
+    ///the unexpired entry for `username`, marked as most recently used; None on miss
+    pub fn get(&self, username: &str) -> Option<CachedCredential> {
+        let mut entries = self.entries.lock().ok()?;
+        entries.clock += 1;
+        let clock = entries.clock;
+        match entries.map.get_mut(username) {
+            Some(entry) if entry.expires_at > Instant::now() => {
+                entry.last_used = clock;
+                Some(entry.credential.clone())
+            }
+            Some(_) => {
+                entries.map.remove(username);
+                None
+            }
+            None => None,
+        }
+    }
+
+    ///caches `credential` for `username`, dropping the least recently used entry when full
+    pub fn insert(&self, username: &str, credential: CachedCredential) {
+        let Ok(mut entries) = self.entries.lock() else {
+            return;
+        };
+        entries.clock += 1;
+        let clock = entries.clock;
+        if !entries.map.contains_key(username) && entries.map.len() >= self.capacity {
+            let now = Instant::now();
+            entries.map.retain(|_, entry| entry.expires_at > now);
+            if entries.map.len() >= self.capacity {
+                let oldest = entries
+                    .map
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.map.remove(&oldest);
+                }
+            }
+        }
+        entries.map.insert(
+            username.to_string(),
+            Entry {
+                credential,
+                expires_at: Instant::now() + self.ttl,
+                last_used: clock,
+            },
+        );
+    }
+
+    ///drops every entry of the connection, whatever username it was cached under
+    pub fn invalidate_connection(&self, connection_id: i64) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries
+                .map
+                .retain(|_, entry| entry.credential.connection_id != connection_id);
+        }
+    }
+}
+
+fn hash_password(password: &str) -> [u8; 32] {
+    Sha256::digest(password.as_bytes()).into()
+}
+
+///sets up the process-wide cache (CREDENTIAL_CACHE_SIZE / CREDENTIAL_CACHE_TTL_SECS); a zero
+///size or TTL leaves it disabled. Only the first call has an effect
+pub fn init_shared(capacity: usize, ttl: Duration) {
+    let cache = (capacity > 0 && !ttl.is_zero()).then(|| CredentialLookupCache::new(capacity, ttl));
+    let _ = SHARED.set(cache);
+}
+
+///the process-wide cache; None when disabled or not set up
+pub fn shared() -> Option<CredentialLookupCache> {
+    SHARED.get().cloned().flatten()
+}
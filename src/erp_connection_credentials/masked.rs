@@ -0,0 +1,89 @@
+//! Read-only view of a connection's credentials with every secret masked.
+//!
+//! Support uses it to confirm which credential type is configured. Non-secret metadata is
+//! returned as stored; each secret column is reduced to a `has_*` flag. Secret values are
+//! only checked for presence (non-blank), never decrypted or copied into the response.
+
+use entity::erp_connection_credentials;
+use entity::sea_orm_active_enums::{
+    ErpConnectionAuthTokenType, ErpConnectionReauthReason, ErpProviderAuthType,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// BEGUN STRUCTS AND ENUMS ///
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MaskedCredentialsResponse {
+    pub uuid: String,
+    /// The connection's `erp_auth_type`.
+    #[schema(value_type = String)]
+    pub auth_type: ErpProviderAuthType,
+    #[schema(value_type = String)]
+    pub token_type: ErpConnectionAuthTokenType,
+    pub issuer_base_url: Option<String>,
+    pub client_id: Option<String>,
+    pub provider_user_id: Option<String>,
+    #[schema(value_type = Option<String>)]
+    pub reauth_required_reason: Option<ErpConnectionReauthReason>,
+    pub reauth_url: Option<String>,
+    pub enc_scheme: String,
+    pub access_token_expires_at: Option<String>,
+    pub refresh_token_expires_at: Option<String>,
+    pub cert_expires_at: Option<String>,
+    pub session_expires_at: Option<String>,
+    pub has_access_token: bool,
+    pub has_refresh_token: bool,
+    pub has_id_token: bool,
+    pub has_provider_password: bool,
+    pub has_client_cert: bool,
+    pub has_private_key: bool,
+    pub has_session_token: bool,
+    pub has_api_access_token: bool,
+    pub has_api_access_token_key: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// END STRUCTS AND ENUMS ///
+
+
+/// BEGUN IMPLEMENTATION ///
+impl MaskedCredentialsResponse {
+    ///masks `model`'s secrets; `auth_type` comes from the owning connection
+    pub fn from_model(
+        model: &erp_connection_credentials::Model,
+        auth_type: ErpProviderAuthType,
+    ) -> Self {
+        Self {
+            uuid: model.uuid.to_string(),
+            auth_type,
+            token_type: model.token_type.clone(),
+            issuer_base_url: model.issuer_base_url.clone(),
+            client_id: model.client_id.clone(),
+            provider_user_id: model.provider_user_id.clone(),
+            reauth_required_reason: model.reauth_required_reason.clone(),
+            reauth_url: model.reauth_url.clone(),
+            enc_scheme: model.enc_scheme.clone(),
+            access_token_expires_at: model.access_token_expires_at.map(|t| t.to_rfc3339()),
+            refresh_token_expires_at: model.refresh_token_expires_at.map(|t| t.to_rfc3339()),
+            cert_expires_at: model.cert_expires_at.map(|t| t.to_rfc3339()),
+            session_expires_at: model.session_expires_at.map(|t| t.to_rfc3339()),
+            has_access_token: is_present(&model.access_token),
+            has_refresh_token: is_present(&model.refresh_token),
+            has_id_token: is_present(&model.id_token_enc),
+            has_provider_password: is_present(&model.provider_password),
+            has_client_cert: model.client_cert.as_ref().is_some_and(|c| !c.is_empty()),
+            has_private_key: is_present(&model.private_key),
+            has_session_token: is_present(&model.session_token),
+            has_api_access_token: is_present(&model.api_access_token),
+            has_api_access_token_key: is_present(&model.api_access_token_key),
+            created_at: model.created_at.to_rfc3339(),
+            updated_at: model.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+fn is_present(value: &Option<String>) -> bool {
+    value.as_deref().is_some_and(|v| !v.trim().is_empty())
+}
@@ -1,3 +1,32 @@
+pub mod codec;
+pub mod expiry;
+pub mod lookup_cache;
+pub mod masked;
+pub mod routes;
+pub mod secret_store;
 pub mod services;
 
+use std::sync::Arc;
+
+use crate::config;
+
+pub use codec::CredentialCodec;
+pub use secret_store::SecretResolver;
 pub use services::ErpConnectionCredentialsService;
+
+///the resolver for the configured stores: `env:` always, `vault:` when VAULT_ADDR and
+///VAULT_TOKEN are set
+pub fn secret_resolver() -> SecretResolver {
+    let credentials = &config::env::get().credentials;
+    let resolver = SecretResolver::new(
+        CredentialCodec::new().with_require_encryption(credentials.require_encryption),
+    )
+    .with_store("env", Arc::new(secret_store::EnvSecretStore));
+    match (&credentials.vault_addr, &credentials.vault_token) {
+        (Some(addr), Some(token)) => resolver.with_store(
+            "vault",
+            Arc::new(secret_store::VaultSecretStore::new(addr.clone(), token.clone())),
+        ),
+        _ => resolver,
+    }
+}
@@ -0,0 +1,152 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use entity::sea_orm_active_enums::{ErpConnectionStatus, ErpProvider};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::AppState;
+use crate::tenant::routes::ErrorResponse;
+use crate::validation::FieldError;
+use super::expiry::{
+    CredentialExpiryService, ExpiringCredentials, DEFAULT_EXPIRY_WINDOW_DAYS, MAX_EXPIRY_WINDOW_DAYS,
+};
+
+
+/// RESPONSE SCHEMAS ///
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ExpiringFieldResponse {
+    /// `cert_expires_at` or `refresh_token_expires_at`.
+    pub field: String,
+    pub expires_at: String,
+    /// Already past `expires_at`.
+    pub expired: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ExpiringConnectionResponse {
+    pub connection_uuid: String,
+    pub tenant_id: i64,
+    #[schema(value_type = String)]
+    pub erp_provider: ErpProvider,
+    pub display_name: Option<String>,
+    #[schema(value_type = String)]
+    pub status: ErpConnectionStatus,
+    pub credentials_uuid: String,
+    /// Soonest first.
+    pub expiring: Vec<ExpiringFieldResponse>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ExpiringCredentialsResponse {
+    pub within_days: i64,
+    /// Soonest expiry first.
+    pub items: Vec<ExpiringConnectionResponse>,
+}
+
+
+/// REQUEST SCHEMAS ///
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ExpiringCredentialsQuery {
+    /// Days ahead to look (1-365).
+    #[param(default = 30)]
+    pub within_days: Option<i64>,
+}
+
+
+/// VALIDATION ///
+impl ExpiringCredentialsQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if let Some(days) = self.within_days
+            && !(1..=MAX_EXPIRY_WINDOW_DAYS).contains(&days)
+        {
+            errors.push(FieldError::new(
+                "within_days",
+                format!("must be between 1 and {}", MAX_EXPIRY_WINDOW_DAYS),
+            ));
+        }
+        errors
+    }
+}
+
+
+/// HELPER FUNCTIONS ///
+fn expiring_to_response(expiring: ExpiringCredentials) -> ExpiringConnectionResponse {
+    ExpiringConnectionResponse {
+        connection_uuid: expiring.connection_uuid.to_string(),
+        tenant_id: expiring.tenant_id,
+        erp_provider: expiring.erp_provider,
+        display_name: expiring.display_name,
+        status: expiring.status,
+        credentials_uuid: expiring.credentials_uuid.to_string(),
+        expiring: expiring
+            .fields
+            .into_iter()
+            .map(|f| ExpiringFieldResponse {
+                field: f.field.to_string(),
+                expires_at: f.expires_at.to_rfc3339(),
+                expired: f.expired,
+            })
+            .collect(),
+    }
+}
+
+
+/// ROUTE HANDLERS ///
+
+#[utoipa::path(
+    get,
+    path = "/admin/credentials/expiring",
+    tag = "Admin",
+    params(ExpiringCredentialsQuery),
+    responses(
+        (status = 200, description = "Connections with a certificate or refresh token expiring within the window, expired ones included", body = ExpiringCredentialsResponse),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn list_expiring_credentials(
+    State(state): State<AppState>,
+    Query(query): Query<ExpiringCredentialsQuery>,
+) -> Result<Json<ExpiringCredentialsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let errors = query.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let within_days = query.within_days.unwrap_or(DEFAULT_EXPIRY_WINDOW_DAYS);
+    match CredentialExpiryService::new(state.db).find_expiring(within_days).await {
+        Ok(expiring) => Ok(Json(ExpiringCredentialsResponse {
+            within_days,
+            items: expiring.into_iter().map(expiring_to_response).collect(),
+        })),
+        Err(e) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Database error: {}", e),
+                details: None,
+            }),
+        )),
+    }
+}
+
+
+/// ROUTER ///
+///mounted at /admin/credentials behind the admin token middleware (read-only)
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/expiring", get(list_expiring_credentials))
+        .layer(axum::middleware::from_fn(
+            crate::middleware::admin_token_auth_middleware,
+        ))
+}
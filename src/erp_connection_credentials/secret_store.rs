@@ -0,0 +1,270 @@
+//! Reading credential secrets from an external secret store.
+//!
+//! A connection's `secret_storage_ref` names where its secrets live, as `<store>:<path>`:
+//!   - unset: the secrets are the credentials row's own columns, read through
+//!     `CredentialCodec` under the row's `enc_scheme`
+//!   - `env:<NAME>`: environment variables `ERP_SECRET_<NAME>_<FIELD>`, e.g.
+//!     `ERP_SECRET_ACME_PROVIDER_PASSWORD` (`EnvSecretStore`)
+//!   - `vault:<path>`: a Vault KV v2 secret read from `{VAULT_ADDR}/v1/<path>`, whose keys
+//!     are the field names, e.g. `provider_password` (`VaultSecretStore`)
+//!
+//! `secret_version`, when set, pins the version read from the store (Vault only; the
+//! environment has one version).
+//!
+//! `SecretResolver` picks the source per connection at use time, so moving a connection's
+//! secrets into a store only takes setting its `secret_storage_ref`.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use entity::{connection_identity, erp_connection_credentials};
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+
+use crate::erp_connection_credentials::codec::{CodecError, CredentialCodec};
+
+/// Prefix of the environment variables `EnvSecretStore` reads; keeps a ref from naming
+/// arbitrary server variables.
+pub const ENV_SECRET_PREFIX: &str = "ERP_SECRET_";
+
+
+//DEBUG AND ERRORS ///
+#[derive(Debug)]
+pub enum SecretStoreError {
+    /// `secret_storage_ref` names a store this server has no client for (or isn't `<store>:<path>`).
+    UnknownStore(String),
+    /// The store has no secret at the ref (or version).
+    NotFound(String),
+    Backend(String),
+    Codec(CodecError),
+}
+
+impl SecretStoreError {
+    /// User-facing error message.
+    pub fn message(&self) -> String {
+        match self {
+            SecretStoreError::UnknownStore(r) => format!("No secret store for secret_storage_ref '{}'", r),
+            SecretStoreError::NotFound(r) => format!("No secret found at '{}'", r),
+            SecretStoreError::Backend(e) => format!("Secret store error: {}", e),
+            SecretStoreError::Codec(e) => e.message(),
+        }
+    }
+}
+
+impl std::fmt::Display for SecretStoreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl From<CodecError> for SecretStoreError {
+    fn from(e: CodecError) -> Self {
+        SecretStoreError::Codec(e)
+    }
+}
+
+//END DEBUG AND ERRORS
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// A secret of a credentials row; stores key them by `name()`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecretField {
+    ProviderPassword,
+    AccessToken,
+    RefreshToken,
+    SessionToken,
+    PrivateKey,
+}
+
+/// A secret's fields by name, as loaded from a store.
+pub type StoredSecrets = HashMap<String, String>;
+
+/// An external secret store. `path` is the part of `secret_storage_ref` after `<store>:`.
+pub trait SecretStore: Send + Sync {
+    fn load<'a>(
+        &'a self,
+        path: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<StoredSecrets, SecretStoreError>>;
+}
+
+/// Secrets from `ERP_SECRET_<PATH>_<FIELD>` environment variables.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnvSecretStore;
+
+/// Secrets from a Vault KV v2 engine.
+#[derive(Debug, Clone)]
+pub struct VaultSecretStore {
+    client: reqwest::Client,
+    /// e.g. `https://vault.internal:8200`.
+    addr: String,
+    token: String,
+}
+
+/// Reads a connection's secrets from its `secret_storage_ref` store, or from the credentials
+/// row when it has none.
+#[derive(Clone, Default)]
+pub struct SecretResolver {
+    codec: CredentialCodec,
+    /// Stores by the `<store>` prefix of `secret_storage_ref`.
+    stores: HashMap<String, Arc<dyn SecretStore>>,
+}
+
+// END STRUCTS AND ENUMS
+
+
+/// BEGUN IMPLEMENTATION ///
+impl SecretField {
+    pub fn name(&self) -> &'static str {
+        match self {
+            SecretField::ProviderPassword => "provider_password",
+            SecretField::AccessToken => "access_token",
+            SecretField::RefreshToken => "refresh_token",
+            SecretField::SessionToken => "session_token",
+            SecretField::PrivateKey => "private_key",
+        }
+    }
+
+    ///the stored value of the field on the credentials row
+    pub fn column<'a>(&self, creds: &'a erp_connection_credentials::Model) -> Option<&'a str> {
+        match self {
+            SecretField::ProviderPassword => creds.provider_password.as_deref(),
+            SecretField::AccessToken => creds.access_token.as_deref(),
+            SecretField::RefreshToken => creds.refresh_token.as_deref(),
+            SecretField::SessionToken => creds.session_token.as_deref(),
+            SecretField::PrivateKey => creds.private_key.as_deref(),
+        }
+    }
+}
+
+impl SecretStore for EnvSecretStore {
+    fn load<'a>(
+        &'a self,
+        path: &'a str,
+        _version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<StoredSecrets, SecretStoreError>> {
+        Box::pin(async move {
+            let prefix = format!("{}{}_", ENV_SECRET_PREFIX, path.to_uppercase());
+            let secrets: StoredSecrets = std::env::vars()
+                .filter_map(|(key, value)| {
+                    key.strip_prefix(&prefix)
+                        .map(|field| (field.to_lowercase(), value))
+                })
+                .collect();
+            if secrets.is_empty() {
+                return Err(SecretStoreError::NotFound(format!("env:{}", path)));
+            }
+            Ok(secrets)
+        })
+    }
+}
+
+impl VaultSecretStore {
+    pub fn new(addr: impl Into<String>, token: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            addr: addr.into().trim_end_matches('/').to_string(),
+            token: token.into(),
+        }
+    }
+}
+
+impl SecretStore for VaultSecretStore {
+    fn load<'a>(
+        &'a self,
+        path: &'a str,
+        version: Option<&'a str>,
+    ) -> BoxFuture<'a, Result<StoredSecrets, SecretStoreError>> {
+        Box::pin(async move {
+            let mut request = self
+                .client
+                .get(format!("{}/v1/{}", self.addr, path.trim_start_matches('/')))
+                .header("X-Vault-Token", &self.token);
+            if let Some(version) = version {
+                request = request.query(&[("version", version)]);
+            }
+            let response = request
+                .send()
+                .await
+                .map_err(|e| SecretStoreError::Backend(e.to_string()))?;
+            let status = response.status();
+            if status == reqwest::StatusCode::NOT_FOUND {
+                return Err(SecretStoreError::NotFound(format!("vault:{}", path)));
+            }
+            if !status.is_success() {
+                return Err(SecretStoreError::Backend(format!("Vault answered {}", status)));
+            }
+            let body: Value = response
+                .text()
+                .await
+                .ok()
+                .and_then(|text| serde_json::from_str(&text).ok())
+                .ok_or_else(|| SecretStoreError::Backend("unreadable Vault response".to_string()))?;
+
+            //KV v2 nests the secret's keys under data.data
+            let data = body["data"]["data"]
+                .as_object()
+                .ok_or_else(|| SecretStoreError::NotFound(format!("vault:{}", path)))?;
+            Ok(data
+                .iter()
+                .filter_map(|(key, value)| value.as_str().map(|v| (key.clone(), v.to_string())))
+                .collect())
+        })
+    }
+}
+
+impl SecretResolver {
+    pub fn new(codec: CredentialCodec) -> Self {
+        Self {
+            codec,
+            stores: HashMap::new(),
+        }
+    }
+
+    ///the codec reading secrets stored on the credentials row
+    pub fn with_codec(mut self, codec: CredentialCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    ///resolves refs `<name>:<path>` through `store`
+    pub fn with_store(mut self, name: impl Into<String>, store: Arc<dyn SecretStore>) -> Self {
+        self.stores.insert(name.into(), store);
+        self
+    }
+
+    ///the plaintext of `field` for `conn`: from its `secret_storage_ref` store when set,
+    ///otherwise the credentials row's column decoded under its `enc_scheme`
+    pub async fn resolve(
+        &self,
+        conn: &connection_identity::Model,
+        creds: &erp_connection_credentials::Model,
+        field: SecretField,
+    ) -> Result<Option<String>, SecretStoreError> {
+        let Some(reference) = conn.secret_storage_ref.as_deref().filter(|r| !r.is_empty()) else {
+            return Ok(self.codec.open(creds, field.column(creds))?);
+        };
+
+        let (name, path) = reference
+            .split_once(':')
+            .ok_or_else(|| SecretStoreError::UnknownStore(reference.to_string()))?;
+        let store = self
+            .stores
+            .get(name)
+            .ok_or_else(|| SecretStoreError::UnknownStore(reference.to_string()))?;
+        let mut secrets = store.load(path, conn.secret_version.as_deref()).await?;
+        Ok(secrets.remove(field.name()))
+    }
+}
+
+impl std::fmt::Debug for SecretResolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut stores: Vec<_> = self.stores.keys().collect();
+        stores.sort();
+        f.debug_struct("SecretResolver")
+            .field("codec", &self.codec)
+            .field("stores", &stores)
+            .finish()
+    }
+}
@@ -1,33 +1,107 @@
-use entity::erp_connection_credentials;
-use entity::sea_orm_active_enums::{ErpConnectionAuthTokenType, ErpConnectionReauthReason};
+use entity::{connection_identity, erp_connection_credentials};
+use entity::sea_orm_active_enums::{
+    ErpConnectionAuthTokenType, ErpConnectionReauthReason, ErpProviderAuthType,
+};
+use sea_orm::sea_query::SimpleExpr;
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
-    QueryFilter, Set,
+    ActiveEnum, ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    QueryFilter, Set, TransactionTrait,
 };
 use uuid::Uuid;
 
+use crate::audit::services::{AuditAction, AuditContext, AuditEntry, AuditService};
+use crate::connection_identity::services::{ConnectionIdentityError, ConnectionIdentityService};
+use crate::db::unique::UniqueViolation;
+use crate::erp_connection_credentials::codec::{
+    CodecError, CredentialCodec, EncScheme, ENC_SCHEME_KMS_ENVELOPE_V1,
+};
+use crate::erp_connection_credentials::lookup_cache::{self, CredentialLookupCache};
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum ErpConnectionCredentialsError {
     NotFound,
+    MissingCredential(MissingCredential),
+    /// Unknown `enc_scheme`, a scheme without a cipher, or plaintext refused by policy.
+    Codec(CodecError),
+    /// A unique constraint refused the write, e.g. a second credentials row for a connection.
+    Conflict(String),
     Db(DbErr),
 }
 
+/// Secret columns covered by the `credentials_not_all_null` CHECK; at least one must be set.
+pub const CREDENTIAL_FIELDS: [&str; 6] = [
+    "access_token",
+    "refresh_token",
+    "provider_password",
+    "private_key",
+    "session_token",
+    "api_access_token",
+];
+
+/// Credentials rejected before insert: `fields` are the empty ones that were required.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingCredential {
+    pub fields: Vec<&'static str>,
+    /// Set when the auth type is what required `fields`.
+    pub auth_type: Option<ErpProviderAuthType>,
+}
+
+#[allow(dead_code)]
+impl MissingCredential {
+    /// User-facing error message.
+    pub fn message(&self) -> String {
+        match &self.auth_type {
+            Some(auth_type) => format!(
+                "{} connections require {}",
+                auth_type.to_value(),
+                self.fields.join(", ")
+            ),
+            None => format!(
+                "At least one credential is required; all of {} are empty",
+                self.fields.join(", ")
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for MissingCredential {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message())
+    }
+}
+
+impl From<CodecError> for ErpConnectionCredentialsError {
+    fn from(err: CodecError) -> Self {
+        ErpConnectionCredentialsError::Codec(err)
+    }
+}
+
 #[allow(dead_code)]
 impl From<DbErr> for ErpConnectionCredentialsError {
     fn from(err: DbErr) -> Self {
-        ErpConnectionCredentialsError::Db(err)
+        match UniqueViolation::from_db_err(&err) {
+            Some(violation) => ErpConnectionCredentialsError::Conflict(violation.message()),
+            None => ErpConnectionCredentialsError::Db(err),
+        }
     }
 }
 
 #[allow(dead_code)]
 pub struct ErpConnectionCredentialsService {
     db: DatabaseConnection,
+    audit: Option<AuditContext>,
+    codec: CredentialCodec,
+    lookup_cache: Option<CredentialLookupCache>,
 }
 
 #[allow(dead_code)]
 pub struct CreateErpConnectionCredentials {
     pub connection_id: i64,
+    /// The connection's `erp_auth_type`; selects the credential that must be present
+    /// (oauth2: `refresh_token`, certificate: `private_key`).
+    pub erp_auth_type: Option<ErpProviderAuthType>,
     pub client_id: Option<String>,
     pub issuer_base_url: Option<String>,
     pub token_type: Option<ErpConnectionAuthTokenType>,
@@ -54,6 +128,45 @@ pub struct CreateErpConnectionCredentials {
     pub api_access_token_key: Option<String>,
 }
 
+#[allow(dead_code)]
+impl CreateErpConnectionCredentials {
+    ///checks what the `credentials_not_all_null` CHECK would reject, plus the credential the
+    ///auth type needs; blank strings count as empty
+    pub fn validate(&self) -> Result<(), MissingCredential> {
+        let present = [
+            is_present(&self.access_token),
+            is_present(&self.refresh_token),
+            is_present(&self.provider_password),
+            is_present(&self.private_key),
+            is_present(&self.session_token),
+            is_present(&self.api_access_token),
+        ];
+        if !present.contains(&true) {
+            return Err(MissingCredential {
+                fields: CREDENTIAL_FIELDS.to_vec(),
+                auth_type: None,
+            });
+        }
+
+        let required = match &self.erp_auth_type {
+            Some(ErpProviderAuthType::Oauth2) if !is_present(&self.refresh_token) => {
+                Some("refresh_token")
+            }
+            Some(ErpProviderAuthType::Certificate) if !is_present(&self.private_key) => {
+                Some("private_key")
+            }
+            _ => None,
+        };
+        match required {
+            Some(field) => Err(MissingCredential {
+                fields: vec![field],
+                auth_type: self.erp_auth_type.clone(),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub struct UpdateErpConnectionCredentials {
     pub client_id: Option<String>,
@@ -85,7 +198,44 @@ pub struct UpdateErpConnectionCredentials {
 #[allow(dead_code)]
 impl ErpConnectionCredentialsService {
     pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+        Self {
+            db,
+            audit: None,
+            codec: CredentialCodec::new(),
+            lookup_cache: lookup_cache::shared(),
+        }
+    }
+
+    ///secrets are written through `codec`, which also decides whether plaintext (`none`)
+    ///credentials are accepted
+    pub fn with_codec(mut self, codec: CredentialCodec) -> Self {
+        self.codec = codec;
+        self
+    }
+
+    ///records every create/update in `audit_log` (secrets redacted), in the same
+    ///transaction as the change (one is started when the caller passes none)
+    pub fn with_audit(mut self, audit: AuditContext) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    ///Web Connector credential cache whose entries for a connection are dropped when its
+    ///credentials are updated; the process-wide one by default
+    pub fn with_lookup_cache(mut self, lookup_cache: Option<CredentialLookupCache>) -> Self {
+        self.lookup_cache = lookup_cache;
+        self
+    }
+
+    async fn record_audit(
+        &self,
+        entry: AuditEntry,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<(), DbErr> {
+        if let (Some(audit), Some(txn)) = (&self.audit, txn) {
+            AuditService::new(self.db.clone()).record(audit, entry, txn).await?;
+        }
+        Ok(())
     }
 
     pub async fn get_by_id(
@@ -141,15 +291,31 @@ impl ErpConnectionCredentialsService {
         }
     }
 
+    ///validates credential presence (`CreateErpConnectionCredentials::validate`) and the
+    ///encryption policy before inserting; secrets are stored encoded for `enc_scheme`
     pub async fn create(
         &self,
         data: CreateErpConnectionCredentials,
         txn: Option<&DatabaseTransaction>,
-    ) -> Result<erp_connection_credentials::Model, DbErr> {
+    ) -> Result<erp_connection_credentials::Model, ErpConnectionCredentialsError> {
+        data.validate()
+            .map_err(ErpConnectionCredentialsError::MissingCredential)?;
+        let scheme = EncScheme::parse(
+            data.enc_scheme.as_deref().unwrap_or(ENC_SCHEME_KMS_ENVELOPE_V1),
+        )?;
+        self.codec.check_policy(scheme, data.erp_auth_type.as_ref())?;
+
+        if let (Some(_), None) = (&self.audit, txn) {
+            let txn = self.db.begin().await?;
+            let created = Box::pin(self.create(data, Some(&txn))).await?;
+            txn.commit().await?;
+            return Ok(created);
+        }
+
+        let key_id = data.enc_key_id.as_str();
         let active = erp_connection_credentials::ActiveModel {
             connection_id: Set(data.connection_id),
-            enc_scheme: Set(data.enc_scheme.unwrap_or_else(|| "kms-envelope-v1".to_string())),
-            enc_key_id: Set(data.enc_key_id),
+            enc_scheme: Set(scheme.as_str().to_string()),
             enc_version: Set(data.enc_version.unwrap_or(1)),
             token_type: Set(data.token_type.unwrap_or(ErpConnectionAuthTokenType::Bearer)),
             client_id: Set(data.client_id),
@@ -158,27 +324,32 @@ impl ErpConnectionCredentialsService {
             reauth_url: Set(data.reauth_url),
             enc_iv: Set(data.enc_iv),
             enc_tag: Set(data.enc_tag),
-            access_token: Set(data.access_token),
-            refresh_token: Set(data.refresh_token),
+            access_token: Set(self.codec.encode_field(scheme, key_id, data.access_token)?),
+            refresh_token: Set(self.codec.encode_field(scheme, key_id, data.refresh_token)?),
             access_token_expires_at: Set(data.access_token_expires_at.map(Into::into)),
             refresh_token_expires_at: Set(data.refresh_token_expires_at.map(Into::into)),
-            id_token_enc: Set(data.id_token_enc),
+            id_token_enc: Set(self.codec.encode_field(scheme, key_id, data.id_token_enc)?),
             provider_user_id: Set(data.provider_user_id),
-            provider_password: Set(data.provider_password),
+            provider_password: Set(self.codec.encode_field(scheme, key_id, data.provider_password)?),
             client_cert: Set(data.client_cert),
-            private_key: Set(data.private_key),
+            private_key: Set(self.codec.encode_field(scheme, key_id, data.private_key)?),
             cert_expires_at: Set(data.cert_expires_at.map(Into::into)),
-            session_token: Set(data.session_token),
+            session_token: Set(self.codec.encode_field(scheme, key_id, data.session_token)?),
             session_expires_at: Set(data.session_expires_at.map(Into::into)),
-            api_access_token: Set(data.api_access_token),
-            api_access_token_key: Set(data.api_access_token_key),
+            api_access_token: Set(self.codec.encode_field(scheme, key_id, data.api_access_token)?),
+            api_access_token_key: Set(self.codec.encode_field(scheme, key_id, data.api_access_token_key)?),
+            enc_key_id: Set(data.enc_key_id),
             ..Default::default()
         };
 
-        match txn {
-            Some(txn) => active.insert(txn).await,
-            None => active.insert(&self.db).await,
-        }
+        let created = match txn {
+            Some(txn) => active.insert(txn).await?,
+            None => active.insert(&self.db).await?,
+        };
+        self.record_audit(AuditEntry::created(created.uuid, &created), txn)
+            .await?;
+
+        Ok(created)
     }
 
     pub async fn update_by_uuid(
@@ -187,51 +358,88 @@ impl ErpConnectionCredentialsService {
         patch: UpdateErpConnectionCredentials,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<erp_connection_credentials::Model>, ErpConnectionCredentialsError> {
-        let model = match txn {
-            Some(txn) => {
-                erp_connection_credentials::Entity::find()
-                    .filter(erp_connection_credentials::Column::Uuid.eq(uuid))
-                    .one(txn)
-                    .await?
-            }
-            None => {
-                erp_connection_credentials::Entity::find()
-                    .filter(erp_connection_credentials::Column::Uuid.eq(uuid))
-                    .one(&self.db)
-                    .await?
-            }
-        };
+        self.update_where(erp_connection_credentials::Column::Uuid.eq(uuid), patch, false, txn)
+            .await
+    }
 
-        let Some(model) = model else {
-            return Err(ErpConnectionCredentialsError::NotFound);
-        };
+    pub async fn update_by_connection_id(
+        &self,
+        connection_id: i64,
+        patch: UpdateErpConnectionCredentials,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<erp_connection_credentials::Model>, ErpConnectionCredentialsError> {
+        self.update_where(
+            erp_connection_credentials::Column::ConnectionId.eq(connection_id),
+            patch,
+            false,
+            txn,
+        )
+        .await
+    }
 
-        let mut active: erp_connection_credentials::ActiveModel = model.into();
-        apply_credentials_patch(&mut active, patch);
-        active.updated_at = Set(chrono::Utc::now().into());
+    ///updates the connection's credentials after a successful refresh and, in the same
+    ///transaction, clears `reauth_required_reason` and marks the connection connected
+    ///(`ConnectionIdentityService::record_success`), so a prior NeedsReauth doesn't outlive it
+    pub async fn update_and_mark_connected(
+        &self,
+        connection_id: i64,
+        patch: UpdateErpConnectionCredentials,
+    ) -> Result<Option<erp_connection_credentials::Model>, ErpConnectionCredentialsError> {
+        let txn = self.db.begin().await?;
+        let updated = self
+            .update_where(
+                erp_connection_credentials::Column::ConnectionId.eq(connection_id),
+                patch,
+                true,
+                Some(&txn),
+            )
+            .await?;
 
-        match txn {
-            Some(txn) => Ok(Some(active.update(txn).await?)),
-            None => Ok(Some(active.update(&self.db).await?)),
-        }
+        let connection = connection_identity::Entity::find_by_id(connection_id)
+            .one(&txn)
+            .await?
+            .ok_or(ErpConnectionCredentialsError::NotFound)?;
+        ConnectionIdentityService::new(self.db.clone())
+            .record_success(connection.uuid, Some(&txn))
+            .await
+            .map_err(|e| match e {
+                ConnectionIdentityError::NotFound => ErpConnectionCredentialsError::NotFound,
+                ConnectionIdentityError::Db(e) => ErpConnectionCredentialsError::Db(e),
+                ConnectionIdentityError::Conflict(message) => {
+                    ErpConnectionCredentialsError::Conflict(message)
+                }
+            })?;
+
+        txn.commit().await?;
+        Ok(updated)
     }
 
-    pub async fn update_by_connection_id(
+    ///`clear_reauth` also resets `reauth_required_reason`, which a patch can only set
+    async fn update_where(
         &self,
-        connection_id: i64,
+        condition: SimpleExpr,
         patch: UpdateErpConnectionCredentials,
+        clear_reauth: bool,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<erp_connection_credentials::Model>, ErpConnectionCredentialsError> {
+        if let (Some(_), None) = (&self.audit, txn) {
+            let txn = self.db.begin().await?;
+            let updated =
+                Box::pin(self.update_where(condition, patch, clear_reauth, Some(&txn))).await?;
+            txn.commit().await?;
+            return Ok(updated);
+        }
+
         let model = match txn {
             Some(txn) => {
                 erp_connection_credentials::Entity::find()
-                    .filter(erp_connection_credentials::Column::ConnectionId.eq(connection_id))
+                    .filter(condition)
                     .one(txn)
                     .await?
             }
             None => {
                 erp_connection_credentials::Entity::find()
-                    .filter(erp_connection_credentials::Column::ConnectionId.eq(connection_id))
+                    .filter(condition)
                     .one(&self.db)
                     .await?
             }
@@ -241,15 +449,63 @@ impl ErpConnectionCredentialsService {
             return Err(ErpConnectionCredentialsError::NotFound);
         };
 
-        let mut active: erp_connection_credentials::ActiveModel = model.into();
+        //a patch's secrets are encoded for the scheme the row ends up with; switching an
+        //encrypted row to `none` is refused while encryption is required
+        let scheme = EncScheme::parse(patch.enc_scheme.as_deref().unwrap_or(&model.enc_scheme))?;
+        if scheme.as_str() != model.enc_scheme {
+            self.codec.check_policy(scheme, None)?;
+        }
+        let key_id = patch.enc_key_id.clone().unwrap_or_else(|| model.enc_key_id.clone());
+        let patch = self.encode_patch(scheme, &key_id, patch)?;
+
+        let mut active: erp_connection_credentials::ActiveModel = model.clone().into();
         apply_credentials_patch(&mut active, patch);
+        if clear_reauth {
+            active.reauth_required_reason = Set(None);
+        }
         active.updated_at = Set(chrono::Utc::now().into());
 
-        match txn {
-            Some(txn) => Ok(Some(active.update(txn).await?)),
-            None => Ok(Some(active.update(&self.db).await?)),
+        let updated = match txn {
+            Some(txn) => active.update(txn).await?,
+            None => active.update(&self.db).await?,
+        };
+        self.record_audit(
+            AuditEntry::changed(AuditAction::Update, updated.uuid, &model, &updated),
+            txn,
+        )
+        .await?;
+        //a rotated password or username must be checked against the database again
+        if let Some(cache) = &self.lookup_cache {
+            cache.invalidate_connection(updated.connection_id);
         }
+
+        Ok(Some(updated))
     }
+
+    fn encode_patch(
+        &self,
+        scheme: EncScheme,
+        key_id: &str,
+        patch: UpdateErpConnectionCredentials,
+    ) -> Result<UpdateErpConnectionCredentials, CodecError> {
+        Ok(UpdateErpConnectionCredentials {
+            access_token: self.codec.encode_field(scheme, key_id, patch.access_token)?,
+            refresh_token: self.codec.encode_field(scheme, key_id, patch.refresh_token)?,
+            id_token_enc: self.codec.encode_field(scheme, key_id, patch.id_token_enc)?,
+            provider_password: self.codec.encode_field(scheme, key_id, patch.provider_password)?,
+            private_key: self.codec.encode_field(scheme, key_id, patch.private_key)?,
+            session_token: self.codec.encode_field(scheme, key_id, patch.session_token)?,
+            api_access_token: self.codec.encode_field(scheme, key_id, patch.api_access_token)?,
+            api_access_token_key: self
+                .codec
+                .encode_field(scheme, key_id, patch.api_access_token_key)?,
+            ..patch
+        })
+    }
+}
+
+fn is_present(value: &Option<String>) -> bool {
+    value.as_deref().is_some_and(|v| !v.trim().is_empty())
 }
 
 fn apply_credentials_patch(
@@ -1,11 +1,17 @@
 use sea_orm::{
-    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
-    QueryFilter, Set,
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, Set,
 };
 use sea_orm::entity::prelude::Json;
+use sea_orm::sea_query::{Expr, OnConflict};
 use entity::erp_connection_sync_state;
 use uuid::Uuid;
 
+use crate::clock::{self, SharedClock};
+
+/// Poll interval used when a sync state has no `poll_interval_seconds`.
+pub const DEFAULT_POLL_INTERVAL_SECS: i64 = 900;
+
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum ErpConnectionSyncStateError {
@@ -23,6 +29,7 @@ impl From<DbErr> for ErpConnectionSyncStateError {
 #[allow(dead_code)]
 pub struct ErpConnectionSyncStateService {
     db: DatabaseConnection,
+    clock: SharedClock,
 }
 
 #[allow(dead_code)]
@@ -36,6 +43,7 @@ pub struct CreateErpConnectionSyncState {
     pub rate_limit_reset_at: Option<chrono::DateTime<chrono::Utc>>,
     pub rate_limit_backoff_until: Option<chrono::DateTime<chrono::Utc>>,
     pub rate_limit_window_seconds: Option<i32>,
+    pub poll_interval_seconds: Option<i32>,
 }
 
 #[allow(dead_code)]
@@ -48,12 +56,72 @@ pub struct UpdateErpConnectionSyncState {
     pub rate_limit_reset_at: Option<chrono::DateTime<chrono::Utc>>,
     pub rate_limit_backoff_until: Option<chrono::DateTime<chrono::Utc>>,
     pub rate_limit_window_seconds: Option<i32>,
+    pub poll_interval_seconds: Option<i32>,
 }
 
 #[allow(dead_code)]
 impl ErpConnectionSyncStateService {
     pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+        Self {
+            db,
+            clock: clock::system(),
+        }
+    }
+
+    ///uses `clock` for lock leases, backoff checks and updated_at
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    ///whether another poll must wait: `sync_lock_until` is still in the future
+    pub fn is_locked(&self, sync_state: &erp_connection_sync_state::Model) -> bool {
+        sync_state
+            .sync_lock_until
+            .is_some_and(|until| until > self.clock.now())
+    }
+
+    ///whether the connection is backing off: `rate_limit_backoff_until` is still in the future
+    pub fn is_backing_off(&self, sync_state: &erp_connection_sync_state::Model) -> bool {
+        sync_state
+            .rate_limit_backoff_until
+            .is_some_and(|until| until > self.clock.now())
+    }
+
+    ///takes the sync lock for `owner` for `lease`, unless someone else holds an unexpired
+    ///lease; returns whether the lock was taken
+    pub async fn try_acquire_lock(
+        &self,
+        sync_state_id: i64,
+        owner: &str,
+        lease: chrono::Duration,
+        txn: &DatabaseTransaction,
+    ) -> Result<bool, DbErr> {
+        let now = self.clock.now();
+        let until = now + lease;
+
+        let result = erp_connection_sync_state::Entity::update_many()
+            .col_expr(
+                erp_connection_sync_state::Column::SyncLockOwner,
+                Expr::value(owner.to_string()),
+            )
+            .col_expr(
+                erp_connection_sync_state::Column::SyncLockUntil,
+                Expr::value(chrono::DateTime::<chrono::FixedOffset>::from(until)),
+            )
+            .filter(erp_connection_sync_state::Column::Id.eq(sync_state_id))
+            .filter(
+                Condition::any()
+                    .add(erp_connection_sync_state::Column::SyncLockUntil.is_null())
+                    .add(
+                        erp_connection_sync_state::Column::SyncLockUntil
+                            .lte(chrono::DateTime::<chrono::FixedOffset>::from(now)),
+                    ),
+            )
+            .exec(txn)
+            .await?;
+
+        Ok(result.rows_affected == 1)
     }
 
     pub async fn get_by_id(
@@ -109,23 +177,33 @@ impl ErpConnectionSyncStateService {
         }
     }
 
+    ///sync states whose `next_poll_at` has passed (or was never set), most overdue first
+    pub async fn find_due(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Vec<erp_connection_sync_state::Model>, DbErr> {
+        let now: chrono::DateTime<chrono::FixedOffset> = now.into();
+        let query = erp_connection_sync_state::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(erp_connection_sync_state::Column::NextPollAt.is_null())
+                    .add(erp_connection_sync_state::Column::NextPollAt.lte(now)),
+            )
+            .order_by_asc(erp_connection_sync_state::Column::NextPollAt);
+
+        match txn {
+            Some(txn) => query.all(txn).await,
+            None => query.all(&self.db).await,
+        }
+    }
+
     pub async fn create(
         &self,
         data: CreateErpConnectionSyncState,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<erp_connection_sync_state::Model, DbErr> {
-        let active = erp_connection_sync_state::ActiveModel {
-            connection_id: Set(data.connection_id),
-            sync_cursor: Set(data.sync_cursor),
-            sync_lock_owner: Set(data.sync_lock_owner),
-            sync_lock_until: Set(data.sync_lock_until.map(Into::into)),
-            rate_limit_remaining: Set(data.rate_limit_remaining),
-            rate_limit: Set(data.rate_limit),
-            rate_limit_reset_at: Set(data.rate_limit_reset_at.map(Into::into)),
-            rate_limit_backoff_until: Set(data.rate_limit_backoff_until.map(Into::into)),
-            rate_limit_window_seconds: Set(data.rate_limit_window_seconds),
-            ..Default::default()
-        };
+        let active = to_active_model(data);
 
         match txn {
             Some(txn) => active.insert(txn).await,
@@ -133,6 +211,32 @@ impl ErpConnectionSyncStateService {
         }
     }
 
+    ///inserts the connection's sync state unless it already has one, then returns whichever
+    ///row exists; concurrent callers (e.g. two first polls) converge on the same row
+    pub async fn create_if_missing(
+        &self,
+        data: CreateErpConnectionSyncState,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<erp_connection_sync_state::Model, DbErr> {
+        let connection_id = data.connection_id;
+        let insert = erp_connection_sync_state::Entity::insert(to_active_model(data))
+            .on_conflict(
+                OnConflict::column(erp_connection_sync_state::Column::ConnectionId)
+                    .do_nothing()
+                    .to_owned(),
+            )
+            .try_insert();
+
+        //without RETURNING, so a skipped insert reads the same on every backend
+        match txn {
+            Some(txn) => insert.exec_without_returning(txn).await?,
+            None => insert.exec_without_returning(&self.db).await?,
+        };
+        self.get_by_connection_id(connection_id, txn)
+            .await?
+            .ok_or_else(|| DbErr::RecordNotFound(format!("sync state of connection {connection_id}")))
+    }
+
     pub async fn update_by_uuid(
         &self,
         uuid: Uuid,
@@ -184,8 +288,11 @@ impl ErpConnectionSyncStateService {
         if patch.rate_limit_window_seconds.is_some() {
             active.rate_limit_window_seconds = Set(patch.rate_limit_window_seconds);
         }
+        if patch.poll_interval_seconds.is_some() {
+            active.poll_interval_seconds = Set(patch.poll_interval_seconds);
+        }
 
-        active.updated_at = Set(chrono::Utc::now().into());
+        active.updated_at = Set(self.clock.now().into());
 
         match txn {
             Some(txn) => Ok(Some(active.update(txn).await?)),
@@ -244,8 +351,11 @@ impl ErpConnectionSyncStateService {
         if patch.rate_limit_window_seconds.is_some() {
             active.rate_limit_window_seconds = Set(patch.rate_limit_window_seconds);
         }
+        if patch.poll_interval_seconds.is_some() {
+            active.poll_interval_seconds = Set(patch.poll_interval_seconds);
+        }
 
-        active.updated_at = Set(chrono::Utc::now().into());
+        active.updated_at = Set(self.clock.now().into());
 
         match txn {
             Some(txn) => Ok(Some(active.update(txn).await?)),
@@ -253,3 +363,38 @@ impl ErpConnectionSyncStateService {
         }
     }
 }
+
+///when a connection polled at `polled_at` is next due, using its own interval when set
+pub fn compute_next_poll_at(
+    polled_at: chrono::DateTime<chrono::Utc>,
+    poll_interval_seconds: Option<i32>,
+) -> chrono::DateTime<chrono::Utc> {
+    let secs = poll_interval_seconds
+        .filter(|s| *s > 0)
+        .map(i64::from)
+        .unwrap_or(DEFAULT_POLL_INTERVAL_SECS);
+    polled_at + chrono::Duration::seconds(secs)
+}
+
+///whether a sync state is due at `now`; one that was never scheduled is due
+pub fn is_due(sync_state: &erp_connection_sync_state::Model, now: chrono::DateTime<chrono::Utc>) -> bool {
+    sync_state.next_poll_at.is_none_or(|next| next <= now)
+}
+
+///`CreateErpConnectionSyncState` as an insertable model, shared by `create` and
+///`create_if_missing`
+fn to_active_model(data: CreateErpConnectionSyncState) -> erp_connection_sync_state::ActiveModel {
+    erp_connection_sync_state::ActiveModel {
+        connection_id: Set(data.connection_id),
+        sync_cursor: Set(data.sync_cursor),
+        sync_lock_owner: Set(data.sync_lock_owner),
+        sync_lock_until: Set(data.sync_lock_until.map(Into::into)),
+        rate_limit_remaining: Set(data.rate_limit_remaining),
+        rate_limit: Set(data.rate_limit),
+        rate_limit_reset_at: Set(data.rate_limit_reset_at.map(Into::into)),
+        rate_limit_backoff_until: Set(data.rate_limit_backoff_until.map(Into::into)),
+        rate_limit_window_seconds: Set(data.rate_limit_window_seconds),
+        poll_interval_seconds: Set(data.poll_interval_seconds),
+        ..Default::default()
+    }
+}
@@ -0,0 +1,56 @@
+//! ETags and `If-None-Match` handling for conditional GETs.
+//!
+//! A resource's ETag is derived from the `updated_at` of every row it is built from, so it
+//! changes whenever one of them is written. ETags are weak (`W/"..."`): they identify the
+//! resource version, not the exact bytes of its JSON. When the client already holds the
+//! current version the handler answers `304 Not Modified` without a body.
+
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, SecondsFormat, TimeZone};
+use sha2::{Digest, Sha256};
+
+///weak ETag over the version timestamps of a resource's rows; a missing row (`None`)
+///counts as a version of its own
+pub fn etag_from_timestamps<Tz: TimeZone>(versions: &[Option<&DateTime<Tz>>]) -> String
+where
+    Tz::Offset: std::fmt::Display,
+{
+    let key = versions
+        .iter()
+        .map(|v| v.map_or_else(|| "-".to_string(), |t| t.to_rfc3339_opts(SecondsFormat::Nanos, true)))
+        .collect::<Vec<_>>()
+        .join("|");
+    let digest = format!("{:x}", Sha256::digest(key.as_bytes()));
+    format!("W/\"{}\"", &digest[..32])
+}
+
+///true when the request's `If-None-Match` is `*` or lists `etag` (weak comparison)
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let etag = opaque_tag(etag);
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|candidate| candidate == "*" || opaque_tag(candidate) == etag)
+}
+
+///`304 Not Modified` when the client's copy is current, otherwise `body`; both carry the ETag
+pub fn conditional_response(headers: &HeaderMap, etag: &str, body: impl IntoResponse) -> Response {
+    let mut response = if if_none_match(headers, etag) {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        body.into_response()
+    };
+    if let Ok(value) = HeaderValue::from_str(etag) {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+///the quoted part of an entity tag, without the weak `W/` prefix
+fn opaque_tag(tag: &str) -> &str {
+    tag.strip_prefix("W/").unwrap_or(tag)
+}
@@ -0,0 +1,109 @@
+//! In-process bus of typed domain events (no routes).
+//!
+//! Producers `publish` a `DomainEvent` after the change it describes was committed; every
+//! subscriber (`events::subscribers`) gets its own copy and reacts independently, so side
+//! effects like metrics stay out of the poll code. Delivery is best effort: events published
+//! with no subscriber are dropped, and a subscriber that falls more than the bus capacity
+//! behind skips the oldest ones. Anything that must reach another system goes through the
+//! outbox instead.
+
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// Events a subscriber can fall behind by before it starts skipping.
+pub const DEFAULT_EVENT_BUS_CAPACITY: usize = 1024;
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Something that happened to a connection's data.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DomainEvent {
+    InventoryUpserted(InventoryUpserted),
+    SyncEventFailed(SyncEventFailed),
+    ConnectionReauthNeeded(ConnectionReauthNeeded),
+}
+
+/// A pull created or updated an inventory record.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryUpserted {
+    pub tenant_id: i64,
+    pub connection_id: i64,
+    pub connection_uuid: Uuid,
+    /// Provider id of the record (e.g. QBD ListID).
+    pub record_id: String,
+    pub inventory_record_event_id: i64,
+    /// True when the record did not exist before.
+    pub created: bool,
+}
+
+/// A pull page failed and its sync event was marked Error.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncEventFailed {
+    pub tenant_id: i64,
+    pub connection_id: i64,
+    pub connection_uuid: Uuid,
+    /// Provider status code, when the provider reported one.
+    pub code: Option<String>,
+    pub message: String,
+}
+
+/// The provider refused a connection's access; someone has to re-authorize it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionReauthNeeded {
+    pub tenant_id: i64,
+    pub connection_id: i64,
+    pub connection_uuid: Uuid,
+    pub reason: String,
+}
+
+/// Cheap to clone; clones publish to the same subscribers.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<DomainEvent>,
+}
+
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl DomainEvent {
+    ///snake_case name, e.g. the `event` label of `domain_events_total`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::InventoryUpserted(_) => "inventory_upserted",
+            Self::SyncEventFailed(_) => "sync_event_failed",
+            Self::ConnectionReauthNeeded(_) => "connection_reauth_needed",
+        }
+    }
+
+    pub fn connection_id(&self) -> i64 {
+        match self {
+            Self::InventoryUpserted(e) => e.connection_id,
+            Self::SyncEventFailed(e) => e.connection_id,
+            Self::ConnectionReauthNeeded(e) => e.connection_id,
+        }
+    }
+}
+
+impl EventBus {
+    ///`capacity` is how far a subscriber may fall behind before it skips events
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity.max(1));
+        Self { sender }
+    }
+
+    ///hands `event` to every current subscriber; returns how many there were
+    pub fn publish(&self, event: DomainEvent) -> usize {
+        //no subscriber is not an error, the event is just dropped
+        self.sender.send(event).unwrap_or(0)
+    }
+
+    ///receives every event published from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<DomainEvent> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_EVENT_BUS_CAPACITY)
+    }
+}
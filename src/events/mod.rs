@@ -0,0 +1,4 @@
+pub mod bus;
+pub mod subscribers;
+
+pub use bus::{DomainEvent, EventBus};
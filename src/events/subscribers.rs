@@ -0,0 +1,57 @@
+//! Subscribers of the domain event bus, started once at boot (`spawn_defaults`).
+//!
+//! Each subscriber runs on its own task with its own receiver, so a slow one never holds up
+//! the publisher or the others.
+
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::metrics;
+use super::bus::{DomainEvent, EventBus};
+
+///runs `handle` for every event published on `bus` from now on, until the bus is dropped
+pub fn spawn<F>(bus: &EventBus, name: &'static str, handle: F) -> tokio::task::JoinHandle<()>
+where
+    F: Fn(&DomainEvent) + Send + 'static,
+{
+    let mut receiver = bus.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => handle(&event),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(subscriber = name, skipped, "Domain event subscriber fell behind");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+///starts the metrics and log subscribers
+pub fn spawn_defaults(bus: &EventBus) {
+    spawn(bus, "metrics", record_metric);
+    spawn(bus, "log", log_event);
+}
+
+///counts the event in `domain_events_total`
+pub fn record_metric(event: &DomainEvent) {
+    metrics::record_domain_event(event.name());
+}
+
+///logs failures and re-authorization requests; upserts are too frequent to log one by one
+pub fn log_event(event: &DomainEvent) {
+    match event {
+        DomainEvent::InventoryUpserted(_) => {}
+        DomainEvent::SyncEventFailed(e) => tracing::warn!(
+            connection_id = e.connection_id,
+            code = e.code.as_deref(),
+            message = %e.message,
+            "Sync event failed"
+        ),
+        DomainEvent::ConnectionReauthNeeded(e) => tracing::warn!(
+            connection_id = e.connection_id,
+            reason = %e.reason,
+            "Connection needs re-authorization"
+        ),
+    }
+}
@@ -0,0 +1,309 @@
+//! Idempotency keys for create endpoints.
+//!
+//! A client that retries a create (e.g. after a network error) sends the same
+//! `Idempotency-Key` header. The first request's response body is stored under the key for
+//! `IDEMPOTENCY_TTL_SECS` and replayed to every repeat instead of creating another row.
+//! Keys are scoped to the endpoint and the API token that sent them.
+//!
+//! The key is reserved before the create runs, so a repeat that arrives while the first
+//! request is still running is refused (`InProgress`) rather than creating a second row; a
+//! failed create releases the key so the client can try again. Reusing a key with a
+//! different request body is refused (`KeyReused`). Store failures are logged and the
+//! request goes ahead without idempotency, so a Redis outage can't block creates.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Set to `true` on responses replayed from an earlier request.
+pub const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+
+/// How long a completed request's response is replayed for its key.
+pub const IDEMPOTENCY_TTL_SECS: u64 = 24 * 60 * 60;
+
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 255;
+
+/// How long a reservation holds off repeats if the process dies before completing it.
+const RESERVATION_TTL_SECS: u64 = 60;
+
+const KEY_PREFIX: &str = "idempotency:";
+
+/// BEGUN STRUCTS AND ENUMS ///
+#[derive(Clone)]
+pub enum IdempotencyStore {
+    /// Shared across instances via `AppState.redis`.
+    Redis(ConnectionManager),
+    /// In-process only; for single-instance setups and tests.
+    Local(Arc<Mutex<HashMap<String, (String, Instant)>>>),
+}
+
+/// A validated `Idempotency-Key`, already scoped to its endpoint and caller.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyKey(String);
+
+/// What is stored under a key: a reservation while the create runs, then its response.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+enum StoredEntry {
+    Pending { fingerprint: String },
+    Done { fingerprint: String, body: Value },
+}
+
+/// Outcome of `idempotent_create`.
+#[derive(Debug)]
+pub enum Idempotent<T> {
+    /// The create ran for this request.
+    Created(T),
+    /// An earlier request with the same key created the resource; this is its response body.
+    Replayed(Value),
+}
+
+#[derive(Debug)]
+pub enum IdempotencyError<E> {
+    /// A request with the same key is still being processed.
+    InProgress,
+    /// The key was used before with a different request body.
+    KeyReused,
+    /// The create itself failed; the key was released.
+    Create(E),
+}
+
+enum Reservation {
+    Reserved,
+    Replay(Value),
+    InProgress,
+    KeyReused,
+    /// The store failed; the create runs without idempotency.
+    Unavailable,
+}
+
+/// END STRUCTS AND ENUMS ///
+
+
+/// BEGUN IMPLEMENTATION ///
+impl IdempotencyKey {
+    ///reads `Idempotency-Key` from `headers`, scoped to `scope` (e.g. `tenant:create`) and the
+    ///API token that sent it; None when the header is absent, Err when it is malformed
+    pub fn from_headers(
+        headers: &HeaderMap,
+        scope: &str,
+        actor_token_id: Option<i64>,
+    ) -> Result<Option<Self>, String> {
+        let Some(value) = headers.get(IDEMPOTENCY_KEY_HEADER) else {
+            return Ok(None);
+        };
+        let value = value
+            .to_str()
+            .map_err(|_| "must be visible ASCII".to_string())?
+            .trim();
+        if value.is_empty() {
+            return Err("must not be blank".to_string());
+        }
+        if value.len() > MAX_IDEMPOTENCY_KEY_LEN {
+            return Err(format!("must be at most {} characters", MAX_IDEMPOTENCY_KEY_LEN));
+        }
+        let actor = actor_token_id.map_or_else(|| "-".to_string(), |id| id.to_string());
+        Ok(Some(Self(format!("{}{}:{}:{}", KEY_PREFIX, scope, actor, value))))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+///identifies a request body, so a key can't be replayed for a different request
+pub fn fingerprint<B: Serialize>(body: &B) -> String {
+    let bytes = serde_json::to_vec(body).unwrap_or_default();
+    format!("{:x}", Sha256::digest(&bytes))
+}
+
+impl<T: Serialize> Idempotent<T> {
+    ///the response to send: `status` with the body, marked when it is a replay
+    pub fn into_response(self, status: StatusCode) -> Response {
+        match self {
+            Idempotent::Created(body) => (status, Json(body)).into_response(),
+            Idempotent::Replayed(body) => {
+                let mut response = (status, Json(body)).into_response();
+                response
+                    .headers_mut()
+                    .insert(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+                response
+            }
+        }
+    }
+}
+
+impl IdempotencyStore {
+    pub fn local() -> Self {
+        IdempotencyStore::Local(Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    async fn reserve(&self, key: &IdempotencyKey, fingerprint: &str) -> Reservation {
+        let pending = StoredEntry::Pending {
+            fingerprint: fingerprint.to_string(),
+        };
+        let Ok(pending) = serde_json::to_string(&pending) else {
+            return Reservation::Unavailable;
+        };
+
+        let existing = match self {
+            IdempotencyStore::Redis(redis) => {
+                let mut conn = redis.clone();
+                let reserved = redis::cmd("SET")
+                    .arg(key.as_str())
+                    .arg(&pending)
+                    .arg("NX")
+                    .arg("EX")
+                    .arg(RESERVATION_TTL_SECS)
+                    .query_async::<Option<String>>(&mut conn)
+                    .await;
+                match reserved {
+                    Ok(Some(_)) => return Reservation::Reserved,
+                    Ok(None) => match conn.get::<_, Option<String>>(key.as_str()).await {
+                        Ok(existing) => existing,
+                        Err(e) => {
+                            tracing::warn!("Idempotency key lookup failed: {}", e);
+                            return Reservation::Unavailable;
+                        }
+                    },
+                    Err(e) => {
+                        tracing::warn!("Idempotency key reservation failed: {}", e);
+                        return Reservation::Unavailable;
+                    }
+                }
+            }
+            IdempotencyStore::Local(map) => {
+                let Ok(mut map) = map.lock() else {
+                    return Reservation::Unavailable;
+                };
+                let now = Instant::now();
+                match map.get(key.as_str()).filter(|(_, expires_at)| *expires_at > now) {
+                    Some((existing, _)) => Some(existing.clone()),
+                    None => {
+                        let expires_at = now + Duration::from_secs(RESERVATION_TTL_SECS);
+                        map.insert(key.as_str().to_string(), (pending, expires_at));
+                        return Reservation::Reserved;
+                    }
+                }
+            }
+        };
+
+        //the reservation expired between SET and GET; the client can simply retry
+        let Some(existing) = existing else {
+            return Reservation::InProgress;
+        };
+        match serde_json::from_str::<StoredEntry>(&existing) {
+            Ok(StoredEntry::Done { fingerprint: stored, body }) if stored == fingerprint => {
+                Reservation::Replay(body)
+            }
+            Ok(StoredEntry::Pending { fingerprint: stored }) if stored == fingerprint => {
+                Reservation::InProgress
+            }
+            Ok(_) => Reservation::KeyReused,
+            Err(e) => {
+                tracing::warn!("Unreadable idempotency entry {}: {}", key.as_str(), e);
+                Reservation::Unavailable
+            }
+        }
+    }
+
+    async fn complete(&self, key: &IdempotencyKey, fingerprint: &str, body: Value) {
+        let done = StoredEntry::Done {
+            fingerprint: fingerprint.to_string(),
+            body,
+        };
+        let Ok(done) = serde_json::to_string(&done) else {
+            return;
+        };
+        match self {
+            IdempotencyStore::Redis(redis) => {
+                let mut conn = redis.clone();
+                if let Err(e) = conn
+                    .set_ex::<_, _, ()>(key.as_str(), done, IDEMPOTENCY_TTL_SECS)
+                    .await
+                {
+                    tracing::warn!("Idempotency response write failed: {}", e);
+                }
+            }
+            IdempotencyStore::Local(map) => {
+                if let Ok(mut map) = map.lock() {
+                    let expires_at = Instant::now() + Duration::from_secs(IDEMPOTENCY_TTL_SECS);
+                    map.insert(key.as_str().to_string(), (done, expires_at));
+                }
+            }
+        }
+    }
+
+    async fn release(&self, key: &IdempotencyKey) {
+        match self {
+            IdempotencyStore::Redis(redis) => {
+                let mut conn = redis.clone();
+                if let Err(e) = conn.del::<_, ()>(key.as_str()).await {
+                    tracing::warn!("Idempotency key release failed: {}", e);
+                }
+            }
+            IdempotencyStore::Local(map) => {
+                if let Ok(mut map) = map.lock() {
+                    map.remove(key.as_str());
+                }
+            }
+        }
+    }
+}
+
+///runs `create` at most once per key: without a key it just runs; with one, a repeat of a
+///completed request replays the stored body instead (see the module docs)
+pub async fn idempotent_create<T, E, F, Fut>(
+    store: &IdempotencyStore,
+    key: Option<&IdempotencyKey>,
+    fingerprint: &str,
+    create: F,
+) -> Result<Idempotent<T>, IdempotencyError<E>>
+where
+    T: Serialize,
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let Some(key) = key else {
+        return create().await.map(Idempotent::Created).map_err(IdempotencyError::Create);
+    };
+
+    match store.reserve(key, fingerprint).await {
+        Reservation::Replay(body) => return Ok(Idempotent::Replayed(body)),
+        Reservation::InProgress => return Err(IdempotencyError::InProgress),
+        Reservation::KeyReused => return Err(IdempotencyError::KeyReused),
+        Reservation::Unavailable => {
+            return create().await.map(Idempotent::Created).map_err(IdempotencyError::Create);
+        }
+        Reservation::Reserved => {}
+    }
+
+    match create().await {
+        Ok(created) => {
+            match serde_json::to_value(&created) {
+                Ok(body) => store.complete(key, fingerprint, body).await,
+                Err(e) => {
+                    tracing::warn!("Idempotent response not stored: {}", e);
+                    store.release(key).await;
+                }
+            }
+            Ok(Idempotent::Created(created))
+        }
+        Err(e) => {
+            store.release(key).await;
+            Err(IdempotencyError::Create(e))
+        }
+    }
+}
@@ -0,0 +1,322 @@
+//! Batched inventory upserts pushed by a client (`POST /connections/{uuid}/inventory/batch`).
+//!
+//! A batch is applied in one transaction and recorded like a pull: a `batch` connection run,
+//! a List sync event for the whole batch and a Create/Update child event per written record.
+//! Each item is upserted through `upsert::upsert_inventory_item` inside its own savepoint, so
+//! an item that fails (invalid, repeated in the batch, or rejected by the database) is
+//! reported as failed without undoing the others.
+
+use std::collections::HashSet;
+
+use entity::sea_orm_active_enums::{
+    ConnectionRunStatus, ConnectionRunType, SyncEventCategory, SyncEventDirection,
+    SyncEventMethod, SyncEventStatus,
+};
+use entity::{connection_identity, connection_run, inventory_record, sync_event};
+use sea_orm::{ActiveEnum, DatabaseConnection, DatabaseTransaction, DbErr, TransactionTrait};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::connection_run::services::{ConnectionRunService, CreateConnectionRun};
+use crate::outbox::services::{OutboxPayload, OutboxService, TOPIC_INVENTORY_UPSERTED};
+use crate::sync_event::services::{CreateSyncEvent, SyncEventService};
+
+use super::upsert::{existing_records, upsert_inventory_item, InventoryItem, UpsertOutcome};
+
+/// Most items accepted in one batch.
+pub const MAX_BATCH_ITEMS: usize = 500;
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// One item of a batch: the mapped item, or why it was rejected before reaching the database.
+#[derive(Debug, Clone)]
+pub struct BatchItem {
+    pub system_id: String,
+    pub item: Result<InventoryItem, String>,
+}
+
+/// What happened to one item of a batch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatchItemStatus {
+    Created,
+    Updated,
+    Unchanged,
+    Failed,
+}
+
+/// Result of one item, in request order.
+#[derive(Debug, Clone)]
+pub struct BatchItemResult {
+    /// Position of the item in the request.
+    pub index: usize,
+    pub system_id: String,
+    pub status: BatchItemStatus,
+    /// The item's record; None when it failed.
+    pub inventory_record_uuid: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+/// Result of `InventoryBatchService::upsert`.
+#[derive(Debug, Clone)]
+pub struct InventoryBatchOutcome {
+    pub run: connection_run::Model,
+    /// The List event recording the whole batch.
+    pub event: sync_event::Model,
+    pub items: Vec<BatchItemResult>,
+}
+
+pub struct InventoryBatchService {
+    db: DatabaseConnection,
+}
+
+/// A record written by the batch, for its child sync event and outbox message.
+struct Written {
+    record: inventory_record::Model,
+    inventory_record_event_id: i64,
+    created: bool,
+}
+
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl BatchItemStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Created => "created",
+            Self::Updated => "updated",
+            Self::Unchanged => "unchanged",
+            Self::Failed => "failed",
+        }
+    }
+}
+
+impl From<UpsertOutcome> for BatchItemStatus {
+    fn from(outcome: UpsertOutcome) -> Self {
+        match outcome {
+            UpsertOutcome::Created => Self::Created,
+            UpsertOutcome::Updated => Self::Updated,
+            UpsertOutcome::Unchanged => Self::Unchanged,
+        }
+    }
+}
+
+impl InventoryBatchService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    ///upserts `items` for `conn` and records the batch; opens its own transaction when
+    ///`txn` is None. Only a failure to record the batch itself is an error
+    pub async fn upsert(
+        &self,
+        conn: &connection_identity::Model,
+        items: Vec<BatchItem>,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<InventoryBatchOutcome, DbErr> {
+        let Some(txn) = txn else {
+            let txn = self.db.begin().await?;
+            let result = Box::pin(self.upsert(conn, items, Some(&txn))).await;
+            match result {
+                Ok(outcome) => {
+                    txn.commit().await?;
+                    return Ok(outcome);
+                }
+                Err(e) => {
+                    txn.rollback().await?;
+                    return Err(e);
+                }
+            }
+        };
+
+        //every valid item shares its connection's key, so one lookup covers the batch
+        let key = items.iter().find_map(|b| b.item.as_ref().ok().map(|i| i.system_id_key.clone()));
+        let mut existing = match key {
+            Some(key) => {
+                let ids: Vec<&str> = items
+                    .iter()
+                    .filter(|b| b.item.is_ok())
+                    .map(|b| b.system_id.as_str())
+                    .collect();
+                existing_records(conn, key, &ids, txn).await?
+            }
+            None => Default::default(),
+        };
+
+        let outbox = OutboxService::new(self.db.clone());
+        let mut seen = HashSet::new();
+        let mut written = Vec::new();
+        let mut results = Vec::with_capacity(items.len());
+        for (index, batch_item) in items.into_iter().enumerate() {
+            let item = match batch_item.item {
+                Ok(_) if !seen.insert(batch_item.system_id.clone()) => {
+                    Err("system_id appears earlier in the batch".to_string())
+                }
+                other => other,
+            };
+            let item = match item {
+                Ok(item) => item,
+                Err(error) => {
+                    results.push(BatchItemResult {
+                        index,
+                        system_id: batch_item.system_id,
+                        status: BatchItemStatus::Failed,
+                        inventory_record_uuid: None,
+                        error: Some(error),
+                    });
+                    continue;
+                }
+            };
+
+            let savepoint = txn.begin().await?;
+            let applied = match upsert_inventory_item(
+                &self.db,
+                conn,
+                &item,
+                existing.get(&item.system_id),
+                &savepoint,
+            )
+            .await
+            {
+                Ok(upserted) => match upserted.event.as_ref() {
+                    Some(_) => outbox
+                        .enqueue(&savepoint, upserted_message(conn, &upserted.record, &item))
+                        .await
+                        .map(|_| upserted),
+                    None => Ok(upserted),
+                },
+                Err(e) => Err(e),
+            };
+            match applied {
+                Ok(upserted) => {
+                    savepoint.commit().await?;
+                    if let Some(event) = upserted.event.as_ref() {
+                        written.push(Written {
+                            record: upserted.record.clone(),
+                            inventory_record_event_id: event.id,
+                            created: upserted.outcome == UpsertOutcome::Created,
+                        });
+                    }
+                    existing.remove(&item.system_id);
+                    results.push(BatchItemResult {
+                        index,
+                        system_id: item.system_id,
+                        status: upserted.outcome.into(),
+                        inventory_record_uuid: Some(upserted.record.uuid),
+                        error: None,
+                    });
+                }
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    results.push(BatchItemResult {
+                        index,
+                        system_id: item.system_id,
+                        status: BatchItemStatus::Failed,
+                        inventory_record_uuid: None,
+                        error: Some(e.to_string()),
+                    });
+                }
+            }
+        }
+
+        let failed: Vec<_> = results
+            .iter()
+            .filter(|r| r.status == BatchItemStatus::Failed)
+            .map(|r| json!({"index": r.index, "system_id": r.system_id, "error": r.error}))
+            .collect();
+        let summary = format!("{} of {} items failed", failed.len(), results.len());
+
+        let run = ConnectionRunService::new(self.db.clone())
+            .create(
+                CreateConnectionRun {
+                    uuid: None,
+                    connection_id: conn.id,
+                    status: Some(match failed.is_empty() {
+                        true => ConnectionRunStatus::Success,
+                        false => ConnectionRunStatus::Error,
+                    }),
+                    run_type: Some(ConnectionRunType::Batch),
+                    error_message: (!failed.is_empty()).then(|| summary.clone()),
+                },
+                Some(txn),
+            )
+            .await?;
+
+        let events = SyncEventService::new(self.db.clone());
+        //`details` keeps the event out of the one-recurring-List-event-per-state index
+        let event = events
+            .create(
+                CreateSyncEvent {
+                    original_record_body: None,
+                    details: Some(json!({"source": "batch_api", "items": results.len()})),
+                    event_direction: SyncEventDirection::PullFromExternal,
+                    inventory_record_event_id: None,
+                    sync_event_method: SyncEventMethod::List,
+                    sync_event_category: SyncEventCategory::Inventory,
+                    attempts: Some(1),
+                    status: Some(match failed.is_empty() {
+                        true => SyncEventStatus::Success,
+                        false => SyncEventStatus::Error,
+                    }),
+                    last_errored_date: (!failed.is_empty()).then(chrono::Utc::now),
+                    last_error: (!failed.is_empty())
+                        .then(|| json!({"message": summary, "failed": failed})),
+                    connection_sync_state_id: None,
+                    connection_run_id: Some(run.id),
+                },
+                Some(txn),
+            )
+            .await?;
+
+        let children = written
+            .iter()
+            .map(|w| CreateSyncEvent {
+                original_record_body: None,
+                details: Some(json!({
+                    "parent_event_uuid": event.uuid,
+                    "record_id": w.record.system_id,
+                })),
+                event_direction: SyncEventDirection::PullFromExternal,
+                inventory_record_event_id: Some(w.inventory_record_event_id),
+                sync_event_method: match w.created {
+                    true => SyncEventMethod::Create,
+                    false => SyncEventMethod::Update,
+                },
+                sync_event_category: SyncEventCategory::Inventory,
+                attempts: Some(1),
+                status: Some(SyncEventStatus::Success),
+                last_error: None,
+                last_errored_date: None,
+                connection_sync_state_id: None,
+                connection_run_id: Some(run.id),
+            })
+            .collect();
+        events.create_many(children, Some(txn)).await?;
+
+        Ok(InventoryBatchOutcome {
+            run,
+            event,
+            items: results,
+        })
+    }
+}
+
+///`inventory.upserted` message for a record written by a batch
+fn upserted_message(
+    conn: &connection_identity::Model,
+    record: &inventory_record::Model,
+    item: &InventoryItem,
+) -> OutboxPayload {
+    OutboxPayload {
+        topic: TOPIC_INVENTORY_UPSERTED.to_string(),
+        payload: json!({
+            "tenant_id": conn.tenant_id,
+            "connection_uuid": conn.uuid,
+            "inventory_record_uuid": record.uuid,
+            "system_id_key": item.system_id_key.to_value(),
+            "system_id": record.system_id,
+            "name": item.name,
+            "qty": item.qty,
+            "qty_milli": item.qty_milli,
+            "price": item.price,
+        }),
+    }
+}
@@ -1,11 +1,16 @@
-//! CRUD services for inventory_record_event (no routes).
+//! CRUD services for inventory_record_event, plus the inventory search behind
+//! `GET /connections/{uuid}/inventory/search`.
 
 use entity::inventory_record_event;
+use chrono::{DateTime, Utc};
 use entity::sea_orm_active_enums::Currency;
+use sea_orm::sea_query::extension::postgres::PgExpr;
+use sea_orm::sea_query::{Expr, Func, Query, SimpleExpr};
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
-    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Select, Set,
 };
+use std::collections::HashMap;
 use uuid::Uuid;
 
 //DEBUG AND ERRORS ///
@@ -25,6 +30,12 @@ impl From<DbErr> for InventoryRecordEventError {
 
 //END DEBUG AND ERRORS
 
+/// Queries shorter than this (in characters) are matched with ILIKE instead of full-text search.
+pub const MIN_FULL_TEXT_QUERY_LEN: usize = 3;
+/// Results `search` returns when no limit is given.
+pub const DEFAULT_SEARCH_LIMIT: u64 = 20;
+/// Most results `search` returns.
+pub const MAX_SEARCH_LIMIT: u64 = 100;
 
 /// BEGUN STRUCTS AND ENUMS ///
 pub struct InventoryRecordEventService {
@@ -40,9 +51,11 @@ pub struct CreateInventoryRecordEvent {
     pub currency: Option<Currency>,
     pub name: Option<String>,
     pub description: Option<String>,
-    pub attributes: Option<String>,
+    pub attributes: Option<serde_json::Value>,
     pub qty: Option<i32>,
     pub external_code: Option<String>,
+    /// Quantity in thousandths; preserves fractions `qty` (whole units) can't hold.
+    pub qty_milli: Option<i64>,
 }
 
 #[allow(dead_code)]
@@ -52,9 +65,11 @@ pub struct UpdateInventoryRecordEvent {
     pub currency: Option<Currency>,
     pub name: Option<String>,
     pub description: Option<String>,
-    pub attributes: Option<String>,
+    pub attributes: Option<serde_json::Value>,
     pub qty: Option<i32>,
     pub external_code: Option<String>,
+    /// Quantity in thousandths; preserves fractions `qty` (whole units) can't hold.
+    pub qty_milli: Option<i64>,
 }
 
 #[allow(dead_code)]
@@ -62,6 +77,21 @@ pub struct UpdateInventoryRecordEvent {
 pub struct InventoryRecordEventFilter {
     pub inventory_record_id: Option<i64>,
     pub connection_id: Option<i64>,
+    /// Only events created at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only events created before this time.
+    pub until: Option<DateTime<Utc>>,
+    /// Only events where any of these fields differs from the record's previous event (on any
+    /// connection). A record's first event counts as a change of the fields it sets.
+    pub changed: Vec<ChangedField>,
+}
+
+/// A field an event can change, for `InventoryRecordEventFilter::changed`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangedField {
+    Price,
+    Qty,
+    Name,
 }
 
 #[allow(dead_code)]
@@ -77,6 +107,36 @@ pub struct PaginatedInventoryRecordEvents {
 
 
 /// BEGUN IMPLEMENTATION ///
+#[allow(dead_code)]
+impl ChangedField {
+    ///parses a `changed` query value: `price`, `qty` or `name`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "price" => Some(ChangedField::Price),
+            "qty" => Some(ChangedField::Qty),
+            "name" => Some(ChangedField::Name),
+            _ => None,
+        }
+    }
+
+    ///event columns compared with the previous event; qty_milli holds the fractions qty drops
+    fn columns(&self) -> &'static [&'static str] {
+        match self {
+            ChangedField::Price => &["price"],
+            ChangedField::Qty => &["qty", "qty_milli"],
+            ChangedField::Name => &["name"],
+        }
+    }
+
+    ///the event's `column` differs from the same column of the record's previous event
+    ///(ordered by created_at, then id), or is set on the record's first event
+    fn differs_from_previous(column: &str) -> SimpleExpr {
+        Expr::cust(format!(
+            r#""inventory_record_event"."{column}" IS DISTINCT FROM (SELECT "prev"."{column}" FROM "inventory_record_event" AS "prev" WHERE "prev"."inventory_record_id" = "inventory_record_event"."inventory_record_id" AND ("prev"."created_at" < "inventory_record_event"."created_at" OR ("prev"."created_at" = "inventory_record_event"."created_at" AND "prev"."id" < "inventory_record_event"."id")) ORDER BY "prev"."created_at" DESC, "prev"."id" DESC LIMIT 1)"#
+        ))
+    }
+}
+
 #[allow(dead_code)]
 impl InventoryRecordEventService {
     pub fn new(db: DatabaseConnection) -> Self {
@@ -125,6 +185,55 @@ impl InventoryRecordEventService {
         }
     }
 
+    ///the record's most recent event (by `created_at`), across connections
+    pub async fn get_latest_by_inventory_record_id(
+        &self,
+        inventory_record_id: i64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<inventory_record_event::Model>, DbErr> {
+        let query = inventory_record_event::Entity::find()
+            .filter(inventory_record_event::Column::InventoryRecordId.eq(inventory_record_id))
+            .order_by_desc(inventory_record_event::Column::CreatedAt)
+            .order_by_desc(inventory_record_event::Column::Id);
+        match txn {
+            Some(txn) => query.one(txn).await,
+            None => query.one(&self.db).await,
+        }
+    }
+
+    ///the most recent event of each record in `record_ids`, across connections, keyed by
+    ///record id; one query for the whole batch. Records without events are absent from the map
+    pub async fn latest_for_records(
+        &self,
+        record_ids: &[i64],
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<HashMap<i64, inventory_record_event::Model>, DbErr> {
+        if record_ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+
+        //ids grow with insertion, so the highest id per record is its latest event
+        let latest_ids = Query::select()
+            .expr(Func::max(Expr::col(inventory_record_event::Column::Id)))
+            .from(inventory_record_event::Entity)
+            .and_where(
+                inventory_record_event::Column::InventoryRecordId.is_in(record_ids.iter().copied()),
+            )
+            .group_by_col(inventory_record_event::Column::InventoryRecordId)
+            .to_owned();
+        let query = inventory_record_event::Entity::find()
+            .filter(inventory_record_event::Column::Id.in_subquery(latest_ids));
+        let events = match txn {
+            Some(txn) => query.all(txn).await?,
+            None => query.all(&self.db).await?,
+        };
+
+        Ok(events
+            .into_iter()
+            .map(|event| (event.inventory_record_id, event))
+            .collect())
+    }
+
     pub async fn get_by_connection_id(
         &self,
         connection_id: i64,
@@ -139,6 +248,78 @@ impl InventoryRecordEventService {
         }
     }
 
+    ///the connection's records whose current name or description matches `q`, as their newest
+    ///event on the connection: exact name matches first, then by full-text rank. See
+    ///`search_query`
+    pub async fn search(
+        &self,
+        q: &str,
+        connection_id: i64,
+        limit: u64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Vec<inventory_record_event::Model>, DbErr> {
+        let query = Self::search_query(connection_id, q, limit);
+        match txn {
+            Some(txn) => query.all(txn).await,
+            None => query.all(&self.db).await,
+        }
+    }
+
+    ///query behind `search`. Queries of `MIN_FULL_TEXT_QUERY_LEN` characters or more match the
+    ///`search_vector` column (name weighted over description) with every word as a prefix;
+    ///shorter ones fall back to a substring ILIKE on name/description
+    pub fn search_query(
+        connection_id: i64,
+        q: &str,
+        limit: u64,
+    ) -> Select<inventory_record_event::Entity> {
+        let q = q.trim();
+        //records are found by what they are called now, not by names they had before
+        let latest_ids = Query::select()
+            .expr(Func::max(Expr::col(inventory_record_event::Column::Id)))
+            .from(inventory_record_event::Entity)
+            .and_where(inventory_record_event::Column::ConnectionId.eq(connection_id))
+            .group_by_col(inventory_record_event::Column::InventoryRecordId)
+            .to_owned();
+        let exact_name = Expr::cust_with_values(
+            r#"lower("inventory_record_event"."name") = lower($1)"#,
+            [q.to_string()],
+        );
+
+        let query = inventory_record_event::Entity::find()
+            .filter(inventory_record_event::Column::ConnectionId.eq(connection_id))
+            .filter(inventory_record_event::Column::Id.in_subquery(latest_ids));
+        let query = match full_text_query(q) {
+            Some(tsquery) => query
+                .filter(Expr::cust_with_values(
+                    r#""inventory_record_event"."search_vector" @@ to_tsquery('english', $1)"#,
+                    [tsquery.clone()],
+                ))
+                .order_by_desc(exact_name)
+                .order_by_desc(Expr::cust_with_values(
+                    r#"ts_rank("inventory_record_event"."search_vector", to_tsquery('english', $1))"#,
+                    [tsquery],
+                )),
+            None => {
+                let pattern = format!("%{}%", escape_like(q));
+                let matches = |column: inventory_record_event::Column| {
+                    Expr::col((inventory_record_event::Entity, column)).ilike(pattern.as_str())
+                };
+                query
+                    .filter(
+                        Condition::any()
+                            .add(matches(inventory_record_event::Column::Name))
+                            .add(matches(inventory_record_event::Column::Description)),
+                    )
+                    .order_by_desc(exact_name)
+            }
+        };
+
+        query
+            .order_by_desc(inventory_record_event::Column::Id)
+            .limit(limit.clamp(1, MAX_SEARCH_LIMIT))
+    }
+
     pub async fn get_all(
         &self,
         page: u64,
@@ -156,11 +337,26 @@ impl InventoryRecordEventService {
                 condition =
                     condition.add(inventory_record_event::Column::ConnectionId.eq(connection_id));
             }
+            if let Some(since) = f.since {
+                condition = condition.add(inventory_record_event::Column::CreatedAt.gte(since));
+            }
+            if let Some(until) = f.until {
+                condition = condition.add(inventory_record_event::Column::CreatedAt.lt(until));
+            }
+            if !f.changed.is_empty() {
+                let mut changed = Condition::any();
+                for column in f.changed.iter().flat_map(|field| field.columns()) {
+                    changed = changed.add(ChangedField::differs_from_previous(column));
+                }
+                condition = condition.add(changed);
+            }
         }
 
+        //id breaks created_at ties so pages don't overlap
         let query = inventory_record_event::Entity::find()
             .filter(condition)
-            .order_by_desc(inventory_record_event::Column::CreatedAt);
+            .order_by_desc(inventory_record_event::Column::CreatedAt)
+            .order_by_desc(inventory_record_event::Column::Id);
 
         let total = match txn {
             Some(txn) => query.clone().count(txn).await?,
@@ -204,6 +400,7 @@ impl InventoryRecordEventService {
             attributes: Set(data.attributes),
             qty: Set(data.qty),
             external_code: Set(data.external_code),
+            qty_milli: Set(data.qty_milli),
             ..Default::default()
         };
         match txn {
@@ -250,6 +447,9 @@ impl InventoryRecordEventService {
         if patch.external_code.is_some() {
             active.external_code = Set(patch.external_code);
         }
+        if patch.qty_milli.is_some() {
+            active.qty_milli = Set(patch.qty_milli);
+        }
         active.updated_at = Set(chrono::Utc::now().into());
         match txn {
             Some(txn) => Ok(Some(active.update(txn).await?)),
@@ -322,4 +522,31 @@ impl InventoryRecordEventService {
     }
 }
 
+///`to_tsquery` input for `q`: each word lowercased as a prefix, all required
+///(`hex:* & bolt:*`); None when `q` is shorter than `MIN_FULL_TEXT_QUERY_LEN` or has no words.
+///Only letters and digits reach `to_tsquery`, so user input can't break its syntax
+pub fn full_text_query(q: &str) -> Option<String> {
+    let q = q.trim();
+    if q.chars().count() < MIN_FULL_TEXT_QUERY_LEN {
+        return None;
+    }
+    let terms: Vec<String> = q
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|term| !term.is_empty())
+        .map(|term| format!("{}:*", term.to_lowercase()))
+        .collect();
+    match terms.is_empty() {
+        true => None,
+        false => Some(terms.join(" & ")),
+    }
+}
+
+///escapes LIKE wildcards so user input matches literally
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
 // END IMPLEMENTATION
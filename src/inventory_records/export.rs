@@ -0,0 +1,324 @@
+//! Export of a connection's inventory as CSV, a JSON array or NDJSON (no routes).
+//!
+//! Records are read in keyset-paginated batches and each batch is rendered to a chunk in the
+//! requested format, so the response body streams without holding the whole inventory in
+//! memory. Every row is joined with the record's latest `inventory_record_event` from the same
+//! connection and mapped to an `InventoryRow`, which all three formats render.
+
+use std::collections::HashMap;
+
+use entity::{inventory_record, inventory_record_event};
+use futures_util::stream::{self, Stream};
+use serde::Serialize;
+use sea_orm::{
+    ActiveEnum, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+    QuerySelect,
+};
+
+/// Records rendered per streamed chunk.
+pub const EXPORT_BATCH_SIZE: u64 = 500;
+
+/// Header row of the export.
+pub const CSV_HEADER: &str = "system_id,name,price,qty,currency,external_code\r\n";
+
+/// Media types the export can be negotiated to, in order of preference for `*/*`.
+pub const EXPORT_MEDIA_TYPES: &[&str] = &["text/csv", "application/json", "application/x-ndjson"];
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Format of the export, chosen from the request's `Accept` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// `text/csv`: header row, then one CRLF-terminated line per record.
+    Csv,
+    /// `application/json`: one array of row objects.
+    Json,
+    /// `application/x-ndjson`: one row object per line.
+    Ndjson,
+}
+
+/// One exported record: the record's system id and its latest event's fields, formatted the
+/// same way in every format (price as a decimal amount, qty exactly from its thousandths).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct InventoryRow {
+    pub system_id: String,
+    pub name: Option<String>,
+    pub price: Option<String>,
+    pub qty: Option<String>,
+    pub currency: Option<String>,
+    pub external_code: Option<String>,
+}
+
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl ExportFormat {
+    ///the format an `Accept` header asks for; a missing header, `*/*` or `text/*` gets CSV.
+    ///Media ranges are tried by descending `q` (ranges with `q=0` are refused); None when
+    ///nothing offered is acceptable
+    pub fn from_accept(accept: Option<&str>) -> Option<Self> {
+        let Some(accept) = accept.filter(|a| !a.trim().is_empty()) else {
+            return Some(Self::Csv);
+        };
+        let mut ranges: Vec<(&str, f32)> = accept
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let media_type = parts.next()?.trim();
+                let q = parts
+                    .filter_map(|param| param.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (!media_type.is_empty() && q > 0.0).then_some((media_type, q))
+            })
+            .collect();
+        //stable, so ranges with the same q keep the client's order
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges.into_iter().find_map(|(media_type, _)| {
+            match media_type.to_ascii_lowercase().as_str() {
+                "text/csv" | "text/*" | "*/*" => Some(Self::Csv),
+                "application/json" => Some(Self::Json),
+                "application/x-ndjson" => Some(Self::Ndjson),
+                _ => None,
+            }
+        })
+    }
+
+    ///`Content-Type` of the response
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv; charset=utf-8",
+            Self::Json => "application/json",
+            Self::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    ///file extension of the download
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+        }
+    }
+
+    ///written before the first row
+    fn header(self) -> &'static str {
+        match self {
+            Self::Csv => CSV_HEADER,
+            Self::Json => "[",
+            Self::Ndjson => "",
+        }
+    }
+
+    ///written after the last row
+    fn footer(self) -> &'static str {
+        match self {
+            Self::Json => "]",
+            Self::Csv | Self::Ndjson => "",
+        }
+    }
+
+    ///renders one row; `first` is whether it is the first row of the export
+    pub fn render_row(self, row: &InventoryRow, first: bool) -> String {
+        match self {
+            Self::Csv => csv_line(row),
+            Self::Json => {
+                let object = serde_json::to_string(row).unwrap_or_default();
+                if first { object } else { format!(",{}", object) }
+            }
+            Self::Ndjson => format!("{}\n", serde_json::to_string(row).unwrap_or_default()),
+        }
+    }
+}
+
+///quotes a field when it contains a comma, quote or line break (RFC 4180)
+pub fn escape_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+///formats integer cents as a decimal amount, e.g. 1250 -> "12.50"
+pub fn format_cents(cents: i32) -> String {
+    let sign = if cents < 0 { "-" } else { "" };
+    let cents = i64::from(cents).abs();
+    format!("{}{}.{:02}", sign, cents / 100, cents % 100)
+}
+
+///formats a quantity in thousandths without trailing zeros, e.g. 10500 -> "10.5", 4000 -> "4"
+pub fn format_milli(milli: i64) -> String {
+    let sign = if milli < 0 { "-" } else { "" };
+    let milli = milli.unsigned_abs();
+    let fraction = format!("{:03}", milli % 1000);
+    let fraction = fraction.trim_end_matches('0');
+    match fraction.is_empty() {
+        true => format!("{}{}", sign, milli / 1000),
+        false => format!("{}{}.{}", sign, milli / 1000, fraction),
+    }
+}
+
+///maps a record and its latest event to an export row; price is stored in cents and exported
+///as a decimal amount, qty exactly from its thousandths when known
+pub fn inventory_row(
+    record: &inventory_record::Model,
+    event: Option<&inventory_record_event::Model>,
+) -> InventoryRow {
+    InventoryRow {
+        system_id: record.system_id.clone(),
+        name: event.and_then(|e| e.name.clone()),
+        price: event.and_then(|e| e.price).map(format_cents),
+        qty: event.and_then(|e| e.qty_milli.map(format_milli).or(e.qty.map(|q| q.to_string()))),
+        currency: event.and_then(|e| e.currency.as_ref()).map(|c| c.to_value()),
+        external_code: event.and_then(|e| e.external_code.clone()),
+    }
+}
+
+///renders one CSV line; missing fields are left empty
+pub fn csv_row(
+    record: &inventory_record::Model,
+    event: Option<&inventory_record_event::Model>,
+) -> String {
+    csv_line(&inventory_row(record, event))
+}
+
+fn csv_line(row: &InventoryRow) -> String {
+    let fields = [
+        Some(&row.system_id),
+        row.name.as_ref(),
+        row.price.as_ref(),
+        row.qty.as_ref(),
+        row.currency.as_ref(),
+        row.external_code.as_ref(),
+    ];
+
+    let mut line = fields
+        .iter()
+        .map(|f| f.map(|f| escape_field(f)).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push_str("\r\n");
+    line
+}
+
+/// Stream state: the id of the last exported record, `None` before the header is sent.
+struct ExportCursor {
+    db: DatabaseConnection,
+    tenant_id: i64,
+    connection_id: i64,
+    format: ExportFormat,
+    last_id: Option<i64>,
+    /// Whether a row was rendered yet (JSON separates rows with commas).
+    wrote_row: bool,
+    done: bool,
+}
+
+///streams the CSV for the connection's records, scoped to its tenant
+pub fn inventory_csv_stream(
+    db: DatabaseConnection,
+    tenant_id: i64,
+    connection_id: i64,
+) -> impl Stream<Item = Result<String, DbErr>> + Send + 'static {
+    inventory_export_stream(db, tenant_id, connection_id, ExportFormat::Csv)
+}
+
+///streams the connection's records in `format`, scoped to its tenant
+///the first chunk is the header (CSV header row, JSON `[`), the last batch carries the footer
+///(JSON `]`); a failed batch ends the stream with the error
+pub fn inventory_export_stream(
+    db: DatabaseConnection,
+    tenant_id: i64,
+    connection_id: i64,
+    format: ExportFormat,
+) -> impl Stream<Item = Result<String, DbErr>> + Send + 'static {
+    let cursor = ExportCursor {
+        db,
+        tenant_id,
+        connection_id,
+        format,
+        last_id: None,
+        wrote_row: false,
+        done: false,
+    };
+
+    stream::unfold(cursor, |mut cursor| async move {
+        if cursor.done {
+            return None;
+        }
+        let last_id = match cursor.last_id {
+            Some(last_id) => last_id,
+            None => {
+                cursor.last_id = Some(0);
+                let header = cursor.format.header();
+                if !header.is_empty() {
+                    return Some((Ok(header.to_string()), cursor));
+                }
+                0
+            }
+        };
+
+        match fetch_batch(&cursor, last_id).await {
+            Ok(rows) => {
+                cursor.done = (rows.len() as u64) < EXPORT_BATCH_SIZE;
+                cursor.last_id = rows.last().map(|(r, _)| r.id).or(cursor.last_id);
+                let mut chunk = String::new();
+                for (record, event) in &rows {
+                    let row = inventory_row(record, event.as_ref());
+                    chunk.push_str(&cursor.format.render_row(&row, !cursor.wrote_row));
+                    cursor.wrote_row = true;
+                }
+                if cursor.done {
+                    chunk.push_str(cursor.format.footer());
+                }
+                (!chunk.is_empty()).then_some((Ok(chunk), cursor))
+            }
+            Err(e) => {
+                cursor.done = true;
+                Some((Err(e), cursor))
+            }
+        }
+    })
+}
+
+/// Next batch of records after `last_id`, each with its latest event from the connection.
+async fn fetch_batch(
+    cursor: &ExportCursor,
+    last_id: i64,
+) -> Result<Vec<(inventory_record::Model, Option<inventory_record_event::Model>)>, DbErr> {
+    let records = inventory_record::Entity::find()
+        .filter(inventory_record::Column::TenantId.eq(cursor.tenant_id))
+        .filter(inventory_record::Column::OriginatingConnectionId.eq(cursor.connection_id))
+        .filter(inventory_record::Column::DeletedAt.is_null())
+        .filter(inventory_record::Column::Id.gt(last_id))
+        .order_by_asc(inventory_record::Column::Id)
+        .limit(EXPORT_BATCH_SIZE)
+        .all(&cursor.db)
+        .await?;
+    if records.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let ids: Vec<i64> = records.iter().map(|r| r.id).collect();
+    let events = inventory_record_event::Entity::find()
+        .filter(inventory_record_event::Column::InventoryRecordId.is_in(ids))
+        .filter(inventory_record_event::Column::ConnectionId.eq(cursor.connection_id))
+        .order_by_desc(inventory_record_event::Column::CreatedAt)
+        .order_by_desc(inventory_record_event::Column::Id)
+        .all(&cursor.db)
+        .await?;
+
+    // Ordered newest first, so the first event seen per record is its latest.
+    let mut latest: HashMap<i64, inventory_record_event::Model> = HashMap::new();
+    for event in events {
+        latest.entry(event.inventory_record_id).or_insert(event);
+    }
+
+    Ok(records
+        .into_iter()
+        .map(|r| {
+            let event = latest.remove(&r.id);
+            (r, event)
+        })
+        .collect())
+}
@@ -1,5 +1,10 @@
+pub mod batch;
 pub mod events_services;
+pub mod export;
+pub mod routes;
 pub mod services;
+pub mod upsert;
 
 pub use events_services::InventoryRecordEventService;
+pub use routes::create_router;
 pub use services::InventoryRecordService;
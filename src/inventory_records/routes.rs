@@ -0,0 +1,407 @@
+use axum::{
+    extract::{Extension, OriginalUri, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use entity::{inventory_record, inventory_record_event};
+use entity::sea_orm_active_enums::{Currency, SystemIdKey};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::etag::{conditional_response, etag_from_timestamps};
+use crate::pagination::{paginated_response, PageInfo};
+use crate::security::AuthenticatedTenant;
+use crate::tenant::routes::ErrorResponse;
+use crate::validation::{parse_optional_enum, FieldError, PathUuid};
+use super::events_services::InventoryRecordEventService;
+use super::services::{InventoryRecordError, InventoryRecordFilter, InventoryRecordService};
+
+/// Most duplicates one merge request may name.
+pub const MAX_MERGE_DUPLICATES: usize = 100;
+
+
+/// RESPONSE SCHEMAS ///
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct InventoryRecordEventResponse {
+    pub uuid: String,
+    pub inventory_record_id: i64,
+    pub connection_id: i64,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub external_code: Option<String>,
+    /// Price in cents.
+    pub price: Option<i32>,
+    #[schema(value_type = Option<String>)]
+    pub currency: Option<Currency>,
+    /// Quantity in whole units; fractions are truncated.
+    pub qty: Option<i32>,
+    /// Quantity in thousandths, e.g. 10500 for 10.5; exact where `qty` truncates.
+    pub qty_milli: Option<i64>,
+    #[schema(value_type = Object)]
+    pub attributes: Option<Value>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct InventoryRecordResponse {
+    pub uuid: String,
+    pub tenant_id: i64,
+    pub originating_connection_id: i64,
+    #[schema(value_type = String)]
+    pub system_id_key: SystemIdKey,
+    pub system_id: String,
+    #[schema(value_type = Object)]
+    pub original_record_body: Option<Value>,
+    /// Most recent event of the record; null before the first sync wrote one.
+    pub latest_event: Option<InventoryRecordEventResponse>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MergeInventoryRecordsResponse {
+    /// The primary record after the merge, with its latest event.
+    pub record: InventoryRecordResponse,
+    /// Duplicates merged into it; they are soft-deleted.
+    pub merged_uuids: Vec<String>,
+    /// Events moved from the duplicates to the primary.
+    pub events_moved: u64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct PaginatedInventoryRecordsResponse {
+    pub items: Vec<InventoryRecordResponse>,
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+}
+
+
+/// REQUEST SCHEMAS ///
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ListInventoryRecordsQuery {
+    #[param(default = 1)]
+    pub page: Option<u64>,
+    #[param(default = 20)]
+    pub per_page: Option<u64>,
+    pub originating_connection_id: Option<i64>,
+    /// `qbd`, `qbo`, `sapo` or `sfdc`.
+    pub system_id_key: Option<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct MergeInventoryRecordsRequest {
+    /// Record that keeps its UUID and receives the duplicates' events.
+    #[schema(value_type = String)]
+    pub primary_uuid: Uuid,
+    /// Records to merge into the primary; at most 100.
+    #[schema(value_type = Vec<String>)]
+    pub duplicate_uuids: Vec<Uuid>,
+}
+
+
+/// VALIDATION ///
+impl ListInventoryRecordsQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        self.parse().err().unwrap_or_default()
+    }
+
+    ///validates and resolves enum strings in one pass
+    pub fn parse(&self) -> Result<InventoryRecordFilter, Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        let filter = InventoryRecordFilter {
+            tenant_id: None,
+            originating_connection_id: self.originating_connection_id,
+            system_id_key: parse_optional_enum(&mut errors, "system_id_key", self.system_id_key.as_deref()),
+        };
+        if self.per_page == Some(0) {
+            errors.push(FieldError::new("per_page", "must be greater than 0"));
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        Ok(filter)
+    }
+}
+
+impl MergeInventoryRecordsRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.duplicate_uuids.is_empty() || self.duplicate_uuids.len() > MAX_MERGE_DUPLICATES {
+            errors.push(FieldError::new(
+                "duplicate_uuids",
+                format!("must hold between 1 and {} UUIDs", MAX_MERGE_DUPLICATES),
+            ));
+        }
+        if self.duplicate_uuids.contains(&self.primary_uuid) {
+            errors.push(FieldError::new("duplicate_uuids", "must not contain primary_uuid"));
+        }
+        errors
+    }
+}
+
+
+/// HELPER FUNCTIONS ///
+pub fn event_to_response(model: inventory_record_event::Model) -> InventoryRecordEventResponse {
+    InventoryRecordEventResponse {
+        uuid: model.uuid.to_string(),
+        inventory_record_id: model.inventory_record_id,
+        connection_id: model.connection_id,
+        name: model.name,
+        description: model.description,
+        external_code: model.external_code,
+        price: model.price,
+        currency: model.currency,
+        qty: model.qty,
+        qty_milli: model.qty_milli,
+        attributes: model.attributes,
+        created_at: model.created_at.to_rfc3339(),
+        updated_at: model.updated_at.to_rfc3339(),
+    }
+}
+
+pub fn model_to_response(
+    model: inventory_record::Model,
+    latest_event: Option<inventory_record_event::Model>,
+) -> InventoryRecordResponse {
+    InventoryRecordResponse {
+        uuid: model.uuid.to_string(),
+        tenant_id: model.tenant_id,
+        originating_connection_id: model.originating_connection_id,
+        system_id_key: model.system_id_key,
+        system_id: model.system_id,
+        original_record_body: model.original_record_body,
+        latest_event: latest_event.map(event_to_response),
+        created_at: model.created_at.to_rfc3339(),
+        updated_at: model.updated_at.to_rfc3339(),
+    }
+}
+
+///ETag of a record as served by `GET /inventory-records/{uuid}`: changes when the record
+///or its latest event is updated, or a newer event replaces it
+pub fn record_etag(
+    record: &inventory_record::Model,
+    latest_event: Option<&inventory_record_event::Model>,
+) -> String {
+    etag_from_timestamps(&[
+        Some(&record.updated_at),
+        latest_event.map(|e| &e.updated_at),
+    ])
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.into(),
+            details: None,
+        }),
+    )
+}
+
+fn not_found() -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::NOT_FOUND, "Inventory record not found")
+}
+
+fn db_error(e: impl std::fmt::Display) -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))
+}
+
+
+/// ROUTE HANDLERS ///
+
+#[utoipa::path(
+    get,
+    path = "/inventory-records",
+    tag = "Inventory",
+    params(ListInventoryRecordsQuery),
+    responses(
+        (status = 200, description = "Inventory records, newest first, each with its latest event", body = PaginatedInventoryRecordsResponse,
+            headers(
+                ("X-Total-Count" = u64, description = "Total matching records"),
+                ("X-Page" = u64, description = "Current page"),
+                ("X-Per-Page" = u64, description = "Page size"),
+                ("X-Total-Pages" = u64, description = "Number of pages"),
+                ("Link" = String, description = "RFC 5988 first/prev/next/last page links")
+            )),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn list_inventory_records(
+    State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    Query(query): Query<ListInventoryRecordsQuery>,
+) -> Result<(HeaderMap, Json<PaginatedInventoryRecordsResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let mut filter = query.parse().map_err(ErrorResponse::validation)?;
+    //a tenant-scoped API token only sees its own tenant's records
+    filter.tenant_id = tenant.and_then(|Extension(AuthenticatedTenant(tenant_id))| tenant_id);
+
+    let page = query.page.unwrap_or(1);
+    let per_page = query.per_page.unwrap_or(20);
+
+    let result = InventoryRecordService::new(state.db.clone())
+        .get_all(page, per_page, Some(filter), None)
+        .await
+        .map_err(db_error)?;
+
+    //latest events of the whole page in one query
+    let ids: Vec<i64> = result.items.iter().map(|r| r.id).collect();
+    let mut latest = InventoryRecordEventService::new(state.db)
+        .latest_for_records(&ids, None)
+        .await
+        .map_err(db_error)?;
+
+    let headers = paginated_response(
+        &PageInfo {
+            page: result.page,
+            per_page: result.per_page,
+            total: result.total,
+            total_pages: result.total_pages,
+        },
+        &uri,
+    );
+    Ok((
+        headers,
+        Json(PaginatedInventoryRecordsResponse {
+            items: result
+                .items
+                .into_iter()
+                .map(|record| {
+                    let event = latest.remove(&record.id);
+                    model_to_response(record, event)
+                })
+                .collect(),
+            total: result.total,
+            page: result.page,
+            per_page: result.per_page,
+            total_pages: result.total_pages,
+        }),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/inventory-records/{uuid}",
+    tag = "Inventory",
+    params(
+        ("uuid" = String, Path, description = "Inventory record UUID"),
+        ("If-None-Match" = Option<String>, Header, description = "ETag from a previous response")
+    ),
+    responses(
+        (status = 200, description = "Inventory record with its latest event; carries an ETag header", body = InventoryRecordResponse),
+        (status = 304, description = "Not modified since the ETag in If-None-Match"),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Inventory record not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn get_inventory_record(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    PathUuid(uuid): PathUuid,
+    headers: HeaderMap,
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    //another tenant's record is not found, before any ETag could reveal it exists
+    let tenant_id = tenant.and_then(|Extension(AuthenticatedTenant(tenant_id))| tenant_id);
+    let record = match InventoryRecordService::new(state.db.clone())
+        .get_by_uuid(uuid, None)
+        .await
+    {
+        Ok(Some(record))
+            if record.deleted_at.is_none()
+                && tenant_id.is_none_or(|tenant_id| record.tenant_id == tenant_id) =>
+        {
+            record
+        }
+        Ok(_) => return Err(not_found()),
+        Err(e) => return Err(db_error(e)),
+    };
+
+    let latest_event = InventoryRecordEventService::new(state.db)
+        .get_latest_by_inventory_record_id(record.id, None)
+        .await
+        .map_err(db_error)?;
+
+    let etag = record_etag(&record, latest_event.as_ref());
+    Ok(conditional_response(
+        &headers,
+        &etag,
+        Json(model_to_response(record, latest_event)),
+    ))
+}
+
+#[utoipa::path(
+    post,
+    path = "/inventory-records/merge",
+    tag = "Inventory",
+    request_body = MergeInventoryRecordsRequest,
+    responses(
+        (status = 200, description = "Duplicates merged into the primary record", body = MergeInventoryRecordsResponse),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "A record was not found, is deleted or belongs to another tenant", body = ErrorResponse),
+        (status = 422, description = "The records belong to different tenants", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn merge_inventory_records(
+    State(state): State<AppState>,
+    tenant: Option<Extension<AuthenticatedTenant>>,
+    Json(body): Json<MergeInventoryRecordsRequest>,
+) -> Result<Json<MergeInventoryRecordsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let errors = body.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+    //a tenant-scoped API token only merges its own tenant's records
+    let tenant_id = tenant.and_then(|Extension(AuthenticatedTenant(tenant_id))| tenant_id);
+
+    let merged = match InventoryRecordService::new(state.db.clone())
+        .merge(body.primary_uuid, &body.duplicate_uuids, tenant_id)
+        .await
+    {
+        Ok(merged) => merged,
+        Err(InventoryRecordError::NotFound) => return Err(not_found()),
+        Err(InventoryRecordError::InvalidMerge(message)) => {
+            return Err(error(StatusCode::UNPROCESSABLE_ENTITY, message));
+        }
+        Err(InventoryRecordError::Db(e)) => return Err(db_error(e)),
+    };
+
+    let latest_event = InventoryRecordEventService::new(state.db)
+        .get_latest_by_inventory_record_id(merged.record.id, None)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(MergeInventoryRecordsResponse {
+        record: model_to_response(merged.record, latest_event),
+        merged_uuids: merged.merged_uuids.iter().map(Uuid::to_string).collect(),
+        events_moved: merged.events_moved,
+    }))
+}
+
+
+/// ROUTER ///
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_inventory_records))
+        .route("/merge", post(merge_inventory_records))
+        .route("/{uuid}", get(get_inventory_record))
+}
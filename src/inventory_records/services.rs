@@ -1,10 +1,12 @@
-//! CRUD services for inventory_record (no routes).
+//! CRUD services for inventory_record, and merging duplicate records.
 
-use entity::inventory_record;
+use entity::{inventory_record, inventory_record_event};
 use entity::sea_orm_active_enums::SystemIdKey;
+use sea_orm::sea_query::Expr;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
-    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
+    EntityTrait, Iterable, ModelTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
+    TransactionTrait,
 };
 use uuid::Uuid;
 
@@ -13,6 +15,8 @@ use uuid::Uuid;
 #[derive(Debug)]
 pub enum InventoryRecordError {
     NotFound,
+    /// The merge can't be done as asked, e.g. the records belong to different tenants.
+    InvalidMerge(String),
     Db(DbErr),
 }
 
@@ -55,6 +59,16 @@ pub struct InventoryRecordFilter {
     pub system_id_key: Option<SystemIdKey>,
 }
 
+/// Result of `merge`.
+pub struct MergedInventoryRecords {
+    /// The primary record after the merge.
+    pub record: inventory_record::Model,
+    /// UUIDs of the duplicates, now soft-deleted.
+    pub merged_uuids: Vec<Uuid>,
+    /// Events moved from the duplicates to the primary.
+    pub events_moved: u64,
+}
+
 #[allow(dead_code)]
 pub struct PaginatedInventoryRecords {
     pub items: Vec<inventory_record::Model>,
@@ -81,6 +95,7 @@ impl InventoryRecordService {
             SystemIdKey::Qbd => "QBD",
             SystemIdKey::Qbo => "QBO",
             SystemIdKey::Sapo => "SAPO",
+            SystemIdKey::Sfdc => "SFDC",
         };
         format!("{}:{}", prefix, system_id)
     }
@@ -134,7 +149,7 @@ impl InventoryRecordService {
         filter: Option<InventoryRecordFilter>,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<PaginatedInventoryRecords, DbErr> {
-        let mut condition = Condition::all();
+        let mut condition = Condition::all().add(inventory_record::Column::DeletedAt.is_null());
         if let Some(f) = filter {
             if let Some(tenant_id) = f.tenant_id {
                 condition = condition.add(inventory_record::Column::TenantId.eq(tenant_id));
@@ -291,6 +306,93 @@ impl InventoryRecordService {
         };
         self.delete_by_id(model.id, txn).await
     }
+
+    ///merges duplicates of one item into `primary_uuid`, in one transaction: every event of
+    ///the duplicates is re-pointed to the primary (history kept as is), every column the primary
+    ///has no value for is copied from the first duplicate that has one, and the duplicates are
+    ///soft-deleted with `merged_into_id` set. With `tenant_id`, records of other tenants are
+    ///`NotFound`; all records must belong to the same tenant either way
+    pub async fn merge(
+        &self,
+        primary_uuid: Uuid,
+        duplicate_uuids: &[Uuid],
+        tenant_id: Option<i64>,
+    ) -> Result<MergedInventoryRecords, InventoryRecordError> {
+        if duplicate_uuids.contains(&primary_uuid) {
+            return Err(InventoryRecordError::InvalidMerge(
+                "A record can't be merged into itself".to_string(),
+            ));
+        }
+
+        let txn = self.db.begin().await?;
+        let mut uuids = duplicate_uuids.to_vec();
+        uuids.push(primary_uuid);
+        let mut query = inventory_record::Entity::find()
+            .filter(inventory_record::Column::Uuid.is_in(uuids))
+            .filter(inventory_record::Column::DeletedAt.is_null());
+        if let Some(tenant_id) = tenant_id {
+            query = query.filter(inventory_record::Column::TenantId.eq(tenant_id));
+        }
+        let mut records = query.order_by_asc(inventory_record::Column::Id).all(&txn).await?;
+
+        let Some(primary_pos) = records.iter().position(|r| r.uuid == primary_uuid) else {
+            return Err(InventoryRecordError::NotFound);
+        };
+        let primary = records.remove(primary_pos);
+        let duplicates = records;
+        if duplicate_uuids.iter().any(|uuid| !duplicates.iter().any(|d| d.uuid == *uuid)) {
+            return Err(InventoryRecordError::NotFound);
+        }
+        if duplicates.iter().any(|d| d.tenant_id != primary.tenant_id) {
+            return Err(InventoryRecordError::InvalidMerge(
+                "Records of different tenants can't be merged".to_string(),
+            ));
+        }
+
+        let duplicate_ids: Vec<i64> = duplicates.iter().map(|d| d.id).collect();
+        let moved = inventory_record_event::Entity::update_many()
+            .col_expr(inventory_record_event::Column::InventoryRecordId, Expr::value(primary.id))
+            .filter(inventory_record_event::Column::InventoryRecordId.is_in(duplicate_ids.clone()))
+            .exec(&txn)
+            .await?;
+
+        let now: sea_orm::prelude::DateTimeWithTimeZone = chrono::Utc::now().into();
+        inventory_record::Entity::update_many()
+            .col_expr(inventory_record::Column::DeletedAt, Expr::value(now))
+            .col_expr(inventory_record::Column::MergedIntoId, Expr::value(primary.id))
+            .col_expr(inventory_record::Column::UpdatedAt, Expr::value(now))
+            .filter(inventory_record::Column::Id.is_in(duplicate_ids))
+            .exec(&txn)
+            .await?;
+
+        let mut active: inventory_record::ActiveModel = primary.clone().into();
+        for column in inventory_record::Column::iter() {
+            //the soft-delete columns describe the primary itself
+            if matches!(
+                column,
+                inventory_record::Column::DeletedAt | inventory_record::Column::MergedIntoId
+            ) {
+                continue;
+            }
+            let value = primary.get(column);
+            if value != value.as_null() {
+                continue;
+            }
+            let filled = duplicates.iter().map(|d| d.get(column)).find(|v| *v != v.as_null());
+            if let Some(filled) = filled {
+                active.set(column, filled);
+            }
+        }
+        active.updated_at = Set(now);
+        let record = active.update(&txn).await?;
+
+        txn.commit().await?;
+        Ok(MergedInventoryRecords {
+            record,
+            merged_uuids: duplicates.into_iter().map(|d| d.uuid).collect(),
+            events_moved: moved.rows_affected,
+        })
+    }
 }
 
 // END IMPLEMENTATION
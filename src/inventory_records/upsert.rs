@@ -0,0 +1,281 @@
+//! Provider-agnostic create-or-update of inventory items.
+//!
+//! An item is matched to its `inventory_record` by `system_id_key` + `system_id` among the
+//! connection's records. A new item gets a record and a first event. A known item whose fields
+//! differ from its latest event gets a new event (earlier events are never overwritten) and
+//! its record's `original_record_body` follows the new payload. An unchanged item writes
+//! nothing. The QuickBooks Desktop pull and the batch endpoint both upsert through here.
+
+use std::collections::HashMap;
+
+use entity::sea_orm_active_enums::{Currency, ErpProvider, ErpProviderType, SystemIdKey};
+use entity::{connection_identity, inventory_record, inventory_record_event};
+use sea_orm::sea_query::{Expr, Func, Query};
+use sea_orm::{
+    ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait, QueryFilter,
+    QueryOrder,
+};
+use serde_json::Value;
+
+use super::events_services::{CreateInventoryRecordEvent, InventoryRecordEventService};
+use super::services::{
+    CreateInventoryRecord, InventoryRecordError, InventoryRecordService, UpdateInventoryRecord,
+};
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// One item as a source reports it, already mapped to inventory fields.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InventoryItem {
+    pub system_id_key: SystemIdKey,
+    pub system_id: String,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// Price in cents.
+    pub price: Option<i32>,
+    pub currency: Option<Currency>,
+    pub qty: Option<i32>,
+    pub qty_milli: Option<i64>,
+    pub external_code: Option<String>,
+    pub attributes: Option<Value>,
+    /// Payload stored as `original_record_body`.
+    pub raw: Value,
+}
+
+/// A record already stored for an item, with its latest event on the connection.
+#[derive(Debug, Clone)]
+pub struct ExistingRecord {
+    pub record: inventory_record::Model,
+    pub event: Option<inventory_record_event::Model>,
+}
+
+/// What `upsert_inventory_item` did with an item.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Created,
+    Updated,
+    Unchanged,
+}
+
+/// Result of `upsert_inventory_item`.
+#[derive(Debug, Clone)]
+pub struct UpsertedItem {
+    pub record: inventory_record::Model,
+    /// The event written for the item; None when it was unchanged.
+    pub event: Option<inventory_record_event::Model>,
+    pub outcome: UpsertOutcome,
+}
+
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+///the key records of `conn` are stored under; None for QuickBooks Desktop, whose records
+///only come from the Web Connector, and for providers without a key
+pub fn system_id_key_for(conn: &connection_identity::Model) -> Option<SystemIdKey> {
+    match (&conn.erp_provider, &conn.erp_type) {
+        (ErpProvider::Quickbooks, ErpProviderType::Desktop) => None,
+        (ErpProvider::Quickbooks, _) => Some(SystemIdKey::Qbo),
+        (ErpProvider::Sap, _) => Some(SystemIdKey::Sapo),
+        (ErpProvider::Salesforce, _) => Some(SystemIdKey::Sfdc),
+        (ErpProvider::Dmsi, _) => None,
+    }
+}
+
+///the connection's records among `system_ids` (under `system_id_key`), by system_id, each
+///with its newest event on the connection. A merged duplicate stands for the record it was
+///merged into, so its item keeps writing to that primary; other deleted records are left out
+pub async fn existing_records(
+    conn: &connection_identity::Model,
+    system_id_key: SystemIdKey,
+    system_ids: &[&str],
+    txn: &DatabaseTransaction,
+) -> Result<HashMap<String, ExistingRecord>, DbErr> {
+    let found = inventory_record::Entity::find()
+        .filter(inventory_record::Column::SystemIdKey.eq(system_id_key))
+        .filter(inventory_record::Column::SystemId.is_in(system_ids.iter().copied()))
+        .filter(inventory_record::Column::OriginatingConnectionId.eq(conn.id))
+        .order_by_asc(inventory_record::Column::Id)
+        .all(txn)
+        .await?;
+
+    let mut records: HashMap<String, inventory_record::Model> = HashMap::new();
+    let mut merged: HashMap<String, i64> = HashMap::new();
+    for record in found {
+        match (record.deleted_at, record.merged_into_id) {
+            (None, _) => {
+                records.entry(record.system_id.clone()).or_insert(record);
+            }
+            (Some(_), Some(primary_id)) => {
+                merged.insert(record.system_id.clone(), primary_id);
+            }
+            (Some(_), None) => {}
+        }
+    }
+    //a live record under the system_id wins; otherwise the merge chain is followed to the
+    //primary, which may itself have been merged since. Merging only into live records
+    //keeps the chain acyclic
+    merged.retain(|system_id, _| !records.contains_key(system_id));
+    while !merged.is_empty() {
+        let primaries: HashMap<i64, inventory_record::Model> = inventory_record::Entity::find()
+            .filter(inventory_record::Column::Id.is_in(merged.values().copied()))
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(|primary| (primary.id, primary))
+            .collect();
+        let mut next = HashMap::new();
+        for (system_id, primary_id) in merged {
+            match primaries.get(&primary_id) {
+                Some(primary) if primary.deleted_at.is_none() => {
+                    records.insert(system_id, primary.clone());
+                }
+                Some(primary) => {
+                    if let Some(id) = primary.merged_into_id {
+                        next.insert(system_id, id);
+                    }
+                }
+                None => {}
+            }
+        }
+        merged = next;
+    }
+
+    let (system_ids, records): (Vec<String>, Vec<inventory_record::Model>) =
+        records.into_iter().unzip();
+    Ok(system_ids
+        .into_iter()
+        .zip(with_latest_events(conn, records, txn).await?)
+        .collect())
+}
+
+///pairs each of `records` with its newest event on `conn` (if any)
+pub async fn with_latest_events(
+    conn: &connection_identity::Model,
+    records: Vec<inventory_record::Model>,
+    txn: &DatabaseTransaction,
+) -> Result<Vec<ExistingRecord>, DbErr> {
+    if records.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    //only the newest event of each record is compared against
+    let latest_ids = Query::select()
+        .expr(Func::max(Expr::col(inventory_record_event::Column::Id)))
+        .from(inventory_record_event::Entity)
+        .and_where(
+            inventory_record_event::Column::InventoryRecordId.is_in(records.iter().map(|r| r.id)),
+        )
+        .and_where(inventory_record_event::Column::ConnectionId.eq(conn.id))
+        .group_by_col(inventory_record_event::Column::InventoryRecordId)
+        .to_owned();
+    let latest: HashMap<i64, inventory_record_event::Model> =
+        inventory_record_event::Entity::find()
+            .filter(inventory_record_event::Column::Id.in_subquery(latest_ids))
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(|event| (event.inventory_record_id, event))
+            .collect();
+
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            //two merged system_ids can resolve to the same primary
+            let event = latest.get(&record.id).cloned();
+            ExistingRecord { record, event }
+        })
+        .collect())
+}
+
+///whether `event` already holds every field of `item`
+pub fn event_matches(event: &inventory_record_event::Model, item: &InventoryItem) -> bool {
+    event.name == item.name
+        && event.description == item.description
+        && event.external_code == item.external_code
+        && event.price == item.price
+        && event.currency == item.currency
+        && event.qty == item.qty
+        && event.qty_milli == item.qty_milli
+        && event.attributes == item.attributes
+}
+
+///creates or updates one item of `conn`; `existing` is its entry from `existing_records`
+pub async fn upsert_inventory_item(
+    db: &DatabaseConnection,
+    conn: &connection_identity::Model,
+    item: &InventoryItem,
+    existing: Option<&ExistingRecord>,
+    txn: &DatabaseTransaction,
+) -> Result<UpsertedItem, DbErr> {
+    let records = InventoryRecordService::new(db.clone());
+    let record = match existing.map(|e| e.record.clone()) {
+        Some(r) if r.original_record_body.as_ref() == Some(&item.raw) => r,
+        Some(r) => {
+            let patch = UpdateInventoryRecord {
+                original_record_body: Some(item.raw.clone()),
+                system_id_key: None,
+                system_id: None,
+            };
+            records
+                .update_by_id(r.id, patch, Some(txn))
+                .await
+                .map_err(|e| match e {
+                    InventoryRecordError::Db(e) => e,
+                    //the record was read in this transaction, so it can't be missing
+                    _ => DbErr::RecordNotUpdated,
+                })?
+                .unwrap_or(r)
+        }
+        None => {
+            records
+                .create(
+                    CreateInventoryRecord {
+                        tenant_id: conn.tenant_id,
+                        originating_connection_id: conn.id,
+                        original_record_body: Some(item.raw.clone()),
+                        system_id_key: item.system_id_key.clone(),
+                        system_id: item.system_id.clone(),
+                    },
+                    Some(txn),
+                )
+                .await?
+        }
+    };
+
+    let latest = existing.and_then(|e| e.event.as_ref());
+    if latest.is_some_and(|event| event_matches(event, item)) {
+        return Ok(UpsertedItem {
+            record,
+            event: None,
+            outcome: UpsertOutcome::Unchanged,
+        });
+    }
+
+    let event = InventoryRecordEventService::new(db.clone())
+        .create(
+            CreateInventoryRecordEvent {
+                inventory_record_id: record.id,
+                connection_id: conn.id,
+                original_record_body: Some(item.raw.clone()),
+                price: item.price,
+                currency: item.currency.clone(),
+                name: item.name.clone(),
+                description: item.description.clone(),
+                attributes: item.attributes.clone(),
+                qty: item.qty,
+                external_code: item.external_code.clone(),
+                qty_milli: item.qty_milli,
+            },
+            Some(txn),
+        )
+        .await?;
+
+    let outcome = match existing {
+        Some(_) => UpsertOutcome::Updated,
+        None => UpsertOutcome::Created,
+    };
+    Ok(UpsertedItem {
+        record,
+        event: Some(event),
+        outcome,
+    })
+}
@@ -0,0 +1,7 @@
+pub mod queue;
+pub mod worker;
+
+pub use queue::{
+    FailedJob, Job, JobEnvelope, JobError, JobQueue, JobStore, ReservedJob, DEFAULT_MAX_ATTEMPTS,
+};
+pub use worker::{JobHandlers, JobOutcome, JobWorker};
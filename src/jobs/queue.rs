@@ -0,0 +1,350 @@
+//! Redis-backed job queue.
+//!
+//! Jobs live in three lists under `KEY_PREFIX`:
+//!   - `queue`: waiting jobs; `enqueue` pushes on the left, workers take from the right
+//!   - `processing`: jobs a worker reserved; `BRPOPLPUSH` moves a job here in one step, so a
+//!     job is never only in the worker's memory
+//!   - `dead`: jobs that failed `max_attempts` times, or that no worker can run
+//!
+//! A finished job is removed from `processing`; a failed one is moved back to `queue` (or to
+//! `dead`) with its attempt count and last error. Delivery is at-least-once: a job reserved by
+//! a worker that dies stays in `processing` until `requeue_processing` puts it back.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+const KEY_PREFIX: &str = "jobs:";
+
+/// Attempts before a failing job is moved to the dead-letter list.
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+
+
+//DEBUG AND ERRORS ///
+#[derive(Debug)]
+pub enum JobError {
+    Redis(redis::RedisError),
+    Serialize(serde_json::Error),
+}
+
+impl From<redis::RedisError> for JobError {
+    fn from(err: redis::RedisError) -> Self {
+        JobError::Redis(err)
+    }
+}
+
+impl From<serde_json::Error> for JobError {
+    fn from(err: serde_json::Error) -> Self {
+        JobError::Serialize(err)
+    }
+}
+
+impl std::fmt::Display for JobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobError::Redis(e) => write!(f, "Redis error: {}", e),
+            JobError::Serialize(e) => write!(f, "Job serialization error: {}", e),
+        }
+    }
+}
+
+//END DEBUG AND ERRORS
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// A typed job, stored as JSON under its `KIND`.
+pub trait Job: Serialize + DeserializeOwned + Send + 'static {
+    /// Name the job is stored and dispatched under; unique per job type.
+    const KIND: &'static str;
+}
+
+/// A job as stored in the lists.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JobEnvelope {
+    pub id: Uuid,
+    pub kind: String,
+    pub payload: Value,
+    /// Failed attempts so far.
+    pub attempts: u32,
+    pub enqueued_at: DateTime<Utc>,
+    /// Error of the last failed attempt.
+    pub last_error: Option<String>,
+}
+
+/// A job taken from the queue; it stays in `processing` until completed or failed.
+#[derive(Debug, Clone)]
+pub struct ReservedJob {
+    /// The stored JSON, which identifies the entry in `processing`.
+    raw: String,
+    pub envelope: JobEnvelope,
+}
+
+/// Where a failed job went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailedJob {
+    /// Back in the queue for another attempt.
+    Retried,
+    DeadLettered,
+}
+
+#[derive(Clone)]
+pub enum JobStore {
+    /// Shared across instances. Workers need their own connection without a response
+    /// timeout (see `config::redis::connect_blocking`): `BRPOPLPUSH` blocks it.
+    Redis(ConnectionManager),
+    /// In-process only; for tests.
+    Local(Arc<LocalLists>),
+}
+
+/// In-process lists keyed like the Redis ones; `notify` wakes a waiting reserve.
+#[derive(Default)]
+pub struct LocalLists {
+    lists: Mutex<HashMap<String, VecDeque<String>>>,
+    notify: Notify,
+}
+
+#[derive(Clone)]
+pub struct JobQueue {
+    store: JobStore,
+    max_attempts: u32,
+}
+
+/// END STRUCTS AND ENUMS ///
+
+
+/// BEGUN IMPLEMENTATION ///
+impl JobStore {
+    pub fn local() -> Self {
+        JobStore::Local(Arc::new(LocalLists::default()))
+    }
+}
+
+impl LocalLists {
+    fn push_left(&self, key: &str, value: String) {
+        if let Ok(mut lists) = self.lists.lock() {
+            lists.entry(key.to_string()).or_default().push_front(value);
+        }
+    }
+
+    ///RPOPLPUSH: takes the rightmost entry of `src` and pushes it left onto `dst`
+    fn pop_right_push_left(&self, src: &str, dst: &str) -> Option<String> {
+        let mut lists = self.lists.lock().ok()?;
+        let value = lists.get_mut(src)?.pop_back()?;
+        lists.entry(dst.to_string()).or_default().push_front(value.clone());
+        Some(value)
+    }
+
+    ///LREM key 1 value
+    fn remove(&self, key: &str, value: &str) {
+        if let Ok(mut lists) = self.lists.lock()
+            && let Some(list) = lists.get_mut(key)
+            && let Some(pos) = list.iter().position(|v| v == value)
+        {
+            list.remove(pos);
+        }
+    }
+
+    fn range(&self, key: &str) -> Vec<String> {
+        self.lists
+            .lock()
+            .ok()
+            .and_then(|lists| lists.get(key).map(|list| list.iter().cloned().collect()))
+            .unwrap_or_default()
+    }
+}
+
+impl JobQueue {
+    pub fn new(store: JobStore) -> Self {
+        Self {
+            store,
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+        }
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    fn key(list: &str) -> String {
+        format!("{}{}", KEY_PREFIX, list)
+    }
+
+    ///adds `job` to the queue; returns its id
+    pub async fn enqueue<J: Job>(&self, job: &J) -> Result<Uuid, JobError> {
+        let envelope = JobEnvelope {
+            id: Uuid::new_v4(),
+            kind: J::KIND.to_string(),
+            payload: serde_json::to_value(job)?,
+            attempts: 0,
+            enqueued_at: Utc::now(),
+            last_error: None,
+        };
+        let raw = serde_json::to_string(&envelope)?;
+
+        match &self.store {
+            JobStore::Redis(redis) => {
+                let mut conn = redis.clone();
+                conn.lpush::<_, _, ()>(Self::key("queue"), raw).await?;
+            }
+            JobStore::Local(local) => {
+                local.push_left(&Self::key("queue"), raw);
+                local.notify.notify_one();
+            }
+        }
+        Ok(envelope.id)
+    }
+
+    ///moves the oldest job to `processing`, waiting up to `timeout` for one; None when the
+    ///queue stayed empty. An entry that isn't a job is moved to the dead-letter list
+    pub async fn reserve(&self, timeout: Duration) -> Result<Option<ReservedJob>, JobError> {
+        let queue = Self::key("queue");
+        let processing = Self::key("processing");
+
+        let raw = match &self.store {
+            JobStore::Redis(redis) => {
+                let mut conn = redis.clone();
+                conn.brpoplpush::<_, _, Option<String>>(&queue, &processing, timeout.as_secs_f64())
+                    .await?
+            }
+            JobStore::Local(local) => match local.pop_right_push_left(&queue, &processing) {
+                Some(raw) => Some(raw),
+                None => {
+                    let _ = tokio::time::timeout(timeout, local.notify.notified()).await;
+                    local.pop_right_push_left(&queue, &processing)
+                }
+            },
+        };
+        let Some(raw) = raw else {
+            return Ok(None);
+        };
+
+        match serde_json::from_str::<JobEnvelope>(&raw) {
+            Ok(envelope) => Ok(Some(ReservedJob { raw, envelope })),
+            Err(e) => {
+                tracing::error!(error = %e, "Unreadable job moved to the dead-letter list");
+                self.settle(&raw, Self::key("dead"), raw.clone()).await?;
+                Ok(None)
+            }
+        }
+    }
+
+    ///removes a finished job from `processing`
+    pub async fn complete(&self, job: &ReservedJob) -> Result<(), JobError> {
+        let processing = Self::key("processing");
+        match &self.store {
+            JobStore::Redis(redis) => {
+                let mut conn = redis.clone();
+                conn.lrem::<_, _, ()>(processing, 1, &job.raw).await?;
+            }
+            JobStore::Local(local) => local.remove(&processing, &job.raw),
+        }
+        Ok(())
+    }
+
+    ///records a failed attempt: the job goes back to the queue, or to the dead-letter list
+    ///once it has failed `max_attempts` times (or right away when `retry` is false)
+    pub async fn fail(
+        &self,
+        job: ReservedJob,
+        error: &str,
+        retry: bool,
+    ) -> Result<FailedJob, JobError> {
+        let mut envelope = job.envelope;
+        envelope.attempts += 1;
+        envelope.last_error = Some(error.to_string());
+
+        let outcome = match retry && envelope.attempts < self.max_attempts {
+            true => FailedJob::Retried,
+            false => FailedJob::DeadLettered,
+        };
+        let target = match outcome {
+            FailedJob::Retried => Self::key("queue"),
+            FailedJob::DeadLettered => Self::key("dead"),
+        };
+        self.settle(&job.raw, target, serde_json::to_string(&envelope)?)
+            .await?;
+        Ok(outcome)
+    }
+
+    ///removes `raw` from `processing` and pushes `value` onto `target`, atomically in Redis
+    async fn settle(&self, raw: &str, target: String, value: String) -> Result<(), JobError> {
+        let processing = Self::key("processing");
+        match &self.store {
+            JobStore::Redis(redis) => {
+                let mut conn = redis.clone();
+                redis::pipe()
+                    .atomic()
+                    .lrem(&processing, 1, raw)
+                    .ignore()
+                    .lpush(&target, value)
+                    .ignore()
+                    .query_async::<()>(&mut conn)
+                    .await?;
+            }
+            JobStore::Local(local) => {
+                local.remove(&processing, raw);
+                local.push_left(&target, value);
+                if target == Self::key("queue") {
+                    local.notify.notify_one();
+                }
+            }
+        }
+        Ok(())
+    }
+
+    ///moves every job left in `processing` back to the queue, e.g. after a crash; only safe
+    ///while no worker (of any instance) is running, or jobs in flight would run twice
+    pub async fn requeue_processing(&self) -> Result<usize, JobError> {
+        let queue = Self::key("queue");
+        let processing = Self::key("processing");
+        let mut moved = 0;
+        loop {
+            let raw = match &self.store {
+                JobStore::Redis(redis) => {
+                    let mut conn = redis.clone();
+                    conn.rpoplpush::<_, _, Option<String>>(&processing, &queue).await?
+                }
+                JobStore::Local(local) => local.pop_right_push_left(&processing, &queue),
+            };
+            match raw {
+                Some(_) => moved += 1,
+                None => return Ok(moved),
+            }
+        }
+    }
+
+    async fn list(&self, list: &str) -> Result<Vec<JobEnvelope>, JobError> {
+        let key = Self::key(list);
+        let raw = match &self.store {
+            JobStore::Redis(redis) => {
+                let mut conn = redis.clone();
+                conn.lrange::<_, Vec<String>>(key, 0, -1).await?
+            }
+            JobStore::Local(local) => local.range(&key),
+        };
+        Ok(raw
+            .iter()
+            .filter_map(|raw| serde_json::from_str(raw).ok())
+            .collect())
+    }
+
+    ///waiting jobs, newest first
+    pub async fn queued(&self) -> Result<Vec<JobEnvelope>, JobError> {
+        self.list("queue").await
+    }
+
+    ///dead-lettered jobs, newest first
+    pub async fn dead_letters(&self) -> Result<Vec<JobEnvelope>, JobError> {
+        self.list("dead").await
+    }
+}
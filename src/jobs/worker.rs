@@ -0,0 +1,140 @@
+//! Job workers.
+//!
+//! `JobHandlers` maps each job `KIND` to its handler; workers share one registry. Each worker
+//! loops on `run_once`: reserve a job (blocking up to `block` for one), run its handler, then
+//! complete or fail it on the queue. A job whose kind has no handler is dead-lettered right
+//! away, since retrying it on this build can't succeed.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+
+use super::queue::{FailedJob, Job, JobError, JobQueue};
+
+/// How long a worker waits for a job before checking again.
+pub const DEFAULT_BLOCK: Duration = Duration::from_secs(5);
+
+/// Pause after a queue error (e.g. Redis unreachable) before the worker retries.
+const ERROR_BACKOFF: Duration = Duration::from_secs(1);
+
+/// BEGUN STRUCTS AND ENUMS ///
+type Handler = dyn Fn(Value) -> BoxFuture<'static, Result<(), String>> + Send + Sync;
+
+#[derive(Default)]
+pub struct JobHandlers {
+    handlers: HashMap<&'static str, Arc<Handler>>,
+}
+
+pub struct JobWorker {
+    queue: JobQueue,
+    handlers: Arc<JobHandlers>,
+    block: Duration,
+}
+
+/// What `run_once` did with the job it reserved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JobOutcome {
+    Completed,
+    /// Failed; back in the queue.
+    Retried,
+    /// Failed for the last time, or has no handler.
+    DeadLettered,
+}
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl JobHandlers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///registers the handler for jobs of type `J`; an `Err` counts as a failed attempt
+    pub fn register<J, F, Fut>(mut self, handler: F) -> Self
+    where
+        J: Job,
+        F: Fn(J) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.handlers.insert(
+            J::KIND,
+            Arc::new(move |payload: Value| -> BoxFuture<'static, Result<(), String>> {
+                let handler = handler.clone();
+                Box::pin(async move {
+                    let job = serde_json::from_value::<J>(payload)
+                        .map_err(|e| format!("Invalid {} payload: {}", J::KIND, e))?;
+                    handler(job).await
+                })
+            }),
+        );
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.handlers.is_empty()
+    }
+}
+
+impl JobWorker {
+    pub fn new(queue: JobQueue, handlers: Arc<JobHandlers>) -> Self {
+        Self {
+            queue,
+            handlers,
+            block: DEFAULT_BLOCK,
+        }
+    }
+
+    pub fn with_block(mut self, block: Duration) -> Self {
+        self.block = block;
+        self
+    }
+
+    ///processes at most one job; None when none arrived within the block timeout
+    pub async fn run_once(&self) -> Result<Option<JobOutcome>, JobError> {
+        let Some(job) = self.queue.reserve(self.block).await? else {
+            return Ok(None);
+        };
+        let (id, kind) = (job.envelope.id, job.envelope.kind.clone());
+
+        let Some(handler) = self.handlers.handlers.get(kind.as_str()).cloned() else {
+            tracing::error!(job_id = %id, kind = %kind, "No handler for job kind; dead-lettered");
+            self.queue
+                .fail(job, &format!("No handler registered for {}", kind), false)
+                .await?;
+            return Ok(Some(JobOutcome::DeadLettered));
+        };
+
+        match handler(job.envelope.payload.clone()).await {
+            Ok(()) => {
+                self.queue.complete(&job).await?;
+                Ok(Some(JobOutcome::Completed))
+            }
+            Err(e) => match self.queue.fail(job, &e, true).await? {
+                FailedJob::Retried => {
+                    tracing::warn!(job_id = %id, kind = %kind, error = %e, "Job failed; retrying");
+                    Ok(Some(JobOutcome::Retried))
+                }
+                FailedJob::DeadLettered => {
+                    tracing::error!(job_id = %id, kind = %kind, error = %e, "Job failed; dead-lettered");
+                    Ok(Some(JobOutcome::DeadLettered))
+                }
+            },
+        }
+    }
+}
+
+///runs `worker` until the process exits
+pub fn spawn(worker: JobWorker) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            if let Err(e) = worker.run_once().await {
+                tracing::error!("Job worker failed to reach the queue: {}", e);
+                tokio::time::sleep(ERROR_BACKOFF).await;
+            }
+        }
+    })
+}
@@ -1,17 +1,30 @@
 mod admin;
+mod audit;
 mod auth;
+mod capabilities;
+mod clock;
 mod config;
 mod connection_identity;
 mod connection_run;
+mod db;
 mod erp_connection_credentials;
 mod erp_connection_sync_state;
+mod etag;
+mod events;
+mod idempotency;
 mod inventory_records;
+mod jobs;
 mod middleware;
 mod openapi;
+mod outbox;
+mod pagination;
+mod quarantine;
+mod sync;
 mod sync_event;
 mod routes;
 mod security;
 mod tenant;
+mod validation;
 
 #[path = "client-systems/mod.rs"]
 mod client_systems;
@@ -19,14 +32,15 @@ mod client_systems;
 use migration::MigratorTrait;
 use redis::aio::ConnectionManager;
 use sea_orm::DatabaseConnection;
-use tower_http::trace::TraceLayer;
-use tracing_subscriber;
+use std::net::SocketAddr;
+use tower_http::trace::{DefaultMakeSpan, TraceLayer};
 
 ///application state shared across all routes
 #[derive(Clone)]
 pub struct AppState {
     pub db: DatabaseConnection,
     pub redis: ConnectionManager,
+    pub events: events::EventBus,
 }
 
 #[tokio::main]
@@ -37,11 +51,15 @@ async fn main() {
     //initialize central config from environment variables
     config::env::init();
 
-    //initialize tracing
-    tracing_subscriber::fmt()
-        .with_target(false)
-        .compact()
-        .init();
+    //initialize tracing (spans are also exported over OTLP when OTEL_EXPORTER_OTLP_ENDPOINT is set)
+    let telemetry = &config::env::get().telemetry;
+    let _telemetry_guard =
+        config::init_tracing(
+            telemetry.otlp_endpoint.as_deref(),
+            &telemetry.service_name,
+            &config::env::get().server.rust_log,
+        )
+        .expect("Failed to initialize tracing");
 
     //initialize prometheus metrics
     config::init_metrics();
@@ -51,21 +69,122 @@ async fn main() {
         .await
         .expect("Failed to connect to database");
 
+    //log pool size/idle counts periodically (DB_POOL_HEALTH_LOG_SECS)
+    if let Some(interval) = config::env::get().db.pool_health_log_interval {
+        config::database::spawn_pool_health_logger(db.clone(), interval);
+    }
+
     //run pending migrations (idempotent; safe on every startup)
     migration::Migrator::up(&db, None)
         .await
         .expect("Failed to run migrations");
 
+    //create the default tenant of single-tenant deployments (DEFAULT_TENANT_ID)
+    if let Some(tenant_id) = &config::env::get().tenant.default_tenant_id {
+        let tenant = tenant::default::ensure_default_tenant(&db, tenant_id)
+            .await
+            .expect("Failed to create the default tenant");
+        tracing::info!(tenant_id = %tenant.tenant_id, "Default tenant ready");
+    }
+
     //connect to Redis
     let redis = config::redis_connect()
         .await
         .expect("Failed to connect to Redis");
 
-    let state = AppState { db, redis };
+    //in-process domain events (metrics, logs), published by the QBD poll
+    let events = events::EventBus::default();
+    events::subscribers::spawn_defaults(&events);
+
+    let state = AppState { db, redis, events };
+
+    //start the outbox relay (publishes committed outbox rows to Redis)
+    if config::env::get().outbox.relay_enabled {
+        tracing::info!("Outbox relay enabled");
+        outbox::relay::spawn(state.db.clone(), state.redis.clone());
+    } else {
+        tracing::info!("Outbox relay disabled");
+    }
+
+    //start the poll scheduler for API connections (Desktop connections are driven by QBWC)
+    let sync_config = &config::env::get().sync;
+    if sync_config.scheduler_enabled {
+        let scheduler = sync::PollScheduler::new(state.db.clone())
+            .with_jitter(sync_config.scheduler_jitter)
+            .with_adapter(std::sync::Arc::new(client_systems::sap::SapAdapter::new(
+                state.db.clone(),
+            )))
+            .with_adapter(std::sync::Arc::new(
+                client_systems::salesforce::SalesforceAdapter::new(state.db.clone()),
+            ));
+        if !scheduler.has_adapters() {
+            tracing::warn!("Poll scheduler enabled but no API pull adapters are registered");
+        }
+        tracing::info!("Poll scheduler enabled");
+        sync::scheduler::spawn(scheduler, sync_config.scheduler_tick);
+    } else {
+        tracing::info!("Poll scheduler disabled");
+    }
+
+    //cache Web Connector credential checks (CREDENTIAL_CACHE_SIZE / CREDENTIAL_CACHE_TTL_SECS)
+    let credentials_config = &config::env::get().credentials;
+    erp_connection_credentials::lookup_cache::init_shared(
+        credentials_config.lookup_cache_size,
+        credentials_config.lookup_cache_ttl,
+    );
+
+    //refresh credentials_expiring_total periodically (CREDENTIAL_EXPIRY_CHECK_SECS)
+    if let Some(interval) = credentials_config.expiry_check_interval {
+        erp_connection_credentials::expiry::spawn(
+            state.db.clone(),
+            interval,
+            credentials_config.expiry_window_days,
+        );
+    }
+
+    //start the background job workers, each on its own Redis connection since BRPOPLPUSH blocks it
+    let jobs_config = &config::env::get().jobs;
+    if jobs_config.workers > 0 {
+        let handlers = std::sync::Arc::new(jobs::JobHandlers::new());
+        if handlers.is_empty() {
+            tracing::warn!("Job workers enabled but no job handlers are registered");
+        }
+        for _ in 0..jobs_config.workers {
+            let redis = config::redis::connect_blocking()
+                .await
+                .expect("Failed to connect job worker to Redis");
+            let queue = jobs::JobQueue::new(jobs::JobStore::Redis(redis))
+                .with_max_attempts(jobs_config.max_attempts);
+            jobs::worker::spawn(
+                jobs::JobWorker::new(queue, handlers.clone()).with_block(jobs_config.block),
+            );
+        }
+        tracing::info!(workers = jobs_config.workers, "Job workers enabled");
+    } else {
+        tracing::info!("Job workers disabled");
+    }
+
+    //start in maintenance mode if MAINTENANCE_MODE is set (toggled later via PUT /admin/maintenance)
+    if config::env::get().server.maintenance_mode {
+        middleware::maintenance::set_maintenance_mode(true);
+        tracing::warn!("Starting in maintenance mode");
+    }
 
     //create application router with middleware
     let mut app = routes::create_router(state.clone());
 
+    //apply per-tenant rate limiting if enabled; layered first so it runs inside the API
+    //token middleware, which identifies the tenant
+    if config::env::get().rate_limit.enabled {
+        tracing::info!("Rate limiting middleware enabled");
+        app = app.layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::rate_limit_middleware,
+        ));
+    } else {
+        tracing::info!("Rate limiting middleware disabled");
+    }
+
     //apply API token authentication middleware if enabled
     if config::is_api_token_auth_enabled() {
         tracing::info!("API token authentication middleware enabled");
@@ -91,8 +210,22 @@ async fn main() {
     //apply other middleware
     app = app
         .layer(axum::middleware::from_fn(middleware::allowed_hosts_middleware))
+        //maintenance mode rejects before host checks and auth; inside CORS so the 503 is readable
+        .layer(axum::middleware::from_fn_with_state(
+            config::env::get().server.base_url.as_deref(),
+            middleware::maintenance_middleware,
+        ))
         .layer(middleware::cors_layer())
-        .layer(TraceLayer::new_for_http())
+        //origins registered per tenant; wraps the static layer, which stays the fallback
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::tenant_cors_middleware,
+        ))
+        //request spans at INFO so they pass the subscriber filter and reach the OTLP exporter
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(DefaultMakeSpan::new().level(tracing::Level::INFO)),
+        )
         .layer(axum::middleware::from_fn(middleware::request_logging_middleware))
         .layer(axum::middleware::from_fn(middleware::metrics_middleware));
 
@@ -107,7 +240,7 @@ async fn main() {
         .await
         .expect("Failed to bind to address");
 
-    axum::serve(listener, app.into_make_service())
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .expect("Server failed to start");
 }
@@ -0,0 +1,29 @@
+//operator routes guarded by ADMIN_TOKEN (admin_token_auth) instead of an API token, so the
+//first API token can be issued before any exist. Every router layered with
+//admin_token_auth_middleware alone must be listed here, or it also demands an API token
+
+const ADMIN_ROUTES: [&str; 8] = [
+    "/admin/api-tokens",
+    "/admin/allowed-origins",
+    "/admin/migrations",
+    "/admin/log-level",
+    "/admin/maintenance",
+    "/admin/audit-log",
+    "/admin/credentials",
+    "/admin/sync-events",
+];
+
+//operator actions on a single connection, e.g. /connections/{uuid}/purge
+const ADMIN_CONNECTION_ACTIONS: [&str; 2] = ["/delete-preview", "/purge"];
+
+///whether `path` is an admin token route, skipped by API token auth; `base_url` is stripped first
+pub fn is_admin_token_route(path: &str, base_url: Option<&str>) -> bool {
+    let effective_path = base_url
+        .and_then(|base| path.strip_prefix(base))
+        .unwrap_or(path);
+
+    ADMIN_ROUTES.iter().any(|route| {
+        effective_path == *route || effective_path.starts_with(&format!("{}/", route))
+    }) || (effective_path.starts_with("/connections/")
+        && ADMIN_CONNECTION_ACTIONS.iter().any(|action| effective_path.ends_with(action)))
+}
@@ -0,0 +1,42 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use crate::config;
+use crate::security::admin_token::{admin_token_matches, ADMIN_TOKEN_HEADER};
+
+//admin token middleware for operator routes (API token management, migration status)
+//checks the X-Admin-Token header against ADMIN_TOKEN (distinct from normal API tokens)
+//the routes are disabled (403) while ADMIN_TOKEN is unset
+pub async fn admin_token_auth_middleware(request: Request<Body>, next: Next) -> Response {
+    let Some(expected) = config::env::get().middleware.admin_token.as_deref() else {
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(Body::from("Forbidden: admin API is disabled (ADMIN_TOKEN is not set)"))
+            .unwrap();
+    };
+
+    let presented = request
+        .headers()
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|v| v.to_str().ok());
+
+    if !admin_token_matches(Some(expected), presented) {
+        tracing::error!(
+            severity = "CRITICAL",
+            event = "unauthorized_admin_token_attempt",
+            route = %request.uri().path(),
+            method = %request.method(),
+            token_present = presented.is_some(),
+            "Unauthorized admin API request"
+        );
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body(Body::from("Unauthorized: valid admin token required"))
+            .unwrap();
+    }
+
+    next.run(request).await
+}
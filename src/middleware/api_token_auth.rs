@@ -5,10 +5,15 @@ use axum::{
     middleware::Next,
     response::Response,
 };
-use axum::body::to_bytes;
 use crate::AppState;
 use crate::config;
-use crate::security::ApiTokenService;
+use crate::middleware::admin_routes::is_admin_token_route;
+use crate::middleware::body_capture::capture_body;
+use crate::security::connection_api_key::{is_connection_key, key_prefix};
+use crate::security::{
+    ApiTokenService, AuthenticatedConnection, AuthenticatedTenant, AuthenticatedToken,
+    ConnectionApiKeyService,
+};
 
 //extracts API token from request headers
 //checks Authorization header (Bearer token) and X-API-Key header
@@ -85,22 +90,6 @@ fn format_headers(headers: &HeaderMap) -> String {
         .join(", ")
 }
 
-//extracts request body for logging
-//note: This consumes the body, so we need to reconstruct it for the next handler
-async fn extract_body(body: Body) -> (String, Body) {
-    let body_bytes = match to_bytes(body, usize::MAX).await {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return ("[Error reading body]".to_string(), Body::empty());
-        }
-    };
-
-    let body_str = String::from_utf8_lossy(&body_bytes).to_string();
-    let new_body = Body::from(body_bytes);
-
-    (body_str, new_body)
-}
-
 //strips the base_url prefix from the path for route matching
 //e.g. with BASE_URL=/api, "/api/healthcheck" becomes "/healthcheck"
 fn strip_base_url<'a>(path: &'a str) -> &'a str {
@@ -119,6 +108,7 @@ fn is_api_token_public_route(path: &str) -> bool {
         "/",
         "/healthcheck",
         "/metrics",
+        "/admin/readyz",
         "/local/swagger-ui",
         "/api-doc/openapi.json"
     ];
@@ -128,6 +118,96 @@ fn is_api_token_public_route(path: &str) -> bool {
     })
 }
 
+//routes a connection API key may call: Some(Some(uuid)) for /connections/{uuid}/inventory/batch,
+//which must be the key's connection; Some(None) for the QBD poll, whose connection the poll
+//service checks against the key once the Web Connector credentials resolve it
+fn connection_key_route(path: &str) -> Option<Option<&str>> {
+    let effective_path = strip_base_url(path);
+
+    if ["/poll/v1/qbwc", "/poll/v1/qbwc/receive"].contains(&effective_path) {
+        return Some(None);
+    }
+    effective_path
+        .strip_prefix("/connections/")
+        .and_then(|rest| rest.strip_suffix("/inventory/batch"))
+        .filter(|uuid| !uuid.contains('/'))
+        .map(Some)
+}
+
+//authenticates a connection API key (see crate::security::connection_api_key): only
+//connection-scoped routes of the key's own connection are let through
+async fn connection_key_auth(
+    state: &AppState,
+    mut request: Request<Body>,
+    next: Next,
+    key: &str,
+) -> Response {
+    let authenticated = match ConnectionApiKeyService::new(state.db.clone())
+        .authenticate(key, None)
+        .await
+    {
+        Ok(authenticated) => authenticated,
+        Err(e) => {
+            tracing::error!(
+                error = %e,
+                "Database error while validating connection API key"
+            );
+            return Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::from("Internal server error"))
+                .unwrap();
+        }
+    };
+
+    let Some(authenticated) = authenticated else {
+        //only the prefix is logged: unlike a mistyped API token, a connection key is
+        //usually configured on a customer machine
+        tracing::error!(
+            severity = "CRITICAL",
+            event = "unauthorized_connection_api_key_attempt",
+            key_prefix = %key_prefix(key),
+            client_ip = %get_client_ip(&request),
+            route = %request.uri().path(),
+            method = %request.method(),
+            "Unauthorized connection API key attempt detected"
+        );
+        return Response::builder()
+            .status(StatusCode::UNAUTHORIZED)
+            .body(Body::from("Unauthorized: Invalid or revoked connection API key"))
+            .unwrap();
+    };
+
+    let allowed = match connection_key_route(request.uri().path()) {
+        Some(Some(uuid)) => uuid == authenticated.connection_uuid.to_string(),
+        Some(None) => true,
+        None => false,
+    };
+    if !allowed {
+        tracing::warn!(
+            event = "connection_api_key_out_of_scope",
+            connection_id = authenticated.connection_id,
+            key_id = authenticated.key_id,
+            route = %request.uri().path(),
+            method = %request.method(),
+            "Connection API key used outside its connection's routes"
+        );
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(Body::from(
+                "Forbidden: connection API keys only authorize their connection's routes",
+            ))
+            .unwrap();
+    }
+
+    let tenant = AuthenticatedTenant(Some(authenticated.tenant_id));
+    request.extensions_mut().insert(tenant);
+    request.extensions_mut().insert(authenticated);
+    let mut response = next.run(request).await;
+
+    response.extensions_mut().insert(tenant);
+    response
+}
+
 //API token authentication middleware
 //validates API tokens on every request and logs unauthorized attempts critically
 //skips authentication for public routes
@@ -138,8 +218,9 @@ pub async fn api_token_auth_middleware(
 ) -> Response {
     let path = request.uri().path();
 
-    //skip authentication for public routes
-    if is_api_token_public_route(path) {
+    //skip authentication for public routes and admin-token routes
+    let base_url = config::env::get().server.base_url.as_deref();
+    if is_api_token_public_route(path) || is_admin_token_route(path, base_url) {
         return next.run(request).await;
     }
     
@@ -159,9 +240,9 @@ pub async fn api_token_auth_middleware(
                 format!("{}?{}", route, query)
             };
 
-            //extract body for logging (this consumes it, but we'll return error anyway so it's fine)
+            //body for logging, only read when LOG_REQUEST_BODY is on (the request is rejected anyway)
             let body = std::mem::replace(request.body_mut(), Body::empty());
-            let (body_content, _) = extract_body(body).await;
+            let body_content = capture_body(body, config::env::get().middleware.log_request_body).await;
 
             //critical log with all security-relevant information
             tracing::error!(
@@ -181,10 +262,15 @@ pub async fn api_token_auth_middleware(
         }
     };
 
+    //connection API keys are checked against their own table and routes
+    if is_connection_key(&api_token) {
+        return connection_key_auth(&state, request, next, &api_token).await;
+    }
+
     //validate API token
     let api_token_service = ApiTokenService::new(state.db.clone());
-    let is_api_token_valid = match api_token_service.is_token_valid(&api_token, None).await {
-        Ok(valid) => valid,
+    let active_token = match api_token_service.get_active_by_token(&api_token, None).await {
+        Ok(token) => token,
         Err(e) => {
             //database error - log and reject
             tracing::error!(
@@ -198,7 +284,7 @@ pub async fn api_token_auth_middleware(
         }
     };
 
-    if !is_api_token_valid {
+    let Some(active_token) = active_token else {
         //API token is invalid - critically log all details
         let client_ip = get_client_ip(&request);
         let route = request.uri().path().to_string();
@@ -211,9 +297,9 @@ pub async fn api_token_auth_middleware(
             format!("{}?{}", route, query)
         };
 
-        //extract body for logging (this consumes it, but we'll return error anyway so it's fine)
+        //body for logging, only read when LOG_REQUEST_BODY is on (the request is rejected anyway)
         let body = std::mem::replace(request.body_mut(), Body::empty());
-        let (body_content, _) = extract_body(body).await;
+        let body_content = capture_body(body, config::env::get().middleware.log_request_body).await;
 
         //critical log with all security-relevant information
         tracing::error!(
@@ -232,8 +318,19 @@ pub async fn api_token_auth_middleware(
             .status(StatusCode::UNAUTHORIZED)
             .body(Body::from("Unauthorized: Invalid or inactive API token"))
             .unwrap();
-    }
+    };
+
+    //API token is valid - record its tenant for tenant-scoped middleware (rate limiting)
+    //and the token itself as the actor for the audit log, then proceed (body is still
+    //intact since we didn't extract it)
+    let tenant = AuthenticatedTenant(active_token.tenant_id);
+    request.extensions_mut().insert(tenant);
+    request
+        .extensions_mut()
+        .insert(AuthenticatedToken(active_token.id));
+    let mut response = next.run(request).await;
 
-    //API token is valid - proceed with request (body is still intact since we didn't extract it)
-    next.run(request).await
+    //also on the response for the tenant CORS middleware, which runs outside this one
+    response.extensions_mut().insert(tenant);
+    response
 }
@@ -0,0 +1,142 @@
+//! Request body capture for the auth middlewares' rejection logs.
+//!
+//! Capture is off unless `LOG_REQUEST_BODY` is set: the body is then never read. When on, at
+//! most `MAX_CAPTURED_BODY_BYTES` are read and the string values of secret-looking JSON keys
+//! are replaced with `[REDACTED]`.
+
+use axum::body::Body;
+use futures_util::StreamExt;
+
+///bytes of a rejected request's body kept for the log when capture is enabled
+pub const MAX_CAPTURED_BODY_BYTES: usize = 4096;
+
+///logged in place of the body when capture is disabled
+pub const BODY_NOT_CAPTURED: &str = "[not captured]";
+
+///appended to a body cut at `MAX_CAPTURED_BODY_BYTES`
+pub const TRUNCATED_MARKER: &str = "...[truncated]";
+
+pub const REDACTED: &str = "[REDACTED]";
+
+//JSON keys whose values are never logged (matched case-insensitively, as substrings)
+const SECRET_KEYS: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "token",
+    "api_key",
+    "apikey",
+    "authorization",
+    "private_key",
+    "credential",
+];
+
+///reads the body for logging; with `enabled` false the body is dropped unread. Only the first
+///`MAX_CAPTURED_BODY_BYTES` are read, so a large body is never buffered whole
+pub async fn capture_body(body: Body, enabled: bool) -> String {
+    if !enabled {
+        return BODY_NOT_CAPTURED.to_string();
+    }
+
+    let mut captured: Vec<u8> = Vec::new();
+    let mut truncated = false;
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        let Ok(chunk) = chunk else {
+            return "[Error reading body]".to_string();
+        };
+        let room = MAX_CAPTURED_BODY_BYTES - captured.len();
+        if chunk.len() > room {
+            captured.extend_from_slice(&chunk[..room]);
+            truncated = true;
+            break;
+        }
+        captured.extend_from_slice(&chunk);
+    }
+
+    let mut text = redact_secrets(&String::from_utf8_lossy(&captured));
+    if truncated {
+        text.push_str(TRUNCATED_MARKER);
+    }
+    text
+}
+
+fn is_secret_key(key: &str) -> bool {
+    let key = key.to_lowercase();
+    SECRET_KEYS.iter().any(|secret| key.contains(secret))
+}
+
+//index just past the JSON string starting at `start` (a '"'), or the end of `text` when the
+//string is cut off
+fn string_end(text: &str, start: usize) -> usize {
+    let bytes = text.as_bytes();
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+fn skip_whitespace(text: &str, mut i: usize) -> usize {
+    let bytes = text.as_bytes();
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    i
+}
+
+///replaces the values of secret-looking keys (`password`, `token`, `api_key`, ...) in a JSON
+///body with `[REDACTED]`; works on truncated or otherwise invalid JSON, and leaves text that
+///isn't JSON unchanged. Object and array values are left for their own keys to be checked
+pub fn redact_secrets(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] != b'"' {
+            i += 1;
+            continue;
+        }
+        let key_end = string_end(text, i);
+        let colon = skip_whitespace(text, key_end);
+        //a ':' after it means the string was terminated and is a key
+        if colon >= bytes.len() || bytes[colon] != b':' || !is_secret_key(&text[i + 1..key_end - 1]) {
+            i = key_end;
+            continue;
+        }
+
+        let value_start = skip_whitespace(text, colon + 1);
+        let value_end = match bytes.get(value_start) {
+            Some(b'"') => string_end(text, value_start),
+            Some(b'{') | Some(b'[') | None => {
+                i = value_start;
+                continue;
+            }
+            Some(_) => {
+                let mut end = value_start;
+                while end < bytes.len() && !matches!(bytes[end], b',' | b'}' | b']')
+                    && !bytes[end].is_ascii_whitespace()
+                {
+                    end += 1;
+                }
+                end
+            }
+        };
+
+        out.push_str(&text[copied..value_start]);
+        out.push('"');
+        out.push_str(REDACTED);
+        out.push('"');
+        copied = value_end;
+        i = value_end;
+    }
+
+    out.push_str(&text[copied..]);
+    out
+}
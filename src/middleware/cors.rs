@@ -1,20 +1,174 @@
-use axum::http::HeaderName;
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, HeaderName, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
 use tower_http::cors::{AllowOrigin, CorsLayer};
 
+use crate::AppState;
+use crate::idempotency::IDEMPOTENT_REPLAYED_HEADER;
+use crate::pagination::EXPOSED_HEADERS;
 use crate::config::cors::{get_allow_credentials, get_allowed_headers, get_allowed_methods, get_allowed_origins};
+use crate::security::{AllowedOriginService, AuthenticatedTenant};
 
-///creates a configured CORS layer
-pub fn cors_layer() -> CorsLayer {
-    let origins = get_allowed_origins();
+///headers browser clients may read: the pagination headers of list routes, ETags, the
+///Retry-After of rate-limited and maintenance responses and the replay marker of
+///idempotent creates
+fn exposed_headers() -> Vec<HeaderName> {
+    EXPOSED_HEADERS
+        .into_iter()
+        .chain([
+            header::ETAG,
+            header::RETRY_AFTER,
+            HeaderName::from_static(IDEMPOTENT_REPLAYED_HEADER),
+        ])
+        .collect()
+}
 
-    let headers: Vec<HeaderName> = get_allowed_headers()
+fn allowed_headers() -> Vec<HeaderName> {
+    get_allowed_headers()
         .iter()
         .filter_map(|header| header.parse().ok())
-        .collect();
+        .collect()
+}
+
+///creates a configured CORS layer
+pub fn cors_layer() -> CorsLayer {
+    let origins = get_allowed_origins();
 
     CorsLayer::new()
         .allow_origin(AllowOrigin::list(origins))
         .allow_methods(get_allowed_methods())
-        .allow_headers(headers)
+        .allow_headers(allowed_headers())
+        .expose_headers(exposed_headers())
         .allow_credentials(get_allow_credentials())
 }
+
+fn join<T: AsRef<str>>(values: impl IntoIterator<Item = T>) -> String {
+    values
+        .into_iter()
+        .map(|value| value.as_ref().to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn is_preflight(request: &Request<Body>) -> bool {
+    request.method() == Method::OPTIONS
+        && request
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD)
+}
+
+///answers a preflight for an origin a tenant registered, with the static layer's settings
+fn preflight_response(origin: HeaderValue) -> Response {
+    let mut response = Response::builder()
+        .status(StatusCode::OK)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin)
+        .header(
+            header::ACCESS_CONTROL_ALLOW_METHODS,
+            join(get_allowed_methods().iter().map(Method::as_str)),
+        )
+        .header(
+            header::ACCESS_CONTROL_ALLOW_HEADERS,
+            join(allowed_headers().iter().map(HeaderName::as_str)),
+        )
+        .header(
+            header::VARY,
+            "origin, access-control-request-method, access-control-request-headers",
+        );
+    if get_allow_credentials() {
+        response = response.header(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true");
+    }
+    response.body(Body::empty()).unwrap()
+}
+
+///adds the headers that let the browser read `response` from `origin`
+fn allow_response_origin(response: &mut Response, origin: HeaderValue) {
+    let headers = response.headers_mut();
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    if let Ok(exposed) = HeaderValue::from_str(&join(exposed_headers().iter().map(HeaderName::as_str))) {
+        headers.insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, exposed);
+    }
+    if get_allow_credentials() {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+}
+
+///marks `response` as depending on the request's Origin, so a cache never serves one origin's
+///CORS headers to another; a Vary that already covers it (e.g. the static layer's) is kept as is
+fn vary_on_origin(response: &mut Response) {
+    let headers = response.headers_mut();
+    let covered = headers
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .any(|name| name == "*" || name.eq_ignore_ascii_case("origin"));
+    if !covered {
+        headers.append(header::VARY, HeaderValue::from_static("origin"));
+    }
+}
+
+//per-tenant CORS middleware, layered outside the static CORS layer
+//origins in CORS_ALLOWED_ORIGINS are left to the static layer; any other Origin is checked
+//against the allowed_origin table:
+//- a preflight carries no API token, so it is answered here when any tenant registered the origin
+//- an actual request gets the CORS headers only when the tenant of its API token (echoed on the
+//  response by api_token_auth) registered the origin
+//an origin nobody registered gets no Access-Control-Allow-Origin, so the browser blocks it
+//every response decided here carries `Vary: origin`, whether the origin was allowed or not
+pub async fn tenant_cors_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let Some(origin) = request.headers().get(header::ORIGIN).cloned() else {
+        return next.run(request).await;
+    };
+    if get_allowed_origins().contains(&origin) {
+        return next.run(request).await;
+    }
+    let Ok(origin_str) = origin.to_str().map(str::to_string) else {
+        return next.run(request).await;
+    };
+
+    let service = AllowedOriginService::new(state.db.clone());
+
+    if is_preflight(&request) {
+        let mut response = match service.origin_registered(&origin_str, None).await {
+            Ok(true) => preflight_response(origin),
+            Ok(false) => next.run(request).await,
+            Err(e) => {
+                tracing::error!(error = %e, "Database error while checking tenant CORS origins");
+                next.run(request).await
+            }
+        };
+        vary_on_origin(&mut response);
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    vary_on_origin(&mut response);
+    let tenant_id = response
+        .extensions()
+        .get::<AuthenticatedTenant>()
+        .and_then(|tenant| tenant.0);
+    let Some(tenant_id) = tenant_id else {
+        return response;
+    };
+
+    match service.origin_allowed(tenant_id, &origin_str, None).await {
+        Ok(true) => allow_response_origin(&mut response, origin),
+        Ok(false) => {}
+        Err(e) => {
+            tracing::error!(error = %e, "Database error while checking tenant CORS origins");
+        }
+    }
+    response
+}
@@ -5,9 +5,9 @@ use axum::{
     middleware::Next,
     response::Response,
 };
-use axum::body::to_bytes;
 use crate::AppState;
 use crate::config;
+use crate::middleware::body_capture::capture_body;
 use crate::security::AllowedIpAddressService;
 
 //extracts client IP address from request headers
@@ -63,22 +63,6 @@ fn format_headers(headers: &HeaderMap) -> String {
         .join(", ")
 }
 
-//extracts request body for logging
-//note: This consumes the body, so we need to reconstruct it for the next handler
-async fn extract_body(body: Body) -> (String, Body) {
-    let body_bytes = match to_bytes(body, usize::MAX).await {
-        Ok(bytes) => bytes,
-        Err(_) => {
-            return ("[Error reading body]".to_string(), Body::empty());
-        }
-    };
-
-    let body_str = String::from_utf8_lossy(&body_bytes).to_string();
-    let new_body = Body::from(body_bytes);
-
-    (body_str, new_body)
-}
-
 //strips the base_url prefix from the path for route matching
 //e.g. with BASE_URL=/api, "/api/healthcheck" becomes "/healthcheck"
 fn strip_base_url<'a>(path: &'a str) -> &'a str {
@@ -97,6 +81,7 @@ fn is_public_route(path: &str) -> bool {
         "/",
         "/healthcheck",
         "/metrics",
+        "/admin/readyz",
         "/local/swagger-ui",
         "/api-doc/openapi.json"
     ];
@@ -153,9 +138,9 @@ pub async fn ip_address_auth_middleware(
             format!("{}?{}", route, query)
         };
 
-        //extract body for logging (this consumes it, but we'll return error anyway so it's fine)
+        //body for logging, only read when LOG_REQUEST_BODY is on (the request is rejected anyway)
         let body = std::mem::replace(request.body_mut(), Body::empty());
-        let (body_content, _) = extract_body(body).await;
+        let body_content = capture_body(body, config::env::get().middleware.log_request_body).await;
 
         //critical log with all security-relevant information
         tracing::error!(
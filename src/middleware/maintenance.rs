@@ -0,0 +1,57 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+///seconds clients are told to wait (Retry-After) while maintenance mode is on
+pub const MAINTENANCE_RETRY_AFTER_SECS: u64 = 120;
+
+//process-wide flag: set from MAINTENANCE_MODE at startup, toggled by PUT /admin/maintenance
+static MAINTENANCE_MODE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_maintenance_mode() -> bool {
+    MAINTENANCE_MODE.load(Ordering::Relaxed)
+}
+
+///turns maintenance mode on or off; returns the previous setting
+pub fn set_maintenance_mode(enabled: bool) -> bool {
+    MAINTENANCE_MODE.swap(enabled, Ordering::Relaxed)
+}
+
+///health and readiness checks, metrics scrapes and the toggle itself stay reachable during
+///maintenance; `base_url` is stripped first
+pub fn is_exempt_route(path: &str, base_url: Option<&str>) -> bool {
+    let effective_path = base_url
+        .and_then(|base| path.strip_prefix(base))
+        .unwrap_or(path);
+
+    ["/", "/healthcheck", "/metrics", "/admin/health", "/admin/readyz", "/admin/maintenance"]
+        .contains(&effective_path)
+}
+
+//maintenance mode middleware
+//while MAINTENANCE_MODE is on, every route except the exempt ones answers 503 with
+//{"error":"maintenance"} and Retry-After, before authentication or rate limiting runs
+//the state is the base URL the routes are mounted under (ServerConfig.base_url)
+pub async fn maintenance_middleware(
+    State(base_url): State<Option<&'static str>>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    if !is_maintenance_mode() || is_exempt_route(request.uri().path(), base_url) {
+        return next.run(request).await;
+    }
+
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::RETRY_AFTER, MAINTENANCE_RETRY_AFTER_SECS.to_string())],
+        Json(json!({ "error": "maintenance" })),
+    )
+        .into_response()
+}
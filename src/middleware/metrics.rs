@@ -1,16 +1,23 @@
 use axum::{
     body::Body,
-    extract::MatchedPath,
+    extract::{ConnectInfo, MatchedPath},
     http::{Request, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use prometheus::{Encoder, TextEncoder};
+use std::net::SocketAddr;
+use std::sync::OnceLock;
 use std::time::Instant;
 
+use crate::config;
 use crate::config::metrics::{
     HTTP_REQUESTS_IN_FLIGHT, HTTP_REQUESTS_TOTAL, HTTP_REQUEST_DURATION, REGISTRY,
 };
+use crate::security::metrics_access::{metrics_access_allowed, parse_cidrs, scraper_ip, IpCidr};
+
+//METRICS_ALLOWED_CIDRS, parsed on the first scrape
+static METRICS_ALLOWED_CIDRS: OnceLock<Vec<IpCidr>> = OnceLock::new();
 
 ///handler for /metrics endpoint - returns prometheus metrics in text format
 pub async fn metrics_handler() -> impl IntoResponse {
@@ -30,6 +37,39 @@ pub async fn metrics_handler() -> impl IntoResponse {
     )
 }
 
+//guard for /metrics
+//with METRICS_ALLOWED_CIDRS or METRICS_REQUIRE_AUTH set, only scrapers from an allowed range
+//or presenting the bearer token get through; everyone else gets 403. Open when neither is set
+pub async fn metrics_access_middleware(request: Request<Body>, next: Next) -> Response {
+    let middleware = &config::env::get().middleware;
+    let allowed_cidrs =
+        METRICS_ALLOWED_CIDRS.get_or_init(|| parse_cidrs(&middleware.metrics_allowed_cidrs));
+    let peer = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    let ip = scraper_ip(peer, request.headers(), &middleware.trusted_proxies);
+
+    if !metrics_access_allowed(
+        ip,
+        request.headers(),
+        allowed_cidrs,
+        middleware.metrics_token.as_deref(),
+    ) {
+        tracing::warn!(
+            event = "unauthorized_metrics_scrape",
+            client_ip = ?ip,
+            "Metrics scrape rejected"
+        );
+        return Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .body(Body::from("Forbidden: metrics access not allowed"))
+            .unwrap();
+    }
+
+    next.run(request).await
+}
+
 ///middleware to track HTTP request metrics
 pub async fn metrics_middleware(request: Request<Body>, next: Next) -> Response {
     let method = request.method().to_string();
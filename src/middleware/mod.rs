@@ -1,13 +1,21 @@
+pub mod admin_routes;
+pub mod admin_token_auth;
 pub mod allowed_hosts;
 pub mod api_token_auth;
+pub mod body_capture;
 pub mod cors;
 pub mod ip_auth;
 pub mod logging;
+pub mod maintenance;
 pub mod metrics;
+pub mod rate_limit;
 
+pub use admin_token_auth::admin_token_auth_middleware;
 pub use allowed_hosts::allowed_hosts_middleware;
 pub use api_token_auth::api_token_auth_middleware;
-pub use cors::cors_layer;
+pub use cors::{cors_layer, tenant_cors_middleware};
 pub use ip_auth::ip_address_auth_middleware;
 pub use logging::request_logging_middleware;
-pub use metrics::{metrics_handler, metrics_middleware};
+pub use maintenance::maintenance_middleware;
+pub use metrics::{metrics_access_middleware, metrics_handler, metrics_middleware};
+pub use rate_limit::rate_limit_middleware;
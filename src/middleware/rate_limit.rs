@@ -0,0 +1,58 @@
+use axum::{
+    body::Body,
+    extract::State,
+    http::{header, Request, StatusCode},
+    middleware::Next,
+    response::Response,
+};
+use crate::AppState;
+use crate::config;
+use crate::security::rate_limit::{is_exempt_route, RateLimitStore, RateLimiter};
+use crate::security::AuthenticatedTenant;
+
+//per-tenant rate limiting middleware
+//keyed by the tenant of the API token (AuthenticatedTenant, set by api_token_auth), so it
+//must run inside the API token middleware; requests without a tenant are not limited
+//responds 429 with Retry-After once a tenant exceeds RATE_LIMIT_REQUESTS per window
+pub async fn rate_limit_middleware(
+    State(state): State<AppState>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let settings = &config::env::get().rate_limit;
+    let base_url = config::env::get().server.base_url.as_deref();
+    if is_exempt_route(request.uri().path(), base_url) {
+        return next.run(request).await;
+    }
+
+    let tenant_id = request
+        .extensions()
+        .get::<AuthenticatedTenant>()
+        .and_then(|tenant| tenant.0);
+    let Some(tenant_id) = tenant_id else {
+        return next.run(request).await;
+    };
+
+    let limiter = RateLimiter::new(
+        RateLimitStore::Redis(state.redis.clone()),
+        settings.requests,
+        settings.window,
+    );
+    let decision = limiter.check(tenant_id).await;
+    let Some(retry_after) = decision.retry_after_secs() else {
+        return next.run(request).await;
+    };
+
+    tracing::warn!(
+        event = "rate_limited",
+        tenant_id = tenant_id,
+        route = %request.uri().path(),
+        retry_after_secs = retry_after,
+        "Tenant exceeded its request rate limit"
+    );
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header(header::RETRY_AFTER, retry_after)
+        .body(Body::from("Too Many Requests: tenant rate limit exceeded"))
+        .unwrap()
+}
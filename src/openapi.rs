@@ -5,37 +5,187 @@ use crate::auth::services::{health_check as auth_health_check, AuthHealthRespons
 use crate::admin::services::{health_check as admin_health_check, AdminHealthResponse};
 use crate::tenant::routes::{
     TenantResponse, PaginatedTenantsResponse, ErrorResponse, DeleteResponse,
-    CreateTenantRequest, UpdateTenantRequest,
+    CreateTenantRequest, UpdateTenantRequest, SetConnectionsEnabledRequest,
+    SetConnectionsEnabledResponse, BulkCreateTenantsRequest, BulkCreateTenantsResponse,
+    BulkTenantErrorResponse,
 };
+use crate::connection_identity::routes::{
+    ConnectionResponse, ConnectionTestResponse, PaginatedConnectionsResponse, ReprocessResponse,
+    SyncProgressResponse, ConnectionStatusResponse, ConnectionRunSummaryResponse, ConnectionStatusBatchResponse,
+    ConnectionStatusBatchRequest, ConnectionDependentsResponse, QuarantinedItemResponse, TimelineResponse,
+    TimelineEntryResponse, ConnectionRunResponse, ConnectionRunsResponse,
+    PaginatedQuarantineResponse, QuarantineReplayResponse, CreateConnectionRequest, UpdateConnectionRequest,
+    SyncWindowRequest, SyncWindowResponse, UpdateConnectionResponse, PaginatedConnectionEventsResponse,
+    LastErrorResponse, InventoryBatchRequest, InventoryBatchItemRequest, InventoryBatchResponse,
+    InventoryBatchItemResponse, InventorySearchResponse, ConnectionApiKeyResponse,
+    IssuedConnectionApiKeyResponse, CreateConnectionApiKeyRequest,
+};
+use crate::security::routes::{
+    ApiTokenResponse, CreateApiTokenRequest, IssuedApiTokenResponse,
+};
+use crate::security::allowed_origin_routes::{
+    AllowedOriginResponse, CreateAllowedOriginRequest, UpdateAllowedOriginRequest,
+};
+use crate::audit::routes::{AuditLogResponse, PaginatedAuditLogResponse};
+use crate::erp_connection_credentials::routes::{
+    ExpiringConnectionResponse, ExpiringCredentialsResponse, ExpiringFieldResponse,
+};
+use crate::sync_event::routes::{CancelSyncEventRequest, SyncEventResponse};
+use crate::inventory_records::routes::{
+    InventoryRecordEventResponse, InventoryRecordResponse, MergeInventoryRecordsRequest,
+    MergeInventoryRecordsResponse, PaginatedInventoryRecordsResponse,
+};
+use crate::validation::FieldError;
 
 #[derive(OpenApi)]
 #[openapi(
     paths(
         crate::routes::healthcheck,
+        crate::capabilities::routes::get_capabilities,
         crate::auth::services::health_check,
         crate::admin::services::health_check,
+        crate::admin::services::readyz,
+        crate::admin::services::get_migrations,
+        crate::admin::services::set_log_level,
+        crate::admin::services::set_maintenance,
+        crate::security::routes::list_api_tokens,
+        crate::security::routes::create_api_token,
+        crate::security::routes::rotate_api_token,
+        crate::security::routes::revoke_api_token,
+        crate::security::allowed_origin_routes::list_allowed_origins,
+        crate::security::allowed_origin_routes::create_allowed_origin,
+        crate::security::allowed_origin_routes::get_allowed_origin,
+        crate::security::allowed_origin_routes::update_allowed_origin,
+        crate::security::allowed_origin_routes::delete_allowed_origin,
+        crate::audit::routes::list_audit_log,
+        crate::erp_connection_credentials::routes::list_expiring_credentials,
+        crate::sync_event::routes::retry_sync_event,
+        crate::sync_event::routes::cancel_sync_event,
         crate::tenant::routes::list_tenants,
         crate::tenant::routes::get_tenant,
         crate::tenant::routes::create_tenant,
+        crate::tenant::routes::bulk_create_tenants,
         crate::tenant::routes::update_tenant,
         crate::tenant::routes::delete_tenant,
+        crate::tenant::routes::set_connections_enabled,
+        crate::connection_identity::routes::list_connections,
+        crate::connection_identity::routes::search_connections,
+        crate::connection_identity::routes::get_connection,
+        crate::connection_identity::routes::create_connection,
+        crate::connection_identity::routes::update_connection,
+        crate::connection_identity::routes::delete_connection,
+        crate::connection_identity::routes::export_inventory,
+        crate::connection_identity::routes::batch_upsert_inventory,
+        crate::connection_identity::routes::search_inventory,
+        crate::connection_identity::routes::list_api_keys,
+        crate::connection_identity::routes::create_api_key,
+        crate::connection_identity::routes::revoke_api_key,
+        crate::connection_identity::routes::test_connection,
+        crate::connection_identity::routes::reprocess_connection,
+        crate::connection_identity::routes::status_batch,
+        crate::connection_identity::routes::get_sync_progress,
+        crate::connection_identity::routes::list_runs,
+        crate::connection_identity::routes::get_timeline,
+        crate::connection_identity::routes::get_credentials,
+        crate::connection_identity::routes::get_last_error,
+        crate::connection_identity::routes::list_events,
+        crate::connection_identity::routes::list_quarantine,
+        crate::connection_identity::routes::replay_quarantined,
+        crate::connection_identity::routes::delete_preview,
+        crate::connection_identity::routes::purge_connection,
+        crate::inventory_records::routes::list_inventory_records,
+        crate::inventory_records::routes::get_inventory_record,
+        crate::inventory_records::routes::merge_inventory_records,
     ),
     components(schemas(
         HealthCheckResponse,
         AuthHealthResponse,
         AdminHealthResponse,
+        crate::admin::services::MigrationStatusResponse,
+        crate::admin::readiness::ReadinessReport,
+        crate::admin::readiness::DatabaseReadiness,
+        crate::admin::readiness::RedisReadiness,
+        crate::admin::readiness::MigrationReadiness,
+        crate::admin::services::LogLevelResponse,
+        crate::admin::services::SetLogLevelRequest,
+        crate::admin::services::MaintenanceModeResponse,
+        crate::admin::services::SetMaintenanceModeRequest,
+        ApiTokenResponse,
+        IssuedApiTokenResponse,
+        CreateApiTokenRequest,
+        AllowedOriginResponse,
+        CreateAllowedOriginRequest,
+        UpdateAllowedOriginRequest,
+        AuditLogResponse,
+        PaginatedAuditLogResponse,
+        ExpiringCredentialsResponse,
+        SyncEventResponse,
+        CancelSyncEventRequest,
+        ExpiringConnectionResponse,
+        ExpiringFieldResponse,
         TenantResponse,
         PaginatedTenantsResponse,
         ErrorResponse,
         DeleteResponse,
         CreateTenantRequest,
         UpdateTenantRequest,
+        SetConnectionsEnabledRequest,
+        SetConnectionsEnabledResponse,
+        BulkCreateTenantsRequest,
+        BulkCreateTenantsResponse,
+        BulkTenantErrorResponse,
+        FieldError,
+        ConnectionResponse,
+        SyncWindowResponse,
+        UpdateConnectionResponse,
+        PaginatedConnectionsResponse,
+        ConnectionTestResponse,
+        ReprocessResponse,
+        InventoryBatchRequest,
+        InventoryBatchItemRequest,
+        InventoryBatchResponse,
+        InventoryBatchItemResponse,
+        InventorySearchResponse,
+        ConnectionApiKeyResponse,
+        IssuedConnectionApiKeyResponse,
+        CreateConnectionApiKeyRequest,
+        SyncProgressResponse,
+        ConnectionStatusResponse,
+        ConnectionRunSummaryResponse,
+        ConnectionStatusBatchResponse,
+        ConnectionStatusBatchRequest,
+        ConnectionRunsResponse,
+        ConnectionRunResponse,
+        TimelineResponse,
+        TimelineEntryResponse,
+        crate::erp_connection_credentials::masked::MaskedCredentialsResponse,
+        ConnectionDependentsResponse,
+        QuarantinedItemResponse,
+        PaginatedQuarantineResponse,
+        QuarantineReplayResponse,
+        PaginatedConnectionEventsResponse,
+        LastErrorResponse,
+        CreateConnectionRequest,
+        SyncWindowRequest,
+        UpdateConnectionRequest,
+        InventoryRecordResponse,
+        PaginatedInventoryRecordsResponse,
+        InventoryRecordEventResponse,
+        MergeInventoryRecordsRequest,
+        MergeInventoryRecordsResponse,
+        crate::capabilities::services::CapabilitiesResponse,
+        crate::capabilities::services::ProviderCapabilityResponse,
+        crate::capabilities::services::SyncCapabilityResponse,
+        crate::capabilities::services::FeatureFlags,
     )),
     tags(
         (name = "Health", description = "Health check endpoints"),
+        (name = "Capabilities", description = "What this deployment supports"),
         (name = "Auth", description = "Authentication module endpoints"),
         (name = "Admin", description = "Admin module endpoints"),
         (name = "Tenant", description = "Tenant management endpoints"),
+        (name = "Connection", description = "ERP connection management endpoints"),
+        (name = "Inventory", description = "Synced inventory record endpoints"),
     ),
     info(
         title = "ERP Proxy Server API",
@@ -0,0 +1,4 @@
+pub mod relay;
+pub mod services;
+
+pub use services::OutboxService;
@@ -0,0 +1,79 @@
+//! Background relay that drains the outbox into a Redis stream.
+//!
+//! Each message is appended with `XADD <stream> * topic <topic> uuid <uuid> payload <json>`.
+//! Consumers should de-duplicate on `uuid` since delivery is at-least-once.
+
+use redis::aio::ConnectionManager;
+use sea_orm::{DatabaseConnection, DbErr};
+
+use crate::config::env::OutboxConfig;
+use super::services::OutboxService;
+
+///spawns the relay loop on the tokio runtime
+pub fn spawn(db: DatabaseConnection, redis: ConnectionManager) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let config = &crate::config::env::get().outbox;
+        let service = OutboxService::new(db);
+        let mut redis = redis;
+        let mut interval = tokio::time::interval(config.poll_interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            interval.tick().await;
+            //drain full batches back to back, then wait for the next tick
+            loop {
+                match relay_batch(&service, &mut redis, config).await {
+                    Ok(sent) if sent as u64 == config.batch_size => continue,
+                    Ok(_) => break,
+                    Err(e) => {
+                        tracing::error!("Outbox relay failed to read pending messages: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    })
+}
+
+///delivers one batch of unpublished messages, returns how many were handled
+pub async fn relay_batch(
+    service: &OutboxService,
+    redis: &mut ConnectionManager,
+    config: &OutboxConfig,
+) -> Result<usize, DbErr> {
+    let messages = service.fetch_unpublished(config.batch_size, None).await?;
+    let count = messages.len();
+
+    for message in messages {
+        let delivered: redis::RedisResult<String> = redis::cmd("XADD")
+            .arg(&config.stream_key)
+            .arg("*")
+            .arg("topic")
+            .arg(&message.topic)
+            .arg("uuid")
+            .arg(message.uuid.to_string())
+            .arg("payload")
+            .arg(message.payload.to_string())
+            .query_async(redis)
+            .await;
+
+        let result = match delivered {
+            Ok(_) => service.mark_published(message.id, None).await.map(|_| ()),
+            Err(e) => {
+                tracing::warn!(
+                    "Outbox delivery failed for message {} (attempt {}): {}",
+                    message.uuid,
+                    message.attempts + 1,
+                    e
+                );
+                service.mark_failed(message.id, &e.to_string(), None).await.map(|_| ())
+            }
+        };
+
+        if let Err(e) = result {
+            tracing::error!("Failed to update outbox message {}: {:?}", message.uuid, e);
+        }
+    }
+
+    Ok(count)
+}
@@ -0,0 +1,165 @@
+//! Transactional outbox (no routes).
+//!
+//! Producers call `enqueue` with the same transaction that writes the domain change, so a
+//! message exists if and only if the change was committed. The relay (`outbox::relay`) picks
+//! up unpublished rows, delivers them and marks them published. Delivery is at-least-once:
+//! a crash between delivery and `mark_published` re-sends the message on the next pass.
+
+use entity::outbox;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use uuid::Uuid;
+
+/// Topic for inventory records created or updated by a provider pull.
+pub const TOPIC_INVENTORY_UPSERTED: &str = "inventory.upserted";
+
+/// Topic for a connection whose `auth_status` just became `needs_reauth` or `error`.
+pub const TOPIC_CONNECTION_NEEDS_REAUTH: &str = "connection.needs_reauth";
+
+/// Upper bound for the retry backoff between failed deliveries.
+const MAX_RETRY_DELAY_SECS: i64 = 300;
+
+//DEBUG AND ERRORS ///
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum OutboxError {
+    NotFound,
+    Db(DbErr),
+}
+
+#[allow(dead_code)]
+impl From<DbErr> for OutboxError {
+    fn from(err: DbErr) -> Self {
+        OutboxError::Db(err)
+    }
+}
+
+//END DEBUG AND ERRORS
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+pub struct OutboxService {
+    db: DatabaseConnection,
+}
+
+#[allow(dead_code)]
+pub struct OutboxPayload {
+    pub topic: String,
+    pub payload: serde_json::Value,
+}
+
+// END STRUCTS AND ENUMS
+
+
+/// BEGUN IMPLEMENTATION ///
+#[allow(dead_code)]
+impl OutboxService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    ///writes a message inside the caller's transaction
+    ///the message only becomes visible to the relay once the caller commits
+    pub async fn enqueue(
+        &self,
+        txn: &DatabaseTransaction,
+        payload: OutboxPayload,
+    ) -> Result<outbox::Model, DbErr> {
+        let now = chrono::Utc::now();
+        let active = outbox::ActiveModel {
+            uuid: Set(Uuid::new_v4()),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            topic: Set(payload.topic),
+            payload: Set(payload.payload),
+            attempts: Set(0),
+            next_attempt_at: Set(now.into()),
+            ..Default::default()
+        };
+        active.insert(txn).await
+    }
+
+    ///oldest unpublished messages that are due for (re)delivery
+    pub async fn fetch_unpublished(
+        &self,
+        limit: u64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Vec<outbox::Model>, DbErr> {
+        let query = outbox::Entity::find()
+            .filter(outbox::Column::PublishedAt.is_null())
+            .filter(outbox::Column::NextAttemptAt.lte(chrono::Utc::now()))
+            .order_by_asc(outbox::Column::Id)
+            .limit(limit);
+
+        match txn {
+            Some(txn) => query.all(txn).await,
+            None => query.all(&self.db).await,
+        }
+    }
+
+    pub async fn mark_published(
+        &self,
+        id: i64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<outbox::Model, OutboxError> {
+        let model = match txn {
+            Some(txn) => outbox::Entity::find_by_id(id).one(txn).await?,
+            None => outbox::Entity::find_by_id(id).one(&self.db).await?,
+        };
+        let Some(model) = model else {
+            return Err(OutboxError::NotFound);
+        };
+
+        let now = chrono::Utc::now();
+        let attempts = model.attempts + 1;
+        let mut active: outbox::ActiveModel = model.into();
+        active.attempts = Set(attempts);
+        active.published_at = Set(Some(now.into()));
+        active.last_error = Set(None);
+        active.updated_at = Set(now.into());
+
+        match txn {
+            Some(txn) => Ok(active.update(txn).await?),
+            None => Ok(active.update(&self.db).await?),
+        }
+    }
+
+    ///records a failed delivery and pushes the next attempt back exponentially
+    pub async fn mark_failed(
+        &self,
+        id: i64,
+        error: &str,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<outbox::Model, OutboxError> {
+        let model = match txn {
+            Some(txn) => outbox::Entity::find_by_id(id).one(txn).await?,
+            None => outbox::Entity::find_by_id(id).one(&self.db).await?,
+        };
+        let Some(model) = model else {
+            return Err(OutboxError::NotFound);
+        };
+
+        let now = chrono::Utc::now();
+        let attempts = model.attempts + 1;
+        let mut active: outbox::ActiveModel = model.into();
+        active.attempts = Set(attempts);
+        active.last_error = Set(Some(error.to_string()));
+        active.next_attempt_at = Set((now + retry_delay(attempts)).into());
+        active.updated_at = Set(now.into());
+
+        match txn {
+            Some(txn) => Ok(active.update(txn).await?),
+            None => Ok(active.update(&self.db).await?),
+        }
+    }
+}
+
+///delay before the next delivery attempt: 2^attempts seconds, capped at 5 minutes
+pub fn retry_delay(attempts: i32) -> chrono::Duration {
+    let exp = attempts.clamp(0, 16) as u32;
+    chrono::Duration::seconds(2_i64.pow(exp).min(MAX_RETRY_DELAY_SECS))
+}
+
+// END IMPLEMENTATION
@@ -0,0 +1,73 @@
+//! Pagination response headers shared by list routes.
+//!
+//! List handlers keep their JSON envelope (`items`, `total`, `page`, ...) and also return
+//! the same numbers as `X-Total-Count` / `X-Page` / `X-Per-Page` / `X-Total-Pages`, plus an
+//! RFC 5988 `Link` header whose `first`/`prev`/`next`/`last` targets are the request URI
+//! with only its `page` parameter replaced.
+
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Uri, header};
+
+pub const X_TOTAL_COUNT: HeaderName = HeaderName::from_static("x-total-count");
+pub const X_PAGE: HeaderName = HeaderName::from_static("x-page");
+pub const X_PER_PAGE: HeaderName = HeaderName::from_static("x-per-page");
+pub const X_TOTAL_PAGES: HeaderName = HeaderName::from_static("x-total-pages");
+
+/// Headers browsers may read cross-origin (see `middleware::cors_layer`).
+pub const EXPOSED_HEADERS: [HeaderName; 5] =
+    [X_TOTAL_COUNT, X_PAGE, X_PER_PAGE, X_TOTAL_PAGES, header::LINK];
+
+/// Position of a returned page, as reported by the list services.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageInfo {
+    pub page: u64,
+    pub per_page: u64,
+    pub total: u64,
+    pub total_pages: u64,
+}
+
+///builds the pagination headers for `page`, linking relative to the request `uri`
+pub fn paginated_response(page: &PageInfo, uri: &Uri) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(X_TOTAL_COUNT, HeaderValue::from(page.total));
+    headers.insert(X_PAGE, HeaderValue::from(page.page));
+    headers.insert(X_PER_PAGE, HeaderValue::from(page.per_page));
+    headers.insert(X_TOTAL_PAGES, HeaderValue::from(page.total_pages));
+
+    if let Ok(link) = HeaderValue::from_str(&link_header(page, uri)) {
+        headers.insert(header::LINK, link);
+    }
+    headers
+}
+
+///`Link` header value; `prev`/`next` are omitted on the first/last page
+pub fn link_header(page: &PageInfo, uri: &Uri) -> String {
+    //an empty result still has one (empty) page to point at
+    let last = page.total_pages.max(1);
+
+    let mut rels = vec![("first", 1)];
+    if page.page > 1 {
+        rels.push(("prev", (page.page - 1).min(last)));
+    }
+    if page.page < last {
+        rels.push(("next", page.page + 1));
+    }
+    rels.push(("last", last));
+
+    rels.into_iter()
+        .map(|(rel, n)| format!("<{}>; rel=\"{}\"", page_uri(uri, n), rel))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+///the request path and query with `page` set to `n`; other parameters keep their encoding
+fn page_uri(uri: &Uri, n: u64) -> String {
+    let mut params: Vec<String> = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|p| !p.is_empty() && p.split('=').next() != Some("page"))
+        .map(str::to_string)
+        .collect();
+    params.push(format!("page={}", n));
+    format!("{}?{}", uri.path(), params.join("&"))
+}
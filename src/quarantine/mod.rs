@@ -0,0 +1,3 @@
+pub mod services;
+
+pub use services::QuarantineService;
@@ -0,0 +1,161 @@
+//! Quarantine of provider items that could not be applied (routes live under
+//! `/connections/{uuid}/quarantine`).
+//!
+//! When a pull page fails to upsert one item, the item's parsed body is written here in the
+//! page transaction, so the rest of the page still commits and the item can be inspected
+//! and replayed later. There is one row per connection and ListID: a repeat failure
+//! replaces the body and error and bumps `attempts`. A successful replay deletes the row.
+
+use entity::quarantine;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    PaginatorTrait, QueryFilter, QueryOrder, Set,
+};
+use serde_json::Value;
+use uuid::Uuid;
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+pub struct QuarantineService {
+    db: DatabaseConnection,
+}
+
+/// An item that failed to apply.
+#[allow(dead_code)]
+pub struct QuarantineItem {
+    pub connection_id: i64,
+    pub list_id: String,
+    pub item_type: Option<String>,
+    /// The item as parsed from the provider response (`original_record_body` format).
+    pub raw_body: Value,
+    pub error: String,
+}
+
+#[allow(dead_code)]
+pub struct PaginatedQuarantine {
+    pub items: Vec<quarantine::Model>,
+    pub total: u64,
+    pub page: u64,
+    pub per_page: u64,
+    pub total_pages: u64,
+}
+
+/// END STRUCTS AND ENUMS ///
+
+
+/// BEGUN IMPLEMENTATION ///
+#[allow(dead_code)]
+impl QuarantineService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    ///writes (or refreshes) the item's row inside the caller's transaction
+    pub async fn quarantine(
+        &self,
+        txn: &DatabaseTransaction,
+        item: QuarantineItem,
+    ) -> Result<quarantine::Model, DbErr> {
+        let existing = quarantine::Entity::find()
+            .filter(quarantine::Column::ConnectionId.eq(item.connection_id))
+            .filter(quarantine::Column::ListId.eq(&item.list_id))
+            .one(txn)
+            .await?;
+
+        let now = chrono::Utc::now();
+        match existing {
+            Some(existing) => {
+                let attempts = existing.attempts + 1;
+                let mut active: quarantine::ActiveModel = existing.into();
+                active.item_type = Set(item.item_type);
+                active.raw_body = Set(item.raw_body);
+                active.error = Set(item.error);
+                active.attempts = Set(attempts);
+                active.updated_at = Set(now.into());
+                active.update(txn).await
+            }
+            None => {
+                quarantine::ActiveModel {
+                    uuid: Set(Uuid::new_v4()),
+                    created_at: Set(now.into()),
+                    updated_at: Set(now.into()),
+                    connection_id: Set(item.connection_id),
+                    list_id: Set(item.list_id),
+                    item_type: Set(item.item_type),
+                    raw_body: Set(item.raw_body),
+                    error: Set(item.error),
+                    attempts: Set(1),
+                    ..Default::default()
+                }
+                .insert(txn)
+                .await
+            }
+        }
+    }
+
+    ///the connection's quarantined item with `uuid`
+    pub async fn get_by_uuid(
+        &self,
+        connection_id: i64,
+        uuid: Uuid,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<quarantine::Model>, DbErr> {
+        let query = quarantine::Entity::find()
+            .filter(quarantine::Column::ConnectionId.eq(connection_id))
+            .filter(quarantine::Column::Uuid.eq(uuid));
+        match txn {
+            Some(txn) => query.one(txn).await,
+            None => query.one(&self.db).await,
+        }
+    }
+
+    ///the connection's quarantined items, most recently failed first
+    pub async fn get_all(
+        &self,
+        connection_id: i64,
+        page: u64,
+        per_page: u64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<PaginatedQuarantine, DbErr> {
+        let query = quarantine::Entity::find()
+            .filter(quarantine::Column::ConnectionId.eq(connection_id))
+            .order_by_desc(quarantine::Column::UpdatedAt)
+            .order_by_desc(quarantine::Column::Id);
+
+        let total = match txn {
+            Some(txn) => query.clone().count(txn).await?,
+            None => query.clone().count(&self.db).await?,
+        };
+
+        let total_pages = (total as f64 / per_page as f64).ceil() as u64;
+
+        let items = match txn {
+            Some(txn) => {
+                query
+                    .paginate(txn, per_page)
+                    .fetch_page(page.saturating_sub(1))
+                    .await?
+            }
+            None => {
+                query
+                    .paginate(&self.db, per_page)
+                    .fetch_page(page.saturating_sub(1))
+                    .await?
+            }
+        };
+
+        Ok(PaginatedQuarantine {
+            items,
+            total,
+            page,
+            per_page,
+            total_pages,
+        })
+    }
+
+    ///removes a row once its item was applied
+    pub async fn delete_by_id(&self, txn: &DatabaseTransaction, id: i64) -> Result<(), DbErr> {
+        quarantine::Entity::delete_by_id(id).exec(txn).await?;
+        Ok(())
+    }
+}
@@ -34,10 +34,17 @@ pub fn create_router(state: AppState) -> Router {
     let routes = Router::new()
         .merge(swagger_ui)
         .route("/healthcheck", get(healthcheck))
-        .route("/metrics", get(crate::middleware::metrics_handler))
+        .route("/capabilities", get(crate::capabilities::routes::get_capabilities))
+        .route(
+            "/metrics",
+            get(crate::middleware::metrics_handler)
+                .layer(axum::middleware::from_fn(crate::middleware::metrics_access_middleware)),
+        )
         .nest("/auth", crate::auth::create_router())
         .nest("/admin", crate::admin::create_router())
         .nest("/tenant", crate::tenant::create_router())
+        .nest("/connections", crate::connection_identity::create_router())
+        .nest("/inventory-records", crate::inventory_records::create_router())
         .nest(
             "/client-systems/quickbooks/desktop",
             crate::client_systems::quickbooks::desktop::create_router(),
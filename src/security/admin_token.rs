@@ -0,0 +1,19 @@
+/// Header carrying the admin token on `/admin/api-tokens` requests.
+pub const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+
+///checks a presented admin token against the configured `ADMIN_TOKEN`
+///always false when no admin token is configured; compares in constant time
+pub fn admin_token_matches(expected: Option<&str>, presented: Option<&str>) -> bool {
+    let (Some(expected), Some(presented)) = (expected, presented) else {
+        return false;
+    };
+    if expected.is_empty() || expected.len() != presented.len() {
+        return false;
+    }
+
+    expected
+        .bytes()
+        .zip(presented.bytes())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
@@ -0,0 +1,276 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use entity::allowed_origin;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::tenant::routes::{DeleteResponse, ErrorResponse};
+use crate::tenant::TenantService;
+use crate::validation::FieldError;
+use super::allowed_origins::{AllowedOriginError, AllowedOriginService, CreateAllowedOrigin};
+
+
+/// RESPONSE SCHEMAS ///
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct AllowedOriginResponse {
+    pub uuid: String,
+    pub tenant_id: i64,
+    /// Normalized `scheme://host[:port]`.
+    pub origin: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+
+/// REQUEST SCHEMAS ///
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateAllowedOriginRequest {
+    /// Tenant the origin belongs to (TN_xxx format).
+    pub tenant_id: String,
+    /// Front-end origin, e.g. `https://app.example.com`.
+    pub origin: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct UpdateAllowedOriginRequest {
+    pub origin: String,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ListAllowedOriginsQuery {
+    /// Only origins of this tenant (TN_xxx format).
+    pub tenant_id: Option<String>,
+}
+
+
+/// HELPERS ///
+pub fn model_to_response(model: allowed_origin::Model) -> AllowedOriginResponse {
+    AllowedOriginResponse {
+        uuid: model.uuid.to_string(),
+        tenant_id: model.tenant_id,
+        origin: model.origin,
+        created_at: model.created_at.to_rfc3339(),
+        updated_at: model.updated_at.to_rfc3339(),
+    }
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.into(),
+            details: None,
+        }),
+    )
+}
+
+fn not_found() -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::NOT_FOUND, "Allowed origin not found")
+}
+
+fn db_error(e: impl std::fmt::Display) -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))
+}
+
+fn service_error(e: AllowedOriginError) -> (StatusCode, Json<ErrorResponse>) {
+    match e {
+        AllowedOriginError::NotFound => not_found(),
+        AllowedOriginError::Conflict => error(
+            StatusCode::CONFLICT,
+            "Origin is already allowed for this tenant",
+        ),
+        AllowedOriginError::Invalid(message) => {
+            ErrorResponse::validation(vec![FieldError::new("origin", message)])
+        }
+        AllowedOriginError::Db(e) => db_error(e),
+    }
+}
+
+///resolves a TN_xxx tenant id to its row id
+async fn resolve_tenant(
+    state: &AppState,
+    tenant_id: &str,
+) -> Result<i64, (StatusCode, Json<ErrorResponse>)> {
+    match TenantService::new(state.db.clone())
+        .get_by_tenant_id(tenant_id, None)
+        .await
+    {
+        Ok(Some(tenant)) => Ok(tenant.id),
+        Ok(None) => Err(error(StatusCode::NOT_FOUND, "Tenant not found")),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+
+/// ROUTE HANDLERS ///
+
+#[utoipa::path(
+    get,
+    path = "/admin/allowed-origins",
+    tag = "Admin",
+    params(ListAllowedOriginsQuery),
+    responses(
+        (status = 200, description = "Per-tenant CORS origins", body = Vec<AllowedOriginResponse>),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Tenant not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn list_allowed_origins(
+    State(state): State<AppState>,
+    Query(query): Query<ListAllowedOriginsQuery>,
+) -> Result<Json<Vec<AllowedOriginResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let tenant_id = match &query.tenant_id {
+        Some(tenant_id) => Some(resolve_tenant(&state, tenant_id).await?),
+        None => None,
+    };
+
+    match AllowedOriginService::new(state.db).get_all(tenant_id, None).await {
+        Ok(origins) => Ok(Json(origins.into_iter().map(model_to_response).collect())),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/allowed-origins",
+    tag = "Admin",
+    request_body = CreateAllowedOriginRequest,
+    responses(
+        (status = 201, description = "Origin allowed for the tenant", body = AllowedOriginResponse),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Tenant not found", body = ErrorResponse),
+        (status = 409, description = "Origin is already allowed for this tenant", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn create_allowed_origin(
+    State(state): State<AppState>,
+    Json(body): Json<CreateAllowedOriginRequest>,
+) -> Result<(StatusCode, Json<AllowedOriginResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let tenant_id = resolve_tenant(&state, &body.tenant_id).await?;
+    let data = CreateAllowedOrigin {
+        tenant_id,
+        origin: body.origin,
+    };
+
+    match AllowedOriginService::new(state.db).create(data, None).await {
+        Ok(model) => Ok((StatusCode::CREATED, Json(model_to_response(model)))),
+        Err(e) => Err(service_error(e)),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/admin/allowed-origins/{uuid}",
+    tag = "Admin",
+    params(
+        ("uuid" = String, Path, description = "Allowed origin UUID")
+    ),
+    responses(
+        (status = 200, description = "Allowed origin", body = AllowedOriginResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Allowed origin not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn get_allowed_origin(
+    State(state): State<AppState>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<AllowedOriginResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match AllowedOriginService::new(state.db).get_by_uuid(uuid, None).await {
+        Ok(Some(model)) => Ok(Json(model_to_response(model))),
+        Ok(None) => Err(not_found()),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/admin/allowed-origins/{uuid}",
+    tag = "Admin",
+    params(
+        ("uuid" = String, Path, description = "Allowed origin UUID")
+    ),
+    request_body = UpdateAllowedOriginRequest,
+    responses(
+        (status = 200, description = "Origin replaced", body = AllowedOriginResponse),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Allowed origin not found", body = ErrorResponse),
+        (status = 409, description = "Origin is already allowed for this tenant", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn update_allowed_origin(
+    State(state): State<AppState>,
+    Path(uuid): Path<Uuid>,
+    Json(body): Json<UpdateAllowedOriginRequest>,
+) -> Result<Json<AllowedOriginResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match AllowedOriginService::new(state.db)
+        .update_by_uuid(uuid, &body.origin, None)
+        .await
+    {
+        Ok(model) => Ok(Json(model_to_response(model))),
+        Err(e) => Err(service_error(e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/allowed-origins/{uuid}",
+    tag = "Admin",
+    params(
+        ("uuid" = String, Path, description = "Allowed origin UUID")
+    ),
+    responses(
+        (status = 200, description = "Origin removed", body = DeleteResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Allowed origin not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn delete_allowed_origin(
+    State(state): State<AppState>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match AllowedOriginService::new(state.db).delete_by_uuid(uuid, None).await {
+        Ok(()) => Ok(Json(DeleteResponse {
+            message: "Allowed origin removed successfully".to_string(),
+        })),
+        Err(e) => Err(service_error(e)),
+    }
+}
+
+
+/// ROUTER ///
+///mounted at /admin/allowed-origins behind the admin token middleware
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_allowed_origins).post(create_allowed_origin))
+        .route(
+            "/{uuid}",
+            get(get_allowed_origin)
+                .put(update_allowed_origin)
+                .delete(delete_allowed_origin),
+        )
+        .layer(axum::middleware::from_fn(
+            crate::middleware::admin_token_auth_middleware,
+        ))
+}
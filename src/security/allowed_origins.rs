@@ -0,0 +1,211 @@
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set, SqlErr,
+};
+use entity::allowed_origin;
+use uuid::Uuid;
+
+
+//DEBUG AND ERRORS ///
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum AllowedOriginError {
+    NotFound,
+    ///the tenant already registered this origin
+    Conflict,
+    ///the origin isn't a `scheme://host[:port]` value
+    Invalid(String),
+    Db(DbErr),
+}
+
+#[allow(dead_code)]
+impl From<DbErr> for AllowedOriginError {
+    fn from(err: DbErr) -> Self {
+        match err.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => AllowedOriginError::Conflict,
+            _ => AllowedOriginError::Db(err),
+        }
+    }
+}
+
+//END DEBUG AND ERRORS
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+pub struct AllowedOriginService {
+    db: DatabaseConnection,
+}
+
+pub struct CreateAllowedOrigin {
+    pub tenant_id: i64,
+    /// Raw origin; stored normalized (see `normalize_origin`).
+    pub origin: String,
+}
+
+/// END STRUCTS AND ENUMS ///
+
+
+///normalizes an origin to the form browsers send in `Origin`: lowercase `scheme://host[:port]`
+///without a trailing slash; anything with a path, query, credentials or another scheme is rejected
+pub fn normalize_origin(origin: &str) -> Result<String, String> {
+    let origin = origin.trim().trim_end_matches('/').to_ascii_lowercase();
+    let Some(host) = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+    else {
+        return Err("Origin must start with http:// or https://".to_string());
+    };
+
+    if host.is_empty() {
+        return Err("Origin must include a host".to_string());
+    }
+    if host
+        .chars()
+        .any(|c| matches!(c, '/' | '?' | '#' | '@' | '*') || c.is_whitespace())
+    {
+        return Err("Origin must be scheme://host[:port] only".to_string());
+    }
+
+    Ok(origin)
+}
+
+
+/// BEGUN IMPLEMENTATION ///
+#[allow(dead_code)]
+impl AllowedOriginService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+
+    ///lists registered origins, optionally for one tenant
+    pub async fn get_all(
+        &self,
+        tenant_id: Option<i64>,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Vec<allowed_origin::Model>, DbErr> {
+        let mut query = allowed_origin::Entity::find().order_by_asc(allowed_origin::Column::Id);
+        if let Some(tenant_id) = tenant_id {
+            query = query.filter(allowed_origin::Column::TenantId.eq(tenant_id));
+        }
+
+        match txn {
+            Some(txn) => query.all(txn).await,
+            None => query.all(&self.db).await,
+        }
+    }
+
+    pub async fn get_by_uuid(
+        &self,
+        uuid: Uuid,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<allowed_origin::Model>, DbErr> {
+        let query = allowed_origin::Entity::find().filter(allowed_origin::Column::Uuid.eq(uuid));
+        match txn {
+            Some(txn) => query.one(txn).await,
+            None => query.one(&self.db).await,
+        }
+    }
+
+    ///registers an origin for a tenant; `Conflict` when the tenant already has it
+    pub async fn create(
+        &self,
+        data: CreateAllowedOrigin,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<allowed_origin::Model, AllowedOriginError> {
+        let origin = normalize_origin(&data.origin).map_err(AllowedOriginError::Invalid)?;
+        let active = allowed_origin::ActiveModel {
+            uuid: Set(Uuid::new_v4()),
+            tenant_id: Set(data.tenant_id),
+            origin: Set(origin),
+            ..Default::default()
+        };
+
+        match txn {
+            Some(txn) => Ok(active.insert(txn).await?),
+            None => Ok(active.insert(&self.db).await?),
+        }
+    }
+
+    ///replaces the origin of an entry, keeping its tenant
+    pub async fn update_by_uuid(
+        &self,
+        uuid: Uuid,
+        origin: &str,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<allowed_origin::Model, AllowedOriginError> {
+        let origin = normalize_origin(origin).map_err(AllowedOriginError::Invalid)?;
+        let Some(model) = self.get_by_uuid(uuid, txn).await? else {
+            return Err(AllowedOriginError::NotFound);
+        };
+
+        let mut new_data: allowed_origin::ActiveModel = model.into();
+        new_data.origin = Set(origin);
+        new_data.updated_at = Set(chrono::Utc::now().into());
+
+        match txn {
+            Some(txn) => Ok(new_data.update(txn).await?),
+            None => Ok(new_data.update(&self.db).await?),
+        }
+    }
+
+    pub async fn delete_by_uuid(
+        &self,
+        uuid: Uuid,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<(), AllowedOriginError> {
+        let query = allowed_origin::Entity::delete_many()
+            .filter(allowed_origin::Column::Uuid.eq(uuid));
+        let result = match txn {
+            Some(txn) => query.exec(txn).await?,
+            None => query.exec(&self.db).await?,
+        };
+
+        match result.rows_affected {
+            0 => Err(AllowedOriginError::NotFound),
+            _ => Ok(()),
+        }
+    }
+
+
+    ///whether `tenant_id` registered the request `Origin`
+    pub async fn origin_allowed(
+        &self,
+        tenant_id: i64,
+        origin: &str,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<bool, DbErr> {
+        let Ok(origin) = normalize_origin(origin) else {
+            return Ok(false);
+        };
+        let query = allowed_origin::Entity::find()
+            .filter(allowed_origin::Column::TenantId.eq(tenant_id))
+            .filter(allowed_origin::Column::Origin.eq(origin));
+
+        let count = match txn {
+            Some(txn) => query.count(txn).await?,
+            None => query.count(&self.db).await?,
+        };
+        Ok(count > 0)
+    }
+
+    ///whether any tenant registered the request `Origin`; used for preflights, which carry
+    ///no API token to tell the tenant
+    pub async fn origin_registered(
+        &self,
+        origin: &str,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<bool, DbErr> {
+        let Ok(origin) = normalize_origin(origin) else {
+            return Ok(false);
+        };
+        let query = allowed_origin::Entity::find()
+            .filter(allowed_origin::Column::Origin.eq(origin));
+
+        let count = match txn {
+            Some(txn) => query.count(txn).await?,
+            None => query.count(&self.db).await?,
+        };
+        Ok(count > 0)
+    }
+}
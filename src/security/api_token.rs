@@ -1,11 +1,15 @@
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr,
-    EntityTrait, QueryFilter, Set,
+    EntityTrait, QueryFilter, QueryOrder, Set,
 };
 use entity::api_token;
 use entity::sea_orm_active_enums::ApiTokenStatusEnum as ApiTokenStatus;
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
+/// Characters of the raw token kept in `token_prefix` to tell tokens apart in listings.
+pub const TOKEN_PREFIX_LEN: usize = 8;
+
 
 //DEBUG AND ERRORS ///
 #[allow(dead_code)]
@@ -34,10 +38,34 @@ pub struct ApiTokenService {
 
 #[allow(dead_code)]
 pub struct UpdateApiToken {
+    /// Raw token; only its hash and prefix are stored.
     pub token: Option<String>,
     pub status: Option<ApiTokenStatus>,
 }
 
+pub struct CreateApiToken {
+    pub tenant_id: i64,
+    pub display_name: Option<String>,
+}
+
+/// Request extension set by `api_token_auth_middleware` once a token is accepted: the
+/// tenant the token belongs to (None for tokens without a tenant). Also set on the response
+/// for `tenant_cors_middleware`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthenticatedTenant(pub Option<i64>);
+
+/// Request extension set by `api_token_auth_middleware` next to `AuthenticatedTenant`: the
+/// row id of the accepted token, recorded as the actor of audited changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthenticatedToken(pub i64);
+
+/// A newly issued (or rotated) token. `token` is the raw secret: it is not stored and
+/// can't be read back later.
+pub struct IssuedApiToken {
+    pub model: api_token::Model,
+    pub token: String,
+}
+
 /// END STRUCTS AND ENUMS ///
 
 
@@ -46,6 +74,21 @@ pub struct UpdateApiToken {
 
 
 
+///sha256 hex digest of a raw token, as stored in `token_hash`
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+///new random token (two v4 uuids, 244 random bits)
+pub fn generate_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn token_prefix(token: &str) -> String {
+    token.chars().take(TOKEN_PREFIX_LEN).collect()
+}
+
+
 /// BEGUN IMPLEMENTATION ///
 #[allow(dead_code)]
 impl ApiTokenService {
@@ -54,21 +97,23 @@ impl ApiTokenService {
     }
 
 
+    ///looks a raw token up by its hash
     pub async fn get_by_token(
         &self,
         token: &str,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<api_token::Model>, DbErr> {
+        let token_hash = hash_token(token);
         match txn {
             Some(txn) => {
                 api_token::Entity::find()
-                    .filter(api_token::Column::Token.eq(token))
+                    .filter(api_token::Column::TokenHash.eq(token_hash))
                     .one(txn)
                     .await
             }
             None => {
                 api_token::Entity::find()
-                    .filter(api_token::Column::Token.eq(token))
+                    .filter(api_token::Column::TokenHash.eq(token_hash))
                     .one(&self.db)
                     .await
             }
@@ -96,23 +141,78 @@ impl ApiTokenService {
         }
     }
 
+    ///lists tokens (metadata only, the secret is never stored), optionally for one tenant
+    pub async fn get_all(
+        &self,
+        tenant_id: Option<i64>,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Vec<api_token::Model>, DbErr> {
+        let mut query = api_token::Entity::find().order_by_asc(api_token::Column::Id);
+        if let Some(tenant_id) = tenant_id {
+            query = query.filter(api_token::Column::TenantId.eq(tenant_id));
+        }
+
+        match txn {
+            Some(txn) => query.all(txn).await,
+            None => query.all(&self.db).await,
+        }
+    }
+
+    ///issues a new active token for a tenant; the raw token is only in the returned value
     pub async fn create(
         &self,
-        token: String,
+        data: CreateApiToken,
         txn: Option<&DatabaseTransaction>,
-    ) -> Result<api_token::Model, DbErr> {
+    ) -> Result<IssuedApiToken, DbErr> {
+        let token = generate_token();
         let active = api_token::ActiveModel {
-            token: Set(token),
+            token_hash: Set(hash_token(&token)),
+            token_prefix: Set(Some(token_prefix(&token))),
+            display_name: Set(data.display_name),
+            tenant_id: Set(Some(data.tenant_id)),
             status: Set(ApiTokenStatus::Active),
             ..Default::default()
         };
 
-        match txn {
-            Some(txn) => active.insert(txn).await,
-            None => active.insert(&self.db).await,
+        let model = match txn {
+            Some(txn) => active.insert(txn).await?,
+            None => active.insert(&self.db).await?,
+        };
+
+        Ok(IssuedApiToken { model, token })
+    }
+
+    ///replaces the secret of a token, keeping its uuid and metadata; the old secret stops working
+    pub async fn rotate_by_uuid(
+        &self,
+        uuid: Uuid,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<IssuedApiToken, ApiTokenError> {
+        let token = generate_token();
+        let patch = UpdateApiToken {
+            token: Some(token.clone()),
+            status: None,
+        };
+
+        match self.update_token_by_uuid(uuid, patch, txn).await? {
+            Some(model) => Ok(IssuedApiToken { model, token }),
+            None => Err(ApiTokenError::NotFound),
         }
     }
 
+    ///revokes a token; requests using it are rejected from then on
+    pub async fn revoke_by_uuid(
+        &self,
+        uuid: Uuid,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<api_token::Model>, ApiTokenError> {
+        let patch = UpdateApiToken {
+            token: None,
+            status: Some(ApiTokenStatus::Inactive),
+        };
+        self.update_token_by_uuid(uuid, patch, txn).await
+    }
+
 
     pub async fn update_token_by_uuid(
         &self,
@@ -142,7 +242,8 @@ impl ApiTokenService {
         let mut new_data: api_token::ActiveModel = model.into();
 
         if let Some(token) = patch.token {
-            new_data.token = Set(token);
+            new_data.token_hash = Set(hash_token(&token));
+            new_data.token_prefix = Set(Some(token_prefix(&token)));
         }
 
         if let Some(status) = patch.status {
@@ -163,16 +264,17 @@ impl ApiTokenService {
         token: &str,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<bool, DbErr> {
-        let model = match self.get_by_token(token, txn).await? {
-            Some(m) => m,
-            None => return Ok(false),
-        };
-
-        if model.status == ApiTokenStatus::Active {
-            return Ok(true);
-        }
+        Ok(self.get_active_by_token(token, txn).await?.is_some())
+    }
 
-        Ok(false)
+    ///the token's row if it exists and is active
+    pub async fn get_active_by_token(
+        &self,
+        token: &str,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<api_token::Model>, DbErr> {
+        let model = self.get_by_token(token, txn).await?;
+        Ok(model.filter(|m| m.status == ApiTokenStatus::Active))
     }
 
 }
@@ -0,0 +1,202 @@
+//! Connection API keys: credentials that only authorize one connection's routes.
+//!
+//! A key (`ck_` followed by 64 hex characters) is accepted by `api_token_auth_middleware`
+//! in place of an API token, but only on connection-scoped routes: the QuickBooks Desktop
+//! poll (`/poll/v1/qbwc`, `/poll/v1/qbwc/receive`) and `/connections/{uuid}/inventory/batch`
+//! of its own connection. A leaked key can't read or change anything else. Like API tokens,
+//! only the sha256 of the key is stored; revoked keys stay listed with `revoked_at` set.
+
+use entity::{connection_api_key, connection_identity};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait,
+    QueryFilter, QueryOrder, QuerySelect, Set,
+};
+use uuid::Uuid;
+
+use super::api_token::{generate_token, hash_token, TOKEN_PREFIX_LEN};
+
+/// Start of every connection API key, telling it apart from API tokens.
+pub const CONNECTION_KEY_PREFIX: &str = "ck_";
+
+
+//DEBUG AND ERRORS ///
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum ConnectionApiKeyError {
+    NotFound,
+    Db(DbErr),
+}
+
+#[allow(dead_code)]
+impl From<DbErr> for ConnectionApiKeyError {
+    fn from(err: DbErr) -> Self {
+        ConnectionApiKeyError::Db(err)
+    }
+}
+
+//END DEBUG AND ERRORS
+
+
+/// BEGUN STRUCTS AND ENUMS ///
+pub struct ConnectionApiKeyService {
+    db: DatabaseConnection,
+}
+
+pub struct CreateConnectionApiKey {
+    pub connection_id: i64,
+    pub display_name: Option<String>,
+}
+
+/// A newly minted key. `key` is the raw secret: it is not stored and can't be read back later.
+pub struct IssuedConnectionApiKey {
+    pub model: connection_api_key::Model,
+    pub key: String,
+}
+
+/// Request extension set by `api_token_auth_middleware` when a request carries a connection
+/// API key: the only connection the request may act on. `AuthenticatedTenant` is set to the
+/// connection's tenant next to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuthenticatedConnection {
+    pub key_id: i64,
+    pub connection_id: i64,
+    pub connection_uuid: Uuid,
+    pub tenant_id: i64,
+}
+
+// END STRUCTS AND ENUMS
+
+
+///whether a raw credential is a connection API key rather than an API token
+pub fn is_connection_key(key: &str) -> bool {
+    key.starts_with(CONNECTION_KEY_PREFIX)
+}
+
+///new random key: `ck_` and a token from `generate_token`
+pub fn generate_key() -> String {
+    format!("{CONNECTION_KEY_PREFIX}{}", generate_token())
+}
+
+///first characters of a key, stored in `key_prefix` to tell keys apart in listings
+pub fn key_prefix(key: &str) -> String {
+    key.chars()
+        .take(CONNECTION_KEY_PREFIX.len() + TOKEN_PREFIX_LEN)
+        .collect()
+}
+
+
+/// BEGUN IMPLEMENTATION ///
+#[allow(dead_code)]
+impl ConnectionApiKeyService {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    ///keys of a connection (metadata only), revoked ones included, oldest first
+    pub async fn get_all(
+        &self,
+        connection_id: i64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Vec<connection_api_key::Model>, DbErr> {
+        let query = connection_api_key::Entity::find()
+            .filter(connection_api_key::Column::ConnectionId.eq(connection_id))
+            .order_by_asc(connection_api_key::Column::Id);
+        match txn {
+            Some(txn) => query.all(txn).await,
+            None => query.all(&self.db).await,
+        }
+    }
+
+    ///mints a key for a connection; the raw key is only in the returned value
+    pub async fn create(
+        &self,
+        data: CreateConnectionApiKey,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<IssuedConnectionApiKey, DbErr> {
+        let key = generate_key();
+        let active = connection_api_key::ActiveModel {
+            connection_id: Set(data.connection_id),
+            key_hash: Set(hash_token(&key)),
+            key_prefix: Set(Some(key_prefix(&key))),
+            display_name: Set(data.display_name),
+            ..Default::default()
+        };
+
+        let model = match txn {
+            Some(txn) => active.insert(txn).await?,
+            None => active.insert(&self.db).await?,
+        };
+
+        Ok(IssuedConnectionApiKey { model, key })
+    }
+
+    ///revokes one of the connection's keys; requests using it are rejected from then on.
+    ///Revoking a revoked key keeps its first `revoked_at`
+    pub async fn revoke(
+        &self,
+        connection_id: i64,
+        uuid: Uuid,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<connection_api_key::Model, ConnectionApiKeyError> {
+        let query = connection_api_key::Entity::find()
+            .filter(connection_api_key::Column::ConnectionId.eq(connection_id))
+            .filter(connection_api_key::Column::Uuid.eq(uuid));
+        let model = match txn {
+            Some(txn) => query.one(txn).await?,
+            None => query.one(&self.db).await?,
+        };
+        let Some(model) = model else {
+            return Err(ConnectionApiKeyError::NotFound);
+        };
+        if model.revoked_at.is_some() {
+            return Ok(model);
+        }
+
+        let now = chrono::Utc::now();
+        let mut active: connection_api_key::ActiveModel = model.into();
+        active.revoked_at = Set(Some(now.into()));
+        active.updated_at = Set(now.into());
+        match txn {
+            Some(txn) => Ok(active.update(txn).await?),
+            None => Ok(active.update(&self.db).await?),
+        }
+    }
+
+    ///the connection a raw key authorizes; None for an unknown or revoked key, or one whose
+    ///connection is gone
+    pub async fn authenticate(
+        &self,
+        key: &str,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<AuthenticatedConnection>, DbErr> {
+        let query = connection_api_key::Entity::find()
+            .filter(connection_api_key::Column::KeyHash.eq(hash_token(key)))
+            .filter(connection_api_key::Column::RevokedAt.is_null());
+        let model = match txn {
+            Some(txn) => query.one(txn).await?,
+            None => query.one(&self.db).await?,
+        };
+        let Some(model) = model else {
+            return Ok(None);
+        };
+
+        let connection = connection_identity::Entity::find_by_id(model.connection_id)
+            .select_only()
+            .column(connection_identity::Column::Uuid)
+            .column(connection_identity::Column::TenantId)
+            .into_tuple::<(Uuid, i64)>();
+        let connection = match txn {
+            Some(txn) => connection.one(txn).await?,
+            None => connection.one(&self.db).await?,
+        };
+
+        Ok(connection.map(|(connection_uuid, tenant_id)| AuthenticatedConnection {
+            key_id: model.id,
+            connection_id: model.connection_id,
+            connection_uuid,
+            tenant_id,
+        }))
+    }
+}
+
+// END IMPLEMENTATION
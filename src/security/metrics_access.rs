@@ -0,0 +1,106 @@
+//! Who may scrape `/metrics`.
+//!
+//! Open to everyone unless `METRICS_ALLOWED_CIDRS` or `METRICS_REQUIRE_AUTH` is set; then a
+//! scrape is let through when its client IP falls in one of the ranges, or when it presents
+//! `Authorization: Bearer <METRICS_REQUIRE_AUTH>`. The client IP is the connecting peer; only
+//! when that peer is a trusted proxy is `X-Forwarded-For` consulted, walked from the right like
+//! the IP address middleware, so a scraper can't claim an allowed address itself.
+
+use axum::http::header::AUTHORIZATION;
+use axum::http::HeaderMap;
+use std::net::IpAddr;
+
+use crate::security::admin_token::admin_token_matches;
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// An IP range such as `10.0.0.0/8` or `fd00::/8`; a bare address is a single-host range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IpCidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl IpCidr {
+    pub fn parse(value: &str) -> Option<Self> {
+        let (addr, prefix) = match value.trim().split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+            None => (value.trim(), None),
+        };
+        let network: IpAddr = addr.parse().ok()?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = prefix.unwrap_or(max);
+        (prefix <= max).then_some(Self { network, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+///parses METRICS_ALLOWED_CIDRS entries, dropping (and logging) the ones that aren't ranges
+pub fn parse_cidrs(values: &[String]) -> Vec<IpCidr> {
+    values
+        .iter()
+        .filter_map(|value| {
+            let cidr = IpCidr::parse(value);
+            if cidr.is_none() {
+                tracing::warn!(value = %value, "Ignoring invalid METRICS_ALLOWED_CIDRS entry");
+            }
+            cidr
+        })
+        .collect()
+}
+
+///the scraper's IP: the peer, or the rightmost untrusted X-Forwarded-For entry when the peer
+///is a trusted proxy
+pub fn scraper_ip(peer: Option<IpAddr>, headers: &HeaderMap, trusted: &[String]) -> Option<IpAddr> {
+    let is_trusted = |ip: &IpAddr| trusted.iter().any(|t| t.parse::<IpAddr>().is_ok_and(|t| t == *ip));
+    let peer = peer?;
+    if !is_trusted(&peer) {
+        return Some(peer);
+    }
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    forwarded
+        .split(',')
+        .rev()
+        .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .find(|ip| !is_trusted(ip))
+        .or(Some(peer))
+}
+
+///whether a scrape from `ip` with `headers` may read /metrics; always true when neither
+///ranges nor a token are configured
+pub fn metrics_access_allowed(
+    ip: Option<IpAddr>,
+    headers: &HeaderMap,
+    allowed_cidrs: &[IpCidr],
+    token: Option<&str>,
+) -> bool {
+    if allowed_cidrs.is_empty() && token.is_none() {
+        return true;
+    }
+    if ip.is_some_and(|ip| allowed_cidrs.iter().any(|cidr| cidr.contains(ip))) {
+        return true;
+    }
+    let presented = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    admin_token_matches(token, presented)
+}
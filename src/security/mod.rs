@@ -1,6 +1,14 @@
 pub mod routes;
+pub mod allowed_origin_routes;
+pub mod admin_token;
 pub mod api_token;
+pub mod connection_api_key;
 pub mod allowed_ip_addresses;
+pub mod allowed_origins;
+pub mod metrics_access;
+pub mod rate_limit;
 
-pub use api_token::ApiTokenService;
+pub use api_token::{ApiTokenService, AuthenticatedTenant, AuthenticatedToken};
+pub use connection_api_key::{AuthenticatedConnection, ConnectionApiKeyService};
 pub use allowed_ip_addresses::AllowedIpAddressService;
+pub use allowed_origins::AllowedOriginService;
@@ -0,0 +1,159 @@
+//! Per-tenant request rate limiting.
+//!
+//! A sliding-window log: every allowed request is recorded with its timestamp, and a request
+//! is refused while `limit` requests already fall within the last `window`. Refused requests
+//! are not recorded, so a client that keeps retrying is let through again as soon as the
+//! oldest request in the window ages out. Limiter failures are logged and the request is
+//! allowed, so a Redis outage can't take the API down with it.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use redis::aio::ConnectionManager;
+use uuid::Uuid;
+
+use crate::clock::{self, SharedClock};
+
+const KEY_PREFIX: &str = "rate_limit:tenant:";
+
+/// Trims the window, then records the request if there is room. Returns 0 when the request
+/// is allowed, otherwise the milliseconds until the oldest request leaves the window.
+const SLIDING_WINDOW_SCRIPT: &str = r#"
+local key = KEYS[1]
+local now = tonumber(ARGV[1])
+local window = tonumber(ARGV[2])
+local limit = tonumber(ARGV[3])
+redis.call('ZREMRANGEBYSCORE', key, '-inf', now - window)
+if redis.call('ZCARD', key) < limit then
+    redis.call('ZADD', key, now, ARGV[4])
+    redis.call('PEXPIRE', key, window)
+    return 0
+end
+local oldest = redis.call('ZRANGE', key, 0, 0, 'WITHSCORES')
+return math.max(tonumber(oldest[2]) + window - now, 1)
+"#;
+
+/// BEGUN STRUCTS AND ENUMS ///
+#[derive(Clone)]
+pub enum RateLimitStore {
+    /// Shared across instances via `AppState.redis` (one sorted set per tenant).
+    Redis(ConnectionManager),
+    /// In-process only; for single-instance setups and tests.
+    Local(Arc<Mutex<HashMap<String, VecDeque<DateTime<Utc>>>>>),
+}
+
+#[derive(Clone)]
+pub struct RateLimiter {
+    store: RateLimitStore,
+    /// Requests allowed per window.
+    limit: u64,
+    window: Duration,
+    clock: SharedClock,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitDecision {
+    Allowed,
+    /// Over the limit; a request is accepted again after `retry_after`.
+    Limited { retry_after: Duration },
+}
+
+/// BEGUN IMPLEMENTATION ///
+///health checks and metrics scrapes are never limited; `base_url` is stripped first
+pub fn is_exempt_route(path: &str, base_url: Option<&str>) -> bool {
+    let effective_path = base_url
+        .and_then(|base| path.strip_prefix(base))
+        .unwrap_or(path);
+
+    ["/", "/healthcheck", "/metrics"].contains(&effective_path)
+}
+
+impl RateLimitStore {
+    pub fn local() -> Self {
+        RateLimitStore::Local(Arc::new(Mutex::new(HashMap::new())))
+    }
+}
+
+impl RateLimitDecision {
+    ///whole seconds for the `Retry-After` header (rounded up, at least 1)
+    pub fn retry_after_secs(&self) -> Option<u64> {
+        match self {
+            RateLimitDecision::Allowed => None,
+            RateLimitDecision::Limited { retry_after } => {
+                Some(retry_after.as_millis().div_ceil(1000).max(1) as u64)
+            }
+        }
+    }
+}
+
+impl RateLimiter {
+    pub fn new(store: RateLimitStore, limit: u64, window: Duration) -> Self {
+        Self {
+            store,
+            limit,
+            window,
+            clock: clock::system(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    fn key(tenant_id: i64) -> String {
+        format!("{}{}", KEY_PREFIX, tenant_id)
+    }
+
+    ///records a request for `tenant_id` if the tenant is within its limit
+    pub async fn check(&self, tenant_id: i64) -> RateLimitDecision {
+        let now = self.clock.now();
+        let window_ms = self.window.as_millis() as i64;
+
+        match &self.store {
+            RateLimitStore::Redis(redis) => {
+                let mut conn = redis.clone();
+                let result = redis::Script::new(SLIDING_WINDOW_SCRIPT)
+                    .key(Self::key(tenant_id))
+                    .arg(now.timestamp_millis())
+                    .arg(window_ms)
+                    .arg(self.limit)
+                    .arg(Uuid::new_v4().to_string())
+                    .invoke_async::<i64>(&mut conn)
+                    .await;
+                match result {
+                    Ok(0) => RateLimitDecision::Allowed,
+                    Ok(wait_ms) => RateLimitDecision::Limited {
+                        retry_after: Duration::from_millis(wait_ms.max(1) as u64),
+                    },
+                    Err(e) => {
+                        tracing::warn!("Rate limiter check failed, allowing request: {}", e);
+                        RateLimitDecision::Allowed
+                    }
+                }
+            }
+            RateLimitStore::Local(map) => {
+                let Ok(mut map) = map.lock() else {
+                    return RateLimitDecision::Allowed;
+                };
+                let window_start = now - chrono::Duration::milliseconds(window_ms);
+                let requests = map.entry(Self::key(tenant_id)).or_default();
+                while requests.front().is_some_and(|at| *at <= window_start) {
+                    requests.pop_front();
+                }
+
+                if (requests.len() as u64) < self.limit {
+                    requests.push_back(now);
+                    return RateLimitDecision::Allowed;
+                }
+                let oldest = requests.front().copied().unwrap_or(now);
+                let wait = (oldest - window_start).to_std().unwrap_or_default();
+                RateLimitDecision::Limited {
+                    retry_after: wait.max(Duration::from_millis(1)),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,255 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use entity::api_token;
+use entity::sea_orm_active_enums::ApiTokenStatusEnum;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::AppState;
+use crate::tenant::routes::{DeleteResponse, ErrorResponse};
+use crate::tenant::TenantService;
+use crate::validation::{check_length, FieldError, MAX_DISPLAY_NAME_LEN};
+use super::api_token::{ApiTokenError, ApiTokenService, CreateApiToken, IssuedApiToken};
+
+
+/// RESPONSE SCHEMAS ///
+/// Token metadata; the secret itself is never returned after creation.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct ApiTokenResponse {
+    pub uuid: String,
+    pub tenant_id: Option<i64>,
+    pub display_name: Option<String>,
+    /// First characters of the token, to tell tokens apart.
+    pub token_prefix: Option<String>,
+    #[schema(value_type = String)]
+    pub status: ApiTokenStatusEnum,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct IssuedApiTokenResponse {
+    /// The raw token. Shown only in this response: store it now, it can't be read back.
+    pub token: String,
+    #[serde(flatten)]
+    pub metadata: ApiTokenResponse,
+}
+
+
+/// REQUEST SCHEMAS ///
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CreateApiTokenRequest {
+    /// Tenant the token belongs to (TN_xxx format).
+    pub tenant_id: String,
+    pub display_name: Option<String>,
+}
+
+impl CreateApiTokenRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_length(&mut errors, "display_name", self.display_name.as_deref(), MAX_DISPLAY_NAME_LEN);
+        errors
+    }
+}
+
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct ListApiTokensQuery {
+    /// Only tokens of this tenant (TN_xxx format).
+    pub tenant_id: Option<String>,
+}
+
+
+/// HELPERS ///
+pub fn model_to_response(model: api_token::Model) -> ApiTokenResponse {
+    ApiTokenResponse {
+        uuid: model.uuid.to_string(),
+        tenant_id: model.tenant_id,
+        display_name: model.display_name,
+        token_prefix: model.token_prefix,
+        status: model.status,
+        created_at: model.created_at.to_rfc3339(),
+        updated_at: model.updated_at.to_rfc3339(),
+    }
+}
+
+pub fn issued_to_response(issued: IssuedApiToken) -> IssuedApiTokenResponse {
+    IssuedApiTokenResponse {
+        token: issued.token,
+        metadata: model_to_response(issued.model),
+    }
+}
+
+fn error(status: StatusCode, message: impl Into<String>) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        status,
+        Json(ErrorResponse {
+            error: message.into(),
+            details: None,
+        }),
+    )
+}
+
+fn not_found() -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::NOT_FOUND, "API token not found")
+}
+
+fn db_error(e: impl std::fmt::Display) -> (StatusCode, Json<ErrorResponse>) {
+    error(StatusCode::INTERNAL_SERVER_ERROR, format!("Database error: {}", e))
+}
+
+///resolves a TN_xxx tenant id to its row id
+async fn resolve_tenant(
+    state: &AppState,
+    tenant_id: &str,
+) -> Result<i64, (StatusCode, Json<ErrorResponse>)> {
+    match TenantService::new(state.db.clone())
+        .get_by_tenant_id(tenant_id, None)
+        .await
+    {
+        Ok(Some(tenant)) => Ok(tenant.id),
+        Ok(None) => Err(error(StatusCode::NOT_FOUND, "Tenant not found")),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+
+/// ROUTE HANDLERS ///
+
+#[utoipa::path(
+    get,
+    path = "/admin/api-tokens",
+    tag = "Admin",
+    params(ListApiTokensQuery),
+    responses(
+        (status = 200, description = "API token metadata", body = Vec<ApiTokenResponse>),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Tenant not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn list_api_tokens(
+    State(state): State<AppState>,
+    Query(query): Query<ListApiTokensQuery>,
+) -> Result<Json<Vec<ApiTokenResponse>>, (StatusCode, Json<ErrorResponse>)> {
+    let tenant_id = match &query.tenant_id {
+        Some(tenant_id) => Some(resolve_tenant(&state, tenant_id).await?),
+        None => None,
+    };
+
+    match ApiTokenService::new(state.db).get_all(tenant_id, None).await {
+        Ok(tokens) => Ok(Json(tokens.into_iter().map(model_to_response).collect())),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/api-tokens",
+    tag = "Admin",
+    request_body = CreateApiTokenRequest,
+    responses(
+        (status = 201, description = "Token issued; the raw token is only returned here", body = IssuedApiTokenResponse),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Tenant not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn create_api_token(
+    State(state): State<AppState>,
+    Json(body): Json<CreateApiTokenRequest>,
+) -> Result<(StatusCode, Json<IssuedApiTokenResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let errors = body.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let tenant_id = resolve_tenant(&state, &body.tenant_id).await?;
+    let data = CreateApiToken {
+        tenant_id,
+        display_name: body.display_name,
+    };
+
+    match ApiTokenService::new(state.db).create(data, None).await {
+        Ok(issued) => Ok((StatusCode::CREATED, Json(issued_to_response(issued)))),
+        Err(e) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/api-tokens/{uuid}/rotate",
+    tag = "Admin",
+    params(
+        ("uuid" = String, Path, description = "API token UUID")
+    ),
+    responses(
+        (status = 200, description = "New secret issued; the previous one stops working", body = IssuedApiTokenResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "API token not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn rotate_api_token(
+    State(state): State<AppState>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<IssuedApiTokenResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match ApiTokenService::new(state.db).rotate_by_uuid(uuid, None).await {
+        Ok(issued) => Ok(Json(issued_to_response(issued))),
+        Err(ApiTokenError::NotFound) => Err(not_found()),
+        Err(ApiTokenError::Db(e)) => Err(db_error(e)),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/admin/api-tokens/{uuid}",
+    tag = "Admin",
+    params(
+        ("uuid" = String, Path, description = "API token UUID")
+    ),
+    responses(
+        (status = 200, description = "Token revoked (status set to inactive)", body = DeleteResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "API token not found", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn revoke_api_token(
+    State(state): State<AppState>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<DeleteResponse>, (StatusCode, Json<ErrorResponse>)> {
+    match ApiTokenService::new(state.db).revoke_by_uuid(uuid, None).await {
+        Ok(Some(_)) => Ok(Json(DeleteResponse {
+            message: "API token revoked successfully".to_string(),
+        })),
+        Ok(None) | Err(ApiTokenError::NotFound) => Err(not_found()),
+        Err(ApiTokenError::Db(e)) => Err(db_error(e)),
+    }
+}
+
+
+/// ROUTER ///
+///mounted at /admin/api-tokens behind the admin token middleware
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(list_api_tokens).post(create_api_token))
+        .route("/{uuid}", delete(revoke_api_token))
+        .route("/{uuid}/rotate", post(rotate_api_token))
+        .layer(axum::middleware::from_fn(
+            crate::middleware::admin_token_auth_middleware,
+        ))
+}
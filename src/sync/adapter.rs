@@ -0,0 +1,138 @@
+//! Provider side of a pull sync.
+//!
+//! A `PullAdapter` only knows its wire protocol: how to build the next request from the
+//! stored cursor and how to apply a response. Sync events, connection runs, locking and
+//! backoff are handled once by `SyncOrchestrator` for every provider.
+
+use std::future::Future;
+
+use entity::{connection_identity, erp_connection_sync_state, sync_event};
+use sea_orm::{DatabaseTransaction, DbErr};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::validation::FieldError;
+
+//DEBUG AND ERRORS ///
+#[derive(Debug)]
+pub enum PullError {
+    ///the provider returned an error or a payload the adapter could not apply
+    Provider(String),
+    ///the provider no longer accepts the stored cursor (e.g. an expired QBD iterator);
+    ///pagination restarts from the first page instead of failing the event
+    CursorExpired(String),
+    ///the provider answered with an error status it classifies (e.g. a QBD status code);
+    ///the code is kept on the event's `last_error`
+    Status(ProviderStatus),
+    Db(DbErr),
+}
+
+/// Error status reported by the provider for a page.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProviderStatus {
+    pub code: String,
+    pub message: String,
+    /// Whether resending the request can succeed.
+    pub retryable: bool,
+}
+
+impl From<DbErr> for PullError {
+    fn from(err: DbErr) -> Self {
+        PullError::Db(err)
+    }
+}
+
+impl std::fmt::Display for PullError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PullError::Provider(msg) => write!(f, "{}", msg),
+            PullError::CursorExpired(msg) => write!(f, "Cursor expired: {}", msg),
+            PullError::Status(status) => write!(f, "{}", status.message),
+            PullError::Db(e) => write!(f, "Database error: {}", e),
+        }
+    }
+}
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Request the caller sends to the provider (e.g. QBXML for QuickBooks Desktop).
+#[derive(Debug, Clone, PartialEq)]
+pub struct PullRequest {
+    pub payload: String,
+}
+
+/// Provider reply handed back to the orchestrator.
+#[derive(Debug, Clone, Default)]
+pub struct PullResponse {
+    /// Raw response body; `None` when the provider sent nothing.
+    pub body: Option<String>,
+    /// Error reported by the provider instead of a body.
+    pub error: Option<String>,
+}
+
+/// Result of applying one response page.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PullOutcome {
+    /// Cursor for the next page; `None` once pagination is complete.
+    pub cursor: Option<Value>,
+    /// Per-record failures; the page itself was still applied.
+    pub errors: Vec<String>,
+    /// Records skipped because their payload failed validation.
+    pub rejected: Vec<RejectedRecord>,
+    /// Records the page created or updated; the orchestrator links each to a sync event.
+    pub applied: Vec<AppliedRecord>,
+    /// Notes the provider attached to an otherwise successful page (e.g. QBD warnings).
+    pub warnings: Vec<String>,
+}
+
+/// A record written by the page, with the inventory record event holding its data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppliedRecord {
+    /// Provider id of the record (e.g. QBD ListID).
+    pub record_id: String,
+    pub inventory_record_event_id: i64,
+    /// True when the record did not exist before this page.
+    pub created: bool,
+}
+
+/// A record left out of the upsert, with the field-level reasons.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RejectedRecord {
+    /// Provider id of the record (e.g. QBD ListID).
+    pub record_id: String,
+    pub errors: Vec<FieldError>,
+}
+
+impl PullOutcome {
+    pub fn has_more(&self) -> bool {
+        self.cursor.is_some()
+    }
+}
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+/// Protocol specifics of one provider's pull.
+pub trait PullAdapter: Send + Sync {
+    ///builds the request for `event`, continuing from `sync_state.sync_cursor` when it applies
+    fn next_request(
+        &self,
+        conn: &connection_identity::Model,
+        sync_state: &erp_connection_sync_state::Model,
+        event: &sync_event::Model,
+    ) -> PullRequest;
+
+    ///applies a response body inside the orchestrator's transaction and returns the next cursor
+    fn apply_response(
+        &self,
+        conn: &connection_identity::Model,
+        event: &sync_event::Model,
+        body: &str,
+        txn: &DatabaseTransaction,
+    ) -> impl Future<Output = Result<PullOutcome, PullError>> + Send;
+
+    ///whether `event` is the one the stored cursor belongs to, so its pagination is
+    ///finished before another event starts
+    fn resumes_cursor(&self, event: &sync_event::Model, cursor: &Value) -> bool {
+        let _ = (event, cursor);
+        false
+    }
+}
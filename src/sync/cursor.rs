@@ -0,0 +1,73 @@
+//! Typed `erp_connection_sync_state.sync_cursor`.
+//!
+//! The cursor is stored as plain JSON without a tag: its fields tell the variants apart, so
+//! cursors written before this type existed still read back. A stored cursor matching no
+//! variant (a bug, a manual edit) is logged and read as no cursor, so the pass starts over
+//! rather than continuing from whatever part of it happened to parse.
+
+use chrono::{DateTime, Utc};
+use entity::erp_connection_sync_state;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Where a paginated pull continues from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SyncCursor {
+    /// QuickBooks Desktop item query iterator.
+    QbdIterator {
+        /// QBD's `iteratorID`, sent back with `iterator="Continue"`.
+        iterator_id: Option<String>,
+        /// Items QBD reported remaining after the last page.
+        remaining_count: i64,
+        /// Item list being paginated (`details.item_type`); inventory when unset.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        item_type: Option<String>,
+        /// ListIDs already applied in this pass. Not written by the QBD pull yet.
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        seen_ids: Vec<String>,
+        /// `FromModifiedDate` the pass was started with; None for a full pass. Not written
+        /// by the QBD pull yet.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        from_modified_date: Option<DateTime<Utc>>,
+    },
+    /// Offset into a REST listing, e.g. the SAP Service Layer `$skip`.
+    RestOffset { skip: u64 },
+}
+
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl SyncCursor {
+    ///reads a cursor from its stored JSON
+    pub fn parse(value: &Value) -> Result<Self, serde_json::Error> {
+        Self::deserialize(value)
+    }
+
+    ///the cursor stored on `sync_state`; None without one, and (with a warning) when the
+    ///stored JSON is not a cursor, which makes the next request start the pass over
+    pub fn of(sync_state: &erp_connection_sync_state::Model) -> Option<Self> {
+        let value = sync_state.sync_cursor.as_ref()?;
+        match Self::parse(value) {
+            Ok(cursor) => Some(cursor),
+            Err(e) => {
+                tracing::warn!(
+                    connection_id = sync_state.connection_id,
+                    sync_state_id = sync_state.id,
+                    cursor = %value,
+                    error = %e,
+                    "Malformed sync cursor; starting the pass over"
+                );
+                None
+            }
+        }
+    }
+
+    ///the JSON stored in `sync_cursor`
+    pub fn to_value(&self) -> Value {
+        serde_json::to_value(self).unwrap_or_default()
+    }
+}
+
+// END IMPLEMENTATION
@@ -0,0 +1,88 @@
+//! Why a poll did (or didn't) hand out work.
+//!
+//! Every poll ends in exactly one `PollDecision`, logged once through `PollDecision::log`
+//! with the connection id, the decision and its reason as structured fields, e.g.
+//! `connection_id=7 decision="disabled" reason="connection is disabled"`. The QBD poll service
+//! decides the checks in front of the orchestrator (disabled, environment, order push);
+//! `SyncOrchestrator::next_request` decides the rest.
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Outcome of one poll for a connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollDecision {
+    /// The connection is disabled; no work.
+    Disabled,
+    /// The connection belongs to another environment than the server's; no work.
+    WrongEnvironment,
+    /// A pending order push was handed out instead of a pull.
+    OrderPush,
+    /// `rate_limit_backoff_until` is in the future; no work.
+    BackedOff,
+    /// Outside the connection's sync window; no work.
+    OutsideSyncWindow,
+    /// Another run holds an unexpired sync lock; no work.
+    LockNotAcquired,
+    /// No recurring event could be created or found to run; no work.
+    NoDueEvent,
+    /// First pull for the connection: its recurring event was created.
+    CreatedEvent,
+    /// The event the stored cursor belongs to was resumed.
+    ContinuedCursor,
+    /// A ready event was started from the beginning.
+    StartedEvent,
+}
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl PollDecision {
+    ///snake_case name logged as the `decision` field
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Disabled => "disabled",
+            Self::WrongEnvironment => "wrong_environment",
+            Self::OrderPush => "order_push",
+            Self::BackedOff => "backed_off",
+            Self::OutsideSyncWindow => "outside_sync_window",
+            Self::LockNotAcquired => "lock_not_acquired",
+            Self::NoDueEvent => "no_due_event",
+            Self::CreatedEvent => "created_event",
+            Self::ContinuedCursor => "continued_cursor",
+            Self::StartedEvent => "started_event",
+        }
+    }
+
+    ///human-readable reason logged as the `reason` field
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Disabled => "connection is disabled",
+            Self::WrongEnvironment => "connection is outside the server's environment",
+            Self::OrderPush => "an order push is pending",
+            Self::BackedOff => "backing off after a failed pull",
+            Self::OutsideSyncWindow => "outside the connection's sync window",
+            Self::LockNotAcquired => "sync lock held by another run",
+            Self::NoDueEvent => "no sync event is due",
+            Self::CreatedEvent => "created the recurring sync event",
+            Self::ContinuedCursor => "continuing the stored cursor",
+            Self::StartedEvent => "started the next ready sync event",
+        }
+    }
+
+    ///whether the poll hands out a request
+    pub fn has_work(&self) -> bool {
+        matches!(
+            self,
+            Self::OrderPush | Self::CreatedEvent | Self::ContinuedCursor | Self::StartedEvent
+        )
+    }
+
+    ///log the decision for `connection_id`
+    pub fn log(&self, connection_id: i64) {
+        tracing::info!(
+            connection_id,
+            decision = self.as_str(),
+            reason = self.reason(),
+            has_work = self.has_work(),
+            "Poll decision"
+        );
+    }
+}
@@ -0,0 +1,18 @@
+pub mod adapter;
+pub mod cursor;
+pub mod decision;
+pub mod orchestrator;
+pub mod retry;
+pub mod scheduler;
+pub mod window;
+
+pub use adapter::{
+    AppliedRecord, ProviderStatus, PullAdapter, PullError, PullOutcome, PullRequest, PullResponse,
+    RejectedRecord,
+};
+pub use cursor::SyncCursor;
+pub use decision::PollDecision;
+pub use orchestrator::SyncOrchestrator;
+pub use scheduler::{ApiPullAdapter, PollScheduler, ScheduledPull, TickSummary};
+pub use retry::{retry_on_transient, RetryPolicy, Transient, DEFAULT_TRANSIENT_RETRIES};
+pub use window::{in_sync_window, SyncWindow};
@@ -0,0 +1,754 @@
+//! Provider-agnostic bookkeeping for pull syncs.
+//!
+//! `SyncOrchestrator` drives a `PullAdapter` through the two phases of a pull:
+//!
+//! **`next_request`**
+//!   1. Ensure an `erp_connection_sync_state` row exists for the connection
+//!   2. Skip (no work) while `rate_limit_backoff_until` is in the future, or outside the
+//!      connection's sync window (`crate::sync::window`); a pass paused by the window keeps
+//!      its cursor and resumes when the window opens
+//!   3. Take the sync lock for a new run (`sync_lock_owner` = run uuid, leased for
+//!      `PULL_LOCK_LEASE_SECS`) and create its `ConnectionRun`; skip, creating no run, if
+//!      another run holds an unexpired lock.
+//!      Stamp `last_polled_at` and schedule `next_poll_at` one poll interval out
+//!   4. Pick the recurring List/Inventory sync event: the one the stored cursor belongs to
+//!      first, otherwise the least recently run. Events left InProgress by an abandoned run
+//!      are eligible again once the lock is ours. Create one if none exists; the insert is
+//!      `ON CONFLICT DO NOTHING` on the recurring-event unique index, so when a concurrent
+//!      first pull created it in the meantime, that event is used instead of a duplicate.
+//!   5. Mark it InProgress, increment attempts, link it to the run
+//!   6. Ask the adapter for the request
+//!
+//!   Which of these ended the poll is logged as a `PollDecision` (`crate::sync::decision`).
+//!
+//! **`apply_response`**
+//!   1. Find the InProgress event and its run
+//!   2. Provider error or adapter failure → event + run Error, release the lock and back
+//!      off exponentially on the event's attempts (`next_poll_at` = end of the backoff).
+//!      A classified provider status (`PullError::Status`) also stores its code and
+//!      `retryable` in the event's `last_error`
+//!   3. Expired cursor (`PullError::CursorExpired`) → cursor cleared, event back to Pending,
+//!      run Error noting the restart, lock released with no backoff (`next_poll_at` = now)
+//!   4. Otherwise apply the page in one transaction: adapter writes, new cursor, event back
+//!      to Pending (List events are recurring), record errors, validation rejections and
+//!      provider warnings in `last_error`, run Error when records failed or were rejected, lock released and
+//!      backoff cleared. `next_poll_at` is now while pages remain, otherwise one poll
+//!      interval out. Every record the page wrote gets its own Success sync event (method
+//!      Create/Update) linked to its `inventory_record_event` and the run, so a record
+//!      change can be traced back to the pull that produced it. The page transaction is rerun on deadlocks and serialization
+//!      failures (`retry_on_transient`, `with_retry`)
+//!
+//! Lock leases, backoff and poll scheduling read the time from the orchestrator's `Clock`
+//! (`with_clock`; the system clock by default).
+
+use entity::sea_orm_active_enums::{
+    ConnectionRunStatus, ConnectionRunType, SyncEventCategory, SyncEventDirection,
+    SyncEventMethod, SyncEventStatus,
+};
+use entity::{connection_identity, connection_run, erp_connection_sync_state, sync_event};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
+    EntityTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
+};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::clock::{self, SharedClock};
+use crate::connection_run::services::{
+    ConnectionRunError, ConnectionRunService, CreateConnectionRun, UpdateConnectionRun,
+};
+use crate::erp_connection_sync_state::services::{
+    compute_next_poll_at, CreateErpConnectionSyncState, ErpConnectionSyncStateService,
+};
+use crate::sync_event::services::{
+    CreateSyncEvent, SyncEventError, SyncEventService, UpdateSyncEvent,
+};
+
+use super::decision::PollDecision;
+use super::adapter::{
+    AppliedRecord, ProviderStatus, PullAdapter, PullError, PullOutcome, PullRequest,
+    PullResponse, RejectedRecord,
+};
+use super::retry::{retry_on_transient, RetryPolicy};
+use super::window::in_sync_window;
+
+/// How long a run may hold the sync lock before another poll can take over.
+pub const PULL_LOCK_LEASE_SECS: i64 = 300;
+
+/// Backoff after the first failure; doubles per attempt.
+const BASE_BACKOFF_SECS: i64 = 30;
+
+/// Upper bound for the backoff between failed pulls.
+const MAX_BACKOFF_SECS: i64 = 3600;
+
+///backoff before retrying a pull that failed on its `attempts`-th try
+pub fn backoff_delay(attempts: i32) -> chrono::Duration {
+    let exp = attempts.saturating_sub(1).clamp(0, 16) as u32;
+    let secs = BASE_BACKOFF_SECS.saturating_mul(2i64.pow(exp));
+    chrono::Duration::seconds(secs.min(MAX_BACKOFF_SECS))
+}
+
+/// Summary stored on the run: record errors, then the number of rejected records.
+fn run_error_message(errors: &[String], rejected: &[RejectedRecord]) -> String {
+    let mut parts = errors.to_vec();
+    if !rejected.is_empty() {
+        parts.push(format!("{} record(s) failed validation", rejected.len()));
+    }
+    parts.join("; ")
+}
+
+/// `DbErr` for a failed sync event update; the event was read under the run's lock, so a
+/// missing one means the update didn't happen.
+fn sync_event_db_err(e: SyncEventError) -> DbErr {
+    match e {
+        SyncEventError::Db(e) => e,
+        _ => DbErr::RecordNotUpdated,
+    }
+}
+
+/// `DbErr` for a failed connection run update, as for `sync_event_db_err`.
+fn run_db_err(e: ConnectionRunError) -> DbErr {
+    match e {
+        ConnectionRunError::Db(e) => e,
+        ConnectionRunError::NotFound => DbErr::RecordNotUpdated,
+    }
+}
+
+/// Mark a picked event InProgress for `run_id` and count the attempt.
+async fn start_event(
+    sync_event_svc: &SyncEventService,
+    event: sync_event::Model,
+    run_id: i64,
+    txn: &DatabaseTransaction,
+) -> Result<sync_event::Model, DbErr> {
+    let updated = sync_event_svc
+        .update_by_uuid(
+            event.uuid,
+            UpdateSyncEvent {
+                status: Some(SyncEventStatus::InProgress),
+                attempts: Some(event.attempts + 1),
+                connection_run_id: Some(run_id),
+                original_record_body: None,
+                details: None,
+                event_direction: None,
+                inventory_record_event_id: None,
+                sync_event_method: None,
+                sync_event_category: None,
+                last_error: None,
+                last_errored_date: None,
+                connection_sync_state_id: None,
+            },
+            Some(txn),
+        )
+        .await
+        .map_err(sync_event_db_err)?;
+    Ok(updated.unwrap_or(event))
+}
+
+/// Tag the caller's span (e.g. `qbd.poll.request`) with the event being synced; no-op when
+/// the span has no `event_uuid` field.
+fn record_event_uuid(event: &sync_event::Model) {
+    tracing::Span::current().record("event_uuid", tracing::field::display(event.uuid));
+}
+
+pub struct SyncOrchestrator<'a, A: PullAdapter> {
+    db: DatabaseConnection,
+    adapter: &'a A,
+    clock: SharedClock,
+    retry: RetryPolicy,
+}
+
+impl<'a, A: PullAdapter> SyncOrchestrator<'a, A> {
+    pub fn new(db: DatabaseConnection, adapter: &'a A) -> Self {
+        Self {
+            db,
+            adapter,
+            clock: clock::system(),
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Read the time from `clock` for locks, backoff and poll scheduling.
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Retry the page transaction on transient database errors according to `retry`.
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    fn sync_states(&self) -> ErpConnectionSyncStateService {
+        ErpConnectionSyncStateService::new(self.db.clone()).with_clock(self.clock.clone())
+    }
+
+    ///the connection's sync state, created on its first poll; two concurrent first polls
+    ///converge on the same row (insert-or-ignore on the unique `connection_id`)
+    pub async fn ensure_sync_state(
+        &self,
+        connection_id: i64,
+    ) -> Result<erp_connection_sync_state::Model, DbErr> {
+        let svc = self.sync_states();
+        if let Some(s) = svc.get_by_connection_id(connection_id, None).await? {
+            return Ok(s);
+        }
+        svc.create_if_missing(
+            CreateErpConnectionSyncState {
+                connection_id,
+                sync_cursor: None,
+                sync_lock_owner: None,
+                sync_lock_until: None,
+                rate_limit_remaining: None,
+                rate_limit: None,
+                rate_limit_reset_at: None,
+                rate_limit_backoff_until: None,
+                rate_limit_window_seconds: None,
+                poll_interval_seconds: None,
+            },
+            None,
+        )
+        .await
+    }
+
+    // ── Request phase ─────────────────────────────────────────────────────────
+
+    /// Start (or continue) a pull and return the provider request.
+    /// `None` means there is nothing to do right now (backing off or locked by another run).
+    /// The `PollDecision` behind the answer is logged.
+    pub async fn next_request(
+        &self,
+        conn: &connection_identity::Model,
+    ) -> Result<Option<PullRequest>, DbErr> {
+        let (decision, request) = self.decide_request(conn).await?;
+        decision.log(conn.id);
+        Ok(request)
+    }
+
+    async fn decide_request(
+        &self,
+        conn: &connection_identity::Model,
+    ) -> Result<(PollDecision, Option<PullRequest>), DbErr> {
+        let sync_state = self.ensure_sync_state(conn.id).await?;
+        let now = self.clock.now();
+
+        if self.sync_states().is_backing_off(&sync_state) {
+            tracing::debug!(
+                connection_id = conn.id,
+                "Pull skipped: backing off until {:?}",
+                sync_state.rate_limit_backoff_until
+            );
+            return Ok((PollDecision::BackedOff, None));
+        }
+        if !in_sync_window(conn, now) {
+            return Ok((PollDecision::OutsideSyncWindow, None));
+        }
+
+        let run_svc = ConnectionRunService::new(self.db.clone());
+        let sync_event_svc = SyncEventService::new(self.db.clone());
+
+        //the lock is taken first, so a skipped pull leaves no run behind (or counted)
+        let run_uuid = Uuid::new_v4();
+        let txn = self.db.begin().await?;
+        let locked = self
+            .sync_states()
+            .try_acquire_lock(
+                sync_state.id,
+                &run_uuid.to_string(),
+                chrono::Duration::seconds(PULL_LOCK_LEASE_SECS),
+                &txn,
+            )
+            .await?;
+        if !locked {
+            txn.rollback().await?;
+            return Ok((PollDecision::LockNotAcquired, None));
+        }
+
+        let run = run_svc
+            .create(
+                CreateConnectionRun {
+                    uuid: Some(run_uuid),
+                    connection_id: conn.id,
+                    status: Some(ConnectionRunStatus::Success),
+                    run_type: Some(ConnectionRunType::Poll),
+                    error_message: None,
+                },
+                Some(&txn),
+            )
+            .await?;
+
+        erp_connection_sync_state::Entity::update_many()
+            .col_expr(
+                erp_connection_sync_state::Column::LastPolledAt,
+                Expr::value(chrono::DateTime::<chrono::FixedOffset>::from(now)),
+            )
+            .col_expr(
+                erp_connection_sync_state::Column::NextPollAt,
+                Expr::value(chrono::DateTime::<chrono::FixedOffset>::from(
+                    compute_next_poll_at(now, sync_state.poll_interval_seconds),
+                )),
+            )
+            .filter(erp_connection_sync_state::Column::Id.eq(sync_state.id))
+            .exec(&txn)
+            .await?;
+
+        let ready_events = sync_event::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(sync_event::Column::Status.eq(SyncEventStatus::Pending))
+                    .add(sync_event::Column::Status.eq(SyncEventStatus::Error))
+                    .add(sync_event::Column::Status.eq(SyncEventStatus::InProgress)),
+            )
+            .filter(sync_event::Column::ConnectionSyncStateId.eq(sync_state.id))
+            .filter(sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
+            .filter(sync_event::Column::SyncEventCategory.eq(SyncEventCategory::Inventory))
+            .order_by_asc(sync_event::Column::UpdatedAt)
+            .all(&txn)
+            .await?;
+
+        // Finish an in-flight pagination before starting another event.
+        let resumed = sync_state.sync_cursor.as_ref().and_then(|cursor| {
+            ready_events
+                .iter()
+                .find(|e| self.adapter.resumes_cursor(e, cursor))
+                .cloned()
+        });
+
+        let (decision, event) = match resumed {
+            Some(event) => (
+                PollDecision::ContinuedCursor,
+                start_event(&sync_event_svc, event, run.id, &txn).await?,
+            ),
+            None => match ready_events.into_iter().next() {
+                Some(event) => (
+                    PollDecision::StartedEvent,
+                    start_event(&sync_event_svc, event, run.id, &txn).await?,
+                ),
+                None => {
+                    // First ever pull — create the recurring event.
+                    let created = sync_event_svc
+                        .create_recurring(
+                            CreateSyncEvent {
+                                original_record_body: None,
+                                details: None,
+                                event_direction: SyncEventDirection::PullFromExternal,
+                                inventory_record_event_id: None,
+                                sync_event_method: SyncEventMethod::List,
+                                sync_event_category: SyncEventCategory::Inventory,
+                                attempts: Some(1),
+                                status: Some(SyncEventStatus::InProgress),
+                                last_error: None,
+                                last_errored_date: None,
+                                connection_sync_state_id: Some(sync_state.id),
+                                connection_run_id: Some(run.id),
+                            },
+                            Some(&txn),
+                        )
+                        .await?;
+                    match created {
+                        Some(event) => (PollDecision::CreatedEvent, event),
+                        // A concurrent first pull created it after the lookup above.
+                        None => {
+                            let existing = sync_event_svc
+                                .find_recurring(sync_state.id, SyncEventCategory::Inventory, Some(&txn))
+                                .await?;
+                            let Some(existing) = existing else {
+                                txn.rollback().await?;
+                                return Ok((PollDecision::NoDueEvent, None));
+                            };
+                            (
+                                PollDecision::StartedEvent,
+                                start_event(&sync_event_svc, existing, run.id, &txn).await?,
+                            )
+                        }
+                    }
+                }
+            },
+        };
+
+        record_event_uuid(&event);
+        let request = self.adapter.next_request(conn, &sync_state, &event);
+        txn.commit().await?;
+
+        Ok((decision, Some(request)))
+    }
+
+    // ── Response phase ────────────────────────────────────────────────────────
+
+    /// Apply the provider's reply to the InProgress event.
+    pub async fn apply_response(
+        &self,
+        conn: &connection_identity::Model,
+        response: PullResponse,
+    ) -> Result<PullOutcome, PullError> {
+        let sync_state = self.ensure_sync_state(conn.id).await?;
+
+        // At most one event is InProgress per sync state while its run holds the lock.
+        let event = sync_event::Entity::find()
+            .filter(sync_event::Column::ConnectionSyncStateId.eq(sync_state.id))
+            .filter(sync_event::Column::Status.eq(SyncEventStatus::InProgress))
+            .filter(sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
+            .filter(sync_event::Column::SyncEventCategory.eq(SyncEventCategory::Inventory))
+            .one(&self.db)
+            .await?;
+
+        let Some(event) = event else {
+            tracing::warn!(connection_id = conn.id, "Pull response received with no event in progress");
+            return Ok(PullOutcome::default());
+        };
+        record_event_uuid(&event);
+
+        let run = match event.connection_run_id {
+            Some(run_id) => connection_run::Entity::find_by_id(run_id).one(&self.db).await?,
+            None => None,
+        };
+
+        if let Some(err_msg) = response.error {
+            self.fail(&sync_state, &event, run.as_ref(), &err_msg, None).await?;
+            return Ok(PullOutcome::default());
+        }
+
+        let Some(body) = response.body else {
+            // Nothing to apply; hand the event back without touching the cursor.
+            let txn = self.db.begin().await?;
+            self.finish(&sync_state, &event, run.as_ref(), None, &txn)
+                .await?;
+            txn.commit().await?;
+            return Ok(PullOutcome::default());
+        };
+
+        let result = retry_on_transient(&self.retry, || {
+            self.apply_page(conn, &sync_state, &event, run.as_ref(), &body)
+        })
+        .await;
+
+        match result {
+            Ok(outcome) => Ok(outcome),
+            Err(PullError::Provider(msg)) => {
+                self.fail(&sync_state, &event, run.as_ref(), &msg, None).await?;
+                Err(PullError::Provider(msg))
+            }
+            Err(PullError::Status(status)) => {
+                self.fail(&sync_state, &event, run.as_ref(), &status.message, Some(&status))
+                    .await?;
+                Err(PullError::Status(status))
+            }
+            Err(PullError::CursorExpired(msg)) => {
+                self.restart(&sync_state, &event, run.as_ref(), &msg).await?;
+                Ok(PullOutcome::default())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // ── Private helpers ───────────────────────────────────────────────────────
+
+    /// One Success sync event per record the page wrote: method Create or Update,
+    /// `inventory_record_event_id` set to the record's event, `connection_run_id` to the
+    /// run, and `details.parent_event_uuid` to the List event that pulled it. The List
+    /// event itself is recurring, so it never points at a single record event.
+    async fn record_applied(
+        &self,
+        event: &sync_event::Model,
+        run: Option<&connection_run::Model>,
+        applied: &[AppliedRecord],
+        txn: &DatabaseTransaction,
+    ) -> Result<(), DbErr> {
+        let children = applied
+            .iter()
+            .map(|record| CreateSyncEvent {
+                original_record_body: None,
+                details: Some(json!({
+                    "parent_event_uuid": event.uuid,
+                    "record_id": record.record_id,
+                })),
+                event_direction: event.event_direction.clone(),
+                inventory_record_event_id: Some(record.inventory_record_event_id),
+                sync_event_method: if record.created {
+                    SyncEventMethod::Create
+                } else {
+                    SyncEventMethod::Update
+                },
+                sync_event_category: event.sync_event_category.clone(),
+                attempts: Some(1),
+                status: Some(SyncEventStatus::Success),
+                last_error: None,
+                last_errored_date: None,
+                connection_sync_state_id: event.connection_sync_state_id,
+                connection_run_id: run.map(|r| r.id),
+            })
+            .collect();
+
+        SyncEventService::new(self.db.clone())
+            .create_many(children, Some(txn))
+            .await
+            .map(|_| ())
+    }
+
+    /// Apply one page in its own transaction: adapter writes, then `finish`. Any error
+    /// rolls the whole page back, so it can be run again from scratch.
+    async fn apply_page(
+        &self,
+        conn: &connection_identity::Model,
+        sync_state: &erp_connection_sync_state::Model,
+        event: &sync_event::Model,
+        run: Option<&connection_run::Model>,
+        body: &str,
+    ) -> Result<PullOutcome, PullError> {
+        let txn = self.db.begin().await?;
+        let outcome = match self.adapter.apply_response(conn, event, body, &txn).await {
+            Ok(outcome) => outcome,
+            Err(e) => {
+                txn.rollback().await?;
+                return Err(e);
+            }
+        };
+
+        self.finish(sync_state, event, run, Some(&outcome), &txn)
+            .await?;
+        txn.commit().await?;
+
+        Ok(outcome)
+    }
+
+    /// Hand the event back after a page: Pending for List events, Success/Error otherwise.
+    /// The page's `outcome` (if any) replaces the stored cursor; its record errors,
+    /// validation rejections and provider warnings land in the event's `last_error`.
+    /// Warnings alone don't fail the event or run. Releases the lock and clears backoff.
+    async fn finish(
+        &self,
+        sync_state: &erp_connection_sync_state::Model,
+        event: &sync_event::Model,
+        run: Option<&connection_run::Model>,
+        outcome: Option<&PullOutcome>,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), DbErr> {
+        let cursor = outcome.map(|o| o.cursor.clone());
+        let errors: &[String] = outcome.map_or(&[], |o| &o.errors);
+        let rejected: &[RejectedRecord] = outcome.map_or(&[], |o| &o.rejected);
+        let warnings: &[String] = outcome.map_or(&[], |o| &o.warnings);
+        let has_errors = !errors.is_empty() || !rejected.is_empty();
+        let last_error = match (has_errors, warnings.is_empty()) {
+            (true, true) => Some(json!({ "errors": errors, "rejected": rejected })),
+            (true, false) => Some(
+                json!({ "errors": errors, "rejected": rejected, "warnings": warnings }),
+            ),
+            (false, false) => Some(json!({ "warnings": warnings })),
+            (false, true) => None,
+        };
+        let is_list = event.sync_event_method == SyncEventMethod::List;
+        let status = match (is_list, has_errors) {
+            (true, _) => SyncEventStatus::Pending,
+            (false, true) => SyncEventStatus::Error,
+            (false, false) => SyncEventStatus::Success,
+        };
+
+        SyncEventService::new(self.db.clone())
+            .update_by_uuid(
+                event.uuid,
+                UpdateSyncEvent {
+                    status: Some(status),
+                    last_error,
+                    last_errored_date: has_errors.then(|| self.clock.now()),
+                    attempts: None,
+                    original_record_body: None,
+                    details: None,
+                    event_direction: None,
+                    inventory_record_event_id: None,
+                    sync_event_method: None,
+                    sync_event_category: None,
+                    connection_sync_state_id: None,
+                    connection_run_id: None,
+                },
+                Some(txn),
+            )
+            .await
+            .map_err(sync_event_db_err)?;
+
+        if let Some(outcome) = outcome {
+            self.record_applied(event, run, &outcome.applied, txn).await?;
+        }
+
+        if has_errors && let Some(r) = run {
+            ConnectionRunService::new(self.db.clone())
+                .update_by_uuid(
+                    r.uuid,
+                    UpdateConnectionRun {
+                        status: Some(ConnectionRunStatus::Error),
+                        error_message: Some(run_error_message(errors, rejected)),
+                    },
+                    Some(txn),
+                )
+                .await
+                .map_err(run_db_err)?;
+        }
+
+        let now = self.clock.now();
+        let next_poll_at = match &cursor {
+            Some(Some(_)) => now,
+            _ => compute_next_poll_at(now, sync_state.poll_interval_seconds),
+        };
+        self.release(sync_state.id, run, cursor, None, next_poll_at, txn)
+            .await
+    }
+
+    /// Mark the event and run Error in their own transaction, release the lock and back off.
+    /// A provider `status` adds its code and `retryable` to the event's `last_error`.
+    async fn fail(
+        &self,
+        sync_state: &erp_connection_sync_state::Model,
+        event: &sync_event::Model,
+        run: Option<&connection_run::Model>,
+        message: &str,
+        status: Option<&ProviderStatus>,
+    ) -> Result<(), DbErr> {
+        let txn = self.db.begin().await?;
+
+        SyncEventService::new(self.db.clone())
+            .update_by_uuid(
+                event.uuid,
+                UpdateSyncEvent {
+                    status: Some(SyncEventStatus::Error),
+                    last_error: Some(match status {
+                        Some(s) => json!({
+                            "message": message,
+                            "code": s.code,
+                            "retryable": s.retryable,
+                        }),
+                        None => json!({ "message": message }),
+                    }),
+                    last_errored_date: Some(self.clock.now()),
+                    attempts: None,
+                    original_record_body: None,
+                    details: None,
+                    event_direction: None,
+                    inventory_record_event_id: None,
+                    sync_event_method: None,
+                    sync_event_category: None,
+                    connection_sync_state_id: None,
+                    connection_run_id: None,
+                },
+                Some(&txn),
+            )
+            .await
+            .map_err(sync_event_db_err)?;
+
+        if let Some(r) = run {
+            ConnectionRunService::new(self.db.clone())
+                .update_by_uuid(
+                    r.uuid,
+                    UpdateConnectionRun {
+                        status: Some(ConnectionRunStatus::Error),
+                        error_message: Some(message.to_string()),
+                    },
+                    Some(&txn),
+                )
+                .await
+                .map_err(run_db_err)?;
+        }
+
+        let backoff_until = self.clock.now() + backoff_delay(event.attempts);
+        self.release(sync_state.id, run, None, Some(backoff_until), backoff_until, &txn)
+            .await?;
+
+        txn.commit().await
+    }
+
+    /// Drop the expired cursor so the next poll starts from the first page. The event stays
+    /// retryable (Pending); the run is marked Error so the restart shows up in its history.
+    async fn restart(
+        &self,
+        sync_state: &erp_connection_sync_state::Model,
+        event: &sync_event::Model,
+        run: Option<&connection_run::Model>,
+        message: &str,
+    ) -> Result<(), DbErr> {
+        tracing::warn!(
+            connection_id = sync_state.connection_id,
+            "Pull cursor expired, restarting from the first page: {}",
+            message
+        );
+        let txn = self.db.begin().await?;
+
+        SyncEventService::new(self.db.clone())
+            .update_by_uuid(
+                event.uuid,
+                UpdateSyncEvent {
+                    status: Some(SyncEventStatus::Pending),
+                    last_error: Some(json!({ "message": message, "cursor_reset": true })),
+                    last_errored_date: Some(self.clock.now()),
+                    attempts: None,
+                    original_record_body: None,
+                    details: None,
+                    event_direction: None,
+                    inventory_record_event_id: None,
+                    sync_event_method: None,
+                    sync_event_category: None,
+                    connection_sync_state_id: None,
+                    connection_run_id: None,
+                },
+                Some(&txn),
+            )
+            .await
+            .map_err(sync_event_db_err)?;
+
+        if let Some(r) = run {
+            ConnectionRunService::new(self.db.clone())
+                .update_by_uuid(
+                    r.uuid,
+                    UpdateConnectionRun {
+                        status: Some(ConnectionRunStatus::Error),
+                        error_message: Some(format!(
+                            "Cursor expired, pagination restarted from the first page: {}",
+                            message
+                        )),
+                    },
+                    Some(&txn),
+                )
+                .await
+                .map_err(run_db_err)?;
+        }
+
+        self.release(sync_state.id, run, Some(None), None, self.clock.now(), &txn)
+            .await?;
+
+        txn.commit().await
+    }
+
+    /// Release the lock held by `run`, optionally replacing the cursor, set (or clear) backoff
+    /// and schedule the next poll.
+    async fn release(
+        &self,
+        sync_state_id: i64,
+        run: Option<&connection_run::Model>,
+        cursor: Option<Option<Value>>,
+        backoff_until: Option<chrono::DateTime<chrono::Utc>>,
+        next_poll_at: chrono::DateTime<chrono::Utc>,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), DbErr> {
+        let Some(ss) = erp_connection_sync_state::Entity::find_by_id(sync_state_id)
+            .one(txn)
+            .await?
+        else {
+            return Ok(());
+        };
+
+        let holds_lock = run.is_some_and(|r| ss.sync_lock_owner == Some(r.uuid.to_string()));
+        let mut active: erp_connection_sync_state::ActiveModel = ss.into();
+        if holds_lock {
+            active.sync_lock_owner = Set(None);
+            active.sync_lock_until = Set(None);
+        }
+        if let Some(cursor) = cursor {
+            active.sync_cursor = Set(cursor);
+        }
+        active.rate_limit_backoff_until = Set(backoff_until.map(Into::into));
+        active.next_poll_at = Set(Some(next_poll_at.into()));
+        active.updated_at = Set(self.clock.now().into());
+        active.update(txn).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+//! Retries for transient database errors.
+//!
+//! Postgres aborts a transaction that loses a deadlock (SQLSTATE `40P01`) or a serializable
+//! conflict (`40001`); running it again from the start usually succeeds. `retry_on_transient`
+//! reruns an operation on those errors only, sleeping with exponential backoff plus jitter
+//! between attempts so the competing writers don't collide again in lockstep. Any other
+//! error is returned straight away.
+
+use std::fmt::Debug;
+use std::future::Future;
+use std::time::Duration;
+
+use sea_orm::{DbErr, RuntimeErr};
+use uuid::Uuid;
+
+use super::adapter::PullError;
+
+/// Default number of retries after the first attempt.
+pub const DEFAULT_TRANSIENT_RETRIES: u32 = 3;
+
+/// SQLSTATEs worth retrying: serialization_failure, deadlock_detected.
+pub const TRANSIENT_SQLSTATES: [&str; 2] = ["40001", "40P01"];
+
+/// Delay before the first retry; doubles per retry.
+const BASE_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// Upper bound for the delay between retries (before jitter).
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// Errors that may succeed when the whole operation is run again.
+pub trait Transient {
+    fn is_transient(&self) -> bool;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Retries after the first attempt; 0 disables retrying.
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+/// BEGUN IMPLEMENTATION ///
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(DEFAULT_TRANSIENT_RETRIES)
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            base_delay: BASE_RETRY_DELAY,
+            max_delay: MAX_RETRY_DELAY,
+        }
+    }
+
+    ///delay before the `retry`-th retry (1-based): half of the exponential step is fixed,
+    ///the other half is random
+    pub fn delay(&self, retry: u32) -> Duration {
+        let exp = retry.saturating_sub(1).min(16);
+        let step = self.base_delay.saturating_mul(2u32.pow(exp)).min(self.max_delay);
+        let half = step / 2;
+        let jitter_nanos = half.as_nanos() as u64;
+        let jitter = match jitter_nanos {
+            0 => 0,
+            n => (Uuid::new_v4().as_u128() % u128::from(n + 1)) as u64,
+        };
+        half + Duration::from_nanos(jitter)
+    }
+}
+
+///SQLSTATE reported by the database for `err`, if any
+pub fn sqlstate(err: &DbErr) -> Option<String> {
+    match err {
+        DbErr::Conn(RuntimeErr::SqlxError(e))
+        | DbErr::Exec(RuntimeErr::SqlxError(e))
+        | DbErr::Query(RuntimeErr::SqlxError(e)) => e
+            .as_database_error()
+            .and_then(|db_err| db_err.code())
+            .map(|code| code.into_owned()),
+        _ => None,
+    }
+}
+
+impl Transient for DbErr {
+    fn is_transient(&self) -> bool {
+        sqlstate(self).is_some_and(|code| TRANSIENT_SQLSTATES.contains(&code.as_str()))
+    }
+}
+
+impl Transient for PullError {
+    fn is_transient(&self) -> bool {
+        matches!(self, PullError::Db(e) if e.is_transient())
+    }
+}
+
+///runs `op` until it succeeds, fails with a non-transient error, or `policy.max_retries`
+///retries are used up; `op` must redo the whole unit of work (e.g. begin a new transaction)
+pub async fn retry_on_transient<T, E, F, Fut>(policy: &RetryPolicy, mut op: F) -> Result<T, E>
+where
+    E: Transient + Debug,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut retry = 0;
+    loop {
+        match op().await {
+            Err(e) if e.is_transient() && retry < policy.max_retries => {
+                retry += 1;
+                let delay = policy.delay(retry);
+                tracing::warn!(
+                    retry,
+                    max_retries = policy.max_retries,
+                    delay_ms = delay.as_millis() as u64,
+                    error = ?e,
+                    "Transient database error, retrying"
+                );
+                tokio::time::sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
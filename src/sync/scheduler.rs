@@ -0,0 +1,266 @@
+//! Internal poll scheduler for API connections.
+//!
+//! QuickBooks Desktop is driven by the Web Connector calling us; API providers (QuickBooks
+//! Online, Salesforce) have nobody calling in, so the scheduler polls them itself. Every tick:
+//!   1. Find due connections: sync states past `next_poll_at` (`find_due`), plus connections
+//!      never polled (no sync state yet). Only enabled, active, pull-enabled connections of
+//!      type `api` are considered; Desktop connections stay with QBWC
+//!   2. Hand each one to the registered `ApiPullAdapter` that handles it, after a random
+//!      delay of up to `jitter` so connections that fall due together don't hit the
+//!      provider (and the pool) at the same instant
+//!   3. Drive the pull through `SyncOrchestrator` page by page until pagination finishes
+//!
+//! Several server instances may run the scheduler. The orchestrator takes the connection's
+//! sync lock before each page, so only one instance pulls a connection at a time; the others
+//! see the lock held and skip it until the next tick. A connection outside its sync window
+//! (`super::window`) is skipped the same way.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use entity::sea_orm_active_enums::{ErpConnectionStatus, ErpProviderType};
+use entity::{connection_identity, erp_connection_sync_state};
+use futures_util::future::{BoxFuture, FutureExt};
+use futures_util::stream::{self, StreamExt};
+use sea_orm::{
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder,
+};
+use uuid::Uuid;
+
+use crate::clock::{self, SharedClock};
+use crate::erp_connection_sync_state::services::ErpConnectionSyncStateService;
+
+use super::adapter::{PullAdapter, PullError, PullRequest, PullResponse};
+use super::orchestrator::SyncOrchestrator;
+
+/// Connections pulled in parallel within one tick.
+pub const MAX_CONCURRENT_PULLS: usize = 8;
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// A pull adapter for a provider the server calls itself.
+pub trait ApiPullAdapter: PullAdapter {
+    ///whether this adapter pulls for `conn` (e.g. QuickBooks Online API connections)
+    fn handles(&self, conn: &connection_identity::Model) -> bool;
+
+    ///sends `request` to the provider; transport failures go in `PullResponse::error`
+    fn fetch(
+        &self,
+        conn: &connection_identity::Model,
+        request: PullRequest,
+    ) -> impl Future<Output = PullResponse> + Send;
+}
+
+/// Object-safe form of `ApiPullAdapter`, so adapters for different providers can be
+/// registered side by side. Implemented for every `ApiPullAdapter`.
+pub trait ScheduledPull: Send + Sync {
+    fn handles(&self, conn: &connection_identity::Model) -> bool;
+
+    ///pulls every remaining page; returns whether a page was pulled (false when the
+    ///connection is backing off or another instance holds its sync lock)
+    fn pull(
+        self: Arc<Self>,
+        db: DatabaseConnection,
+        clock: SharedClock,
+        conn: connection_identity::Model,
+    ) -> BoxFuture<'static, Result<bool, PullError>>;
+}
+
+pub struct PollScheduler {
+    db: DatabaseConnection,
+    adapters: Vec<Arc<dyn ScheduledPull>>,
+    /// Upper bound for the random delay before each pull.
+    jitter: Duration,
+    clock: SharedClock,
+}
+
+/// What one tick did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TickSummary {
+    pub polled: usize,
+    /// Backing off, or locked by another instance.
+    pub skipped: usize,
+    pub failed: usize,
+}
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl<A: ApiPullAdapter + 'static> ScheduledPull for A {
+    fn handles(&self, conn: &connection_identity::Model) -> bool {
+        ApiPullAdapter::handles(self, conn)
+    }
+
+    fn pull(
+        self: Arc<Self>,
+        db: DatabaseConnection,
+        clock: SharedClock,
+        conn: connection_identity::Model,
+    ) -> BoxFuture<'static, Result<bool, PullError>> {
+        Box::pin(async move {
+            let orchestrator = SyncOrchestrator::new(db, &*self).with_clock(clock);
+            let mut pulled = false;
+
+            while let Some(request) = orchestrator.next_request(&conn).await? {
+                pulled = true;
+                let response = self.fetch(&conn, request).await;
+                if !orchestrator.apply_response(&conn, response).await?.has_more() {
+                    break;
+                }
+            }
+
+            Ok(pulled)
+        })
+    }
+}
+
+impl PollScheduler {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self {
+            db,
+            adapters: Vec::new(),
+            jitter: Duration::ZERO,
+            clock: clock::system(),
+        }
+    }
+
+    ///registers the adapter for one provider's API connections
+    pub fn with_adapter(mut self, adapter: Arc<dyn ScheduledPull>) -> Self {
+        self.adapters.push(adapter);
+        self
+    }
+
+    ///delays each pull by a random duration of up to `jitter`
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    ///uses `clock` for due checks and the orchestrator's locks and scheduling
+    pub fn with_clock(mut self, clock: SharedClock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn has_adapters(&self) -> bool {
+        !self.adapters.is_empty()
+    }
+
+    fn adapter_for(&self, conn: &connection_identity::Model) -> Option<&Arc<dyn ScheduledPull>> {
+        self.adapters.iter().find(|a| a.handles(conn))
+    }
+
+    ///enabled API connections that are due: past their `next_poll_at`, or never polled
+    pub async fn due_connections(&self) -> Result<Vec<connection_identity::Model>, DbErr> {
+        let due_ids: Vec<i64> = ErpConnectionSyncStateService::new(self.db.clone())
+            .find_due(self.clock.now(), None)
+            .await?
+            .into_iter()
+            .map(|s| s.connection_id)
+            .collect();
+
+        connection_identity::Entity::find()
+            .left_join(erp_connection_sync_state::Entity)
+            .filter(connection_identity::Column::ErpType.eq(ErpProviderType::Api))
+            .filter(connection_identity::Column::Status.eq(ErpConnectionStatus::Active))
+            .filter(connection_identity::Column::IsEnabled.eq(true))
+            .filter(connection_identity::Column::SyncEnabledPull.eq(true))
+            .filter(
+                Condition::any()
+                    .add(connection_identity::Column::Id.is_in(due_ids))
+                    .add(erp_connection_sync_state::Column::Id.is_null()),
+            )
+            .order_by_asc(connection_identity::Column::Id)
+            .all(&self.db)
+            .await
+    }
+
+    ///pulls `connections` that have an adapter, at most `MAX_CONCURRENT_PULLS` at a time
+    pub async fn poll(&self, connections: Vec<connection_identity::Model>) -> TickSummary {
+        let jobs: Vec<_> = connections
+            .into_iter()
+            .filter_map(|conn| self.adapter_for(&conn).cloned().map(|a| (a, conn)))
+            .collect();
+
+        pull_all(jobs, self.db.clone(), self.clock.clone(), self.jitter).await
+    }
+
+    ///one scheduler pass: find due connections and pull them
+    pub async fn tick(&self) -> Result<TickSummary, DbErr> {
+        if !self.has_adapters() {
+            return Ok(TickSummary::default());
+        }
+        let due = self.due_connections().await?;
+        Ok(self.poll(due).await)
+    }
+}
+
+///runs the pulls with jitter, at most `MAX_CONCURRENT_PULLS` at a time
+async fn pull_all(
+    jobs: Vec<(Arc<dyn ScheduledPull>, connection_identity::Model)>,
+    db: DatabaseConnection,
+    clock: SharedClock,
+    jitter: Duration,
+) -> TickSummary {
+    let pulls: Vec<BoxFuture<'static, Result<bool, PullError>>> = jobs
+        .into_iter()
+        .map(|(adapter, conn)| {
+            let (db, clock, delay) = (db.clone(), clock.clone(), jitter_delay(jitter));
+            async move {
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                let connection_id = conn.id;
+                adapter.pull(db, clock, conn).await.inspect_err(|e| {
+                    tracing::error!(connection_id, "Scheduled pull failed: {}", e);
+                })
+            }
+            .boxed()
+        })
+        .collect();
+
+    //pulls of different connections run on their own pool connections
+    let mut results = stream::iter(pulls).buffer_unordered(MAX_CONCURRENT_PULLS);
+    let mut summary = TickSummary::default();
+    while let Some(result) = results.next().await {
+        match result {
+            Ok(true) => summary.polled += 1,
+            Ok(false) => summary.skipped += 1,
+            Err(_) => summary.failed += 1,
+        }
+    }
+
+    summary
+}
+
+///a random delay in `[0, max]`
+pub fn jitter_delay(max: Duration) -> Duration {
+    let max_ms = max.as_millis() as u64;
+    if max_ms == 0 {
+        return Duration::ZERO;
+    }
+    Duration::from_millis((Uuid::new_v4().as_u128() % (max_ms as u128 + 1)) as u64)
+}
+
+///spawns the scheduler loop on the tokio runtime, ticking every `interval`
+pub fn spawn(scheduler: PollScheduler, interval: Duration) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        loop {
+            ticker.tick().await;
+            match scheduler.tick().await {
+                Ok(summary) if summary != TickSummary::default() => {
+                    tracing::info!(
+                        polled = summary.polled,
+                        skipped = summary.skipped,
+                        failed = summary.failed,
+                        "Scheduled poll tick"
+                    );
+                }
+                Ok(_) => {}
+                Err(e) => tracing::error!("Poll scheduler failed to find due connections: {}", e),
+            }
+        }
+    })
+}
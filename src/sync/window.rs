@@ -0,0 +1,53 @@
+//! Per-connection sync windows.
+//!
+//! A connection may restrict its pulls to a range of UTC hours, e.g. 22:00-06:00 for a
+//! company that wants syncs off-hours only. The window is stored as two hour columns on
+//! `connection_identity`; outside it `SyncOrchestrator` hands out no work, so neither the
+//! Web Connector nor the poll scheduler starts a pull.
+
+use chrono::{DateTime, Timelike, Utc};
+use entity::connection_identity;
+
+/// BEGUN STRUCTS AND ENUMS ///
+/// UTC hours a connection's pulls may run in: from `start_hour` up to, not including,
+/// `end_hour`. A window with `start_hour > end_hour` wraps past midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SyncWindow {
+    pub start_hour: u8,
+    pub end_hour: u8,
+}
+// END STRUCTS AND ENUMS
+
+/// BEGUN IMPLEMENTATION ///
+impl SyncWindow {
+    ///None unless both hours are within 0-23 and differ (an empty or full-day window is
+    ///meaningless; a connection without a window already syncs around the clock)
+    pub fn new(start_hour: i16, end_hour: i16) -> Option<Self> {
+        let hours = 0..=23;
+        if !hours.contains(&start_hour) || !hours.contains(&end_hour) || start_hour == end_hour {
+            return None;
+        }
+        Some(Self {
+            start_hour: start_hour as u8,
+            end_hour: end_hour as u8,
+        })
+    }
+
+    ///the window stored on `conn`; None when it has none (or only half of one)
+    pub fn of(conn: &connection_identity::Model) -> Option<Self> {
+        Self::new(conn.sync_window_start_hour?, conn.sync_window_end_hour?)
+    }
+
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let hour = now.hour() as u8;
+        match self.start_hour < self.end_hour {
+            true => hour >= self.start_hour && hour < self.end_hour,
+            false => hour >= self.start_hour || hour < self.end_hour,
+        }
+    }
+}
+
+///whether `conn` may sync at `now`; always true for a connection without a window
+pub fn in_sync_window(conn: &connection_identity::Model, now: DateTime<Utc>) -> bool {
+    SyncWindow::of(conn).is_none_or(|window| window.contains(now))
+}
@@ -1,3 +1,4 @@
+pub mod routes;
 pub mod services;
 
 pub use services::SyncEventService;
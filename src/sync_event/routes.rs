@@ -0,0 +1,173 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    routing::post,
+    Json, Router,
+};
+use entity::sea_orm_active_enums::SyncEventStatus;
+use entity::sync_event;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+
+use crate::AppState;
+use crate::tenant::routes::ErrorResponse;
+use crate::validation::{check_length, FieldError, PathUuid};
+use super::services::{SyncEventError, SyncEventService};
+
+/// Max length of a cancel note.
+pub const MAX_CANCEL_NOTE_LEN: usize = 1000;
+
+
+/// RESPONSE SCHEMAS ///
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SyncEventResponse {
+    pub uuid: String,
+    #[schema(value_type = String)]
+    pub status: SyncEventStatus,
+    pub attempts: i32,
+    /// For a cancelled event: `{"cancelled": true, "message": "<note>"}`.
+    #[schema(value_type = Option<Object>)]
+    pub last_error: Option<Value>,
+    pub last_errored_date: Option<String>,
+    pub updated_at: String,
+}
+
+
+/// REQUEST SCHEMAS ///
+#[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
+pub struct RetrySyncEventQuery {
+    /// Reset `attempts` to 0 (default: keep the count).
+    #[param(default = false)]
+    pub reset_attempts: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct CancelSyncEventRequest {
+    /// Why the event was cancelled; stored in its `last_error`.
+    pub note: String,
+}
+
+
+/// VALIDATION ///
+impl CancelSyncEventRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_length(&mut errors, "note", Some(&self.note), MAX_CANCEL_NOTE_LEN);
+        errors
+    }
+}
+
+
+/// HELPER FUNCTIONS ///
+fn model_to_response(model: sync_event::Model) -> SyncEventResponse {
+    SyncEventResponse {
+        uuid: model.uuid.to_string(),
+        status: model.status,
+        attempts: model.attempts,
+        last_error: model.last_error,
+        last_errored_date: model.last_errored_date.map(|t| t.to_rfc3339()),
+        updated_at: model.updated_at.to_rfc3339(),
+    }
+}
+
+fn sync_event_error(e: SyncEventError) -> (StatusCode, Json<ErrorResponse>) {
+    let (status, error) = match e {
+        SyncEventError::NotFound => (StatusCode::NOT_FOUND, "Sync event not found".to_string()),
+        SyncEventError::InvalidState(message) => (StatusCode::CONFLICT, message),
+        SyncEventError::Db(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Database error: {}", e),
+        ),
+    };
+    (status, Json(ErrorResponse { error, details: None }))
+}
+
+
+/// ROUTE HANDLERS ///
+
+#[utoipa::path(
+    post,
+    path = "/admin/sync-events/{uuid}/retry",
+    tag = "Admin",
+    params(
+        ("uuid" = String, Path, description = "Sync event UUID"),
+        RetrySyncEventQuery
+    ),
+    responses(
+        (status = 200, description = "Event is Pending again; an InProgress event's run was failed and its sync lock released", body = SyncEventResponse),
+        (status = 400, description = "Malformed UUID", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Sync event not found", body = ErrorResponse),
+        (status = 409, description = "The event already succeeded", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn retry_sync_event(
+    State(state): State<AppState>,
+    PathUuid(uuid): PathUuid,
+    Query(query): Query<RetrySyncEventQuery>,
+) -> Result<Json<SyncEventResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let reset_attempts = query.reset_attempts.unwrap_or(false);
+    match SyncEventService::new(state.db).retry(uuid, reset_attempts, None).await {
+        Ok(event) => {
+            tracing::info!(sync_event_uuid = %uuid, reset_attempts, "Sync event requeued by operator");
+            Ok(Json(model_to_response(event)))
+        }
+        Err(e) => Err(sync_event_error(e)),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/admin/sync-events/{uuid}/cancel",
+    tag = "Admin",
+    params(
+        ("uuid" = String, Path, description = "Sync event UUID")
+    ),
+    request_body = CancelSyncEventRequest,
+    responses(
+        (status = 200, description = "Event is Error with the note in last_error; an InProgress event's run was failed and its sync lock released", body = SyncEventResponse),
+        (status = 400, description = "Malformed UUID or blank note", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid X-Admin-Token", body = ErrorResponse),
+        (status = 403, description = "ADMIN_TOKEN is not configured", body = ErrorResponse),
+        (status = 404, description = "Sync event not found", body = ErrorResponse),
+        (status = 409, description = "The event is not pending or in progress", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    )
+)]
+pub async fn cancel_sync_event(
+    State(state): State<AppState>,
+    PathUuid(uuid): PathUuid,
+    Json(body): Json<CancelSyncEventRequest>,
+) -> Result<Json<SyncEventResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let errors = body.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let note = body.note.trim();
+    match SyncEventService::new(state.db).cancel(uuid, note, None).await {
+        Ok(event) => {
+            tracing::info!(sync_event_uuid = %uuid, note, "Sync event cancelled by operator");
+            Ok(Json(model_to_response(event)))
+        }
+        Err(e) => Err(sync_event_error(e)),
+    }
+}
+
+
+/// ROUTER ///
+///mounted at /admin/sync-events behind the admin token middleware
+pub fn create_router() -> Router<AppState> {
+    Router::new()
+        .route("/{uuid}/retry", post(retry_sync_event))
+        .route("/{uuid}/cancel", post(cancel_sync_event))
+        .layer(axum::middleware::from_fn(
+            crate::middleware::admin_token_auth_middleware,
+        ))
+}
@@ -1,4 +1,5 @@
-//! CRUD services for sync_event (no routes).
+//! CRUD services for sync_event. Operators retry and cancel events through
+//! `/admin/sync-events` (see routes).
 //!
 //! When sync method is list and pagination is used: create a new sync event when the allotted
 //! pagination span has been used (e.g. page size 25, 50 total → pull 25, then create a new sync
@@ -6,21 +7,31 @@
 //! events can happen in the same request. Create a new connection_sync_state per sync event ONLY
 //! if the sync method is list.
 
-use entity::sync_event;
+use entity::{connection_run, erp_connection_sync_state, sync_event};
 use entity::sea_orm_active_enums::{
-    SyncEventCategory, SyncEventDirection, SyncEventMethod, SyncEventStatus,
+    ConnectionRunStatus, SyncEventCategory, SyncEventDirection, SyncEventMethod, SyncEventStatus,
 };
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::{Expr, OnConflict};
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
-    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set, TransactionTrait, TryInsertResult,
 };
 use uuid::Uuid;
 
+use crate::db;
+
+/// Predicate of the partial unique index on recurring List events
+/// (`ux_sync_event_recurring_list`); must match the migration for ON CONFLICT to use it.
+const RECURRING_EVENT_PREDICATE: &str = "sync_event_method = 'list' AND details IS NULL";
+
 //DEBUG AND ERRORS ///
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum SyncEventError {
     NotFound,
+    ///the event's status doesn't allow the operation, e.g. retrying a Success event
+    InvalidState(String),
     Db(DbErr),
 }
 
@@ -106,10 +117,7 @@ impl SyncEventService {
         id: i64,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<sync_event::Model>, DbErr> {
-        match txn {
-            Some(txn) => sync_event::Entity::find_by_id(id).one(txn).await,
-            None => sync_event::Entity::find_by_id(id).one(&self.db).await,
-        }
+        sync_event::Entity::find_by_id(id).one(&db::conn(&self.db, txn)).await
     }
 
     /// Get by uuid (idempotent key).
@@ -118,16 +126,10 @@ impl SyncEventService {
         uuid: Uuid,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<sync_event::Model>, DbErr> {
-        match txn {
-            Some(txn) => sync_event::Entity::find()
-                .filter(sync_event::Column::Uuid.eq(uuid))
-                .one(txn)
-                .await,
-            None => sync_event::Entity::find()
-                .filter(sync_event::Column::Uuid.eq(uuid))
-                .one(&self.db)
-                .await,
-        }
+        sync_event::Entity::find()
+            .filter(sync_event::Column::Uuid.eq(uuid))
+            .one(&db::conn(&self.db, txn))
+            .await
     }
 
     pub async fn get_by_inventory_record_event_id(
@@ -138,10 +140,7 @@ impl SyncEventService {
         let query = sync_event::Entity::find()
             .filter(sync_event::Column::InventoryRecordEventId.eq(inventory_record_event_id))
             .order_by_desc(sync_event::Column::CreatedAt);
-        match txn {
-            Some(txn) => query.all(txn).await,
-            None => query.all(&self.db).await,
-        }
+        query.all(&db::conn(&self.db, txn)).await
     }
 
     pub async fn get_by_connection_sync_state_id(
@@ -152,10 +151,7 @@ impl SyncEventService {
         let query = sync_event::Entity::find()
             .filter(sync_event::Column::ConnectionSyncStateId.eq(connection_sync_state_id))
             .order_by_desc(sync_event::Column::CreatedAt);
-        match txn {
-            Some(txn) => query.all(txn).await,
-            None => query.all(&self.db).await,
-        }
+        query.all(&db::conn(&self.db, txn)).await
     }
 
     pub async fn get_by_connection_run_id(
@@ -166,10 +162,21 @@ impl SyncEventService {
         let query = sync_event::Entity::find()
             .filter(sync_event::Column::ConnectionRunId.eq(connection_run_id))
             .order_by_desc(sync_event::Column::CreatedAt);
-        match txn {
-            Some(txn) => query.all(txn).await,
-            None => query.all(&self.db).await,
-        }
+        query.all(&db::conn(&self.db, txn)).await
+    }
+
+    ///InProgress events last touched before `older_than`, oldest first: a poll that took them
+    ///never answered (or is still running far longer than a page should take)
+    pub async fn find_stale_in_progress(
+        &self,
+        older_than: DateTimeWithTimeZone,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Vec<sync_event::Model>, DbErr> {
+        let query = sync_event::Entity::find()
+            .filter(sync_event::Column::Status.eq(SyncEventStatus::InProgress))
+            .filter(sync_event::Column::UpdatedAt.lt(older_than))
+            .order_by_asc(sync_event::Column::UpdatedAt);
+        query.all(&db::conn(&self.db, txn)).await
     }
 
     pub async fn get_all(
@@ -207,22 +214,14 @@ impl SyncEventService {
             .filter(condition)
             .order_by_desc(sync_event::Column::CreatedAt);
 
-        let total = match txn {
-            Some(txn) => query.clone().count(txn).await?,
-            None => query.clone().count(&self.db).await?,
-        };
+        let conn = db::conn(&self.db, txn);
+        let total = query.clone().count(&conn).await?;
         let total_pages = (total as f64 / per_page as f64).ceil() as u64;
 
-        let items = match txn {
-            Some(txn) => query
-                .paginate(txn, per_page)
-                .fetch_page(page.saturating_sub(1))
-                .await?,
-            None => query
-                .paginate(&self.db, per_page)
-                .fetch_page(page.saturating_sub(1))
-                .await?,
-        };
+        let items = query
+            .paginate(&conn, per_page)
+            .fetch_page(page.saturating_sub(1))
+            .await?;
 
         Ok(PaginatedSyncEvents {
             items,
@@ -238,38 +237,76 @@ impl SyncEventService {
         data: CreateSyncEvent,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<sync_event::Model, DbErr> {
-        let active = sync_event::ActiveModel {
-            original_record_body: Set(data.original_record_body),
-            details: Set(data.details),
-            event_direction: Set(data.event_direction),
-            inventory_record_event_id: Set(data.inventory_record_event_id),
-            sync_event_method: Set(data.sync_event_method),
-            sync_event_category: Set(data.sync_event_category),
-            attempts: Set(data.attempts.unwrap_or(0)),
-            status: Set(data.status.unwrap_or(SyncEventStatus::Pending)),
-            last_error: Set(data.last_error),
-            last_errored_date: Set(data.last_errored_date.map(Into::into)),
-            connection_sync_state_id: Set(data.connection_sync_state_id),
-            connection_run_id: Set(data.connection_run_id),
-            ..Default::default()
-        };
-        match txn {
-            Some(txn) => active.insert(txn).await,
-            None => active.insert(&self.db).await,
+        let active = to_active_model(data);
+        active.insert(&db::conn(&self.db, txn)).await
+    }
+
+    ///inserts all events in one statement; returns the number of rows written
+    pub async fn create_many(
+        &self,
+        data: Vec<CreateSyncEvent>,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<u64, DbErr> {
+        if data.is_empty() {
+            return Ok(0);
+        }
+        let insert = sync_event::Entity::insert_many(data.into_iter().map(to_active_model));
+        insert.exec_without_returning(&db::conn(&self.db, txn)).await
+    }
+
+    ///inserts a recurring List event (no `details`) unless its sync state already has one for
+    ///the category; None when it does, e.g. a concurrent first pull created it first
+    pub async fn create_recurring(
+        &self,
+        data: CreateSyncEvent,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<sync_event::Model>, DbErr> {
+        let mut on_conflict = OnConflict::columns([
+            sync_event::Column::ConnectionSyncStateId,
+            sync_event::Column::SyncEventMethod,
+            sync_event::Column::SyncEventCategory,
+        ]);
+        on_conflict
+            .target_and_where(Expr::cust(RECURRING_EVENT_PREDICATE))
+            .do_nothing();
+        let (sync_state_id, category) = (data.connection_sync_state_id, data.sync_event_category.clone());
+        let insert = sync_event::Entity::insert(to_active_model(data))
+            .on_conflict(on_conflict)
+            .try_insert();
+
+        //without RETURNING, so a skipped insert reads the same on every backend
+        let result = insert.exec_without_returning(&db::conn(&self.db, txn)).await?;
+        match (result, sync_state_id) {
+            (TryInsertResult::Inserted(rows), Some(sync_state_id)) if rows > 0 => {
+                self.find_recurring(sync_state_id, category, txn).await
+            }
+            _ => Ok(None),
         }
     }
 
+    ///the recurring List event (no `details`) of a sync state for `category`
+    pub async fn find_recurring(
+        &self,
+        connection_sync_state_id: i64,
+        category: SyncEventCategory,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<sync_event::Model>, DbErr> {
+        let query = sync_event::Entity::find()
+            .filter(sync_event::Column::ConnectionSyncStateId.eq(connection_sync_state_id))
+            .filter(sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
+            .filter(sync_event::Column::SyncEventCategory.eq(category))
+            .filter(sync_event::Column::Details.is_null());
+        query.one(&db::conn(&self.db, txn)).await
+    }
+
     pub async fn update_by_id(
         &self,
         id: i64,
         patch: UpdateSyncEvent,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<sync_event::Model>, SyncEventError> {
-        let model = match txn {
-            Some(txn) => sync_event::Entity::find_by_id(id).one(txn).await?,
-            None => sync_event::Entity::find_by_id(id).one(&self.db).await?,
-        };
-        let Some(model) = model else {
+        let conn = db::conn(&self.db, txn);
+        let Some(model) = sync_event::Entity::find_by_id(id).one(&conn).await? else {
             return Err(SyncEventError::NotFound);
         };
         let mut active: sync_event::ActiveModel = model.into();
@@ -310,10 +347,7 @@ impl SyncEventService {
             active.connection_run_id = Set(patch.connection_run_id);
         }
         active.updated_at = Set(chrono::Utc::now().into());
-        match txn {
-            Some(txn) => Ok(Some(active.update(txn).await?)),
-            None => Ok(Some(active.update(&self.db).await?)),
-        }
+        Ok(Some(active.update(&conn).await?))
     }
 
     pub async fn update_by_uuid(
@@ -322,17 +356,7 @@ impl SyncEventService {
         patch: UpdateSyncEvent,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<sync_event::Model>, SyncEventError> {
-        let model = match txn {
-            Some(txn) => sync_event::Entity::find()
-                .filter(sync_event::Column::Uuid.eq(uuid))
-                .one(txn)
-                .await?,
-            None => sync_event::Entity::find()
-                .filter(sync_event::Column::Uuid.eq(uuid))
-                .one(&self.db)
-                .await?,
-        };
-        let Some(model) = model else {
+        let Some(model) = self.get_by_uuid(uuid, txn).await? else {
             return Err(SyncEventError::NotFound);
         };
         self.update_by_id(model.id, patch, txn).await
@@ -343,19 +367,13 @@ impl SyncEventService {
         id: i64,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<sync_event::Model>, SyncEventError> {
-        let model = match txn {
-            Some(txn) => sync_event::Entity::find_by_id(id).one(txn).await?,
-            None => sync_event::Entity::find_by_id(id).one(&self.db).await?,
-        };
-        let Some(model) = model else {
+        let conn = db::conn(&self.db, txn);
+        let Some(model) = sync_event::Entity::find_by_id(id).one(&conn).await? else {
             return Err(SyncEventError::NotFound);
         };
         let deleted = model.clone();
         let active: sync_event::ActiveModel = model.into();
-        match txn {
-            Some(txn) => active.delete(txn).await?,
-            None => active.delete(&self.db).await?,
-        };
+        active.delete(&conn).await?;
         Ok(Some(deleted))
     }
 
@@ -364,21 +382,137 @@ impl SyncEventService {
         uuid: Uuid,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<sync_event::Model>, SyncEventError> {
-        let model = match txn {
-            Some(txn) => sync_event::Entity::find()
-                .filter(sync_event::Column::Uuid.eq(uuid))
-                .one(txn)
-                .await?,
-            None => sync_event::Entity::find()
-                .filter(sync_event::Column::Uuid.eq(uuid))
-                .one(&self.db)
-                .await?,
-        };
-        let Some(model) = model else {
+        let Some(model) = self.get_by_uuid(uuid, txn).await? else {
             return Err(SyncEventError::NotFound);
         };
         self.delete_by_id(model.id, txn).await
     }
+
+    ///hands the event back to the poller as Pending, with `attempts` reset to 0 when
+    ///`reset_attempts`; an InProgress event's run is failed and its sync lock released.
+    ///Success events can't be retried
+    pub async fn retry(
+        &self,
+        uuid: Uuid,
+        reset_attempts: bool,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<sync_event::Model, SyncEventError> {
+        let Some(txn) = txn else {
+            let txn = self.db.begin().await?;
+            let retried = Box::pin(self.retry(uuid, reset_attempts, Some(&txn))).await?;
+            txn.commit().await?;
+            return Ok(retried);
+        };
+
+        let event = self.get_by_uuid(uuid, Some(txn)).await?.ok_or(SyncEventError::NotFound)?;
+        if event.status == SyncEventStatus::Success {
+            return Err(SyncEventError::InvalidState(
+                "A successful sync event can't be retried".to_string(),
+            ));
+        }
+        self.release_in_progress(&event, "Requeued by an operator", txn).await?;
+
+        let attempts = event.attempts;
+        let mut active: sync_event::ActiveModel = event.into();
+        active.status = Set(SyncEventStatus::Pending);
+        active.attempts = Set(if reset_attempts { 0 } else { attempts });
+        active.updated_at = Set(chrono::Utc::now().into());
+        Ok(active.update(txn).await?)
+    }
+
+    ///stops a Pending or InProgress event for good: Error, with `note` in `last_error`
+    ///(`{"cancelled": true, "message": note}`); an InProgress event's run is failed and its
+    ///sync lock released
+    pub async fn cancel(
+        &self,
+        uuid: Uuid,
+        note: &str,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<sync_event::Model, SyncEventError> {
+        let Some(txn) = txn else {
+            let txn = self.db.begin().await?;
+            let cancelled = Box::pin(self.cancel(uuid, note, Some(&txn))).await?;
+            txn.commit().await?;
+            return Ok(cancelled);
+        };
+
+        let event = self.get_by_uuid(uuid, Some(txn)).await?.ok_or(SyncEventError::NotFound)?;
+        if !matches!(event.status, SyncEventStatus::Pending | SyncEventStatus::InProgress) {
+            return Err(SyncEventError::InvalidState(
+                "Only pending or in-progress sync events can be cancelled".to_string(),
+            ));
+        }
+        let reason = format!("Cancelled by an operator: {}", note);
+        self.release_in_progress(&event, &reason, txn).await?;
+
+        let now = chrono::Utc::now();
+        let mut active: sync_event::ActiveModel = event.into();
+        active.status = Set(SyncEventStatus::Error);
+        active.last_error = Set(Some(serde_json::json!({ "cancelled": true, "message": note })));
+        active.last_errored_date = Set(Some(now.into()));
+        active.updated_at = Set(now.into());
+        Ok(active.update(txn).await?)
+    }
+
+    ///for an InProgress event: fails its run with `reason` and releases the sync lock the
+    ///run holds, so the connection can be polled again right away
+    async fn release_in_progress(
+        &self,
+        event: &sync_event::Model,
+        reason: &str,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), DbErr> {
+        if event.status != SyncEventStatus::InProgress {
+            return Ok(());
+        }
+        let Some(run_id) = event.connection_run_id else {
+            return Ok(());
+        };
+        let Some(run) = connection_run::Entity::find_by_id(run_id).one(txn).await? else {
+            return Ok(());
+        };
+        let run_uuid = run.uuid.to_string();
+        let mut active: connection_run::ActiveModel = run.into();
+        active.status = Set(ConnectionRunStatus::Error);
+        active.error_message = Set(Some(reason.to_string()));
+        active.updated_at = Set(chrono::Utc::now().into());
+        active.update(txn).await?;
+
+        let Some(sync_state_id) = event.connection_sync_state_id else {
+            return Ok(());
+        };
+        let sync_state = erp_connection_sync_state::Entity::find_by_id(sync_state_id)
+            .one(txn)
+            .await?
+            .filter(|ss| ss.sync_lock_owner.as_deref() == Some(run_uuid.as_str()));
+        if let Some(sync_state) = sync_state {
+            let mut active: erp_connection_sync_state::ActiveModel = sync_state.into();
+            active.sync_lock_owner = Set(None);
+            active.sync_lock_until = Set(None);
+            active.updated_at = Set(chrono::Utc::now().into());
+            active.update(txn).await?;
+        }
+        Ok(())
+    }
+}
+
+///`CreateSyncEvent` as an insertable model, shared by `create` and `create_many`
+fn to_active_model(data: CreateSyncEvent) -> sync_event::ActiveModel {
+    sync_event::ActiveModel {
+        original_record_body: Set(data.original_record_body),
+        details: Set(data.details),
+        event_direction: Set(data.event_direction),
+        inventory_record_event_id: Set(data.inventory_record_event_id),
+        sync_event_method: Set(data.sync_event_method),
+        sync_event_category: Set(data.sync_event_category),
+        attempts: Set(data.attempts.unwrap_or(0)),
+        status: Set(data.status.unwrap_or(SyncEventStatus::Pending)),
+        last_error: Set(data.last_error),
+        last_errored_date: Set(data.last_errored_date.map(Into::into)),
+        connection_sync_state_id: Set(data.connection_sync_state_id),
+        connection_run_id: Set(data.connection_run_id),
+        ..Default::default()
+    }
 }
 
 // END IMPLEMENTATION
@@ -0,0 +1,100 @@
+//! Short-lived cache of tenant active/removed status.
+//!
+//! Keyed by the public `tenant_id` (TN_xxx), or by `id_key` for lookups by the tenant's row
+//! id. Entries expire after `TENANT_STATUS_TTL_SECS` and are invalidated by `TenantService`
+//! once a tenant update or removal has committed. Cache failures are logged and treated as
+//! misses so lookups always fall back to the database.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+
+/// How long a cached status is trusted before re-reading the database.
+pub const TENANT_STATUS_TTL_SECS: u64 = 30;
+
+const KEY_PREFIX: &str = "tenant:active:";
+
+#[derive(Clone)]
+pub enum TenantStatusCache {
+    /// Shared across instances via `AppState.redis`.
+    Redis(ConnectionManager),
+    /// In-process only; for single-instance setups and tests.
+    Local(Arc<Mutex<HashMap<String, (bool, Instant)>>>),
+}
+
+impl TenantStatusCache {
+    pub fn local() -> Self {
+        TenantStatusCache::Local(Arc::new(Mutex::new(HashMap::new())))
+    }
+
+    ///key for a status looked up by the tenant's row id; never a `TN_` tenant_id
+    pub fn id_key(id: i64) -> String {
+        format!("id:{}", id)
+    }
+
+    fn key(tenant_id: &str) -> String {
+        format!("{}{}", KEY_PREFIX, tenant_id)
+    }
+
+    ///cached active flag, None on miss (or cache error)
+    pub async fn get(&self, tenant_id: &str) -> Option<bool> {
+        match self {
+            TenantStatusCache::Redis(redis) => {
+                let mut conn = redis.clone();
+                match conn.get::<_, Option<String>>(Self::key(tenant_id)).await {
+                    Ok(value) => value.map(|v| v == "1"),
+                    Err(e) => {
+                        tracing::warn!("Tenant status cache read failed: {}", e);
+                        None
+                    }
+                }
+            }
+            TenantStatusCache::Local(map) => {
+                let map = map.lock().ok()?;
+                map.get(tenant_id)
+                    .filter(|(_, expires_at)| *expires_at > Instant::now())
+                    .map(|(active, _)| *active)
+            }
+        }
+    }
+
+    pub async fn set(&self, tenant_id: &str, active: bool) {
+        match self {
+            TenantStatusCache::Redis(redis) => {
+                let mut conn = redis.clone();
+                let value = if active { "1" } else { "0" };
+                if let Err(e) = conn
+                    .set_ex::<_, _, ()>(Self::key(tenant_id), value, TENANT_STATUS_TTL_SECS)
+                    .await
+                {
+                    tracing::warn!("Tenant status cache write failed: {}", e);
+                }
+            }
+            TenantStatusCache::Local(map) => {
+                if let Ok(mut map) = map.lock() {
+                    let expires_at = Instant::now() + Duration::from_secs(TENANT_STATUS_TTL_SECS);
+                    map.insert(tenant_id.to_string(), (active, expires_at));
+                }
+            }
+        }
+    }
+
+    pub async fn invalidate(&self, tenant_id: &str) {
+        match self {
+            TenantStatusCache::Redis(redis) => {
+                let mut conn = redis.clone();
+                if let Err(e) = conn.del::<_, ()>(Self::key(tenant_id)).await {
+                    tracing::warn!("Tenant status cache invalidation failed: {}", e);
+                }
+            }
+            TenantStatusCache::Local(map) => {
+                if let Ok(mut map) = map.lock() {
+                    map.remove(tenant_id);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,58 @@
+//! The default tenant of single-tenant deployments.
+//!
+//! With `DEFAULT_TENANT_ID` set, the tenant is created at startup if it doesn't exist yet
+//! (`ensure_default_tenant`), and create APIs that take a tenant (`POST /connections`,
+//! `POST /client-systems/quickbooks/desktop/qwc`) fall back to it when the request names none
+//! (`tenant_id_or_default`). Callers authenticated with a tenant-scoped API token get no
+//! default: their requests keep naming the tenant.
+
+use entity::sea_orm_active_enums::Enum as TenantStatus;
+use entity::tenant;
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, DbErr, EntityTrait, QueryFilter, Set};
+
+/// Display name given to a default tenant created at startup.
+pub const DEFAULT_TENANT_DISPLAY_NAME: &str = "Default tenant";
+
+/// BEGUN IMPLEMENTATION ///
+///the tenant a create request is for: the one it names, otherwise `default` unless the caller
+///is tenant-scoped; None when neither applies
+pub fn tenant_id_or_default<'a>(
+    requested: Option<&'a str>,
+    default: Option<&'a str>,
+    tenant_scoped: bool,
+) -> Option<&'a str> {
+    match requested.filter(|id| !id.trim().is_empty()) {
+        Some(requested) => Some(requested),
+        None if tenant_scoped => None,
+        None => default,
+    }
+}
+
+///the tenant with public id `tenant_id`, created (active) if it doesn't exist yet
+pub async fn ensure_default_tenant(
+    db: &DatabaseConnection,
+    tenant_id: &str,
+) -> Result<tenant::Model, DbErr> {
+    let find = || {
+        tenant::Entity::find()
+            .filter(tenant::Column::TenantId.eq(tenant_id))
+            .one(db)
+    };
+    if let Some(existing) = find().await? {
+        return Ok(existing);
+    }
+
+    let created = tenant::ActiveModel {
+        tenant_id: Set(tenant_id.to_string()),
+        display_name: Set(Some(DEFAULT_TENANT_DISPLAY_NAME.to_string())),
+        status: Set(TenantStatus::Active),
+        ..Default::default()
+    }
+    .insert(db)
+    .await;
+    match created {
+        Ok(created) => Ok(created),
+        //another instance starting at the same time may have created it first
+        Err(e) => find().await?.ok_or(e),
+    }
+}
@@ -1,3 +1,5 @@
+pub mod cache;
+pub mod default;
 pub mod routes;
 pub mod services;
 
@@ -1,6 +1,7 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{Extension, OriginalUri, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    response::Response,
     routing::{delete, get, post, put},
     Json, Router,
 };
@@ -9,23 +10,39 @@ use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
 use crate::AppState;
-use super::services::{CreateTenant, TenantFilter, TenantService, UpdateTenant};
-use entity::sea_orm_active_enums::Enum as TenantStatus;
+use crate::audit::AuditContext;
+use crate::connection_identity::ConnectionIdentityService;
+use crate::idempotency::{
+    fingerprint, idempotent_create, IdempotencyError, IdempotencyKey, IdempotencyStore,
+};
+use crate::pagination::{paginated_response, PageInfo};
+use crate::security::AuthenticatedTenant;
+use crate::validation::{check_length, FieldError, MAX_DISPLAY_NAME_LEN};
+use super::services::{
+    BulkCreateError, CreateTenant, TenantError, TenantFilter, TenantService, UpdateTenant,
+};
+use entity::enum_strings::TenantStatus;
+
+/// Max tenants in one `POST /tenant/bulk` request.
+pub const MAX_BULK_TENANTS: usize = 500;
 
 
 /// RESPONSE SCHEMAS ///
 #[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct TenantResponse {
     pub id: i64,
     pub uuid: String,
     pub tenant_id: String,
     pub display_name: Option<String>,
-    pub status: String,
+    #[schema(value_type = String)]
+    pub status: TenantStatus,
     pub created_at: String,
     pub updated_at: String,
 }
 
 #[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct PaginatedTenantsResponse {
     pub items: Vec<TenantResponse>,
     pub total: u64,
@@ -35,29 +52,126 @@ pub struct PaginatedTenantsResponse {
 }
 
 #[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct ErrorResponse {
     pub error: String,
+    /// Field-level validation errors (only present on 400 responses).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Vec<FieldError>>,
+}
+
+impl ErrorResponse {
+    ///builds the 400 envelope for a failed `validate()`
+    pub fn validation(errors: Vec<FieldError>) -> (StatusCode, Json<ErrorResponse>) {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: "Validation failed".to_string(),
+                details: Some(errors),
+            }),
+        )
+    }
+
+    ///400 envelope for a malformed `Idempotency-Key` header
+    pub fn idempotency_key(message: String) -> (StatusCode, Json<ErrorResponse>) {
+        Self::validation(vec![FieldError::new("Idempotency-Key", message)])
+    }
+
+    ///409 while a request with the same `Idempotency-Key` is running, 422 when the key was
+    ///used with a different body; failures of the create itself go through `on_create`
+    pub fn idempotency<E>(
+        err: IdempotencyError<E>,
+        on_create: impl FnOnce(E) -> (StatusCode, Json<ErrorResponse>),
+    ) -> (StatusCode, Json<ErrorResponse>) {
+        let (status, error) = match err {
+            IdempotencyError::Create(e) => return on_create(e),
+            IdempotencyError::InProgress => (
+                StatusCode::CONFLICT,
+                "A request with this Idempotency-Key is still being processed",
+            ),
+            IdempotencyError::KeyReused => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "This Idempotency-Key was already used with a different request body",
+            ),
+        };
+        (
+            status,
+            Json(ErrorResponse {
+                error: error.to_string(),
+                details: None,
+            }),
+        )
+    }
 }
 
 #[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct DeleteResponse {
     pub message: String,
 }
 
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SetConnectionsEnabledResponse {
+    pub tenant_id: String,
+    pub enabled: bool,
+    /// Number of the tenant's connections that were updated.
+    pub updated: u64,
+}
+
+/// A row of a bulk create that was not created.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct BulkTenantErrorResponse {
+    /// Position of the row in `tenants`.
+    pub index: usize,
+    pub error: String,
+    /// Field-level validation errors (only present when the row failed validation).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<Vec<FieldError>>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct BulkCreateTenantsResponse {
+    /// Created tenants, in request order.
+    pub created: Vec<TenantResponse>,
+    /// Rows that were not created (always empty with `all_or_nothing`).
+    pub errors: Vec<BulkTenantErrorResponse>,
+}
+
 
 /// REQUEST SCHEMAS ///
-#[derive(Deserialize, ToSchema)]
+#[derive(Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct CreateTenantRequest {
     pub display_name: Option<String>,
 }
 
 #[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct BulkCreateTenantsRequest {
+    pub tenants: Vec<CreateTenantRequest>,
+    /// `true` (default): one invalid or failing row creates nothing. `false`: the other rows
+    /// are created and the failures are reported in `errors`.
+    pub all_or_nothing: Option<bool>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
 pub struct UpdateTenantRequest {
     pub display_name: Option<String>,
     pub status: Option<String>,
 }
 
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct SetConnectionsEnabledRequest {
+    pub enabled: bool,
+}
+
 #[derive(Deserialize, IntoParams)]
+#[serde(rename_all = "snake_case")]
 pub struct ListTenantsQuery {
     #[param(default = 1)]
     pub page: Option<u64>,
@@ -69,6 +183,65 @@ pub struct ListTenantsQuery {
 }
 
 
+/// VALIDATION ///
+impl CreateTenantRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_length(&mut errors, "display_name", self.display_name.as_deref(), MAX_DISPLAY_NAME_LEN);
+        errors
+    }
+}
+
+impl BulkCreateTenantsRequest {
+    pub fn all_or_nothing(&self) -> bool {
+        self.all_or_nothing.unwrap_or(true)
+    }
+
+    ///batch size, plus every row when `all_or_nothing` (otherwise invalid rows are reported
+    ///per row by the handler)
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        if self.tenants.is_empty() {
+            errors.push(FieldError::new("tenants", "must not be empty"));
+        } else if self.tenants.len() > MAX_BULK_TENANTS {
+            errors.push(FieldError::new(
+                "tenants",
+                format!("must contain at most {} tenants", MAX_BULK_TENANTS),
+            ));
+        }
+        if self.all_or_nothing() {
+            for (index, row) in self.tenants.iter().enumerate() {
+                errors.extend(row.validate().into_iter().map(|e| FieldError {
+                    field: format!("tenants[{}].{}", index, e.field),
+                    message: e.message,
+                }));
+            }
+        }
+        errors
+    }
+}
+
+impl UpdateTenantRequest {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_length(&mut errors, "display_name", self.display_name.as_deref(), MAX_DISPLAY_NAME_LEN);
+        check_status(&mut errors, self.status.as_deref());
+        errors
+    }
+}
+
+impl ListTenantsQuery {
+    pub fn validate(&self) -> Vec<FieldError> {
+        let mut errors = Vec::new();
+        check_status(&mut errors, self.status.as_deref());
+        if self.per_page == Some(0) {
+            errors.push(FieldError::new("per_page", "must be greater than 0"));
+        }
+        errors
+    }
+}
+
+
 /// HELPER FUNCTIONS ///
 fn model_to_response(model: entity::tenant::Model) -> TenantResponse {
     TenantResponse {
@@ -76,20 +249,32 @@ fn model_to_response(model: entity::tenant::Model) -> TenantResponse {
         uuid: model.uuid.to_string(),
         tenant_id: model.tenant_id,
         display_name: model.display_name,
-        status: format!("{:?}", model.status).to_lowercase(),
+        status: model.status,
         created_at: model.created_at.to_rfc3339(),
         updated_at: model.updated_at.to_rfc3339(),
     }
 }
 
-fn parse_status(status: &str) -> Option<TenantStatus> {
-    match status.to_lowercase().as_str() {
-        "active" => Some(TenantStatus::Active),
-        "removed" => Some(TenantStatus::Removed),
-        _ => None,
+fn check_status(errors: &mut Vec<FieldError>, status: Option<&str>) {
+    if let Some(Err(e)) = status.map(str::parse::<TenantStatus>) {
+        errors.push(FieldError::new(
+            "status",
+            format!("must be one of: {}", e.expected.join(", ")),
+        ));
     }
 }
 
+///the error envelope for a `TenantError`, with its status (409 for a unique violation)
+fn tenant_error(e: TenantError) -> (StatusCode, Json<ErrorResponse>) {
+    (
+        e.status_code(),
+        Json(ErrorResponse {
+            error: e.message(),
+            details: None,
+        }),
+    )
+}
+
 
 /// ROUTE HANDLERS ///
 
@@ -99,15 +284,29 @@ fn parse_status(status: &str) -> Option<TenantStatus> {
     tag = "Tenant",
     params(ListTenantsQuery),
     responses(
-        (status = 200, description = "List of tenants", body = PaginatedTenantsResponse),
+        (status = 200, description = "List of tenants", body = PaginatedTenantsResponse,
+            headers(
+                ("X-Total-Count" = u64, description = "Total matching tenants"),
+                ("X-Page" = u64, description = "Current page"),
+                ("X-Per-Page" = u64, description = "Page size"),
+                ("X-Total-Pages" = u64, description = "Number of pages"),
+                ("Link" = String, description = "RFC 5988 first/prev/next/last page links")
+            )),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     )
 )]
 pub async fn list_tenants(
     State(state): State<AppState>,
+    OriginalUri(uri): OriginalUri,
     Query(query): Query<ListTenantsQuery>,
-) -> Result<Json<PaginatedTenantsResponse>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<(HeaderMap, Json<PaginatedTenantsResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let errors = query.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
     let service = TenantService::new(state.db);
 
     let page = query.page.unwrap_or(1);
@@ -115,7 +314,7 @@ pub async fn list_tenants(
 
     let filter = if query.status.is_some() || query.display_name.is_some() || query.tenant_id.is_some() {
         Some(TenantFilter {
-            status: query.status.and_then(|s| parse_status(&s)),
+            status: query.status.and_then(|s| s.parse().ok()),
             display_name: query.display_name,
             tenant_id: query.tenant_id,
         })
@@ -124,17 +323,32 @@ pub async fn list_tenants(
     };
 
     match service.get_all(page, per_page, filter, None).await {
-        Ok(result) => Ok(Json(PaginatedTenantsResponse {
-            items: result.items.into_iter().map(model_to_response).collect(),
-            total: result.total,
-            page: result.page,
-            per_page: result.per_page,
-            total_pages: result.total_pages,
-        })),
+        Ok(result) => {
+            let headers = paginated_response(
+                &PageInfo {
+                    page: result.page,
+                    per_page: result.per_page,
+                    total: result.total,
+                    total_pages: result.total_pages,
+                },
+                &uri,
+            );
+            Ok((
+                headers,
+                Json(PaginatedTenantsResponse {
+                    items: result.items.into_iter().map(model_to_response).collect(),
+                    total: result.total,
+                    page: result.page,
+                    per_page: result.per_page,
+                    total_pages: result.total_pages,
+                }),
+            ))
+        }
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: format!("Database error: {}", e),
+                details: None,
             }),
         )),
     }
@@ -165,12 +379,14 @@ pub async fn get_tenant(
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 error: "Tenant not found".to_string(),
+                details: None,
             }),
         )),
         Err(e) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: format!("Database error: {}", e),
+                details: None,
             }),
         )),
     }
@@ -181,28 +397,126 @@ pub async fn get_tenant(
     post,
     path = "/create",
     tag = "Tenant",
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Repeats with the same key within 24 hours return the first response instead of creating another tenant")
+    ),
     request_body = CreateTenantRequest,
     responses(
-        (status = 201, description = "Tenant created", body = TenantResponse),
+        (status = 201, description = "Tenant created (or replayed for a repeated Idempotency-Key, with Idempotent-Replayed: true)", body = TenantResponse),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 409, description = "A tenant with the same unique value exists, or a request with the same Idempotency-Key is still being processed", body = ErrorResponse),
+        (status = 422, description = "Idempotency-Key already used with a different body", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
     ))]
 pub async fn create_tenant(
     State(state): State<AppState>,
+    audit: AuditContext,
+    headers: HeaderMap,
     Json(body): Json<CreateTenantRequest>,
-) -> Result<(StatusCode, Json<TenantResponse>), (StatusCode, Json<ErrorResponse>)> {
-    let service = TenantService::new(state.db);
+) -> Result<Response, (StatusCode, Json<ErrorResponse>)> {
+    let errors = body.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+    let key = IdempotencyKey::from_headers(&headers, "tenant:create", audit.actor_token_id)
+        .map_err(ErrorResponse::idempotency_key)?;
+    let request_fingerprint = fingerprint(&body);
+
+    let service = TenantService::new(state.db).with_audit(audit);
 
     let data = CreateTenant {
         display_name: body.display_name,
     };
 
-    match service.create(data, None).await {
-        Ok(tenant) => Ok((StatusCode::CREATED, Json(model_to_response(tenant)))),
-        Err(e) => Err((
+    let created = idempotent_create(
+        &IdempotencyStore::Redis(state.redis),
+        key.as_ref(),
+        &request_fingerprint,
+        || async { service.create(data, None).await.map(model_to_response) },
+    )
+    .await
+    .map_err(|e| ErrorResponse::idempotency(e, |e| tenant_error(TenantError::from(e))))?;
+    Ok(created.into_response(StatusCode::CREATED))
+}
+
+#[utoipa::path(
+    post,
+    path = "/bulk",
+    tag = "Tenant",
+    request_body = BulkCreateTenantsRequest,
+    responses(
+        (status = 201, description = "Tenants created in one transaction; with all_or_nothing=false, rows that were not created are listed in errors", body = BulkCreateTenantsResponse),
+        (status = 400, description = "Validation failed (empty or oversized batch; any invalid row with all_or_nothing)", body = ErrorResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 500, description = "Internal server error; with all_or_nothing nothing was created", body = ErrorResponse)
+    ))]
+pub async fn bulk_create_tenants(
+    State(state): State<AppState>,
+    audit: AuditContext,
+    Json(body): Json<BulkCreateTenantsRequest>,
+) -> Result<(StatusCode, Json<BulkCreateTenantsResponse>), (StatusCode, Json<ErrorResponse>)> {
+    let errors = body.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let all_or_nothing = body.all_or_nothing();
+    let mut errors = Vec::new();
+    let mut indexes = Vec::new();
+    let mut items = Vec::new();
+    for (index, row) in body.tenants.into_iter().enumerate() {
+        let row_errors = row.validate();
+        if row_errors.is_empty() {
+            indexes.push(index);
+            items.push(CreateTenant {
+                display_name: row.display_name,
+            });
+        } else {
+            errors.push(BulkTenantErrorResponse {
+                index,
+                error: "Validation failed".to_string(),
+                details: Some(row_errors),
+            });
+        }
+    }
+
+    let service = TenantService::new(state.db).with_audit(audit);
+
+    match service.create_many(items, all_or_nothing, None).await {
+        Ok(results) => {
+            let mut created = Vec::new();
+            for (index, result) in indexes.into_iter().zip(results) {
+                match result {
+                    Ok(tenant) => created.push(model_to_response(tenant)),
+                    Err(e) => errors.push(BulkTenantErrorResponse {
+                        index,
+                        error: format!("Database error: {}", e),
+                        details: None,
+                    }),
+                }
+            }
+            errors.sort_by_key(|e| e.index);
+            Ok((
+                StatusCode::CREATED,
+                Json(BulkCreateTenantsResponse { created, errors }),
+            ))
+        }
+        Err(BulkCreateError::Row(row, e)) => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!(
+                    "Database error at tenants[{}], no tenants were created: {}",
+                    indexes[row], e
+                ),
+                details: None,
+            }),
+        )),
+        Err(BulkCreateError::Db(e)) => Err((
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: format!("Database error: {}", e),
+                details: None,
             }),
         )),
     }
@@ -218,6 +532,7 @@ pub async fn create_tenant(
     request_body = UpdateTenantRequest,
     responses(
         (status = 200, description = "Tenant updated", body = TenantResponse),
+        (status = 400, description = "Validation failed", body = ErrorResponse),
         (status = 401, description = "Unauthorized", body = ErrorResponse),
         (status = 404, description = "Tenant not found", body = ErrorResponse),
         (status = 500, description = "Internal server error", body = ErrorResponse)
@@ -225,13 +540,19 @@ pub async fn create_tenant(
 pub async fn update_tenant(
     State(state): State<AppState>,
     Path(tenant_id): Path<String>,
+    audit: AuditContext,
     Json(body): Json<UpdateTenantRequest>,
 ) -> Result<Json<TenantResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let service = TenantService::new(state.db);
+    let errors = body.validate();
+    if !errors.is_empty() {
+        return Err(ErrorResponse::validation(errors));
+    }
+
+    let service = TenantService::with_cache(state.db, state.redis).with_audit(audit);
 
     let patch = UpdateTenant {
         display_name: body.display_name,
-        status: body.status.and_then(|s| parse_status(&s)),
+        status: body.status.and_then(|s| s.parse().ok()),
     };
 
     match service.update_by_tenant_id(&tenant_id, patch, None).await {
@@ -240,20 +561,17 @@ pub async fn update_tenant(
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 error: "Tenant not found".to_string(),
+                details: None,
             }),
         )),
         Err(super::services::TenantError::NotFound) => Err((
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 error: "Tenant not found".to_string(),
+                details: None,
             }),
         )),
-        Err(super::services::TenantError::Db(e)) => Err((
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: format!("Database error: {}", e),
-            }),
-        )),
+        Err(e) => Err(tenant_error(e)),
     }
 }
 
@@ -274,8 +592,9 @@ pub async fn update_tenant(
 pub async fn delete_tenant(
     State(state): State<AppState>,
     Path(tenant_id): Path<String>,
+    audit: AuditContext,
 ) -> Result<Json<DeleteResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let service = TenantService::new(state.db);
+    let service = TenantService::with_cache(state.db, state.redis).with_audit(audit);
 
     match service.delete_by_tenant_id(&tenant_id, None).await {
         Ok(Some(_)) => Ok(Json(DeleteResponse {
@@ -285,21 +604,78 @@ pub async fn delete_tenant(
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 error: "Tenant not found".to_string(),
+                details: None,
             }),
         )),
         Err(super::services::TenantError::NotFound) => Err((
             StatusCode::NOT_FOUND,
             Json(ErrorResponse {
                 error: "Tenant not found".to_string(),
+                details: None,
             }),
         )),
-        Err(super::services::TenantError::Db(e)) => Err((
+        Err(e) => Err(tenant_error(e)),
+    }
+}
+
+
+#[utoipa::path(
+    post,
+    path = "/{tenant_id}/connections/set-enabled",
+    tag = "Tenant",
+    params(
+        ("tenant_id" = String, Path, description = "Tenant ID (TN_xxx format)")
+    ),
+    request_body = SetConnectionsEnabledRequest,
+    responses(
+        (status = 200, description = "Every connection of the tenant enabled or disabled; disabling releases their sync locks", body = SetConnectionsEnabledResponse),
+        (status = 401, description = "Unauthorized", body = ErrorResponse),
+        (status = 404, description = "Tenant not found, or another tenant than the API token's", body = ErrorResponse),
+        (status = 500, description = "Internal server error", body = ErrorResponse)
+    ))]
+pub async fn set_connections_enabled(
+    State(state): State<AppState>,
+    Path(tenant_id): Path<String>,
+    audit: AuditContext,
+    scope: Option<Extension<AuthenticatedTenant>>,
+    Json(body): Json<SetConnectionsEnabledRequest>,
+) -> Result<Json<SetConnectionsEnabledResponse>, (StatusCode, Json<ErrorResponse>)> {
+    let db_error = |e: sea_orm::DbErr| {
+        (
             StatusCode::INTERNAL_SERVER_ERROR,
             Json(ErrorResponse {
                 error: format!("Database error: {}", e),
+                details: None,
             }),
-        )),
-    }
+        )
+    };
+
+    //a tenant-scoped API token only reaches its own tenant's connections
+    let scoped_tenant = scope.and_then(|Extension(AuthenticatedTenant(tenant_id))| tenant_id);
+    let tenant = TenantService::new(state.db.clone())
+        .get_by_tenant_id(&tenant_id, None)
+        .await
+        .map_err(db_error)?
+        .filter(|tenant| scoped_tenant.is_none_or(|id| id == tenant.id))
+        .ok_or((
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Tenant not found".to_string(),
+                details: None,
+            }),
+        ))?;
+
+    let updated = ConnectionIdentityService::new(state.db)
+        .with_audit(audit)
+        .set_enabled_by_tenant(tenant.id, body.enabled, None)
+        .await
+        .map_err(db_error)?;
+
+    Ok(Json(SetConnectionsEnabledResponse {
+        tenant_id: tenant.tenant_id,
+        enabled: body.enabled,
+        updated,
+    }))
 }
 
 
@@ -309,5 +685,7 @@ pub async fn delete_tenant(
 pub fn create_router() -> Router<AppState> {
     Router::new()
         .route("/", get(list_tenants).post(create_tenant))
+        .route("/bulk", post(bulk_create_tenants))
         .route("/{tenant_id}", get(get_tenant).put(update_tenant).delete(delete_tenant))
+        .route("/{tenant_id}/connections/set-enabled", post(set_connections_enabled))
 }
@@ -1,24 +1,73 @@
+use sea_orm::sea_query::SimpleExpr;
 use sea_orm::{
     ActiveModelTrait, ColumnTrait, Condition, DatabaseConnection, DatabaseTransaction, DbErr,
-    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set,
+    EntityTrait, PaginatorTrait, QueryFilter, QueryOrder, Set, TransactionTrait,
 };
 use entity::tenant;
 use entity::sea_orm_active_enums::Enum as TenantStatus;
+use axum::http::StatusCode;
+use redis::aio::ConnectionManager;
 use uuid::Uuid;
 
+use crate::audit::services::{AuditAction, AuditContext, AuditEntry, AuditService};
+use crate::db::unique::UniqueViolation;
+
+use super::cache::TenantStatusCache;
+
 
 //DEBUG AND ERRORS ///
 #[allow(dead_code)]
 #[derive(Debug)]
 pub enum TenantError {
     NotFound,
+    ///a unique constraint refused the write; the message says which
+    Conflict(String),
     Db(DbErr),
 }
 
 #[allow(dead_code)]
 impl From<DbErr> for TenantError {
     fn from(err: DbErr) -> Self {
-        TenantError::Db(err)
+        match UniqueViolation::from_db_err(&err) {
+            Some(violation) => TenantError::Conflict(violation.message()),
+            None => TenantError::Db(err),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl TenantError {
+    /// HTTP status for this error.
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            TenantError::NotFound => StatusCode::NOT_FOUND,
+            TenantError::Conflict(_) => StatusCode::CONFLICT,
+            TenantError::Db(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// User-facing error message.
+    pub fn message(&self) -> String {
+        match self {
+            TenantError::NotFound => "Tenant not found".to_string(),
+            TenantError::Conflict(message) => message.clone(),
+            TenantError::Db(e) => format!("Database error: {}", e),
+        }
+    }
+}
+
+/// A bulk create that failed: either row `index` of the batch, or the transaction itself.
+#[allow(dead_code)]
+#[derive(Debug)]
+pub enum BulkCreateError {
+    Row(usize, DbErr),
+    Db(DbErr),
+}
+
+#[allow(dead_code)]
+impl From<DbErr> for BulkCreateError {
+    fn from(err: DbErr) -> Self {
+        BulkCreateError::Db(err)
     }
 }
 
@@ -28,6 +77,8 @@ impl From<DbErr> for TenantError {
 /// BEGUN STRUCTS AND ENUMS ///
 pub struct TenantService {
     db: DatabaseConnection,
+    cache: Option<TenantStatusCache>,
+    audit: Option<AuditContext>,
 }
 
 #[allow(dead_code)]
@@ -65,7 +116,57 @@ pub struct PaginatedTenants {
 #[allow(dead_code)]
 impl TenantService {
     pub fn new(db: DatabaseConnection) -> Self {
-        Self { db }
+        Self {
+            db,
+            cache: None,
+            audit: None,
+        }
+    }
+
+    ///caches `is_tenant_active` in Redis and invalidates it on update/remove
+    pub fn with_cache(db: DatabaseConnection, redis: ConnectionManager) -> Self {
+        Self {
+            db,
+            cache: Some(TenantStatusCache::Redis(redis)),
+            audit: None,
+        }
+    }
+
+    ///same as `with_cache` but with an explicit cache backend
+    pub fn with_status_cache(db: DatabaseConnection, cache: TenantStatusCache) -> Self {
+        Self {
+            db,
+            cache: Some(cache),
+            audit: None,
+        }
+    }
+
+    ///records every create/update/delete in `audit_log`, in the same transaction as the
+    ///change (one is started when the caller passes none)
+    pub fn with_audit(mut self, audit: AuditContext) -> Self {
+        self.audit = Some(audit);
+        self
+    }
+
+    async fn record_audit(
+        &self,
+        entry: AuditEntry,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<(), DbErr> {
+        if let (Some(audit), Some(txn)) = (&self.audit, txn) {
+            AuditService::new(self.db.clone()).record(audit, entry, txn).await?;
+        }
+        Ok(())
+    }
+
+    ///drops the cached status of `tenant`, under both its tenant_id and its row id. An update
+    ///or removal made in the caller's transaction leaves the cache alone (a lookup before the
+    ///commit would cache the old status again), so the caller calls this once it has committed
+    pub async fn invalidate_cache(&self, tenant: &tenant::Model) {
+        if let Some(cache) = &self.cache {
+            cache.invalidate(&tenant.tenant_id).await;
+            cache.invalidate(&TenantStatusCache::id_key(tenant.id)).await;
+        }
     }
 
     ///generates a tenant ID in format TN_<uuid without dashes>
@@ -197,6 +298,13 @@ impl TenantService {
         data: CreateTenant,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<tenant::Model, DbErr> {
+        if let (Some(_), None) = (&self.audit, txn) {
+            let txn = self.db.begin().await?;
+            let created = Box::pin(self.create(data, Some(&txn))).await?;
+            txn.commit().await?;
+            return Ok(created);
+        }
+
         let tenant_id = Self::generate_tenant_id();
 
         let active = tenant::ActiveModel {
@@ -206,71 +314,111 @@ impl TenantService {
             ..Default::default()
         };
 
-        match txn {
-            Some(txn) => active.insert(txn).await,
-            None => active.insert(&self.db).await,
-        }
+        let created = match txn {
+            Some(txn) => active.insert(txn).await?,
+            None => active.insert(&self.db).await?,
+        };
+        self.record_audit(AuditEntry::created(created.uuid, &created), txn)
+            .await?;
+
+        Ok(created)
     }
 
-    pub async fn update_by_uuid(
+    ///creates every tenant of `items` in one transaction (one is started when the caller
+    ///passes none), returning one result per item in order. With `all_or_nothing` the first
+    ///failing row aborts the batch and nothing is created; otherwise each row runs in its own
+    ///savepoint, so a failed row is reported and the others are still created
+    pub async fn create_many(
         &self,
-        uuid: Uuid,
-        patch: UpdateTenant,
+        items: Vec<CreateTenant>,
+        all_or_nothing: bool,
         txn: Option<&DatabaseTransaction>,
-    ) -> Result<Option<tenant::Model>, TenantError> {
-        let model = match txn {
-            Some(txn) => {
-                tenant::Entity::find()
-                    .filter(tenant::Column::Uuid.eq(uuid))
-                    .one(txn)
-                    .await?
-            }
-            None => {
-                tenant::Entity::find()
-                    .filter(tenant::Column::Uuid.eq(uuid))
-                    .one(&self.db)
-                    .await?
-            }
-        };
-
-        let Some(model) = model else {
-            return Err(TenantError::NotFound);
+    ) -> Result<Vec<Result<tenant::Model, DbErr>>, BulkCreateError> {
+        let Some(txn) = txn else {
+            let txn = self.db.begin().await?;
+            let results = Box::pin(self.create_many(items, all_or_nothing, Some(&txn))).await?;
+            txn.commit().await?;
+            return Ok(results);
         };
 
-        let mut new_data: tenant::ActiveModel = model.into();
-
-        if let Some(display_name) = patch.display_name {
-            new_data.display_name = Set(Some(display_name));
-        }
+        let mut results = Vec::with_capacity(items.len());
+        for (index, data) in items.into_iter().enumerate() {
+            if all_or_nothing {
+                let created = self
+                    .create(data, Some(txn))
+                    .await
+                    .map_err(|e| BulkCreateError::Row(index, e))?;
+                results.push(Ok(created));
+                continue;
+            }
 
-        if let Some(status) = patch.status {
-            new_data.status = Set(status);
+            let savepoint = txn.begin().await?;
+            match self.create(data, Some(&savepoint)).await {
+                Ok(created) => {
+                    savepoint.commit().await?;
+                    results.push(Ok(created));
+                }
+                Err(e) => {
+                    savepoint.rollback().await?;
+                    results.push(Err(e));
+                }
+            }
         }
 
-        new_data.updated_at = Set(chrono::Utc::now().into());
+        Ok(results)
+    }
 
-        match txn {
-            Some(txn) => Ok(Some(new_data.update(txn).await?)),
-            None => Ok(Some(new_data.update(&self.db).await?)),
-        }
+    ///with `txn`, call `invalidate_cache` once it has committed
+    pub async fn update_by_uuid(
+        &self,
+        uuid: Uuid,
+        patch: UpdateTenant,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<tenant::Model>, TenantError> {
+        self.update_where(tenant::Column::Uuid.eq(uuid), patch, AuditAction::Update, txn)
+            .await
     }
 
+    ///with `txn`, call `invalidate_cache` once it has committed
     pub async fn update_by_tenant_id(
         &self,
         tenant_id: &str,
         patch: UpdateTenant,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<tenant::Model>, TenantError> {
+        self.update_where(tenant::Column::TenantId.eq(tenant_id), patch, AuditAction::Update, txn)
+            .await
+    }
+
+    ///applies `patch` to the tenant matching `condition`; `action` is what the audit row
+    ///records (soft deletes are updates of the status)
+    async fn update_where(
+        &self,
+        condition: SimpleExpr,
+        patch: UpdateTenant,
+        action: AuditAction,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<Option<tenant::Model>, TenantError> {
+        if let (Some(_), None) = (&self.audit, txn) {
+            let txn = self.db.begin().await?;
+            let updated = Box::pin(self.update_where(condition, patch, action, Some(&txn))).await?;
+            txn.commit().await?;
+            if let Some(updated) = &updated {
+                self.invalidate_cache(updated).await;
+            }
+            return Ok(updated);
+        }
+
         let model = match txn {
             Some(txn) => {
                 tenant::Entity::find()
-                    .filter(tenant::Column::TenantId.eq(tenant_id))
+                    .filter(condition)
                     .one(txn)
                     .await?
             }
             None => {
                 tenant::Entity::find()
-                    .filter(tenant::Column::TenantId.eq(tenant_id))
+                    .filter(condition)
                     .one(&self.db)
                     .await?
             }
@@ -280,7 +428,7 @@ impl TenantService {
             return Err(TenantError::NotFound);
         };
 
-        let mut new_data: tenant::ActiveModel = model.into();
+        let mut new_data: tenant::ActiveModel = model.clone().into();
 
         if let Some(display_name) = patch.display_name {
             new_data.display_name = Set(Some(display_name));
@@ -292,56 +440,105 @@ impl TenantService {
 
         new_data.updated_at = Set(chrono::Utc::now().into());
 
-        match txn {
-            Some(txn) => Ok(Some(new_data.update(txn).await?)),
-            None => Ok(Some(new_data.update(&self.db).await?)),
+        let updated = match txn {
+            Some(txn) => new_data.update(txn).await?,
+            None => new_data.update(&self.db).await?,
+        };
+        self.record_audit(AuditEntry::changed(action, updated.uuid, &model, &updated), txn)
+            .await?;
+        //a cache refilled before the caller's transaction commits would keep the old status
+        if txn.is_none() {
+            self.invalidate_cache(&updated).await;
         }
+
+        Ok(Some(updated))
     }
 
-    ///soft delete - sets status to removed instead of deleting
+    ///soft delete - sets status to removed instead of deleting; with `txn`, call
+    ///`invalidate_cache` once it has committed
     pub async fn delete_by_uuid(
         &self,
         uuid: Uuid,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<tenant::Model>, TenantError> {
-        self.update_by_uuid(
-            uuid,
+        self.update_where(
+            tenant::Column::Uuid.eq(uuid),
             UpdateTenant {
                 display_name: None,
                 status: Some(TenantStatus::Removed),
             },
+            AuditAction::Delete,
             txn,
         )
         .await
     }
 
-    ///soft delete - sets status to removed instead of deleting
+    ///soft delete - sets status to removed instead of deleting; with `txn`, call
+    ///`invalidate_cache` once it has committed
     pub async fn delete_by_tenant_id(
         &self,
         tenant_id: &str,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<Option<tenant::Model>, TenantError> {
-        self.update_by_tenant_id(
-            tenant_id,
+        self.update_where(
+            tenant::Column::TenantId.eq(tenant_id),
             UpdateTenant {
                 display_name: None,
                 status: Some(TenantStatus::Removed),
             },
+            AuditAction::Delete,
             txn,
         )
         .await
     }
 
+    ///checks the cache first (when configured), falls back to the DB and repopulates
+    ///lookups inside a transaction always read the DB so they see uncommitted changes
     pub async fn is_tenant_active(
         &self,
         tenant_id: &str,
         txn: Option<&DatabaseTransaction>,
     ) -> Result<bool, DbErr> {
-        let model = match self.get_by_tenant_id(tenant_id, txn).await? {
-            Some(m) => m,
-            None => return Ok(false),
+        self.is_active_where(tenant_id, tenant::Column::TenantId.eq(tenant_id), txn)
+            .await
+    }
+
+    ///`is_tenant_active` by the tenant's row id, the `tenant_id` connections and API tokens
+    ///carry; cached under `TenantStatusCache::id_key`
+    pub async fn is_tenant_active_by_id(
+        &self,
+        id: i64,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<bool, DbErr> {
+        self.is_active_where(&TenantStatusCache::id_key(id), tenant::Column::Id.eq(id), txn)
+            .await
+    }
+
+    ///active status of the tenant matching `condition`, cached under `key`
+    async fn is_active_where(
+        &self,
+        key: &str,
+        condition: SimpleExpr,
+        txn: Option<&DatabaseTransaction>,
+    ) -> Result<bool, DbErr> {
+        let cache = if txn.is_none() { self.cache.as_ref() } else { None };
+
+        if let Some(cache) = cache
+            && let Some(active) = cache.get(key).await
+        {
+            return Ok(active);
+        }
+
+        let model = match txn {
+            Some(txn) => tenant::Entity::find().filter(condition).one(txn).await?,
+            None => tenant::Entity::find().filter(condition).one(&self.db).await?,
         };
+        let active = model.is_some_and(|m| m.status == TenantStatus::Active);
+
+        if let Some(cache) = cache {
+            cache.set(key, active).await;
+        }
 
-        Ok(model.status == TenantStatus::Active)
+        Ok(active)
     }
 }
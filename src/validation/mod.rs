@@ -0,0 +1,86 @@
+//! Request validation helpers shared by route handlers.
+//!
+//! Each request type exposes a `validate()` method returning a `Vec<FieldError>`.
+//! An empty vec means the request is valid; otherwise the handler responds with
+//! 400 and the field-level details in the error envelope.
+//!
+//! `payload` applies the same `FieldError` reporting to provider record bodies, and `path`
+//! to UUID path parameters (`PathUuid`).
+
+pub mod path;
+pub mod payload;
+
+pub use path::PathUuid;
+
+use sea_orm::ActiveEnum;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// Max length for free-text display names.
+pub const MAX_DISPLAY_NAME_LEN: usize = 255;
+
+/// Max length for `VARCHAR(255)` identifier columns (realm id, company file id, ...).
+pub const MAX_IDENTIFIER_LEN: usize = 255;
+
+/// A single validation failure for a request field.
+#[derive(Debug, Clone, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+///records an error when an optional string is blank or longer than `max` characters
+pub fn check_length(errors: &mut Vec<FieldError>, field: &str, value: Option<&str>, max: usize) {
+    let Some(value) = value else {
+        return;
+    };
+
+    if value.trim().is_empty() {
+        errors.push(FieldError::new(field, "must not be blank"));
+    } else if value.chars().count() > max {
+        errors.push(FieldError::new(
+            field,
+            format!("must be at most {} characters", max),
+        ));
+    }
+}
+
+///parses an enum from its database wire value (case-insensitive)
+///records an error listing the accepted values when the string is unknown
+pub fn parse_enum<E>(errors: &mut Vec<FieldError>, field: &str, value: &str) -> Option<E>
+where
+    E: ActiveEnum<Value = String>,
+{
+    match E::try_from_value(&value.trim().to_lowercase()) {
+        Ok(v) => Some(v),
+        Err(_) => {
+            errors.push(FieldError::new(
+                field,
+                format!("must be one of: {}", E::values().join(", ")),
+            ));
+            None
+        }
+    }
+}
+
+///parses an optional enum field, see `parse_enum`
+pub fn parse_optional_enum<E>(
+    errors: &mut Vec<FieldError>,
+    field: &str,
+    value: Option<&str>,
+) -> Option<E>
+where
+    E: ActiveEnum<Value = String>,
+{
+    value.and_then(|v| parse_enum(errors, field, v))
+}
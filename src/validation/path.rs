@@ -0,0 +1,59 @@
+//! `PathUuid`: UUID path parameters that reject malformed values with a 400.
+//!
+//! axum's `Path<Uuid>` answers a malformed UUID with a plain-text 400, unlike every other
+//! validation failure. `PathUuid` checks each path parameter first and answers with the
+//! validation envelope (`{"error": "Validation failed", "details": [...]}`, naming the
+//! parameter), so a handler only sees well-formed UUIDs and its 404 always means "no such
+//! row".
+
+use axum::extract::{FromRequestParts, Path, RawPathParams};
+use axum::http::{request::Parts, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use serde::de::DeserializeOwned;
+use serde_json::json;
+use uuid::Uuid;
+
+use super::FieldError;
+
+/// UUID path parameter(s): `PathUuid(uuid): PathUuid` for one, `PathUuid((a, b)):
+/// PathUuid<(Uuid, Uuid)>` for several. Every parameter of the route must be a UUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathUuid<T = Uuid>(pub T);
+
+///the path parameters of `params` that aren't UUIDs, as validation errors
+pub fn invalid_uuid_params<'a>(params: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<FieldError> {
+    params
+        .into_iter()
+        .filter(|(_, value)| Uuid::parse_str(value).is_err())
+        .map(|(name, _)| FieldError::new(name, "must be a UUID"))
+        .collect()
+}
+
+impl<S, T> FromRequestParts<S> for PathUuid<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Send,
+{
+    type Rejection = Response;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let params = RawPathParams::from_request_parts(parts, state)
+            .await
+            .map_err(IntoResponse::into_response)?;
+        let errors = invalid_uuid_params(&params);
+        if !errors.is_empty() {
+            //same envelope as ErrorResponse::validation
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "error": "Validation failed", "details": errors })),
+            )
+                .into_response());
+        }
+
+        Path::<T>::from_request_parts(parts, state)
+            .await
+            .map(|Path(value)| PathUuid(value))
+            .map_err(IntoResponse::into_response)
+    }
+}
@@ -0,0 +1,77 @@
+//! Schema checks for provider payloads (`original_record_body`) before they are upserted.
+//!
+//! Each sync event category has a small schema: the fields a record must carry, each
+//! accepted under any of the key spellings providers use (`ListID` for QBD, `Id` for
+//! Salesforce, `id` for JSON APIs). A record failing its schema is rejected on its own;
+//! the rest of the batch is still applied.
+
+use entity::sea_orm_active_enums::SyncEventCategory;
+use serde_json::Value;
+
+use super::FieldError;
+
+/// A field a payload must carry, under any of `keys`.
+pub struct RequiredField {
+    /// Name reported in `FieldError::field`.
+    pub field: &'static str,
+    pub keys: &'static [&'static str],
+}
+
+/// Required fields for one record category.
+pub struct PayloadSchema {
+    pub required: &'static [RequiredField],
+}
+
+/// Inventory records need a provider id and a name.
+pub const INVENTORY_SCHEMA: PayloadSchema = PayloadSchema {
+    required: &[
+        RequiredField {
+            field: "id",
+            keys: &["id", "Id", "ListID", "system_id"],
+        },
+        RequiredField {
+            field: "name",
+            keys: &["name", "Name"],
+        },
+    ],
+};
+
+///schema for a category; categories without one are not checked
+pub fn schema_for(category: &SyncEventCategory) -> Option<&'static PayloadSchema> {
+    match category {
+        SyncEventCategory::Inventory => Some(&INVENTORY_SCHEMA),
+        _ => None,
+    }
+}
+
+///validates a record body against its category's schema; empty means valid
+pub fn validate_record_body(category: &SyncEventCategory, body: &Value) -> Vec<FieldError> {
+    match schema_for(category) {
+        Some(schema) => validate_payload(schema, body),
+        None => Vec::new(),
+    }
+}
+
+///checks that `body` is an object carrying every required field as a non-blank string or number
+pub fn validate_payload(schema: &PayloadSchema, body: &Value) -> Vec<FieldError> {
+    let Some(object) = body.as_object() else {
+        return vec![FieldError::new("body", "must be a JSON object")];
+    };
+
+    let mut errors = Vec::new();
+    for required in schema.required {
+        let value = required.keys.iter().find_map(|key| object.get(*key));
+        match value {
+            None | Some(Value::Null) => errors.push(FieldError::new(required.field, "is required")),
+            Some(Value::String(s)) if s.trim().is_empty() => {
+                errors.push(FieldError::new(required.field, "must not be blank"))
+            }
+            Some(Value::String(_)) | Some(Value::Number(_)) => {}
+            Some(_) => errors.push(FieldError::new(
+                required.field,
+                "must be a string or number",
+            )),
+        }
+    }
+    errors
+}
@@ -0,0 +1,129 @@
+//! Tests for the migration status reported by GET /admin/migrations and GET /admin/readyz
+//!
+//! Run with: cargo test --test admin_migrations_tests
+//!
+//! The real migrations are Postgres-only, so these run a one-migration test migrator
+//! against in-memory SQLite; `migration_status` is generic over the migrator.
+
+#[path = "../src/admin/migrations.rs"]
+mod migrations;
+#[path = "../src/admin/readiness.rs"]
+mod readiness;
+
+use migration::{async_trait, MigrationName, MigrationTrait, MigratorTrait, SchemaManager};
+use migrations::migration_status;
+use readiness::{database_readiness, ReadinessReport, RedisReadiness};
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, DbErr};
+
+struct CreateWidget;
+
+impl MigrationName for CreateWidget {
+    fn name(&self) -> &str {
+        "m20260101_000001_create_widget"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for CreateWidget {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("CREATE TABLE widget (id INTEGER PRIMARY KEY)")
+            .await
+            .map(|_| ())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute_unprepared("DROP TABLE widget")
+            .await
+            .map(|_| ())
+    }
+}
+
+struct TestMigrator;
+
+#[async_trait::async_trait]
+impl MigratorTrait for TestMigrator {
+    fn migrations() -> Vec<Box<dyn MigrationTrait>> {
+        vec![Box::new(CreateWidget)]
+    }
+}
+
+async fn setup_db() -> DatabaseConnection {
+    Database::connect("sqlite::memory:").await.unwrap()
+}
+
+#[cfg(test)]
+mod migration_status_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_fresh_database_reports_pending() {
+        let db = setup_db().await;
+        //get_pending_migrations creates seaql_migrations if it's missing
+        TestMigrator::install(&db).await.unwrap();
+
+        let status = migration_status::<TestMigrator>(&db).await.unwrap();
+        assert!(status.applied.is_empty());
+        assert_eq!(status.pending, vec!["m20260101_000001_create_widget".to_string()]);
+        assert!(!status.is_up_to_date());
+    }
+
+    #[tokio::test]
+    async fn test_nothing_pending_after_up() {
+        let db = setup_db().await;
+        TestMigrator::up(&db, None).await.unwrap();
+
+        let status = migration_status::<TestMigrator>(&db).await.unwrap();
+        assert_eq!(status.applied, vec!["m20260101_000001_create_widget".to_string()]);
+        assert!(status.pending.is_empty());
+        assert!(status.is_up_to_date());
+    }
+}
+
+#[cfg(test)]
+mod readiness_tests {
+    use super::*;
+
+    //Redis isn't available here; the report takes its result as given
+    async fn report(db: &DatabaseConnection) -> ReadinessReport {
+        let (db, migrations) = database_readiness::<TestMigrator>(db).await;
+        ReadinessReport {
+            db,
+            redis: RedisReadiness { connected: true },
+            migrations,
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_report_keys_and_no_pending_after_startup() {
+        let db = setup_db().await;
+        TestMigrator::up(&db, None).await.unwrap();
+
+        let report = report(&db).await;
+        assert!(report.is_ready());
+
+        let body = serde_json::to_value(&report).unwrap();
+        for key in ["connected", "pool_active", "pool_idle"] {
+            assert!(body["db"].get(key).is_some(), "db.{key}");
+        }
+        assert!(body["redis"].get("connected").is_some());
+        assert_eq!(body["db"]["connected"], true);
+        assert_eq!(body["migrations"]["applied_count"], 1);
+        assert_eq!(body["migrations"]["pending_count"], 0);
+        assert!(body["version"].is_string());
+    }
+
+    #[tokio::test]
+    async fn test_pending_migration_is_not_ready() {
+        let db = setup_db().await;
+        TestMigrator::install(&db).await.unwrap();
+
+        let report = report(&db).await;
+        assert_eq!(report.migrations.pending_count, Some(1));
+        assert!(!report.is_ready());
+    }
+}
@@ -0,0 +1,147 @@
+//! Tests for per-tenant CORS origins
+//!
+//! Run with: cargo test --test allowed_origin_tests
+//!
+//! Uses an in-memory SQLite `allowed_origin` table, so lookups go through the real
+//! normalization.
+
+#[path = "../src/security/allowed_origins.rs"]
+mod allowed_origins;
+
+use allowed_origins::{
+    normalize_origin, AllowedOriginError, AllowedOriginService, CreateAllowedOrigin,
+};
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, Schema};
+
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+const TENANT_ORIGIN: &str = "https://app.tenant-one.com";
+
+/// Helper to create an in-memory database with the `allowed_origin` table and the
+/// created_at/updated_at defaults Postgres provides
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //tenant rows are not needed by these tests
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let sql = schema
+        .create_table_from_entity(entity::allowed_origin::Entity)
+        .to_string(SqliteQueryBuilder)
+        .replace(
+            "\"created_at\" timestamp_with_timezone_text NOT NULL",
+            &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+        )
+        .replace(
+            "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+            &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+        );
+    db.execute_unprepared(&sql).await.unwrap();
+    db.execute_unprepared(
+        "CREATE UNIQUE INDEX allowed_origin_tenant_origin_idx ON allowed_origin (tenant_id, origin)",
+    )
+    .await
+    .unwrap();
+    db
+}
+
+fn new_origin(tenant_id: i64, origin: &str) -> CreateAllowedOrigin {
+    CreateAllowedOrigin {
+        tenant_id,
+        origin: origin.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod origin_check_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tenant_origin_allowed_unknown_rejected() {
+        let db = setup_db().await;
+        let service = AllowedOriginService::new(db);
+
+        //stored normalized, so the browser's lowercase Origin matches
+        let created = service
+            .create(new_origin(1, "https://App.Tenant-One.com/"), None)
+            .await
+            .unwrap();
+        assert_eq!(created.origin, TENANT_ORIGIN);
+
+        assert!(service.origin_allowed(1, TENANT_ORIGIN, None).await.unwrap());
+        assert!(!service.origin_allowed(1, "https://evil.example.com", None).await.unwrap());
+        //another tenant's origin is not allowed for this one
+        assert!(!service.origin_allowed(2, TENANT_ORIGIN, None).await.unwrap());
+
+        //preflights only know the origin
+        assert!(service.origin_registered(TENANT_ORIGIN, None).await.unwrap());
+        assert!(!service.origin_registered("https://evil.example.com", None).await.unwrap());
+        assert!(!service.origin_registered("null", None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_origin_conflicts_per_tenant() {
+        let db = setup_db().await;
+        let service = AllowedOriginService::new(db);
+
+        service.create(new_origin(1, TENANT_ORIGIN), None).await.unwrap();
+        let duplicate = service
+            .create(new_origin(1, "HTTPS://app.tenant-one.com"), None)
+            .await;
+        assert!(matches!(duplicate, Err(AllowedOriginError::Conflict)));
+
+        //the same origin may be registered by another tenant
+        service.create(new_origin(2, TENANT_ORIGIN), None).await.unwrap();
+        assert_eq!(service.get_all(None, None).await.unwrap().len(), 2);
+        assert_eq!(service.get_all(Some(2), None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete() {
+        let db = setup_db().await;
+        let service = AllowedOriginService::new(db);
+
+        let created = service.create(new_origin(1, TENANT_ORIGIN), None).await.unwrap();
+        let updated = service
+            .update_by_uuid(created.uuid, "https://admin.tenant-one.com", None)
+            .await
+            .unwrap();
+        assert_eq!(updated.tenant_id, 1);
+        assert!(!service.origin_allowed(1, TENANT_ORIGIN, None).await.unwrap());
+        assert!(service.origin_allowed(1, "https://admin.tenant-one.com", None).await.unwrap());
+
+        service.delete_by_uuid(created.uuid, None).await.unwrap();
+        assert!(!service.origin_registered("https://admin.tenant-one.com", None).await.unwrap());
+        assert!(matches!(
+            service.delete_by_uuid(created.uuid, None).await,
+            Err(AllowedOriginError::NotFound)
+        ));
+    }
+}
+
+#[cfg(test)]
+mod normalize_origin_tests {
+    use super::*;
+
+    #[test]
+    fn test_normalizes_case_and_trailing_slash() {
+        assert_eq!(normalize_origin(" https://App.Example.com/ ").unwrap(), "https://app.example.com");
+        assert_eq!(normalize_origin("http://localhost:3000").unwrap(), "http://localhost:3000");
+    }
+
+    #[test]
+    fn test_rejects_non_origins() {
+        for origin in [
+            "app.example.com",
+            "ftp://app.example.com",
+            "https://",
+            "https://app.example.com/path",
+            "https://app.example.com?x=1",
+            "https://user@app.example.com",
+            "https://*.example.com",
+            "*",
+        ] {
+            assert!(normalize_origin(origin).is_err(), "{origin}");
+        }
+    }
+}
@@ -0,0 +1,317 @@
+//! Tests for API token and connection API key issuing/revocation and the admin token check
+//!
+//! Run with: cargo test --test api_token_tests
+//!
+//! Uses in-memory SQLite `api_token` / `connection_api_key` tables, so lookups go through the
+//! real hashing.
+
+#[path = "../src/middleware/admin_routes.rs"]
+mod admin_routes;
+#[path = "../src/security/admin_token.rs"]
+mod admin_token;
+#[path = "../src/security/api_token.rs"]
+mod api_token;
+#[path = "../src/security/connection_api_key.rs"]
+mod connection_api_key;
+
+use admin_routes::is_admin_token_route;
+use admin_token::admin_token_matches;
+use api_token::{hash_token, ApiTokenService, CreateApiToken};
+use connection_api_key::{
+    is_connection_key, ConnectionApiKeyError, ConnectionApiKeyService, CreateConnectionApiKey,
+};
+use entity::connection_identity;
+use entity::sea_orm_active_enums::{
+    ApiTokenStatusEnum, ErpConnectionAuthStatus, ErpConnectionStatus, ErpEnvironment,
+    ErpProvider, ErpProviderAuthType, ErpProviderType,
+};
+use sea_orm::sea_query::{SqliteQueryBuilder, Table};
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, EntityTrait, Schema, Set};
+
+const NEW_UUID: &str = "(randomblob(16))";
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+/// Helper to create an in-memory database with the `api_token`, `connection_api_key` and
+/// `connection_identity` tables and the uuid/created_at/updated_at defaults Postgres provides
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //tenant rows are not needed by these tests
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let tables = [
+        schema.create_table_from_entity(entity::api_token::Entity),
+        schema.create_table_from_entity(entity::connection_api_key::Entity),
+    ];
+    for table in tables {
+        let sql = table
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+    }
+    //SQLite has no arrays; `scopes` is never written or read here, so a text column will do
+    let mut connections = Table::create();
+    connections.table(connection_identity::Entity);
+    for column in schema.create_table_from_entity(connection_identity::Entity).get_columns() {
+        let mut column = column.clone();
+        if column.get_column_name() == "scopes" {
+            column.text();
+        }
+        connections.col(column);
+    }
+    db.execute(&connections).await.unwrap();
+    db
+}
+
+fn new_token() -> CreateApiToken {
+    CreateApiToken {
+        tenant_id: 1,
+        display_name: Some("ci".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod api_token_service_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_returns_raw_token_once() {
+        let db = setup_db().await;
+        let service = ApiTokenService::new(db.clone());
+
+        let issued = service.create(new_token(), None).await.unwrap();
+        assert_eq!(issued.model.tenant_id, Some(1));
+        assert_eq!(issued.model.status, ApiTokenStatusEnum::Active);
+        assert_eq!(issued.model.token_prefix.as_deref(), Some(&issued.token[..8]));
+
+        //only the hash is stored; nothing read back contains the raw token
+        let stored = entity::api_token::Entity::find().all(&db).await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].token_hash, hash_token(&issued.token));
+        assert_ne!(stored[0].token_hash, issued.token);
+        let listed = service.get_all(Some(1), None).await.unwrap();
+        assert!(!format!("{:?}", listed).contains(&issued.token));
+
+        assert!(service.is_token_valid(&issued.token, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_token_is_rejected() {
+        let db = setup_db().await;
+        let service = ApiTokenService::new(db);
+
+        let issued = service.create(new_token(), None).await.unwrap();
+        assert!(service.is_token_valid(&issued.token, None).await.unwrap());
+
+        let revoked = service
+            .revoke_by_uuid(issued.model.uuid, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(revoked.status, ApiTokenStatusEnum::Inactive);
+        assert!(!service.is_token_valid(&issued.token, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_rotate_replaces_secret() {
+        let db = setup_db().await;
+        let service = ApiTokenService::new(db);
+
+        let issued = service.create(new_token(), None).await.unwrap();
+        let rotated = service.rotate_by_uuid(issued.model.uuid, None).await.unwrap();
+
+        assert_eq!(rotated.model.uuid, issued.model.uuid);
+        assert_ne!(rotated.token, issued.token);
+        assert!(!service.is_token_valid(&issued.token, None).await.unwrap());
+        assert!(service.is_token_valid(&rotated.token, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_list_filters_by_tenant() {
+        let db = setup_db().await;
+        let service = ApiTokenService::new(db);
+
+        service.create(new_token(), None).await.unwrap();
+        service
+            .create(
+                CreateApiToken {
+                    tenant_id: 2,
+                    display_name: None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(service.get_all(None, None).await.unwrap().len(), 2);
+        let tenant_two = service.get_all(Some(2), None).await.unwrap();
+        assert_eq!(tenant_two.len(), 1);
+        assert_eq!(tenant_two[0].tenant_id, Some(2));
+    }
+}
+
+#[cfg(test)]
+mod admin_token_tests {
+    use super::*;
+
+    #[test]
+    fn test_admin_token_must_match() {
+        assert!(admin_token_matches(Some("s3cret"), Some("s3cret")));
+        assert!(!admin_token_matches(Some("s3cret"), Some("s3creT")));
+        assert!(!admin_token_matches(Some("s3cret"), Some("s3cret-longer")));
+        assert!(!admin_token_matches(Some("s3cret"), None));
+    }
+
+    #[test]
+    fn test_unset_admin_token_rejects_everything() {
+        assert!(!admin_token_matches(None, Some("anything")));
+        assert!(!admin_token_matches(Some(""), Some("")));
+    }
+}
+
+//API token auth skips admin token routes, leaving X-Admin-Token as their only credential
+#[cfg(test)]
+mod admin_token_route_tests {
+    use super::*;
+
+    #[test]
+    fn test_expiring_credentials_route_needs_only_the_admin_token() {
+        assert!(is_admin_token_route("/admin/credentials/expiring", None));
+        assert!(is_admin_token_route("/api/admin/credentials/expiring", Some("/api")));
+    }
+
+    #[test]
+    fn test_sync_event_actions_need_only_the_admin_token() {
+        let event = "5b0c3a1e-2f4d-4a8e-9c6b-1d2e3f4a5b6c";
+        assert!(is_admin_token_route(&format!("/admin/sync-events/{event}/retry"), None));
+        assert!(is_admin_token_route(&format!("/api/admin/sync-events/{event}/cancel"), Some("/api")));
+    }
+
+    #[test]
+    fn test_operator_routes_need_only_the_admin_token() {
+        for path in [
+            "/admin/api-tokens",
+            "/admin/api-tokens/5b0c3a1e-2f4d-4a8e-9c6b-1d2e3f4a5b6c/rotate",
+            "/admin/migrations",
+            "/admin/log-level",
+            "/admin/maintenance",
+            "/admin/audit-log",
+            "/connections/5b0c3a1e-2f4d-4a8e-9c6b-1d2e3f4a5b6c/purge",
+        ] {
+            assert!(is_admin_token_route(path, None), "{path}");
+        }
+    }
+
+    #[test]
+    fn test_other_routes_need_an_api_token() {
+        for path in [
+            "/tenant",
+            "/admin/credentials-export",
+            "/connections/5b0c3a1e-2f4d-4a8e-9c6b-1d2e3f4a5b6c/credentials",
+            "/connections/5b0c3a1e-2f4d-4a8e-9c6b-1d2e3f4a5b6c/api-keys",
+        ] {
+            assert!(!is_admin_token_route(path, None), "{path}");
+        }
+    }
+}
+
+/// Helper to insert a QBD connection of tenant 7 and return its uuid; `scopes` is left unset
+async fn insert_connection(db: &DatabaseConnection, id: i64) -> uuid::Uuid {
+    let uuid = uuid::Uuid::new_v4();
+    let now = chrono::Utc::now();
+    let active = connection_identity::ActiveModel {
+        id: Set(id),
+        uuid: Set(uuid),
+        tenant_id: Set(7),
+        erp_provider: Set(ErpProvider::Quickbooks),
+        erp_type: Set(ErpProviderType::Desktop),
+        erp_auth_type: Set(ErpProviderAuthType::UsernamePassword),
+        environment: Set(ErpEnvironment::Production),
+        status: Set(ErpConnectionStatus::Active),
+        auth_status: Set(ErpConnectionAuthStatus::Connected),
+        is_enabled: Set(true),
+        sync_enabled_push: Set(true),
+        sync_enabled_pull: Set(true),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    };
+    connection_identity::Entity::insert(active)
+        .exec_without_returning(db)
+        .await
+        .unwrap();
+    uuid
+}
+
+fn new_key(connection_id: i64) -> CreateConnectionApiKey {
+    CreateConnectionApiKey {
+        connection_id,
+        display_name: Some("qbwc adapter".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod connection_api_key_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_key_authenticates_only_its_own_connection() {
+        let db = setup_db().await;
+        let a = insert_connection(&db, 1).await;
+        let b = insert_connection(&db, 2).await;
+        let service = ConnectionApiKeyService::new(db.clone());
+
+        let issued = service.create(new_key(1), None).await.unwrap();
+        assert!(is_connection_key(&issued.key));
+        assert_eq!(issued.model.key_prefix.as_deref(), Some(&issued.key[..11]));
+        //only the hash is stored, and it is not an API token
+        let stored = entity::connection_api_key::Entity::find().all(&db).await.unwrap();
+        assert_eq!(stored[0].key_hash, hash_token(&issued.key));
+        let tokens = ApiTokenService::new(db.clone());
+        assert!(!tokens.is_token_valid(&issued.key, None).await.unwrap());
+
+        let authenticated = service.authenticate(&issued.key, None).await.unwrap().unwrap();
+        assert_eq!(authenticated.connection_id, 1);
+        assert_eq!(authenticated.connection_uuid, a);
+        assert_ne!(authenticated.connection_uuid, b);
+        assert_eq!(authenticated.tenant_id, 7);
+        assert_eq!(authenticated.key_id, issued.model.id);
+
+        assert!(service.authenticate("ck_unknown", None).await.unwrap().is_none());
+        assert!(service.get_all(2, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_revoked_key_is_rejected_but_still_listed() {
+        let db = setup_db().await;
+        insert_connection(&db, 1).await;
+        insert_connection(&db, 2).await;
+        let service = ConnectionApiKeyService::new(db);
+        let issued = service.create(new_key(1), None).await.unwrap();
+
+        //a key is only revoked through its own connection
+        let other = service.revoke(2, issued.model.uuid, None).await;
+        assert!(matches!(other, Err(ConnectionApiKeyError::NotFound)));
+        assert!(service.authenticate(&issued.key, None).await.unwrap().is_some());
+
+        let revoked = service.revoke(1, issued.model.uuid, None).await.unwrap();
+        assert!(revoked.revoked_at.is_some());
+        assert!(service.authenticate(&issued.key, None).await.unwrap().is_none());
+
+        let again = service.revoke(1, issued.model.uuid, None).await.unwrap();
+        assert_eq!(again.revoked_at, revoked.revoked_at);
+        let listed = service.get_all(1, None).await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(!format!("{:?}", listed).contains(&issued.key));
+    }
+}
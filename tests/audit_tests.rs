@@ -0,0 +1,273 @@
+//! Tests for the audit log of mutating operations
+//!
+//! Run with: cargo test --test audit_tests
+//!
+//! Runs the tenant and credentials services against an in-memory SQLite database, with the
+//! uuid/created_at/updated_at defaults Postgres provides.
+
+#[path = "../src/audit/services.rs"]
+mod audit_services;
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/connection_identity/services.rs"]
+mod connection_identity_services;
+#[path = "../src/erp_connection_credentials/codec.rs"]
+mod credential_codec;
+#[path = "../src/erp_connection_credentials/lookup_cache.rs"]
+mod credential_lookup_cache;
+#[path = "../src/erp_connection_credentials/services.rs"]
+mod erp_connection_credentials_services;
+#[path = "../src/tenant/cache.rs"]
+mod cache;
+#[path = "../src/tenant/services.rs"]
+mod tenant_services;
+#[path = "../src/outbox/services.rs"]
+mod outbox_services;
+#[path = "../src/sync/window.rs"]
+mod sync_window;
+
+//the services record audit rows through `crate::audit::services`, encode secrets through
+//`crate::erp_connection_credentials::codec` and mark connections through
+//`crate::connection_identity::services`
+mod audit {
+    pub(crate) use super::audit_services as services;
+}
+mod connection_identity {
+    pub(crate) use super::connection_identity_services as services;
+}
+mod erp_connection_credentials {
+    pub(crate) use super::credential_codec as codec;
+    pub(crate) use super::credential_lookup_cache as lookup_cache;
+}
+mod outbox {
+    pub(crate) use super::outbox_services as services;
+}
+mod sync {
+    pub(crate) use super::sync_window as window;
+}
+
+use audit_services::{AuditContext, AuditService, REDACTED};
+use erp_connection_credentials_services::{
+    CreateErpConnectionCredentials, ErpConnectionCredentialsService,
+    UpdateErpConnectionCredentials,
+};
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, EntityTrait, Schema};
+use serde_json::json;
+use tenant_services::{CreateTenant, TenantService, UpdateTenant};
+
+const NEW_UUID: &str = "(randomblob(16))";
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+/// Helper to create an in-memory database with the audited tables and `audit_log`
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let tables = [
+        schema.create_table_from_entity(entity::tenant::Entity),
+        schema.create_table_from_entity(entity::erp_connection_credentials::Entity),
+        schema.create_table_from_entity(entity::audit_log::Entity),
+    ];
+    for table in tables {
+        let sql = table
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+    }
+    db
+}
+
+fn context() -> AuditContext {
+    AuditContext {
+        actor_token_id: Some(7),
+        request_id: Some("req-123".to_string()),
+    }
+}
+
+/// Helper to build Web Connector credentials for connection 1
+fn qbwc_credentials(password: &str) -> CreateErpConnectionCredentials {
+    CreateErpConnectionCredentials {
+        connection_id: 1,
+        erp_auth_type: None,
+        client_id: None,
+        issuer_base_url: None,
+        token_type: None,
+        reauth_required_reason: None,
+        reauth_url: None,
+        enc_scheme: Some("none".to_string()),
+        enc_key_id: "none".to_string(),
+        enc_version: None,
+        enc_iv: None,
+        enc_tag: None,
+        access_token: None,
+        refresh_token: None,
+        access_token_expires_at: None,
+        refresh_token_expires_at: None,
+        id_token_enc: None,
+        provider_user_id: Some("qbwc-user".to_string()),
+        provider_password: Some(password.to_string()),
+        client_cert: None,
+        private_key: None,
+        cert_expires_at: None,
+        session_token: None,
+        session_expires_at: None,
+        api_access_token: None,
+        api_access_token_key: None,
+    }
+}
+
+/// Helper to build a credentials patch that only sets `provider_password`
+fn password_patch(password: &str) -> UpdateErpConnectionCredentials {
+    UpdateErpConnectionCredentials {
+        client_id: None,
+        issuer_base_url: None,
+        token_type: None,
+        reauth_required_reason: None,
+        reauth_url: None,
+        enc_scheme: None,
+        enc_key_id: None,
+        enc_version: None,
+        enc_iv: None,
+        enc_tag: None,
+        access_token: None,
+        refresh_token: None,
+        access_token_expires_at: None,
+        refresh_token_expires_at: None,
+        id_token_enc: None,
+        provider_user_id: None,
+        provider_password: Some(password.to_string()),
+        client_cert: None,
+        private_key: None,
+        cert_expires_at: None,
+        session_token: None,
+        session_expires_at: None,
+        api_access_token: None,
+        api_access_token_key: None,
+    }
+}
+
+async fn audit_rows(db: &DatabaseConnection) -> Vec<entity::audit_log::Model> {
+    AuditService::new(db.clone())
+        .get_all(1, 50, None, None)
+        .await
+        .unwrap()
+        .items
+}
+
+#[cfg(test)]
+mod tenant_audit_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tenant_update_writes_audit_row_with_diff() {
+        let db = setup_db().await;
+        let tenant = TenantService::new(db.clone())
+            .create(CreateTenant { display_name: Some("Acme".to_string()) }, None)
+            .await
+            .unwrap();
+
+        TenantService::new(db.clone())
+            .with_audit(context())
+            .update_by_tenant_id(
+                &tenant.tenant_id,
+                UpdateTenant {
+                    display_name: Some("Acme Corp".to_string()),
+                    status: None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let rows = audit_rows(&db).await;
+        assert_eq!(rows.len(), 1);
+        let row = &rows[0];
+        assert_eq!(row.action, "update");
+        assert_eq!(row.entity_type, "tenant");
+        assert_eq!(row.entity_uuid, tenant.uuid);
+        assert_eq!(row.actor_token_id, Some(7));
+        assert_eq!(row.request_id.as_deref(), Some("req-123"));
+        //only the changed field; updated_at always changes and is left out
+        assert_eq!(row.before, Some(json!({ "display_name": "Acme" })));
+        assert_eq!(row.after, Some(json!({ "display_name": "Acme Corp" })));
+    }
+
+    #[tokio::test]
+    async fn test_create_and_soft_delete_are_recorded() {
+        let db = setup_db().await;
+        let service = TenantService::new(db.clone()).with_audit(context());
+
+        let tenant = service
+            .create(CreateTenant { display_name: Some("Acme".to_string()) }, None)
+            .await
+            .unwrap();
+        service.delete_by_uuid(tenant.uuid, None).await.unwrap();
+
+        let mut rows = audit_rows(&db).await;
+        rows.sort_by_key(|r| r.id);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].action, "create");
+        assert!(rows[0].before.is_none());
+        let after = rows[0].after.as_ref().unwrap();
+        assert_eq!(after["tenant_id"], json!(tenant.tenant_id));
+        assert_eq!(rows[1].action, "delete");
+        assert_eq!(rows[1].before, Some(json!({ "status": "active" })));
+        assert_eq!(rows[1].after, Some(json!({ "status": "removed" })));
+    }
+
+    #[tokio::test]
+    async fn test_no_audit_row_without_context() {
+        let db = setup_db().await;
+
+        TenantService::new(db.clone())
+            .create(CreateTenant { display_name: None }, None)
+            .await
+            .unwrap();
+
+        assert!(audit_rows(&db).await.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod credentials_audit_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_secrets_are_redacted() {
+        let db = setup_db().await;
+        let service = ErpConnectionCredentialsService::new(db.clone()).with_audit(context());
+
+        let creds = service.create(qbwc_credentials("hunter2"), None).await.unwrap();
+        service
+            .update_by_uuid(creds.uuid, password_patch("correct-horse"), None)
+            .await
+            .unwrap();
+
+        let mut rows = audit_rows(&db).await;
+        rows.sort_by_key(|r| r.id);
+        let created = rows[0].after.as_ref().unwrap();
+        assert_eq!(created["provider_password"], REDACTED);
+        assert_eq!(created["provider_user_id"], "qbwc-user");
+        assert!(created["access_token"].is_null());
+        //a changed secret still shows up, without its value
+        assert_eq!(rows[1].before, Some(json!({ "provider_password": REDACTED })));
+        assert_eq!(rows[1].after, Some(json!({ "provider_password": REDACTED })));
+        let stored = format!("{:?}", entity::audit_log::Entity::find().all(&db).await.unwrap());
+        assert!(!stored.contains("hunter2"));
+        assert!(!stored.contains("correct-horse"));
+    }
+}
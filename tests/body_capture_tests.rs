@@ -0,0 +1,109 @@
+//! Tests for request body capture in the auth middlewares' rejection logs
+//!
+//! Run with: cargo test --test body_capture_tests
+
+#[path = "../src/middleware/body_capture.rs"]
+mod body_capture;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use axum::body::{Body, Bytes};
+use futures_util::StreamExt;
+use body_capture::{
+    capture_body, redact_secrets, BODY_NOT_CAPTURED, MAX_CAPTURED_BODY_BYTES, TRUNCATED_MARKER,
+};
+
+/// Helper to build a streaming body that records whether it was ever polled
+fn tracked_body(chunks: Vec<&'static str>) -> (Body, Arc<AtomicBool>) {
+    let polled = Arc::new(AtomicBool::new(false));
+    let flag = polled.clone();
+    let stream = futures_util::stream::iter(chunks).map(move |chunk| {
+        flag.store(true, Ordering::SeqCst);
+        Ok::<_, std::io::Error>(Bytes::from_static(chunk.as_bytes()))
+    });
+    (Body::from_stream(stream), polled)
+}
+
+#[cfg(test)]
+mod capture_body_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_disabled_capture_does_not_read_body() {
+        let (body, polled) = tracked_body(vec![r#"{"name":"acme"}"#]);
+
+        assert_eq!(capture_body(body, false).await, BODY_NOT_CAPTURED);
+        assert!(!polled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_enabled_capture_keeps_small_body() {
+        let (body, polled) = tracked_body(vec![r#"{"name":"#, r#""acme"}"#]);
+
+        assert_eq!(capture_body(body, true).await, r#"{"name":"acme"}"#);
+        assert!(polled.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_enabled_capture_truncates_large_body() {
+        let large = "a".repeat(MAX_CAPTURED_BODY_BYTES * 3);
+        let captured = capture_body(Body::from(large), true).await;
+
+        assert_eq!(captured.len(), MAX_CAPTURED_BODY_BYTES + TRUNCATED_MARKER.len());
+        assert!(captured.ends_with(TRUNCATED_MARKER));
+
+        //a body of exactly the cap is kept whole
+        let exact = "b".repeat(MAX_CAPTURED_BODY_BYTES);
+        assert_eq!(capture_body(Body::from(exact.clone()), true).await, exact);
+    }
+
+    #[tokio::test]
+    async fn test_enabled_capture_redacts_secrets() {
+        let body = Body::from(r#"{"user":"acme","password":"hunter2"}"#);
+
+        assert_eq!(
+            capture_body(body, true).await,
+            r#"{"user":"acme","password":"[REDACTED]"}"#
+        );
+    }
+}
+
+#[cfg(test)]
+mod redact_secrets_tests {
+    use super::*;
+
+    #[test]
+    fn test_redacts_secret_values() {
+        let body = r#"{"client_secret": "abc", "Refresh_Token":"def", "api_key": 12345, "pin": 42}"#;
+        assert_eq!(
+            redact_secrets(body),
+            r#"{"client_secret": "[REDACTED]", "Refresh_Token":"[REDACTED]", "api_key": "[REDACTED]", "pin": 42}"#
+        );
+    }
+
+    #[test]
+    fn test_redacts_nested_and_escaped_values() {
+        let body = r#"{"auth":{"password":"a\"b"},"tokens":["x"],"note":"password"}"#;
+        assert_eq!(
+            redact_secrets(body),
+            r#"{"auth":{"password":"[REDACTED]"},"tokens":["x"],"note":"password"}"#
+        );
+    }
+
+    #[test]
+    fn test_redacts_truncated_json() {
+        assert_eq!(
+            redact_secrets(r#"{"user":"acme","password":"hunt"#),
+            r#"{"user":"acme","password":"[REDACTED]""#
+        );
+        assert_eq!(redact_secrets(r#"{"password""#), r#"{"password""#);
+    }
+
+    #[test]
+    fn test_leaves_other_text_unchanged() {
+        assert_eq!(redact_secrets("password=hunter2"), "password=hunter2");
+        assert_eq!(redact_secrets(""), "");
+        assert_eq!(redact_secrets(r#"{"name":"café"}"#), r#"{"name":"café"}"#);
+    }
+}
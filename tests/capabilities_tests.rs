@@ -0,0 +1,53 @@
+//! Tests for the deployment capabilities
+//!
+//! Run with: cargo test --test capabilities_tests
+
+#[path = "../src/capabilities/services.rs"]
+mod services;
+
+use serde_json::json;
+use services::{capabilities, FeatureFlags};
+
+#[cfg(test)]
+mod capabilities_tests {
+    use super::*;
+
+    #[test]
+    fn test_quickbooks_desktop_inventory_list_is_listed() {
+        let body = serde_json::to_value(capabilities(FeatureFlags::default())).unwrap();
+
+        let desktop = body["providers"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|p| p["erp_provider"] == "quickbooks" && p["erp_type"] == "desktop")
+            .expect("QuickBooks Desktop is listed");
+        assert!(desktop["syncs"].as_array().unwrap().contains(&json!({
+            "category": "inventory",
+            "method": "list",
+            "direction": "pull_from_external",
+        })));
+        assert!(body["erp_types"].as_array().unwrap().contains(&json!("desktop")));
+    }
+
+    #[test]
+    fn test_feature_flags_come_from_config() {
+        let body = serde_json::to_value(capabilities(FeatureFlags {
+            encryption_required: true,
+            scheduler_enabled: true,
+            webhooks_enabled: false,
+            outbox_relay_enabled: false,
+        }))
+        .unwrap();
+
+        assert_eq!(
+            body["features"],
+            json!({
+                "encryption_required": true,
+                "scheduler_enabled": true,
+                "webhooks_enabled": false,
+                "outbox_relay_enabled": false,
+            })
+        );
+    }
+}
@@ -0,0 +1,1338 @@
+//! Tests for connection identity uniqueness, search, bulk enable/disable, purge, the
+//! timeline and batch status
+//!
+//! Run with: cargo test --test connection_identity_tests
+//!
+//! Uses the sea-orm mock database (no Postgres required); bulk enable/disable, purge, the
+//! timeline and batch status run against an in-memory SQLite database.
+
+#[path = "../src/audit/services.rs"]
+mod audit_services;
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/connection_identity/last_error.rs"]
+mod last_error;
+#[path = "../src/connection_identity/services.rs"]
+mod services;
+#[path = "../src/connection_identity/status.rs"]
+mod status;
+#[path = "../src/connection_identity/timeline.rs"]
+mod timeline;
+#[path = "../src/outbox/services.rs"]
+mod outbox_services;
+#[path = "../src/sync/window.rs"]
+mod sync_window;
+#[path = "../src/tenant/default.rs"]
+mod default_tenant;
+
+//the services record audit rows through `crate::audit::services`, enqueue messages through
+//`crate::outbox::services` and read sync windows through `crate::sync::window`
+mod audit {
+    pub(crate) use super::audit_services as services;
+}
+mod outbox {
+    pub(crate) use super::outbox_services as services;
+}
+mod sync {
+    pub(crate) use super::sync_window as window;
+}
+
+use entity::{
+    connection_identity, connection_run, erp_connection_credentials, erp_connection_sync_state,
+    inventory_record, inventory_record_event, sync_event,
+};
+use entity::sea_orm_active_enums::{
+    ConnectionRunStatus, ConnectionRunType, ErpConnectionAuthStatus, ErpConnectionAuthTokenType,
+    ErpConnectionStatus, ErpEnvironment, ErpProvider, ErpProviderAuthType, ErpProviderType,
+    SyncEventCategory, SyncEventDirection, SyncEventMethod, SyncEventStatus, SystemIdKey,
+};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection,
+    DbErr, EntityTrait, IntoActiveModel, MockDatabase, MockExecResult, NotSet, QueryFilter,
+    QueryOrder, QuerySelect, RuntimeErr, Schema, Set, Value,
+};
+use sea_orm::sea_query::Table;
+use std::collections::BTreeMap;
+use services::{
+    ConnectionIdentityError, ConnectionIdentityService, CreateConnectionIdentity,
+    UpdateConnectionIdentity,
+};
+
+const COMPANY_FILE_ID: &str = "0f1c2d3e-4b5a-6978-8a9b-0c1d2e3f4a5b";
+
+/// Helper to build an active QBD connection row
+fn connection_model(id: i64) -> connection_identity::Model {
+    let now = chrono::Utc::now().into();
+    connection_identity::Model {
+        id,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider: ErpProvider::Quickbooks,
+        erp_type: ErpProviderType::Desktop,
+        erp_auth_type: ErpProviderAuthType::UsernamePassword,
+        display_name: None,
+        environment: ErpEnvironment::Production,
+        status: ErpConnectionStatus::Active,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: now,
+        updated_at: now,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: Some(COMPANY_FILE_ID.to_string()),
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+}
+
+/// Helper to build a create request for the same QBD company file
+fn create_request() -> CreateConnectionIdentity {
+    CreateConnectionIdentity {
+        tenant_id: 1,
+        erp_provider: ErpProvider::Quickbooks,
+        erp_type: ErpProviderType::Desktop,
+        erp_auth_type: ErpProviderAuthType::UsernamePassword,
+        display_name: None,
+        environment: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: Some(COMPANY_FILE_ID.to_string()),
+        system_version: None,
+        web_connector_app_name: None,
+        secret_storage_ref: None,
+        secret_version: None,
+        sync_enabled_push: None,
+        sync_enabled_pull: None,
+        sync_window: None,
+    }
+}
+
+#[cfg(test)]
+mod uniqueness_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_create_duplicate_returns_conflict() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            //find_conflicting
+            .append_query_results([vec![connection_model(1)]])
+            .into_connection();
+        let service = ConnectionIdentityService::new(db.clone());
+
+        let result = service.create(create_request(), None).await;
+
+        assert!(matches!(result, Err(ConnectionIdentityError::Conflict(_))));
+        //no INSERT after the pre-check found a match
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_create_without_existing_inserts() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            //find_conflicting
+            .append_query_results([Vec::<connection_identity::Model>::new()])
+            //INSERT ... RETURNING
+            .append_query_results([vec![connection_model(2)]])
+            .into_connection();
+        let service = ConnectionIdentityService::new(db);
+
+        let created = service.create(create_request(), None).await.unwrap();
+
+        assert_eq!(created.id, 2);
+    }
+
+    #[tokio::test]
+    async fn test_create_without_identity_skips_pre_check() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![connection_model(3)]])
+            .into_connection();
+        let service = ConnectionIdentityService::new(db.clone());
+
+        let mut request = create_request();
+        request.company_file_id = None;
+        let created = service.create(request, None).await.unwrap();
+
+        assert_eq!(created.id, 3);
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+
+    #[test]
+    fn test_other_db_errors_are_not_conflicts() {
+        let err = DbErr::Query(RuntimeErr::Internal("connection reset".to_string()));
+
+        assert!(matches!(
+            ConnectionIdentityError::from(err),
+            ConnectionIdentityError::Db(_)
+        ));
+    }
+}
+
+/// Helper to build a mock answering one search page: the count, then the rows
+fn search_db(rows: Vec<connection_identity::Model>) -> sea_orm::DatabaseConnection {
+    MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[BTreeMap::from([(
+            "num_items".to_string(),
+            Value::from(rows.len() as i64),
+        )])]])
+        .append_query_results([rows])
+        .into_connection()
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_search_by_username_finds_connection() {
+        let db = search_db(vec![connection_model(7)]);
+        let service = ConnectionIdentityService::new(db.clone());
+
+        let result = service.search(" QBWC-User ", 1, 1, 20, None).await.unwrap();
+
+        assert_eq!(result.total, 1);
+        assert_eq!(result.items.len(), 1);
+        assert_eq!(result.items[0].id, 7);
+
+        //the username is matched on the joined credentials, within the tenant only
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains(r#"LEFT JOIN \"erp_connection_credentials\""#));
+        assert!(log.contains(r#"\"erp_connection_credentials\".\"provider_user_id\" ILIKE"#));
+        assert!(log.contains(r#"\"connection_identity\".\"company_file_path\" ILIKE"#));
+        assert!(log.contains(r#"\"connection_identity\".\"tenant_id\" = "#));
+        assert!(log.contains("%QBWC-User%"));
+    }
+
+    #[tokio::test]
+    async fn test_search_matches_wildcards_literally() {
+        let db = search_db(vec![]);
+        let service = ConnectionIdentityService::new(db.clone());
+
+        let result = service.search("50%_off", 1, 1, 20, None).await.unwrap();
+
+        assert_eq!(result.total, 0);
+        let log = format!("{:?}", db.into_transaction_log());
+        //Debug doubles backslashes: the bound pattern is %50\%\_off%
+        assert!(log.contains(r#"%50\\%\\_off%"#));
+    }
+}
+
+/// Helper to create an in-memory database with the connection and sync state tables
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    //SQLite has no arrays; `scopes` is never written or read here, so a text column will do
+    let mut connections = Table::create();
+    connections.table(connection_identity::Entity);
+    for column in schema.create_table_from_entity(connection_identity::Entity).get_columns() {
+        let mut column = column.clone();
+        if column.get_column_name() == "scopes" {
+            column.text();
+        }
+        connections.col(column);
+    }
+    db.execute(&connections).await.unwrap();
+    db.execute(&schema.create_table_from_entity(erp_connection_sync_state::Entity))
+        .await
+        .unwrap();
+    db
+}
+
+/// Helper to insert a connection for `tenant_id` whose sync state holds the sync lock
+async fn insert_locked_connection(db: &DatabaseConnection, id: i64, tenant_id: i64) {
+    let mut model = connection_model(id);
+    model.tenant_id = tenant_id;
+    model.company_file_id = Some(format!("company-file-{id}"));
+    let mut active = model.into_active_model().reset_all();
+    active.scopes = NotSet;
+    connection_identity::Entity::insert(active)
+        .exec_without_returning(db)
+        .await
+        .unwrap();
+
+    let now = chrono::Utc::now();
+    erp_connection_sync_state::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        connection_id: Set(id),
+        sync_lock_owner: Set(Some(uuid::Uuid::new_v4().to_string())),
+        sync_lock_until: Set(Some((now + chrono::Duration::minutes(5)).into())),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+/// Helper to build a patch that changes nothing
+fn empty_patch() -> UpdateConnectionIdentity {
+    UpdateConnectionIdentity {
+        display_name: None,
+        environment: None,
+        status: None,
+        auth_status: None,
+        is_enabled: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: None,
+        system_version: None,
+        web_connector_app_name: None,
+        secret_storage_ref: None,
+        secret_version: None,
+        sync_enabled_push: None,
+        sync_enabled_pull: None,
+        last_error_code: None,
+        last_error_message: None,
+        sync_window: None,
+        field_mapping: None,
+    }
+}
+
+/// Helper to read `(id, is_enabled)` of every connection, by id
+async fn enabled_flags(db: &DatabaseConnection) -> Vec<(i64, bool)> {
+    connection_identity::Entity::find()
+        .select_only()
+        .columns([connection_identity::Column::Id, connection_identity::Column::IsEnabled])
+        .order_by_asc(connection_identity::Column::Id)
+        .into_tuple()
+        .all(db)
+        .await
+        .unwrap()
+}
+
+#[cfg(test)]
+mod bulk_enable_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_set_enabled_by_tenant_flips_every_connection() {
+        let db = setup_db().await;
+        for id in 1..=3 {
+            insert_locked_connection(&db, id, 1).await;
+        }
+        insert_locked_connection(&db, 4, 2).await;
+        let service = ConnectionIdentityService::new(db.clone());
+
+        let updated = service.set_enabled_by_tenant(1, false, None).await.unwrap();
+
+        assert_eq!(updated, 3);
+        assert_eq!(
+            enabled_flags(&db).await,
+            vec![(1, false), (2, false), (3, false), (4, true)]
+        );
+
+        //disabling released the tenant's locks, and only theirs
+        let states = erp_connection_sync_state::Entity::find().all(&db).await.unwrap();
+        for state in states {
+            let released = state.sync_lock_owner.is_none() && state.sync_lock_until.is_none();
+            assert_eq!(released, state.connection_id != 4);
+        }
+
+        let updated = service.set_enabled_by_tenant(1, true, None).await.unwrap();
+
+        assert_eq!(updated, 3);
+        assert!(enabled_flags(&db).await.iter().all(|(_, enabled)| *enabled));
+    }
+
+    #[tokio::test]
+    async fn test_set_enabled_by_tenant_audits_each_changed_connection() {
+        //`scopes` can't be read back from SQLite, so the connections come from a mock
+        let audit_row = |uuid| entity::audit_log::Model {
+            id: 1,
+            uuid: uuid::Uuid::new_v4(),
+            created_at: chrono::Utc::now().into(),
+            actor_token_id: Some(7),
+            action: "update".to_string(),
+            entity_type: "connection_identity".to_string(),
+            entity_uuid: uuid,
+            before: None,
+            after: None,
+            request_id: None,
+        };
+        let (first, second) = (connection_model(1), connection_model(2));
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![first.clone(), second.clone()]])
+            .append_exec_results([
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+                MockExecResult { last_insert_id: 0, rows_affected: 2 },
+            ])
+            .append_query_results([[audit_row(first.uuid)], [audit_row(second.uuid)]])
+            .into_connection();
+        let service = ConnectionIdentityService::new(db.clone()).with_audit(
+            audit_services::AuditContext {
+                actor_token_id: Some(7),
+                request_id: None,
+            },
+        );
+
+        assert_eq!(service.set_enabled_by_tenant(1, false, None).await.unwrap(), 2);
+
+        //only connections whose flag changes are read, then one audit row each
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains(r#"\"is_enabled\" <> "#));
+        assert_eq!(log.matches(r#"INSERT INTO \"audit_log\""#).count(), 2);
+        assert!(log.contains(&first.uuid.to_string()));
+        assert!(log.contains(&second.uuid.to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_set_enabled_by_tenant_without_connections_updates_nothing() {
+        let db = setup_db().await;
+        let service = ConnectionIdentityService::new(db);
+
+        assert_eq!(service.set_enabled_by_tenant(9, false, None).await.unwrap(), 0);
+    }
+}
+
+/// Helper to create an in-memory database with the connection and every table that depends
+/// on it, with the FK cascades a purge relies on
+async fn setup_purge_db() -> DatabaseConnection {
+    let db = setup_db().await;
+    let schema = Schema::new(db.get_database_backend());
+    //inventory records reference the tenant; SQLite needs the table to resolve the FK
+    db.execute(&schema.create_table_from_entity(entity::tenant::Entity))
+        .await
+        .unwrap();
+    db.execute(&schema.create_table_from_entity(erp_connection_credentials::Entity))
+        .await
+        .unwrap();
+    db.execute(&schema.create_table_from_entity(connection_run::Entity))
+        .await
+        .unwrap();
+    db.execute(&schema.create_table_from_entity(sync_event::Entity))
+        .await
+        .unwrap();
+    db.execute(&schema.create_table_from_entity(inventory_record::Entity))
+        .await
+        .unwrap();
+    db.execute(&schema.create_table_from_entity(inventory_record_event::Entity))
+        .await
+        .unwrap();
+    db
+}
+
+/// Helper to insert connection `id` with credentials, a sync state, a run and its sync
+/// event, and two inventory records with one event each
+async fn insert_connection_with_dependents(db: &DatabaseConnection, id: i64) {
+    insert_locked_connection(db, id, 1).await;
+    let now: chrono::DateTime<chrono::FixedOffset> = chrono::Utc::now().into();
+
+    erp_connection_credentials::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        connection_id: Set(id),
+        token_type: Set(ErpConnectionAuthTokenType::Bearer),
+        enc_scheme: Set("none".to_string()),
+        enc_key_id: Set("none".to_string()),
+        enc_version: Set(1),
+        provider_password: Set(Some("secret".to_string())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    let state = erp_connection_sync_state::Entity::find()
+        .filter(erp_connection_sync_state::Column::ConnectionId.eq(id))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap();
+    let run = connection_run::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        status: Set(ConnectionRunStatus::Success),
+        run_type: Set(ConnectionRunType::Poll),
+        connection_id: Set(id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    sync_event::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        event_direction: Set(SyncEventDirection::PullFromExternal),
+        sync_event_method: Set(SyncEventMethod::List),
+        sync_event_category: Set(SyncEventCategory::Inventory),
+        attempts: Set(1),
+        status: Set(SyncEventStatus::Success),
+        connection_sync_state_id: Set(Some(state.id)),
+        connection_run_id: Set(Some(run.id)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+
+    for n in 0..2 {
+        let record = inventory_record::ActiveModel {
+            uuid: Set(uuid::Uuid::new_v4()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            tenant_id: Set(1),
+            originating_connection_id: Set(id),
+            system_id_key: Set(SystemIdKey::Qbd),
+            system_id: Set(format!("{id}-{n}")),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+        inventory_record_event::ActiveModel {
+            uuid: Set(uuid::Uuid::new_v4()),
+            created_at: Set(now),
+            updated_at: Set(now),
+            inventory_record_id: Set(record.id),
+            connection_id: Set(id),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+}
+
+#[cfg(test)]
+mod purge_tests {
+    use super::*;
+    use services::ConnectionDependents;
+
+    #[tokio::test]
+    async fn test_purge_removes_connection_and_dependents() {
+        let db = setup_purge_db().await;
+        insert_connection_with_dependents(&db, 1).await;
+        insert_connection_with_dependents(&db, 2).await;
+        //seeded without a tenant row; the cascades need the FK checks on
+        db.execute_unprepared("PRAGMA foreign_keys = ON").await.unwrap();
+        let service = ConnectionIdentityService::new(db.clone());
+
+        let preview = service.delete_preview(1, None).await.unwrap();
+        let expected = ConnectionDependents {
+            credentials: 1,
+            sync_states: 1,
+            connection_runs: 1,
+            sync_events: 1,
+            inventory_records: 2,
+            inventory_record_events: 2,
+        };
+        assert_eq!(preview, expected);
+
+        let removed = service.purge(&connection_model(1), None).await.unwrap();
+
+        assert_eq!(removed, preview);
+        assert_eq!(enabled_flags(&db).await, vec![(2, true)]);
+        assert_eq!(service.delete_preview(1, None).await.unwrap(), ConnectionDependents::default());
+        //the other connection keeps everything
+        assert_eq!(service.delete_preview(2, None).await.unwrap(), expected);
+        assert_eq!(sync_event::Entity::find().all(&db).await.unwrap().len(), 1);
+    }
+}
+
+/// Helper to create the purge database plus the audit log the timeline reads auth status
+/// changes from
+async fn setup_timeline_db() -> DatabaseConnection {
+    let db = setup_purge_db().await;
+    let schema = Schema::new(db.get_database_backend());
+    db.execute(&schema.create_table_from_entity(entity::audit_log::Entity))
+        .await
+        .unwrap();
+    db
+}
+
+/// Helper to insert an audited update of connection `uuid` changing `after`'s fields at `at`
+async fn insert_connection_update(
+    db: &DatabaseConnection,
+    uuid: uuid::Uuid,
+    before: serde_json::Value,
+    after: serde_json::Value,
+    at: chrono::DateTime<chrono::FixedOffset>,
+) -> entity::audit_log::Model {
+    entity::audit_log::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(at),
+        action: Set("update".to_string()),
+        entity_type: Set("connection_identity".to_string()),
+        entity_uuid: Set(uuid),
+        before: Set(Some(before)),
+        after: Set(Some(after)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+/// Helper to build the timeline's timestamps: minutes after a fixed start
+fn minute(n: i64) -> chrono::DateTime<chrono::FixedOffset> {
+    let start = chrono::DateTime::parse_from_rfc3339("2026-01-01T12:00:00+00:00").unwrap();
+    start + chrono::Duration::minutes(n)
+}
+
+/// Helper to insert a run of connection `id` started at `started` and, when `failed` is set,
+/// failed then
+async fn insert_run(
+    db: &DatabaseConnection,
+    id: i64,
+    started: chrono::DateTime<chrono::FixedOffset>,
+    failed: Option<chrono::DateTime<chrono::FixedOffset>>,
+) -> connection_run::Model {
+    connection_run::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(started),
+        updated_at: Set(failed.unwrap_or(started)),
+        status: Set(if failed.is_some() { ConnectionRunStatus::Error } else { ConnectionRunStatus::Success }),
+        error_message: Set(failed.map(|_| "QBWC session timed out".to_string())),
+        run_type: Set(ConnectionRunType::Poll),
+        connection_id: Set(id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+/// Helper to insert a sync event of `run` that settled with `status` at `settled`
+async fn insert_settled_event(
+    db: &DatabaseConnection,
+    run: &connection_run::Model,
+    status: SyncEventStatus,
+    settled: chrono::DateTime<chrono::FixedOffset>,
+) -> sync_event::Model {
+    sync_event::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(run.created_at),
+        updated_at: Set(settled),
+        event_direction: Set(SyncEventDirection::PullFromExternal),
+        sync_event_method: Set(SyncEventMethod::List),
+        sync_event_category: Set(SyncEventCategory::Inventory),
+        attempts: Set(1),
+        status: Set(status),
+        connection_run_id: Set(Some(run.id)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+#[cfg(test)]
+mod timeline_tests {
+    use super::*;
+    use timeline::{TimelineEntryKind, TimelineService};
+
+    #[tokio::test]
+    async fn test_runs_and_sync_events_interleave_by_time() {
+        let db = setup_timeline_db().await;
+        insert_locked_connection(&db, 1, 1).await;
+        insert_locked_connection(&db, 2, 1).await;
+        let run = insert_run(&db, 1, minute(0), Some(minute(3))).await;
+        let event = insert_settled_event(&db, &run, SyncEventStatus::Success, minute(1)).await;
+        //still running: not on the timeline yet
+        insert_settled_event(&db, &run, SyncEventStatus::InProgress, minute(2)).await;
+        //another connection's activity stays off it
+        let other = insert_run(&db, 2, minute(2), None).await;
+        insert_settled_event(&db, &other, SyncEventStatus::Error, minute(2)).await;
+
+        //`scopes` can't be read back from SQLite; only the id and uuid matter here
+        let mut connection = connection_model(1);
+        connection.uuid = connection_identity::Entity::find_by_id(1)
+            .select_only()
+            .column(connection_identity::Column::Uuid)
+            .into_tuple()
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let auth_change = insert_connection_update(
+            &db,
+            connection.uuid,
+            serde_json::json!({ "auth_status": "connected" }),
+            serde_json::json!({ "auth_status": "expired" }),
+            minute(4),
+        )
+        .await;
+        //updates that leave auth_status alone are not auth status changes
+        insert_connection_update(
+            &db,
+            connection.uuid,
+            serde_json::json!({ "display_name": null }),
+            serde_json::json!({ "display_name": "Warehouse" }),
+            minute(5),
+        )
+        .await;
+
+        let page = TimelineService::new(db)
+            .get_page(&connection, None, 50)
+            .await
+            .unwrap();
+
+        let entries: Vec<_> = page
+            .entries
+            .iter()
+            .map(|e| (e.kind, e.at, e.source_uuid))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![
+                (TimelineEntryKind::AuthStatusChanged, minute(4), auth_change.uuid),
+                (TimelineEntryKind::RunFailed, minute(3), run.uuid),
+                (TimelineEntryKind::EventSuccess, minute(1), event.uuid),
+                (TimelineEntryKind::RunStarted, minute(0), run.uuid),
+            ]
+        );
+        assert_eq!(page.entries[0].message.as_deref(), Some("connected -> expired"));
+        assert_eq!(page.entries[1].message.as_deref(), Some("QBWC session timed out"));
+        assert_eq!(page.next_before, None);
+    }
+
+    #[tokio::test]
+    async fn test_pages_continue_before_the_last_entry() {
+        let db = setup_timeline_db().await;
+        insert_locked_connection(&db, 1, 1).await;
+        let first = insert_run(&db, 1, minute(0), None).await;
+        let second = insert_run(&db, 1, minute(2), None).await;
+        insert_settled_event(&db, &first, SyncEventStatus::Error, minute(1)).await;
+        let service = TimelineService::new(db);
+
+        let page = service.get_page(&connection_model(1), None, 2).await.unwrap();
+
+        let kinds: Vec<_> = page.entries.iter().map(|e| e.kind).collect();
+        assert_eq!(kinds, vec![TimelineEntryKind::RunStarted, TimelineEntryKind::EventError]);
+        assert_eq!(page.entries[0].source_uuid, second.uuid);
+        assert_eq!(page.next_before, Some(minute(1)));
+
+        let page = service
+            .get_page(&connection_model(1), page.next_before, 2)
+            .await
+            .unwrap();
+
+        let entries: Vec<_> = page.entries.iter().map(|e| (e.kind, e.source_uuid)).collect();
+        assert_eq!(entries, vec![(TimelineEntryKind::RunStarted, first.uuid)]);
+        assert_eq!(page.next_before, None);
+    }
+}
+
+#[cfg(test)]
+mod last_error_tests {
+    use super::*;
+    use last_error::LastErrorService;
+    use sea_orm::IntoActiveModel;
+
+    /// Helper to record `last_error` on `event` at `errored`
+    async fn mark_errored(
+        db: &DatabaseConnection,
+        event: sync_event::Model,
+        last_error: serde_json::Value,
+        errored: chrono::DateTime<chrono::FixedOffset>,
+    ) -> sync_event::Model {
+        let mut active = event.into_active_model();
+        active.last_error = Set(Some(last_error));
+        active.last_errored_date = Set(Some(errored));
+        active.update(db).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_latest_errored_event_with_its_run_message() {
+        let db = setup_timeline_db().await;
+        insert_locked_connection(&db, 1, 1).await;
+        insert_locked_connection(&db, 2, 1).await;
+        let earlier = insert_run(&db, 1, minute(0), None).await;
+        let failed = insert_run(&db, 1, minute(5), Some(minute(7))).await;
+        let older = insert_settled_event(&db, &earlier, SyncEventStatus::Error, minute(1)).await;
+        mark_errored(&db, older, serde_json::json!({ "code": "3100" }), minute(1)).await;
+        let latest = insert_settled_event(&db, &failed, SyncEventStatus::Error, minute(6)).await;
+        let latest = mark_errored(
+            &db,
+            latest,
+            serde_json::json!({ "code": "3200", "message": "Object out of date" }),
+            minute(6),
+        )
+        .await;
+        //another connection's later error is not this one's
+        let other = insert_run(&db, 2, minute(8), None).await;
+        let other = insert_settled_event(&db, &other, SyncEventStatus::Error, minute(9)).await;
+        mark_errored(&db, other, serde_json::json!({ "code": "500" }), minute(9)).await;
+
+        let last_error = LastErrorService::new(db).latest(1).await.unwrap().unwrap();
+
+        assert_eq!(last_error.event.uuid, latest.uuid);
+        assert_eq!(
+            last_error.event.last_error,
+            Some(serde_json::json!({ "code": "3200", "message": "Object out of date" }))
+        );
+        assert_eq!(last_error.event.last_errored_date, Some(minute(6)));
+        let run = last_error.run.unwrap();
+        assert_eq!(run.uuid, failed.uuid);
+        assert_eq!(run.error_message.as_deref(), Some("QBWC session timed out"));
+    }
+
+    #[tokio::test]
+    async fn test_no_errored_event_is_none() {
+        let db = setup_timeline_db().await;
+        insert_locked_connection(&db, 1, 1).await;
+        let run = insert_run(&db, 1, minute(0), None).await;
+        insert_settled_event(&db, &run, SyncEventStatus::Success, minute(1)).await;
+
+        assert!(LastErrorService::new(db).latest(1).await.unwrap().is_none());
+    }
+}
+
+/// Helper to build the credentials row of connection `connection_id`
+fn credentials_model(connection_id: i64) -> erp_connection_credentials::Model {
+    let now: chrono::DateTime<chrono::FixedOffset> = chrono::Utc::now().into();
+    erp_connection_credentials::Model {
+        id: 7,
+        uuid: uuid::Uuid::new_v4(),
+        created_at: now,
+        updated_at: now,
+        connection_id,
+        client_id: None,
+        issuer_base_url: None,
+        token_type: ErpConnectionAuthTokenType::Bearer,
+        reauth_required_reason: None,
+        reauth_url: None,
+        enc_scheme: "none".to_string(),
+        enc_key_id: "key-1".to_string(),
+        enc_version: 1,
+        enc_iv: None,
+        enc_tag: None,
+        access_token: None,
+        refresh_token: None,
+        access_token_expires_at: None,
+        refresh_token_expires_at: None,
+        id_token_enc: None,
+        provider_user_id: Some("qbwc-user".to_string()),
+        provider_password: Some("secret".to_string()),
+        client_cert: None,
+        private_key: None,
+        cert_expires_at: None,
+        session_token: None,
+        session_expires_at: None,
+        api_access_token: None,
+        api_access_token_key: None,
+    }
+}
+
+#[cfg(test)]
+mod relation_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connection_with_credentials_in_one_query() {
+        let connection = connection_model(4);
+        let credentials = credentials_model(4);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![(connection.clone(), Some(credentials.clone()))]])
+            .into_connection();
+        let service = ConnectionIdentityService::new(db.clone());
+
+        let (found, found_credentials) = service
+            .get_with_credentials_by_uuid(connection.uuid, None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(found, connection);
+        assert_eq!(found_credentials, Some(credentials));
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 1);
+        let sql = &log[0].statements()[0].sql;
+        assert!(
+            sql.contains(r#"LEFT JOIN "erp_connection_credentials""#),
+            "{sql}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connection_without_credentials() {
+        let connection = connection_model(5);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![(
+                connection.clone(),
+                None::<erp_connection_credentials::Model>,
+            )]])
+            .into_connection();
+        let service = ConnectionIdentityService::new(db);
+
+        let found = service
+            .get_with_credentials_by_uuid(connection.uuid, None)
+            .await
+            .unwrap();
+
+        assert_eq!(found, Some((connection, None)));
+    }
+}
+
+/// Helper to insert an inventory sync event with `status`, tied to a sync state and/or a run
+async fn insert_event(
+    db: &DatabaseConnection,
+    sync_state_id: Option<i64>,
+    run_id: Option<i64>,
+    status: SyncEventStatus,
+) {
+    let now: chrono::DateTime<chrono::FixedOffset> = chrono::Utc::now().into();
+    sync_event::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(now),
+        updated_at: Set(now),
+        event_direction: Set(SyncEventDirection::PullFromExternal),
+        sync_event_method: Set(SyncEventMethod::List),
+        sync_event_category: Set(SyncEventCategory::Inventory),
+        attempts: Set(0),
+        status: Set(status),
+        connection_sync_state_id: Set(sync_state_id),
+        connection_run_id: Set(run_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+/// Helper to read the uuid and sync state id of connection `id`
+async fn connection_refs(db: &DatabaseConnection, id: i64) -> (uuid::Uuid, i64) {
+    let uuid = connection_identity::Entity::find_by_id(id)
+        .select_only()
+        .column(connection_identity::Column::Uuid)
+        .into_tuple::<uuid::Uuid>()
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap();
+    let state = erp_connection_sync_state::Entity::find()
+        .filter(erp_connection_sync_state::Column::ConnectionId.eq(id))
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap();
+    (uuid, state.id)
+}
+
+#[cfg(test)]
+mod status_batch_tests {
+    use super::*;
+    use status::{ConnectionStatusService, SyncEventCounts};
+
+    #[tokio::test]
+    async fn test_three_connections_with_pending_counts() {
+        let db = setup_purge_db().await;
+        for id in 1..=3 {
+            insert_locked_connection(&db, id, 1).await;
+        }
+        insert_locked_connection(&db, 4, 2).await;
+        let (uuid_1, state_1) = connection_refs(&db, 1).await;
+        let (uuid_2, _) = connection_refs(&db, 2).await;
+        let (uuid_3, _) = connection_refs(&db, 3).await;
+        let (uuid_4, state_4) = connection_refs(&db, 4).await;
+
+        //connection 1: two pending and one in progress through its sync state; settled events
+        //are not counted
+        let run_1 = insert_run(&db, 1, minute(0), None).await;
+        insert_event(&db, Some(state_1), None, SyncEventStatus::Pending).await;
+        insert_event(&db, Some(state_1), Some(run_1.id), SyncEventStatus::Pending).await;
+        insert_event(&db, Some(state_1), None, SyncEventStatus::InProgress).await;
+        insert_event(&db, Some(state_1), None, SyncEventStatus::Success).await;
+        insert_event(&db, Some(state_1), None, SyncEventStatus::Error).await;
+        //connection 2: one pending tied to a run only
+        let run_2 = insert_run(&db, 2, minute(0), None).await;
+        insert_event(&db, None, Some(run_2.id), SyncEventStatus::Pending).await;
+        //another tenant's connection
+        insert_event(&db, Some(state_4), None, SyncEventStatus::Pending).await;
+
+        let statuses = ConnectionStatusService::new(db)
+            .get_batch(&[uuid_3, uuid_1, uuid_2, uuid_4], Some(1))
+            .await
+            .unwrap();
+
+        let got: Vec<(uuid::Uuid, SyncEventCounts)> = statuses
+            .iter()
+            .map(|s| (s.connection.uuid, s.events))
+            .collect();
+        assert_eq!(
+            got,
+            vec![
+                (uuid_3, SyncEventCounts { pending: 0, in_progress: 0 }),
+                (uuid_1, SyncEventCounts { pending: 2, in_progress: 1 }),
+                (uuid_2, SyncEventCounts { pending: 1, in_progress: 0 }),
+            ]
+        );
+        assert!(statuses.iter().all(|s| s.connection.status == ErpConnectionStatus::Active));
+    }
+
+    #[tokio::test]
+    async fn test_unscoped_batch_and_unknown_uuids() {
+        let db = setup_purge_db().await;
+        insert_locked_connection(&db, 1, 1).await;
+        insert_locked_connection(&db, 2, 2).await;
+        let (uuid_1, _) = connection_refs(&db, 1).await;
+        let (uuid_2, state_2) = connection_refs(&db, 2).await;
+        insert_event(&db, Some(state_2), None, SyncEventStatus::Pending).await;
+
+        //without a tenant every connection is visible; unknown uuids are left out
+        let statuses = ConnectionStatusService::new(db)
+            .get_batch(&[uuid_2, uuid::Uuid::new_v4(), uuid_1], None)
+            .await
+            .unwrap();
+        let got: Vec<(uuid::Uuid, u64)> = statuses
+            .iter()
+            .map(|s| (s.connection.uuid, s.events.pending))
+            .collect();
+        assert_eq!(got, vec![(uuid_2, 1), (uuid_1, 0)]);
+    }
+}
+
+/// Helper to build a mock answering one update: the lookup by uuid, then UPDATE ... RETURNING
+fn update_db(
+    before: connection_identity::Model,
+    after: connection_identity::Model,
+) -> sea_orm::DatabaseConnection {
+    MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([vec![before]])
+        .append_query_results([vec![after]])
+        .into_connection()
+}
+
+#[cfg(test)]
+mod update_diff_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_display_name_patch_reports_only_display_name() {
+        let before = connection_model(1);
+        let mut after = before.clone();
+        after.display_name = Some("Main warehouse".to_string());
+        after.updated_at = (chrono::Utc::now() + chrono::Duration::seconds(1)).into();
+        let service = ConnectionIdentityService::new(update_db(before.clone(), after));
+
+        let patch = UpdateConnectionIdentity {
+            display_name: Some("Main warehouse".to_string()),
+            ..empty_patch()
+        };
+        let updated = service.update_by_uuid(before.uuid, patch, None).await.unwrap().unwrap();
+
+        //updated_at moved too, but is not reported
+        assert_eq!(updated.changed_fields, vec!["display_name".to_string()]);
+        assert_eq!(updated.model.display_name.as_deref(), Some("Main warehouse"));
+    }
+
+    #[tokio::test]
+    async fn test_unchanged_values_report_nothing() {
+        let before = connection_model(1);
+        let service = ConnectionIdentityService::new(update_db(before.clone(), before.clone()));
+
+        //the connection is already enabled
+        let patch = UpdateConnectionIdentity {
+            is_enabled: Some(true),
+            ..empty_patch()
+        };
+        let updated = service.update_by_uuid(before.uuid, patch, None).await.unwrap().unwrap();
+
+        assert!(updated.changed_fields.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_several_changes_are_listed_by_column_name() {
+        let before = connection_model(1);
+        let after = connection_identity::Model {
+            is_enabled: false,
+            sync_window_start_hour: Some(22),
+            sync_window_end_hour: Some(6),
+            ..before.clone()
+        };
+        let service = ConnectionIdentityService::new(update_db(before.clone(), after));
+
+        let patch = UpdateConnectionIdentity {
+            is_enabled: Some(false),
+            sync_window: Some(sync_window::SyncWindow::new(22, 6)),
+            ..empty_patch()
+        };
+        let updated = service.update_by_uuid(before.uuid, patch, None).await.unwrap().unwrap();
+
+        assert_eq!(
+            updated.changed_fields,
+            vec!["is_enabled", "sync_window_end_hour", "sync_window_start_hour"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_field_mapping_is_set_and_reported() {
+        let before = connection_model(1);
+        let mapping = serde_json::json!({"external_code": "ManufacturerPartNumber"});
+        let after = connection_identity::Model {
+            field_mapping: Some(mapping.clone()),
+            ..before.clone()
+        };
+        let service = ConnectionIdentityService::new(update_db(before.clone(), after));
+
+        let patch = UpdateConnectionIdentity {
+            field_mapping: Some(Some(mapping.clone())),
+            ..empty_patch()
+        };
+        let updated = service.update_by_uuid(before.uuid, patch, None).await.unwrap().unwrap();
+
+        assert_eq!(updated.changed_fields, vec!["field_mapping"]);
+        assert_eq!(updated.model.field_mapping, Some(mapping));
+    }
+}
+
+#[cfg(test)]
+mod default_tenant_tests {
+    use super::*;
+    use default_tenant::{ensure_default_tenant, tenant_id_or_default};
+    use entity::sea_orm_active_enums::Enum as TenantStatus;
+
+    const DEFAULT: &str = "TN_00000000000000000000000000000001";
+
+    #[tokio::test]
+    async fn test_create_without_tenant_id_attaches_to_default() {
+        let now = chrono::Utc::now().into();
+        let tenant = entity::tenant::Model {
+            id: 42,
+            uuid: uuid::Uuid::new_v4(),
+            display_name: None,
+            tenant_id: DEFAULT.to_string(),
+            created_at: now,
+            updated_at: now,
+            status: TenantStatus::Active,
+        };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            //ensure_default_tenant finds the tenant created at startup
+            .append_query_results([vec![tenant]])
+            //find_conflicting
+            .append_query_results([Vec::<connection_identity::Model>::new()])
+            //INSERT ... RETURNING
+            .append_query_results([vec![connection_identity::Model {
+                tenant_id: 42,
+                ..connection_model(5)
+            }]])
+            .into_connection();
+
+        //no tenant_id in the request, an unscoped token and DEFAULT_TENANT_ID set
+        let tenant_id = tenant_id_or_default(None, Some(DEFAULT), false).unwrap();
+        let tenant = ensure_default_tenant(&db, tenant_id).await.unwrap();
+        let created = ConnectionIdentityService::new(db.clone())
+            .create(
+                CreateConnectionIdentity {
+                    tenant_id: tenant.id,
+                    ..create_request()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(created.tenant_id, 42);
+        let log = db.into_transaction_log();
+        let insert = format!("{:?}", log.last().unwrap());
+        assert!(insert.contains(r#"INSERT INTO \"connection_identity\""#));
+        assert!(insert.contains("BigInt(Some(42))"));
+    }
+}
+
+#[cfg(test)]
+mod health_order_tests {
+    use super::*;
+    use services::ConnectionOrder;
+
+    /// Helper to insert a connection created `created_days_ago` days ago, with `auth_status`
+    /// and its last success `success_days_ago` days ago (None: never)
+    async fn insert_connection(
+        db: &DatabaseConnection,
+        id: i64,
+        auth_status: ErpConnectionAuthStatus,
+        created_days_ago: i64,
+        success_days_ago: Option<i64>,
+    ) {
+        let now = chrono::Utc::now();
+        let mut model = connection_model(id);
+        model.company_file_id = Some(format!("company-file-{id}"));
+        model.auth_status = auth_status;
+        model.created_at = (now - chrono::Duration::days(created_days_ago)).into();
+        model.last_success_at = success_days_ago.map(|days| (now - chrono::Duration::days(days)).into());
+        let mut active = model.into_active_model().reset_all();
+        active.scopes = NotSet;
+        connection_identity::Entity::insert(active)
+            .exec_without_returning(db)
+            .await
+            .unwrap();
+    }
+
+    /// Helper to list connection ids in `get_all` order; only the ids are selected, since
+    /// SQLite can't read `scopes` back
+    async fn listed_ids(db: &DatabaseConnection, order_by: ConnectionOrder) -> Vec<i64> {
+        services::list_query(None, order_by)
+            .select_only()
+            .column(connection_identity::Column::Id)
+            .into_tuple::<i64>()
+            .all(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_errored_connection_precedes_healthy_one_regardless_of_creation_time() {
+        let db = setup_db().await;
+        //the errored connection is older and succeeded more recently than the healthy one
+        insert_connection(&db, 1, ErpConnectionAuthStatus::Error, 30, Some(1)).await;
+        insert_connection(&db, 2, ErpConnectionAuthStatus::Connected, 1, Some(10)).await;
+
+        assert_eq!(listed_ids(&db, ConnectionOrder::HealthPriority).await, vec![1, 2]);
+        assert_eq!(listed_ids(&db, ConnectionOrder::CreatedAt).await, vec![2, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_health_order_puts_oldest_success_first_within_each_group() {
+        let db = setup_db().await;
+        insert_connection(&db, 1, ErpConnectionAuthStatus::Connected, 5, Some(1)).await;
+        insert_connection(&db, 2, ErpConnectionAuthStatus::Connected, 4, None).await;
+        insert_connection(&db, 3, ErpConnectionAuthStatus::NeedsReauth, 3, Some(2)).await;
+        insert_connection(&db, 4, ErpConnectionAuthStatus::Error, 2, Some(7)).await;
+        insert_connection(&db, 5, ErpConnectionAuthStatus::Revoked, 1, Some(3)).await;
+
+        assert_eq!(
+            listed_ids(&db, ConnectionOrder::HealthPriority).await,
+            vec![4, 3, 2, 5, 1]
+        );
+    }
+}
+
+#[cfg(test)]
+mod needs_reauth_tests {
+    use super::*;
+    use services::needs_reauth_message;
+
+    /// Helper to build the outbox row a mocked `INSERT ... RETURNING` hands back
+    fn outbox_model() -> entity::outbox::Model {
+        let now = chrono::Utc::now().into();
+        entity::outbox::Model {
+            id: 1,
+            uuid: uuid::Uuid::new_v4(),
+            created_at: now,
+            updated_at: now,
+            topic: "connection.needs_reauth".to_string(),
+            payload: serde_json::json!({}),
+            attempts: 0,
+            next_attempt_at: now,
+            published_at: None,
+            last_error: None,
+        }
+    }
+
+    fn errored(model: connection_identity::Model) -> connection_identity::Model {
+        connection_identity::Model {
+            auth_status: ErpConnectionAuthStatus::Error,
+            last_error_code: Some("E_AUTH".to_string()),
+            last_error_message: Some("token expired".to_string()),
+            ..model
+        }
+    }
+
+    #[tokio::test]
+    async fn test_two_consecutive_errors_enqueue_one_message() {
+        let healthy = connection_model(7);
+        let errored = errored(healthy.clone());
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            //first error: lookup, UPDATE ... RETURNING, outbox INSERT ... RETURNING
+            .append_query_results([vec![healthy.clone()], vec![errored.clone()]])
+            .append_query_results([vec![outbox_model()]])
+            //second error: the connection is already in error, so no message
+            .append_query_results([vec![errored.clone()], vec![errored.clone()]])
+            .into_connection();
+        let service = ConnectionIdentityService::new(db.clone());
+
+        for _ in 0..2 {
+            service
+                .record_error(healthy.uuid, "E_AUTH", "token expired", None)
+                .await
+                .unwrap();
+        }
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches(r#"INSERT INTO \"outbox\""#).count(), 1, "{log}");
+        assert!(log.contains("connection.needs_reauth"));
+        assert_eq!(log.matches("COMMIT").count(), 2);
+    }
+
+    #[test]
+    fn test_message_only_on_transition_into_an_unhealthy_status() {
+        let healthy = connection_model(7);
+        let errored = errored(healthy.clone());
+
+        let message =
+            needs_reauth_message(&ErpConnectionAuthStatus::Connected, &errored, "token expired")
+                .unwrap();
+        assert_eq!(message.topic, "connection.needs_reauth");
+        assert_eq!(message.payload["connection_uuid"], healthy.uuid.to_string());
+        assert_eq!(message.payload["erp_provider"], "quickbooks");
+        assert_eq!(message.payload["auth_status"], "error");
+        assert_eq!(message.payload["previous_auth_status"], "connected");
+        assert_eq!(message.payload["reason"], "token expired");
+
+        let reauth = connection_identity::Model {
+            auth_status: ErpConnectionAuthStatus::NeedsReauth,
+            ..healthy.clone()
+        };
+        assert!(needs_reauth_message(&ErpConnectionAuthStatus::Error, &reauth, "reauth").is_some());
+        assert!(needs_reauth_message(&ErpConnectionAuthStatus::Error, &errored, "again").is_none());
+        assert!(needs_reauth_message(&ErpConnectionAuthStatus::Error, &healthy, "fixed").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_update_to_needs_reauth_enqueues_in_the_update_transaction() {
+        let healthy = connection_model(7);
+        let reauth = connection_identity::Model {
+            auth_status: ErpConnectionAuthStatus::NeedsReauth,
+            ..healthy.clone()
+        };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![healthy.clone()], vec![reauth]])
+            .append_query_results([vec![outbox_model()]])
+            .into_connection();
+
+        ConnectionIdentityService::new(db.clone())
+            .update_by_uuid(
+                healthy.uuid,
+                UpdateConnectionIdentity {
+                    auth_status: Some(ErpConnectionAuthStatus::NeedsReauth),
+                    ..empty_patch()
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains("BEGIN"));
+        assert!(log.contains(r#"INSERT INTO \"outbox\""#), "{log}");
+        assert!(log.contains("COMMIT"));
+    }
+}
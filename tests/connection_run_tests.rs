@@ -0,0 +1,142 @@
+//! Tests for paging through a connection's runs
+//!
+//! Run with: cargo test --test connection_run_tests
+//!
+//! Runs against an in-memory SQLite database.
+
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+#[path = "../src/connection_run/services.rs"]
+mod services;
+
+//the service counts created runs through `crate::config::metrics`
+mod config {
+    pub(crate) use super::metrics;
+}
+
+use std::collections::HashSet;
+
+use entity::connection_run;
+use entity::sea_orm_active_enums::{ConnectionRunStatus, ConnectionRunType};
+use sea_orm::{ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, Schema, Set};
+use services::{ConnectionRunService, RunCursor};
+
+/// Helper to create an in-memory database with the connection run table
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    db.execute(&schema.create_table_from_entity(connection_run::Entity))
+        .await
+        .unwrap();
+    db
+}
+
+/// Helper to build run timestamps: minutes after a fixed start
+fn minute(n: i64) -> chrono::DateTime<chrono::FixedOffset> {
+    let start = chrono::DateTime::parse_from_rfc3339("2026-01-01T12:00:00+00:00").unwrap();
+    start + chrono::Duration::minutes(n)
+}
+
+/// Helper to insert a run of connection `connection_id` created at `at`
+async fn insert_run(
+    db: &DatabaseConnection,
+    connection_id: i64,
+    at: chrono::DateTime<chrono::FixedOffset>,
+) -> connection_run::Model {
+    connection_run::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(at),
+        updated_at: Set(at),
+        status: Set(ConnectionRunStatus::Success),
+        run_type: Set(ConnectionRunType::Poll),
+        error_message: Set(None),
+        connection_id: Set(connection_id),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+/// Helper to page through connection `connection_id`'s runs `limit` at a time, returning the
+/// run ids in the order served and the number of pages
+async fn page_through(db: &DatabaseConnection, connection_id: i64, limit: u64) -> (Vec<i64>, usize) {
+    let service = ConnectionRunService::new(db.clone());
+    let mut ids = Vec::new();
+    let mut pages = 0;
+    let mut cursor = None;
+    loop {
+        let page = service
+            .page_by_connection_id(connection_id, cursor, limit, None)
+            .await
+            .unwrap();
+        pages += 1;
+        ids.extend(page.runs.iter().map(|run| run.id));
+        //the cursor travels as a query string token
+        cursor = match page.next_cursor {
+            Some(next) => Some(RunCursor::decode(&next.encode()).unwrap()),
+            None => break,
+        };
+    }
+    (ids, pages)
+}
+
+#[cfg(test)]
+mod run_paging_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_identical_timestamps_are_served_once() {
+        let db = setup_db().await;
+        let older = insert_run(&db, 1, minute(0)).await;
+        let mut same: Vec<i64> = Vec::new();
+        for _ in 0..7 {
+            same.push(insert_run(&db, 1, minute(5)).await.id);
+        }
+        let newer = insert_run(&db, 1, minute(10)).await;
+        //another connection's runs at the same time stay out
+        insert_run(&db, 2, minute(5)).await;
+
+        let (ids, pages) = page_through(&db, 1, 3).await;
+
+        assert_eq!(ids.len(), 9);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 9, "{ids:?}");
+        assert_eq!(pages, 3);
+        //newest first, ties by id descending
+        same.reverse();
+        let mut expected = vec![newer.id];
+        expected.extend(same);
+        expected.push(older.id);
+        assert_eq!(ids, expected);
+    }
+
+    #[tokio::test]
+    async fn test_full_last_page_has_no_cursor() {
+        let db = setup_db().await;
+        for n in 0..4 {
+            insert_run(&db, 1, minute(n)).await;
+        }
+
+        let service = ConnectionRunService::new(db.clone());
+        let first = service.page_by_connection_id(1, None, 2, None).await.unwrap();
+        assert_eq!(first.runs.len(), 2);
+        let second = service
+            .page_by_connection_id(1, first.next_cursor, 2, None)
+            .await
+            .unwrap();
+        assert_eq!(second.runs.len(), 2);
+        assert!(second.next_cursor.is_none());
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = RunCursor {
+            created_at: minute(5),
+            id: 42,
+        };
+        assert_eq!(RunCursor::decode(&cursor.encode()), Some(cursor));
+        assert_eq!(RunCursor::decode("not-a-cursor"), None);
+        assert_eq!(RunCursor::decode(""), None);
+    }
+}
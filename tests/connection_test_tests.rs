@@ -0,0 +1,283 @@
+//! Tests for the connection test / ping
+//!
+//! Run with: cargo test --test connection_test_tests
+//!
+//! Uses the sea-orm mock database (no Postgres required) and a local axum server standing
+//! in for the QuickBooks Online API.
+
+#[path = "../src/audit/services.rs"]
+mod audit_services;
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/connection_identity/probe.rs"]
+mod probe;
+#[path = "../src/connection_identity/services.rs"]
+mod services;
+#[path = "../src/outbox/services.rs"]
+mod outbox_services;
+#[path = "../src/sync/window.rs"]
+mod sync_window;
+
+//the services record audit rows through `crate::audit::services` and read sync windows
+//through `crate::sync::window`
+mod audit {
+    pub(crate) use super::audit_services as services;
+}
+mod outbox {
+    pub(crate) use super::outbox_services as services;
+}
+mod sync {
+    pub(crate) use super::sync_window as window;
+}
+
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use entity::sea_orm_active_enums::{
+    ErpConnectionAuthStatus, ErpConnectionAuthTokenType, ErpConnectionStatus, ErpEnvironment,
+    ErpProvider, ErpProviderAuthType, ErpProviderType,
+};
+use entity::{connection_identity, erp_connection_credentials};
+use probe::{probe_qbd, ConnectionTestError, ConnectionTestService};
+use sea_orm::{DatabaseBackend, DatabaseConnection, MockDatabase};
+
+const GOOD_TOKEN: &str = "good-token";
+
+/// Helper to build a connection row
+fn connection_model(provider: ErpProvider, erp_type: ErpProviderType) -> connection_identity::Model {
+    let now = chrono::Utc::now().into();
+    connection_identity::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider: provider,
+        erp_type,
+        erp_auth_type: ErpProviderAuthType::Oauth2,
+        display_name: None,
+        environment: ErpEnvironment::Sandbox,
+        status: ErpConnectionStatus::Active,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: now,
+        updated_at: now,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: Some("9130".to_string()),
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: None,
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+}
+
+/// Helper to build a credentials row with either a Web Connector login or an access token
+fn credentials_model(
+    username: Option<&str>,
+    password: Option<&str>,
+    access_token: Option<&str>,
+) -> erp_connection_credentials::Model {
+    let now = chrono::Utc::now().into();
+    erp_connection_credentials::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        created_at: now,
+        updated_at: now,
+        connection_id: 1,
+        client_id: None,
+        issuer_base_url: None,
+        token_type: ErpConnectionAuthTokenType::Bearer,
+        reauth_required_reason: None,
+        reauth_url: None,
+        enc_scheme: "none".to_string(),
+        enc_key_id: "none".to_string(),
+        enc_version: 1,
+        enc_iv: None,
+        enc_tag: None,
+        access_token: access_token.map(str::to_string),
+        refresh_token: None,
+        access_token_expires_at: None,
+        refresh_token_expires_at: None,
+        id_token_enc: None,
+        provider_user_id: username.map(str::to_string),
+        provider_password: password.map(str::to_string),
+        client_cert: None,
+        private_key: None,
+        cert_expires_at: None,
+        session_token: None,
+        session_expires_at: None,
+        api_access_token: None,
+        api_access_token_key: None,
+    }
+}
+
+/// Mock QuickBooks Online: companyinfo answers 200 for GOOD_TOKEN, 401 otherwise.
+/// Returns the base URL.
+async fn spawn_mock_qbo() -> String {
+    async fn company_info(headers: HeaderMap) -> (StatusCode, &'static str) {
+        let expected = format!("Bearer {GOOD_TOKEN}");
+        match headers.get("authorization").and_then(|v| v.to_str().ok()) {
+            Some(auth) if auth == expected => (StatusCode::OK, r#"{"CompanyInfo":{}}"#),
+            _ => (StatusCode::UNAUTHORIZED, r#"{"fault":"AuthenticationFailed"}"#),
+        }
+    }
+
+    let app = Router::new().route("/v3/company/{realm}/companyinfo/{id}", get(company_info));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// Helper to build a mock answering an API test: credentials lookup, then the
+/// record_success/record_error find + update
+fn api_test_db(
+    conn: &connection_identity::Model,
+    creds: erp_connection_credentials::Model,
+) -> DatabaseConnection {
+    MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[creds]])
+        .append_query_results([[conn.clone()]])
+        .append_query_results([[conn.clone()]])
+        .into_connection()
+}
+
+/// Statements sent to the mock, for asserting which auth_status was written
+fn statements(db: DatabaseConnection) -> String {
+    format!("{:?}", db.into_transaction_log())
+}
+
+#[cfg(test)]
+mod qbd_probe_tests {
+    use super::*;
+
+    #[test]
+    fn test_qbd_reports_expected_username() {
+        let creds = credentials_model(Some("qbwc-user"), Some("secret"), None);
+        let outcome = probe_qbd(Some(&creds));
+
+        assert!(outcome.ok);
+        assert_eq!(outcome.expected_username.as_deref(), Some("qbwc-user"));
+        //the server never calls QBD
+        assert_eq!(outcome.reachable, None);
+    }
+
+    #[test]
+    fn test_qbd_missing_credentials() {
+        let outcome = probe_qbd(None);
+        assert!(!outcome.ok);
+        assert_eq!(outcome.error_code.as_deref(), Some("missing_credentials"));
+
+        let no_password = credentials_model(Some("qbwc-user"), None, None);
+        assert!(!probe_qbd(Some(&no_password)).ok);
+    }
+
+    #[tokio::test]
+    async fn test_qbd_run_leaves_auth_status_alone() {
+        let conn = connection_model(ErpProvider::Quickbooks, ErpProviderType::Desktop);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[credentials_model(Some("qbwc-user"), Some("secret"), None)]])
+            .into_connection();
+
+        let outcome = ConnectionTestService::new(db.clone()).run(&conn).await.unwrap();
+        assert!(outcome.ok);
+
+        let log = statements(db);
+        assert!(!log.contains("UPDATE"), "unexpected write: {log}");
+    }
+}
+
+#[cfg(test)]
+mod api_probe_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_valid_token_records_success() {
+        let base_url = spawn_mock_qbo().await;
+        let conn = connection_model(ErpProvider::Quickbooks, ErpProviderType::Api);
+        let db = api_test_db(&conn, credentials_model(None, None, Some(GOOD_TOKEN)));
+
+        let outcome = ConnectionTestService::new(db.clone())
+            .with_qbo_base_url(Some(base_url))
+            .run(&conn)
+            .await
+            .unwrap();
+
+        assert!(outcome.ok);
+        assert_eq!(outcome.reachable, Some(true));
+        assert_eq!(outcome.token_valid, Some(true));
+        assert!(outcome.error_code.is_none());
+
+        let log = statements(db);
+        assert!(log.contains("UPDATE"));
+        assert!(log.contains("connected"), "auth_status not set to connected: {log}");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_token_records_error() {
+        let base_url = spawn_mock_qbo().await;
+        let conn = connection_model(ErpProvider::Quickbooks, ErpProviderType::Api);
+        let db = api_test_db(&conn, credentials_model(None, None, Some("expired-token")));
+
+        let outcome = ConnectionTestService::new(db.clone())
+            .with_qbo_base_url(Some(base_url))
+            .run(&conn)
+            .await
+            .unwrap();
+
+        assert!(!outcome.ok);
+        assert_eq!(outcome.reachable, Some(true));
+        assert_eq!(outcome.token_valid, Some(false));
+        assert_eq!(outcome.error_code.as_deref(), Some("token_invalid"));
+
+        let log = statements(db);
+        assert!(log.contains("token_invalid"), "error not recorded: {log}");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_provider_records_error() {
+        //bind then drop a listener so nothing answers on the port
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let conn = connection_model(ErpProvider::Quickbooks, ErpProviderType::Api);
+        let db = api_test_db(&conn, credentials_model(None, None, Some(GOOD_TOKEN)));
+
+        let outcome = ConnectionTestService::new(db)
+            .with_qbo_base_url(Some(base_url))
+            .run(&conn)
+            .await
+            .unwrap();
+
+        assert!(!outcome.ok);
+        assert_eq!(outcome.reachable, Some(false));
+        assert_eq!(outcome.error_code.as_deref(), Some("unreachable"));
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_provider() {
+        let conn = connection_model(ErpProvider::Sap, ErpProviderType::Idoc);
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<erp_connection_credentials::Model>::new()])
+            .into_connection();
+
+        let result = ConnectionTestService::new(db).run(&conn).await;
+        assert!(matches!(result, Err(ConnectionTestError::Unsupported)));
+    }
+}
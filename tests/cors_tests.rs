@@ -0,0 +1,178 @@
+//! Tests for the per-tenant CORS middleware
+//!
+//! Run with: cargo test --test cors_tests
+//!
+//! Drives `tenant_cors_middleware` through a router with an in-memory SQLite
+//! `allowed_origin` table; the route stands in for api_token_auth by putting the token's
+//! `AuthenticatedTenant` on its response.
+
+#[path = "../src/config/cors.rs"]
+mod config_cors;
+#[path = "../src/middleware/cors.rs"]
+mod cors;
+#[path = "../src/config/env.rs"]
+mod env;
+#[path = "../src/idempotency/mod.rs"]
+mod idempotency;
+#[path = "../src/pagination/mod.rs"]
+mod pagination;
+#[path = "../src/security/allowed_origins.rs"]
+mod allowed_origins;
+
+//the middleware reaches these through `crate::<module>::...`
+mod config {
+    pub(crate) use super::config_cors as cors;
+}
+mod security {
+    pub(crate) use super::allowed_origins::AllowedOriginService;
+
+    ///the API token's tenant, as api_token_auth puts it on the response
+    #[derive(Clone)]
+    pub struct AuthenticatedTenant(pub Option<i64>);
+}
+
+///the part of the app state the middleware reads
+#[derive(Clone)]
+pub struct AppState {
+    pub db: DatabaseConnection,
+}
+
+use allowed_origins::CreateAllowedOrigin;
+use axum::body::Body;
+use axum::http::{header, HeaderValue, Method, Request};
+use axum::middleware::from_fn_with_state;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use cors::{cors_layer, tenant_cors_middleware};
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, Schema};
+use security::AuthenticatedTenant;
+use std::sync::Once;
+use tower::ServiceExt;
+
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+const TENANT_ORIGIN: &str = "https://app.tenant-one.com";
+const OTHER_ORIGIN: &str = "https://app.nobody.com";
+
+static INIT_CONFIG: Once = Once::new();
+
+/// Helper to set up the global config the CORS settings are read from
+fn init_config() {
+    INIT_CONFIG.call_once(env::init);
+}
+
+/// Helper to create an in-memory database where tenant 1 registered `TENANT_ORIGIN`
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //tenant rows are not needed by these tests
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let sql = schema
+        .create_table_from_entity(entity::allowed_origin::Entity)
+        .to_string(SqliteQueryBuilder)
+        .replace(
+            "\"created_at\" timestamp_with_timezone_text NOT NULL",
+            &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+        )
+        .replace(
+            "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+            &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+        );
+    db.execute_unprepared(&sql).await.unwrap();
+    allowed_origins::AllowedOriginService::new(db.clone())
+        .create(
+            CreateAllowedOrigin {
+                tenant_id: 1,
+                origin: TENANT_ORIGIN.to_string(),
+            },
+            None,
+        )
+        .await
+        .unwrap();
+    db
+}
+
+/// Helper to build a router whose route answers for tenant 1 and already varies on Accept,
+/// with the static layer inside the tenant middleware when `with_static_layer`, as in the app
+async fn app(with_static_layer: bool) -> Router {
+    init_config();
+    let route = get(|| async {
+        let mut response = (([(header::VARY, "accept")]), "ok").into_response();
+        response.extensions_mut().insert(AuthenticatedTenant(Some(1)));
+        response
+    });
+    let mut router = Router::new().route("/items", route);
+    if with_static_layer {
+        router = router.layer(cors_layer());
+    }
+    router.layer(from_fn_with_state(
+        AppState { db: setup_db().await },
+        tenant_cors_middleware,
+    ))
+}
+
+async fn send(router: Router, method: Method, origin: &str) -> Response {
+    let mut request = Request::builder()
+        .method(method.clone())
+        .uri("/items")
+        .header(header::ORIGIN, origin);
+    if method == Method::OPTIONS {
+        request = request.header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET");
+    }
+    router.oneshot(request.body(Body::empty()).unwrap()).await.unwrap()
+}
+
+/// Helper to list the response's Vary names, lowercased
+fn vary(response: &Response) -> Vec<String> {
+    response
+        .headers()
+        .get_all(header::VARY)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|name| name.trim().to_lowercase())
+        .collect()
+}
+
+#[cfg(test)]
+mod vary_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_allowed_actual_request_varies_on_origin() {
+        let response = send(app(false).await, Method::GET, TENANT_ORIGIN).await;
+
+        assert_eq!(
+            response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN),
+            Some(&HeaderValue::from_static(TENANT_ORIGIN))
+        );
+        assert_eq!(vary(&response), vec!["accept", "origin"]);
+    }
+
+    #[tokio::test]
+    async fn test_refused_actual_request_varies_on_origin() {
+        let response = send(app(false).await, Method::GET, OTHER_ORIGIN).await;
+
+        assert!(response.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        assert_eq!(vary(&response), vec!["accept", "origin"]);
+    }
+
+    #[tokio::test]
+    async fn test_preflights_vary_on_origin() {
+        let registered = send(app(false).await, Method::OPTIONS, TENANT_ORIGIN).await;
+        assert!(vary(&registered).contains(&"origin".to_string()));
+
+        let unknown = send(app(false).await, Method::OPTIONS, OTHER_ORIGIN).await;
+        assert!(vary(&unknown).contains(&"origin".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_origin_is_not_repeated_after_the_static_layer() {
+        let response = send(app(true).await, Method::GET, TENANT_ORIGIN).await;
+
+        let origins = vary(&response).iter().filter(|name| *name == "origin").count();
+        assert_eq!(origins, 1);
+    }
+}
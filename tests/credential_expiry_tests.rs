@@ -0,0 +1,189 @@
+//! Tests for finding connections whose certificate or refresh token is about to expire
+//!
+//! Run with: cargo test --test credential_expiry_tests
+//!
+//! Runs against an in-memory SQLite database.
+
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+#[path = "../src/erp_connection_credentials/expiry.rs"]
+mod expiry;
+
+//the gauge is set through `crate::config::metrics`
+mod config {
+    pub(crate) use super::metrics;
+}
+
+use std::sync::Arc;
+
+use clock::FixedClock;
+use entity::connection_identity;
+use entity::sea_orm_active_enums::{
+    ErpConnectionAuthStatus, ErpConnectionAuthTokenType, ErpConnectionStatus, ErpEnvironment,
+    ErpProvider, ErpProviderAuthType, ErpProviderType,
+};
+use expiry::CredentialExpiryService;
+use sea_orm::sea_query::Table;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait, IntoActiveModel,
+    NotSet, Schema, Set,
+};
+
+/// Helper to create an in-memory database with the connection and credentials tables
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    //SQLite has no arrays; `scopes` is left NULL, so a text column will do
+    let mut connections = Table::create();
+    connections.table(connection_identity::Entity);
+    for column in schema.create_table_from_entity(connection_identity::Entity).get_columns() {
+        let mut column = column.clone();
+        if column.get_column_name() == "scopes" {
+            column.text();
+        }
+        connections.col(column);
+    }
+    db.execute(&connections).await.unwrap();
+    db.execute(&schema.create_table_from_entity(entity::erp_connection_credentials::Entity))
+        .await
+        .unwrap();
+    db
+}
+
+/// Helper for the fixed "now" the service is run at
+fn now() -> chrono::DateTime<chrono::Utc> {
+    chrono::DateTime::parse_from_rfc3339("2026-03-01T12:00:00+00:00")
+        .unwrap()
+        .with_timezone(&chrono::Utc)
+}
+
+/// Helper to insert connection `id` with `status`
+async fn insert_connection(db: &DatabaseConnection, id: i64, status: ErpConnectionStatus) {
+    let at = now().into();
+    let mut active = connection_identity::Model {
+        id,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider: ErpProvider::Sap,
+        erp_type: ErpProviderType::Api,
+        erp_auth_type: ErpProviderAuthType::Oauth2,
+        display_name: Some(format!("Connection {}", id)),
+        environment: ErpEnvironment::Production,
+        status,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: at,
+        updated_at: at,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: None,
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+    .into_active_model()
+    .reset_all();
+    active.scopes = NotSet;
+    connection_identity::Entity::insert(active)
+        .exec_without_returning(db)
+        .await
+        .unwrap();
+}
+
+/// Helper to insert credentials for `connection_id` with the given expiries
+async fn insert_credentials(
+    db: &DatabaseConnection,
+    connection_id: i64,
+    cert_expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    refresh_token_expires_at: Option<chrono::DateTime<chrono::Utc>>,
+) {
+    let at = now().into();
+    entity::erp_connection_credentials::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(at),
+        updated_at: Set(at),
+        connection_id: Set(connection_id),
+        token_type: Set(ErpConnectionAuthTokenType::Bearer),
+        enc_scheme: Set("kms-envelope-v1".to_string()),
+        enc_key_id: Set("test-key".to_string()),
+        enc_version: Set(1),
+        cert_expires_at: Set(cert_expires_at.map(Into::into)),
+        refresh_token_expires_at: Set(refresh_token_expires_at.map(Into::into)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+/// Helper to build the service at the fixed "now"
+fn service(db: &DatabaseConnection) -> CredentialExpiryService {
+    CredentialExpiryService::new(db.clone()).with_clock(Arc::new(FixedClock::new(now())))
+}
+
+#[cfg(test)]
+mod credential_expiry_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_cert_expiring_in_three_days_is_listed_within_a_week_only() {
+        let db = setup_db().await;
+        insert_connection(&db, 1, ErpConnectionStatus::Active).await;
+        insert_credentials(&db, 1, Some(now() + chrono::Duration::days(3)), None).await;
+
+        let within_week = service(&db).find_expiring(7).await.unwrap();
+        assert_eq!(within_week.len(), 1);
+        assert_eq!(within_week[0].connection_id, 1);
+        assert_eq!(within_week[0].fields.len(), 1);
+        assert_eq!(within_week[0].fields[0].field, "cert_expires_at");
+        assert!(!within_week[0].fields[0].expired);
+
+        let within_day = service(&db).find_expiring(1).await.unwrap();
+        assert!(within_day.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expired_and_removed_connections() {
+        let db = setup_db().await;
+        insert_connection(&db, 1, ErpConnectionStatus::Active).await;
+        insert_connection(&db, 2, ErpConnectionStatus::Removed).await;
+        insert_connection(&db, 3, ErpConnectionStatus::Active).await;
+        //refresh token already expired, certificate still far off
+        insert_credentials(
+            &db,
+            1,
+            Some(now() + chrono::Duration::days(200)),
+            Some(now() - chrono::Duration::hours(1)),
+        )
+        .await;
+        //removed connections are nobody's problem
+        insert_credentials(&db, 2, Some(now() + chrono::Duration::days(2)), None).await;
+        //nothing ever expires
+        insert_credentials(&db, 3, None, None).await;
+
+        let expiring = service(&db).find_expiring(7).await.unwrap();
+        assert_eq!(expiring.len(), 1);
+        assert_eq!(expiring[0].connection_id, 1);
+        assert_eq!(expiring[0].fields.len(), 1);
+        assert_eq!(expiring[0].fields[0].field, "refresh_token_expires_at");
+        assert!(expiring[0].fields[0].expired);
+    }
+}
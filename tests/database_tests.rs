@@ -0,0 +1,49 @@
+//! Tests for database pool warmup
+//!
+//! Run with: cargo test --test database_tests
+//!
+//! Uses a file-backed SQLite database: an in-memory one is limited to a single
+//! connection, which would not exercise holding several at once.
+
+#[path = "../src/config/env.rs"]
+mod env;
+#[path = "../src/config/database.rs"]
+mod database;
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+
+use database::{pool_stats, warmup};
+use sea_orm::{ConnectOptions, Database, DatabaseConnection};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Helper to connect to a fresh SQLite file with a pool of `max_connections`
+async fn connect(max_connections: u32) -> DatabaseConnection {
+    let path = std::env::temp_dir().join(format!("warmup-{}.db", Uuid::new_v4()));
+    let mut opt = ConnectOptions::new(format!("sqlite://{}?mode=rwc", path.display()));
+    opt.max_connections(max_connections)
+        .min_connections(1)
+        .acquire_timeout(Duration::from_millis(500))
+        .sqlx_logging(false);
+    Database::connect(opt).await.unwrap()
+}
+
+#[cfg(test)]
+mod warmup_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_warmup_opens_min_connections() {
+        let db = connect(4).await;
+        warmup(&db, 3).await.unwrap();
+        //stats are only reported for Postgres pools
+        assert_eq!(pool_stats(&db), None);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_fails_when_pool_is_too_small() {
+        let db = connect(2).await;
+        let err = warmup(&db, 3).await.unwrap_err();
+        assert!(err.to_string().contains("database pool warmup failed"), "{err}");
+    }
+}
@@ -0,0 +1,101 @@
+//! Tests for active enum string conversions
+//!
+//! Run with: cargo test --test enum_strings_tests
+
+use std::fmt::{Debug, Display};
+use std::str::FromStr;
+
+use entity::enum_strings::{ParseEnumError, TenantStatus};
+use entity::sea_orm_active_enums::*;
+use sea_orm::{ActiveEnum, Iterable};
+use serde::Serialize;
+use serde_json::json;
+
+/// Helper asserting that every variant displays and serializes as its database value and
+/// parses back
+fn assert_round_trip<E>()
+where
+    E: ActiveEnum<Value = String> + Iterable + Display + FromStr<Err = ParseEnumError> + PartialEq + Debug + Serialize,
+{
+    for variant in E::iter() {
+        let wire = variant.to_string();
+        assert_eq!(wire, variant.to_value());
+        assert_eq!(serde_json::to_value(&variant).unwrap(), json!(wire));
+        assert_eq!(wire.parse::<E>().unwrap(), variant);
+        assert_eq!(wire.to_uppercase().parse::<E>().unwrap(), variant);
+    }
+    assert!("not-a-value".parse::<E>().is_err());
+}
+
+#[cfg(test)]
+mod round_trip_tests {
+    use super::*;
+
+    #[test]
+    fn test_every_enum_round_trips() {
+        assert_round_trip::<AllowedIpAddressStatusEnum>();
+        assert_round_trip::<ApiTokenStatusEnum>();
+        assert_round_trip::<ConnectionRunStatus>();
+        assert_round_trip::<ConnectionRunType>();
+        assert_round_trip::<Currency>();
+        assert_round_trip::<TenantStatus>();
+        assert_round_trip::<ErpConnectionAuthStatus>();
+        assert_round_trip::<ErpConnectionAuthTokenType>();
+        assert_round_trip::<ErpConnectionReauthReason>();
+        assert_round_trip::<ErpConnectionStatus>();
+        assert_round_trip::<ErpEnvironment>();
+        assert_round_trip::<ErpProvider>();
+        assert_round_trip::<ErpProviderAuthType>();
+        assert_round_trip::<ErpProviderType>();
+        assert_round_trip::<SyncEventCategory>();
+        assert_round_trip::<SyncEventDirection>();
+        assert_round_trip::<SyncEventMethod>();
+        assert_round_trip::<SyncEventStatus>();
+        assert_round_trip::<SystemIdKey>();
+    }
+
+    #[test]
+    fn test_wire_names_are_database_values() {
+        assert_eq!(TenantStatus::Active.to_string(), "active");
+        assert_eq!(ErpProviderType::Desktop.to_string(), "desktop");
+        assert_eq!(ErpProviderAuthType::UsernamePassword.to_string(), "username_password");
+        assert_eq!(" Removed ".parse::<TenantStatus>().unwrap(), TenantStatus::Removed);
+    }
+
+    #[test]
+    fn test_parse_error_lists_expected_values() {
+        let err = "archived".parse::<TenantStatus>().unwrap_err();
+        assert_eq!(err.value, "archived");
+        assert_eq!(err.expected, vec!["active", "removed"]);
+        assert_eq!(err.to_string(), "unknown value `archived`, expected one of: active, removed");
+    }
+}
+
+#[cfg(test)]
+mod serialize_tests {
+    use super::*;
+
+    #[test]
+    fn test_connection_auth_status_serializes_as_snake_case() {
+        let body = serde_json::to_string(&ErpConnectionAuthStatus::NeedsReauth).unwrap();
+        assert_eq!(body, "\"needs_reauth\"");
+        assert!(!body.contains("NeedsReauth"));
+    }
+
+    #[test]
+    fn test_enum_fields_serialize_inside_structs() {
+        #[derive(Serialize)]
+        #[serde(rename_all = "snake_case")]
+        struct Response {
+            auth_status: ErpConnectionAuthStatus,
+            environment: Option<ErpEnvironment>,
+        }
+
+        let body = serde_json::to_value(Response {
+            auth_status: ErpConnectionAuthStatus::NeedsReauth,
+            environment: None,
+        })
+        .unwrap();
+        assert_eq!(body, json!({ "auth_status": "needs_reauth", "environment": null }));
+    }
+}
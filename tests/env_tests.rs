@@ -0,0 +1,76 @@
+//! Tests for config parsing from environment variables
+//!
+//! Run with: cargo test --test env_tests
+//!
+//! Exercises the parse helpers directly, so no environment variables are set.
+
+#[path = "../src/config/env.rs"]
+mod env;
+
+use env::{parse_base_url, parse_qbwc_username_prefix, parse_trusted_proxies};
+
+#[cfg(test)]
+mod trusted_proxies_tests {
+    use super::*;
+
+    #[test]
+    fn test_comma_separated_list_is_trimmed() {
+        assert_eq!(
+            parse_trusted_proxies(Some("10.0.0.2, 10.0.0.3 ,,")),
+            vec!["10.0.0.2".to_string(), "10.0.0.3".to_string()]
+        );
+        assert_eq!(parse_trusted_proxies(Some("10.0.0.2")), vec!["10.0.0.2".to_string()]);
+    }
+
+    #[test]
+    fn test_unset_or_blank_is_empty() {
+        assert!(parse_trusted_proxies(None).is_empty());
+        assert!(parse_trusted_proxies(Some("")).is_empty());
+        assert!(parse_trusted_proxies(Some(" , ")).is_empty());
+    }
+}
+
+#[cfg(test)]
+mod base_url_tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_to_api() {
+        assert_eq!(parse_base_url(None).as_deref(), Some("/api"));
+    }
+
+    #[test]
+    fn test_slashes_are_normalized() {
+        assert_eq!(parse_base_url(Some("/v2")).as_deref(), Some("/v2"));
+        assert_eq!(parse_base_url(Some("v2/")).as_deref(), Some("/v2"));
+        assert_eq!(parse_base_url(Some(" /erp/api/ ")).as_deref(), Some("/erp/api"));
+    }
+
+    #[test]
+    fn test_empty_or_root_mounts_at_root_only() {
+        assert_eq!(parse_base_url(Some("")), None);
+        assert_eq!(parse_base_url(Some("/")), None);
+    }
+}
+
+#[cfg(test)]
+mod qbwc_username_prefix_tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_to_pro_portals() {
+        assert_eq!(parse_qbwc_username_prefix(None), "pro_portals_");
+    }
+
+    #[test]
+    fn test_custom_prefix_is_kept() {
+        assert_eq!(parse_qbwc_username_prefix(Some(" acme-qb_ ")), "acme-qb_");
+        assert_eq!(parse_qbwc_username_prefix(Some("")), "");
+    }
+
+    #[test]
+    fn test_invalid_prefix_falls_back() {
+        assert_eq!(parse_qbwc_username_prefix(Some("acme&co")), "pro_portals_");
+        assert_eq!(parse_qbwc_username_prefix(Some(&"a".repeat(33))), "pro_portals_");
+    }
+}
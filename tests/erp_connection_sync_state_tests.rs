@@ -0,0 +1,164 @@
+//! Tests for sync state poll scheduling
+//!
+//! Run with: cargo test --test erp_connection_sync_state_tests
+//!
+//! `find_due` runs against an in-memory SQLite database.
+
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/erp_connection_sync_state/services.rs"]
+mod services;
+
+use chrono::{Duration, TimeZone, Utc};
+use entity::erp_connection_sync_state;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, Schema, Set,
+};
+use services::{compute_next_poll_at, is_due, ErpConnectionSyncStateService, DEFAULT_POLL_INTERVAL_SECS};
+
+/// Helper to create an in-memory database with the sync state table
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //sync state references connection_identity, which these tests don't need
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    db.execute(&schema.create_table_from_entity(erp_connection_sync_state::Entity))
+        .await
+        .unwrap();
+    db
+}
+
+/// Helper to insert a sync state with the given `next_poll_at`
+async fn insert_state(
+    db: &DatabaseConnection,
+    connection_id: i64,
+    next_poll_at: Option<chrono::DateTime<Utc>>,
+) -> erp_connection_sync_state::Model {
+    let now = Utc::now();
+    erp_connection_sync_state::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        connection_id: Set(connection_id),
+        next_poll_at: Set(next_poll_at.map(Into::into)),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+#[cfg(test)]
+mod poll_schedule_tests {
+    use super::*;
+
+    #[test]
+    fn test_next_poll_uses_connection_interval() {
+        let polled_at = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+
+        assert_eq!(
+            compute_next_poll_at(polled_at, Some(60)),
+            polled_at + Duration::seconds(60)
+        );
+    }
+
+    #[test]
+    fn test_next_poll_falls_back_to_default_interval() {
+        let polled_at = Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap();
+        let expected = polled_at + Duration::seconds(DEFAULT_POLL_INTERVAL_SECS);
+
+        assert_eq!(compute_next_poll_at(polled_at, None), expected);
+        //non-positive intervals would poll in a tight loop
+        assert_eq!(compute_next_poll_at(polled_at, Some(0)), expected);
+        assert_eq!(compute_next_poll_at(polled_at, Some(-5)), expected);
+    }
+
+    #[tokio::test]
+    async fn test_find_due_returns_overdue_and_unscheduled() {
+        let db = setup_db().await;
+        let now = Utc::now();
+        let overdue = insert_state(&db, 1, Some(now - Duration::minutes(5))).await;
+        let unscheduled = insert_state(&db, 2, None).await;
+        let upcoming = insert_state(&db, 3, Some(now + Duration::minutes(5))).await;
+
+        let due = ErpConnectionSyncStateService::new(db)
+            .find_due(now, None)
+            .await
+            .unwrap();
+        let due_ids: Vec<i64> = due.iter().map(|s| s.connection_id).collect();
+
+        assert_eq!(due_ids.len(), 2);
+        assert!(due_ids.contains(&overdue.connection_id));
+        assert!(due_ids.contains(&unscheduled.connection_id));
+        assert!(is_due(&overdue, now));
+        assert!(is_due(&unscheduled, now));
+        assert!(!is_due(&upcoming, now));
+    }
+}
+
+#[cfg(test)]
+mod clock_tests {
+    use super::*;
+    use clock::{Clock, FixedClock};
+    use sea_orm::{EntityTrait, TransactionTrait};
+    use std::sync::Arc;
+
+    fn fixed_clock() -> Arc<FixedClock> {
+        Arc::new(FixedClock::new(Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_lock_is_exclusive_until_lease_expires() {
+        let db = setup_db().await;
+        let clock = fixed_clock();
+        let svc = ErpConnectionSyncStateService::new(db.clone()).with_clock(clock.clone());
+        let state = insert_state(&db, 1, None).await;
+        let lease = Duration::seconds(300);
+
+        let txn = db.begin().await.unwrap();
+        assert!(svc.try_acquire_lock(state.id, "run-a", lease, &txn).await.unwrap());
+        txn.commit().await.unwrap();
+
+        clock.advance(Duration::seconds(299));
+        let txn = db.begin().await.unwrap();
+        assert!(!svc.try_acquire_lock(state.id, "run-b", lease, &txn).await.unwrap());
+        txn.commit().await.unwrap();
+        let held = erp_connection_sync_state::Entity::find_by_id(state.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(held.sync_lock_owner.as_deref(), Some("run-a"));
+        assert!(svc.is_locked(&held));
+
+        //the lease ends exactly `lease` after it was taken
+        clock.advance(Duration::seconds(1));
+        assert!(!svc.is_locked(&held));
+        let txn = db.begin().await.unwrap();
+        assert!(svc.try_acquire_lock(state.id, "run-b", lease, &txn).await.unwrap());
+        txn.commit().await.unwrap();
+        let taken = erp_connection_sync_state::Entity::find_by_id(state.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(taken.sync_lock_owner.as_deref(), Some("run-b"));
+        assert_eq!(taken.sync_lock_until.unwrap(), clock.now() + lease);
+    }
+
+    #[tokio::test]
+    async fn test_backoff_window_follows_clock() {
+        let db = setup_db().await;
+        let clock = fixed_clock();
+        let svc = ErpConnectionSyncStateService::new(db.clone()).with_clock(clock.clone());
+        let mut state = insert_state(&db, 1, None).await;
+        assert!(!svc.is_backing_off(&state));
+
+        state.rate_limit_backoff_until = Some((clock.now() + Duration::seconds(30)).into());
+        assert!(svc.is_backing_off(&state));
+        clock.advance(Duration::seconds(29));
+        assert!(svc.is_backing_off(&state));
+        clock.advance(Duration::seconds(1));
+        assert!(!svc.is_backing_off(&state));
+    }
+}
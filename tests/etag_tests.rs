@@ -0,0 +1,95 @@
+//! Tests for ETags and conditional GET responses
+//!
+//! Run with: cargo test --test etag_tests
+
+#[path = "../src/etag/mod.rs"]
+mod etag;
+
+use axum::body::to_bytes;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::Json;
+use chrono::{DateTime, Duration, FixedOffset};
+use etag::{conditional_response, etag_from_timestamps, if_none_match};
+use serde_json::json;
+
+fn at(rfc3339: &str) -> DateTime<FixedOffset> {
+    DateTime::parse_from_rfc3339(rfc3339).unwrap()
+}
+
+fn if_none_match_header(value: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::IF_NONE_MATCH, HeaderValue::from_str(value).unwrap());
+    headers
+}
+
+#[cfg(test)]
+mod etag_tests {
+    use super::*;
+
+    #[test]
+    fn test_etag_follows_record_and_event_versions() {
+        let record = at("2026-03-01T10:00:00Z");
+        let event = at("2026-03-01T10:00:05Z");
+
+        let etag = etag_from_timestamps(&[Some(&record), Some(&event)]);
+        assert!(etag.starts_with("W/\""));
+        assert_eq!(etag, etag_from_timestamps(&[Some(&record), Some(&event)]));
+
+        //an event update, even within the same second, is a new version
+        let touched = event + Duration::microseconds(1);
+        assert_ne!(etag, etag_from_timestamps(&[Some(&record), Some(&touched)]));
+        //so is a record that has no event yet
+        assert_ne!(etag, etag_from_timestamps(&[Some(&record), None]));
+    }
+
+    #[test]
+    fn test_if_none_match_uses_weak_comparison() {
+        let etag = etag_from_timestamps(&[Some(&at("2026-03-01T10:00:00Z"))]);
+        let strong = etag.trim_start_matches("W/");
+
+        assert!(if_none_match(&if_none_match_header(&etag), &etag));
+        assert!(if_none_match(&if_none_match_header(strong), &etag));
+        assert!(if_none_match(&if_none_match_header(&format!("\"other\", {etag}")), &etag));
+        assert!(if_none_match(&if_none_match_header("*"), &etag));
+        assert!(!if_none_match(&if_none_match_header("W/\"other\""), &etag));
+        assert!(!if_none_match(&HeaderMap::new(), &etag));
+    }
+}
+
+#[cfg(test)]
+mod conditional_response_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_matching_etag_returns_304_without_body() {
+        let etag = etag_from_timestamps(&[Some(&at("2026-03-01T10:00:00Z"))]);
+
+        let response = conditional_response(
+            &if_none_match_header(&etag),
+            &etag,
+            Json(json!({ "uuid": "abc" })),
+        );
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag.as_str());
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert!(body.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_stale_etag_returns_200_with_body() {
+        let stale = etag_from_timestamps(&[Some(&at("2026-03-01T10:00:00Z"))]);
+        let current = etag_from_timestamps(&[Some(&at("2026-03-01T11:00:00Z"))]);
+
+        let response = conditional_response(
+            &if_none_match_header(&stale),
+            &current,
+            Json(json!({ "uuid": "abc" })),
+        );
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::ETAG], current.as_str());
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(serde_json::from_slice::<serde_json::Value>(&body).unwrap()["uuid"], "abc");
+    }
+}
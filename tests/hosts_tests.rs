@@ -0,0 +1,66 @@
+//! Tests for allowed host matching
+//!
+//! Run with: cargo test --test hosts_tests
+
+//hosts.rs reads its list through `super::env`; only `host_matches` is exercised here
+#[path = "../src/config/env.rs"]
+mod env;
+#[path = "../src/config/hosts.rs"]
+mod hosts;
+
+use hosts::host_matches;
+
+fn allowed(hosts: &[&str]) -> Vec<String> {
+    hosts.iter().map(|h| h.to_string()).collect()
+}
+
+#[cfg(test)]
+mod host_matching_tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_match() {
+        let allowed = allowed(&["api.example.com", "localhost"]);
+
+        assert!(host_matches("api.example.com", &allowed));
+        assert!(host_matches("API.Example.com", &allowed));
+        assert!(host_matches("localhost:3000", &allowed));
+        //non-wildcard entries do not cover subdomains
+        assert!(!host_matches("v2.api.example.com", &allowed));
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        let allowed = allowed(&["*.example.com"]);
+
+        assert!(host_matches("a.example.com", &allowed));
+        assert!(host_matches("a.b.example.com:8443", &allowed));
+        assert!(!host_matches("example.com", &allowed));
+        assert!(!host_matches("badexample.com", &allowed));
+    }
+
+    #[test]
+    fn test_leading_dot_is_wildcard() {
+        let allowed = allowed(&[".ddev.site"]);
+
+        assert!(host_matches("erp-proxy-server.ddev.site", &allowed));
+        assert!(!host_matches("ddev.site", &allowed));
+    }
+
+    #[test]
+    fn test_non_matching_host() {
+        let allowed = allowed(&["api.example.com", "*.example.com"]);
+
+        assert!(!host_matches("evil.com", &allowed));
+        assert!(!host_matches("example.com.evil.com", &allowed));
+        assert!(!host_matches("", &allowed));
+    }
+
+    #[test]
+    fn test_port_stripped_from_ipv6_literal() {
+        let allowed = allowed(&["[::1]"]);
+
+        assert!(host_matches("[::1]:3000", &allowed));
+        assert!(host_matches("[::1]", &allowed));
+    }
+}
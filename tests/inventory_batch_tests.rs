@@ -0,0 +1,314 @@
+//! Tests for batched inventory upserts
+//!
+//! Run with: cargo test --test inventory_batch_tests
+//!
+//! Drives `InventoryBatchService::upsert` against an in-memory SQLite database, so records,
+//! events, the connection run, sync events and outbox messages are real rows. The connection
+//! itself is only passed in (not persisted).
+
+#[path = "../src/inventory_records/batch.rs"]
+mod batch;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/inventory_records/events_services.rs"]
+mod events_services;
+#[path = "../src/inventory_records/services.rs"]
+mod services;
+#[path = "../src/inventory_records/upsert.rs"]
+mod upsert;
+
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+#[path = "../src/connection_run/services.rs"]
+mod connection_run_services;
+#[path = "../src/outbox/services.rs"]
+mod outbox_services;
+#[path = "../src/sync_event/services.rs"]
+mod sync_event_services;
+
+mod config {
+    pub(crate) use super::metrics;
+}
+mod connection_run {
+    pub(crate) use super::connection_run_services as services;
+}
+mod outbox {
+    pub(crate) use super::outbox_services as services;
+}
+mod sync_event {
+    pub(crate) use super::sync_event_services as services;
+}
+
+use batch::{BatchItem, BatchItemStatus, InventoryBatchService};
+use entity::connection_identity;
+use entity::sea_orm_active_enums::{
+    ConnectionRunStatus, ConnectionRunType, Currency, ErpConnectionAuthStatus,
+    ErpConnectionStatus, ErpEnvironment, ErpProvider, ErpProviderAuthType, ErpProviderType,
+    SyncEventMethod, SyncEventStatus, SystemIdKey,
+};
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait, PaginatorTrait,
+    QueryFilter, Schema,
+};
+use serde_json::json;
+use upsert::{system_id_key_for, InventoryItem};
+
+const NEW_UUID: &str = "(randomblob(16))";
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+/// Helper to create an in-memory database with the tables a batch writes.
+/// Postgres fills uuid/created_at/updated_at by default, so the same defaults are added here.
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //tenant/connection_identity are not needed by these tests
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let tables = [
+        schema.create_table_from_entity(entity::inventory_record::Entity),
+        schema.create_table_from_entity(entity::inventory_record_event::Entity),
+        schema.create_table_from_entity(entity::connection_run::Entity),
+        schema.create_table_from_entity(entity::sync_event::Entity),
+        schema.create_table_from_entity(entity::outbox::Entity),
+    ];
+    for table in tables {
+        let sql = table
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+    }
+    db
+}
+
+/// Helper to build the SAP connection the batch is pushed to (not persisted)
+fn connection() -> connection_identity::Model {
+    let now = chrono::Utc::now().into();
+    connection_identity::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider: ErpProvider::Sap,
+        erp_type: ErpProviderType::Api,
+        erp_auth_type: ErpProviderAuthType::UsernamePassword,
+        display_name: None,
+        environment: ErpEnvironment::Sandbox,
+        status: ErpConnectionStatus::Active,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: now,
+        updated_at: now,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: None,
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+}
+
+/// Helper to build a valid batch item
+fn item(system_id: &str, name: &str, price: i32, qty: i32) -> BatchItem {
+    BatchItem {
+        system_id: system_id.to_string(),
+        item: Ok(InventoryItem {
+            system_id_key: SystemIdKey::Sapo,
+            system_id: system_id.to_string(),
+            name: Some(name.to_string()),
+            description: None,
+            price: Some(price),
+            currency: Some(Currency::Usd),
+            qty: Some(qty),
+            qty_milli: Some(qty as i64 * 1000),
+            external_code: None,
+            attributes: None,
+            raw: json!({"ItemCode": system_id, "ItemName": name, "Price": price, "Qty": qty}),
+        }),
+    }
+}
+
+mod batch_upsert_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn batch_reports_created_updated_unchanged_and_failed_items() {
+        let db = setup_db().await;
+        let conn = connection();
+        let service = InventoryBatchService::new(db.clone());
+        //existing records: SKU-2 and SKU-3
+        service
+            .upsert(&conn, vec![item("SKU-2", "Bolt", 100, 5), item("SKU-3", "Nut", 20, 50)], None)
+            .await
+            .unwrap();
+
+        let outcome = service
+            .upsert(
+                &conn,
+                vec![
+                    item("SKU-1", "Washer", 10, 200),
+                    item("SKU-2", "Bolt", 120, 5),
+                    item("SKU-3", "Nut", 20, 50),
+                    BatchItem {
+                        system_id: "SKU-4".to_string(),
+                        item: Err("price: must not be negative".to_string()),
+                    },
+                    item("SKU-1", "Washer", 11, 200),
+                ],
+                None,
+            )
+            .await
+            .unwrap();
+
+        let statuses: Vec<_> = outcome.items.iter().map(|i| (i.index, i.system_id.as_str(), i.status)).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                (0, "SKU-1", BatchItemStatus::Created),
+                (1, "SKU-2", BatchItemStatus::Updated),
+                (2, "SKU-3", BatchItemStatus::Unchanged),
+                (3, "SKU-4", BatchItemStatus::Failed),
+                (4, "SKU-1", BatchItemStatus::Failed),
+            ]
+        );
+        assert_eq!(outcome.items[3].error.as_deref(), Some("price: must not be negative"));
+        assert!(outcome.items[4].error.is_some(), "a repeated system_id fails");
+        assert!(outcome.items[..3].iter().all(|i| i.inventory_record_uuid.is_some()));
+
+        //SKU-4 was never written; SKU-2 kept its record and got a second event
+        assert_eq!(entity::inventory_record::Entity::find().count(&db).await.unwrap(), 3);
+        assert_eq!(entity::inventory_record_event::Entity::find().count(&db).await.unwrap(), 4);
+
+        assert_eq!(outcome.run.run_type, ConnectionRunType::Batch);
+        assert_eq!(outcome.run.status, ConnectionRunStatus::Error);
+        assert_eq!(outcome.run.error_message.as_deref(), Some("2 of 5 items failed"));
+        assert_eq!(outcome.event.status, SyncEventStatus::Error);
+        assert_eq!(outcome.event.connection_run_id, Some(outcome.run.id));
+
+        //one child event per written record: SKU-1 created, SKU-2 updated
+        let children = entity::sync_event::Entity::find()
+            .filter(entity::sync_event::Column::ConnectionRunId.eq(outcome.run.id))
+            .filter(entity::sync_event::Column::Id.ne(outcome.event.id))
+            .all(&db)
+            .await
+            .unwrap();
+        let mut methods: Vec<_> = children.iter().map(|e| e.sync_event_method.clone()).collect();
+        methods.sort_by_key(|m| format!("{m:?}"));
+        assert_eq!(methods, vec![SyncEventMethod::Create, SyncEventMethod::Update]);
+
+        //2 messages for the first batch, 2 for this one
+        assert_eq!(entity::outbox::Entity::find().count(&db).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn batch_without_failures_records_a_successful_run() {
+        let db = setup_db().await;
+
+        let outcome = InventoryBatchService::new(db.clone())
+            .upsert(&connection(), vec![item("SKU-1", "Washer", 10, 200)], None)
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.items[0].status, BatchItemStatus::Created);
+        assert_eq!(outcome.run.status, ConnectionRunStatus::Success);
+        assert_eq!(outcome.run.error_message, None);
+        assert_eq!(outcome.event.status, SyncEventStatus::Success);
+        assert_eq!(outcome.event.last_error, None);
+    }
+
+    #[test]
+    fn quickbooks_desktop_connections_take_no_batches() {
+        let mut conn = connection();
+        assert_eq!(system_id_key_for(&conn), Some(SystemIdKey::Sapo));
+
+        conn.erp_provider = ErpProvider::Quickbooks;
+        conn.erp_type = ErpProviderType::Desktop;
+        assert_eq!(system_id_key_for(&conn), None);
+    }
+}
+
+mod merged_record_tests {
+    use super::*;
+    use services::InventoryRecordService;
+
+    /// Helper to upsert one item and return its status and record uuid
+    async fn upsert_one(db: &DatabaseConnection, item: BatchItem) -> (BatchItemStatus, uuid::Uuid) {
+        let outcome = InventoryBatchService::new(db.clone())
+            .upsert(&connection(), vec![item], None)
+            .await
+            .unwrap();
+        (outcome.items[0].status, outcome.items[0].inventory_record_uuid.unwrap())
+    }
+
+    #[tokio::test]
+    async fn merged_duplicate_items_write_to_the_primary() {
+        let db = setup_db().await;
+        let (_, primary) = upsert_one(&db, item("SKU-1", "Washer", 10, 200)).await;
+        let (_, duplicate) = upsert_one(&db, item("SKU-2", "Washer", 10, 200)).await;
+        InventoryRecordService::new(db.clone())
+            .merge(primary, &[duplicate], None)
+            .await
+            .unwrap();
+
+        //a changed pull of the duplicate's system_id is a new event on the primary
+        let (status, uuid) = upsert_one(&db, item("SKU-2", "Washer", 12, 200)).await;
+        assert_eq!((status, uuid), (BatchItemStatus::Updated, primary));
+        assert_eq!(entity::inventory_record::Entity::find().count(&db).await.unwrap(), 2);
+
+        //and once stored there, the same values write nothing
+        let (status, uuid) = upsert_one(&db, item("SKU-2", "Washer", 12, 200)).await;
+        assert_eq!((status, uuid), (BatchItemStatus::Unchanged, primary));
+
+        let deleted = entity::inventory_record::Entity::find()
+            .filter(entity::inventory_record::Column::Uuid.eq(duplicate))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let events_on_deleted = entity::inventory_record_event::Entity::find()
+            .filter(entity::inventory_record_event::Column::InventoryRecordId.eq(deleted.id))
+            .count(&db)
+            .await
+            .unwrap();
+        assert_eq!(events_on_deleted, 0);
+    }
+
+    #[tokio::test]
+    async fn merge_chains_are_followed_to_the_live_primary() {
+        let db = setup_db().await;
+        let (_, first) = upsert_one(&db, item("SKU-1", "Washer", 10, 200)).await;
+        let (_, second) = upsert_one(&db, item("SKU-2", "Washer", 10, 200)).await;
+        let (_, last) = upsert_one(&db, item("SKU-3", "Washer", 10, 200)).await;
+        let service = InventoryRecordService::new(db.clone());
+        service.merge(second, &[first], None).await.unwrap();
+        service.merge(last, &[second], None).await.unwrap();
+
+        let (status, uuid) = upsert_one(&db, item("SKU-1", "Washer", 15, 200)).await;
+        assert_eq!((status, uuid), (BatchItemStatus::Updated, last));
+        assert_eq!(entity::inventory_record::Entity::find().count(&db).await.unwrap(), 3);
+    }
+}
@@ -0,0 +1,320 @@
+//! Tests for the inventory export (CSV, JSON, NDJSON)
+//!
+//! Run with: cargo test --test inventory_export_tests
+//!
+//! The streaming tests use an in-memory SQLite database.
+
+#[path = "../src/inventory_records/export.rs"]
+mod export;
+
+use entity::sea_orm_active_enums::{Currency, SystemIdKey};
+use entity::{inventory_record, inventory_record_event};
+use export::{
+    csv_row, escape_field, format_cents, format_milli, inventory_csv_stream,
+    inventory_export_stream, ExportFormat, CSV_HEADER,
+};
+use futures_util::StreamExt;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, Schema, Set,
+};
+
+/// Helper to create an in-memory database with the inventory tables
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //inventory tables reference tenant/connection_identity, which these tests don't need
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    db.execute(&schema.create_table_from_entity(inventory_record::Entity))
+        .await
+        .unwrap();
+    db.execute(&schema.create_table_from_entity(inventory_record_event::Entity))
+        .await
+        .unwrap();
+    db
+}
+
+/// Helper to insert a record for a tenant/connection
+async fn insert_record(
+    db: &DatabaseConnection,
+    tenant_id: i64,
+    connection_id: i64,
+    system_id: &str,
+) -> inventory_record::Model {
+    let now = chrono::Utc::now();
+    inventory_record::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        tenant_id: Set(tenant_id),
+        originating_connection_id: Set(connection_id),
+        original_record_body: Set(None),
+        system_id_key: Set(SystemIdKey::Qbd),
+        system_id: Set(system_id.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+/// Helper to insert an event for a record, `age_secs` in the past
+async fn insert_event(
+    db: &DatabaseConnection,
+    record: &inventory_record::Model,
+    name: &str,
+    price: i32,
+    age_secs: i64,
+) {
+    let at = chrono::Utc::now() - chrono::Duration::seconds(age_secs);
+    inventory_record_event::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(at.into()),
+        updated_at: Set(at.into()),
+        inventory_record_id: Set(record.id),
+        connection_id: Set(record.originating_connection_id),
+        price: Set(Some(price)),
+        currency: Set(Some(Currency::Usd)),
+        name: Set(Some(name.to_string())),
+        qty: Set(Some(4)),
+        external_code: Set(Some("Widgets:Blue".to_string())),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+#[cfg(test)]
+mod csv_format_tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_field() {
+        assert_eq!(escape_field("plain"), "plain");
+        assert_eq!(escape_field("Bolt, 3/8\""), "\"Bolt, 3/8\"\"\"");
+        assert_eq!(escape_field("two\nlines"), "\"two\nlines\"");
+    }
+
+    #[test]
+    fn test_format_cents() {
+        assert_eq!(format_cents(1250), "12.50");
+        assert_eq!(format_cents(5), "0.05");
+        assert_eq!(format_cents(-199), "-1.99");
+    }
+
+    #[test]
+    fn test_format_milli() {
+        assert_eq!(format_milli(10_500), "10.5");
+        assert_eq!(format_milli(4_000), "4");
+        assert_eq!(format_milli(125), "0.125");
+        assert_eq!(format_milli(-2_250), "-2.25");
+    }
+
+    #[test]
+    fn test_row_without_event_keeps_columns() {
+        let now = chrono::Utc::now().into();
+        let record = inventory_record::Model {
+            id: 1,
+            uuid: uuid::Uuid::new_v4(),
+            created_at: now,
+            updated_at: now,
+            tenant_id: 1,
+            originating_connection_id: 1,
+            original_record_body: None,
+            system_id_key: SystemIdKey::Qbd,
+            system_id: "80000001-1".to_string(),
+            deleted_at: None,
+            merged_into_id: None,
+        };
+
+        assert_eq!(csv_row(&record, None), "80000001-1,,,,,\r\n");
+    }
+}
+
+#[cfg(test)]
+mod csv_stream_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stream_writes_header_and_quoted_rows() {
+        let db = setup_db().await;
+        let record = insert_record(&db, 1, 10, "80000001-1").await;
+        //only the latest event is exported
+        insert_event(&db, &record, "Old name", 100, 60).await;
+        insert_event(&db, &record, "Bolt, hex \"large\"", 1250, 0).await;
+        //other tenants' records are never exported
+        let other = insert_record(&db, 2, 10, "80000002-1").await;
+        insert_event(&db, &other, "Other tenant", 1, 0).await;
+
+        let chunks: Vec<String> = inventory_csv_stream(db, 1, 10)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        let csv = chunks.concat();
+
+        assert!(csv.starts_with(CSV_HEADER));
+        assert_eq!(CSV_HEADER, "system_id,name,price,qty,currency,external_code\r\n");
+        assert_eq!(
+            csv,
+            format!(
+                "{}80000001-1,\"Bolt, hex \"\"large\"\"\",12.50,4,usd,Widgets:Blue\r\n",
+                CSV_HEADER
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_with_no_records_is_header_only() {
+        let db = setup_db().await;
+
+        let chunks: Vec<String> = inventory_csv_stream(db, 1, 10)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+
+        assert_eq!(chunks.concat(), CSV_HEADER);
+    }
+}
+
+#[cfg(test)]
+mod export_format_tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    /// Helper to collect a whole export in `format` for tenant 1 / connection 10
+    async fn export(db: DatabaseConnection, format: ExportFormat) -> String {
+        let chunks: Vec<String> = inventory_export_stream(db, 1, 10, format)
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        chunks.concat()
+    }
+
+    /// Helper to seed two records, the second without any event
+    async fn seed(db: &DatabaseConnection) {
+        let record = insert_record(db, 1, 10, "80000001-1").await;
+        insert_event(db, &record, "Bolt", 1250, 0).await;
+        insert_record(db, 1, 10, "80000002-1").await;
+    }
+
+    #[test]
+    fn test_accept_selects_format_and_content_type() {
+        let cases = [
+            ("text/csv", ExportFormat::Csv, "text/csv; charset=utf-8"),
+            ("application/json", ExportFormat::Json, "application/json"),
+            ("application/x-ndjson", ExportFormat::Ndjson, "application/x-ndjson"),
+        ];
+        for (accept, format, content_type) in cases {
+            let negotiated = ExportFormat::from_accept(Some(accept)).unwrap();
+            assert_eq!(negotiated, format, "Accept: {}", accept);
+            assert_eq!(negotiated.content_type(), content_type);
+        }
+    }
+
+    #[test]
+    fn test_missing_or_wildcard_accept_defaults_to_csv() {
+        assert_eq!(ExportFormat::from_accept(None), Some(ExportFormat::Csv));
+        assert_eq!(ExportFormat::from_accept(Some("")), Some(ExportFormat::Csv));
+        assert_eq!(ExportFormat::from_accept(Some("*/*")), Some(ExportFormat::Csv));
+        //a browser: html isn't offered, the wildcard is
+        assert_eq!(
+            ExportFormat::from_accept(Some("text/html,application/xhtml+xml,*/*;q=0.8")),
+            Some(ExportFormat::Csv)
+        );
+    }
+
+    #[test]
+    fn test_accept_quality_values() {
+        assert_eq!(
+            ExportFormat::from_accept(Some("text/csv;q=0.5, application/x-ndjson")),
+            Some(ExportFormat::Ndjson)
+        );
+        assert_eq!(
+            ExportFormat::from_accept(Some("application/json;q=0, text/csv;q=0.1")),
+            Some(ExportFormat::Csv)
+        );
+        assert_eq!(
+            ExportFormat::from_accept(Some("Application/JSON; charset=utf-8")),
+            Some(ExportFormat::Json)
+        );
+    }
+
+    #[test]
+    fn test_unsupported_accept_is_refused() {
+        assert_eq!(ExportFormat::from_accept(Some("application/xml")), None);
+        assert_eq!(ExportFormat::from_accept(Some("text/csv;q=0")), None);
+    }
+
+    #[tokio::test]
+    async fn test_csv_export_shape() {
+        let db = setup_db().await;
+        seed(&db).await;
+
+        let csv = export(db, ExportFormat::Csv).await;
+
+        assert_eq!(
+            csv,
+            format!(
+                "{}80000001-1,Bolt,12.50,4,usd,Widgets:Blue\r\n80000002-1,,,,,\r\n",
+                CSV_HEADER
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_json_export_is_one_array_of_rows() {
+        let db = setup_db().await;
+        seed(&db).await;
+
+        let body = export(db, ExportFormat::Json).await;
+        let rows: Value = serde_json::from_str(&body).unwrap();
+
+        assert_eq!(
+            rows,
+            json!([
+                {
+                    "system_id": "80000001-1",
+                    "name": "Bolt",
+                    "price": "12.50",
+                    "qty": "4",
+                    "currency": "usd",
+                    "external_code": "Widgets:Blue"
+                },
+                {
+                    "system_id": "80000002-1",
+                    "name": null,
+                    "price": null,
+                    "qty": null,
+                    "currency": null,
+                    "external_code": null
+                }
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_empty_json_export_is_empty_array() {
+        let db = setup_db().await;
+
+        assert_eq!(export(db, ExportFormat::Json).await, "[]");
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_export_is_one_object_per_line() {
+        let db = setup_db().await;
+        seed(&db).await;
+
+        let body = export(db, ExportFormat::Ndjson).await;
+
+        assert!(body.ends_with('\n'));
+        let rows: Vec<Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["system_id"], "80000001-1");
+        assert_eq!(rows[0]["price"], "12.50");
+        assert_eq!(rows[1]["system_id"], "80000002-1");
+        assert_eq!(rows[1]["name"], Value::Null);
+    }
+}
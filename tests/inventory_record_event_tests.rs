@@ -0,0 +1,313 @@
+//! Tests for inventory record event persistence
+//!
+//! Run with: cargo test --test inventory_record_event_tests
+//!
+//! Uses an in-memory SQLite database so JSON columns go through a real encode/decode.
+//! Search is checked on its Postgres SQL, since the search column only exists there.
+
+#[path = "../src/inventory_records/events_services.rs"]
+mod events_services;
+
+use entity::inventory_record_event;
+use events_services::{
+    ChangedField, CreateInventoryRecordEvent, InventoryRecordEventFilter,
+    InventoryRecordEventService, UpdateInventoryRecordEvent,
+};
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, EntityTrait, Schema};
+use serde_json::json;
+
+/// Helper to create an in-memory database with the inventory_record_event table.
+/// Postgres fills uuid/created_at/updated_at by default, so the same defaults are added here.
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //inventory_record/connection_identity are not needed by these tests
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let sql = schema
+        .create_table_from_entity(inventory_record_event::Entity)
+        .to_string(SqliteQueryBuilder)
+        .replace(
+            "\"uuid\" uuid_text NOT NULL UNIQUE",
+            "\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT (randomblob(16))",
+        )
+        .replace(
+            "\"created_at\" timestamp_with_timezone_text NOT NULL",
+            "\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))",
+        )
+        .replace(
+            "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+            "\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))",
+        );
+    db.execute_unprepared(&sql).await.unwrap();
+    db
+}
+
+fn create_request(attributes: Option<serde_json::Value>) -> CreateInventoryRecordEvent {
+    CreateInventoryRecordEvent {
+        inventory_record_id: 1,
+        connection_id: 1,
+        original_record_body: None,
+        price: Some(1999),
+        currency: None,
+        name: Some("Widget".to_string()),
+        description: None,
+        attributes,
+        qty: Some(42),
+        external_code: None,
+        qty_milli: Some(42_000),
+    }
+}
+
+#[cfg(test)]
+mod attributes_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_attributes_round_trip() {
+        let db = setup_db().await;
+        let service = InventoryRecordEventService::new(db.clone());
+        let attributes = json!({
+            "item_type": "inventory",
+            "manufacturer_part_number": "MPN-42",
+            "tags": ["blue", "metric"],
+        });
+
+        let created = service
+            .create(create_request(Some(attributes.clone())), None)
+            .await
+            .unwrap();
+        let stored = inventory_record_event::Entity::find_by_id(created.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.attributes, Some(attributes));
+    }
+
+    #[tokio::test]
+    async fn test_update_replaces_attributes() {
+        let db = setup_db().await;
+        let service = InventoryRecordEventService::new(db.clone());
+        let created = service
+            .create(create_request(Some(json!({ "item_type": "service" }))), None)
+            .await
+            .unwrap();
+
+        let replacement = json!({ "item_type": "service", "manufacturer_part_number": "SVC-1" });
+        service
+            .update_by_id(
+                created.id,
+                UpdateInventoryRecordEvent {
+                    original_record_body: None,
+                    price: None,
+                    currency: None,
+                    name: None,
+                    description: None,
+                    attributes: Some(replacement.clone()),
+                    qty: None,
+                    external_code: None,
+                    qty_milli: None,
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let stored = inventory_record_event::Entity::find_by_id(created.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.attributes, Some(replacement));
+        //untouched fields survive the patch
+        assert_eq!(stored.name.as_deref(), Some("Widget"));
+    }
+}
+
+#[cfg(test)]
+mod latest_for_records_tests {
+    use super::*;
+
+    /// Helper to write an event for `record_id` named `name`
+    async fn event(service: &InventoryRecordEventService, record_id: i64, name: &str) -> i64 {
+        let mut request = create_request(None);
+        request.inventory_record_id = record_id;
+        request.name = Some(name.to_string());
+        service.create(request, None).await.unwrap().id
+    }
+
+    #[tokio::test]
+    async fn test_latest_event_per_record() {
+        let db = setup_db().await;
+        let service = InventoryRecordEventService::new(db.clone());
+        event(&service, 1, "Widget v1").await;
+        event(&service, 2, "Gadget v1").await;
+        event(&service, 1, "Widget v2").await;
+        event(&service, 2, "Gadget v2").await;
+        let latest_widget = event(&service, 1, "Widget v3").await;
+        event(&service, 3, "Other record").await;
+
+        let latest = service.latest_for_records(&[1, 2, 4], None).await.unwrap();
+
+        assert_eq!(latest.len(), 2);
+        assert_eq!(latest[&1].id, latest_widget);
+        assert_eq!(latest[&1].name.as_deref(), Some("Widget v3"));
+        assert_eq!(latest[&2].name.as_deref(), Some("Gadget v2"));
+        //records that weren't asked for, or have no events, are left out
+        assert!(!latest.contains_key(&3));
+        assert!(!latest.contains_key(&4));
+    }
+
+    #[tokio::test]
+    async fn test_no_records_no_query() {
+        let db = setup_db().await;
+        let service = InventoryRecordEventService::new(db);
+
+        assert!(service.latest_for_records(&[], None).await.unwrap().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod filter_tests {
+    use super::*;
+    use chrono::{DateTime, TimeZone, Utc};
+    use sea_orm::{ActiveModelTrait, Set};
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 1, hour, 0, 0).unwrap()
+    }
+
+    /// Helper to write an event of record `record_id` on connection 1, created at `hour`
+    async fn event_at(db: &DatabaseConnection, record_id: i64, hour: u32, price: i32) -> i64 {
+        inventory_record_event::ActiveModel {
+            inventory_record_id: Set(record_id),
+            connection_id: Set(1),
+            created_at: Set(at(hour).into()),
+            updated_at: Set(at(hour).into()),
+            price: Set(Some(price)),
+            name: Set(Some("Widget".to_string())),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap()
+        .id
+    }
+
+    async fn filtered_ids(db: &DatabaseConnection, filter: InventoryRecordEventFilter) -> Vec<i64> {
+        InventoryRecordEventService::new(db.clone())
+            .get_all(1, 20, Some(filter), None)
+            .await
+            .unwrap()
+            .items
+            .into_iter()
+            .map(|e| e.id)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_date_range_returns_only_events_within_it() {
+        let db = setup_db().await;
+        event_at(&db, 1, 8, 100).await;
+        let at_since = event_at(&db, 1, 10, 100).await;
+        let inside = event_at(&db, 2, 11, 100).await;
+        event_at(&db, 1, 12, 100).await;
+        event_at(&db, 2, 13, 100).await;
+
+        let ids = filtered_ids(
+            &db,
+            InventoryRecordEventFilter {
+                connection_id: Some(1),
+                since: Some(at(10)),
+                until: Some(at(12)),
+                ..Default::default()
+            },
+        )
+        .await;
+
+        //since is inclusive, until exclusive; newest first
+        assert_eq!(ids, vec![inside, at_since]);
+    }
+
+    #[tokio::test]
+    async fn test_changed_price_skips_unchanged_events() {
+        let db = setup_db().await;
+        let first = event_at(&db, 1, 8, 100).await;
+        event_at(&db, 1, 9, 100).await;
+        let repriced = event_at(&db, 1, 10, 150).await;
+
+        let ids = filtered_ids(
+            &db,
+            InventoryRecordEventFilter {
+                changed: vec![ChangedField::Price],
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(ids, vec![repriced, first]);
+
+        //the name never changes after the first event
+        let ids = filtered_ids(
+            &db,
+            InventoryRecordEventFilter {
+                changed: vec![ChangedField::Name],
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(ids, vec![first]);
+    }
+}
+
+mod search_tests {
+    use super::*;
+    use events_services::{full_text_query, MAX_SEARCH_LIMIT};
+    use sea_orm::{DbBackend, QueryTrait};
+
+    /// Helper to render the Postgres SQL `search` runs (the search column and ILIKE are
+    /// Postgres-only, so it isn't run against SQLite)
+    fn search_sql(q: &str, limit: u64) -> String {
+        InventoryRecordEventService::search_query(7, q, limit)
+            .build(DbBackend::Postgres)
+            .to_string()
+    }
+
+    #[test]
+    fn test_multi_word_search_ranks_exact_name_match_first() {
+        let sql = search_sql(" Hex Bolt ", 20);
+
+        //every word is required, as a prefix
+        assert!(sql.contains(r#""inventory_record_event"."search_vector" @@ to_tsquery('english', 'hex:* & bolt:*')"#));
+        //an exact (case-insensitive) name match sorts ahead of the full-text rank
+        let exact = sql
+            .find(r#"ORDER BY lower("inventory_record_event"."name") = lower('Hex Bolt') DESC"#)
+            .expect("orders by exact name match");
+        let rank = sql
+            .find(r#"ts_rank("inventory_record_event"."search_vector", to_tsquery('english', 'hex:* & bolt:*')) DESC"#)
+            .expect("orders by full-text rank");
+        assert!(exact < rank);
+        assert!(!sql.contains("ILIKE"));
+        //only each record's newest event on the connection is searched
+        assert!(sql.contains(r#"GROUP BY "inventory_record_id""#));
+        assert!(sql.contains(r#""inventory_record_event"."connection_id" = 7"#));
+        assert!(sql.ends_with("LIMIT 20"));
+    }
+
+    #[test]
+    fn test_short_query_falls_back_to_ilike() {
+        let sql = search_sql("m8", 500);
+
+        assert!(sql.contains(r#""inventory_record_event"."name" ILIKE '%m8%'"#));
+        assert!(sql.contains(r#""inventory_record_event"."description" ILIKE '%m8%'"#));
+        assert!(!sql.contains("to_tsquery"));
+        assert!(sql.ends_with(&format!("LIMIT {MAX_SEARCH_LIMIT}")));
+    }
+
+    #[test]
+    fn test_full_text_query_keeps_only_words() {
+        assert_eq!(full_text_query("hex-bolt M8!"), Some("hex:* & bolt:* & m8:*".to_string()));
+        assert_eq!(full_text_query("  ab "), None);
+        assert_eq!(full_text_query("&|!"), None);
+    }
+}
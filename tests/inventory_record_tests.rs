@@ -0,0 +1,188 @@
+//! Tests for inventory record services
+//!
+//! Run with: cargo test --test inventory_record_tests
+//!
+//! Uses an in-memory SQLite database.
+
+#[path = "../src/inventory_records/services.rs"]
+mod services;
+
+use entity::sea_orm_active_enums::SystemIdKey;
+use entity::{inventory_record, inventory_record_event};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait,
+    QueryFilter, Schema, Set,
+};
+use serde_json::json;
+use services::{InventoryRecordError, InventoryRecordFilter, InventoryRecordService};
+
+/// Helper to create an in-memory database with the inventory tables
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //inventory tables reference tenant/connection_identity, which these tests don't need
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    db.execute(&schema.create_table_from_entity(inventory_record::Entity))
+        .await
+        .unwrap();
+    db.execute(&schema.create_table_from_entity(inventory_record_event::Entity))
+        .await
+        .unwrap();
+    db
+}
+
+/// Helper to insert a record of a tenant
+async fn insert_record(
+    db: &DatabaseConnection,
+    tenant_id: i64,
+    system_id: &str,
+    body: Option<serde_json::Value>,
+) -> inventory_record::Model {
+    let now = chrono::Utc::now();
+    inventory_record::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        tenant_id: Set(tenant_id),
+        originating_connection_id: Set(1),
+        original_record_body: Set(body),
+        system_id_key: Set(SystemIdKey::Qbd),
+        system_id: Set(system_id.to_string()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+/// Helper to insert `count` events for a record
+async fn insert_events(db: &DatabaseConnection, record: &inventory_record::Model, count: usize) {
+    for i in 0..count {
+        let at = chrono::Utc::now() - chrono::Duration::seconds(i as i64);
+        inventory_record_event::ActiveModel {
+            uuid: Set(uuid::Uuid::new_v4()),
+            created_at: Set(at.into()),
+            updated_at: Set(at.into()),
+            inventory_record_id: Set(record.id),
+            connection_id: Set(record.originating_connection_id),
+            name: Set(Some(format!("{} v{}", record.system_id, i))),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+}
+
+async fn event_record_ids(db: &DatabaseConnection) -> Vec<i64> {
+    inventory_record_event::Entity::find()
+        .all(db)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|e| e.inventory_record_id)
+        .collect()
+}
+
+#[cfg(test)]
+mod list_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_tenant_filter_hides_other_tenants_records() {
+        let db = setup_db().await;
+        let own = insert_record(&db, 1, "80000001-1", None).await;
+        insert_record(&db, 2, "80000002-1", None).await;
+        //what `GET /inventory-records` builds for a token scoped to tenant 1
+        let filter = InventoryRecordFilter {
+            tenant_id: Some(1),
+            originating_connection_id: None,
+            system_id_key: None,
+        };
+
+        let listed = InventoryRecordService::new(db.clone())
+            .get_all(1, 20, Some(filter), None)
+            .await
+            .unwrap();
+
+        assert_eq!(listed.total, 1);
+        assert_eq!(listed.items.iter().map(|r| r.id).collect::<Vec<_>>(), vec![own.id]);
+    }
+}
+
+#[cfg(test)]
+mod merge_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_merge_moves_events_and_removes_duplicates() {
+        let db = setup_db().await;
+        let primary = insert_record(&db, 1, "80000001-1", None).await;
+        let first = insert_record(&db, 1, "80000002-1", Some(json!({"ListID": "80000002-1"}))).await;
+        let second = insert_record(&db, 1, "80000003-1", None).await;
+        insert_events(&db, &primary, 1).await;
+        insert_events(&db, &first, 2).await;
+        insert_events(&db, &second, 1).await;
+
+        let merged = InventoryRecordService::new(db.clone())
+            .merge(primary.uuid, &[first.uuid, second.uuid], Some(1))
+            .await
+            .unwrap();
+
+        assert_eq!(merged.events_moved, 3);
+        assert_eq!(merged.merged_uuids, vec![first.uuid, second.uuid]);
+        //the primary lacked a raw body, so it takes the duplicate's
+        assert_eq!(merged.record.original_record_body, Some(json!({"ListID": "80000002-1"})));
+        assert_eq!(event_record_ids(&db).await, vec![primary.id; 4]);
+
+        let duplicates = inventory_record::Entity::find()
+            .filter(inventory_record::Column::Id.ne(primary.id))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(duplicates.len(), 2);
+        assert!(duplicates
+            .iter()
+            .all(|d| d.deleted_at.is_some() && d.merged_into_id == Some(primary.id)));
+
+        //listings only show the primary
+        let listed = InventoryRecordService::new(db.clone())
+            .get_all(1, 20, None, None)
+            .await
+            .unwrap();
+        assert_eq!(listed.items.iter().map(|r| r.id).collect::<Vec<_>>(), vec![primary.id]);
+    }
+
+    #[tokio::test]
+    async fn test_other_tenant_records_are_not_found() {
+        let db = setup_db().await;
+        let primary = insert_record(&db, 1, "80000001-1", None).await;
+        let duplicate = insert_record(&db, 2, "80000002-1", None).await;
+        insert_events(&db, &duplicate, 1).await;
+        let service = InventoryRecordService::new(db.clone());
+
+        let scoped = service.merge(primary.uuid, &[duplicate.uuid], Some(1)).await;
+        assert!(matches!(scoped, Err(InventoryRecordError::NotFound)));
+
+        //without a tenant scope the records are found but still can't be merged
+        let unscoped = service.merge(primary.uuid, &[duplicate.uuid], None).await;
+        assert!(matches!(unscoped, Err(InventoryRecordError::InvalidMerge(_))));
+
+        assert_eq!(event_record_ids(&db).await, vec![duplicate.id]);
+        let duplicate = service.get_by_id(duplicate.id, None).await.unwrap().unwrap();
+        assert!(duplicate.deleted_at.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_merged_record_cannot_be_merged_again() {
+        let db = setup_db().await;
+        let primary = insert_record(&db, 1, "80000001-1", None).await;
+        let duplicate = insert_record(&db, 1, "80000002-1", None).await;
+        let service = InventoryRecordService::new(db.clone());
+
+        service.merge(primary.uuid, &[duplicate.uuid], None).await.unwrap();
+        let again = service.merge(duplicate.uuid, &[primary.uuid], None).await;
+
+        assert!(matches!(again, Err(InventoryRecordError::NotFound)));
+    }
+}
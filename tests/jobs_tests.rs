@@ -0,0 +1,146 @@
+//! Tests for the background job queue
+//!
+//! Run with: cargo test --test jobs_tests
+//!
+//! Uses the in-process job store (no Redis required), which keeps the same queue /
+//! processing / dead lists as the Redis one.
+
+#[path = "../src/jobs/queue.rs"]
+mod queue;
+#[path = "../src/jobs/worker.rs"]
+mod worker;
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use queue::{Job, JobQueue, JobStore};
+use serde::{Deserialize, Serialize};
+use worker::{JobHandlers, JobOutcome, JobWorker};
+
+const BLOCK: Duration = Duration::from_millis(50);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Greet {
+    name: String,
+}
+
+impl Job for Greet {
+    const KIND: &'static str = "greet";
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AlwaysFails;
+
+impl Job for AlwaysFails {
+    const KIND: &'static str = "always_fails";
+}
+
+/// Helper to build a worker that records greeted names and fails every `AlwaysFails`
+fn worker(queue: &JobQueue) -> (JobWorker, Arc<Mutex<Vec<String>>>) {
+    let greeted = Arc::new(Mutex::new(Vec::new()));
+    let recorded = greeted.clone();
+    let handlers = JobHandlers::new()
+        .register(move |job: Greet| {
+            let recorded = recorded.clone();
+            async move {
+                recorded.lock().unwrap().push(job.name);
+                Ok(())
+            }
+        })
+        .register(|_: AlwaysFails| async { Err("provider unavailable".to_string()) });
+
+    let worker = JobWorker::new(queue.clone(), Arc::new(handlers)).with_block(BLOCK);
+    (worker, greeted)
+}
+
+#[cfg(test)]
+mod job_worker_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_worker_processes_enqueued_job() {
+        let queue = JobQueue::new(JobStore::local());
+        let (worker, greeted) = worker(&queue);
+
+        queue.enqueue(&Greet { name: "acme".to_string() }).await.unwrap();
+        assert_eq!(queue.queued().await.unwrap().len(), 1);
+
+        assert_eq!(worker.run_once().await.unwrap(), Some(JobOutcome::Completed));
+        assert_eq!(*greeted.lock().unwrap(), vec!["acme".to_string()]);
+        assert!(queue.queued().await.unwrap().is_empty());
+        //nothing left in processing either
+        assert_eq!(queue.requeue_processing().await.unwrap(), 0);
+
+        //an empty queue times out
+        assert_eq!(worker.run_once().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_spawned_worker_picks_up_job() {
+        let queue = JobQueue::new(JobStore::local());
+        let (worker, greeted) = worker(&queue);
+        let handle = worker::spawn(worker);
+
+        queue.enqueue(&Greet { name: "acme".to_string() }).await.unwrap();
+        for _ in 0..50 {
+            if !greeted.lock().unwrap().is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        handle.abort();
+        assert_eq!(*greeted.lock().unwrap(), vec!["acme".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn test_failing_job_moves_to_dead_letters() {
+        let queue = JobQueue::new(JobStore::local()).with_max_attempts(3);
+        let (worker, _) = worker(&queue);
+
+        let id = queue.enqueue(&AlwaysFails).await.unwrap();
+        assert_eq!(worker.run_once().await.unwrap(), Some(JobOutcome::Retried));
+        assert_eq!(worker.run_once().await.unwrap(), Some(JobOutcome::Retried));
+        assert_eq!(queue.queued().await.unwrap()[0].attempts, 2);
+        assert_eq!(worker.run_once().await.unwrap(), Some(JobOutcome::DeadLettered));
+
+        assert!(queue.queued().await.unwrap().is_empty());
+        let dead = queue.dead_letters().await.unwrap();
+        assert_eq!(dead.len(), 1);
+        assert_eq!(dead[0].id, id);
+        assert_eq!(dead[0].kind, "always_fails");
+        assert_eq!(dead[0].attempts, 3);
+        assert_eq!(dead[0].last_error.as_deref(), Some("provider unavailable"));
+        assert_eq!(queue.requeue_processing().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_job_without_handler_is_dead_lettered() {
+        #[derive(Serialize, Deserialize)]
+        struct Unregistered;
+        impl Job for Unregistered {
+            const KIND: &'static str = "unregistered";
+        }
+
+        let queue = JobQueue::new(JobStore::local());
+        let (worker, _) = worker(&queue);
+
+        queue.enqueue(&Unregistered).await.unwrap();
+        assert_eq!(worker.run_once().await.unwrap(), Some(JobOutcome::DeadLettered));
+        let dead = queue.dead_letters().await.unwrap();
+        assert_eq!(dead[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_requeue_processing_returns_reserved_jobs() {
+        let queue = JobQueue::new(JobStore::local());
+        queue.enqueue(&Greet { name: "acme".to_string() }).await.unwrap();
+
+        //reserved by a worker that never finished
+        let reserved = queue.reserve(BLOCK).await.unwrap().unwrap();
+        assert_eq!(reserved.envelope.kind, "greet");
+        assert!(queue.queued().await.unwrap().is_empty());
+
+        assert_eq!(queue.requeue_processing().await.unwrap(), 1);
+        assert_eq!(queue.queued().await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,57 @@
+//! Tests for reloading the tracing filter at runtime (`PUT /admin/log-level`)
+//!
+//! Run with: cargo test --test log_level_tests
+//!
+//! Kept apart from telemetry_tests: the global subscriber can only be installed once per
+//! process, and these tests need one installed without OTLP export.
+
+#[path = "../src/config/telemetry.rs"]
+mod telemetry;
+
+use std::sync::Once;
+use telemetry::{current_log_filter, init_tracing, parse_log_filter, set_log_filter, TelemetryError};
+
+static INIT: Once = Once::new();
+
+/// Helper to install the global subscriber once for every test in this file
+fn init() {
+    INIT.call_once(|| {
+        //the guard only holds a tracer provider when exporting, so dropping it is harmless
+        drop(init_tracing(None, "erp-proxy-server-test", "info").unwrap());
+    });
+}
+
+#[cfg(test)]
+mod log_filter_tests {
+    use super::*;
+
+    #[test]
+    fn test_set_new_filter() {
+        init();
+
+        let applied = set_log_filter("info,sea_orm=debug").unwrap();
+
+        assert!(applied.contains("sea_orm=debug"));
+        assert_eq!(current_log_filter(), Some(applied));
+        tracing::debug!(target: "sea_orm", "visible after the reload");
+    }
+
+    #[test]
+    fn test_invalid_directive_is_rejected() {
+        init();
+
+        let result = set_log_filter("sea_orm=loudest");
+
+        assert!(matches!(result, Err(TelemetryError::Filter(_))));
+        //a rejected filter leaves the active one in place
+        assert!(!current_log_filter().unwrap().contains("loudest"));
+    }
+
+    #[test]
+    fn test_parse_rejects_blank_and_malformed_filters() {
+        assert!(parse_log_filter("debug").is_ok());
+        assert!(parse_log_filter("erp_proxy_server=trace,sea_orm=warn").is_ok());
+        assert!(matches!(parse_log_filter("  "), Err(TelemetryError::Filter(_))));
+        assert!(matches!(parse_log_filter("sea_orm=[{"), Err(TelemetryError::Filter(_))));
+    }
+}
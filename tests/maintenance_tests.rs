@@ -0,0 +1,91 @@
+//! Tests for the maintenance mode middleware
+//!
+//! Run with: cargo test --test maintenance_tests
+
+#[path = "../src/middleware/maintenance.rs"]
+mod maintenance;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::from_fn_with_state;
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use maintenance::{
+    is_exempt_route, maintenance_middleware, set_maintenance_mode, MAINTENANCE_RETRY_AFTER_SECS,
+};
+use tower::ServiceExt;
+
+/// Helper to build a router mounted under `/api` like the app, with a health check, a normal
+/// route and the maintenance middleware in front
+fn app() -> Router {
+    let routes = Router::new()
+        .route("/healthcheck", get(|| async { "ok" }))
+        .route("/tenant", get(|| async { "tenants" }));
+    Router::new()
+        .nest("/api", routes)
+        .layer(from_fn_with_state(Some("/api"), maintenance_middleware))
+}
+
+async fn get_path(path: &str) -> Response {
+    app()
+        .oneshot(Request::get(path).body(Body::empty()).unwrap())
+        .await
+        .unwrap()
+}
+
+#[cfg(test)]
+mod maintenance_mode_tests {
+    use super::*;
+
+    //the flag is process-wide, so everything that flips it lives in this one test
+    #[tokio::test]
+    async fn test_maintenance_rejects_routes_but_not_health() {
+        assert_eq!(get_path("/api/tenant").await.status(), StatusCode::OK);
+
+        let previous = set_maintenance_mode(true);
+        assert!(!previous);
+
+        let response = get_path("/api/tenant").await;
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(
+            response.headers()[header::RETRY_AFTER],
+            MAINTENANCE_RETRY_AFTER_SECS.to_string().as_str()
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body, serde_json::json!({ "error": "maintenance" }));
+
+        assert_eq!(get_path("/api/healthcheck").await.status(), StatusCode::OK);
+
+        assert!(set_maintenance_mode(false));
+        assert_eq!(get_path("/api/tenant").await.status(), StatusCode::OK);
+    }
+}
+
+#[cfg(test)]
+mod exempt_route_tests {
+    use super::*;
+
+    #[test]
+    fn test_health_metrics_and_toggle_are_exempt() {
+        for path in [
+            "/",
+            "/healthcheck",
+            "/metrics",
+            "/admin/health",
+            "/admin/readyz",
+            "/admin/maintenance",
+        ] {
+            assert!(is_exempt_route(path, None), "{path}");
+            assert!(is_exempt_route(&format!("/api{path}"), Some("/api")), "/api{path}");
+        }
+    }
+
+    #[test]
+    fn test_other_routes_are_not_exempt() {
+        assert!(!is_exempt_route("/api/tenant", Some("/api")));
+        assert!(!is_exempt_route("/api/admin/log-level", Some("/api")));
+        assert!(!is_exempt_route("/api/healthcheck/extra", Some("/api")));
+    }
+}
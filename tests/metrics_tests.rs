@@ -0,0 +1,173 @@
+//! Tests for the Prometheus metrics
+//!
+//! Run with: cargo test --test metrics_tests
+//!
+//! The registry is process-global and can only be initialized once, so the histogram and
+//! the run counter are covered by a single test against an in-memory SQLite database.
+
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+#[path = "../src/security/admin_token.rs"]
+mod admin_token;
+#[path = "../src/security/metrics_access.rs"]
+mod metrics_access;
+
+//the token check is reached through `crate::security::admin_token`
+mod security {
+    pub(crate) use super::admin_token;
+}
+
+use metrics::{
+    classify_statement, init_metrics, observe_db_query, record_connection_run,
+    CONNECTION_RUNS_TOTAL, DB_QUERY_DURATION, REGISTRY,
+};
+use prometheus::{Encoder, TextEncoder};
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{ConnectionTrait, Database, EntityTrait, Schema};
+
+#[cfg(test)]
+mod db_query_duration_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_query_is_recorded_in_histogram() {
+        init_metrics();
+        let mut db = Database::connect("sqlite::memory:").await.unwrap();
+        db.set_metric_callback(observe_db_query);
+        let schema = Schema::new(db.get_database_backend());
+        let sql = schema
+            .create_table_from_entity(entity::tenant::Entity)
+            .to_string(SqliteQueryBuilder);
+        db.execute_unprepared(&sql).await.unwrap();
+
+        entity::tenant::Entity::find().all(&db).await.unwrap();
+
+        let histogram = DB_QUERY_DURATION.get().unwrap();
+        assert_eq!(histogram.with_label_values(&["select", "tenant"]).get_sample_count(), 1);
+
+        //exposed with the rest of the registry, as /metrics serves it
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&REGISTRY.get().unwrap().gather(), &mut buffer)
+            .unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        assert!(text.contains(r#"db_query_duration_seconds_count{entity="tenant",operation="select"} 1"#));
+
+        //connection runs are counted per status
+        record_connection_run("success");
+        record_connection_run("success");
+        record_connection_run("error");
+        let runs = CONNECTION_RUNS_TOTAL.get().unwrap();
+        assert_eq!(runs.with_label_values(&["success"]).get(), 2);
+        assert_eq!(runs.with_label_values(&["error"]).get(), 1);
+    }
+}
+
+#[cfg(test)]
+mod classify_statement_tests {
+    use super::*;
+
+    #[test]
+    fn test_operation_and_table() {
+        assert_eq!(
+            classify_statement(r#"SELECT "tenant"."id" FROM "tenant" WHERE "tenant"."id" = $1"#),
+            ("select", "tenant".to_string())
+        );
+        assert_eq!(
+            classify_statement(r#"INSERT INTO "inventory_record" ("uuid") VALUES ($1) RETURNING "id""#),
+            ("insert", "inventory_record".to_string())
+        );
+        assert_eq!(
+            classify_statement(r#"UPDATE "public"."sync_event" SET "status" = $1"#),
+            ("update", "sync_event".to_string())
+        );
+        assert_eq!(
+            classify_statement(r#"DELETE FROM "outbox" WHERE "id" = $1"#),
+            ("delete", "outbox".to_string())
+        );
+    }
+
+    #[test]
+    fn test_paginator_count_uses_inner_table() {
+        let sql = r#"SELECT COUNT(*) AS num_items FROM (SELECT "connection_identity"."id" FROM "connection_identity") AS "sub_query""#;
+        assert_eq!(classify_statement(sql), ("select", "connection_identity".to_string()));
+    }
+
+    #[test]
+    fn test_other_statements() {
+        assert_eq!(classify_statement("SELECT 1"), ("select", "unknown".to_string()));
+        assert_eq!(classify_statement("BEGIN"), ("other", "unknown".to_string()));
+    }
+}
+
+#[cfg(test)]
+mod metrics_access_tests {
+    use super::*;
+    use axum::http::{header::AUTHORIZATION, HeaderMap, HeaderValue};
+    use metrics_access::{metrics_access_allowed, parse_cidrs, scraper_ip, IpCidr};
+    use std::net::IpAddr;
+
+    fn ip(value: &str) -> Option<IpAddr> {
+        Some(value.parse().unwrap())
+    }
+
+    fn cidrs(values: &[&str]) -> Vec<IpCidr> {
+        parse_cidrs(&values.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn test_open_when_nothing_configured() {
+        assert!(metrics_access_allowed(ip("203.0.113.9"), &HeaderMap::new(), &[], None));
+        assert!(metrics_access_allowed(None, &HeaderMap::new(), &[], None));
+    }
+
+    #[test]
+    fn test_allowed_cidr_passes() {
+        let allowed = cidrs(&["10.0.0.0/8", "fd00::/8", "192.0.2.7"]);
+        for scraper in ["10.1.2.3", "fd12::1", "192.0.2.7", "::ffff:10.9.9.9"] {
+            assert!(metrics_access_allowed(ip(scraper), &HeaderMap::new(), &allowed, None), "{scraper}");
+        }
+    }
+
+    #[test]
+    fn test_disallowed_ip_is_blocked() {
+        let allowed = cidrs(&["10.0.0.0/8", "192.0.2.7"]);
+        for scraper in ["11.0.0.1", "192.0.2.8", "fe80::1"] {
+            assert!(!metrics_access_allowed(ip(scraper), &HeaderMap::new(), &allowed, None), "{scraper}");
+        }
+        //no peer address at all (e.g. a router without ConnectInfo)
+        assert!(!metrics_access_allowed(None, &HeaderMap::new(), &allowed, None));
+    }
+
+    #[test]
+    fn test_bearer_token_admits_any_ip() {
+        let allowed = cidrs(&["10.0.0.0/8"]);
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer scrape-secret"));
+        assert!(metrics_access_allowed(ip("203.0.113.9"), &headers, &allowed, Some("scrape-secret")));
+        assert!(metrics_access_allowed(ip("203.0.113.9"), &headers, &[], Some("scrape-secret")));
+
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer wrong"));
+        assert!(!metrics_access_allowed(ip("203.0.113.9"), &headers, &[], Some("scrape-secret")));
+        assert!(!metrics_access_allowed(ip("203.0.113.9"), &HeaderMap::new(), &[], Some("scrape-secret")));
+    }
+
+    #[test]
+    fn test_invalid_entries_are_dropped() {
+        assert_eq!(cidrs(&["10.0.0.0/33", "not-an-ip", "::/0"]), cidrs(&["::/0"]));
+        assert!(IpCidr::parse("0.0.0.0/0").unwrap().contains("198.51.100.1".parse().unwrap()));
+    }
+
+    #[test]
+    fn test_forwarded_for_only_trusted_from_proxies() {
+        let trusted = vec!["10.0.0.2".to_string()];
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("10.5.5.5, 203.0.113.9"));
+
+        //behind the proxy the rightmost untrusted hop is the scraper
+        assert_eq!(scraper_ip(ip("10.0.0.2"), &headers, &trusted), ip("203.0.113.9"));
+        //anyone else's header is ignored
+        assert_eq!(scraper_ip(ip("203.0.113.50"), &headers, &trusted), ip("203.0.113.50"));
+        assert_eq!(scraper_ip(ip("10.0.0.2"), &HeaderMap::new(), &trusted), ip("10.0.0.2"));
+    }
+}
@@ -0,0 +1,164 @@
+//! Tests for the transactional outbox
+//!
+//! Run with: cargo test --test outbox_tests
+//!
+//! Uses an in-memory SQLite database so commit/rollback semantics are real.
+
+#[path = "../src/outbox/services.rs"]
+mod outbox_services;
+
+use entity::sea_orm_active_enums::SystemIdKey;
+use entity::{inventory_record, outbox};
+use outbox_services::{retry_delay, OutboxPayload, OutboxService, TOPIC_INVENTORY_UPSERTED};
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, DatabaseTransaction,
+    EntityTrait, PaginatorTrait, Schema, Set, TransactionTrait,
+};
+use serde_json::json;
+
+/// Helper to create an in-memory database with the outbox and inventory_record tables
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //inventory_record references tenant/connection_identity, which these tests don't need
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    db.execute(&schema.create_table_from_entity(outbox::Entity))
+        .await
+        .unwrap();
+    db.execute(&schema.create_table_from_entity(inventory_record::Entity))
+        .await
+        .unwrap();
+    db
+}
+
+/// Mirrors the poll service: upsert an inventory record and enqueue its message in one txn
+async fn upsert_with_outbox(service: &OutboxService, txn: &DatabaseTransaction, system_id: &str) {
+    let now = chrono::Utc::now();
+    let record = inventory_record::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        tenant_id: Set(1),
+        originating_connection_id: Set(1),
+        original_record_body: Set(None),
+        system_id_key: Set(SystemIdKey::Qbd),
+        system_id: Set(system_id.to_string()),
+        ..Default::default()
+    }
+    .insert(txn)
+    .await
+    .unwrap();
+
+    service
+        .enqueue(
+            txn,
+            OutboxPayload {
+                topic: TOPIC_INVENTORY_UPSERTED.to_string(),
+                payload: json!({ "inventory_record_uuid": record.uuid }),
+            },
+        )
+        .await
+        .unwrap();
+}
+
+#[cfg(test)]
+mod outbox_transaction_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rolled_back_upsert_leaves_no_outbox_row() {
+        let db = setup_db().await;
+        let service = OutboxService::new(db.clone());
+
+        let txn = db.begin().await.unwrap();
+        upsert_with_outbox(&service, &txn, "80000001-1234567890").await;
+        txn.rollback().await.unwrap();
+
+        assert_eq!(outbox::Entity::find().count(&db).await.unwrap(), 0);
+        assert_eq!(inventory_record::Entity::find().count(&db).await.unwrap(), 0);
+        assert!(service.fetch_unpublished(10, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_committed_upsert_enqueues_exactly_one() {
+        let db = setup_db().await;
+        let service = OutboxService::new(db.clone());
+
+        let txn = db.begin().await.unwrap();
+        upsert_with_outbox(&service, &txn, "80000001-1234567890").await;
+        txn.commit().await.unwrap();
+
+        let pending = service.fetch_unpublished(10, None).await.unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].topic, TOPIC_INVENTORY_UPSERTED);
+        assert_eq!(pending[0].attempts, 0);
+        assert!(pending[0].published_at.is_none());
+    }
+}
+
+#[cfg(test)]
+mod outbox_delivery_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_published_message_is_not_fetched_again() {
+        let db = setup_db().await;
+        let service = OutboxService::new(db.clone());
+
+        let txn = db.begin().await.unwrap();
+        upsert_with_outbox(&service, &txn, "80000001-1234567890").await;
+        txn.commit().await.unwrap();
+
+        let pending = service.fetch_unpublished(10, None).await.unwrap();
+        let published = service.mark_published(pending[0].id, None).await.unwrap();
+
+        assert!(published.published_at.is_some());
+        assert_eq!(published.attempts, 1);
+        assert!(service.fetch_unpublished(10, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_message_is_delayed() {
+        let db = setup_db().await;
+        let service = OutboxService::new(db.clone());
+
+        let txn = db.begin().await.unwrap();
+        upsert_with_outbox(&service, &txn, "80000001-1234567890").await;
+        txn.commit().await.unwrap();
+
+        let pending = service.fetch_unpublished(10, None).await.unwrap();
+        let failed = service
+            .mark_failed(pending[0].id, "connection refused", None)
+            .await
+            .unwrap();
+
+        assert_eq!(failed.attempts, 1);
+        assert_eq!(failed.last_error.as_deref(), Some("connection refused"));
+        assert!(failed.published_at.is_none());
+        //not due again until the backoff elapses
+        assert!(service.fetch_unpublished(10, None).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fetch_unpublished_respects_limit_and_order() {
+        let db = setup_db().await;
+        let service = OutboxService::new(db.clone());
+
+        let txn = db.begin().await.unwrap();
+        for i in 0..3 {
+            upsert_with_outbox(&service, &txn, &format!("8000000{}-1234567890", i)).await;
+        }
+        txn.commit().await.unwrap();
+
+        let pending = service.fetch_unpublished(2, None).await.unwrap();
+        assert_eq!(pending.len(), 2);
+        assert!(pending[0].id < pending[1].id);
+    }
+
+    #[test]
+    fn test_retry_delay_backoff() {
+        assert_eq!(retry_delay(1).num_seconds(), 2);
+        assert_eq!(retry_delay(3).num_seconds(), 8);
+        assert_eq!(retry_delay(20).num_seconds(), 300);
+    }
+}
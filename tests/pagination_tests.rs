@@ -0,0 +1,92 @@
+//! Tests for list route pagination headers
+//!
+//! Run with: cargo test --test pagination_tests
+
+#[path = "../src/pagination/mod.rs"]
+mod pagination;
+
+use axum::http::{header, Uri};
+use pagination::{link_header, paginated_response, PageInfo};
+
+fn page(page: u64, total_pages: u64) -> PageInfo {
+    PageInfo {
+        page,
+        per_page: 20,
+        total: total_pages * 20,
+        total_pages,
+    }
+}
+
+#[cfg(test)]
+mod link_header_tests {
+    use super::*;
+
+    #[test]
+    fn test_middle_page_has_all_rels() {
+        let uri: Uri = "/connections?page=3&per_page=20".parse().unwrap();
+        let link = link_header(&page(3, 5), &uri);
+
+        assert_eq!(
+            link,
+            "</connections?per_page=20&page=1>; rel=\"first\", \
+             </connections?per_page=20&page=2>; rel=\"prev\", \
+             </connections?per_page=20&page=4>; rel=\"next\", \
+             </connections?per_page=20&page=5>; rel=\"last\""
+        );
+    }
+
+    #[test]
+    fn test_first_and_last_pages_omit_prev_and_next() {
+        let uri: Uri = "/tenant".parse().unwrap();
+
+        let first = link_header(&page(1, 2), &uri);
+        assert!(!first.contains("rel=\"prev\""));
+        assert!(first.contains("</tenant?page=2>; rel=\"next\""));
+
+        let last = link_header(&page(2, 2), &uri);
+        assert!(last.contains("</tenant?page=1>; rel=\"prev\""));
+        assert!(!last.contains("rel=\"next\""));
+    }
+
+    #[test]
+    fn test_empty_result_links_to_page_one() {
+        let uri: Uri = "/connections?status=active".parse().unwrap();
+        let link = link_header(&page(1, 0), &uri);
+
+        assert_eq!(
+            link,
+            "</connections?status=active&page=1>; rel=\"first\", \
+             </connections?status=active&page=1>; rel=\"last\""
+        );
+    }
+
+    #[test]
+    fn test_filters_keep_their_encoding() {
+        let uri: Uri = "/tenant?display_name=Acme%20Co&page=2".parse().unwrap();
+        let link = link_header(&page(2, 3), &uri);
+        assert!(link.contains("</tenant?display_name=Acme%20Co&page=3>; rel=\"next\""));
+    }
+}
+
+#[cfg(test)]
+mod pagination_header_tests {
+    use super::*;
+
+    #[test]
+    fn test_count_headers() {
+        let uri: Uri = "/connections?page=2".parse().unwrap();
+        let info = PageInfo {
+            page: 2,
+            per_page: 10,
+            total: 45,
+            total_pages: 5,
+        };
+        let headers = paginated_response(&info, &uri);
+
+        assert_eq!(headers["x-total-count"], "45");
+        assert_eq!(headers["x-page"], "2");
+        assert_eq!(headers["x-per-page"], "10");
+        assert_eq!(headers["x-total-pages"], "5");
+        assert_eq!(headers[header::LINK], link_header(&info, &uri).as_str());
+    }
+}
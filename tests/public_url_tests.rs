@@ -0,0 +1,145 @@
+//! Tests for the external origin used in absolute URLs (X-Forwarded-Proto/-Host handling)
+//!
+//! Run with: cargo test --test public_url_tests
+
+//public_url.rs reads its config through `super::env`; only the pure helpers are exercised here
+#[path = "../src/config/env.rs"]
+mod env;
+#[path = "../src/config/public_url.rs"]
+mod public_url;
+
+use axum::http::HeaderMap;
+use public_url::{oauth_redirect_uri, resolve_origin, ExternalOrigin};
+use std::net::IpAddr;
+
+const PROXY: &str = "10.0.0.2";
+const CLIENT: &str = "203.0.113.9";
+
+fn ip(addr: &str) -> Option<IpAddr> {
+    Some(addr.parse().unwrap())
+}
+
+fn trusted() -> Vec<String> {
+    vec![PROXY.to_string()]
+}
+
+/// Helper to build headers as a TLS-terminating proxy forwards them
+fn forwarded_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert("host", "app:3000".parse().unwrap());
+    headers.insert("x-forwarded-proto", "https".parse().unwrap());
+    headers.insert("x-forwarded-host", "api.example.com".parse().unwrap());
+    headers
+}
+
+#[cfg(test)]
+mod trusted_proxy_tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarded_headers_from_trusted_proxy_are_used() {
+        let origin = resolve_origin(&forwarded_headers(), ip(PROXY), &trusted(), None).unwrap();
+
+        assert_eq!(origin.base_url(), "https://api.example.com");
+        assert!(origin.is_secure());
+    }
+
+    #[test]
+    fn test_first_value_wins_when_proxies_chain() {
+        let mut headers = forwarded_headers();
+        headers.insert("x-forwarded-proto", "HTTPS, http".parse().unwrap());
+        headers.insert("x-forwarded-host", "api.example.com, app:3000".parse().unwrap());
+
+        let origin = resolve_origin(&headers, ip(PROXY), &trusted(), None).unwrap();
+
+        assert_eq!(origin.base_url(), "https://api.example.com");
+    }
+
+    #[test]
+    fn test_host_header_is_used_without_forwarded_host() {
+        let mut headers = forwarded_headers();
+        headers.remove("x-forwarded-host");
+
+        let origin = resolve_origin(&headers, ip(PROXY), &trusted(), None).unwrap();
+
+        assert_eq!(origin.base_url(), "https://app:3000");
+    }
+}
+
+#[cfg(test)]
+mod untrusted_peer_tests {
+    use super::*;
+
+    #[test]
+    fn test_forwarded_headers_from_untrusted_peer_are_ignored() {
+        let origin = resolve_origin(&forwarded_headers(), ip(CLIENT), &trusted(), None).unwrap();
+
+        assert_eq!(origin.base_url(), "http://app:3000");
+        assert!(!origin.is_secure());
+    }
+
+    #[test]
+    fn test_forwarded_headers_ignored_without_trusted_proxies_or_peer() {
+        let headers = forwarded_headers();
+
+        assert_eq!(
+            resolve_origin(&headers, ip(PROXY), &[], None).unwrap().base_url(),
+            "http://app:3000"
+        );
+        assert_eq!(
+            resolve_origin(&headers, None, &trusted(), None).unwrap().base_url(),
+            "http://app:3000"
+        );
+    }
+
+    #[test]
+    fn test_public_base_url_is_the_fallback() {
+        let origin = resolve_origin(
+            &forwarded_headers(),
+            ip(CLIENT),
+            &trusted(),
+            Some("https://erp.example.com/api"),
+        )
+        .unwrap();
+
+        assert_eq!(origin.base_url(), "https://erp.example.com");
+    }
+
+    #[test]
+    fn test_unknown_forwarded_proto_falls_back() {
+        let mut headers = forwarded_headers();
+        headers.insert("x-forwarded-proto", "javascript".parse().unwrap());
+
+        let origin =
+            resolve_origin(&headers, ip(PROXY), &trusted(), Some("https://erp.example.com")).unwrap();
+
+        assert_eq!(origin.base_url(), "https://erp.example.com");
+    }
+
+    #[test]
+    fn test_no_origin_without_any_source() {
+        assert!(resolve_origin(&HeaderMap::new(), ip(CLIENT), &trusted(), None).is_none());
+    }
+}
+
+#[cfg(test)]
+mod redirect_uri_tests {
+    use super::*;
+
+    #[test]
+    fn test_oauth_redirect_uri_joins_origin_and_path() {
+        let origin = ExternalOrigin::parse("https://api.example.com/").unwrap();
+
+        assert_eq!(
+            oauth_redirect_uri(&origin, "/api/connections/oauth/callback"),
+            "https://api.example.com/api/connections/oauth/callback"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_non_http_urls() {
+        assert!(ExternalOrigin::parse("ftp://example.com").is_none());
+        assert!(ExternalOrigin::parse("example.com").is_none());
+        assert!(ExternalOrigin::parse("https://").is_none());
+    }
+}
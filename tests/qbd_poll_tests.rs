@@ -0,0 +1,2189 @@
+//! Tests for the QuickBooks Desktop poll service
+//!
+//! Run with: cargo test --test qbd_poll_tests
+//!
+//! Uses the sea-orm mock database (no Postgres required); `connection_identity.scopes` is a
+//! Postgres array, so the QBD connection can't live in SQLite. Orchestrator paths that only
+//! touch the sync tables run against in-memory SQLite instead.
+
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/events/mod.rs"]
+mod events;
+#[path = "../src/client-systems/quickbooks/desktop/field_mapping.rs"]
+mod field_mapping;
+#[path = "../src/client-systems/quickbooks/desktop/order_push.rs"]
+mod order_push;
+#[path = "../src/client-systems/quickbooks/desktop/order_qbxml.rs"]
+mod order_qbxml;
+#[path = "../src/client-systems/quickbooks/desktop/poll_health.rs"]
+mod poll_health;
+#[path = "../src/client-systems/quickbooks/desktop/poll_services.rs"]
+mod poll_services;
+#[path = "../src/client-systems/quickbooks/desktop/qbd_status.rs"]
+mod qbd_status;
+#[path = "../src/client-systems/quickbooks/desktop/qbxml.rs"]
+mod qbxml;
+#[path = "../src/client-systems/quickbooks/desktop/test_support.rs"]
+mod test_support;
+#[path = "../src/sync/mod.rs"]
+mod sync;
+#[path = "../src/validation/mod.rs"]
+mod validation;
+
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+#[path = "../src/connection_run/services.rs"]
+mod connection_run_services;
+#[path = "../src/erp_connection_credentials/codec.rs"]
+mod credential_codec;
+#[path = "../src/erp_connection_credentials/lookup_cache.rs"]
+mod credential_lookup_cache;
+#[path = "../src/erp_connection_credentials/secret_store.rs"]
+mod secret_store;
+#[path = "../src/erp_connection_sync_state/services.rs"]
+mod erp_connection_sync_state_services;
+#[path = "../src/inventory_records/events_services.rs"]
+mod inventory_record_events_services;
+#[path = "../src/inventory_records/services.rs"]
+mod inventory_records_services;
+#[path = "../src/inventory_records/upsert.rs"]
+mod inventory_records_upsert;
+#[path = "../src/outbox/services.rs"]
+mod outbox_services;
+#[path = "../src/quarantine/services.rs"]
+mod quarantine_services;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/sync_event/services.rs"]
+mod sync_event_services;
+
+//the poll service and orchestrator reach these through `crate::<module>::...`
+mod connection_run {
+    pub(crate) use super::connection_run_services as services;
+}
+mod config {
+    pub(crate) use super::metrics;
+}
+mod erp_connection_credentials {
+    pub(crate) use super::credential_codec as codec;
+    pub(crate) use super::credential_lookup_cache as lookup_cache;
+    pub(crate) use super::secret_store;
+}
+mod erp_connection_sync_state {
+    pub(crate) use super::erp_connection_sync_state_services as services;
+}
+mod inventory_records {
+    pub(crate) use super::inventory_record_events_services as events_services;
+    pub(crate) use super::inventory_records_services as services;
+    pub(crate) use super::inventory_records_upsert as upsert;
+}
+mod outbox {
+    pub(crate) use super::outbox_services as services;
+}
+mod quarantine {
+    pub(crate) use super::quarantine_services as services;
+}
+mod sync_event {
+    pub(crate) use super::sync_event_services as services;
+}
+//the shared upsert reaches its siblings through `super::<module>`
+use inventory_record_events_services as events_services;
+use inventory_records_services as services;
+
+use entity::sea_orm_active_enums::{
+    ErpConnectionAuthStatus, ErpConnectionAuthTokenType, ErpConnectionStatus, ErpEnvironment,
+    ErpProvider, ErpProviderAuthType, ErpProviderType,
+};
+use entity::connection_identity;
+use poll_services::{PollResponseInput, QbdPollError, QbdPollService};
+use sea_orm::sea_query::{SqliteQueryBuilder, Table};
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection,
+    EntityTrait, IntoActiveModel, MockDatabase, NotSet, Schema, Set, Value,
+};
+use sync::{PullResponse, SyncOrchestrator};
+use std::collections::BTreeMap;
+
+const USERNAME: &str = "qbwc-user";
+const PASSWORD: &str = "qbwc-pass";
+
+const TWO_ITEM_RS: &str = r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <ItemInventoryQueryRs requestID="1" statusCode="0" statusSeverity="Info" statusMessage="Status OK" iteratorRemainingCount="3" iteratorID="{abc-123}">
+      <ItemInventoryRet>
+        <ListID>80000001-1234567890</ListID>
+        <Name>Widget</Name>
+        <FullName>Widgets:Widget</FullName>
+        <SalesPrice>19.99</SalesPrice>
+        <QuantityOnHand>42</QuantityOnHand>
+      </ItemInventoryRet>
+      <ItemInventoryRet>
+        <ListID>80000002-1234567890</ListID>
+        <Name>Gadget</Name>
+        <FullName>Widgets:Gadget</FullName>
+        <SalesPrice>5.00</SalesPrice>
+        <QuantityOnHand>7</QuantityOnHand>
+      </ItemInventoryRet>
+    </ItemInventoryQueryRs>
+  </QBXMLMsgsRs>
+</QBXML>"#;
+
+/// Helper to build the QBD connection the Web Connector credentials belong to
+fn connection_model() -> connection_identity::Model {
+    let now = chrono::Utc::now().into();
+    connection_identity::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider: ErpProvider::Quickbooks,
+        erp_type: ErpProviderType::Desktop,
+        erp_auth_type: ErpProviderAuthType::UsernamePassword,
+        display_name: None,
+        environment: ErpEnvironment::Production,
+        status: ErpConnectionStatus::Active,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: now,
+        updated_at: now,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: None,
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+}
+
+/// Helper to build the Web Connector credentials row
+fn credentials_model() -> entity::erp_connection_credentials::Model {
+    let now = chrono::Utc::now().into();
+    entity::erp_connection_credentials::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        created_at: now,
+        updated_at: now,
+        connection_id: 1,
+        client_id: None,
+        issuer_base_url: None,
+        token_type: ErpConnectionAuthTokenType::Bearer,
+        reauth_required_reason: None,
+        reauth_url: None,
+        enc_scheme: "none".to_string(),
+        enc_key_id: "none".to_string(),
+        enc_version: 1,
+        enc_iv: None,
+        enc_tag: None,
+        access_token: None,
+        refresh_token: None,
+        access_token_expires_at: None,
+        refresh_token_expires_at: None,
+        id_token_enc: None,
+        provider_user_id: Some(USERNAME.to_string()),
+        provider_password: Some(PASSWORD.to_string()),
+        client_cert: None,
+        private_key: None,
+        cert_expires_at: None,
+        session_token: None,
+        session_expires_at: None,
+        api_access_token: None,
+        api_access_token_key: None,
+    }
+}
+
+/// Helper to build a mock answering a dry run for a connection with no sync state yet:
+/// credentials joined with their connection, sync state (none), then the count of
+/// already-stored items
+fn dry_run_db(existing_records: i64) -> DatabaseConnection {
+    MockDatabase::new(DatabaseBackend::Postgres)
+        .append_query_results([[(credentials_model(), Some(connection_model()))]])
+        .append_query_results([Vec::<entity::erp_connection_sync_state::Model>::new()])
+        .append_query_results([[BTreeMap::from([(
+            "num_items".to_string(),
+            Value::from(existing_records),
+        )])]])
+        .into_connection()
+}
+
+fn dry_run_input(xml: Option<&str>) -> PollResponseInput {
+    PollResponseInput {
+        qbd_response_xml: xml.map(str::to_string),
+        qbd_error: None,
+        dry_run: true,
+    }
+}
+
+#[cfg(test)]
+mod dry_run_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dry_run_reports_count_without_writes() {
+        let db = dry_run_db(0);
+        let svc = QbdPollService::new(db.clone());
+
+        let out = svc
+            .handle_response(USERNAME, PASSWORD, dry_run_input(Some(TWO_ITEM_RS)))
+            .await
+            .unwrap();
+        assert!(!out.has_more);
+
+        let summary = out.dry_run.expect("dry run returns a summary");
+        assert_eq!(summary.item_type, "inventory");
+        assert_eq!(summary.item_count, 2);
+        assert_eq!(summary.would_create, 2);
+        assert_eq!(summary.would_update, 0);
+        assert!(summary.has_more);
+        assert_eq!(summary.cursor.unwrap()["iterator_id"], "{abc-123}");
+        assert_eq!(summary.sample.unwrap()["ListID"], "80000001-1234567890");
+
+        //only reads were issued: no inventory rows, outbox rows or sync bookkeeping
+        drop(svc);
+        let log = db.into_transaction_log();
+        assert_eq!(log.len(), 3);
+        for txn in log {
+            let sql = format!("{:?}", txn);
+            assert!(sql.contains("SELECT"), "unexpected statement: {sql}");
+            assert!(!sql.contains("INSERT") && !sql.contains("UPDATE"), "write issued: {sql}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_counts_existing_records_as_updates() {
+        let summary = QbdPollService::new(dry_run_db(1))
+            .handle_response(USERNAME, PASSWORD, dry_run_input(Some(TWO_ITEM_RS)))
+            .await
+            .unwrap()
+            .dry_run
+            .unwrap();
+
+        assert_eq!(summary.would_create, 1);
+        assert_eq!(summary.would_update, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_counts_invalid_items_as_rejects() {
+        //the second item has no Name, so it fails the inventory payload schema
+        let xml = TWO_ITEM_RS.replace("<Name>Gadget</Name>", "");
+        let summary = QbdPollService::new(dry_run_db(0))
+            .handle_response(USERNAME, PASSWORD, dry_run_input(Some(&xml)))
+            .await
+            .unwrap()
+            .dry_run
+            .unwrap();
+
+        assert_eq!(summary.item_count, 2);
+        assert_eq!(summary.would_create, 1);
+        assert_eq!(summary.would_reject, 1);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_rejects_bad_credentials_and_missing_xml() {
+        let denied = QbdPollService::new(dry_run_db(0))
+            .handle_response(USERNAME, "wrong", dry_run_input(Some(TWO_ITEM_RS)))
+            .await;
+        assert!(matches!(denied, Err(QbdPollError::Unauthorized)));
+
+        let missing = QbdPollService::new(dry_run_db(0))
+            .handle_response(USERNAME, PASSWORD, dry_run_input(None))
+            .await;
+        assert!(matches!(missing, Err(QbdPollError::XmlParse(_))));
+    }
+}
+
+#[cfg(test)]
+mod connection_scope_tests {
+    use super::*;
+
+    /// Helper to build a mock that only answers the credentials lookup (connection 1)
+    fn credentials_db() -> DatabaseConnection {
+        MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[(credentials_model(), Some(connection_model()))]])
+            .into_connection()
+    }
+
+    #[tokio::test]
+    async fn test_key_for_another_connection_cannot_drive_the_poll() {
+        //valid Web Connector credentials of connection 1, under a key minted for connection 2
+        let db = credentials_db();
+        let denied = QbdPollService::new(db.clone())
+            .with_connection_scope(Some(2))
+            .handle_request(USERNAME, PASSWORD, None)
+            .await;
+        assert!(matches!(denied, Err(QbdPollError::Unauthorized)));
+        //rejected right after the credentials lookup: no sync state read, nothing written
+        assert_eq!(db.into_transaction_log().len(), 1);
+
+        let denied = QbdPollService::new(dry_run_db(0))
+            .with_connection_scope(Some(2))
+            .handle_response(USERNAME, PASSWORD, dry_run_input(Some(TWO_ITEM_RS)))
+            .await;
+        assert!(matches!(denied, Err(QbdPollError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_key_for_the_credentials_connection_is_served() {
+        let out = QbdPollService::new(dry_run_db(0))
+            .with_connection_scope(Some(1))
+            .handle_response(USERNAME, PASSWORD, dry_run_input(Some(TWO_ITEM_RS)))
+            .await
+            .unwrap();
+
+        assert_eq!(out.dry_run.unwrap().item_count, 2);
+    }
+}
+
+const ITERATOR_EXPIRED_RS: &str = r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <ItemInventoryQueryRs requestID="1" statusCode="3100" statusSeverity="Error" statusMessage="Invalid iterator">
+    </ItemInventoryQueryRs>
+  </QBXMLMsgsRs>
+</QBXML>"#;
+
+const NEW_UUID: &str = "(randomblob(16))";
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+/// Helper to create an in-memory database with the connection, sync, inventory, outbox and
+/// quarantine tables a page is applied to, with the uuid/created_at/updated_at defaults
+/// Postgres provides
+async fn setup_sync_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    //SQLite has no arrays; `scopes` is never written or read here, so a text column will do
+    let mut connections = Table::create();
+    connections.table(connection_identity::Entity);
+    for column in schema.create_table_from_entity(connection_identity::Entity).get_columns() {
+        let mut column = column.clone();
+        if column.get_column_name() == "scopes" {
+            column.text();
+        }
+        connections.col(column);
+    }
+    db.execute(&connections).await.unwrap();
+    let tables = [
+        schema.create_table_from_entity(entity::erp_connection_sync_state::Entity),
+        schema.create_table_from_entity(entity::connection_run::Entity),
+        schema.create_table_from_entity(entity::sync_event::Entity),
+        schema.create_table_from_entity(entity::inventory_record::Entity),
+        schema.create_table_from_entity(entity::inventory_record_event::Entity),
+        schema.create_table_from_entity(entity::outbox::Entity),
+        schema.create_table_from_entity(entity::quarantine::Entity),
+    ];
+    for table in tables {
+        let sql = table
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+    }
+    //same partial index as the migration; the first pull inserts against it
+    db.execute_unprepared(
+        "CREATE UNIQUE INDEX ux_sync_event_recurring_list
+         ON sync_event (connection_sync_state_id, sync_event_method, sync_event_category)
+         WHERE sync_event_method = 'list' AND details IS NULL",
+    )
+    .await
+    .unwrap();
+    db
+}
+
+#[cfg(test)]
+mod iterator_expiry_tests {
+    use super::*;
+    use entity::sea_orm_active_enums::{ConnectionRunStatus, SyncEventStatus};
+
+    #[tokio::test]
+    async fn test_expired_iterator_restarts_pagination() {
+        let db = setup_sync_db().await;
+        let conn = connection_model();
+        let svc = QbdPollService::new(db.clone());
+        let orchestrator = SyncOrchestrator::new(db.clone(), &svc);
+
+        //a page was fetched earlier; the iterator has since expired on the QBD side
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        let mut active: entity::erp_connection_sync_state::ActiveModel = state.into();
+        active.sync_cursor = Set(Some(serde_json::json!({
+            "iterator_id": "{abc-123}",
+            "remaining_count": 3,
+            "item_type": "inventory",
+        })));
+        active.update(&db).await.unwrap();
+
+        let request = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        assert!(request.payload.contains(r#"iterator="Continue""#));
+
+        let outcome = orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(ITERATOR_EXPIRED_RS.to_string()),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(!outcome.has_more());
+
+        //cursor cleared without backoff, so the next cycle can start over right away
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert!(state.sync_cursor.is_none());
+        assert!(state.sync_lock_owner.is_none());
+        assert!(state.rate_limit_backoff_until.is_none());
+
+        //the event stays retryable; the run records the restart
+        let events = entity::sync_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, SyncEventStatus::Pending);
+        assert_eq!(events[0].last_error.as_ref().unwrap()["cursor_reset"], true);
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs[0].status, ConnectionRunStatus::Error);
+        assert!(runs[0].error_message.as_ref().unwrap().contains("restarted"));
+
+        let request = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        assert!(request.payload.contains(r#"iterator="Start""#));
+    }
+}
+
+#[cfg(test)]
+mod sync_cursor_tests {
+    use super::*;
+    use serde_json::json;
+    use sync::SyncCursor;
+
+    #[test]
+    fn test_cursor_round_trips_through_json() {
+        let cursors = [
+            SyncCursor::QbdIterator {
+                iterator_id: Some("{abc-123}".to_string()),
+                remaining_count: 3,
+                item_type: Some("service".to_string()),
+                seen_ids: vec!["80000001-1234567890".to_string()],
+                from_modified_date: Some("2026-03-01T00:00:00Z".parse().unwrap()),
+            },
+            SyncCursor::QbdIterator {
+                iterator_id: None,
+                remaining_count: 1,
+                item_type: None,
+                seen_ids: Vec::new(),
+                from_modified_date: None,
+            },
+            SyncCursor::RestOffset { skip: 200 },
+        ];
+        for cursor in cursors {
+            assert_eq!(SyncCursor::parse(&cursor.to_value()).unwrap(), cursor);
+        }
+
+        //the shape the poll has always stored still reads as an iterator
+        let stored = json!({"iterator_id": "{abc-123}", "remaining_count": 3, "item_type": "inventory"});
+        let cursor = SyncCursor::parse(&stored).unwrap();
+        assert!(matches!(
+            &cursor,
+            SyncCursor::QbdIterator { iterator_id: Some(id), remaining_count: 3, seen_ids, .. }
+                if id == "{abc-123}" && seen_ids.is_empty()
+        ));
+        assert_eq!(cursor.to_value(), stored);
+        assert_eq!(SyncCursor::parse(&json!({"skip": 100})).unwrap(), SyncCursor::RestOffset { skip: 100 });
+    }
+
+    #[test]
+    fn test_malformed_cursor_does_not_parse() {
+        for value in [
+            json!({"iterator_id": 42, "remaining_count": 3}),
+            json!({"iterator_id": "{abc-123}", "remaining_count": "three"}),
+            json!({"iterator_id": "{abc-123}"}),
+            json!({"skip": -1}),
+            json!({}),
+            json!("{abc-123}"),
+        ] {
+            assert!(SyncCursor::parse(&value).is_err(), "{value} should not parse");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_malformed_stored_cursor_starts_pass_over() {
+        let db = setup_sync_db().await;
+        let conn = connection_model();
+        let svc = QbdPollService::new(db.clone());
+        let orchestrator = SyncOrchestrator::new(db.clone(), &svc);
+
+        //an iterator id that isn't a string: the page can't be continued
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        let mut active: entity::erp_connection_sync_state::ActiveModel = state.into();
+        active.sync_cursor = Set(Some(json!({
+            "iterator_id": 42,
+            "remaining_count": 3,
+            "item_type": "inventory",
+        })));
+        active.update(&db).await.unwrap();
+
+        let progress = svc.sync_progress(&conn).await.unwrap();
+        assert!(progress.iterator_id.is_none());
+        assert!(progress.remaining_count.is_none());
+        assert!(!progress.in_progress);
+
+        let request = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        assert!(request.payload.contains(r#"iterator="Start""#));
+
+        //the first page of the new pass replaces the malformed cursor
+        orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(TWO_ITEM_RS.to_string()),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert_eq!(
+            state.sync_cursor,
+            Some(json!({"iterator_id": "{abc-123}", "remaining_count": 3, "item_type": "inventory"}))
+        );
+    }
+}
+
+/// Helper to build a self-closing ItemInventoryQueryRs carrying only a status
+fn status_rs(code: &str, severity: &str, message: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <ItemInventoryQueryRs requestID="1" statusCode="{code}" statusSeverity="{severity}" statusMessage="{message}" />
+  </QBXMLMsgsRs>
+</QBXML>"#
+    )
+}
+
+#[cfg(test)]
+mod status_code_tests {
+    use super::*;
+    use entity::sea_orm_active_enums::{ConnectionRunStatus, SyncEventMethod, SyncEventStatus};
+    use sea_orm::{ColumnTrait, QueryFilter};
+    use sync::PullError;
+
+    /// Helper to pull one page answered with `body`; returns the orchestrator's result
+    async fn pull_page(
+        db: &DatabaseConnection,
+        body: String,
+    ) -> Result<sync::PullOutcome, PullError> {
+        let conn = connection_model();
+        let svc = QbdPollService::new(db.clone());
+        let orchestrator = SyncOrchestrator::new(db.clone(), &svc);
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(body),
+                    error: None,
+                },
+            )
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_no_records_is_an_empty_successful_page() {
+        let db = setup_sync_db().await;
+
+        let outcome = pull_page(
+            &db,
+            status_rs("1", "Info", "A query request did not find a matching object in QuickBooks"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(outcome, sync::PullOutcome::default());
+        let events = entity::sync_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, SyncEventStatus::Pending);
+        assert!(events[0].last_error.is_none());
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_ne!(runs[0].status, ConnectionRunStatus::Error);
+    }
+
+    #[tokio::test]
+    async fn test_warning_applies_page_and_keeps_note() {
+        let db = setup_sync_db().await;
+        let body = inventory_page(2).replace(
+            r#"statusCode="0" statusSeverity="Info" statusMessage="Status OK""#,
+            r#"statusCode="500" statusSeverity="Warn" statusMessage="Object 80000009 not found""#,
+        );
+
+        let outcome = pull_page(&db, body).await.unwrap();
+
+        assert_eq!(outcome.applied.len(), 2);
+        assert_eq!(outcome.warnings.len(), 1);
+        assert!(outcome.warnings[0].contains("QBD status 500"));
+        let events = entity::sync_event::Entity::find()
+            .filter(entity::sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(events[0].status, SyncEventStatus::Pending);
+        let last_error = events[0].last_error.as_ref().unwrap();
+        assert!(last_error["warnings"][0].as_str().unwrap().contains("80000009"));
+        assert!(last_error.get("errors").is_none());
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_ne!(runs[0].status, ConnectionRunStatus::Error);
+    }
+
+    #[tokio::test]
+    async fn test_error_status_fails_event_with_mapped_code() {
+        let db = setup_sync_db().await;
+
+        let result = pull_page(&db, status_rs("3200", "Error", "The edit sequence is out-of-date")).await;
+
+        match result {
+            Err(PullError::Status(status)) => {
+                assert_eq!(status.code, "3200");
+                assert!(!status.retryable);
+            }
+            other => panic!("expected a status error, got {other:?}"),
+        }
+        let events = entity::sync_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events[0].status, SyncEventStatus::Error);
+        let last_error = events[0].last_error.as_ref().unwrap();
+        assert_eq!(last_error["code"], "3200");
+        assert_eq!(last_error["retryable"], false);
+        assert!(last_error["message"].as_str().unwrap().contains("edit sequence"));
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs[0].status, ConnectionRunStatus::Error);
+        //backed off like any provider error
+        let state = entity::erp_connection_sync_state::Entity::find()
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(state.rate_limit_backoff_until.is_some());
+    }
+}
+
+/// Helper to build a single-page ItemInventoryQueryRs with `count` items
+fn inventory_page(count: usize) -> String {
+    inventory_page_with_qty(count, 0)
+}
+
+/// Same items as `inventory_page`, with `qty_offset` added to every QuantityOnHand
+fn inventory_page_with_qty(count: usize, qty_offset: usize) -> String {
+    let items: String = (1..=count)
+        .map(|i| {
+            let qty = i + qty_offset;
+            format!(
+                "<ItemInventoryRet><ListID>8000{i:04}-1234567890</ListID><Name>Item {i}</Name>\
+                 <FullName>Items:Item {i}</FullName><QuantityOnHand>{qty}</QuantityOnHand></ItemInventoryRet>"
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <ItemInventoryQueryRs requestID="1" statusCode="0" statusSeverity="Info" statusMessage="Status OK" iteratorRemainingCount="0" iteratorID="{{abc-123}}">
+      {items}
+    </ItemInventoryQueryRs>
+  </QBXMLMsgsRs>
+</QBXML>"#
+    )
+}
+
+#[cfg(test)]
+mod large_page_tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    ///applies one page through the orchestrator and returns (system_id, name, qty) per record
+    async fn apply_page(
+        db: &DatabaseConnection,
+        svc: &QbdPollService,
+        xml: &str,
+    ) -> BTreeSet<(String, Option<String>, Option<i32>)> {
+        let conn = connection_model();
+        let orchestrator = SyncOrchestrator::new(db.clone(), svc);
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        let outcome = orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(xml.to_string()),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert!(outcome.errors.is_empty());
+
+        let records = entity::inventory_record::Entity::find().all(db).await.unwrap();
+        let events = entity::inventory_record_event::Entity::find().all(db).await.unwrap();
+        records
+            .iter()
+            .map(|r| {
+                let event = events.iter().find(|e| e.inventory_record_id == r.id).unwrap();
+                (r.system_id.clone(), event.name.clone(), event.qty)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_hundred_item_page_applies_every_item() {
+        let xml = inventory_page(100);
+        let db = setup_sync_db().await;
+        let svc = QbdPollService::new(db.clone());
+
+        let applied = apply_page(&db, &svc, &xml).await;
+        assert_eq!(applied.len(), 100);
+        assert!(applied.contains(&("80000099-1234567890".to_string(), Some("Item 99".to_string()), Some(99))));
+        let messages = entity::outbox::Entity::find().all(&db).await.unwrap();
+        assert_eq!(messages.len(), 100);
+
+        //the same page again only updates: no new records or events
+        let reapplied = apply_page(&db, &svc, &xml).await;
+        assert_eq!(reapplied, applied);
+        let events = entity::inventory_record_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events.len(), 100);
+    }
+}
+
+#[cfg(test)]
+mod record_event_linkage_tests {
+    use super::*;
+    use entity::sea_orm_active_enums::{SyncEventMethod, SyncEventStatus};
+    use sea_orm::{ColumnTrait, QueryFilter};
+
+    ///takes the next request and applies `xml` as its response
+    async fn apply_page(db: &DatabaseConnection, svc: &QbdPollService, xml: &str) {
+        let conn = connection_model();
+        let orchestrator = SyncOrchestrator::new(db.clone(), svc);
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(xml.to_string()),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn item_events(
+        db: &DatabaseConnection,
+        method: SyncEventMethod,
+    ) -> Vec<entity::sync_event::Model> {
+        entity::sync_event::Entity::find()
+            .filter(entity::sync_event::Column::SyncEventMethod.eq(method))
+            .all(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_upsert_links_sync_events_to_record_events() {
+        let db = setup_sync_db().await;
+        let svc = QbdPollService::new(db.clone());
+        let xml = inventory_page(3);
+
+        apply_page(&db, &svc, &xml).await;
+
+        let list_event = item_events(&db, SyncEventMethod::List).await.remove(0);
+        let record_events = entity::inventory_record_event::Entity::find().all(&db).await.unwrap();
+        let created = item_events(&db, SyncEventMethod::Create).await;
+        assert_eq!(created.len(), 3);
+        for event in &created {
+            let record_event_id = event.inventory_record_event_id.expect("linked record event");
+            assert!(record_events.iter().any(|e| e.id == record_event_id));
+            assert_eq!(event.status, SyncEventStatus::Success);
+            assert_eq!(event.connection_run_id, list_event.connection_run_id);
+            assert_eq!(
+                event.details.as_ref().unwrap()["parent_event_uuid"],
+                serde_json::json!(list_event.uuid)
+            );
+        }
+        //the recurring List event is not tied to a single record event
+        assert_eq!(list_event.inventory_record_event_id, None);
+
+        //a changed page links Update events to the new record events it wrote
+        apply_page(&db, &svc, &inventory_page_with_qty(3, 10)).await;
+        let updated = item_events(&db, SyncEventMethod::Update).await;
+        assert_eq!(updated.len(), 3);
+        for event in &updated {
+            let record_event_id = event.inventory_record_event_id.unwrap();
+            assert!(created.iter().all(|c| c.inventory_record_event_id != Some(record_event_id)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod change_history_tests {
+    use super::*;
+    use sea_orm::{ColumnTrait, QueryFilter, QueryOrder};
+
+    async fn apply_page(db: &DatabaseConnection, svc: &QbdPollService, xml: &str) {
+        let conn = connection_model();
+        let orchestrator = SyncOrchestrator::new(db.clone(), svc);
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(xml.to_string()),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    ///qty of every event of the page's first item, oldest first
+    async fn first_item_history(db: &DatabaseConnection) -> Vec<Option<i32>> {
+        let record = entity::inventory_record::Entity::find()
+            .filter(entity::inventory_record::Column::SystemId.eq("80000001-1234567890"))
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap();
+        entity::inventory_record_event::Entity::find()
+            .filter(entity::inventory_record_event::Column::InventoryRecordId.eq(record.id))
+            .order_by_asc(entity::inventory_record_event::Column::Id)
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.qty)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_changed_values_append_events() {
+        let db = setup_sync_db().await;
+        let svc = QbdPollService::new(db.clone());
+
+        apply_page(&db, &svc, &inventory_page(2)).await;
+        apply_page(&db, &svc, &inventory_page_with_qty(2, 5)).await;
+
+        //the first version stays as history next to the new one
+        assert_eq!(first_item_history(&db).await, vec![Some(1), Some(6)]);
+        let events = entity::inventory_record_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events.len(), 4);
+        let messages = entity::outbox::Entity::find().all(&db).await.unwrap();
+        assert_eq!(messages.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_fractional_qty_is_stored_and_tracked() {
+        let db = setup_sync_db().await;
+        let svc = QbdPollService::new(db.clone());
+        let page = |qty: &str| inventory_page(1).replace(
+            "<QuantityOnHand>1</QuantityOnHand>",
+            &format!("<QuantityOnHand>{qty}</QuantityOnHand>"),
+        );
+
+        apply_page(&db, &svc, &page("10.5")).await;
+        //same whole units, different fraction: still a change
+        apply_page(&db, &svc, &page("10.75")).await;
+        apply_page(&db, &svc, &page("10.75")).await;
+
+        let events = entity::inventory_record_event::Entity::find()
+            .order_by_asc(entity::inventory_record_event::Column::Id)
+            .all(&db)
+            .await
+            .unwrap();
+        let quantities: Vec<_> = events.iter().map(|e| (e.qty, e.qty_milli)).collect();
+        assert_eq!(quantities, vec![(Some(10), Some(10_500)), (Some(10), Some(10_750))]);
+    }
+
+    #[tokio::test]
+    async fn test_identical_repull_writes_no_events() {
+        let db = setup_sync_db().await;
+        let svc = QbdPollService::new(db.clone());
+        let xml = inventory_page(2);
+
+        apply_page(&db, &svc, &xml).await;
+        let record_updated_at: Vec<_> = entity::inventory_record::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.updated_at)
+            .collect();
+
+        apply_page(&db, &svc, &xml).await;
+
+        assert_eq!(first_item_history(&db).await, vec![Some(1)]);
+        let events = entity::inventory_record_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events.len(), 2);
+        //no outbox messages or per-item sync events for the unchanged pull
+        let messages = entity::outbox::Entity::find().all(&db).await.unwrap();
+        assert_eq!(messages.len(), 2);
+        let item_events = entity::sync_event::Entity::find()
+            .filter(entity::sync_event::Column::InventoryRecordEventId.is_not_null())
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(item_events.len(), 2);
+        //records are left untouched as well
+        let after: Vec<_> = entity::inventory_record::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|r| r.updated_at)
+            .collect();
+        assert_eq!(after, record_updated_at);
+    }
+}
+
+#[cfg(test)]
+mod reprocess_tests {
+    use super::*;
+    use sea_orm::{ColumnTrait, QueryFilter, QueryOrder};
+
+    const WIDGET: &str = "80000001-1234567890";
+
+    async fn apply_page(db: &DatabaseConnection, svc: &QbdPollService, xml: &str) {
+        let conn = connection_model();
+        let orchestrator = SyncOrchestrator::new(db.clone(), svc);
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(xml.to_string()),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    async fn widget(db: &DatabaseConnection) -> entity::inventory_record::Model {
+        entity::inventory_record::Entity::find()
+            .filter(entity::inventory_record::Column::SystemId.eq(WIDGET))
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    ///price of every Widget event, oldest first
+    async fn widget_prices(db: &DatabaseConnection) -> Vec<Option<i32>> {
+        let record = widget(db).await;
+        entity::inventory_record_event::Entity::find()
+            .filter(entity::inventory_record_event::Column::InventoryRecordId.eq(record.id))
+            .order_by_asc(entity::inventory_record_event::Column::Id)
+            .all(db)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.price)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_reprocess_rederives_price_and_keeps_raw_body() {
+        let db = setup_sync_db().await;
+        let svc = QbdPollService::new(db.clone());
+        apply_page(&db, &svc, TWO_ITEM_RS).await;
+
+        //stand-in for data pulled before a normalization fix: 19.99 stored as whole dollars
+        let record = widget(&db).await;
+        let event = entity::inventory_record_event::Entity::find()
+            .filter(entity::inventory_record_event::Column::InventoryRecordId.eq(record.id))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut active: entity::inventory_record_event::ActiveModel = event.into();
+        active.price = Set(Some(1900));
+        active.update(&db).await.unwrap();
+
+        let summary = svc.reprocess(&connection_model(), None).await.unwrap();
+        assert_eq!(summary.scanned, 2);
+        assert_eq!(summary.updated, 1);
+        assert_eq!(summary.unchanged, 1);
+        assert_eq!(summary.skipped, 0);
+
+        //the corrected price is appended; the raw body is untouched
+        assert_eq!(widget_prices(&db).await, vec![Some(1900), Some(1999)]);
+        let after = widget(&db).await;
+        assert_eq!(after.original_record_body, record.original_record_body);
+        assert_eq!(after.original_record_body.unwrap()["SalesPrice"], "19.99");
+        let messages = entity::outbox::Entity::find().all(&db).await.unwrap();
+        assert_eq!(messages.len(), 3);
+
+        //nothing left to correct
+        let again = svc.reprocess(&connection_model(), None).await.unwrap();
+        assert_eq!(again.updated, 0);
+        assert_eq!(again.unchanged, 2);
+    }
+
+    #[tokio::test]
+    async fn test_reprocess_since_skips_older_records() {
+        let db = setup_sync_db().await;
+        let svc = QbdPollService::new(db.clone());
+        apply_page(&db, &svc, TWO_ITEM_RS).await;
+
+        let since = chrono::Utc::now() + chrono::Duration::hours(1);
+        let summary = svc.reprocess(&connection_model(), Some(since)).await.unwrap();
+        assert_eq!(summary.scanned, 0);
+        assert_eq!(widget_prices(&db).await, vec![Some(1999)]);
+    }
+}
+
+#[cfg(test)]
+mod field_mapping_tests {
+    use super::*;
+    use field_mapping::QbdFieldMapping;
+
+    const MPN_PAGE: &str = r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <ItemInventoryQueryRs requestID="1" statusCode="0" statusSeverity="Info" statusMessage="Status OK" iteratorRemainingCount="0">
+      <ItemInventoryRet><ListID>80000001-1234567890</ListID><Name>Widget</Name><FullName>Hardware:Widget</FullName><ManufacturerPartNumber>MPN-778</ManufacturerPartNumber><QuantityOnHand>4</QuantityOnHand></ItemInventoryRet>
+    </ItemInventoryQueryRs>
+  </QBXMLMsgsRs>
+</QBXML>"#;
+
+    async fn apply_page(db: &DatabaseConnection, conn: &connection_identity::Model) {
+        let svc = QbdPollService::new(db.clone());
+        let orchestrator = SyncOrchestrator::new(db.clone(), &svc);
+        orchestrator.next_request(conn).await.unwrap().unwrap();
+        orchestrator
+            .apply_response(
+                conn,
+                PullResponse {
+                    body: Some(MPN_PAGE.to_string()),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_mpn_mapped_to_external_code() {
+        let db = setup_sync_db().await;
+        let conn = connection_identity::Model {
+            field_mapping: Some(serde_json::json!({"external_code": "ManufacturerPartNumber"})),
+            ..connection_model()
+        };
+
+        apply_page(&db, &conn).await;
+
+        let record = entity::inventory_record::Entity::find().one(&db).await.unwrap().unwrap();
+        let event = entity::inventory_record_event::Entity::find().one(&db).await.unwrap().unwrap();
+        //unmapped fields keep their defaults
+        assert_eq!(record.system_id, "80000001-1234567890");
+        assert_eq!(event.name.as_deref(), Some("Widget"));
+        assert_eq!(event.external_code.as_deref(), Some("MPN-778"));
+    }
+
+    #[tokio::test]
+    async fn test_default_mapping_uses_full_name() {
+        let db = setup_sync_db().await;
+
+        apply_page(&db, &connection_model()).await;
+
+        let event = entity::inventory_record_event::Entity::find().one(&db).await.unwrap().unwrap();
+        assert_eq!(event.external_code.as_deref(), Some("Hardware:Widget"));
+    }
+
+    #[test]
+    fn test_missing_mapped_field_falls_back_to_default() {
+        let mapping = QbdFieldMapping::from_json(&serde_json::json!({"external_code": "BarCodeValue"})).unwrap();
+        let mut item = qbxml::parse_item_query_response(MPN_PAGE, qbxml::QbdItemType::Inventory)
+            .unwrap()
+            .items
+            .remove(0);
+
+        mapping.apply(&mut item);
+
+        assert_eq!(item.full_name.as_deref(), Some("Hardware:Widget"));
+    }
+
+    #[test]
+    fn test_invalid_mapping_is_rejected() {
+        assert!(QbdFieldMapping::from_json(&serde_json::json!({"sku": "Name"})).is_err());
+        assert!(QbdFieldMapping::from_json(&serde_json::json!({"name": ""})).is_err());
+        assert!(QbdFieldMapping::from_json(&serde_json::json!("ListID")).is_err());
+    }
+}
+
+#[cfg(test)]
+mod quarantine_tests {
+    use super::*;
+    use poll_services::ReplayOutcome;
+
+    const BAD_LIST_ID: &str = "80000002-1234567890";
+
+    ///makes every insert of the second item's inventory record fail
+    async fn reject_second_item(db: &DatabaseConnection) {
+        db.execute_unprepared(&format!(
+            "CREATE TRIGGER reject_item BEFORE INSERT ON inventory_record \
+             WHEN NEW.system_id = '{BAD_LIST_ID}' BEGIN SELECT RAISE(ABORT, 'rejected'); END"
+        ))
+        .await
+        .unwrap();
+    }
+
+    ///pulls `inventory_page(3)` with the second item failing
+    async fn pull_with_failed_item(db: &DatabaseConnection) -> sync::PullOutcome {
+        reject_second_item(db).await;
+        let conn = connection_model();
+        let svc = QbdPollService::new(db.clone());
+        let orchestrator = SyncOrchestrator::new(db.clone(), &svc);
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(inventory_page(3)),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_failed_upsert_is_quarantined_and_page_commits() {
+        let db = setup_sync_db().await;
+
+        let outcome = pull_with_failed_item(&db).await;
+
+        assert_eq!(outcome.applied.len(), 2);
+        assert_eq!(outcome.errors.len(), 1);
+        assert!(outcome.errors[0].contains(BAD_LIST_ID));
+        let records = entity::inventory_record::Entity::find().all(&db).await.unwrap();
+        assert_eq!(records.len(), 2);
+        let quarantined = entity::quarantine::Entity::find().all(&db).await.unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].connection_id, 1);
+        assert_eq!(quarantined[0].list_id, BAD_LIST_ID);
+        assert_eq!(quarantined[0].item_type.as_deref(), Some("inventory"));
+        assert_eq!(quarantined[0].raw_body["ListID"], BAD_LIST_ID);
+        assert!(quarantined[0].error.contains("rejected"));
+        assert_eq!(quarantined[0].attempts, 1);
+    }
+
+    ///acts like Postgres after a failed statement: once the second item's record is
+    ///written, its event insert fails and every later write is refused until the
+    ///transaction is rolled back past that record
+    async fn abort_transaction_on_second_item(db: &DatabaseConnection) {
+        for sql in [
+            "CREATE TABLE aborted_txn (id INTEGER NOT NULL)".to_string(),
+            format!(
+                "CREATE TRIGGER poison_txn AFTER INSERT ON inventory_record \
+                 WHEN NEW.system_id = '{BAD_LIST_ID}' BEGIN INSERT INTO aborted_txn VALUES (1); END"
+            ),
+            "CREATE TRIGGER reject_event BEFORE INSERT ON inventory_record_event \
+             WHEN EXISTS (SELECT 1 FROM aborted_txn) BEGIN SELECT RAISE(ABORT, 'rejected'); END"
+                .to_string(),
+        ] {
+            db.execute_unprepared(&sql).await.unwrap();
+        }
+        for (name, event) in [
+            ("inventory_record", "INSERT"),
+            ("outbox", "INSERT"),
+            ("quarantine", "INSERT"),
+            ("sync_event", "UPDATE"),
+        ] {
+            db.execute_unprepared(&format!(
+                "CREATE TRIGGER aborted_{name} BEFORE {event} ON {name} \
+                 WHEN EXISTS (SELECT 1 FROM aborted_txn) \
+                 BEGIN SELECT RAISE(ABORT, 'current transaction is aborted'); END"
+            ))
+            .await
+            .unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_failed_item_is_rolled_back_before_quarantine() {
+        let db = setup_sync_db().await;
+        abort_transaction_on_second_item(&db).await;
+        let conn = connection_model();
+        let svc = QbdPollService::new(db.clone());
+        let orchestrator = SyncOrchestrator::new(db.clone(), &svc);
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+
+        let outcome = orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(inventory_page(3)),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(outcome.applied.len(), 2);
+        assert_eq!(outcome.errors.len(), 1);
+        assert!(outcome.errors[0].contains("rejected"));
+        //the failed item's record went with its savepoint
+        let records = entity::inventory_record::Entity::find().all(&db).await.unwrap();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.system_id != BAD_LIST_ID));
+        let quarantined = entity::quarantine::Entity::find().all(&db).await.unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].list_id, BAD_LIST_ID);
+        assert_eq!(entity::outbox::Entity::find().all(&db).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_replay_applies_item_and_clears_quarantine() {
+        let db = setup_sync_db().await;
+        pull_with_failed_item(&db).await;
+        db.execute_unprepared("DROP TRIGGER reject_item").await.unwrap();
+        let entry = entity::quarantine::Entity::find().one(&db).await.unwrap().unwrap();
+
+        let outcome = QbdPollService::new(db.clone())
+            .replay_quarantined(&connection_model(), &entry)
+            .await
+            .unwrap();
+
+        assert_eq!(outcome, ReplayOutcome::Applied);
+        assert!(entity::quarantine::Entity::find().all(&db).await.unwrap().is_empty());
+        let records = entity::inventory_record::Entity::find().all(&db).await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().any(|r| r.system_id == BAD_LIST_ID));
+        //announced like a pulled item: 2 from the page, 1 from the replay
+        assert_eq!(entity::outbox::Entity::find().all(&db).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_replay_keeps_item_with_new_attempt() {
+        let db = setup_sync_db().await;
+        pull_with_failed_item(&db).await;
+        let entry = entity::quarantine::Entity::find().one(&db).await.unwrap().unwrap();
+
+        let outcome = QbdPollService::new(db.clone())
+            .replay_quarantined(&connection_model(), &entry)
+            .await
+            .unwrap();
+
+        assert!(matches!(outcome, ReplayOutcome::Failed(ref e) if e.contains("rejected")));
+        let quarantined = entity::quarantine::Entity::find().all(&db).await.unwrap();
+        assert_eq!(quarantined.len(), 1);
+        assert_eq!(quarantined[0].uuid, entry.uuid);
+        assert_eq!(quarantined[0].attempts, 2);
+        assert_eq!(entity::inventory_record::Entity::find().all(&db).await.unwrap().len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod qbxml_version_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connection_pinned_to_older_version_gets_that_header() {
+        let db = setup_sync_db().await;
+        let mut conn = connection_model();
+        conn.qbxml_version = Some("6.0".to_string());
+        let svc = QbdPollService::new(db.clone());
+
+        let request = SyncOrchestrator::new(db.clone(), &svc)
+            .next_request(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(request.payload.contains(r#"<?qbxml version="6.0"?>"#));
+        assert!(request.payload.contains("ItemInventoryQueryRq"));
+    }
+
+    #[tokio::test]
+    async fn test_connection_without_version_gets_default_header() {
+        let db = setup_sync_db().await;
+        let svc = QbdPollService::new(db.clone());
+
+        let request = SyncOrchestrator::new(db.clone(), &svc)
+            .next_request(&connection_model())
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert!(request.payload.contains(r#"<?qbxml version="13.0"?>"#));
+    }
+}
+
+#[cfg(test)]
+mod sync_progress_tests {
+    use super::*;
+    use entity::sea_orm_active_enums::SyncEventStatus;
+
+    #[tokio::test]
+    async fn test_mid_pass_cursor_reports_remaining_count() {
+        let db = setup_sync_db().await;
+        let conn = connection_model();
+        let svc = QbdPollService::new(db.clone());
+        let orchestrator = SyncOrchestrator::new(db.clone(), &svc);
+
+        //first page of a pass: two items applied, QBD reports three still to come
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(TWO_ITEM_RS.to_string()),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let progress = svc.sync_progress(&conn).await.unwrap();
+        assert_eq!(progress.iterator_id.as_deref(), Some("{abc-123}"));
+        assert_eq!(progress.remaining_count, Some(3));
+        assert_eq!(progress.item_type, Some("inventory"));
+        assert_eq!(progress.event_status, Some(SyncEventStatus::Pending));
+        assert_eq!(progress.records_synced, 2);
+        assert!(progress.in_progress);
+        assert_eq!(progress.percent_complete.as_deref(), Some("40.0%"));
+    }
+
+    #[tokio::test]
+    async fn test_never_synced_connection_has_no_progress() {
+        let db = setup_sync_db().await;
+        let svc = QbdPollService::new(db.clone());
+
+        let progress = svc.sync_progress(&connection_model()).await.unwrap();
+        assert!(progress.remaining_count.is_none());
+        assert!(progress.event_uuid.is_none());
+        assert_eq!(progress.records_synced, 0);
+        assert!(!progress.in_progress);
+        assert!(progress.percent_complete.is_none());
+    }
+}
+
+/// Helper to read the connection's `initial_sync_completed_at` (`scopes` can't be read back
+/// from SQLite, so only that column is selected)
+async fn initial_sync_completed_at(
+    db: &DatabaseConnection,
+) -> Option<chrono::DateTime<chrono::FixedOffset>> {
+    use sea_orm::{QuerySelect, QueryFilter, ColumnTrait};
+    connection_identity::Entity::find()
+        .select_only()
+        .column(connection_identity::Column::InitialSyncCompletedAt)
+        .filter(connection_identity::Column::Id.eq(1))
+        .into_tuple::<Option<chrono::DateTime<chrono::FixedOffset>>>()
+        .one(db)
+        .await
+        .unwrap()
+        .flatten()
+}
+
+#[cfg(test)]
+mod initial_sync_tests {
+    use super::*;
+
+    /// Helper to pull one page answered with `body`, letting the next poll run right away
+    async fn pull_page(db: &DatabaseConnection, body: String) {
+        let conn = connection_model();
+        let svc = QbdPollService::new(db.clone());
+        let orchestrator = SyncOrchestrator::new(db.clone(), &svc);
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: Some(body),
+                    error: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        let mut active: entity::erp_connection_sync_state::ActiveModel = state.into();
+        active.next_poll_at = Set(None);
+        active.update(db).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_set_once_on_first_completed_pass() {
+        let db = setup_sync_db().await;
+        let mut active = connection_model().into_active_model().reset_all();
+        active.scopes = NotSet;
+        connection_identity::Entity::insert(active)
+            .exec_without_returning(&db)
+            .await
+            .unwrap();
+
+        //first page of the initial pass: three items still to come
+        pull_page(&db, TWO_ITEM_RS.to_string()).await;
+        assert!(initial_sync_completed_at(&db).await.is_none());
+
+        //last page: the pass is complete
+        pull_page(&db, inventory_page(3)).await;
+        let completed_at = initial_sync_completed_at(&db).await.expect("stamped on completion");
+
+        //a later full pass leaves the first completion in place
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        pull_page(&db, inventory_page(3)).await;
+        assert_eq!(initial_sync_completed_at(&db).await, Some(completed_at));
+    }
+}
+
+#[cfg(test)]
+mod environment_mode_tests {
+    use super::*;
+
+    /// Helper to build a mock that only answers the credential lookup, for a sandbox
+    /// connection; any further query fails the test
+    fn sandbox_db() -> DatabaseConnection {
+        let conn = connection_identity::Model {
+            environment: ErpEnvironment::Sandbox,
+            ..connection_model()
+        };
+        MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[(credentials_model(), Some(conn))]])
+            .into_connection()
+    }
+
+    #[tokio::test]
+    async fn test_sandbox_connection_gets_no_work_on_production_server() {
+        let out = QbdPollService::new(sandbox_db())
+            .with_required_environment(Some(ErpEnvironment::Production))
+            .handle_request(USERNAME, PASSWORD, Some("16.0"))
+            .await
+            .unwrap();
+        assert!(!out.has_work);
+        assert!(out.xml.is_none());
+
+        let out = QbdPollService::new(sandbox_db())
+            .with_required_environment(Some(ErpEnvironment::Production))
+            .handle_response(USERNAME, PASSWORD, dry_run_input(Some(TWO_ITEM_RS)))
+            .await
+            .unwrap();
+        assert!(!out.has_more);
+        assert!(out.dry_run.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_environment_check_runs_after_credentials() {
+        let denied = QbdPollService::new(sandbox_db())
+            .with_required_environment(Some(ErpEnvironment::Production))
+            .handle_request(USERNAME, "wrong", None)
+            .await;
+        assert!(matches!(denied, Err(QbdPollError::Unauthorized)));
+    }
+}
+
+#[cfg(test)]
+mod poll_decision_tests {
+    use super::*;
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    /// Log lines written by the test's subscriber.
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl CapturedLogs {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    /// Helper to run `handle_request` against a mock that only answers the credential lookup
+    /// for `conn`, capturing what it logs
+    async fn logged_request(
+        conn: connection_identity::Model,
+        service: impl FnOnce(DatabaseConnection) -> QbdPollService,
+    ) -> (poll_services::PollRequestOutput, String) {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[(credentials_model(), Some(conn))]])
+            .into_connection();
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .with_writer(move || writer.clone())
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let out = service(db)
+            .handle_request(USERNAME, PASSWORD, Some("16.0"))
+            .await
+            .unwrap();
+        (out, logs.text())
+    }
+
+    #[tokio::test]
+    async fn test_disabled_connection_logs_disabled_decision() {
+        let conn = connection_identity::Model {
+            is_enabled: false,
+            ..connection_model()
+        };
+        let (out, logs) = logged_request(conn, QbdPollService::new).await;
+
+        assert!(!out.has_work);
+        assert!(out.xml.is_none());
+        assert!(logs.contains("decision=\"disabled\""), "{logs}");
+        assert!(logs.contains("connection_id=1"), "{logs}");
+        assert!(logs.contains("reason=\"connection is disabled\""), "{logs}");
+        assert_eq!(logs.matches("Poll decision").count(), 1, "logged once: {logs}");
+    }
+
+    #[tokio::test]
+    async fn test_wrong_environment_logs_its_decision() {
+        let conn = connection_identity::Model {
+            environment: ErpEnvironment::Sandbox,
+            ..connection_model()
+        };
+        let (out, logs) = logged_request(conn, |db| {
+            QbdPollService::new(db).with_required_environment(Some(ErpEnvironment::Production))
+        })
+        .await;
+
+        assert!(!out.has_work);
+        assert!(logs.contains("decision=\"wrong_environment\""), "{logs}");
+    }
+}
+
+#[cfg(test)]
+mod secret_store_tests {
+    use super::*;
+    use futures_util::future::BoxFuture;
+    use secret_store::{SecretResolver, SecretStore, SecretStoreError, StoredSecrets};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    const STORE_PASSWORD: &str = "from-the-store";
+
+    /// Fake store holding one secret at `acme`; records the (path, version) of each load
+    #[derive(Default)]
+    struct FakeStore {
+        loads: Mutex<Vec<(String, Option<String>)>>,
+    }
+
+    impl SecretStore for FakeStore {
+        fn load<'a>(
+            &'a self,
+            path: &'a str,
+            version: Option<&'a str>,
+        ) -> BoxFuture<'a, Result<StoredSecrets, SecretStoreError>> {
+            self.loads
+                .lock()
+                .unwrap()
+                .push((path.to_string(), version.map(str::to_string)));
+            Box::pin(async move {
+                match path {
+                    "acme" => Ok(HashMap::from([(
+                        "provider_password".to_string(),
+                        STORE_PASSWORD.to_string(),
+                    )])),
+                    _ => Err(SecretStoreError::NotFound(path.to_string())),
+                }
+            })
+        }
+    }
+
+    /// Helper to build a mock answering the credential lookup for a connection whose
+    /// secrets live at `secret_storage_ref`; the DB column keeps the old password. The
+    /// connection is a sandbox one, so a successful check ends the request right there.
+    fn stored_db(secret_storage_ref: Option<&str>) -> DatabaseConnection {
+        let conn = connection_identity::Model {
+            environment: ErpEnvironment::Sandbox,
+            secret_storage_ref: secret_storage_ref.map(str::to_string),
+            secret_version: Some("3".to_string()),
+            ..connection_model()
+        };
+        MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[(credentials_model(), Some(conn))]])
+            .into_connection()
+    }
+
+    fn service(db: DatabaseConnection, store: Arc<FakeStore>) -> QbdPollService {
+        QbdPollService::new(db)
+            .with_required_environment(Some(ErpEnvironment::Production))
+            .with_secret_resolver(SecretResolver::default().with_store("test", store))
+    }
+
+    #[tokio::test]
+    async fn test_store_password_authenticates_poll() {
+        let store = Arc::new(FakeStore::default());
+        let out = service(stored_db(Some("test:acme")), store.clone())
+            .handle_request(USERNAME, STORE_PASSWORD, Some("16.0"))
+            .await
+            .unwrap();
+        assert!(!out.has_work);
+        assert_eq!(
+            *store.loads.lock().unwrap(),
+            vec![("acme".to_string(), Some("3".to_string()))]
+        );
+
+        //the DB column is ignored once the connection has a ref
+        let denied = service(stored_db(Some("test:acme")), store)
+            .handle_request(USERNAME, PASSWORD, None)
+            .await;
+        assert!(matches!(denied, Err(QbdPollError::Unauthorized)));
+    }
+
+    #[tokio::test]
+    async fn test_missing_or_unknown_store_secret_is_unauthorized() {
+        let store = Arc::new(FakeStore::default());
+        for reference in ["test:other", "vault:secret/data/acme", "no-separator"] {
+            let denied = service(stored_db(Some(reference)), store.clone())
+                .handle_request(USERNAME, STORE_PASSWORD, None)
+                .await;
+            assert!(matches!(denied, Err(QbdPollError::Unauthorized)), "{reference}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_without_ref_the_db_column_is_used() {
+        let store = Arc::new(FakeStore::default());
+        let out = service(stored_db(None), store.clone())
+            .handle_request(USERNAME, PASSWORD, None)
+            .await
+            .unwrap();
+        assert!(!out.has_work);
+        assert!(store.loads.lock().unwrap().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod credential_cache_tests {
+    use super::*;
+    use credential_lookup_cache::CredentialLookupCache;
+    use std::time::Duration;
+
+    /// Helper to build a sandbox connection, so a successful check ends the request there
+    fn sandbox_connection() -> connection_identity::Model {
+        connection_identity::Model {
+            environment: ErpEnvironment::Sandbox,
+            ..connection_model()
+        }
+    }
+
+    fn service(db: DatabaseConnection, cache: &CredentialLookupCache) -> QbdPollService {
+        QbdPollService::new(db)
+            .with_required_environment(Some(ErpEnvironment::Production))
+            .with_credential_cache(Some(cache.clone()))
+    }
+
+    #[tokio::test]
+    async fn test_second_poll_is_served_from_the_cache() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[(credentials_model(), Some(sandbox_connection()))]])
+            .append_query_results([[sandbox_connection()]])
+            .into_connection();
+        let cache = CredentialLookupCache::new(10, Duration::from_secs(60));
+
+        for _ in 0..2 {
+            let out = service(db.clone(), &cache)
+                .handle_request(USERNAME, PASSWORD, None)
+                .await
+                .unwrap();
+            assert!(!out.has_work);
+        }
+
+        //only the first call read the credentials; the second reloaded the connection by id
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches(r#"FROM \"erp_connection_credentials\""#).count(), 1);
+        assert_eq!(log.matches("SELECT").count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_wrong_password_is_checked_against_the_database() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([[(credentials_model(), Some(sandbox_connection()))]])
+            .append_query_results([[(credentials_model(), Some(sandbox_connection()))]])
+            .into_connection();
+        let cache = CredentialLookupCache::new(10, Duration::from_secs(60));
+
+        service(db.clone(), &cache)
+            .handle_request(USERNAME, PASSWORD, None)
+            .await
+            .unwrap();
+        let denied = service(db.clone(), &cache)
+            .handle_request(USERNAME, "wrong", None)
+            .await;
+        assert!(matches!(denied, Err(QbdPollError::Unauthorized)));
+
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches(r#"FROM \"erp_connection_credentials\""#).count(), 2);
+    }
+}
+
+#[cfg(test)]
+mod error_response_tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use sea_orm::DbErr;
+
+    #[tokio::test]
+    async fn test_request_db_error_is_json_envelope() {
+        //the credential lookup is the first query the request phase issues
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_errors([DbErr::Custom("connection reset".to_string())])
+            .into_connection();
+
+        let Err(err) = QbdPollService::new(db).handle_request(USERNAME, PASSWORD, None).await else {
+            panic!("expected the request phase to fail");
+        };
+        assert!(matches!(err, QbdPollError::Db(_)));
+
+        let (status, body) = err.to_response();
+        assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+        let body = serde_json::to_value(&body.0).unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["code"], "database_error");
+        assert!(body["message"].as_str().unwrap().contains("connection reset"));
+    }
+
+    #[test]
+    fn test_error_status_codes() {
+        let (status, body) = QbdPollError::Unauthorized.to_response();
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body.0.code, "unauthorized");
+        assert!(!body.0.success);
+
+        let (status, body) = QbdPollError::XmlParse("XML parse error: eof".to_string()).to_response();
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(body.0.code, "invalid_response");
+        assert_eq!(body.0.message, "XML parse error: eof");
+    }
+}
+
+#[cfg(test)]
+mod poll_health_tests {
+    use super::*;
+    use clock::FixedClock;
+    use entity::sea_orm_active_enums::SyncEventStatus;
+    use poll_health::QbdPollHealthService;
+    use std::sync::Arc;
+
+    /// Helper to insert the connection and hand out its first page, leaving the inventory
+    /// sync event InProgress until QBD answers
+    async fn db_with_request_in_flight() -> DatabaseConnection {
+        let db = setup_sync_db().await;
+        let mut active = connection_model().into_active_model().reset_all();
+        active.scopes = NotSet;
+        connection_identity::Entity::insert(active)
+            .exec_without_returning(&db)
+            .await
+            .unwrap();
+
+        let conn = connection_model();
+        let svc = QbdPollService::new(db.clone());
+        SyncOrchestrator::new(db.clone(), &svc)
+            .next_request(&conn)
+            .await
+            .unwrap()
+            .unwrap();
+        let events = entity::sync_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events[0].status, SyncEventStatus::InProgress);
+        db
+    }
+
+    #[tokio::test]
+    async fn test_request_in_flight_is_ok() {
+        let db = db_with_request_in_flight().await;
+
+        let health = QbdPollHealthService::new(db).check().await.unwrap();
+        assert_eq!(health.stuck_connections, 0);
+        assert_eq!(health.stuck_events, 0);
+        assert!(!health.degraded);
+    }
+
+    #[tokio::test]
+    async fn test_stale_in_progress_event_is_degraded() {
+        let db = db_with_request_in_flight().await;
+
+        //no response an hour later: the event is stuck
+        let later = chrono::Utc::now() + chrono::Duration::hours(1);
+        let health = QbdPollHealthService::new(db.clone())
+            .with_clock(Arc::new(FixedClock::new(later)))
+            .check()
+            .await
+            .unwrap();
+        assert_eq!(health.stuck_connections, 1);
+        assert_eq!(health.stuck_events, 1);
+        assert!(health.degraded);
+
+        //within the tolerance it is reported but not degraded
+        let health = QbdPollHealthService::new(db)
+            .with_clock(Arc::new(FixedClock::new(later)))
+            .with_max_stuck(1)
+            .check()
+            .await
+            .unwrap();
+        assert_eq!(health.stuck_connections, 1);
+        assert!(!health.degraded);
+    }
+
+    #[tokio::test]
+    async fn test_backoff_and_reauth_do_not_degrade() {
+        let db = db_with_request_in_flight().await;
+        let state = entity::erp_connection_sync_state::Entity::find()
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        let mut active: entity::erp_connection_sync_state::ActiveModel = state.into();
+        active.rate_limit_backoff_until = Set(Some(
+            (chrono::Utc::now() + chrono::Duration::minutes(10)).into(),
+        ));
+        active.update(&db).await.unwrap();
+        db.execute_unprepared("UPDATE connection_identity SET auth_status = 'needs_reauth'")
+            .await
+            .unwrap();
+
+        let health = QbdPollHealthService::new(db).check().await.unwrap();
+        assert_eq!(health.backed_off_connections, 1);
+        assert_eq!(health.needs_reauth_connections, 1);
+        assert!(!health.degraded);
+    }
+}
+
+/// Helper to seed the harness connection on the sync tables plus credentials
+async fn harness() -> test_support::QbdPollHarness {
+    let db = setup_sync_db().await;
+    let schema = Schema::new(db.get_database_backend());
+    db.execute(&schema.create_table_from_entity(entity::erp_connection_credentials::Entity))
+        .await
+        .unwrap();
+    //SQLite can't read the connection row back for the credential check
+    test_support::QbdPollHarness::seed(db, 1)
+        .await
+        .unwrap()
+        .without_credential_check()
+}
+
+#[cfg(test)]
+mod harness_tests {
+    use super::*;
+    use test_support::inventory_page;
+
+    #[tokio::test]
+    async fn test_two_page_pull_upserts_every_record() {
+        let harness = harness().await;
+
+        let query = harness.begin_request().await.unwrap().expect("a query for the first page");
+        assert!(query.contains("ItemInventoryQueryRq"));
+        let first = harness.respond(&inventory_page(1, 3, 2)).await.unwrap();
+        assert!(first.has_more);
+        assert_eq!(first.records.len(), 3);
+
+        let last = harness.pull(&[inventory_page(4, 2, 0)]).await.unwrap();
+        assert!(!last.has_more);
+        let ids: Vec<String> = last.records.iter().map(|r| r.system_id.clone()).collect();
+        assert_eq!(
+            ids,
+            (1..=5).map(|n| format!("8000{n:04}-1234567890")).collect::<Vec<_>>()
+        );
+        assert!(last.records.iter().all(|r| r.originating_connection_id == harness.connection().id));
+    }
+
+    #[tokio::test]
+    async fn test_empty_item_list_completes_the_pass() {
+        use entity::sea_orm_active_enums::{ConnectionRunStatus, SyncEventStatus};
+        let harness = harness().await;
+
+        harness.begin_request().await.unwrap().expect("a query for the first page");
+        //a new company file: status OK, no ItemInventoryRet, nothing remaining
+        let page = harness.respond(&inventory_page(1, 0, 0)).await.unwrap();
+
+        assert!(!page.has_more);
+        assert!(page.records.is_empty());
+        let db = harness.db();
+        let state = entity::erp_connection_sync_state::Entity::find()
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(state.sync_cursor.is_none());
+        let events = entity::sync_event::Entity::find().all(db).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, SyncEventStatus::Pending);
+        assert!(events[0].last_error.is_none());
+        let runs = entity::connection_run::Entity::find().all(db).await.unwrap();
+        assert_eq!(runs[0].status, ConnectionRunStatus::Success);
+        assert!(initial_sync_completed_at(db).await.is_some());
+        assert!(entity::inventory_record_event::Entity::find().all(db).await.unwrap().is_empty());
+        assert!(entity::outbox::Entity::find().all(db).await.unwrap().is_empty());
+    }
+}
+
+#[cfg(test)]
+mod domain_event_tests {
+    use super::*;
+    use events::bus::{ConnectionReauthNeeded, InventoryUpserted, SyncEventFailed};
+    use events::{DomainEvent, EventBus};
+    use test_support::inventory_page;
+
+    /// Helper to build a harness whose poll service publishes on a fresh bus, subscribed to
+    async fn harness_with_bus() -> (
+        test_support::QbdPollHarness,
+        tokio::sync::broadcast::Receiver<DomainEvent>,
+    ) {
+        let harness = harness().await;
+        let bus = EventBus::default();
+        let receiver = bus.subscribe();
+        let service = QbdPollService::new(harness.db().clone()).with_event_bus(Some(bus));
+        (harness.with_service(service), receiver)
+    }
+
+    fn drain(receiver: &mut tokio::sync::broadcast::Receiver<DomainEvent>) -> Vec<DomainEvent> {
+        std::iter::from_fn(|| receiver.try_recv().ok()).collect()
+    }
+
+    #[tokio::test]
+    async fn applied_page_emits_one_inventory_upserted_per_record() {
+        let (harness, mut receiver) = harness_with_bus().await;
+        harness.begin_request().await.unwrap().unwrap();
+
+        harness.respond(&inventory_page(1, 2, 0)).await.unwrap();
+
+        let conn = harness.connection();
+        let events = drain(&mut receiver);
+        let mut record_ids: Vec<String> = events
+            .iter()
+            .map(|event| match event {
+                DomainEvent::InventoryUpserted(InventoryUpserted {
+                    connection_id,
+                    connection_uuid,
+                    tenant_id,
+                    created,
+                    record_id,
+                    ..
+                }) => {
+                    assert_eq!((*connection_id, *connection_uuid, *tenant_id), (conn.id, conn.uuid, 1));
+                    assert!(created);
+                    record_id.clone()
+                }
+                other => panic!("unexpected event {other:?}"),
+            })
+            .collect();
+        record_ids.sort();
+        assert_eq!(record_ids, vec!["80000001-1234567890", "80000002-1234567890"]);
+    }
+
+    #[tokio::test]
+    async fn failed_page_emits_sync_event_failed() {
+        let (harness, mut receiver) = harness_with_bus().await;
+        harness.begin_request().await.unwrap().unwrap();
+
+        let result = harness
+            .respond(&status_rs("3200", "Error", "The edit sequence is out-of-date"))
+            .await;
+
+        assert!(result.is_err());
+        let events = drain(&mut receiver);
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            &events[0],
+            DomainEvent::SyncEventFailed(SyncEventFailed { code: Some(code), .. }) if code == "3200"
+        ));
+    }
+
+    #[tokio::test]
+    async fn permission_error_also_emits_connection_reauth_needed() {
+        let (harness, mut receiver) = harness_with_bus().await;
+        harness.begin_request().await.unwrap().unwrap();
+
+        let _ = harness
+            .respond(&status_rs("3260", "Error", "Insufficient permission level"))
+            .await;
+
+        let events = drain(&mut receiver);
+        let names: Vec<&str> = events.iter().map(DomainEvent::name).collect();
+        assert_eq!(names, vec!["sync_event_failed", "connection_reauth_needed"]);
+        let conn = harness.connection();
+        assert!(matches!(
+            &events[1],
+            DomainEvent::ConnectionReauthNeeded(ConnectionReauthNeeded {
+                tenant_id: 1,
+                connection_id,
+                connection_uuid,
+                reason,
+            }) if *connection_id == conn.id && *connection_uuid == conn.uuid && reason.contains("3260")
+        ));
+    }
+
+    #[tokio::test]
+    async fn every_subscriber_gets_its_own_copy() {
+        let (harness, mut first) = harness_with_bus().await;
+        let bus = EventBus::default();
+        let mut second = bus.subscribe();
+        let mut third = bus.subscribe();
+        let service = QbdPollService::new(harness.db().clone()).with_event_bus(Some(bus));
+        let harness = harness.with_service(service);
+        harness.begin_request().await.unwrap().unwrap();
+
+        harness.respond(&inventory_page(1, 2, 0)).await.unwrap();
+
+        assert!(drain(&mut first).is_empty(), "the replaced service's bus gets nothing");
+        assert_eq!(drain(&mut second).len(), 2);
+        assert_eq!(drain(&mut third).len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod state_machine_tests {
+    use super::*;
+    use entity::sea_orm_active_enums::{ConnectionRunStatus, SyncEventMethod, SyncEventStatus};
+    use sea_orm::{ColumnTrait, QueryFilter, QueryOrder};
+    use test_support::inventory_page;
+
+    /// Helper for the recurring List events (per-record Create/Update events left out)
+    async fn list_events(db: &DatabaseConnection) -> Vec<entity::sync_event::Model> {
+        entity::sync_event::Entity::find()
+            .filter(entity::sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
+            .all(db)
+            .await
+            .unwrap()
+    }
+
+    async fn runs(db: &DatabaseConnection) -> Vec<entity::connection_run::Model> {
+        entity::connection_run::Entity::find()
+            .order_by_asc(entity::connection_run::Column::Id)
+            .all(db)
+            .await
+            .unwrap()
+    }
+
+    async fn cursor(db: &DatabaseConnection) -> Option<serde_json::Value> {
+        entity::erp_connection_sync_state::Entity::find()
+            .one(db)
+            .await
+            .unwrap()
+            .unwrap()
+            .sync_cursor
+    }
+
+    #[tokio::test]
+    async fn test_first_poll_creates_one_event_and_one_run() {
+        let harness = harness().await;
+        let db = harness.db();
+
+        let query = harness.begin_request().await.unwrap().expect("a query for the first page");
+        assert!(query.contains(r#"iterator="Start""#));
+
+        let events = entity::sync_event::Entity::find().all(db).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].sync_event_method, SyncEventMethod::List);
+        assert_eq!(events[0].status, SyncEventStatus::InProgress);
+        assert_eq!(events[0].attempts, 1);
+        let runs = runs(db).await;
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].connection_id, harness.connection().id);
+        assert_eq!(events[0].connection_run_id, Some(runs[0].id));
+    }
+
+    #[tokio::test]
+    async fn test_pending_event_poll_starts_new_run_and_increments_attempts() {
+        let harness = harness().await;
+        let db = harness.db();
+        harness.pull(&[inventory_page(1, 2, 0)]).await.unwrap();
+
+        //the list event is recurring: back to Pending once the pass completes
+        let events = list_events(db).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, SyncEventStatus::Pending);
+
+        harness.begin_request().await.unwrap().expect("a query for the next pass");
+
+        let events = list_events(db).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, SyncEventStatus::InProgress);
+        assert_eq!(events[0].attempts, 2);
+        let runs = runs(db).await;
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].status, ConnectionRunStatus::Success);
+        assert_eq!(events[0].connection_run_id, Some(runs[1].id));
+    }
+
+    #[tokio::test]
+    async fn test_multi_page_response_advances_then_clears_cursor() {
+        let harness = harness().await;
+        let db = harness.db();
+
+        harness.begin_request().await.unwrap().unwrap();
+        let first = harness.respond(&inventory_page(1, 3, 2)).await.unwrap();
+        assert!(first.has_more);
+        let stored = cursor(db).await.expect("a cursor while pages remain");
+        assert_eq!(stored["iterator_id"], "{harness-iterator}");
+        assert_eq!(stored["remaining_count"], 2);
+        assert_eq!(list_events(db).await[0].status, SyncEventStatus::Pending);
+
+        //the next request continues the iterator instead of starting over
+        let query = harness.begin_request().await.unwrap().unwrap();
+        assert!(query.contains(r#"iterator="Continue""#));
+        assert!(query.contains("{harness-iterator}"));
+
+        let last = harness.respond(&inventory_page(4, 2, 0)).await.unwrap();
+        assert!(!last.has_more);
+        assert_eq!(last.records.len(), 5);
+        assert!(cursor(db).await.is_none());
+        let events = list_events(db).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, SyncEventStatus::Pending);
+        assert!(runs(db).await.iter().all(|r| r.status == ConnectionRunStatus::Success));
+    }
+
+    #[tokio::test]
+    async fn test_qbd_error_marks_event_and_run_error() {
+        let harness = harness().await;
+        let db = harness.db();
+
+        harness.begin_request().await.unwrap().unwrap();
+        let result = harness
+            .respond(&status_rs("3200", "Error", "The edit sequence is out-of-date"))
+            .await;
+
+        assert!(matches!(result, Err(QbdPollError::XmlParse(_))));
+        let events = list_events(db).await;
+        assert_eq!(events[0].status, SyncEventStatus::Error);
+        assert_eq!(events[0].last_error.as_ref().unwrap()["code"], "3200");
+        let runs = runs(db).await;
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].status, ConnectionRunStatus::Error);
+        assert!(cursor(db).await.is_none());
+        assert!(harness.records().await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,388 @@
+//! Tests for QuickBooks Desktop QBXML builders and parsers
+//!
+//! Run with: cargo test --test qbd_qbxml_tests
+
+#[path = "../src/client-systems/quickbooks/desktop/order_qbxml.rs"]
+mod order_qbxml;
+#[path = "../src/client-systems/quickbooks/desktop/qbxml.rs"]
+mod qbxml;
+
+use order_qbxml::*;
+use qbxml::*;
+use serde_json::json;
+
+const INVENTORY_RS: &str = r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <ItemInventoryQueryRs requestID="1" statusCode="0" statusSeverity="Info" statusMessage="Status OK" iteratorRemainingCount="12" iteratorID="{abc-123}">
+      <ItemInventoryRet>
+        <ListID>80000001-1234567890</ListID>
+        <Name>Widget</Name>
+        <FullName>Widgets:Widget</FullName>
+        <ManufacturerPartNumber>WG-100</ManufacturerPartNumber>
+        <SalesDesc>Blue widget</SalesDesc>
+        <SalesPrice>19.99</SalesPrice>
+        <IncomeAccountRef>
+          <ListID>40000001-0000000001</ListID>
+          <FullName>Sales</FullName>
+        </IncomeAccountRef>
+        <QuantityOnHand>42</QuantityOnHand>
+      </ItemInventoryRet>
+    </ItemInventoryQueryRs>
+  </QBXMLMsgsRs>
+</QBXML>"#;
+
+const SERVICE_RS: &str = r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <ItemServiceQueryRs requestID="1" statusCode="0" statusSeverity="Info" statusMessage="Status OK" iteratorRemainingCount="0" iteratorID="{svc-1}">
+      <ItemServiceRet>
+        <ListID>80000010-1234567890</ListID>
+        <Name>Installation</Name>
+        <FullName>Installation</FullName>
+        <SalesOrPurchase>
+          <Desc>On-site installation</Desc>
+          <Price>150.00</Price>
+          <AccountRef>
+            <ListID>40000002-0000000002</ListID>
+            <FullName>Service Income</FullName>
+          </AccountRef>
+        </SalesOrPurchase>
+      </ItemServiceRet>
+      <ItemServiceRet>
+        <ListID>80000011-1234567890</ListID>
+        <Name>Consulting</Name>
+        <SalesAndPurchase>
+          <SalesDesc>Hourly consulting</SalesDesc>
+          <SalesPrice>95.5</SalesPrice>
+        </SalesAndPurchase>
+      </ItemServiceRet>
+    </ItemServiceQueryRs>
+  </QBXMLMsgsRs>
+</QBXML>"#;
+
+const NON_INVENTORY_RS: &str = r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <ItemNonInventoryQueryRs requestID="1" statusCode="0" statusSeverity="Info" statusMessage="Status OK" iteratorRemainingCount="3" iteratorID="{non-1}">
+      <ItemNonInventoryRet>
+        <ListID>80000020-1234567890</ListID>
+        <Name>Gift Wrap</Name>
+        <FullName>Extras:Gift Wrap</FullName>
+        <SalesOrPurchase>
+          <Desc>Gift wrapping</Desc>
+          <Price>4.25</Price>
+        </SalesOrPurchase>
+      </ItemNonInventoryRet>
+    </ItemNonInventoryQueryRs>
+  </QBXMLMsgsRs>
+</QBXML>"#;
+
+#[cfg(test)]
+mod qbxml_builder_tests {
+    use super::*;
+
+    #[test]
+    fn test_inventory_query_start() {
+        let xml = build_item_inventory_query_xml(None, None);
+        assert!(xml.contains(r#"<?qbxml version="13.0"?>"#));
+        assert!(xml.contains(r#"<ItemInventoryQueryRq requestID="1" iterator="Start" maxReturned="50">"#));
+        assert!(xml.contains("</ItemInventoryQueryRq>"));
+    }
+
+    #[test]
+    fn test_service_query_continue() {
+        let xml = build_item_service_query_xml(Some("{svc-1}"), None);
+        assert!(xml.contains(r#"<ItemServiceQueryRq requestID="1" iterator="Continue" iteratorID="{svc-1}""#));
+        assert!(xml.contains("</ItemServiceQueryRq>"));
+    }
+
+    #[test]
+    fn test_non_inventory_query_start() {
+        let xml = build_item_non_inventory_query_xml(None, None);
+        assert!(xml.contains(r#"<ItemNonInventoryQueryRq requestID="1" iterator="Start""#));
+        assert!(xml.contains("</ItemNonInventoryQueryRq>"));
+    }
+
+    #[test]
+    fn test_query_uses_connection_qbxml_version() {
+        let xml = build_item_inventory_query_xml(None, Some("6.0"));
+        assert!(xml.contains(r#"<?qbxml version="6.0"?>"#));
+        assert!(!xml.contains("13.0"));
+    }
+
+    #[test]
+    fn test_unusable_qbxml_version_falls_back_to_default() {
+        let xml = build_item_inventory_query_xml(None, Some(r#"6.0"?><x"#));
+        assert!(xml.contains(r#"<?qbxml version="13.0"?>"#));
+    }
+
+    #[test]
+    fn test_negotiate_qbxml_version() {
+        assert_eq!(negotiate_qbxml_version("6.0").as_deref(), Some("6.0"));
+        assert_eq!(negotiate_qbxml_version("8").as_deref(), Some("8.0"));
+        //newer installs still get the version the queries are written for
+        assert_eq!(negotiate_qbxml_version("16.0").as_deref(), Some(DEFAULT_QBXML_VERSION));
+        assert_eq!(negotiate_qbxml_version("abc"), None);
+        assert_eq!(negotiate_qbxml_version(""), None);
+    }
+
+    #[test]
+    fn test_item_type_from_details() {
+        assert_eq!(QbdItemType::from_details(None), QbdItemType::Inventory);
+        assert_eq!(
+            QbdItemType::from_details(Some(&json!({ "item_type": "service" }))),
+            QbdItemType::Service
+        );
+        assert_eq!(
+            QbdItemType::from_details(Some(&json!({ "item_type": "non_inventory" }))),
+            QbdItemType::NonInventory
+        );
+        assert_eq!(
+            QbdItemType::from_details(Some(&json!({ "item_type": "bogus" }))),
+            QbdItemType::Inventory
+        );
+    }
+}
+
+#[cfg(test)]
+mod qbxml_parser_tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_inventory_response() {
+        let parsed = parse_inventory_response(INVENTORY_RS).unwrap();
+        assert_eq!(parsed.status_code, "0");
+        assert_eq!(parsed.iterator_id.as_deref(), Some("{abc-123}"));
+        assert_eq!(parsed.remaining_count, 12);
+        assert_eq!(parsed.items.len(), 1);
+
+        let item = &parsed.items[0];
+        assert_eq!(item.item_type, QbdItemType::Inventory);
+        //nested IncomeAccountRef must not overwrite the item's own ListID/FullName
+        assert_eq!(item.list_id, "80000001-1234567890");
+        assert_eq!(item.full_name.as_deref(), Some("Widgets:Widget"));
+        assert_eq!(item.sales_price_cents, Some(1999));
+        assert_eq!(item.qty_on_hand, Some(42));
+        assert_eq!(item.qty_on_hand_milli, Some(42_000));
+        assert_eq!(item.sales_desc.as_deref(), Some("Blue widget"));
+        assert_eq!(item.manufacturer_part_number.as_deref(), Some("WG-100"));
+        assert_eq!(item.raw["IncomeAccountRef.FullName"], "Sales");
+        assert_eq!(item.raw["item_type"], "inventory");
+    }
+
+    #[test]
+    fn test_fractional_quantity_is_preserved() {
+        //items sold by weight report fractional quantities
+        let parsed = parse_inventory_response(
+            &INVENTORY_RS.replace("<QuantityOnHand>42</QuantityOnHand>", "<QuantityOnHand>10.5</QuantityOnHand>"),
+        )
+        .unwrap();
+
+        let item = &parsed.items[0];
+        assert_eq!(item.qty_on_hand_milli, Some(10_500));
+        assert_eq!(item.qty_on_hand, Some(10));
+    }
+
+    #[test]
+    fn test_normalize_qty() {
+        assert_eq!(normalize_qty("10.5"), Some(10_500));
+        assert_eq!(normalize_qty(" 0.125 "), Some(125));
+        assert_eq!(normalize_qty("-2.25"), Some(-2_250));
+        assert_eq!(normalize_qty("7"), Some(7_000));
+        assert_eq!(normalize_qty("lots"), None);
+        assert_eq!(normalize_qty("NaN"), None);
+    }
+
+    #[test]
+    fn test_parse_item_service_response() {
+        let parsed = parse_item_service_response(SERVICE_RS).unwrap();
+        assert_eq!(parsed.remaining_count, 0);
+        assert_eq!(parsed.items.len(), 2);
+
+        let install = &parsed.items[0];
+        assert_eq!(install.item_type, QbdItemType::Service);
+        assert_eq!(install.list_id, "80000010-1234567890");
+        assert_eq!(install.sales_price_cents, Some(15000));
+        assert_eq!(install.sales_desc.as_deref(), Some("On-site installation"));
+        assert_eq!(install.qty_on_hand, None);
+        assert_eq!(install.qty_on_hand_milli, None);
+        assert_eq!(install.raw["item_type"], "service");
+
+        let consulting = &parsed.items[1];
+        assert_eq!(consulting.sales_price_cents, Some(9550));
+        assert_eq!(consulting.sales_desc.as_deref(), Some("Hourly consulting"));
+    }
+
+    #[test]
+    fn test_parse_item_non_inventory_response() {
+        let parsed = parse_item_non_inventory_response(NON_INVENTORY_RS).unwrap();
+        assert_eq!(parsed.iterator_id.as_deref(), Some("{non-1}"));
+        assert_eq!(parsed.remaining_count, 3);
+        assert_eq!(parsed.items.len(), 1);
+
+        let item = &parsed.items[0];
+        assert_eq!(item.item_type, QbdItemType::NonInventory);
+        assert_eq!(item.name.as_deref(), Some("Gift Wrap"));
+        assert_eq!(item.sales_price_cents, Some(425));
+        assert_eq!(item.raw["item_type"], "non_inventory");
+    }
+
+    #[test]
+    fn test_parser_ignores_other_item_types() {
+        //a service response parsed as inventory yields no items
+        let parsed = parse_inventory_response(SERVICE_RS).unwrap();
+        assert!(parsed.items.is_empty());
+    }
+
+    #[test]
+    fn test_parse_error_status() {
+        let xml = r#"<QBXML><QBXMLMsgsRs><ItemServiceQueryRs requestID="1" statusCode="3100" statusSeverity="Error" statusMessage="Invalid iterator"></ItemServiceQueryRs></QBXMLMsgsRs></QBXML>"#;
+        let parsed = parse_item_service_response(xml).unwrap();
+        assert_eq!(parsed.status_code, "3100");
+        assert_eq!(parsed.status_message, "Invalid iterator");
+        assert!(parsed.items.is_empty());
+        assert!(parsed.is_iterator_expired());
+    }
+
+    #[test]
+    fn test_parse_self_closing_status_response() {
+        let xml = r#"<QBXML><QBXMLMsgsRs><ItemInventoryQueryRs requestID="1" statusCode="1" statusSeverity="Info" statusMessage="A query request did not find a matching object in QuickBooks" /></QBXMLMsgsRs></QBXML>"#;
+        let parsed = parse_inventory_response(xml).unwrap();
+        assert_eq!(parsed.status_code, "1");
+        assert_eq!(parsed.status_severity, "Info");
+        assert!(parsed.items.is_empty());
+    }
+
+    #[test]
+    fn test_other_error_status_is_not_iterator_expiry() {
+        let xml = r#"<QBXML><QBXMLMsgsRs><ItemInventoryQueryRs requestID="1" statusCode="3250" statusSeverity="Error" statusMessage="This feature is not enabled or not available in this version of QuickBooks."></ItemInventoryQueryRs></QBXMLMsgsRs></QBXML>"#;
+        let parsed = parse_inventory_response(xml).unwrap();
+        assert!(!parsed.is_iterator_expired());
+    }
+
+    #[test]
+    fn test_item_from_raw_matches_parse() {
+        let parsed = parse_item_service_response(SERVICE_RS).unwrap();
+        let item = item_from_raw(&parsed.items[0].raw).unwrap();
+        assert_eq!(item.item_type, QbdItemType::Service);
+        assert_eq!(item.list_id, "80000010-1234567890");
+        assert_eq!(item.sales_price_cents, Some(15000));
+        assert_eq!(item.sales_desc.as_deref(), Some("On-site installation"));
+        assert_eq!(item.raw, parsed.items[0].raw);
+
+        assert!(item_from_raw(&json!({ "Name": "no list id" })).is_none());
+    }
+
+    #[test]
+    fn test_normalize_price() {
+        assert_eq!(normalize_price("19.99"), Some(1999));
+        assert_eq!(normalize_price(" 5 "), Some(500));
+        assert_eq!(normalize_price("n/a"), None);
+    }
+}
+
+const INVOICE_ADD_RS: &str = r#"<?xml version="1.0" ?>
+<QBXML>
+  <QBXMLMsgsRs>
+    <InvoiceAddRs requestID="1" statusCode="0" statusSeverity="Info" statusMessage="Status OK">
+      <InvoiceRet>
+        <TxnID>1A2B-1700000000</TxnID>
+        <TimeCreated>2026-01-31T10:00:00-05:00</TimeCreated>
+        <CustomerRef>
+          <ListID>80000001-1234567890</ListID>
+          <FullName>Acme Corp</FullName>
+        </CustomerRef>
+        <RefNumber>SO-1001</RefNumber>
+        <InvoiceLineRet>
+          <TxnLineID>1A2C-1700000000</TxnLineID>
+          <ItemRef>
+            <ListID>80000002-1234567890</ListID>
+          </ItemRef>
+          <Quantity>2</Quantity>
+        </InvoiceLineRet>
+      </InvoiceRet>
+    </InvoiceAddRs>
+  </QBXMLMsgsRs>
+</QBXML>"#;
+
+#[cfg(test)]
+mod order_qbxml_tests {
+    use super::*;
+
+    fn two_line_order() -> QbdOrder {
+        QbdOrder {
+            customer_list_id: Some("80000001-1234567890".to_string()),
+            customer_full_name: None,
+            ref_number: Some("SO-1001".to_string()),
+            txn_date: Some("2026-01-31".to_string()),
+            memo: Some("Web order & gift wrap".to_string()),
+            lines: vec![
+                QbdOrderLine {
+                    item_list_id: "80000002-1234567890".to_string(),
+                    desc: Some("Widget".to_string()),
+                    quantity: 2,
+                    rate_cents: Some(1999),
+                },
+                QbdOrderLine {
+                    item_list_id: "80000010-1234567890".to_string(),
+                    desc: None,
+                    quantity: 1,
+                    rate_cents: None,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_invoice_add_two_lines() {
+        let xml = build_invoice_add_xml(&two_line_order(), Some("13.0"));
+        assert!(xml.contains(r#"<?qbxml version="13.0"?>"#));
+        assert!(xml.contains(r#"<InvoiceAddRq requestID="1">"#));
+        assert!(xml.contains("<CustomerRef><ListID>80000001-1234567890</ListID></CustomerRef>"));
+        assert!(xml.contains("<TxnDate>2026-01-31</TxnDate>"));
+        assert!(xml.contains("<RefNumber>SO-1001</RefNumber>"));
+        assert!(xml.contains("<Memo>Web order &amp; gift wrap</Memo>"));
+        assert_eq!(xml.matches("<InvoiceLineAdd>").count(), 2);
+        assert!(xml.contains("<ItemRef><ListID>80000002-1234567890</ListID></ItemRef>"));
+        assert!(xml.contains("<ItemRef><ListID>80000010-1234567890</ListID></ItemRef>"));
+        assert!(xml.contains("<Quantity>2</Quantity>"));
+        assert!(xml.contains("<Rate>19.99</Rate>"));
+        assert_eq!(xml.matches("<Rate>").count(), 1);
+        // QBXML requires the header fields before the lines.
+        assert!(xml.find("<Memo>").unwrap() < xml.find("<InvoiceLineAdd>").unwrap());
+
+        let receipt = build_sales_receipt_add_xml(&two_line_order(), None);
+        assert!(receipt.contains(&format!(r#"<?qbxml version="{DEFAULT_QBXML_VERSION}"?>"#)));
+        assert_eq!(receipt.matches("<SalesReceiptLineAdd>").count(), 2);
+        assert!(!receipt.contains("Invoice"));
+    }
+
+    #[test]
+    fn test_parse_invoice_add_response() {
+        let parsed = parse_invoice_add_response(INVOICE_ADD_RS).unwrap();
+        assert_eq!(parsed.status_code, "0");
+        assert_eq!(parsed.status_severity, "Info");
+        assert_eq!(parsed.txn_id.as_deref(), Some("1A2B-1700000000"));
+        assert_eq!(parsed.ref_number.as_deref(), Some("SO-1001"));
+
+        assert!(parse_sales_receipt_add_response(INVOICE_ADD_RS).is_err());
+    }
+
+    #[test]
+    fn test_parse_refused_invoice_add() {
+        let xml = r#"<QBXML><QBXMLMsgsRs><InvoiceAddRs requestID="1" statusCode="3140" statusSeverity="Error" statusMessage="There is an invalid reference to QuickBooks Item &quot;80000099-1&quot;." /></QBXMLMsgsRs></QBXML>"#;
+        let parsed = parse_invoice_add_response(xml).unwrap();
+        assert_eq!(parsed.status_code, "3140");
+        assert_eq!(parsed.status_severity, "Error");
+        assert!(parsed.txn_id.is_none());
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(1999), "19.99");
+        assert_eq!(format_amount(500), "5.00");
+        assert_eq!(format_amount(-550), "-5.50");
+        assert_eq!(format_amount(7), "0.07");
+    }
+}
@@ -0,0 +1,334 @@
+//! Tests for the QuickBooks Desktop .qwc bootstrap, the Web Connector credentials it generates
+//! and downloading an existing .qwc again
+//!
+//! Run with: cargo test --test qbd_qwc_tests
+//!
+//! Uses the sea-orm mock database (no Postgres required): the connection insert binds the
+//! `scopes` array, which SQLite can't take. Rollbacks are asserted on the transaction log.
+
+#[path = "../src/audit/services.rs"]
+mod audit_services;
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/config/env.rs"]
+mod env;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/connection_identity/services.rs"]
+mod connection_identity_services;
+#[path = "../src/erp_connection_credentials/codec.rs"]
+mod credential_codec;
+#[path = "../src/erp_connection_credentials/lookup_cache.rs"]
+mod credential_lookup_cache;
+#[path = "../src/erp_connection_credentials/services.rs"]
+mod erp_connection_credentials_services;
+#[path = "../src/tenant/cache.rs"]
+mod cache;
+#[path = "../src/tenant/services.rs"]
+mod tenant_services;
+#[path = "../src/outbox/services.rs"]
+mod outbox_services;
+#[path = "../src/sync/window.rs"]
+mod sync_window;
+#[path = "../src/client-systems/quickbooks/desktop/qwc_credentials.rs"]
+mod qwc_credentials;
+#[path = "../src/client-systems/quickbooks/desktop/services.rs"]
+mod qwc_services;
+
+//the bootstrap reaches the services through `crate::<module>::services` and reads
+//`crate::config::env`
+mod audit {
+    pub(crate) use super::audit_services as services;
+}
+mod config {
+    pub(crate) use super::env;
+}
+mod connection_identity {
+    pub(crate) use super::connection_identity_services as services;
+}
+mod erp_connection_credentials {
+    pub(crate) use super::credential_codec as codec;
+    pub(crate) use super::credential_lookup_cache as lookup_cache;
+    pub(crate) use super::erp_connection_credentials_services as services;
+}
+mod outbox {
+    pub(crate) use super::outbox_services as services;
+}
+mod sync {
+    pub(crate) use super::sync_window as window;
+}
+mod tenant {
+    pub(crate) use super::tenant_services as services;
+}
+
+use entity::sea_orm_active_enums::{
+    Enum as TenantStatus, ErpConnectionAuthStatus, ErpConnectionAuthTokenType, ErpConnectionStatus, ErpEnvironment,
+    ErpProvider, ErpProviderAuthType, ErpProviderType,
+};
+use qwc_credentials::{
+    generate_username, PasswordPolicy, DEFAULT_USERNAME_PREFIX, MAX_PASSWORD_LENGTH,
+    MIN_PASSWORD_LENGTH,
+};
+use qwc_services::{existing_qwc, generate_qwc, QbdDesktopError};
+use sea_orm::{DatabaseBackend, DbErr, MockDatabase};
+use std::sync::Once;
+
+const TENANT_ID: &str = "TN_qwc";
+
+static INIT_CONFIG: Once = Once::new();
+
+/// Helper to set up the global config the bootstrap reads (CREDENTIALS_REQUIRE_ENCRYPTION)
+fn init_config() {
+    INIT_CONFIG.call_once(env::init);
+}
+
+/// Helper to build the tenant the .qwc is requested for
+fn tenant_model() -> entity::tenant::Model {
+    let now = chrono::Utc::now().into();
+    entity::tenant::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        display_name: None,
+        tenant_id: TENANT_ID.to_string(),
+        created_at: now,
+        updated_at: now,
+        status: TenantStatus::Active,
+    }
+}
+
+/// Helper to build the QBD connection the bootstrap inserts
+fn connection_model() -> entity::connection_identity::Model {
+    let now = chrono::Utc::now().into();
+    entity::connection_identity::Model {
+        id: 7,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider: ErpProvider::Quickbooks,
+        erp_type: ErpProviderType::Desktop,
+        erp_auth_type: ErpProviderAuthType::UsernamePassword,
+        display_name: Some("QuickBooks Desktop Web Connector".to_string()),
+        environment: ErpEnvironment::Production,
+        status: ErpConnectionStatus::Active,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: now,
+        updated_at: now,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: Some(uuid::Uuid::new_v4().to_string()),
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+}
+
+/// Helper to build the Web Connector credentials row the bootstrap inserts
+fn credentials_model() -> entity::erp_connection_credentials::Model {
+    let now = chrono::Utc::now().into();
+    entity::erp_connection_credentials::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        created_at: now,
+        updated_at: now,
+        connection_id: 7,
+        client_id: None,
+        issuer_base_url: None,
+        token_type: ErpConnectionAuthTokenType::Bearer,
+        reauth_required_reason: None,
+        reauth_url: None,
+        enc_scheme: "none".to_string(),
+        enc_key_id: "qbd-webconnector".to_string(),
+        enc_version: 1,
+        enc_iv: None,
+        enc_tag: None,
+        access_token: None,
+        refresh_token: None,
+        access_token_expires_at: None,
+        refresh_token_expires_at: None,
+        id_token_enc: None,
+        provider_user_id: Some("pro_portals_test".to_string()),
+        provider_password: Some("secret".to_string()),
+        client_cert: None,
+        private_key: None,
+        cert_expires_at: None,
+        session_token: None,
+        session_expires_at: None,
+        api_access_token: None,
+        api_access_token_key: None,
+    }
+}
+
+/// Helper to script the reads of a bootstrap for a tenant without a QBD connection, up to the
+/// connection insert
+fn bootstrap_db() -> MockDatabase {
+    MockDatabase::new(DatabaseBackend::Postgres)
+        //tenant lookup
+        .append_query_results([vec![tenant_model()]])
+        //the tenant's connections, then the company file conflict check
+        .append_query_results([
+            Vec::<entity::connection_identity::Model>::new(),
+            Vec::<entity::connection_identity::Model>::new(),
+        ])
+        //connection insert
+        .append_query_results([vec![connection_model()]])
+}
+
+mod bootstrap_transaction_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bootstrap_commits_connection_with_credentials() {
+        init_config();
+        let db = bootstrap_db()
+            //credentials insert
+            .append_query_results([vec![credentials_model()]])
+            .into_connection();
+
+        let out = generate_qwc(&db, Some(TENANT_ID)).await.unwrap();
+
+        assert_eq!(out.tenant_id, TENANT_ID);
+        //defaults: `pro_portals_` usernames, 16-character mixed passwords
+        assert!(out.username.unwrap().starts_with(DEFAULT_USERNAME_PREFIX));
+        assert!(PasswordPolicy::new(16, true).is_satisfied_by(&out.password));
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches("BEGIN").count(), 1);
+        assert!(log.contains(r#"INSERT INTO \"connection_identity\""#), "{}", log);
+        assert!(log.contains(r#"INSERT INTO \"erp_connection_credentials\""#), "{}", log);
+        assert!(log.contains("COMMIT"), "{}", log);
+        assert!(!log.contains("ROLLBACK"), "{}", log);
+    }
+
+    #[tokio::test]
+    async fn test_failed_credentials_insert_leaves_no_orphan_connection() {
+        init_config();
+        let db = bootstrap_db()
+            .append_query_errors([DbErr::Custom("credentials insert rejected".to_string())])
+            .into_connection();
+
+        let result = generate_qwc(&db, Some(TENANT_ID)).await;
+
+        assert!(matches!(result, Err(QbdDesktopError::Db(_))));
+        //the connection was inserted in the same transaction, which is rolled back
+        let log = format!("{:?}", db.into_transaction_log());
+        assert_eq!(log.matches("BEGIN").count(), 1);
+        assert!(log.contains(r#"INSERT INTO \"connection_identity\""#), "{}", log);
+        assert!(log.contains("ROLLBACK"), "{}", log);
+        assert!(!log.contains("COMMIT"), "{}", log);
+    }
+}
+
+mod credential_format_tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_passwords_meet_policy() {
+        for policy in [
+            PasswordPolicy::new(16, true),
+            PasswordPolicy::new(8, true),
+            PasswordPolicy::new(40, false),
+        ] {
+            for _ in 0..200 {
+                let password = policy.generate();
+                assert_eq!(password.len(), policy.min_length);
+                assert!(policy.is_satisfied_by(&password), "{}", password);
+            }
+        }
+    }
+
+    #[test]
+    fn test_policy_predicate() {
+        let policy = PasswordPolicy::new(8, true);
+        assert!(policy.is_satisfied_by("aB3dE5gH"));
+        //too short, single class, missing a digit, characters QBWC may choke on
+        assert!(!policy.is_satisfied_by("aB3dE5g"));
+        assert!(!policy.is_satisfied_by("abcdefgh1"));
+        assert!(!policy.is_satisfied_by("aBcDeFgH"));
+        assert!(!policy.is_satisfied_by("aB3dE5g&"));
+        assert!(!policy.is_satisfied_by(&"aB3".repeat(30)));
+        //without the mixed charset rule any alphanumeric password of the length passes
+        assert!(PasswordPolicy::new(8, false).is_satisfied_by("abcdefgh"));
+    }
+
+    #[test]
+    fn test_length_is_clamped() {
+        assert_eq!(PasswordPolicy::new(4, true).min_length, MIN_PASSWORD_LENGTH);
+        assert_eq!(PasswordPolicy::new(500, true).min_length, MAX_PASSWORD_LENGTH);
+    }
+
+    #[test]
+    fn test_username_prefix_is_applied() {
+        let username = generate_username("acme_");
+        let suffix = username.strip_prefix("acme_").unwrap();
+        assert_eq!(suffix.len(), 32);
+        assert!(suffix.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(generate_username("acme_"), username);
+    }
+}
+
+mod existing_qwc_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_qwc_is_rebuilt_from_stored_username_and_file_id() {
+        let connection = connection_model();
+        let file_id = connection.company_file_id.clone().unwrap();
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![tenant_model()]])
+            .append_query_results([vec![connection]])
+            .append_query_results([vec![credentials_model()]])
+            .into_connection();
+
+        let qwc = existing_qwc(&db, TENANT_ID).await.unwrap();
+
+        assert_eq!(qwc.tenant_db_id, 1);
+        assert_eq!(qwc.username, "pro_portals_test");
+        assert_eq!(qwc.file_id, file_id);
+        assert!(qwc.qwc_xml.contains("<UserName>pro_portals_test</UserName>"), "{}", qwc.qwc_xml);
+        assert!(qwc.qwc_xml.contains(&format!("<FileID>{}</FileID>", file_id)), "{}", qwc.qwc_xml);
+        //read only: nothing is written or rotated
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(!log.contains("INSERT") && !log.contains("UPDATE"), "{}", log);
+    }
+
+    #[tokio::test]
+    async fn test_tenant_without_qbd_connection_is_not_found() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![tenant_model()]])
+            .append_query_results([Vec::<entity::connection_identity::Model>::new()])
+            .into_connection();
+
+        let result = existing_qwc(&db, TENANT_ID).await;
+
+        assert!(matches!(result, Err(QbdDesktopError::ConnectionNotFound)));
+        assert_eq!(
+            QbdDesktopError::ConnectionNotFound.status_code(),
+            axum::http::StatusCode::NOT_FOUND
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_tenant_is_not_found() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<entity::tenant::Model>::new()])
+            .into_connection();
+
+        let result = existing_qwc(&db, "TN_missing").await;
+
+        assert!(matches!(result, Err(QbdDesktopError::TenantNotFound)));
+    }
+}
@@ -0,0 +1,75 @@
+//! Tests for the QuickBooks Desktop status code mapping
+//!
+//! Run with: cargo test --test qbd_status_tests
+
+#[path = "../src/client-systems/quickbooks/desktop/qbd_status.rs"]
+mod qbd_status;
+
+use qbd_status::*;
+
+#[cfg(test)]
+mod classify_tests {
+    use super::*;
+
+    #[test]
+    fn test_ok_is_success() {
+        let status = classify("0", "Info");
+        assert_eq!(status.severity, QbdStatusSeverity::Info);
+        assert_eq!(status.outcome(), QbdStatusOutcome::Success);
+    }
+
+    #[test]
+    fn test_no_matching_records_is_an_empty_page() {
+        let status = classify("1", "Info");
+        assert_eq!(status.outcome(), QbdStatusOutcome::NoRecords);
+        assert!(!status.retryable);
+    }
+
+    #[test]
+    fn test_objects_not_found_is_a_warning() {
+        let status = classify("500", "Warn");
+        assert_eq!(status.severity, QbdStatusSeverity::Warning);
+        assert_eq!(status.outcome(), QbdStatusOutcome::Warning);
+    }
+
+    #[test]
+    fn test_invalid_request_is_an_error() {
+        for code in ["3000", "3120", "3200", "3250", "3260"] {
+            let status = classify(code, "Error");
+            assert_eq!(status.outcome(), QbdStatusOutcome::Error, "code {code}");
+            assert!(!status.retryable, "code {code}");
+        }
+    }
+
+    #[test]
+    fn test_record_in_use_is_a_retryable_error() {
+        for code in ["1000", "3170", "3175", "3180"] {
+            let status = classify(code, "Error");
+            assert_eq!(status.outcome(), QbdStatusOutcome::Error, "code {code}");
+            assert!(status.retryable, "code {code}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_code_uses_reported_severity() {
+        assert_eq!(classify("9001", "Info").outcome(), QbdStatusOutcome::Success);
+        assert_eq!(classify("9001", "Warn").outcome(), QbdStatusOutcome::Warning);
+        assert_eq!(classify("9001", "Error").outcome(), QbdStatusOutcome::Error);
+        assert_eq!(classify("9001", "").outcome(), QbdStatusOutcome::Error);
+        assert!(!classify("9001", "Error").retryable);
+    }
+
+    #[test]
+    fn test_describe_includes_qbd_message() {
+        let status = classify("3200", "Error");
+        assert_eq!(
+            status.describe("The edit sequence is out-of-date"),
+            "QBD status 3200: The request is invalid: the edit sequence is out of date \
+             (The edit sequence is out-of-date)"
+        );
+        assert_eq!(
+            classify("1", "Info").describe(""),
+            "QBD status 1: No matching records found"
+        );
+    }
+}
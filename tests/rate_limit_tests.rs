@@ -0,0 +1,90 @@
+//! Tests for per-tenant rate limiting
+//!
+//! Run with: cargo test --test rate_limit_tests
+//!
+//! Uses the in-process limiter store (no Redis required), which follows the same sliding
+//! window as the Redis script, with a `FixedClock` to move through the window.
+
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/security/rate_limit.rs"]
+mod rate_limit;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use clock::FixedClock;
+use rate_limit::{is_exempt_route, RateLimitDecision, RateLimitStore, RateLimiter};
+
+const LIMIT: u64 = 3;
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Helper to build a limiter of `LIMIT` requests per `WINDOW` on a pinned clock
+fn limiter() -> (RateLimiter, Arc<FixedClock>) {
+    let clock = Arc::new(FixedClock::new(Utc.with_ymd_and_hms(2026, 1, 1, 12, 0, 0).unwrap()));
+    let limiter = RateLimiter::new(RateLimitStore::local(), LIMIT, WINDOW).with_clock(clock.clone());
+    (limiter, clock)
+}
+
+#[cfg(test)]
+mod sliding_window_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_request_over_limit_is_limited() {
+        let (limiter, clock) = limiter();
+
+        for _ in 0..LIMIT {
+            assert_eq!(limiter.check(1).await, RateLimitDecision::Allowed);
+            clock.advance(chrono::Duration::seconds(10));
+        }
+
+        //the first request (30s ago) frees its slot in another 30s
+        let decision = limiter.check(1).await;
+        assert_eq!(
+            decision,
+            RateLimitDecision::Limited {
+                retry_after: Duration::from_secs(30)
+            }
+        );
+        assert_eq!(decision.retry_after_secs(), Some(30));
+    }
+
+    #[tokio::test]
+    async fn test_counter_resets_after_window() {
+        let (limiter, clock) = limiter();
+
+        for _ in 0..LIMIT {
+            assert_eq!(limiter.check(1).await, RateLimitDecision::Allowed);
+        }
+        assert!(limiter.check(1).await.retry_after_secs().is_some());
+
+        //refused requests are not counted, so the full limit is available again
+        clock.advance(chrono::Duration::seconds(60));
+        for _ in 0..LIMIT {
+            assert_eq!(limiter.check(1).await, RateLimitDecision::Allowed);
+        }
+        assert!(limiter.check(1).await.retry_after_secs().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_tenants_are_limited_separately() {
+        let (limiter, _clock) = limiter();
+
+        for _ in 0..LIMIT {
+            limiter.check(1).await;
+        }
+        assert!(limiter.check(1).await.retry_after_secs().is_some());
+        assert_eq!(limiter.check(2).await, RateLimitDecision::Allowed);
+    }
+
+    #[test]
+    fn test_health_and_metrics_are_exempt() {
+        assert!(is_exempt_route("/healthcheck", None));
+        assert!(is_exempt_route("/api/metrics", Some("/api")));
+        assert!(is_exempt_route("/api/", Some("/api")));
+        assert!(!is_exempt_route("/api/connections", Some("/api")));
+        assert!(!is_exempt_route("/healthcheck/extra", None));
+    }
+}
@@ -0,0 +1,585 @@
+//! Tests for the Salesforce pull adapter
+//!
+//! Run with: cargo test --test salesforce_tests
+//!
+//! Pulls run against an in-memory SQLite database, with a local axum server standing in for
+//! the Salesforce API (the REST `/query` endpoint and the Bulk API 2.0 query job lifecycle).
+
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/client-systems/salesforce/mod.rs"]
+mod salesforce;
+#[path = "../src/sync/mod.rs"]
+mod sync;
+#[path = "../src/validation/mod.rs"]
+mod validation;
+
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+#[path = "../src/connection_run/services.rs"]
+mod connection_run_services;
+#[path = "../src/erp_connection_sync_state/services.rs"]
+mod erp_connection_sync_state_services;
+#[path = "../src/inventory_records/events_services.rs"]
+mod inventory_record_events_services;
+#[path = "../src/inventory_records/services.rs"]
+mod inventory_records_services;
+#[path = "../src/outbox/services.rs"]
+mod outbox_services;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/sync_event/services.rs"]
+mod sync_event_services;
+
+//the adapter and orchestrator reach these through `crate::<module>::...`
+mod connection_run {
+    pub(crate) use super::connection_run_services as services;
+}
+mod config {
+    pub(crate) use super::metrics;
+}
+mod erp_connection_sync_state {
+    pub(crate) use super::erp_connection_sync_state_services as services;
+}
+mod inventory_records {
+    pub(crate) use super::inventory_record_events_services as events_services;
+    pub(crate) use super::inventory_records_services as services;
+}
+mod outbox {
+    pub(crate) use super::outbox_services as services;
+}
+mod sync_event {
+    pub(crate) use super::sync_event_services as services;
+}
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use entity::sea_orm_active_enums::{
+    ConnectionRunStatus, ErpConnectionAuthStatus, ErpConnectionAuthTokenType, ErpConnectionStatus,
+    ErpEnvironment, ErpProvider, ErpProviderAuthType, ErpProviderType, SyncEventStatus,
+    SystemIdKey,
+};
+use entity::{connection_identity, erp_connection_credentials};
+use salesforce::SalesforceAdapter;
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait, IntoActiveModel,
+    PaginatorTrait, Schema,
+};
+use serde_json::{json, Value};
+use sync::{ApiPullAdapter, ScheduledPull, SyncOrchestrator};
+
+const ACCESS_TOKEN: &str = "00Dxx-access-token";
+const API_ROOT: &str = "/services/data/v60.0";
+
+const NEW_UUID: &str = "(randomblob(16))";
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+/// Helper to create an in-memory database with the credentials, sync, inventory and outbox
+/// tables a pull touches, with the uuid/created_at/updated_at defaults Postgres provides
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let tables = [
+        schema.create_table_from_entity(entity::erp_connection_credentials::Entity),
+        schema.create_table_from_entity(entity::erp_connection_sync_state::Entity),
+        schema.create_table_from_entity(entity::connection_run::Entity),
+        schema.create_table_from_entity(entity::sync_event::Entity),
+        schema.create_table_from_entity(entity::inventory_record::Entity),
+        schema.create_table_from_entity(entity::inventory_record_event::Entity),
+        schema.create_table_from_entity(entity::outbox::Entity),
+    ];
+    for table in tables {
+        let sql = table
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+    }
+    //same partial index as the migration; the first pull inserts against it
+    db.execute_unprepared(
+        "CREATE UNIQUE INDEX ux_sync_event_recurring_list
+         ON sync_event (connection_sync_state_id, sync_event_method, sync_event_category)
+         WHERE sync_event_method = 'list' AND details IS NULL",
+    )
+    .await
+    .unwrap();
+    db
+}
+
+/// Helper to build the Salesforce connection being pulled (not persisted)
+fn connection() -> connection_identity::Model {
+    let now = chrono::Utc::now().into();
+    connection_identity::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider: ErpProvider::Salesforce,
+        erp_type: ErpProviderType::Api,
+        erp_auth_type: ErpProviderAuthType::Oauth2,
+        display_name: None,
+        environment: ErpEnvironment::Sandbox,
+        status: ErpConnectionStatus::Active,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: now,
+        updated_at: now,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: None,
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+}
+
+/// Helper to store the connection's instance URL and access token
+async fn insert_credentials(db: &DatabaseConnection, instance_url: &str) {
+    let now = chrono::Utc::now();
+    erp_connection_credentials::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        created_at: now.into(),
+        updated_at: now.into(),
+        connection_id: 1,
+        client_id: None,
+        issuer_base_url: Some(instance_url.to_string()),
+        token_type: ErpConnectionAuthTokenType::Bearer,
+        reauth_required_reason: None,
+        reauth_url: None,
+        enc_scheme: "none".to_string(),
+        enc_key_id: "none".to_string(),
+        enc_version: 1,
+        enc_iv: None,
+        enc_tag: None,
+        access_token: Some(ACCESS_TOKEN.to_string()),
+        refresh_token: None,
+        access_token_expires_at: None,
+        refresh_token_expires_at: None,
+        id_token_enc: None,
+        provider_user_id: None,
+        provider_password: None,
+        client_cert: None,
+        private_key: None,
+        cert_expires_at: None,
+        session_token: None,
+        session_expires_at: None,
+        api_access_token: None,
+        api_access_token_key: None,
+    }
+    .into_active_model()
+    .reset_all()
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+/// How the mock Bulk job ends.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum JobOutcome {
+    #[default]
+    Complete,
+    Failed,
+    /// Never leaves `InProgress`.
+    Stuck,
+}
+
+/// What the mock API saw. The catalog holds `product_count` products (three are served).
+#[derive(Default)]
+struct MockSalesforce {
+    product_count: u64,
+    job_outcome: JobOutcome,
+    rest_pages: AtomicUsize,
+    jobs_submitted: AtomicUsize,
+    job_checks: AtomicUsize,
+    job_aborted: AtomicBool,
+    result_locators: Mutex<Vec<String>>,
+}
+
+impl MockSalesforce {
+    fn new(product_count: u64, job_outcome: JobOutcome) -> Arc<Self> {
+        Arc::new(Self {
+            product_count,
+            job_outcome,
+            ..Default::default()
+        })
+    }
+}
+
+fn unauthorized() -> Response {
+    let error = json!([{ "message": "Session expired or invalid", "errorCode": "INVALID_SESSION_ID" }]);
+    (StatusCode::UNAUTHORIZED, Json(error)).into_response()
+}
+
+fn authorized(headers: &HeaderMap) -> bool {
+    headers.get("authorization").and_then(|v| v.to_str().ok())
+        == Some(format!("Bearer {ACCESS_TOKEN}").as_str())
+}
+
+/// Mock Salesforce API. REST: `SELECT COUNT()` answers `product_count`; the product query
+/// answers two products and a `nextRecordsUrl`, then one last product. Bulk: a submitted job
+/// is `InProgress` on its first check and then ends as `job_outcome` says; its results come
+/// as two CSV pages (two products, then one). Returns the instance URL.
+async fn spawn_salesforce(state: Arc<MockSalesforce>) -> String {
+    async fn query(
+        State(state): State<Arc<MockSalesforce>>,
+        headers: HeaderMap,
+        Query(params): Query<std::collections::HashMap<String, String>>,
+    ) -> Response {
+        if !authorized(&headers) {
+            return unauthorized();
+        }
+        let q = params.get("q").cloned().unwrap_or_default();
+        if q.contains("COUNT()") {
+            return Json(json!({ "totalSize": state.product_count, "done": true, "records": [] }))
+                .into_response();
+        }
+        state.rest_pages.fetch_add(1, Ordering::SeqCst);
+        Json(json!({
+            "totalSize": 3,
+            "done": false,
+            "nextRecordsUrl": format!("{API_ROOT}/query/01gQ-2000"),
+            "records": [
+                { "attributes": { "type": "Product2", "url": "/p/1" }, "Id": "01t000000000001AAA", "Name": "Widget", "ProductCode": "W-1", "Description": "A widget", "IsActive": true },
+                { "attributes": { "type": "Product2", "url": "/p/2" }, "Id": "01t000000000002AAA", "Name": "Gadget", "ProductCode": null, "Description": null, "IsActive": false },
+            ],
+        }))
+        .into_response()
+    }
+
+    async fn query_more(
+        State(state): State<Arc<MockSalesforce>>,
+        headers: HeaderMap,
+        Path(_locator): Path<String>,
+    ) -> Response {
+        if !authorized(&headers) {
+            return unauthorized();
+        }
+        state.rest_pages.fetch_add(1, Ordering::SeqCst);
+        Json(json!({
+            "totalSize": 3,
+            "done": true,
+            "records": [
+                { "attributes": { "type": "Product2", "url": "/p/3" }, "Id": "01t000000000003AAA", "Name": "Sprocket", "ProductCode": "S-3", "IsActive": true },
+            ],
+        }))
+        .into_response()
+    }
+
+    async fn submit_job(
+        State(state): State<Arc<MockSalesforce>>,
+        headers: HeaderMap,
+        Json(body): Json<Value>,
+    ) -> Response {
+        if !authorized(&headers) {
+            return unauthorized();
+        }
+        assert_eq!(body["operation"], "query");
+        assert!(body["query"].as_str().unwrap().starts_with("SELECT Id, Name"));
+        state.jobs_submitted.fetch_add(1, Ordering::SeqCst);
+        Json(json!({ "id": "750J1", "operation": "query", "state": "UploadComplete" })).into_response()
+    }
+
+    async fn job(State(state): State<Arc<MockSalesforce>>, Path(id): Path<String>) -> Response {
+        let checks = state.job_checks.fetch_add(1, Ordering::SeqCst) + 1;
+        let job = match (checks, state.job_outcome) {
+            (1, _) | (_, JobOutcome::Stuck) => json!({ "id": id, "state": "InProgress" }),
+            (_, JobOutcome::Complete) => json!({ "id": id, "state": "JobComplete" }),
+            (_, JobOutcome::Failed) => json!({
+                "id": id,
+                "state": "Failed",
+                "errorMessage": "INVALID_FIELD: No such column 'Foo__c' on entity 'Product2'",
+            }),
+        };
+        Json(job).into_response()
+    }
+
+    async fn abort_job(State(state): State<Arc<MockSalesforce>>, Json(body): Json<Value>) -> Response {
+        assert_eq!(body["state"], "Aborted");
+        state.job_aborted.store(true, Ordering::SeqCst);
+        Json(json!({ "id": "750J1", "state": "Aborted" })).into_response()
+    }
+
+    async fn results(
+        State(state): State<Arc<MockSalesforce>>,
+        Query(params): Query<std::collections::HashMap<String, String>>,
+    ) -> Response {
+        let locator = params.get("locator").cloned().unwrap_or_default();
+        state.result_locators.lock().unwrap().push(locator.clone());
+        let (csv, next) = match locator.as_str() {
+            "" => (
+                "\"Id\",\"Name\",\"ProductCode\",\"Description\",\"IsActive\"\n\
+                 \"01t000000000001AAA\",\"Widget\",\"W-1\",\"Sturdy, \"\"blue\"\"\nwidget\",\"true\"\n\
+                 \"01t000000000002AAA\",\"Gadget\",\"\",\"\",\"false\"\n",
+                "LOC2",
+            ),
+            _ => (
+                "\"Id\",\"Name\",\"ProductCode\",\"Description\",\"IsActive\"\n\
+                 \"01t000000000003AAA\",\"Sprocket\",\"S-3\",\"\",\"true\"\n",
+                "null",
+            ),
+        };
+        ([("content-type", "text/csv"), ("sforce-locator", next)], csv).into_response()
+    }
+
+    let app = Router::new()
+        .route(&format!("{API_ROOT}/query"), get(query))
+        .route(&format!("{API_ROOT}/query/{{locator}}"), get(query_more))
+        .route(&format!("{API_ROOT}/jobs/query"), post(submit_job))
+        .route(&format!("{API_ROOT}/jobs/query/{{id}}"), get(job).patch(abort_job))
+        .route(&format!("{API_ROOT}/jobs/query/{{id}}/results"), get(results))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// Helper for an adapter that switches to Bulk at three products and checks jobs quickly
+fn bulk_adapter(db: &DatabaseConnection) -> SalesforceAdapter {
+    SalesforceAdapter::new(db.clone())
+        .with_bulk_threshold(3)
+        .with_bulk_polling(Duration::from_millis(10), Duration::from_millis(200))
+}
+
+/// Helper to run one scheduled pull of the connection
+async fn pull(db: &DatabaseConnection, adapter: SalesforceAdapter) -> bool {
+    Arc::new(adapter)
+        .pull(db.clone(), clock::system(), connection())
+        .await
+        .unwrap()
+}
+
+async fn stored_cursor(db: &DatabaseConnection) -> Option<Value> {
+    entity::erp_connection_sync_state::Entity::find()
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+        .sync_cursor
+}
+
+#[cfg(test)]
+mod salesforce_pull_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_small_catalog_pulls_through_rest() {
+        let db = setup_db().await;
+        let api = MockSalesforce::new(3, JobOutcome::Complete);
+        let instance_url = spawn_salesforce(api.clone()).await;
+        insert_credentials(&db, &instance_url).await;
+
+        assert!(pull(&db, SalesforceAdapter::new(db.clone())).await);
+        assert_eq!(api.rest_pages.load(Ordering::SeqCst), 2);
+        assert_eq!(api.jobs_submitted.load(Ordering::SeqCst), 0);
+
+        let records = entity::inventory_record::Entity::find().all(&db).await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|r| r.system_id_key == SystemIdKey::Sfdc));
+        let events = entity::inventory_record_event::Entity::find().all(&db).await.unwrap();
+        let widget = events.iter().find(|e| e.name.as_deref() == Some("Widget")).unwrap();
+        assert_eq!(widget.external_code.as_deref(), Some("W-1"));
+        assert_eq!(widget.description.as_deref(), Some("A widget"));
+        //REST's `attributes` are not product data
+        assert!(widget.original_record_body.as_ref().unwrap().get("attributes").is_none());
+        assert_eq!(entity::outbox::Entity::find().count(&db).await.unwrap(), 3);
+
+        assert!(stored_cursor(&db).await.is_none());
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert!(runs.iter().all(|r| r.status == ConnectionRunStatus::Success));
+    }
+
+    #[tokio::test]
+    async fn test_large_catalog_pulls_through_a_bulk_job() {
+        let db = setup_db().await;
+        let api = MockSalesforce::new(3, JobOutcome::Complete);
+        let instance_url = spawn_salesforce(api.clone()).await;
+        insert_credentials(&db, &instance_url).await;
+        let adapter = bulk_adapter(&db);
+        let conn = connection();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &adapter);
+
+        //first page: the job is submitted, waited for and its first results applied
+        let request = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        let response = adapter.fetch(&conn, request).await;
+        assert!(orchestrator.apply_response(&conn, response).await.unwrap().has_more());
+        assert_eq!(api.jobs_submitted.load(Ordering::SeqCst), 1);
+        assert_eq!(api.job_checks.load(Ordering::SeqCst), 2);
+        assert_eq!(
+            stored_cursor(&db).await,
+            Some(json!({ "mode": "bulk", "job_id": "750J1", "locator": "LOC2" }))
+        );
+        assert_eq!(entity::inventory_record::Entity::find().count(&db).await.unwrap(), 2);
+
+        //the rest of the pass continues from the locator without a new job
+        assert!(pull(&db, bulk_adapter(&db)).await);
+        assert_eq!(api.jobs_submitted.load(Ordering::SeqCst), 1);
+        assert_eq!(*api.result_locators.lock().unwrap(), vec!["", "LOC2"]);
+        assert_eq!(api.rest_pages.load(Ordering::SeqCst), 0);
+        assert!(stored_cursor(&db).await.is_none());
+
+        let events = entity::inventory_record_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events.len(), 3);
+        let widget = events.iter().find(|e| e.name.as_deref() == Some("Widget")).unwrap();
+        assert_eq!(widget.description.as_deref(), Some("Sturdy, \"blue\"\nwidget"));
+        let gadget = events.iter().find(|e| e.name.as_deref() == Some("Gadget")).unwrap();
+        assert_eq!(gadget.external_code, None);
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert!(runs.iter().all(|r| r.status == ConnectionRunStatus::Success));
+    }
+
+    #[tokio::test]
+    async fn test_failed_bulk_job_marks_the_event_error() {
+        let db = setup_db().await;
+        let api = MockSalesforce::new(3, JobOutcome::Failed);
+        let instance_url = spawn_salesforce(api.clone()).await;
+        insert_credentials(&db, &instance_url).await;
+
+        assert!(pull(&db, bulk_adapter(&db)).await);
+        assert!(api.result_locators.lock().unwrap().is_empty());
+        assert_eq!(entity::inventory_record::Entity::find().count(&db).await.unwrap(), 0);
+
+        let event = entity::sync_event::Entity::find().one(&db).await.unwrap().unwrap();
+        assert_eq!(event.status, SyncEventStatus::Error);
+        let message = event.last_error.unwrap()["message"].as_str().unwrap().to_string();
+        assert!(message.contains("750J1"), "{message}");
+        assert!(message.contains("No such column 'Foo__c'"), "{message}");
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs[0].status, ConnectionRunStatus::Error);
+        //nothing is kept of the failed job; the next pass submits a new one
+        assert!(stored_cursor(&db).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bulk_job_outlasting_the_wait_is_aborted() {
+        let db = setup_db().await;
+        let api = MockSalesforce::new(3, JobOutcome::Stuck);
+        let instance_url = spawn_salesforce(api.clone()).await;
+        insert_credentials(&db, &instance_url).await;
+
+        assert!(pull(&db, bulk_adapter(&db)).await);
+        assert!(api.job_aborted.load(Ordering::SeqCst));
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs[0].status, ConnectionRunStatus::Error);
+        let message = runs[0].error_message.as_deref().unwrap();
+        assert!(message.contains("did not complete"), "{message}");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_token_fails_the_run() {
+        let db = setup_db().await;
+        let api = MockSalesforce::new(3, JobOutcome::Complete);
+        let instance_url = spawn_salesforce(api.clone()).await;
+        insert_credentials(&db, &instance_url).await;
+        erp_connection_credentials::Entity::update_many()
+            .col_expr(
+                erp_connection_credentials::Column::AccessToken,
+                sea_orm::sea_query::Expr::value("revoked"),
+            )
+            .exec(&db)
+            .await
+            .unwrap();
+
+        assert!(pull(&db, SalesforceAdapter::new(db.clone())).await);
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs[0].status, ConnectionRunStatus::Error);
+        let message = runs[0].error_message.as_deref().unwrap();
+        assert!(message.contains("Session expired or invalid"), "{message}");
+    }
+}
+
+#[cfg(test)]
+mod salesforce_page_tests {
+    use super::*;
+
+    #[test]
+    fn test_request_payload_follows_the_cursor() {
+        assert_eq!(salesforce::request_payload(None), r#"{"mode":"start"}"#);
+        let bulk = json!({ "mode": "bulk", "job_id": "750J1", "locator": "LOC2" });
+        assert_eq!(
+            serde_json::from_str::<Value>(&salesforce::request_payload(Some(&bulk))).unwrap(),
+            bulk
+        );
+        //a cursor of another shape starts a new pass
+        assert_eq!(
+            salesforce::request_payload(Some(&json!({ "skip": 100 }))),
+            r#"{"mode":"start"}"#
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_handles_quotes_and_line_breaks() {
+        let rows = salesforce::parse_csv("\"a\",\"b, c\"\r\n\"d \"\"e\"\"\",\"f\ng\"\r\n").unwrap();
+        assert_eq!(
+            rows,
+            vec![vec!["a", "b, c"], vec!["d \"e\"", "f\ng"]]
+        );
+        assert!(salesforce::parse_csv("\"open").is_err());
+    }
+
+    #[test]
+    fn test_parse_page_reads_rest_and_bulk_cursors() {
+        let rest = json!({
+            "mode": "rest",
+            "response": {
+                "done": false,
+                "nextRecordsUrl": "/services/data/v60.0/query/01gQ-2000",
+                "records": [{ "Id": "01t1", "Name": "Widget" }, { "Name": "no id" }],
+            },
+        });
+        let (products, cursor) = salesforce::parse_page(&rest.to_string()).unwrap();
+        //a record without Id can't be matched and is left out
+        assert_eq!(products.len(), 1);
+        assert_eq!(products[0].id, "01t1");
+        assert_eq!(
+            cursor,
+            Some(json!({ "mode": "rest", "next_records_url": "/services/data/v60.0/query/01gQ-2000" }))
+        );
+
+        let last = json!({ "mode": "bulk", "job_id": "750J1", "locator": null, "csv": "Id,Name\n01t1,Widget\n" });
+        let (products, cursor) = salesforce::parse_page(&last.to_string()).unwrap();
+        assert_eq!(products[0].name.as_deref(), Some("Widget"));
+        assert!(cursor.is_none());
+
+        //a stored job whose results are gone restarts the pass
+        let expired = json!({ "mode": "expired", "message": "gone" });
+        assert!(matches!(
+            salesforce::parse_page(&expired.to_string()),
+            Err(sync::PullError::CursorExpired(_))
+        ));
+    }
+}
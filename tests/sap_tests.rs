@@ -0,0 +1,428 @@
+//! Tests for the SAP Business One pull adapter
+//!
+//! Run with: cargo test --test sap_tests
+//!
+//! Pulls run against an in-memory SQLite database, with a local axum server standing in for
+//! the Service Layer (`/Login` and a two-page `/Items`).
+
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/client-systems/sap/mod.rs"]
+mod sap;
+#[path = "../src/sync/mod.rs"]
+mod sync;
+#[path = "../src/validation/mod.rs"]
+mod validation;
+
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+#[path = "../src/connection_run/services.rs"]
+mod connection_run_services;
+#[path = "../src/erp_connection_sync_state/services.rs"]
+mod erp_connection_sync_state_services;
+#[path = "../src/inventory_records/events_services.rs"]
+mod inventory_record_events_services;
+#[path = "../src/inventory_records/services.rs"]
+mod inventory_records_services;
+#[path = "../src/outbox/services.rs"]
+mod outbox_services;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/sync_event/services.rs"]
+mod sync_event_services;
+
+//the adapter and orchestrator reach these through `crate::<module>::...`
+mod connection_run {
+    pub(crate) use super::connection_run_services as services;
+}
+mod config {
+    pub(crate) use super::metrics;
+}
+mod erp_connection_sync_state {
+    pub(crate) use super::erp_connection_sync_state_services as services;
+}
+mod inventory_records {
+    pub(crate) use super::inventory_record_events_services as events_services;
+    pub(crate) use super::inventory_records_services as services;
+}
+mod outbox {
+    pub(crate) use super::outbox_services as services;
+}
+mod sync_event {
+    pub(crate) use super::sync_event_services as services;
+}
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use entity::sea_orm_active_enums::{
+    ConnectionRunStatus, ErpConnectionAuthStatus, ErpConnectionAuthTokenType, ErpConnectionStatus,
+    ErpEnvironment, ErpProvider, ErpProviderAuthType, ErpProviderType, SystemIdKey,
+};
+use entity::{connection_identity, erp_connection_credentials};
+use sap::SapAdapter;
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait, IntoActiveModel,
+    PaginatorTrait, Schema,
+};
+use serde_json::{json, Value};
+use sync::ScheduledPull;
+
+const COMPANY_DB: &str = "SBODEMOUS";
+const USERNAME: &str = "manager";
+const PASSWORD: &str = "b1-pass";
+
+const NEW_UUID: &str = "(randomblob(16))";
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+/// Helper to create an in-memory database with the credentials, sync, inventory and outbox
+/// tables a pull touches, with the uuid/created_at/updated_at defaults Postgres provides
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let tables = [
+        schema.create_table_from_entity(entity::erp_connection_credentials::Entity),
+        schema.create_table_from_entity(entity::erp_connection_sync_state::Entity),
+        schema.create_table_from_entity(entity::connection_run::Entity),
+        schema.create_table_from_entity(entity::sync_event::Entity),
+        schema.create_table_from_entity(entity::inventory_record::Entity),
+        schema.create_table_from_entity(entity::inventory_record_event::Entity),
+        schema.create_table_from_entity(entity::outbox::Entity),
+    ];
+    for table in tables {
+        let sql = table
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+    }
+    //same partial index as the migration; the first pull inserts against it
+    db.execute_unprepared(
+        "CREATE UNIQUE INDEX ux_sync_event_recurring_list
+         ON sync_event (connection_sync_state_id, sync_event_method, sync_event_category)
+         WHERE sync_event_method = 'list' AND details IS NULL",
+    )
+    .await
+    .unwrap();
+    db
+}
+
+/// Helper to build the SAP connection being pulled (not persisted)
+fn connection() -> connection_identity::Model {
+    let now = chrono::Utc::now().into();
+    connection_identity::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider: ErpProvider::Sap,
+        erp_type: ErpProviderType::Api,
+        erp_auth_type: ErpProviderAuthType::UsernamePassword,
+        display_name: None,
+        environment: ErpEnvironment::Sandbox,
+        status: ErpConnectionStatus::Active,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: now,
+        updated_at: now,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: Some(COMPANY_DB.to_string()),
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: None,
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+}
+
+/// Helper to store the connection's Service Layer credentials, optionally with a session
+async fn insert_credentials(
+    db: &DatabaseConnection,
+    base_url: &str,
+    password: &str,
+    session: Option<(&str, chrono::Duration)>,
+) {
+    let now = chrono::Utc::now();
+    erp_connection_credentials::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        created_at: now.into(),
+        updated_at: now.into(),
+        connection_id: 1,
+        client_id: None,
+        issuer_base_url: Some(format!("{base_url}/b1s/v1")),
+        token_type: ErpConnectionAuthTokenType::Bearer,
+        reauth_required_reason: None,
+        reauth_url: None,
+        enc_scheme: "none".to_string(),
+        enc_key_id: "none".to_string(),
+        enc_version: 1,
+        enc_iv: None,
+        enc_tag: None,
+        access_token: None,
+        refresh_token: None,
+        access_token_expires_at: None,
+        refresh_token_expires_at: None,
+        id_token_enc: None,
+        provider_user_id: Some(USERNAME.to_string()),
+        provider_password: Some(password.to_string()),
+        client_cert: None,
+        private_key: None,
+        cert_expires_at: None,
+        session_token: session.map(|(token, _)| token.to_string()),
+        session_expires_at: session.map(|(_, expires_in)| (now + expires_in).into()),
+        api_access_token: None,
+        api_access_token_key: None,
+    }
+    .into_active_model()
+    .reset_all()
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+/// What the mock Service Layer saw; only `current_session` is accepted by `/Items`.
+#[derive(Default)]
+struct MockServiceLayer {
+    logins: AtomicUsize,
+    item_requests: Mutex<Vec<String>>,
+    current_session: Mutex<Option<String>>,
+}
+
+/// Mock Service Layer: `/Login` checks the company/user/password and issues `session-N`;
+/// `/Items` answers two items plus a next link for `$skip=0`, then one last item.
+/// Returns the base URL.
+async fn spawn_service_layer(state: Arc<MockServiceLayer>) -> String {
+    async fn login(
+        State(state): State<Arc<MockServiceLayer>>,
+        Json(body): Json<Value>,
+    ) -> (StatusCode, Json<Value>) {
+        let expected = json!({ "CompanyDB": COMPANY_DB, "UserName": USERNAME, "Password": PASSWORD });
+        if body != expected {
+            let error = json!({ "error": { "code": 100000027, "message": { "lang": "en-us", "value": "Invalid user name or password" } } });
+            return (StatusCode::UNAUTHORIZED, Json(error));
+        }
+        let n = state.logins.fetch_add(1, Ordering::SeqCst) + 1;
+        let session = format!("session-{n}");
+        *state.current_session.lock().unwrap() = Some(session.clone());
+        (
+            StatusCode::OK,
+            Json(json!({ "SessionId": session, "Version": "1000190", "SessionTimeout": 30 })),
+        )
+    }
+
+    async fn items(
+        State(state): State<Arc<MockServiceLayer>>,
+        headers: HeaderMap,
+        Query(params): Query<std::collections::HashMap<String, String>>,
+    ) -> (StatusCode, Json<Value>) {
+        let cookie = headers.get("cookie").and_then(|v| v.to_str().ok()).unwrap_or("");
+        let valid = state
+            .current_session
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|session| cookie == format!("B1SESSION={session}"));
+        if !valid {
+            let error = json!({ "error": { "code": 301, "message": { "lang": "en-us", "value": "Invalid session." } } });
+            return (StatusCode::UNAUTHORIZED, Json(error));
+        }
+
+        let skip = params.get("$skip").cloned().unwrap_or_default();
+        state.item_requests.lock().unwrap().push(skip.clone());
+        let page = match skip.as_str() {
+            "0" => json!({
+                "value": [
+                    { "ItemCode": "A0001", "ItemName": "Widget", "ForeignName": "Widget (DE)", "QuantityOnStock": 42.0 },
+                    { "ItemCode": "A0002", "ItemName": "Gadget", "ForeignName": null, "QuantityOnStock": 7.4 },
+                ],
+                "odata.nextLink": "Items?$select=ItemCode&$skip=2",
+            }),
+            _ => json!({
+                "value": [
+                    { "ItemCode": "A0003", "ItemName": "Sprocket", "QuantityOnStock": 0 },
+                ],
+            }),
+        };
+        (StatusCode::OK, Json(page))
+    }
+
+    let app = Router::new()
+        .route("/b1s/v1/Login", post(login))
+        .route("/b1s/v1/Items", get(items))
+        .with_state(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+/// Helper to run one scheduled pull of the connection
+async fn pull(db: &DatabaseConnection) -> bool {
+    Arc::new(SapAdapter::new(db.clone()))
+        .pull(db.clone(), clock::system(), connection())
+        .await
+        .unwrap()
+}
+
+async fn stored_credentials(db: &DatabaseConnection) -> erp_connection_credentials::Model {
+    erp_connection_credentials::Entity::find_by_id(1)
+        .one(db)
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[cfg(test)]
+mod sap_pull_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_login_and_pull_pages() {
+        let db = setup_db().await;
+        let service_layer = Arc::new(MockServiceLayer::default());
+        let base_url = spawn_service_layer(service_layer.clone()).await;
+        insert_credentials(&db, &base_url, PASSWORD, None).await;
+
+        assert!(pull(&db).await);
+        assert_eq!(service_layer.logins.load(Ordering::SeqCst), 1);
+        assert_eq!(*service_layer.item_requests.lock().unwrap(), vec!["0", "2"]);
+
+        //the session is kept for the next pull
+        let creds = stored_credentials(&db).await;
+        assert_eq!(creds.session_token.as_deref(), Some("session-1"));
+        let expires_at = creds.session_expires_at.unwrap();
+        assert!(expires_at > chrono::Utc::now() + chrono::Duration::minutes(29));
+
+        let records = entity::inventory_record::Entity::find().all(&db).await.unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(records.iter().all(|r| r.system_id_key == SystemIdKey::Sapo));
+        let events = entity::inventory_record_event::Entity::find().all(&db).await.unwrap();
+        let gadget = events.iter().find(|e| e.external_code.as_deref() == Some("A0002")).unwrap();
+        assert_eq!(gadget.name.as_deref(), Some("Gadget"));
+        assert_eq!(gadget.qty, Some(7));
+        assert_eq!(entity::outbox::Entity::find().count(&db).await.unwrap(), 3);
+
+        //pagination finished and the run succeeded
+        let state = entity::erp_connection_sync_state::Entity::find().one(&db).await.unwrap().unwrap();
+        assert!(state.sync_cursor.is_none());
+        assert!(state.sync_lock_owner.is_none());
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert!(runs.iter().all(|r| r.status == ConnectionRunStatus::Success));
+    }
+
+    #[tokio::test]
+    async fn test_second_pull_reuses_session_and_skips_unchanged_items() {
+        let db = setup_db().await;
+        let service_layer = Arc::new(MockServiceLayer::default());
+        let base_url = spawn_service_layer(service_layer.clone()).await;
+        insert_credentials(&db, &base_url, PASSWORD, None).await;
+
+        assert!(pull(&db).await);
+        assert!(pull(&db).await);
+        assert_eq!(service_layer.logins.load(Ordering::SeqCst), 1);
+        assert_eq!(entity::inventory_record_event::Entity::find().count(&db).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_expired_session_logs_in_again() {
+        let db = setup_db().await;
+        let service_layer = Arc::new(MockServiceLayer::default());
+        let base_url = spawn_service_layer(service_layer.clone()).await;
+        insert_credentials(&db, &base_url, PASSWORD, Some(("stale", chrono::Duration::minutes(-5)))).await;
+
+        assert!(pull(&db).await);
+        assert_eq!(service_layer.logins.load(Ordering::SeqCst), 1);
+        let creds = stored_credentials(&db).await;
+        assert_eq!(creds.session_token.as_deref(), Some("session-1"));
+    }
+
+    #[tokio::test]
+    async fn test_rejected_session_is_renewed_once() {
+        let db = setup_db().await;
+        let service_layer = Arc::new(MockServiceLayer::default());
+        let base_url = spawn_service_layer(service_layer.clone()).await;
+        //not expired by our clock, but the Service Layer dropped it
+        insert_credentials(&db, &base_url, PASSWORD, Some(("stale", chrono::Duration::minutes(20)))).await;
+
+        assert!(pull(&db).await);
+        assert_eq!(service_layer.logins.load(Ordering::SeqCst), 1);
+        assert_eq!(entity::inventory_record::Entity::find().count(&db).await.unwrap(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_failed_login_fails_the_run() {
+        let db = setup_db().await;
+        let service_layer = Arc::new(MockServiceLayer::default());
+        let base_url = spawn_service_layer(service_layer.clone()).await;
+        insert_credentials(&db, &base_url, "wrong-pass", None).await;
+
+        assert!(pull(&db).await);
+        assert!(service_layer.item_requests.lock().unwrap().is_empty());
+        assert_eq!(entity::inventory_record::Entity::find().count(&db).await.unwrap(), 0);
+
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs[0].status, ConnectionRunStatus::Error);
+        let message = runs[0].error_message.as_deref().unwrap();
+        assert!(message.contains("Invalid user name or password"), "{message}");
+        let creds = stored_credentials(&db).await;
+        assert!(creds.session_token.is_none());
+    }
+}
+
+#[cfg(test)]
+mod sap_page_tests {
+    use super::*;
+
+    #[test]
+    fn test_items_path_pages_by_skip() {
+        assert_eq!(
+            sap::items_path(200),
+            "Items?$select=ItemCode,ItemName,ForeignName,QuantityOnStock,ItemsGroupCode&$orderby=ItemCode&$skip=200&$top=100"
+        );
+    }
+
+    #[test]
+    fn test_parse_items_page_reads_next_link() {
+        let body = r#"{"value":[{"ItemCode":"A1","ItemName":"Widget","QuantityOnStock":2.6},{"ItemName":"no code"}],"@odata.nextLink":"Items?$skip=100&$top=100"}"#;
+        let (items, cursor) = sap::parse_items_page(body).unwrap();
+        //an entry without ItemCode can't be matched and is left out
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].item_code, "A1");
+        assert_eq!(items[0].qty_on_stock, Some(3));
+        assert_eq!(cursor, Some(json!({ "skip": 100 })));
+
+        let (_, cursor) = sap::parse_items_page(r#"{"value":[]}"#).unwrap();
+        assert!(cursor.is_none());
+        assert!(sap::parse_items_page("<html>").is_err());
+    }
+}
@@ -0,0 +1,248 @@
+//! Tests for operator retry / cancel of sync events
+//!
+//! Run with: cargo test --test sync_event_tests
+//!
+//! Drives `SyncEventService::retry` and `cancel` against an in-memory SQLite database, so the
+//! event, its connection run and the sync lock are real rows.
+
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/sync_event/services.rs"]
+mod services;
+
+use entity::sea_orm_active_enums::{
+    ConnectionRunStatus, ConnectionRunType, SyncEventCategory, SyncEventDirection,
+    SyncEventMethod, SyncEventStatus,
+};
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseConnection, EntityTrait, Schema, Set,
+};
+use serde_json::json;
+use services::{SyncEventError, SyncEventService};
+
+const NEW_UUID: &str = "(randomblob(16))";
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+/// Helper to create an in-memory database with the sync state, run and event tables.
+/// Postgres fills uuid/created_at/updated_at by default, so the same defaults are added here.
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //connection_identity/inventory_record_event are not needed by these tests
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let tables = [
+        schema.create_table_from_entity(entity::erp_connection_sync_state::Entity),
+        schema.create_table_from_entity(entity::connection_run::Entity),
+        schema.create_table_from_entity(entity::sync_event::Entity),
+    ];
+    for table in tables {
+        let sql = table
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+    }
+    db
+}
+
+/// Helper to insert a poll run that holds the connection's sync lock, and its
+/// InProgress event (3 attempts so far).
+async fn seed_in_progress(
+    db: &DatabaseConnection,
+) -> (
+    entity::erp_connection_sync_state::Model,
+    entity::connection_run::Model,
+    entity::sync_event::Model,
+) {
+    let now = chrono::Utc::now();
+    let run = entity::connection_run::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        connection_id: Set(1),
+        status: Set(ConnectionRunStatus::Success),
+        run_type: Set(ConnectionRunType::Poll),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let sync_state = entity::erp_connection_sync_state::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        connection_id: Set(1),
+        sync_lock_owner: Set(Some(run.uuid.to_string())),
+        sync_lock_until: Set(Some((now + chrono::Duration::minutes(5)).into())),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+    let event = insert_event(db, SyncEventStatus::InProgress, Some(sync_state.id), Some(run.id)).await;
+    (sync_state, run, event)
+}
+
+/// Helper to insert a list event with the given status and 3 attempts.
+async fn insert_event(
+    db: &DatabaseConnection,
+    status: SyncEventStatus,
+    sync_state_id: Option<i64>,
+    run_id: Option<i64>,
+) -> entity::sync_event::Model {
+    let now = chrono::Utc::now();
+    entity::sync_event::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        event_direction: Set(SyncEventDirection::PullFromExternal),
+        sync_event_method: Set(SyncEventMethod::List),
+        sync_event_category: Set(SyncEventCategory::Inventory),
+        attempts: Set(3),
+        status: Set(status),
+        connection_sync_state_id: Set(sync_state_id),
+        connection_run_id: Set(run_id),
+        created_at: Set(now.into()),
+        updated_at: Set(now.into()),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap()
+}
+
+mod retry_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn retry_moves_in_progress_event_to_pending_and_releases_lock() {
+        let db = setup_db().await;
+        let (sync_state, run, event) = seed_in_progress(&db).await;
+
+        let retried = SyncEventService::new(db.clone())
+            .retry(event.uuid, false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(retried.status, SyncEventStatus::Pending);
+        assert_eq!(retried.attempts, 3, "attempts are kept unless reset");
+
+        let run = entity::connection_run::Entity::find_by_id(run.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(run.status, ConnectionRunStatus::Error);
+        assert!(run.error_message.is_some());
+
+        let sync_state = entity::erp_connection_sync_state::Entity::find_by_id(sync_state.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sync_state.sync_lock_owner, None);
+        assert_eq!(sync_state.sync_lock_until, None);
+    }
+
+    #[tokio::test]
+    async fn retry_resets_attempts_when_asked() {
+        let db = setup_db().await;
+        let event = insert_event(&db, SyncEventStatus::Error, None, None).await;
+
+        let retried = SyncEventService::new(db)
+            .retry(event.uuid, true, None)
+            .await
+            .unwrap();
+
+        assert_eq!(retried.status, SyncEventStatus::Pending);
+        assert_eq!(retried.attempts, 0);
+    }
+
+    #[tokio::test]
+    async fn retry_rejects_succeeded_event() {
+        let db = setup_db().await;
+        let event = insert_event(&db, SyncEventStatus::Success, None, None).await;
+
+        let result = SyncEventService::new(db).retry(event.uuid, false, None).await;
+
+        assert!(matches!(result, Err(SyncEventError::InvalidState(_))));
+    }
+
+    #[tokio::test]
+    async fn retry_unknown_event_is_not_found() {
+        let db = setup_db().await;
+
+        let result = SyncEventService::new(db)
+            .retry(uuid::Uuid::new_v4(), false, None)
+            .await;
+
+        assert!(matches!(result, Err(SyncEventError::NotFound)));
+    }
+}
+
+mod cancel_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn cancel_moves_pending_event_to_error_with_note() {
+        let db = setup_db().await;
+        let event = insert_event(&db, SyncEventStatus::Pending, None, None).await;
+
+        let cancelled = SyncEventService::new(db)
+            .cancel(event.uuid, "duplicate of another event", None)
+            .await
+            .unwrap();
+
+        assert_eq!(cancelled.status, SyncEventStatus::Error);
+        assert_eq!(
+            cancelled.last_error,
+            Some(json!({"cancelled": true, "message": "duplicate of another event"}))
+        );
+        assert!(cancelled.last_errored_date.is_some());
+    }
+
+    #[tokio::test]
+    async fn cancel_in_progress_event_releases_lock() {
+        let db = setup_db().await;
+        let (sync_state, run, event) = seed_in_progress(&db).await;
+
+        let cancelled = SyncEventService::new(db.clone())
+            .cancel(event.uuid, "stuck", None)
+            .await
+            .unwrap();
+
+        assert_eq!(cancelled.status, SyncEventStatus::Error);
+        let run = entity::connection_run::Entity::find_by_id(run.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(run.status, ConnectionRunStatus::Error);
+        assert_eq!(run.error_message.as_deref(), Some("Cancelled by an operator: stuck"));
+        let sync_state = entity::erp_connection_sync_state::Entity::find_by_id(sync_state.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(sync_state.sync_lock_owner, None);
+    }
+
+    #[tokio::test]
+    async fn cancel_rejects_finished_event() {
+        let db = setup_db().await;
+        let event = insert_event(&db, SyncEventStatus::Error, None, None).await;
+
+        let result = SyncEventService::new(db).cancel(event.uuid, "again", None).await;
+
+        assert!(matches!(result, Err(SyncEventError::InvalidState(_))));
+    }
+}
@@ -0,0 +1,951 @@
+//! Tests for the provider-agnostic pull orchestrator
+//!
+//! Run with: cargo test --test sync_orchestrator_tests
+//!
+//! Drives `SyncOrchestrator` with a mock `PullAdapter` against an in-memory SQLite database,
+//! so the sync event / connection run / sync state bookkeeping is real.
+
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/sync/adapter.rs"]
+mod adapter;
+#[path = "../src/sync/decision.rs"]
+mod decision;
+#[path = "../src/sync/orchestrator.rs"]
+mod orchestrator;
+#[path = "../src/sync/retry.rs"]
+mod retry;
+#[path = "../src/sync/window.rs"]
+mod window;
+#[path = "../src/validation/mod.rs"]
+mod validation;
+
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+#[path = "../src/connection_run/services.rs"]
+mod connection_run_services;
+#[path = "../src/erp_connection_sync_state/services.rs"]
+mod erp_connection_sync_state_services;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/sync_event/services.rs"]
+mod sync_event_services;
+
+//the orchestrator reaches these through `crate::<module>::services`
+mod connection_run {
+    pub(crate) use super::connection_run_services as services;
+}
+mod config {
+    pub(crate) use super::metrics;
+}
+mod erp_connection_sync_state {
+    pub(crate) use super::erp_connection_sync_state_services as services;
+}
+mod sync_event {
+    pub(crate) use super::sync_event_services as services;
+}
+
+use adapter::{PullAdapter, PullError, PullOutcome, PullRequest, PullResponse};
+use entity::sea_orm_active_enums::{
+    ConnectionRunStatus, ErpConnectionAuthStatus, ErpConnectionStatus, ErpEnvironment,
+    ErpProvider, ErpProviderAuthType, ErpProviderType, SyncEventStatus,
+};
+use orchestrator::SyncOrchestrator;
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{
+    ConnectionTrait, Database, DatabaseConnection, DatabaseTransaction, EntityTrait, Schema,
+};
+use serde_json::{json, Value};
+
+const NEW_UUID: &str = "(randomblob(16))";
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+/// Helper to create an in-memory database with the tables the orchestrator writes.
+/// Postgres fills uuid/created_at/updated_at by default, so the same defaults are added here.
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    //connection_identity/inventory_record_event are not needed by these tests
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let tables = [
+        schema.create_table_from_entity(entity::erp_connection_sync_state::Entity),
+        schema.create_table_from_entity(entity::connection_run::Entity),
+        schema.create_table_from_entity(entity::sync_event::Entity),
+    ];
+    for table in tables {
+        let sql = table
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+    }
+    //same partial index as the migration; the first pull inserts against it
+    db.execute_unprepared(
+        "CREATE UNIQUE INDEX ux_sync_event_recurring_list
+         ON sync_event (connection_sync_state_id, sync_event_method, sync_event_category)
+         WHERE sync_event_method = 'list' AND details IS NULL",
+    )
+    .await
+    .unwrap();
+    db
+}
+
+/// Helper to build the connection being synced (not persisted)
+fn connection() -> entity::connection_identity::Model {
+    let now = chrono::Utc::now().into();
+    entity::connection_identity::Model {
+        id: 1,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider: ErpProvider::Salesforce,
+        erp_type: ErpProviderType::Api,
+        erp_auth_type: ErpProviderAuthType::Oauth2,
+        display_name: None,
+        environment: ErpEnvironment::Sandbox,
+        status: ErpConnectionStatus::Active,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: now,
+        updated_at: now,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: None,
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+}
+
+/// Mock provider serving two pages: request "page=N", response body "page=N" or "bad".
+struct TwoPageAdapter;
+
+impl PullAdapter for TwoPageAdapter {
+    fn next_request(
+        &self,
+        _conn: &entity::connection_identity::Model,
+        sync_state: &entity::erp_connection_sync_state::Model,
+        _event: &entity::sync_event::Model,
+    ) -> PullRequest {
+        let page = sync_state
+            .sync_cursor
+            .as_ref()
+            .and_then(|c| c.get("page"))
+            .and_then(Value::as_i64)
+            .unwrap_or(1);
+        PullRequest {
+            payload: format!("page={page}"),
+        }
+    }
+
+    async fn apply_response(
+        &self,
+        _conn: &entity::connection_identity::Model,
+        _event: &entity::sync_event::Model,
+        body: &str,
+        _txn: &DatabaseTransaction,
+    ) -> Result<PullOutcome, PullError> {
+        match body {
+            "page=1" => Ok(PullOutcome {
+                cursor: Some(json!({ "page": 2 })),
+                errors: vec![],
+                rejected: vec![],
+                applied: vec![],
+                warnings: vec![],
+            }),
+            "page=2" => Ok(PullOutcome {
+                cursor: None,
+                errors: vec!["record 7: missing name".to_string()],
+                rejected: vec![],
+                applied: vec![],
+                warnings: vec![],
+            }),
+            other => Err(PullError::Provider(format!("unexpected body: {other}"))),
+        }
+    }
+}
+
+fn body(payload: &str) -> PullResponse {
+    PullResponse {
+        body: Some(payload.to_string()),
+        error: None,
+    }
+}
+
+#[cfg(test)]
+mod orchestrator_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_two_page_pull() {
+        let db = setup_db().await;
+        let conn = connection();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+
+        let first = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        assert_eq!(first.payload, "page=1");
+        let outcome = orchestrator
+            .apply_response(&conn, body(&first.payload))
+            .await
+            .unwrap();
+        assert!(outcome.has_more());
+
+        let second = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        assert_eq!(second.payload, "page=2");
+        let outcome = orchestrator
+            .apply_response(&conn, body(&second.payload))
+            .await
+            .unwrap();
+        assert!(!outcome.has_more());
+
+        //one recurring event, handed back to Pending after each page
+        let events = entity::sync_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].status, SyncEventStatus::Pending);
+        assert_eq!(events[0].attempts, 2);
+        assert!(events[0].last_error.is_some());
+
+        //one run per page; the page with record errors marks its run Error
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].status, ConnectionRunStatus::Success);
+        assert_eq!(runs[1].status, ConnectionRunStatus::Error);
+
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert!(state.sync_cursor.is_none());
+        assert!(state.sync_lock_owner.is_none());
+        //pagination finished, so the next poll is a full interval after this one
+        let last_polled_at = state.last_polled_at.unwrap();
+        assert!(state.next_poll_at.unwrap() > last_polled_at);
+    }
+
+    #[tokio::test]
+    async fn test_lock_blocks_concurrent_request() {
+        let db = setup_db().await;
+        let conn = connection();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_some());
+        //no response yet, the first run still holds the lock
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_none());
+
+        //the skipped poll leaves no run behind
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_provider_error_marks_error_and_backs_off() {
+        let db = setup_db().await;
+        let conn = connection();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        let outcome = orchestrator
+            .apply_response(
+                &conn,
+                PullResponse {
+                    body: None,
+                    error: Some("company file is closed".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+        assert!(!outcome.has_more());
+
+        let events = entity::sync_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events[0].status, SyncEventStatus::Error);
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs[0].status, ConnectionRunStatus::Error);
+
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert!(state.sync_lock_owner.is_none());
+        assert!(state.rate_limit_backoff_until.is_some());
+        assert_eq!(state.next_poll_at, state.rate_limit_backoff_until);
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_adapter_failure_is_returned() {
+        let db = setup_db().await;
+        let conn = connection();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        let result = orchestrator.apply_response(&conn, body("bad")).await;
+        assert!(matches!(result, Err(PullError::Provider(_))));
+
+        let events = entity::sync_event::Entity::find().all(&db).await.unwrap();
+        assert_eq!(events[0].status, SyncEventStatus::Error);
+    }
+
+    #[tokio::test]
+    async fn test_failed_event_start_rolls_back_the_run() {
+        let db = setup_db().await;
+        let conn = connection();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+        let first = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        orchestrator.apply_response(&conn, body(&first.payload)).await.unwrap();
+        db.execute_unprepared(
+            "CREATE TRIGGER reject_start BEFORE UPDATE ON sync_event \
+             WHEN NEW.status = 'in_progress' BEGIN SELECT RAISE(ABORT, 'rejected'); END",
+        )
+        .await
+        .unwrap();
+
+        assert!(orchestrator.next_request(&conn).await.is_err());
+
+        //no run was started and the lock is free for the next poll
+        let runs = entity::connection_run::Entity::find().all(&db).await.unwrap();
+        assert_eq!(runs.len(), 1);
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert!(state.sync_lock_owner.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_run_update_keeps_the_page_uncommitted() {
+        let db = setup_db().await;
+        let conn = connection();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+        let first = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        orchestrator.apply_response(&conn, body(&first.payload)).await.unwrap();
+        let second = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        db.execute_unprepared(
+            "CREATE TRIGGER reject_run_error BEFORE UPDATE ON connection_run \
+             WHEN NEW.status = 'error' BEGIN SELECT RAISE(ABORT, 'rejected'); END",
+        )
+        .await
+        .unwrap();
+
+        let result = orchestrator.apply_response(&conn, body(&second.payload)).await;
+
+        assert!(matches!(result, Err(PullError::Db(_))));
+        //the page's cursor change went with the rolled-back transaction
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert_eq!(state.sync_cursor, Some(json!({ "page": 2 })));
+    }
+
+    #[test]
+    fn test_backoff_delay_doubles_and_caps() {
+        assert_eq!(orchestrator::backoff_delay(1).num_seconds(), 30);
+        assert_eq!(orchestrator::backoff_delay(2).num_seconds(), 60);
+        assert_eq!(orchestrator::backoff_delay(3).num_seconds(), 120);
+        assert_eq!(orchestrator::backoff_delay(50).num_seconds(), 3600);
+    }
+}
+
+#[cfg(test)]
+mod clock_tests {
+    use super::*;
+    use chrono::{Duration, TimeZone, Utc};
+    use clock::{Clock, FixedClock};
+    use std::sync::Arc;
+
+    fn fixed_clock() -> Arc<FixedClock> {
+        Arc::new(FixedClock::new(Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()))
+    }
+
+    #[tokio::test]
+    async fn test_lock_lease_expires() {
+        let db = setup_db().await;
+        let conn = connection();
+        let clock = fixed_clock();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter).with_clock(clock.clone());
+
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_some());
+
+        //the first run never answered; its lease still covers the last second
+        clock.advance(Duration::seconds(orchestrator::PULL_LOCK_LEASE_SECS - 1));
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_none());
+
+        clock.advance(Duration::seconds(1));
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_some());
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert_eq!(
+            state.sync_lock_until.unwrap(),
+            clock.now() + Duration::seconds(orchestrator::PULL_LOCK_LEASE_SECS)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_backoff_doubles_per_failed_attempt() {
+        let db = setup_db().await;
+        let conn = connection();
+        let clock = fixed_clock();
+        let start = clock.now();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter).with_clock(clock.clone());
+
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        assert!(orchestrator.apply_response(&conn, body("bad")).await.is_err());
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert_eq!(state.rate_limit_backoff_until.unwrap(), start + Duration::seconds(30));
+
+        //still backing off one second before the window ends
+        clock.advance(Duration::seconds(29));
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_none());
+
+        clock.advance(Duration::seconds(1));
+        orchestrator.next_request(&conn).await.unwrap().unwrap();
+        assert!(orchestrator.apply_response(&conn, body("bad")).await.is_err());
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert_eq!(
+            state.rate_limit_backoff_until.unwrap(),
+            start + Duration::seconds(30 + 60)
+        );
+        assert_eq!(state.next_poll_at, state.rate_limit_backoff_until);
+    }
+}
+
+#[cfg(test)]
+mod transient_retry_tests {
+    use super::retry::{retry_on_transient, sqlstate, RetryPolicy, Transient};
+    use sea_orm::sqlx::error::{DatabaseError, ErrorKind};
+    use sea_orm::{DbErr, RuntimeErr};
+    use std::borrow::Cow;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    ///database error reporting a fixed SQLSTATE, like Postgres does
+    #[derive(Debug)]
+    struct FakePgError(&'static str);
+
+    impl std::fmt::Display for FakePgError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "SQLSTATE {}", self.0)
+        }
+    }
+
+    impl std::error::Error for FakePgError {}
+
+    impl DatabaseError for FakePgError {
+        fn message(&self) -> &str {
+            "fake database error"
+        }
+
+        fn code(&self) -> Option<Cow<'_, str>> {
+            Some(Cow::Borrowed(self.0))
+        }
+
+        fn as_error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn as_error_mut(&mut self) -> &mut (dyn std::error::Error + Send + Sync + 'static) {
+            self
+        }
+
+        fn into_error(self: Box<Self>) -> Box<dyn std::error::Error + Send + Sync + 'static> {
+            self
+        }
+
+        fn kind(&self) -> ErrorKind {
+            ErrorKind::Other
+        }
+    }
+
+    fn db_err(code: &'static str) -> DbErr {
+        DbErr::Exec(RuntimeErr::SqlxError(Arc::new(
+            sea_orm::sqlx::Error::Database(Box::new(FakePgError(code))),
+        )))
+    }
+
+    fn fast_policy(max_retries: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_retries,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(4),
+        }
+    }
+
+    #[test]
+    fn test_only_serialization_and_deadlock_are_transient() {
+        assert_eq!(sqlstate(&db_err("40P01")).as_deref(), Some("40P01"));
+        assert!(db_err("40001").is_transient());
+        assert!(db_err("40P01").is_transient());
+        assert!(!db_err("23505").is_transient());
+        assert!(!DbErr::Custom("40001".to_string()).is_transient());
+    }
+
+    #[tokio::test]
+    async fn test_succeeds_after_two_transient_failures() {
+        let calls = AtomicU32::new(0);
+
+        let result = retry_on_transient(&fast_policy(3), || {
+            let calls = &calls;
+            async move {
+                match calls.fetch_add(1, Ordering::SeqCst) {
+                    0 => Err(db_err("40P01")),
+                    1 => Err(db_err("40001")),
+                    _ => Ok("committed"),
+                }
+            }
+        })
+        .await;
+
+        assert_eq!(result.unwrap(), "committed");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let calls = AtomicU32::new(0);
+
+        let result: Result<(), DbErr> = retry_on_transient(&fast_policy(2), || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(db_err("40001")) }
+        })
+        .await;
+
+        assert!(result.unwrap_err().is_transient());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_other_errors_are_not_retried() {
+        let calls = AtomicU32::new(0);
+
+        let result: Result<(), DbErr> = retry_on_transient(&fast_policy(3), || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async { Err(db_err("23505")) }
+        })
+        .await;
+
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_delay_grows_with_jitter_and_is_capped() {
+        let policy = RetryPolicy::default();
+        for retry in 1..=3 {
+            let step = policy.base_delay * 2u32.pow(retry - 1);
+            let delay = policy.delay(retry);
+            assert!(delay >= step / 2 && delay <= step, "retry {retry}: {delay:?}");
+        }
+        assert!(policy.delay(30) <= policy.max_delay);
+    }
+}
+
+#[cfg(test)]
+mod recurring_event_tests {
+    use super::*;
+    use entity::sea_orm_active_enums::{SyncEventCategory, SyncEventDirection, SyncEventMethod};
+    use sea_orm::{ColumnTrait, PaginatorTrait, QueryFilter};
+    use sync_event_services::{CreateSyncEvent, SyncEventService};
+
+    fn recurring_event(sync_state_id: i64, details: Option<Value>) -> CreateSyncEvent {
+        CreateSyncEvent {
+            original_record_body: None,
+            details,
+            event_direction: SyncEventDirection::PullFromExternal,
+            inventory_record_event_id: None,
+            sync_event_method: SyncEventMethod::List,
+            sync_event_category: SyncEventCategory::Inventory,
+            attempts: None,
+            status: None,
+            last_error: None,
+            last_errored_date: None,
+            connection_sync_state_id: Some(sync_state_id),
+            connection_run_id: None,
+        }
+    }
+
+    async fn list_event_count(db: &DatabaseConnection) -> u64 {
+        entity::sync_event::Entity::find()
+            .filter(entity::sync_event::Column::SyncEventMethod.eq(SyncEventMethod::List))
+            .count(db)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_first_polls_create_one_recurring_event() {
+        let db = setup_db().await;
+        let conn = connection();
+        let first = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+        let second = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+
+        let (a, b) = tokio::join!(first.next_request(&conn), second.next_request(&conn));
+        let started = [a.unwrap(), b.unwrap()].into_iter().flatten().count();
+        assert_eq!(started, 1);
+        assert_eq!(list_event_count(&db).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_losing_insert_returns_none_and_keeps_one_event() {
+        let db = setup_db().await;
+        let svc = SyncEventService::new(db.clone());
+
+        //the first poll's insert wins
+        let created = svc.create_recurring(recurring_event(1, None), None).await.unwrap();
+        assert!(created.is_some());
+        //a racing poll that missed it in its lookup hits the unique index and does nothing
+        assert!(svc.create_recurring(recurring_event(1, None), None).await.unwrap().is_none());
+        assert_eq!(list_event_count(&db).await, 1);
+
+        let found = svc
+            .find_recurring(1, SyncEventCategory::Inventory, None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.id, created.unwrap().id);
+
+        //other sync states and events with details (e.g. a QBD item type) are not restricted
+        assert!(svc.create_recurring(recurring_event(2, None), None).await.unwrap().is_some());
+        let service_items = Some(json!({ "item_type": "service" }));
+        assert!(svc.create_recurring(recurring_event(1, service_items.clone()), None).await.unwrap().is_some());
+        assert!(svc.create_recurring(recurring_event(1, service_items), None).await.unwrap().is_some());
+        assert_eq!(list_event_count(&db).await, 4);
+    }
+}
+
+#[cfg(test)]
+mod run_summary_tests {
+    use super::*;
+    use connection_run_services::ConnectionRunService;
+    use entity::sea_orm_active_enums::ConnectionRunType;
+    use sea_orm::{ActiveModelTrait, PaginatorTrait, Set};
+
+    /// Helper to insert a run of connection 1 created `hours_ago`
+    async fn seed_run(
+        db: &DatabaseConnection,
+        status: ConnectionRunStatus,
+        hours_ago: i64,
+        error_message: Option<&str>,
+    ) {
+        let at: chrono::DateTime<chrono::FixedOffset> =
+            (chrono::Utc::now() - chrono::Duration::hours(hours_ago)).into();
+        entity::connection_run::ActiveModel {
+            uuid: Set(uuid::Uuid::new_v4()),
+            connection_id: Set(1),
+            status: Set(status),
+            run_type: Set(ConnectionRunType::Poll),
+            error_message: Set(error_message.map(str::to_string)),
+            created_at: Set(at),
+            updated_at: Set(at),
+            ..Default::default()
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_summary_counts_last_24h_and_latest_error() {
+        let db = setup_db().await;
+        //older than the window: not counted
+        seed_run(&db, ConnectionRunStatus::Error, 40, Some("timeout")).await;
+        seed_run(&db, ConnectionRunStatus::Success, 30, None).await;
+        seed_run(&db, ConnectionRunStatus::Error, 3, Some("token expired")).await;
+        seed_run(&db, ConnectionRunStatus::Success, 2, None).await;
+        seed_run(&db, ConnectionRunStatus::Success, 1, None).await;
+
+        let svc = ConnectionRunService::new(db.clone());
+        let summary = svc.summary(1, None).await.unwrap();
+        assert_eq!(summary.success_24h, 2);
+        assert_eq!(summary.error_24h, 1);
+        assert_eq!(summary.last_error_message.as_deref(), Some("token expired"));
+        let last_error_at = summary.last_error_at.unwrap();
+        assert!(last_error_at < chrono::Utc::now() - chrono::Duration::hours(2));
+        assert!(last_error_at > chrono::Utc::now() - chrono::Duration::hours(4));
+
+        //a connection without runs
+        assert_eq!(svc.summary(2, None).await.unwrap(), Default::default());
+    }
+
+    #[tokio::test]
+    async fn test_old_error_is_reported_without_recent_runs() {
+        let db = setup_db().await;
+        seed_run(&db, ConnectionRunStatus::Error, 72, Some("company file closed")).await;
+
+        let summary = ConnectionRunService::new(db).summary(1, None).await.unwrap();
+        assert_eq!((summary.success_24h, summary.error_24h), (0, 0));
+        assert_eq!(summary.last_error_message.as_deref(), Some("company file closed"));
+    }
+
+    #[tokio::test]
+    async fn test_skipped_pull_creates_no_run() {
+        let db = setup_db().await;
+        let conn = connection();
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_some());
+        //the lock is held by the first run
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_none());
+        let runs = entity::connection_run::Entity::find().count(&db).await.unwrap();
+        assert_eq!(runs, 1);
+    }
+}
+
+#[cfg(test)]
+mod sync_window_tests {
+    use super::*;
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+    use clock::FixedClock;
+    use std::sync::Arc;
+    use window::{in_sync_window, SyncWindow};
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, day, hour, minute, 0).unwrap()
+    }
+
+    /// Helper to build the connection with a window from `start` up to `end` (UTC hours)
+    fn windowed_connection(start: i16, end: i16) -> entity::connection_identity::Model {
+        entity::connection_identity::Model {
+            sync_window_start_hour: Some(start),
+            sync_window_end_hour: Some(end),
+            ..connection()
+        }
+    }
+
+    #[test]
+    fn test_window_across_midnight() {
+        let conn = windowed_connection(22, 6);
+
+        assert!(!in_sync_window(&conn, at(1, 21, 59)));
+        assert!(in_sync_window(&conn, at(1, 22, 0)));
+        assert!(in_sync_window(&conn, at(1, 23, 59)));
+        assert!(in_sync_window(&conn, at(2, 0, 0)));
+        assert!(in_sync_window(&conn, at(2, 5, 59)));
+        assert!(!in_sync_window(&conn, at(2, 6, 0)));
+        assert!(!in_sync_window(&conn, at(2, 12, 0)));
+    }
+
+    #[test]
+    fn test_window_within_one_day() {
+        let conn = windowed_connection(9, 17);
+
+        assert!(!in_sync_window(&conn, at(1, 8, 59)));
+        assert!(in_sync_window(&conn, at(1, 9, 0)));
+        assert!(in_sync_window(&conn, at(1, 16, 59)));
+        assert!(!in_sync_window(&conn, at(1, 17, 0)));
+        assert!(!in_sync_window(&conn, at(2, 0, 0)));
+    }
+
+    #[test]
+    fn test_no_window_syncs_any_time() {
+        assert!(in_sync_window(&connection(), at(1, 3, 0)));
+
+        //half a window, or an invalid one, is treated as none
+        let half = entity::connection_identity::Model {
+            sync_window_start_hour: Some(22),
+            ..connection()
+        };
+        assert!(in_sync_window(&half, at(1, 12, 0)));
+        assert!(in_sync_window(&windowed_connection(5, 5), at(1, 12, 0)));
+
+        assert_eq!(SyncWindow::new(24, 6), None);
+        assert_eq!(SyncWindow::new(-1, 6), None);
+        assert_eq!(
+            SyncWindow::new(22, 6),
+            Some(SyncWindow { start_hour: 22, end_hour: 6 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pull_waits_for_window_and_resumes_cursor() {
+        let db = setup_db().await;
+        let conn = windowed_connection(22, 6);
+        let clock = Arc::new(FixedClock::new(at(1, 21, 30)));
+        let orchestrator = SyncOrchestrator::new(db.clone(), &TwoPageAdapter).with_clock(clock.clone());
+
+        //before the window opens: no work, and no run started
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_none());
+        assert!(entity::connection_run::Entity::find().all(&db).await.unwrap().is_empty());
+
+        clock.advance(Duration::minutes(30));
+        let first = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        assert_eq!(first.payload, "page=1");
+        assert!(orchestrator.apply_response(&conn, body(&first.payload)).await.unwrap().has_more());
+
+        //the window closed past midnight before the next page; the cursor is kept
+        clock.advance(Duration::hours(8));
+        assert!(orchestrator.next_request(&conn).await.unwrap().is_none());
+        let state = orchestrator.ensure_sync_state(conn.id).await.unwrap();
+        assert_eq!(state.sync_cursor, Some(json!({ "page": 2 })));
+
+        //and the pass picks up where it stopped when the window opens again
+        clock.advance(Duration::hours(16));
+        let second = orchestrator.next_request(&conn).await.unwrap().unwrap();
+        assert_eq!(second.payload, "page=2");
+    }
+}
+
+#[cfg(test)]
+mod transaction_helper_tests {
+    use super::*;
+    use entity::sea_orm_active_enums::{SyncEventCategory, SyncEventDirection, SyncEventMethod};
+    use sea_orm::TransactionTrait;
+    use sync_event_services::{CreateSyncEvent, SyncEventService, UpdateSyncEvent};
+
+    fn event() -> CreateSyncEvent {
+        CreateSyncEvent {
+            original_record_body: None,
+            details: Some(json!({ "page": 1 })),
+            event_direction: SyncEventDirection::PullFromExternal,
+            inventory_record_event_id: None,
+            sync_event_method: SyncEventMethod::List,
+            sync_event_category: SyncEventCategory::Inventory,
+            attempts: None,
+            status: None,
+            last_error: None,
+            last_errored_date: None,
+            connection_sync_state_id: Some(1),
+            connection_run_id: None,
+        }
+    }
+
+    fn mark_success() -> UpdateSyncEvent {
+        UpdateSyncEvent {
+            original_record_body: None,
+            details: None,
+            event_direction: None,
+            inventory_record_event_id: None,
+            sync_event_method: None,
+            sync_event_category: None,
+            attempts: Some(1),
+            status: Some(SyncEventStatus::Success),
+            last_error: None,
+            last_errored_date: None,
+            connection_sync_state_id: None,
+            connection_run_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_conn_picks_the_transaction_when_given_one() {
+        let db = setup_db().await;
+        assert!(matches!(db::conn(&db, None), db::Conn::Connection(_)));
+        let txn = db.begin().await.unwrap();
+        assert!(matches!(db::conn(&db, Some(&txn)), db::Conn::Transaction(_)));
+        txn.rollback().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pooled_calls_round_trip() {
+        let db = setup_db().await;
+        let svc = SyncEventService::new(db.clone());
+
+        let created = svc.create(event(), None).await.unwrap();
+        assert_eq!(svc.get_by_uuid(created.uuid, None).await.unwrap().unwrap().id, created.id);
+        let updated = svc
+            .update_by_uuid(created.uuid, mark_success(), None)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(updated.status, SyncEventStatus::Success);
+        assert_eq!(svc.get_all(1, 10, None, None).await.unwrap().total, 1);
+
+        svc.delete_by_uuid(created.uuid, None).await.unwrap();
+        assert!(svc.get_by_id(created.id, None).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_transactional_calls_commit_or_roll_back_together() {
+        let db = setup_db().await;
+        let svc = SyncEventService::new(db.clone());
+
+        //visible inside the transaction, gone after a rollback
+        let txn = db.begin().await.unwrap();
+        let rolled_back = svc.create(event(), Some(&txn)).await.unwrap();
+        assert!(svc.get_by_id(rolled_back.id, Some(&txn)).await.unwrap().is_some());
+        assert_eq!(svc.get_all(1, 10, None, Some(&txn)).await.unwrap().items.len(), 1);
+        txn.rollback().await.unwrap();
+        assert!(svc.get_by_uuid(rolled_back.uuid, None).await.unwrap().is_none());
+
+        //a create and an update in one transaction land together on commit
+        let txn = db.begin().await.unwrap();
+        let created = svc.create(event(), Some(&txn)).await.unwrap();
+        svc.update_by_id(created.id, mark_success(), Some(&txn)).await.unwrap();
+        txn.commit().await.unwrap();
+        let stored = svc.get_by_id(created.id, None).await.unwrap().unwrap();
+        assert_eq!(stored.status, SyncEventStatus::Success);
+        assert_eq!(stored.attempts, 1);
+    }
+}
+
+#[cfg(test)]
+mod ensure_sync_state_tests {
+    use super::*;
+    use erp_connection_sync_state_services::{
+        CreateErpConnectionSyncState, ErpConnectionSyncStateService,
+    };
+    use sea_orm::PaginatorTrait;
+
+    fn sync_state(connection_id: i64) -> CreateErpConnectionSyncState {
+        CreateErpConnectionSyncState {
+            connection_id,
+            sync_cursor: None,
+            sync_lock_owner: None,
+            sync_lock_until: None,
+            rate_limit_remaining: None,
+            rate_limit: None,
+            rate_limit_reset_at: None,
+            rate_limit_backoff_until: None,
+            rate_limit_window_seconds: None,
+            poll_interval_seconds: Some(60),
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_first_polls_converge_on_one_row() {
+        let db = setup_db().await;
+        let first = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+        let second = SyncOrchestrator::new(db.clone(), &TwoPageAdapter);
+
+        let (a, b) = tokio::join!(first.ensure_sync_state(1), second.ensure_sync_state(1));
+
+        let (a, b) = (a.unwrap(), b.unwrap());
+        assert_eq!(a.id, b.id);
+        let rows = entity::erp_connection_sync_state::Entity::find()
+            .count(&db)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+    }
+
+    #[tokio::test]
+    async fn create_if_missing_returns_the_row_created_first() {
+        let db = setup_db().await;
+        let svc = ErpConnectionSyncStateService::new(db.clone());
+        //the row a concurrent first poll created between the lookup and the insert
+        let existing = svc.create(sync_state(1), None).await.unwrap();
+
+        let mut late = sync_state(1);
+        late.poll_interval_seconds = Some(300);
+        let converged = svc.create_if_missing(late, None).await.unwrap();
+
+        assert_eq!(converged.id, existing.id);
+        assert_eq!(converged.poll_interval_seconds, Some(60), "the existing row is kept");
+        let rows = entity::erp_connection_sync_state::Entity::find()
+            .count(&db)
+            .await
+            .unwrap();
+        assert_eq!(rows, 1);
+    }
+}
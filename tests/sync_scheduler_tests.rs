@@ -0,0 +1,348 @@
+//! Tests for the internal poll scheduler
+//!
+//! Run with: cargo test --test sync_scheduler_tests
+//!
+//! Pulls run against an in-memory SQLite database with a mock `ApiPullAdapter`; the due
+//! connection lookup uses the sea-orm mock database (connection rows hold a Postgres array).
+
+#[path = "../src/clock/mod.rs"]
+mod clock;
+#[path = "../src/sync/adapter.rs"]
+mod adapter;
+#[path = "../src/sync/decision.rs"]
+mod decision;
+#[path = "../src/sync/orchestrator.rs"]
+mod orchestrator;
+#[path = "../src/sync/retry.rs"]
+mod retry;
+#[path = "../src/sync/scheduler.rs"]
+mod scheduler;
+#[path = "../src/sync/window.rs"]
+mod window;
+#[path = "../src/validation/mod.rs"]
+mod validation;
+
+#[path = "../src/config/metrics.rs"]
+mod metrics;
+#[path = "../src/connection_run/services.rs"]
+mod connection_run_services;
+#[path = "../src/erp_connection_sync_state/services.rs"]
+mod erp_connection_sync_state_services;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/sync_event/services.rs"]
+mod sync_event_services;
+
+//the orchestrator and scheduler reach these through `crate::<module>::services`
+mod connection_run {
+    pub(crate) use super::connection_run_services as services;
+}
+mod config {
+    pub(crate) use super::metrics;
+}
+mod erp_connection_sync_state {
+    pub(crate) use super::erp_connection_sync_state_services as services;
+}
+mod sync_event {
+    pub(crate) use super::sync_event_services as services;
+}
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use adapter::{PullAdapter, PullError, PullOutcome, PullRequest, PullResponse};
+use chrono::{TimeZone, Utc};
+use clock::FixedClock;
+use entity::sea_orm_active_enums::{
+    ErpConnectionAuthStatus, ErpConnectionStatus, ErpEnvironment, ErpProvider,
+    ErpProviderAuthType, ErpProviderType,
+};
+use scheduler::{jitter_delay, ApiPullAdapter, PollScheduler, TickSummary};
+use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::{
+    ActiveModelTrait, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection,
+    DatabaseTransaction, EntityTrait, MockDatabase, Schema, Set,
+};
+
+const NEW_UUID: &str = "(randomblob(16))";
+const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+/// Helper to create an in-memory database with the tables the orchestrator writes, with the
+/// uuid/created_at/updated_at defaults Postgres provides
+async fn setup_db() -> DatabaseConnection {
+    let db = Database::connect("sqlite::memory:").await.unwrap();
+    db.execute_unprepared("PRAGMA foreign_keys = OFF").await.unwrap();
+    let schema = Schema::new(db.get_database_backend());
+    let tables = [
+        schema.create_table_from_entity(entity::erp_connection_sync_state::Entity),
+        schema.create_table_from_entity(entity::connection_run::Entity),
+        schema.create_table_from_entity(entity::sync_event::Entity),
+    ];
+    for table in tables {
+        let sql = table
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+    }
+    //same partial index as the migration; the first pull inserts against it
+    db.execute_unprepared(
+        "CREATE UNIQUE INDEX ux_sync_event_recurring_list
+         ON sync_event (connection_sync_state_id, sync_event_method, sync_event_category)
+         WHERE sync_event_method = 'list' AND details IS NULL",
+    )
+    .await
+    .unwrap();
+    db
+}
+
+/// Helper to build an API connection (not persisted)
+fn connection(id: i64, erp_provider: ErpProvider) -> entity::connection_identity::Model {
+    let now = Utc::now().into();
+    entity::connection_identity::Model {
+        id,
+        uuid: uuid::Uuid::new_v4(),
+        tenant_id: 1,
+        erp_provider,
+        erp_type: ErpProviderType::Api,
+        erp_auth_type: ErpProviderAuthType::Oauth2,
+        display_name: None,
+        environment: ErpEnvironment::Production,
+        status: ErpConnectionStatus::Active,
+        auth_status: ErpConnectionAuthStatus::Connected,
+        created_at: now,
+        updated_at: now,
+        is_enabled: true,
+        last_success_at: None,
+        last_error_code: None,
+        last_error_message: None,
+        error_at: None,
+        sync_enabled_push: true,
+        sync_enabled_pull: true,
+        secret_storage_ref: None,
+        secret_version: None,
+        scopes: None,
+        provider_realm_id: None,
+        provider_tenant_id: None,
+        company_file_identity: None,
+        company_file_path: None,
+        company_file_id: None,
+        system_version: None,
+        web_connector_app_name: None,
+        qbxml_version: None,
+        initial_sync_completed_at: None,
+        sync_window_start_hour: None,
+        sync_window_end_hour: None,
+        field_mapping: None,
+    }
+}
+
+/// Helper to insert a sync state for `connection_id`
+async fn insert_state(
+    db: &DatabaseConnection,
+    connection_id: i64,
+    next_poll_at: chrono::DateTime<Utc>,
+    locked_until: Option<chrono::DateTime<Utc>>,
+) {
+    entity::erp_connection_sync_state::ActiveModel {
+        uuid: Set(uuid::Uuid::new_v4()),
+        connection_id: Set(connection_id),
+        next_poll_at: Set(Some(next_poll_at.into())),
+        sync_lock_owner: Set(locked_until.map(|_| "other-instance".to_string())),
+        sync_lock_until: Set(locked_until.map(Into::into)),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+    .unwrap();
+}
+
+/// Mock Salesforce adapter serving a single page and counting provider calls
+#[derive(Default)]
+struct OnePageAdapter {
+    fetches: AtomicUsize,
+}
+
+impl PullAdapter for OnePageAdapter {
+    fn next_request(
+        &self,
+        _conn: &entity::connection_identity::Model,
+        _sync_state: &entity::erp_connection_sync_state::Model,
+        _event: &entity::sync_event::Model,
+    ) -> PullRequest {
+        PullRequest {
+            payload: "page=1".to_string(),
+        }
+    }
+
+    async fn apply_response(
+        &self,
+        _conn: &entity::connection_identity::Model,
+        _event: &entity::sync_event::Model,
+        _body: &str,
+        _txn: &DatabaseTransaction,
+    ) -> Result<PullOutcome, PullError> {
+        Ok(PullOutcome::default())
+    }
+}
+
+impl ApiPullAdapter for OnePageAdapter {
+    fn handles(&self, conn: &entity::connection_identity::Model) -> bool {
+        conn.erp_provider == ErpProvider::Salesforce
+    }
+
+    async fn fetch(
+        &self,
+        _conn: &entity::connection_identity::Model,
+        request: PullRequest,
+    ) -> PullResponse {
+        self.fetches.fetch_add(1, Ordering::SeqCst);
+        PullResponse {
+            body: Some(request.payload),
+            error: None,
+        }
+    }
+}
+
+fn fixed_clock() -> Arc<FixedClock> {
+    Arc::new(FixedClock::new(Utc.with_ymd_and_hms(2026, 3, 1, 12, 0, 0).unwrap()))
+}
+
+#[cfg(test)]
+mod poll_tests {
+    use super::*;
+    use clock::Clock;
+
+    #[tokio::test]
+    async fn test_due_connection_is_pulled_and_marked_polled() {
+        let db = setup_db().await;
+        let clock = fixed_clock();
+        let now = clock.now();
+        insert_state(&db, 1, now - chrono::Duration::minutes(5), None).await;
+        let adapter = Arc::new(OnePageAdapter::default());
+        let scheduler = PollScheduler::new(db.clone())
+            .with_adapter(adapter.clone())
+            .with_clock(clock.clone());
+
+        let summary = scheduler.poll(vec![connection(1, ErpProvider::Salesforce)]).await;
+
+        assert_eq!(summary, TickSummary { polled: 1, skipped: 0, failed: 0 });
+        assert_eq!(adapter.fetches.load(Ordering::SeqCst), 1);
+        let state = entity::erp_connection_sync_state::Entity::find()
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.last_polled_at.unwrap(), now);
+        assert!(state.next_poll_at.unwrap() > now);
+        assert!(state.sync_lock_owner.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_connection_locked_by_another_instance_is_skipped() {
+        let db = setup_db().await;
+        let clock = fixed_clock();
+        let now = clock.now();
+        let locked_until = now + chrono::Duration::minutes(1);
+        insert_state(&db, 1, now - chrono::Duration::minutes(5), Some(locked_until)).await;
+        let adapter = Arc::new(OnePageAdapter::default());
+        let scheduler = PollScheduler::new(db.clone())
+            .with_adapter(adapter.clone())
+            .with_clock(clock);
+
+        let summary = scheduler.poll(vec![connection(1, ErpProvider::Salesforce)]).await;
+
+        assert_eq!(summary, TickSummary { polled: 0, skipped: 1, failed: 0 });
+        assert_eq!(adapter.fetches.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_connection_without_adapter_is_left_alone() {
+        let db = setup_db().await;
+        let adapter = Arc::new(OnePageAdapter::default());
+        let scheduler = PollScheduler::new(db.clone()).with_adapter(adapter.clone());
+
+        let summary = scheduler.poll(vec![connection(1, ErpProvider::Quickbooks)]).await;
+
+        assert_eq!(summary, TickSummary::default());
+        assert!(entity::erp_connection_sync_state::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_tick_without_adapters_does_nothing() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres).into_connection();
+        let scheduler = PollScheduler::new(db.clone());
+
+        assert_eq!(scheduler.tick().await.unwrap(), TickSummary::default());
+        assert!(db.into_transaction_log().is_empty());
+    }
+
+    #[test]
+    fn test_jitter_stays_within_bound() {
+        assert_eq!(jitter_delay(Duration::ZERO), Duration::ZERO);
+        for _ in 0..100 {
+            assert!(jitter_delay(Duration::from_millis(250)) <= Duration::from_millis(250));
+        }
+    }
+}
+
+#[cfg(test)]
+mod due_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_due_connections_are_enabled_api_connections() {
+        let now = Utc::now();
+        let due_state = entity::erp_connection_sync_state::Model {
+            id: 10,
+            uuid: uuid::Uuid::new_v4(),
+            connection_id: 1,
+            sync_cursor: None,
+            sync_lock_owner: None,
+            sync_lock_until: None,
+            rate_limit_remaining: None,
+            rate_limit: None,
+            rate_limit_reset_at: None,
+            rate_limit_backoff_until: None,
+            rate_limit_window_seconds: None,
+            updated_at: now.into(),
+            created_at: now.into(),
+            last_polled_at: None,
+            next_poll_at: Some((now - chrono::Duration::minutes(1)).into()),
+            poll_interval_seconds: None,
+        };
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            //find_due
+            .append_query_results([vec![due_state]])
+            //due API connections
+            .append_query_results([vec![connection(1, ErpProvider::Salesforce)]])
+            .into_connection();
+        let scheduler = PollScheduler::new(db.clone());
+
+        let due = scheduler.due_connections().await.unwrap();
+
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].id, 1);
+        //Desktop connections stay with QBWC; never-polled connections have no sync state
+        let log = format!("{:?}", db.into_transaction_log());
+        assert!(log.contains(r#"\"connection_identity\".\"erp_type\" = "#));
+        assert!(log.contains(r#"\"connection_identity\".\"is_enabled\" = "#));
+        assert!(log.contains(r#"\"connection_identity\".\"sync_enabled_pull\" = "#));
+        assert!(log.contains(r#"\"erp_connection_sync_state\".\"id\" IS NULL"#));
+    }
+}
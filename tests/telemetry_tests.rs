@@ -0,0 +1,36 @@
+//! Tests for tracing / OpenTelemetry initialization
+//!
+//! Run with: cargo test --test telemetry_tests
+//!
+//! The global subscriber can only be installed once per process, so enabling export is
+//! covered by a single test. No collector is needed: spans are batched and export
+//! failures are only logged.
+
+#[path = "../src/config/telemetry.rs"]
+mod telemetry;
+
+use telemetry::{init_tracing, TelemetryError};
+
+#[cfg(test)]
+mod telemetry_init_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_init_with_otlp_endpoint() {
+        let guard = init_tracing(Some("http://127.0.0.1:4318/"), "erp-proxy-server-test", "info")
+            .expect("tracing should initialize with OTLP export");
+        assert!(guard.is_exporting());
+
+        //spans created while exporting must not panic
+        tracing::info_span!("qbd.poll.request", connection_id = 1).in_scope(|| {
+            tracing::info!("inside span");
+        });
+
+        //a second global subscriber is rejected rather than panicking
+        let second = init_tracing(None, "erp-proxy-server-test", "info");
+        assert!(matches!(second, Err(TelemetryError::Subscriber(_))));
+
+        //shutting down with an unreachable collector must not panic either
+        tokio::task::spawn_blocking(move || drop(guard)).await.unwrap();
+    }
+}
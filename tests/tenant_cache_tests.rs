@@ -0,0 +1,185 @@
+//! Tests for the tenant-active status cache
+//!
+//! Run with: cargo test --test tenant_cache_tests
+//!
+//! Uses the sea-orm mock database (no Postgres/Redis required) and the in-process cache
+//! backend, which shares its invalidation logic with the Redis backend.
+
+#[path = "../src/audit/services.rs"]
+mod audit_services;
+#[path = "../src/tenant/cache.rs"]
+mod cache;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/tenant/services.rs"]
+mod services;
+
+//the services record audit rows through `crate::audit::services`
+mod audit {
+    pub(crate) use super::audit_services as services;
+}
+
+use cache::TenantStatusCache;
+use entity::sea_orm_active_enums::Enum as TenantStatus;
+use entity::tenant;
+use sea_orm::{DatabaseBackend, MockDatabase, TransactionTrait};
+use services::{TenantService, UpdateTenant};
+
+const TENANT_ID: &str = "TN_550e8400e29b41d4a716446655440000";
+const TENANT_ROW_ID: i64 = 1;
+
+/// Helper to build a tenant row with the given status
+fn tenant_model(status: TenantStatus) -> tenant::Model {
+    let now = chrono::Utc::now().into();
+    tenant::Model {
+        id: TENANT_ROW_ID,
+        uuid: uuid::Uuid::new_v4(),
+        display_name: Some("Acme Corp".to_string()),
+        tenant_id: TENANT_ID.to_string(),
+        created_at: now,
+        updated_at: now,
+        status,
+    }
+}
+
+#[cfg(test)]
+mod tenant_cache_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_second_lookup_hits_cache() {
+        //only one query result: a second DB read would fail
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            .into_connection();
+        let service = TenantService::with_status_cache(db.clone(), TenantStatusCache::local());
+
+        assert!(service.is_tenant_active(TENANT_ID, None).await.unwrap());
+        assert!(service.is_tenant_active(TENANT_ID, None).await.unwrap());
+
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_update_invalidates_cache() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            //initial lookup
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            //update_by_tenant_id: find, then UPDATE ... RETURNING
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            .append_query_results([vec![tenant_model(TenantStatus::Removed)]])
+            //lookup after invalidation
+            .append_query_results([vec![tenant_model(TenantStatus::Removed)]])
+            .into_connection();
+        let service = TenantService::with_status_cache(db.clone(), TenantStatusCache::local());
+
+        assert!(service.is_tenant_active(TENANT_ID, None).await.unwrap());
+
+        service
+            .update_by_tenant_id(
+                TENANT_ID,
+                UpdateTenant {
+                    display_name: None,
+                    status: Some(TenantStatus::Removed),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(!service.is_tenant_active(TENANT_ID, None).await.unwrap());
+        assert_eq!(db.into_transaction_log().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_update_in_callers_transaction_waits_for_commit() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            .append_query_results([vec![tenant_model(TenantStatus::Removed)]])
+            .append_query_results([vec![tenant_model(TenantStatus::Removed)]])
+            .into_connection();
+        let service = TenantService::with_status_cache(db.clone(), TenantStatusCache::local());
+        assert!(service.is_tenant_active(TENANT_ID, None).await.unwrap());
+
+        let txn = db.begin().await.unwrap();
+        let updated = service
+            .update_by_tenant_id(
+                TENANT_ID,
+                UpdateTenant {
+                    display_name: None,
+                    status: Some(TenantStatus::Removed),
+                },
+                Some(&txn),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        //not committed yet, so the cached status still holds
+        assert!(service.is_tenant_active(TENANT_ID, None).await.unwrap());
+        txn.commit().await.unwrap();
+        service.invalidate_cache(&updated).await;
+
+        assert!(!service.is_tenant_active(TENANT_ID, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_lookup_by_row_id_is_cached_and_invalidated_on_update() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            //one lookup per key
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            //update_by_tenant_id: find, then UPDATE ... RETURNING
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            .append_query_results([vec![tenant_model(TenantStatus::Removed)]])
+            //lookup by row id after invalidation
+            .append_query_results([vec![tenant_model(TenantStatus::Removed)]])
+            .into_connection();
+        let service = TenantService::with_status_cache(db.clone(), TenantStatusCache::local());
+
+        assert!(service.is_tenant_active_by_id(TENANT_ROW_ID, None).await.unwrap());
+        //the tenant_id key is cached separately
+        assert!(service.is_tenant_active(TENANT_ID, None).await.unwrap());
+        assert!(service.is_tenant_active_by_id(TENANT_ROW_ID, None).await.unwrap());
+
+        service
+            .update_by_tenant_id(
+                TENANT_ID,
+                UpdateTenant {
+                    display_name: None,
+                    status: Some(TenantStatus::Removed),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        assert!(!service.is_tenant_active_by_id(TENANT_ROW_ID, None).await.unwrap());
+        assert_eq!(db.into_transaction_log().len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_missing_tenant_is_inactive_and_cached() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([Vec::<tenant::Model>::new()])
+            .into_connection();
+        let service = TenantService::with_status_cache(db.clone(), TenantStatusCache::local());
+
+        assert!(!service.is_tenant_active(TENANT_ID, None).await.unwrap());
+        assert!(!service.is_tenant_active(TENANT_ID, None).await.unwrap());
+        assert_eq!(db.into_transaction_log().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_without_cache_always_reads_db() {
+        let db = MockDatabase::new(DatabaseBackend::Postgres)
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            .append_query_results([vec![tenant_model(TenantStatus::Active)]])
+            .into_connection();
+        let service = TenantService::new(db.clone());
+
+        assert!(service.is_tenant_active(TENANT_ID, None).await.unwrap());
+        assert!(service.is_tenant_active(TENANT_ID, None).await.unwrap());
+        assert_eq!(db.into_transaction_log().len(), 2);
+    }
+}
@@ -4,6 +4,26 @@
 //!
 //! Note: These tests require a running database connection.
 //! For CI/CD, consider using testcontainers or a mock database.
+//! The bulk and idempotent create tests run `TenantService` against an in-memory SQLite
+//! database instead, as do the default tenant tests.
+
+#[path = "../src/audit/services.rs"]
+mod audit_services;
+#[path = "../src/idempotency/mod.rs"]
+mod idempotency;
+#[path = "../src/tenant/cache.rs"]
+mod cache;
+#[path = "../src/db/mod.rs"]
+mod db;
+#[path = "../src/tenant/default.rs"]
+mod default_tenant;
+#[path = "../src/tenant/services.rs"]
+mod tenant_services;
+
+//the tenant service records audit rows through `crate::audit::services`
+mod audit {
+    pub(crate) use super::audit_services as services;
+}
 
 use axum::{
     body::Body,
@@ -249,3 +269,308 @@ mod uuid_validation_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod tenant_bulk_create_tests {
+    use super::tenant_services::{BulkCreateError, CreateTenant, TenantService};
+    use sea_orm::sea_query::SqliteQueryBuilder;
+    use sea_orm::{ConnectionTrait, Database, DatabaseConnection, EntityTrait, Schema};
+
+    const NEW_UUID: &str = "(randomblob(16))";
+    const NOW: &str = "(strftime('%Y-%m-%dT%H:%M:%fZ', 'now'))";
+
+    /// Helper to create an in-memory database with the tenant table; inserting a tenant
+    /// named "reject" fails, to make one row of a batch fail in the database
+    pub(super) async fn setup_db() -> DatabaseConnection {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let schema = Schema::new(db.get_database_backend());
+        let sql = schema
+            .create_table_from_entity(entity::tenant::Entity)
+            .to_string(SqliteQueryBuilder)
+            .replace(
+                "\"uuid\" uuid_text NOT NULL UNIQUE",
+                &format!("\"uuid\" uuid_text NOT NULL UNIQUE DEFAULT {NEW_UUID}"),
+            )
+            .replace(
+                "\"created_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"created_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            )
+            .replace(
+                "\"updated_at\" timestamp_with_timezone_text NOT NULL",
+                &format!("\"updated_at\" timestamp_with_timezone_text NOT NULL DEFAULT {NOW}"),
+            );
+        db.execute_unprepared(&sql).await.unwrap();
+        db.execute_unprepared(
+            "CREATE TRIGGER reject_tenant BEFORE INSERT ON tenant \
+             WHEN NEW.display_name = 'reject' BEGIN SELECT RAISE(ABORT, 'rejected'); END",
+        )
+        .await
+        .unwrap();
+        db
+    }
+
+    fn batch(names: &[&str]) -> Vec<CreateTenant> {
+        names
+            .iter()
+            .map(|name| CreateTenant { display_name: Some(name.to_string()) })
+            .collect()
+    }
+
+    pub(super) async fn tenant_count(db: &DatabaseConnection) -> usize {
+        entity::tenant::Entity::find().all(db).await.unwrap().len()
+    }
+
+    #[tokio::test]
+    async fn test_all_created_with_generated_ids() {
+        let db = setup_db().await;
+
+        let results = TenantService::new(db.clone())
+            .create_many(batch(&["Acme", "Globex"]), true, None)
+            .await
+            .unwrap();
+
+        let created: Vec<_> = results.into_iter().map(Result::unwrap).collect();
+        assert_eq!(created[0].display_name.as_deref(), Some("Acme"));
+        assert_eq!(created[1].display_name.as_deref(), Some("Globex"));
+        assert!(created.iter().all(|t| t.tenant_id.starts_with("TN_")));
+        assert_ne!(created[0].tenant_id, created[1].tenant_id);
+        assert_eq!(tenant_count(&db).await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_all_or_nothing_rolls_back_on_failed_row() {
+        let db = setup_db().await;
+
+        let result = TenantService::new(db.clone())
+            .create_many(batch(&["Acme", "reject", "Globex"]), true, None)
+            .await;
+
+        assert!(matches!(result, Err(BulkCreateError::Row(1, _))));
+        assert_eq!(tenant_count(&db).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_partial_mode_reports_failed_row_and_keeps_others() {
+        let db = setup_db().await;
+
+        let results = TenantService::new(db.clone())
+            .create_many(batch(&["Acme", "reject", "Globex"]), false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert!(results[0].is_ok());
+        assert!(results[1].as_ref().unwrap_err().to_string().contains("rejected"));
+        assert!(results[2].is_ok());
+        let mut names: Vec<_> = entity::tenant::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .into_iter()
+            .filter_map(|t| t.display_name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["Acme", "Globex"]);
+    }
+}
+
+#[cfg(test)]
+mod tenant_idempotent_create_tests {
+    use super::idempotency::{
+        fingerprint, idempotent_create, Idempotent, IdempotencyError, IdempotencyKey,
+        IdempotencyStore, IDEMPOTENCY_KEY_HEADER,
+    };
+    use super::tenant_bulk_create_tests::{setup_db, tenant_count};
+    use super::tenant_services::{CreateTenant, TenantService};
+    use axum::http::HeaderMap;
+    use sea_orm::{DatabaseConnection, DbErr};
+    use serde_json::{json, Value};
+
+    fn key(value: &str, actor_token_id: Option<i64>) -> IdempotencyKey {
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, value.parse().unwrap());
+        IdempotencyKey::from_headers(&headers, "tenant:create", actor_token_id)
+            .unwrap()
+            .unwrap()
+    }
+
+    /// Helper to run one `POST /tenant/create` the way the route does
+    async fn create(
+        db: &DatabaseConnection,
+        store: &IdempotencyStore,
+        key: &IdempotencyKey,
+        display_name: &str,
+    ) -> Result<Idempotent<Value>, IdempotencyError<DbErr>> {
+        let body = json!({ "display_name": display_name });
+        let service = TenantService::new(db.clone());
+        let data = CreateTenant { display_name: Some(display_name.to_string()) };
+        idempotent_create(store, Some(key), &fingerprint(&body), || async {
+            service
+                .create(data, None)
+                .await
+                .map(|t| json!({ "uuid": t.uuid, "tenant_id": t.tenant_id }))
+        })
+        .await
+    }
+
+    fn body(outcome: Idempotent<Value>) -> Value {
+        match outcome {
+            Idempotent::Created(body) | Idempotent::Replayed(body) => body,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_same_key_creates_one_row_and_replays_the_body() {
+        let db = setup_db().await;
+        let store = IdempotencyStore::local();
+        let key = key("retry-1", Some(7));
+
+        let first = create(&db, &store, &key, "Acme").await.unwrap();
+        let second = create(&db, &store, &key, "Acme").await.unwrap();
+
+        assert!(matches!(first, Idempotent::Created(_)));
+        assert!(matches!(second, Idempotent::Replayed(_)));
+        assert_eq!(body(first), body(second));
+        assert_eq!(tenant_count(&db).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_key_reused_with_different_body_is_refused() {
+        let db = setup_db().await;
+        let store = IdempotencyStore::local();
+        let key = key("retry-1", Some(7));
+
+        create(&db, &store, &key, "Acme").await.unwrap();
+        let reused = create(&db, &store, &key, "Globex").await;
+
+        assert!(matches!(reused, Err(IdempotencyError::KeyReused)));
+        assert_eq!(tenant_count(&db).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_failed_create_releases_the_key() {
+        let db = setup_db().await;
+        let store = IdempotencyStore::local();
+        let key = key("retry-1", Some(7));
+
+        let failed = idempotent_create(&store, Some(&key), "same-body", || async {
+            Err::<Value, _>("database unavailable")
+        })
+        .await;
+        assert!(matches!(failed, Err(IdempotencyError::Create(_))));
+
+        let retried = idempotent_create(&store, Some(&key), "same-body", || async {
+            Ok::<_, &str>(json!({ "ok": true }))
+        })
+        .await;
+        assert!(matches!(retried, Ok(Idempotent::Created(_))));
+    }
+
+    #[tokio::test]
+    async fn test_keys_are_scoped_to_the_caller() {
+        let db = setup_db().await;
+        let store = IdempotencyStore::local();
+
+        create(&db, &store, &key("retry-1", Some(7)), "Acme").await.unwrap();
+        let other_token = create(&db, &store, &key("retry-1", Some(8)), "Acme").await.unwrap();
+
+        assert!(matches!(other_token, Idempotent::Created(_)));
+        assert_eq!(tenant_count(&db).await, 2);
+    }
+
+    #[test]
+    fn test_malformed_keys_are_rejected() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(IdempotencyKey::from_headers(&headers, "tenant:create", None), Ok(None));
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, "  ".parse().unwrap());
+        assert!(IdempotencyKey::from_headers(&headers, "tenant:create", None).is_err());
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, "k".repeat(256).parse().unwrap());
+        assert!(IdempotencyKey::from_headers(&headers, "tenant:create", None).is_err());
+    }
+}
+
+#[cfg(test)]
+mod default_tenant_tests {
+    use super::default_tenant::{ensure_default_tenant, tenant_id_or_default, DEFAULT_TENANT_DISPLAY_NAME};
+    use super::tenant_bulk_create_tests::{setup_db, tenant_count};
+    use entity::sea_orm_active_enums::Enum as TenantStatus;
+
+    const DEFAULT: &str = "TN_00000000000000000000000000000001";
+
+    #[tokio::test]
+    async fn test_default_tenant_is_created_once() {
+        let db = setup_db().await;
+
+        let created = ensure_default_tenant(&db, DEFAULT).await.unwrap();
+        let again = ensure_default_tenant(&db, DEFAULT).await.unwrap();
+
+        assert_eq!(created.tenant_id, DEFAULT);
+        assert_eq!(created.display_name.as_deref(), Some(DEFAULT_TENANT_DISPLAY_NAME));
+        assert_eq!(created.status, TenantStatus::Active);
+        assert_eq!(again.id, created.id);
+        assert_eq!(tenant_count(&db).await, 1);
+    }
+
+    #[test]
+    fn test_requests_fall_back_to_default_unless_tenant_scoped() {
+        assert_eq!(tenant_id_or_default(None, Some(DEFAULT), false), Some(DEFAULT));
+        assert_eq!(tenant_id_or_default(Some(" "), Some(DEFAULT), false), Some(DEFAULT));
+        //a named tenant always wins
+        assert_eq!(tenant_id_or_default(Some("TN_other"), Some(DEFAULT), false), Some("TN_other"));
+        assert_eq!(tenant_id_or_default(Some("TN_other"), Some(DEFAULT), true), Some("TN_other"));
+        //tenant-scoped callers and servers without a default get none
+        assert_eq!(tenant_id_or_default(None, Some(DEFAULT), true), None);
+        assert_eq!(tenant_id_or_default(None, None, false), None);
+    }
+}
+
+#[cfg(test)]
+mod unique_violation_tests {
+    use super::db::unique::UniqueViolation;
+    use super::StatusCode;
+    use super::tenant_bulk_create_tests::setup_db;
+    use super::tenant_services::{CreateTenant, TenantError, TenantService};
+    use entity::sea_orm_active_enums::Enum as TenantStatus;
+    use sea_orm::{ActiveModelTrait, Set};
+
+    #[tokio::test]
+    async fn test_duplicate_tenant_id_is_a_conflict() {
+        let db = setup_db().await;
+        let existing = TenantService::new(db.clone())
+            .create(CreateTenant { display_name: None }, None)
+            .await
+            .unwrap();
+
+        let duplicate = entity::tenant::ActiveModel {
+            tenant_id: Set(existing.tenant_id),
+            status: Set(TenantStatus::Active),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap_err();
+
+        let err = TenantError::from(duplicate);
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
+        assert_eq!(err.message(), "A tenant with this tenant_id already exists");
+    }
+
+    #[test]
+    fn test_constraint_names_map_to_messages() {
+        let message = |constraint: Option<&str>| {
+            UniqueViolation { constraint: constraint.map(str::to_string) }.message()
+        };
+        assert_eq!(
+            message(Some("ux_connection_identity_active_company_file")),
+            "A connection with this company file already exists"
+        );
+        assert_eq!(
+            message(Some("erp_connection_credentials_connection_id_idx")),
+            "This connection already has credentials"
+        );
+        assert_eq!(message(Some("tenant_uuid_key")), "A record with this uuid already exists");
+        assert_eq!(message(None), "A record with the same unique value already exists");
+    }
+}
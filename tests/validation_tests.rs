@@ -0,0 +1,219 @@
+//! Tests for request validation helpers
+//!
+//! Run with: cargo test --test validation_tests
+
+#[path = "../src/validation/mod.rs"]
+mod validation;
+
+use axum::body::{to_bytes, Body};
+use axum::http::{Request, StatusCode};
+use axum::response::Response;
+use axum::routing::get;
+use axum::{Json, Router};
+use entity::sea_orm_active_enums::{ErpEnvironment, ErpProvider, SyncEventCategory};
+use serde_json::json;
+use tower::ServiceExt;
+use uuid::Uuid;
+use validation::{
+    check_length, parse_enum, parse_optional_enum, FieldError, PathUuid, MAX_DISPLAY_NAME_LEN,
+};
+
+#[cfg(test)]
+mod length_validation_tests {
+    use super::*;
+
+    #[test]
+    fn test_over_length_display_name() {
+        let mut errors = Vec::new();
+        let name = "a".repeat(MAX_DISPLAY_NAME_LEN + 1);
+        check_length(&mut errors, "display_name", Some(&name), MAX_DISPLAY_NAME_LEN);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "display_name");
+        assert_eq!(errors[0].message, "must be at most 255 characters");
+    }
+
+    #[test]
+    fn test_max_length_display_name_is_valid() {
+        let mut errors = Vec::new();
+        let name = "a".repeat(MAX_DISPLAY_NAME_LEN);
+        check_length(&mut errors, "display_name", Some(&name), MAX_DISPLAY_NAME_LEN);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_length_counts_characters_not_bytes() {
+        let mut errors = Vec::new();
+        let name = "é".repeat(MAX_DISPLAY_NAME_LEN);
+        check_length(&mut errors, "display_name", Some(&name), MAX_DISPLAY_NAME_LEN);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_blank_display_name() {
+        let mut errors = Vec::new();
+        check_length(&mut errors, "display_name", Some("   "), MAX_DISPLAY_NAME_LEN);
+        assert_eq!(errors, vec![FieldError::new("display_name", "must not be blank")]);
+    }
+
+    #[test]
+    fn test_missing_optional_field_is_valid() {
+        let mut errors = Vec::new();
+        check_length(&mut errors, "display_name", None, MAX_DISPLAY_NAME_LEN);
+        assert!(errors.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod enum_validation_tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_provider_string() {
+        let mut errors = Vec::new();
+        let provider = parse_enum::<ErpProvider>(&mut errors, "erp_provider", "oracle");
+
+        assert!(provider.is_none());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "erp_provider");
+        assert_eq!(
+            errors[0].message,
+            "must be one of: quickbooks, dmsi, sap, salesforce"
+        );
+    }
+
+    #[test]
+    fn test_valid_provider_string_is_case_insensitive() {
+        let mut errors = Vec::new();
+        let provider = parse_enum::<ErpProvider>(&mut errors, "erp_provider", " QuickBooks ");
+
+        assert_eq!(provider, Some(ErpProvider::Quickbooks));
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_optional_enum_absent() {
+        let mut errors = Vec::new();
+        let env = parse_optional_enum::<ErpEnvironment>(&mut errors, "environment", None);
+        assert!(env.is_none());
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_field_error_serialization() {
+        let error = FieldError::new("erp_provider", "must be one of: quickbooks");
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["field"], "erp_provider");
+        assert_eq!(json["message"], "must be one of: quickbooks");
+    }
+}
+
+#[cfg(test)]
+mod payload_validation_tests {
+    use super::*;
+    use validation::payload::validate_record_body;
+
+    #[test]
+    fn test_valid_inventory_payload() {
+        //QBD spelling, as stored in original_record_body
+        let qbd = json!({ "ListID": "80000001-1234567890", "Name": "Widget" });
+        assert!(validate_record_body(&SyncEventCategory::Inventory, &qbd).is_empty());
+
+        //JSON API spelling, numeric id
+        let api = json!({ "id": 42, "name": "Widget" });
+        assert!(validate_record_body(&SyncEventCategory::Inventory, &api).is_empty());
+    }
+
+    #[test]
+    fn test_invalid_inventory_payload() {
+        let body = json!({ "ListID": "  ", "FullName": "Parts:Widget" });
+        let errors = validate_record_body(&SyncEventCategory::Inventory, &body);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].field, "id");
+        assert_eq!(errors[0].message, "must not be blank");
+        assert_eq!(errors[1].field, "name");
+        assert_eq!(errors[1].message, "is required");
+    }
+
+    #[test]
+    fn test_non_object_payload_is_rejected() {
+        let errors = validate_record_body(&SyncEventCategory::Inventory, &json!(["Widget"]));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "body");
+    }
+}
+
+/// The one connection the path tests' router knows
+const KNOWN: &str = "6f1c2d4e-8a9b-4c3d-9e2f-1a2b3c4d5e6f";
+
+/// Helper to build a router shaped like the connection routes: 404 for UUIDs it doesn't know
+fn uuid_app() -> Router {
+    async fn get_one(PathUuid(uuid): PathUuid) -> Result<String, (StatusCode, Json<serde_json::Value>)> {
+        if uuid == Uuid::parse_str(KNOWN).unwrap() {
+            Ok(uuid.to_string())
+        } else {
+            Err((StatusCode::NOT_FOUND, Json(json!({ "error": "Connection not found" }))))
+        }
+    }
+    async fn get_item(PathUuid((uuid, item_uuid)): PathUuid<(Uuid, Uuid)>) -> String {
+        format!("{uuid}/{item_uuid}")
+    }
+    Router::new()
+        .route("/connections/{uuid}", get(get_one))
+        .route("/connections/{uuid}/quarantine/{item_uuid}", get(get_item))
+}
+
+async fn get_uuid_path(path: &str) -> (StatusCode, serde_json::Value) {
+    let response: Response = uuid_app()
+        .oneshot(Request::get(path).body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null))
+}
+
+#[cfg(test)]
+mod path_uuid_tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_malformed_uuid_is_a_validation_error() {
+        let (status, body) = get_uuid_path("/connections/not-a-uuid").await;
+
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(
+            body,
+            json!({
+                "error": "Validation failed",
+                "details": [{ "field": "uuid", "message": "must be a UUID" }]
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_valid_missing_uuid_is_not_found() {
+        let (status, body) = get_uuid_path(&format!("/connections/{}", Uuid::new_v4())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["error"], "Connection not found");
+
+        let (status, _) = get_uuid_path(&format!("/connections/{KNOWN}")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_each_malformed_parameter_is_named() {
+        let (status, body) = get_uuid_path(&format!("/connections/{KNOWN}/quarantine/42")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["details"], json!([{ "field": "item_uuid", "message": "must be a UUID" }]));
+
+        let (status, body) = get_uuid_path("/connections/x/quarantine/y").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["details"].as_array().unwrap().len(), 2);
+
+        let item = Uuid::new_v4();
+        let (status, _) = get_uuid_path(&format!("/connections/{KNOWN}/quarantine/{item}")).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+}